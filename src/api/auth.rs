@@ -19,12 +19,14 @@ use gotham::middleware::{Middleware, NewMiddleware};
 use gotham::state::{FromState, State};
 use grin_util::to_base64;
 use hyper::header::{HeaderMap, AUTHORIZATION};
-use hyper::StatusCode;
+use hyper::{StatusCode, Uri};
 use ring::constant_time::verify_slices_are_equal;
 use std::io;
 
 pub struct BasicAuthMiddleware {
 	api_basic_auth: Option<String>,
+	/// Paths that check credentials of their own
+	exempt_paths: Vec<String>,
 }
 
 impl BasicAuthMiddleware {
@@ -32,8 +34,15 @@ impl BasicAuthMiddleware {
 		Self {
 			api_basic_auth: api_basic_auth
 				.map(|x| String::from("Basic ") + &to_base64(&(String::from("grin:") + &x))),
+			exempt_paths: vec![],
 		}
 	}
+
+	/// Leave the authentication of requests to `path` to its handler
+	pub fn except(mut self, path: &str) -> Self {
+		self.exempt_paths.push(path.to_owned());
+		self
+	}
 }
 
 impl Middleware for BasicAuthMiddleware {
@@ -41,7 +50,8 @@ impl Middleware for BasicAuthMiddleware {
 	where
 		C: FnOnce(State) -> Box<HandlerFuture>,
 	{
-		if self.api_basic_auth.is_none() {
+		let path = Uri::borrow_from(&state).path();
+		if self.api_basic_auth.is_none() || self.exempt_paths.iter().any(|p| p == path) {
 			return chain(state);
 		}
 
@@ -70,6 +80,7 @@ impl NewMiddleware for BasicAuthMiddleware {
 	fn new_middleware(&self) -> io::Result<Self::Instance> {
 		Ok(BasicAuthMiddleware {
 			api_basic_auth: self.api_basic_auth.clone(),
+			exempt_paths: self.exempt_paths.clone(),
 		})
 	}
 }
@@ -0,0 +1,131 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cross-origin requests to the owner API, for frontends running in a
+//! browser on another origin
+
+use futures::{future, Future};
+use gotham::handler::HandlerFuture;
+use gotham::helpers::http::response::create_empty_response;
+use gotham::middleware::{Middleware, NewMiddleware};
+use gotham::state::{FromState, State};
+use hyper::header::{
+	HeaderMap, HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+	ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+	VARY,
+};
+use hyper::{Body, Method, Response, StatusCode};
+use std::io;
+
+/// How long browsers can reuse the answer to a preflight request, in seconds
+const PREFLIGHT_MAX_AGE: u32 = 86400;
+
+/// Origins allowed to call the API from a browser
+#[derive(Clone, Debug, Default)]
+pub struct CorsPolicy {
+	/// Origins such as `https://wallet.example.com`, or `*` for any
+	pub allowed_origins: Vec<String>,
+}
+
+impl CorsPolicy {
+	pub fn is_enabled(&self) -> bool {
+		!self.allowed_origins.is_empty()
+	}
+
+	pub fn allows(&self, origin: &str) -> bool {
+		let origin = origin.trim_end_matches('/');
+		self.allowed_origins
+			.iter()
+			.any(|o| o == "*" || o.trim_end_matches('/').eq_ignore_ascii_case(origin))
+	}
+}
+
+/// Answers preflight requests of allowed origins before they reach the
+/// authentication, which browsers don't send them with, and lets the
+/// browser read the responses to the requests that follow
+pub struct CorsMiddleware {
+	policy: CorsPolicy,
+}
+
+impl CorsMiddleware {
+	pub fn new(policy: &CorsPolicy) -> Self {
+		Self {
+			policy: policy.clone(),
+		}
+	}
+}
+
+impl Middleware for CorsMiddleware {
+	fn call<C>(self, state: State, chain: C) -> Box<HandlerFuture>
+	where
+		C: FnOnce(State) -> Box<HandlerFuture> + Send + 'static,
+	{
+		let headers = HeaderMap::borrow_from(&state);
+		let origin = headers
+			.get(ORIGIN)
+			.filter(|o| o.to_str().map(|o| self.policy.allows(o)).unwrap_or(false))
+			.cloned();
+		let origin = match origin {
+			Some(origin) => origin,
+			None => return chain(state),
+		};
+
+		let preflight = *Method::borrow_from(&state) == Method::OPTIONS
+			&& headers.contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+		if preflight {
+			let mut res = create_empty_response(&state, StatusCode::NO_CONTENT);
+			allow_origin(&mut res, origin);
+			let headers = res.headers_mut();
+			headers.insert(
+				ACCESS_CONTROL_ALLOW_METHODS,
+				HeaderValue::from_static("GET, POST, OPTIONS"),
+			);
+			headers.insert(
+				ACCESS_CONTROL_ALLOW_HEADERS,
+				HeaderValue::from_static("Authorization, Content-Type"),
+			);
+			headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from(PREFLIGHT_MAX_AGE));
+			return Box::new(future::ok((state, res)));
+		}
+
+		Box::new(chain(state).map(move |(state, mut res)| {
+			allow_origin(&mut res, origin);
+			(state, res)
+		}))
+	}
+}
+
+impl NewMiddleware for CorsMiddleware {
+	type Instance = CorsMiddleware;
+
+	fn new_middleware(&self) -> io::Result<Self::Instance> {
+		Ok(CorsMiddleware {
+			policy: self.policy.clone(),
+		})
+	}
+}
+
+/// The origin is sent back rather than `*`, so the answer differs by origin
+fn allow_origin(res: &mut Response<Body>, origin: HeaderValue) {
+	let headers = res.headers_mut();
+	headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+	headers.append(VARY, HeaderValue::from_static("Origin"));
+}
+
+/// Route for preflight requests, so the middleware gets to answer them.
+/// Only reached by requests that aren't a preflight of an allowed origin
+pub fn preflight_handler(state: State) -> Box<HandlerFuture> {
+	let res = create_empty_response(&state, StatusCode::METHOD_NOT_ALLOWED);
+	Box::new(future::ok((state, res)))
+}
@@ -0,0 +1,263 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Limits of the HTTP APIs: a token bucket of requests per client address,
+//! and a maximum size of request bodies
+
+use crate::api::router::client_addr;
+use crate::common::metrics::{SharedMetrics, HTTP_REJECTED};
+use crate::common::{Arc, Mutex};
+use futures::{future, Future, Stream};
+use gotham::handler::{HandlerFuture, IntoHandlerError};
+use gotham::helpers::http::response::create_empty_response;
+use gotham::middleware::{Middleware, NewMiddleware};
+use gotham::state::{FromState, State};
+use hyper::header::{HeaderMap, HeaderValue, CONTENT_LENGTH, RETRY_AFTER};
+use hyper::{Body, Method, StatusCode};
+use std::collections::HashMap;
+use std::io;
+use std::net::IpAddr;
+use std::time::Instant;
+
+/// Clients tracked at most. Past that, the buckets that have filled up
+/// again are dropped, as they behave the same as new ones
+const MAX_CLIENTS: usize = 10_000;
+
+/// Limits applied to every request of an API
+#[derive(Clone, Debug)]
+pub struct ApiLimits {
+	/// Requests per minute of one client address, and how many of them it
+	/// can make at once. Not limited if `None`
+	pub rate: Option<(u32, u32)>,
+	/// Bytes a request body can have at most
+	pub max_body_size: u64,
+}
+
+/// Token bucket of each client address
+pub struct RateLimiter {
+	/// Tokens added per second
+	rate: f64,
+	burst: f64,
+	buckets: HashMap<IpAddr, (f64, Instant)>,
+}
+
+impl RateLimiter {
+	pub fn new(per_minute: u32, burst: u32) -> Self {
+		Self {
+			rate: per_minute as f64 / 60.0,
+			burst: burst.max(1) as f64,
+			buckets: HashMap::new(),
+		}
+	}
+
+	/// Take a token of `client`. Without one left, fails with the seconds
+	/// until the next is available
+	pub fn take(&mut self, client: IpAddr, now: Instant) -> Result<(), u64> {
+		if self.buckets.len() >= MAX_CLIENTS && !self.buckets.contains_key(&client) {
+			self.prune(now);
+		}
+		let (rate, burst) = (self.rate, self.burst);
+		let (tokens, updated) = self.buckets.entry(client).or_insert((burst, now));
+		*tokens = (*tokens + seconds(*updated, now) * rate).min(burst);
+		*updated = now;
+		match *tokens >= 1.0 {
+			true => {
+				*tokens -= 1.0;
+				Ok(())
+			}
+			false => Err(((1.0 - *tokens) / rate).ceil() as u64),
+		}
+	}
+
+	fn prune(&mut self, now: Instant) {
+		let (rate, burst) = (self.rate, self.burst);
+		self.buckets
+			.retain(|_, (tokens, updated)| *tokens + seconds(*updated, now) * rate < burst);
+	}
+}
+
+fn seconds(since: Instant, now: Instant) -> f64 {
+	let elapsed = now.duration_since(since);
+	elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9
+}
+
+/// Answers clients going over the rate limit with 429 Too Many Requests
+pub struct RateLimitMiddleware {
+	api: &'static str,
+	limiter: Option<Arc<Mutex<RateLimiter>>>,
+	metrics: SharedMetrics,
+}
+
+impl RateLimitMiddleware {
+	pub fn new(api: &'static str, limits: &ApiLimits, metrics: SharedMetrics) -> Self {
+		let limiter = limits
+			.rate
+			.map(|(per_minute, burst)| Arc::new(Mutex::new(RateLimiter::new(per_minute, burst))));
+		Self {
+			api,
+			limiter,
+			metrics,
+		}
+	}
+}
+
+impl Middleware for RateLimitMiddleware {
+	fn call<C>(self, state: State, chain: C) -> Box<HandlerFuture>
+	where
+		C: FnOnce(State) -> Box<HandlerFuture> + Send + 'static,
+	{
+		let client = client_addr(&state).map(|a| a.ip());
+		let res = match (&self.limiter, client) {
+			(Some(limiter), Some(client)) => limiter.lock().take(client, Instant::now()),
+			_ => Ok(()),
+		};
+		match res {
+			Ok(()) => chain(state),
+			Err(wait) => {
+				let labels = [("api", self.api), ("reason", "rate_limit")];
+				self.metrics.lock().inc(HTTP_REJECTED, &labels);
+				let mut res = create_empty_response(&state, StatusCode::TOO_MANY_REQUESTS);
+				res.headers_mut()
+					.insert(RETRY_AFTER, HeaderValue::from(wait.max(1)));
+				Box::new(future::ok((state, res)))
+			}
+		}
+	}
+}
+
+impl NewMiddleware for RateLimitMiddleware {
+	type Instance = RateLimitMiddleware;
+
+	fn new_middleware(&self) -> io::Result<Self::Instance> {
+		Ok(RateLimitMiddleware {
+			api: self.api,
+			limiter: self.limiter.clone(),
+			metrics: self.metrics.clone(),
+		})
+	}
+}
+
+/// Answers requests with a body over the maximum size with 413 Payload Too
+/// Large, before any of it is parsed
+pub struct BodySizeMiddleware {
+	api: &'static str,
+	max_body_size: u64,
+	metrics: SharedMetrics,
+}
+
+impl BodySizeMiddleware {
+	pub fn new(api: &'static str, limits: &ApiLimits, metrics: SharedMetrics) -> Self {
+		Self {
+			api,
+			max_body_size: limits.max_body_size,
+			metrics,
+		}
+	}
+
+	fn too_large(self, state: State) -> Box<HandlerFuture> {
+		let labels = [("api", self.api), ("reason", "body_size")];
+		self.metrics.lock().inc(HTTP_REJECTED, &labels);
+		let res = create_empty_response(&state, StatusCode::PAYLOAD_TOO_LARGE);
+		Box::new(future::ok((state, res)))
+	}
+}
+
+impl Middleware for BodySizeMiddleware {
+	fn call<C>(self, mut state: State, chain: C) -> Box<HandlerFuture>
+	where
+		C: FnOnce(State) -> Box<HandlerFuture> + Send + 'static,
+	{
+		let length = HeaderMap::borrow_from(&state)
+			.get(CONTENT_LENGTH)
+			.and_then(|v| v.to_str().ok())
+			.and_then(|v| v.parse::<u64>().ok());
+		match length {
+			Some(length) if length > self.max_body_size => return self.too_large(state),
+			// Hyper doesn't read past the announced length
+			Some(_) => return chain(state),
+			None => {}
+		}
+		// Leave the body of upgrades to the handler
+		if *Method::borrow_from(&state) == Method::GET {
+			return chain(state);
+		}
+
+		// A chunked body, read up to the maximum
+		let max = self.max_body_size as usize;
+		let body = Body::take_from(&mut state).map_err(Some);
+		let body = body.fold(Vec::new(), move |mut buf, chunk| {
+			buf.extend_from_slice(&chunk);
+			match buf.len() > max {
+				true => Err(None),
+				false => Ok(buf),
+			}
+		});
+		Box::new(body.then(move |res| match res {
+			Ok(buf) => {
+				state.put(Body::from(buf));
+				chain(state)
+			}
+			Err(None) => self.too_large(state),
+			Err(Some(e)) => Box::new(future::err((state, e.into_handler_error()))),
+		}))
+	}
+}
+
+impl NewMiddleware for BodySizeMiddleware {
+	type Instance = BodySizeMiddleware;
+
+	fn new_middleware(&self) -> io::Result<Self::Instance> {
+		Ok(BodySizeMiddleware {
+			api: self.api,
+			max_body_size: self.max_body_size,
+			metrics: self.metrics.clone(),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	#[test]
+	fn rate_limiter_refills() {
+		let client: IpAddr = "127.0.0.1".parse().unwrap();
+		let other: IpAddr = "127.0.0.2".parse().unwrap();
+		let now = Instant::now();
+		let mut limiter = RateLimiter::new(60, 2);
+
+		assert_eq!(limiter.take(client, now), Ok(()));
+		assert_eq!(limiter.take(client, now), Ok(()));
+		assert_eq!(limiter.take(client, now), Err(1));
+		// Other clients have their own bucket
+		assert_eq!(limiter.take(other, now), Ok(()));
+
+		let later = now + Duration::from_secs(1);
+		assert_eq!(limiter.take(client, later), Ok(()));
+		assert_eq!(limiter.take(client, later), Err(1));
+	}
+
+	#[test]
+	fn rate_limiter_waits_for_the_next_token() {
+		let client: IpAddr = "::1".parse().unwrap();
+		let now = Instant::now();
+		let mut limiter = RateLimiter::new(6, 1);
+
+		assert_eq!(limiter.take(client, now), Ok(()));
+		assert_eq!(limiter.take(client, now), Err(10));
+		let later = now + Duration::from_secs(4);
+		assert_eq!(limiter.take(client, later), Err(6));
+	}
+}
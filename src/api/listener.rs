@@ -12,21 +12,35 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::api::router::{build_foreign_api_router, build_owner_api_router};
+use crate::api::router::{
+	build_foreign_api_router, build_owner_api_router, build_owner_read_api_router, init_tls_server,
+	tls_acceptor,
+};
 use crate::broker::{
-	Controller, GrinboxPublisher, GrinboxSubscriber, KeybasePublisher, KeybaseSubscriber,
-	Publisher, Subscriber,
+	BackupScheduler, BlockWatcher, ConnectionStatus, Controller, EmailPublisher, EmailSettings,
+	EmailSubscriber, ExecAdapter, GrinboxPublisher, GrinboxSubscriber, KeybasePublisher,
+	KeybaseSubscriber, OutboxWatcher, Publisher, SlateRetrier, Subscriber,
+};
+use crate::common::config::Wallet713Config;
+use crate::common::features::{Feature, FeatureRegistry};
+use crate::common::hasher::derive_account_address_key;
+use crate::common::{Arc, Keychain, Mutex, MutexGuard, RuntimeContext};
+use crate::contacts::{
+	Address, EmailAddress, ExecAddress, GrinboxAddress, KeybaseAddress, DEFAULT_GRINBOX_PORT,
 };
-use crate::common::hasher::derive_address_key;
-use crate::common::{Arc, Keychain, Mutex, MutexGuard};
-use crate::contacts::{Address, GrinboxAddress, KeybaseAddress};
+use crate::wallet::api::Owner;
 use crate::wallet::types::{NodeClient, VersionedSlate, WalletBackend};
-use crate::wallet::Container;
+use crate::wallet::{Container, ErrorKind};
+use chrono::{DateTime, Utc};
 use failure::Error;
 use futures::sync::oneshot;
 use futures::Future;
+use gotham::handler::NewHandler;
 use grin_util::secp::key::PublicKey;
+use log::error;
+use serde::Serialize;
 use std::fmt;
+use std::net::{IpAddr, SocketAddr, UdpSocket};
 use std::thread::{spawn, JoinHandle};
 
 pub trait Listener: Sync + Send + 'static {
@@ -34,28 +48,112 @@ pub trait Listener: Sync + Send + 'static {
 	fn address(&self) -> String;
 	fn publish(&self, slate: &VersionedSlate, to: &String) -> Result<(), Error>;
 	fn stop(self: Box<Self>) -> Result<(), Error>;
+	fn status(&self) -> ListenerStatus;
+	/// Addresses other wallets can send to through this listener
+	fn reachable(&self) -> Vec<ReachableAddress> {
+		vec![]
+	}
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+/// Listener interfaces. Grinbox can listen on several derived addresses at
+/// once, one listener per account number and address index
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash, Serialize)]
 pub enum ListenerInterface {
-	Grinbox,
+	Grinbox(u32, u32),
 	Keybase,
 	ForeignHttp,
 	OwnerHttp,
+	/// Owner API without spend endpoints, on its own port
+	OwnerReadHttp,
+	/// Sends slates over SMTP and picks up responses over IMAP
+	Email,
+	/// Transports run as external processes, configured as `[adapter.<name>]`
+	Exec,
+	Outbox,
+	/// Posts unanswered grinbox and keybase slates again
+	Retry,
+	/// Refreshes outstanding transactions on every new block
+	Blocks,
+	/// Backs the wallet up on a schedule
+	Backup,
+}
+
+/// Snapshot of the state of a running listener
+#[derive(Clone, Debug, Serialize)]
+pub struct ListenerStatus {
+	pub interface: ListenerInterface,
+	pub address: String,
+	pub connected: bool,
+	/// Reconnect attempts since the connection was last lost
+	pub reconnect_attempts: u32,
+	pub last_error: Option<String>,
+	/// Seconds since the current connection was established
+	pub uptime: Option<i64>,
+}
+
+impl ListenerStatus {
+	fn new<L: Listener + ?Sized>(listener: &L, status: ConnectionStatus) -> Self {
+		Self {
+			interface: listener.interface(),
+			address: listener.address(),
+			connected: status.connected,
+			reconnect_attempts: status.reconnect_attempts,
+			last_error: status.last_error,
+			uptime: status
+				.connected_since
+				.map(|t| Utc::now().signed_duration_since(t).num_seconds()),
+		}
+	}
+}
+
+/// A way the wallet can currently be reached, ready to be shared with a sender
+#[derive(Clone, Debug, Serialize)]
+pub struct ReachableAddress {
+	pub interface: ListenerInterface,
+	pub uri: String,
+	/// Where the address comes from or goes through, e.g. the relay
+	pub note: String,
+	pub connected: bool,
+}
+
+impl ListenerInterface {
+	pub fn is_grinbox(&self) -> bool {
+		self.grinbox_account().is_some()
+	}
+
+	/// Number of the account a grinbox listener receives into
+	pub fn grinbox_account(&self) -> Option<u32> {
+		match self {
+			ListenerInterface::Grinbox(account, _) => Some(*account),
+			_ => None,
+		}
+	}
 }
 
 impl fmt::Display for ListenerInterface {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match *self {
-			ListenerInterface::Grinbox => write!(f, "Grinbox"),
+			ListenerInterface::Grinbox(0, index) => write!(f, "Grinbox #{}", index),
+			ListenerInterface::Grinbox(account, index) => {
+				write!(f, "Grinbox #{} of account {}", index, account)
+			}
 			ListenerInterface::Keybase => write!(f, "Keybase"),
 			ListenerInterface::ForeignHttp => write!(f, "Foreign HTTP"),
 			ListenerInterface::OwnerHttp => write!(f, "Owner HTTP"),
+			ListenerInterface::OwnerReadHttp => write!(f, "Owner HTTP (read-only)"),
+			ListenerInterface::Email => write!(f, "Email"),
+			ListenerInterface::Exec => write!(f, "External adapters"),
+			ListenerInterface::Outbox => write!(f, "Outbox"),
+			ListenerInterface::Retry => write!(f, "Slate retry"),
+			ListenerInterface::Blocks => write!(f, "Blocks"),
+			ListenerInterface::Backup => write!(f, "Backups"),
 		}
 	}
 }
 
 pub struct GrinboxListener {
+	account: u32,
+	index: u32,
 	address: GrinboxAddress,
 	publisher: GrinboxPublisher,
 	subscriber: GrinboxSubscriber,
@@ -64,7 +162,7 @@ pub struct GrinboxListener {
 
 impl Listener for GrinboxListener {
 	fn interface(&self) -> ListenerInterface {
-		ListenerInterface::Grinbox
+		ListenerInterface::Grinbox(self.account, self.index)
 	}
 
 	fn address(&self) -> String {
@@ -82,10 +180,25 @@ impl Listener for GrinboxListener {
 		let _ = s.handle.join();
 		Ok(())
 	}
+
+	fn status(&self) -> ListenerStatus {
+		ListenerStatus::new(self, self.subscriber.status())
+	}
+
+	fn reachable(&self) -> Vec<ReachableAddress> {
+		let port = self.address.port.unwrap_or(DEFAULT_GRINBOX_PORT);
+		vec![ReachableAddress {
+			interface: self.interface(),
+			uri: format!("grinbox://{}", self.address.stripped()),
+			note: format!("relay {}:{}", self.address.domain, port),
+			connected: self.subscriber.status().connected,
+		}]
+	}
 }
 
 pub struct KeybaseListener {
 	address: String,
+	username: Option<String>,
 	publisher: KeybasePublisher,
 	subscriber: KeybaseSubscriber,
 	handle: JoinHandle<()>,
@@ -111,12 +224,40 @@ impl Listener for KeybaseListener {
 		let _ = s.handle.join();
 		Ok(())
 	}
+
+	fn status(&self) -> ListenerStatus {
+		ListenerStatus::new(self, self.subscriber.status())
+	}
+
+	fn reachable(&self) -> Vec<ReachableAddress> {
+		let connected = self.subscriber.status().connected;
+		let users = self.username.iter().map(|u| ReachableAddress {
+			interface: self.interface(),
+			uri: format!("keybase://{}", u),
+			note: String::from("keybase chat"),
+			connected,
+		});
+		let teams = self
+			.subscriber
+			.team_channels()
+			.iter()
+			.map(|channel| ReachableAddress {
+				interface: self.interface(),
+				uri: channel.to_string(),
+				note: String::from("keybase team channel"),
+				connected,
+			});
+		users.chain(teams).collect()
+	}
 }
 
 pub struct ForeignHttpListener {
 	address: String,
+	/// URLs the API is advertised at, with where each comes from
+	urls: Vec<(String, String)>,
 	stop: oneshot::Sender<()>,
 	handle: JoinHandle<()>,
+	started: DateTime<Utc>,
 }
 
 impl Listener for ForeignHttpListener {
@@ -138,17 +279,40 @@ impl Listener for ForeignHttpListener {
 		let _ = s.handle;
 		Ok(())
 	}
+
+	fn status(&self) -> ListenerStatus {
+		let status = ConnectionStatus {
+			connected: true,
+			connected_since: Some(self.started),
+			..ConnectionStatus::default()
+		};
+		ListenerStatus::new(self, status)
+	}
+
+	fn reachable(&self) -> Vec<ReachableAddress> {
+		self.urls
+			.iter()
+			.map(|(uri, note)| ReachableAddress {
+				interface: self.interface(),
+				uri: uri.clone(),
+				note: note.clone(),
+				connected: true,
+			})
+			.collect()
+	}
 }
 
 pub struct OwnerHttpListener {
+	interface: ListenerInterface,
 	address: String,
 	stop: oneshot::Sender<()>,
 	handle: JoinHandle<()>,
+	started: DateTime<Utc>,
 }
 
 impl Listener for OwnerHttpListener {
 	fn interface(&self) -> ListenerInterface {
-		ListenerInterface::OwnerHttp
+		self.interface
 	}
 
 	fn address(&self) -> String {
@@ -165,20 +329,31 @@ impl Listener for OwnerHttpListener {
 		let _ = s.handle;
 		Ok(())
 	}
+
+	fn status(&self) -> ListenerStatus {
+		let status = ConnectionStatus {
+			connected: true,
+			connected_since: Some(self.started),
+			..ConnectionStatus::default()
+		};
+		ListenerStatus::new(self, status)
+	}
 }
 
 pub fn start_grinbox<W, C, K>(
 	container: Arc<Mutex<Container<W, C, K>>>,
 	c: &mut MutexGuard<Container<W, C, K>>,
+	account: u32,
+	index: u32,
 ) -> Result<Box<dyn Listener>, Error>
 where
 	W: WalletBackend<C, K>,
 	C: NodeClient,
 	K: Keychain,
 {
-	let index = c.config.grinbox_address_index();
+	let parent_key_id = K::derive_key_id(2, account, 0, 0, 0);
 	let keychain = c.backend()?.keychain();
-	let sec_key = derive_address_key(keychain, index)?;
+	let sec_key = derive_account_address_key(keychain, &parent_key_id, index)?;
 	let pub_key = PublicKey::from_secret_key(keychain.secp(), &sec_key)?;
 
 	let address = GrinboxAddress::new(
@@ -187,17 +362,27 @@ where
 		c.config.grinbox_port,
 	);
 
-	let publisher =
-		GrinboxPublisher::new(&address, &sec_key, c.config.grinbox_protocol_unsecure())?;
+	let publisher = GrinboxPublisher::new(
+		&address,
+		&sec_key,
+		c.config.grinbox_protocol_unsecure(),
+		c.raw_backend().clock(),
+		c.address_book.relays(),
+	)?;
 
 	let subscriber = GrinboxSubscriber::new(&publisher)?;
 
 	let caddress = address.clone();
 	let mut csubscriber = subscriber.clone();
 	let cpublisher = publisher.clone();
+	let events = c.events.clone();
+	let runtime = c.runtime.clone();
 	let handle = spawn(move || {
-		let controller = Controller::new(&caddress.stripped(), container, cpublisher)
-			.expect("could not start grinbox controller!");
+		// Slates received on the address of an account are booked into it
+		let controller =
+			Controller::new(&caddress.stripped(), container, cpublisher, events, runtime)
+				.map(|c| c.with_account(parent_key_id))
+				.expect("could not start grinbox controller!");
 		csubscriber
 			.start(controller)
 			.expect("something went wrong!");
@@ -205,6 +390,8 @@ where
 	});
 
 	Ok(Box::new(GrinboxListener {
+		account,
+		index,
 		address,
 		publisher,
 		subscriber,
@@ -221,13 +408,29 @@ where
 	C: NodeClient,
 	K: Keychain,
 {
-	let subscriber = KeybaseSubscriber::new()?;
-	let publisher = KeybasePublisher::new(c.config.default_keybase_ttl.clone())?;
+	let team_channels = c
+		.config
+		.keybase_team_channels()
+		.iter()
+		.map(|channel| KeybaseAddress::from_str(channel))
+		.collect::<Result<Vec<_>, _>>()?;
+	if let Some(channel) = team_channels.iter().find(|a| !a.is_team()) {
+		Err(ErrorKind::GenericError(format!(
+			"keybase_team_channels entry {} is not a team channel",
+			channel
+		)))?;
+	}
+	let binary = c.config.keybase_binary();
+	let subscriber = KeybaseSubscriber::new(&binary, team_channels)?;
+	let publisher =
+		KeybasePublisher::new(&binary, c.config.keybase_ttl(), c.config.keybase_team_ttl())?;
 
 	let mut csubscriber = subscriber.clone();
 	let cpublisher = publisher.clone();
+	let events = c.events.clone();
+	let runtime = c.runtime.clone();
 	let handle = spawn(move || {
-		let controller = Controller::new("keybase", container, cpublisher)
+		let controller = Controller::new("keybase", container, cpublisher, events, runtime)
 			.expect("could not start keybase controller!");
 		csubscriber
 			.start(controller)
@@ -235,14 +438,483 @@ where
 		()
 	});
 
+	let username = subscriber.username();
 	Ok(Box::new(KeybaseListener {
-		address: String::from("keybase"),
+		address: username.clone().unwrap_or(String::from("keybase")),
+		username,
+		publisher,
+		subscriber,
+		handle,
+	}))
+}
+
+pub struct EmailListener {
+	address: String,
+	publisher: EmailPublisher,
+	subscriber: EmailSubscriber,
+	handle: JoinHandle<()>,
+}
+
+impl Listener for EmailListener {
+	fn interface(&self) -> ListenerInterface {
+		ListenerInterface::Email
+	}
+
+	fn address(&self) -> String {
+		self.address.clone()
+	}
+
+	fn publish(&self, slate: &VersionedSlate, to: &String) -> Result<(), Error> {
+		let address = EmailAddress::from_str(to)?;
+		self.publisher.post_slate(slate, &address)
+	}
+
+	fn stop(self: Box<Self>) -> Result<(), Error> {
+		let s = *self;
+		s.subscriber.stop();
+		let _ = s.handle.join();
+		Ok(())
+	}
+
+	fn status(&self) -> ListenerStatus {
+		ListenerStatus::new(self, self.subscriber.status())
+	}
+
+	fn reachable(&self) -> Vec<ReachableAddress> {
+		vec![ReachableAddress {
+			interface: self.interface(),
+			uri: format!("email://{}", self.address),
+			note: String::from("slate files by email"),
+			connected: self.subscriber.status().connected,
+		}]
+	}
+}
+
+pub fn start_email<W, C, K>(
+	container: Arc<Mutex<Container<W, C, K>>>,
+	c: &mut MutexGuard<Container<W, C, K>>,
+) -> Result<Box<dyn Listener>, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let settings = EmailSettings::from_config(&c.config)?;
+	let address = settings.address.clone();
+	let subscriber = EmailSubscriber::new(settings.clone());
+	let publisher = EmailPublisher::new(settings);
+
+	let mut csubscriber = subscriber.clone();
+	let cpublisher = publisher.clone();
+	let events = c.events.clone();
+	let runtime = c.runtime.clone();
+	let handle = spawn(move || {
+		let controller = Controller::new("email", container, cpublisher, events, runtime)
+			.expect("could not start email controller!");
+		csubscriber
+			.start(controller)
+			.expect("something went wrong!");
+		()
+	});
+
+	Ok(Box::new(EmailListener {
+		address,
 		publisher,
 		subscriber,
 		handle,
 	}))
 }
 
+pub struct ExecListener {
+	adapters: Vec<ExecAdapter>,
+	handles: Vec<JoinHandle<()>>,
+}
+
+impl Listener for ExecListener {
+	fn interface(&self) -> ListenerInterface {
+		ListenerInterface::Exec
+	}
+
+	fn address(&self) -> String {
+		self.adapters
+			.iter()
+			.map(|a| a.address().unwrap_or_else(|| a.name().to_owned()))
+			.collect::<Vec<_>>()
+			.join(", ")
+	}
+
+	fn publish(&self, slate: &VersionedSlate, to: &String) -> Result<(), Error> {
+		let address = ExecAddress::from_str(to)?;
+		let adapter = self
+			.adapters
+			.iter()
+			.find(|a| a.name() == address.scheme)
+			.ok_or(ErrorKind::NoListener(address.scheme.clone()))?;
+		adapter.post_slate(slate, &address)
+	}
+
+	fn stop(self: Box<Self>) -> Result<(), Error> {
+		let s = *self;
+		for adapter in &s.adapters {
+			adapter.stop();
+		}
+		for handle in s.handles {
+			let _ = handle.join();
+		}
+		Ok(())
+	}
+
+	/// Connected when every adapter is, with the first error of any
+	fn status(&self) -> ListenerStatus {
+		let statuses: Vec<ConnectionStatus> = self.adapters.iter().map(|a| a.status()).collect();
+		let status = ConnectionStatus {
+			connected: statuses.iter().all(|s| s.connected),
+			reconnect_attempts: statuses
+				.iter()
+				.map(|s| s.reconnect_attempts)
+				.max()
+				.unwrap_or(0),
+			last_error: statuses.iter().filter_map(|s| s.last_error.clone()).next(),
+			connected_since: statuses.iter().filter_map(|s| s.connected_since).max(),
+		};
+		ListenerStatus::new(self, status)
+	}
+
+	fn reachable(&self) -> Vec<ReachableAddress> {
+		self.adapters
+			.iter()
+			.filter_map(|a| {
+				Some(ReachableAddress {
+					interface: self.interface(),
+					uri: a.address()?,
+					note: format!("adapter {}", a.name()),
+					connected: a.status().connected,
+				})
+			})
+			.collect()
+	}
+}
+
+/// Start every configured adapter and listen on each of them
+pub fn start_exec<W, C, K>(
+	container: Arc<Mutex<Container<W, C, K>>>,
+	c: &mut MutexGuard<Container<W, C, K>>,
+) -> Result<Box<dyn Listener>, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if c.exec_adapters.is_empty() {
+		return Err(ErrorKind::NoExecAdapters.into());
+	}
+	let mut adapters: Vec<ExecAdapter> = c.exec_adapters.values().cloned().collect();
+	adapters.sort_by(|a, b| a.name().cmp(b.name()));
+	for adapter in &adapters {
+		adapter.connect()?;
+	}
+
+	let handles = adapters
+		.iter()
+		.map(|adapter| {
+			let mut csubscriber = adapter.clone();
+			let cpublisher = adapter.clone();
+			let name = adapter.name().to_owned();
+			let container = container.clone();
+			let events = c.events.clone();
+			let runtime = c.runtime.clone();
+			spawn(move || {
+				let controller = Controller::new(&name, container, cpublisher, events, runtime)
+					.expect("could not start adapter controller!");
+				csubscriber
+					.start(controller)
+					.expect("something went wrong!");
+			})
+		})
+		.collect();
+
+	Ok(Box::new(ExecListener { adapters, handles }))
+}
+
+pub struct OutboxListener {
+	watcher: OutboxWatcher,
+	handle: JoinHandle<()>,
+}
+
+impl Listener for OutboxListener {
+	fn interface(&self) -> ListenerInterface {
+		ListenerInterface::Outbox
+	}
+
+	fn address(&self) -> String {
+		self.watcher.dir().display().to_string()
+	}
+
+	fn publish(&self, _slate: &VersionedSlate, _to: &String) -> Result<(), Error> {
+		unimplemented!();
+	}
+
+	fn stop(self: Box<Self>) -> Result<(), Error> {
+		let s = *self;
+		s.watcher.stop();
+		let _ = s.handle.join();
+		Ok(())
+	}
+
+	fn status(&self) -> ListenerStatus {
+		ListenerStatus::new(self, self.watcher.status())
+	}
+}
+
+pub fn start_outbox<W, C, K>(
+	container: Arc<Mutex<Container<W, C, K>>>,
+	c: &mut MutexGuard<Container<W, C, K>>,
+) -> Result<Box<dyn Listener>, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let dir = c.config.outbox_dir().ok_or(ErrorKind::NoOutboxDir)?;
+	let watcher = OutboxWatcher::new(dir);
+
+	let cwatcher = watcher.clone();
+	let handle = spawn(move || {
+		if let Err(e) = cwatcher.start(Owner::new(container)) {
+			error!("outbox listener stopped: {}", e);
+		}
+	});
+
+	Ok(Box::new(OutboxListener { watcher, handle }))
+}
+
+pub struct RetryListener {
+	retrier: SlateRetrier,
+	handle: JoinHandle<()>,
+}
+
+impl Listener for RetryListener {
+	fn interface(&self) -> ListenerInterface {
+		ListenerInterface::Retry
+	}
+
+	fn address(&self) -> String {
+		String::from("retry queue")
+	}
+
+	fn publish(&self, _slate: &VersionedSlate, _to: &String) -> Result<(), Error> {
+		unimplemented!();
+	}
+
+	fn stop(self: Box<Self>) -> Result<(), Error> {
+		let s = *self;
+		s.retrier.stop();
+		let _ = s.handle.join();
+		Ok(())
+	}
+
+	fn status(&self) -> ListenerStatus {
+		ListenerStatus::new(self, self.retrier.status())
+	}
+}
+
+pub fn start_retry<W, C, K>(
+	container: Arc<Mutex<Container<W, C, K>>>,
+	_c: &mut MutexGuard<Container<W, C, K>>,
+) -> Result<Box<dyn Listener>, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let retrier = SlateRetrier::new();
+
+	let cretrier = retrier.clone();
+	let handle = spawn(move || {
+		if let Err(e) = cretrier.start(Owner::new(container)) {
+			error!("retry listener stopped: {}", e);
+		}
+	});
+
+	Ok(Box::new(RetryListener { retrier, handle }))
+}
+
+pub struct BlockListener {
+	address: String,
+	watcher: BlockWatcher,
+	handle: JoinHandle<()>,
+}
+
+impl Listener for BlockListener {
+	fn interface(&self) -> ListenerInterface {
+		ListenerInterface::Blocks
+	}
+
+	fn address(&self) -> String {
+		self.address.clone()
+	}
+
+	fn publish(&self, _slate: &VersionedSlate, _to: &String) -> Result<(), Error> {
+		unimplemented!();
+	}
+
+	fn stop(self: Box<Self>) -> Result<(), Error> {
+		let s = *self;
+		s.watcher.stop();
+		let _ = s.handle.join();
+		Ok(())
+	}
+
+	fn status(&self) -> ListenerStatus {
+		ListenerStatus::new(self, self.watcher.status())
+	}
+}
+
+pub fn start_blocks<W, C, K>(
+	container: Arc<Mutex<Container<W, C, K>>>,
+	c: &mut MutexGuard<Container<W, C, K>>,
+) -> Result<Box<dyn Listener>, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let address = c.config.grin_node_uri();
+	let watcher = BlockWatcher::new(c.config.block_listener_interval(), c.events.clone());
+
+	let cwatcher = watcher.clone();
+	let handle = spawn(move || {
+		if let Err(e) = cwatcher.start(Owner::new(container)) {
+			error!("block listener stopped: {}", e);
+		}
+	});
+
+	Ok(Box::new(BlockListener {
+		address,
+		watcher,
+		handle,
+	}))
+}
+
+pub struct BackupListener {
+	address: String,
+	scheduler: BackupScheduler,
+	handle: JoinHandle<()>,
+}
+
+impl Listener for BackupListener {
+	fn interface(&self) -> ListenerInterface {
+		ListenerInterface::Backup
+	}
+
+	fn address(&self) -> String {
+		self.address.clone()
+	}
+
+	fn publish(&self, _slate: &VersionedSlate, _to: &String) -> Result<(), Error> {
+		unimplemented!();
+	}
+
+	fn stop(self: Box<Self>) -> Result<(), Error> {
+		let s = *self;
+		s.scheduler.stop();
+		let _ = s.handle.join();
+		Ok(())
+	}
+
+	fn status(&self) -> ListenerStatus {
+		ListenerStatus::new(self, self.scheduler.status())
+	}
+}
+
+pub fn start_backup<W, C, K>(
+	container: Arc<Mutex<Container<W, C, K>>>,
+	c: &mut MutexGuard<Container<W, C, K>>,
+) -> Result<Box<dyn Listener>, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if !c.config.backup_schedule() {
+		return Err(ErrorKind::NoBackupSchedule.into());
+	}
+	let address = c.config.backup_dir()?.display().to_string();
+	let scheduler = BackupScheduler::new(
+		c.config.backup_interval_hours(),
+		c.config.backup_every_txs(),
+		c.events.clone(),
+	);
+
+	let cscheduler = scheduler.clone();
+	let handle = spawn(move || {
+		if let Err(e) = cscheduler.start(Owner::new(container)) {
+			error!("backup scheduler stopped: {}", e);
+		}
+	});
+
+	Ok(Box::new(BackupListener {
+		address,
+		scheduler,
+		handle,
+	}))
+}
+
+/// Plain HTTP server, or HTTPS when a certificate and key are configured
+fn serve<NH>(
+	address: &str,
+	router: NH,
+	tls_files: Option<(String, String)>,
+) -> Result<Box<dyn Future<Item = (), Error = ()> + Send>, Error>
+where
+	NH: NewHandler + 'static,
+{
+	let server: Box<dyn Future<Item = (), Error = ()> + Send> = match tls_files {
+		Some((cert_file, key_file)) => {
+			let acceptor = tls_acceptor(&cert_file, &key_file)?;
+			Box::new(init_tls_server(address, router, acceptor)?)
+		}
+		None => Box::new(gotham::init_server(address.to_owned(), router)),
+	};
+	Ok(server)
+}
+
+/// URLs the foreign API listening on `address` can be reached at. When bound
+/// to all interfaces, the address of the interface that routes outside is
+/// used. The configured public URL, e.g. an onion service, comes last
+fn foreign_api_urls(address: &str, tls: bool, public_url: Option<String>) -> Vec<(String, String)> {
+	let scheme = if tls { "https" } else { "http" };
+	let mut urls = vec![];
+	if let Ok(addr) = address.parse::<SocketAddr>() {
+		let ip = addr.ip();
+		if ip.is_unspecified() {
+			if let Some(local) = outbound_ip() {
+				let url = format!("{}://{}", scheme, SocketAddr::new(local, addr.port()));
+				urls.push((url, String::from("detected IP")));
+			}
+		} else {
+			let note = if ip.is_loopback() {
+				"this machine only"
+			} else {
+				"listening address"
+			};
+			urls.push((format!("{}://{}", scheme, addr), String::from(note)));
+		}
+	}
+	if let Some(url) = public_url {
+		urls.push((url, String::from("configured public URL")));
+	}
+	urls
+}
+
+/// Address of the interface used for outgoing traffic. Connecting a UDP
+/// socket only picks the route, nothing is sent
+fn outbound_ip() -> Option<IpAddr> {
+	let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+	socket.connect("1.1.1.1:80").ok()?;
+	socket.local_addr().ok().map(|a| a.ip())
+}
+
 pub fn start_foreign_http<W, C, K>(
 	container: Arc<Mutex<Container<W, C, K>>>,
 	c: &mut MutexGuard<Container<W, C, K>>,
@@ -254,8 +926,15 @@ where
 {
 	let (stop, stop_recv) = oneshot::channel::<()>();
 	let address = c.config.foreign_api_address();
-	let router = build_foreign_api_router(container, c.config.foreign_api_secret.clone());
-	let server = gotham::init_server(address.clone(), router);
+	let secret = c.config.foreign_api_secret.clone();
+	let router = build_foreign_api_router(container, secret, &c.config.api_limits(), &c.metrics);
+	let tls_files = c.config.foreign_api_tls_files()?;
+	let urls = foreign_api_urls(
+		&address,
+		tls_files.is_some(),
+		c.config.foreign_api_public_url.clone(),
+	);
+	let server = serve(&address, router, tls_files)?;
 	let fut = stop_recv
 		.map_err(|_| ())
 		.select(server)
@@ -268,8 +947,10 @@ where
 
 	Ok(Box::new(ForeignHttpListener {
 		address,
+		urls,
 		stop,
 		handle,
+		started: Utc::now(),
 	}))
 }
 
@@ -284,8 +965,16 @@ where
 {
 	let (stop, stop_recv) = oneshot::channel::<()>();
 	let address = c.config.owner_api_address();
-	let router = build_owner_api_router(container, c.config.owner_api_secret.clone());
-	let server = gotham::init_server(address.clone(), router);
+	let config = &c.config;
+	let router = build_owner_api_router(
+		container,
+		config.owner_api_secret.clone(),
+		&config.api_limits(),
+		&c.metrics,
+		&config.owner_api_cors(),
+		&config.api_base_path(),
+	);
+	let server = serve(&address, router, c.config.owner_api_tls_files()?)?;
 	let fut = stop_recv
 		.map_err(|_| ())
 		.select(server)
@@ -297,8 +986,175 @@ where
 	});
 
 	Ok(Box::new(OwnerHttpListener {
+		interface: ListenerInterface::OwnerHttp,
 		address,
 		stop,
 		handle,
+		started: Utc::now(),
 	}))
 }
+
+pub fn start_owner_read_http<W, C, K>(
+	container: Arc<Mutex<Container<W, C, K>>>,
+	c: &mut MutexGuard<Container<W, C, K>>,
+) -> Result<Box<dyn Listener>, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let (stop, stop_recv) = oneshot::channel::<()>();
+	let address = c.config.owner_read_api_address();
+	let config = &c.config;
+	let router = build_owner_read_api_router(
+		container,
+		config.owner_read_api_secret.clone(),
+		&config.api_limits(),
+		&c.metrics,
+		&config.owner_api_cors(),
+		&config.api_base_path(),
+	);
+	let server = serve(&address, router, c.config.owner_api_tls_files()?)?;
+	let fut = stop_recv
+		.map_err(|_| ())
+		.select(server)
+		.map(|(res, _)| res)
+		.map_err(|(error, _)| error);
+	let handle = spawn(move || {
+		tokio::run(fut);
+		()
+	});
+
+	Ok(Box::new(OwnerHttpListener {
+		interface: ListenerInterface::OwnerReadHttp,
+		address,
+		stop,
+		handle,
+		started: Utc::now(),
+	}))
+}
+
+/// Transports and APIs the listeners can provide
+pub fn register_features(
+	registry: &mut FeatureRegistry,
+	config: &Wallet713Config,
+	runtime: &RuntimeContext,
+) {
+	let on_off = |enabled: bool, key: &str| {
+		let state = if enabled { "on" } else { "off" };
+		format!("{} {}", key, state)
+	};
+	registry.register(Feature::new(
+		"grinbox",
+		true,
+		true,
+		&on_off(config.grinbox_listener_auto_start(runtime), "auto start"),
+	));
+	registry.register(Feature::new(
+		"keybase",
+		true,
+		true,
+		&on_off(config.keybase_listener_auto_start(), "auto start"),
+	));
+	registry.register(Feature::new(
+		"outbox",
+		true,
+		config.outbox_dir().is_some(),
+		"needs outbox_dir",
+	));
+	registry.register(Feature::new(
+		"email",
+		true,
+		config.email_address.is_some(),
+		"needs email_address and mail servers",
+	));
+	let mut adapters: Vec<String> = config.exec_adapters().into_iter().map(|(n, _)| n).collect();
+	adapters.sort();
+	let detail = if adapters.is_empty() {
+		String::from("needs [adapter.<name>] tables")
+	} else {
+		let auto_start = on_off(config.adapters_listener_auto_start(), "auto start");
+		format!("{}, {}", adapters.join(", "), auto_start)
+	};
+	registry.register(Feature::new(
+		"adapters",
+		true,
+		!adapters.is_empty(),
+		&detail,
+	));
+	registry.register(Feature::new(
+		"tor",
+		false,
+		false,
+		"not supported by this build",
+	));
+	registry.register(Feature::new(
+		"owner_api",
+		true,
+		config.owner_api(),
+		&config.owner_api_address(),
+	));
+	registry.register(Feature::new(
+		"owner_read_api",
+		true,
+		config.owner_read_api(),
+		&config.owner_read_api_address(),
+	));
+	registry.register(Feature::new(
+		"foreign_api",
+		true,
+		config.foreign_api(),
+		&config.foreign_api_address(),
+	));
+	let tls = config
+		.owner_api_tls_files()
+		.and_then(|owner| Ok((owner, config.foreign_api_tls_files()?)));
+	let (tls, detail) = match tls {
+		Ok((owner, foreign)) => {
+			let mut served = vec![];
+			if owner.is_some() {
+				served.push("owner APIs");
+			}
+			if foreign.is_some() {
+				served.push("foreign API");
+			}
+			if served.is_empty() {
+				(false, "needs tls_cert_file and tls_key_file".to_owned())
+			} else {
+				(true, served.join(", "))
+			}
+		}
+		Err(e) => (false, e.to_string()),
+	};
+	registry.register(Feature::new("tls", true, tls, &detail));
+	registry.register(Feature::new(
+		"block_listener",
+		true,
+		config.block_listener(),
+		&format!("every {}s", config.block_listener_interval()),
+	));
+	let mut schedule = vec![];
+	if let Some(hours) = config.backup_interval_hours() {
+		schedule.push(format!("every {}h", hours));
+	}
+	if let Some(txs) = config.backup_every_txs() {
+		schedule.push(format!("after {} transactions", txs));
+	}
+	let detail = if schedule.is_empty() {
+		String::from("needs backup_interval_hours or backup_every_txs")
+	} else {
+		format!("{}, keep {}", schedule.join(", "), config.backup_keep())
+	};
+	registry.register(Feature::new(
+		"backups",
+		true,
+		config.backup_schedule(),
+		&detail,
+	));
+	registry.register(Feature::new(
+		"event_stream",
+		true,
+		config.owner_api() || config.owner_read_api(),
+		"websocket on /v1/wallet/owner/ws",
+	));
+}
@@ -13,7 +13,11 @@
 // limitations under the License.
 
 mod auth;
+pub mod cors;
 mod error;
+pub mod limits;
 pub mod listener;
+mod rest;
 pub mod router;
 mod rpc;
+mod ws;
@@ -0,0 +1,563 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `/v2` REST routes of the owner APIs. Every route is described once in
+//! `routes`, which is used both to register it and to generate the OpenAPI
+//! spec served at `/v2/spec.json`, so the two can't drift apart
+
+use super::router::MAX_PAGE_SIZE;
+use crate::common::{ErrorKind, Keychain};
+use crate::wallet::api::Owner;
+use crate::wallet::types::{EstimateFeeArgs, NodeClient, Page, TxExportFormat, WalletBackend};
+use crate::wallet::ErrorKind as WalletErrorKind;
+use clap::crate_version;
+use failure::Error;
+use futures::{future, Future, Stream};
+use gotham::handler::HandlerFuture;
+use gotham::helpers::http::response::create_response;
+use gotham::state::{FromState, State};
+use hyper::{Body, Method, Response, StatusCode, Uri};
+use log::{debug, error};
+use mime::Mime;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+use url::form_urlencoded;
+
+pub const SPEC_PATH: &str = "/v2/spec.json";
+
+/// Handler of a REST route, given the request state, its validated query
+/// and its body
+pub type RestFn = fn(&State, &Query, &[u8]) -> Result<Reply, Error>;
+
+#[derive(Clone, Copy, Debug)]
+pub enum ParamKind {
+	Boolean,
+	Integer,
+	String,
+}
+
+impl ParamKind {
+	fn schema_type(self) -> &'static str {
+		match self {
+			ParamKind::Boolean => "boolean",
+			ParamKind::Integer => "integer",
+			ParamKind::String => "string",
+		}
+	}
+}
+
+/// Query parameter of a route
+#[derive(Debug)]
+pub struct Param {
+	pub name: &'static str,
+	pub kind: ParamKind,
+	pub required: bool,
+	pub description: &'static str,
+}
+
+const fn param(name: &'static str, kind: ParamKind, description: &'static str) -> Param {
+	Param {
+		name,
+		kind,
+		required: false,
+		description,
+	}
+}
+
+const REFRESH: Param = param(
+	"refresh",
+	ParamKind::Boolean,
+	"Refresh from the node first, false by default",
+);
+const TX_ID: Param = param(
+	"tx_id",
+	ParamKind::Integer,
+	"Only entries of this transaction",
+);
+const OFFSET: Param = param("offset", ParamKind::Integer, "Entries to skip");
+const LIMIT: Param = param(
+	"limit",
+	ParamKind::Integer,
+	"Entries to return, at most 1000",
+);
+
+const INFO_PARAMS: &[Param] = &[
+	REFRESH,
+	param(
+		"minimum_confirmations",
+		ParamKind::Integer,
+		"Confirmations for an output to be spendable, 10 by default",
+	),
+];
+const TXS_PARAMS: &[Param] = &[REFRESH, TX_ID, OFFSET, LIMIT];
+const TX_DETAIL_PARAMS: &[Param] = &[Param {
+	name: "id",
+	kind: ParamKind::Integer,
+	required: true,
+	description: "Id of the transaction",
+}];
+const EXPORT_PARAMS: &[Param] = &[param(
+	"format",
+	ParamKind::String,
+	"json or csv, json by default",
+)];
+const OUTPUTS_PARAMS: &[Param] = &[
+	REFRESH,
+	param(
+		"include_spent",
+		ParamKind::Boolean,
+		"Include spent outputs, false by default",
+	),
+	TX_ID,
+	OFFSET,
+	LIMIT,
+];
+
+/// A REST route and its documentation
+pub struct RestRoute {
+	pub method: Method,
+	pub path: &'static str,
+	pub summary: &'static str,
+	pub params: &'static [Param],
+	/// What the JSON body has to hold, for routes taking one
+	pub body: Option<&'static str>,
+	/// What a successful response holds
+	pub response: &'static str,
+	pub handler: RestFn,
+}
+
+/// Every `/v2` REST route. All of them only read the wallet, so they are
+/// served by the read-only owner API as well
+pub fn routes<W, C, K>() -> Vec<RestRoute>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	vec![
+		RestRoute {
+			method: Method::GET,
+			path: "/v2/accounts",
+			summary: "Accounts of the wallet, without the archived ones",
+			params: &[],
+			body: None,
+			response: "List of accounts with their label and derivation path",
+			handler: accounts::<W, C, K>,
+		},
+		RestRoute {
+			method: Method::GET,
+			path: "/v2/info",
+			summary: "Balances of the active account",
+			params: INFO_PARAMS,
+			body: None,
+			response: "`validated` tells whether the node was reached, `info` holds the balances",
+			handler: info::<W, C, K>,
+		},
+		RestRoute {
+			method: Method::GET,
+			path: "/v2/height",
+			summary: "Chain height",
+			params: &[],
+			body: None,
+			response: "`height`, and whether it was updated from the node",
+			handler: height::<W, C, K>,
+		},
+		RestRoute {
+			method: Method::GET,
+			path: "/v2/txs",
+			summary: "Transactions of the active account, oldest first",
+			params: TXS_PARAMS,
+			body: None,
+			response: "`validated` tells whether the node was reached, `txs` holds the page",
+			handler: txs::<W, C, K>,
+		},
+		RestRoute {
+			method: Method::GET,
+			path: "/v2/txs/detail",
+			summary: "Everything the wallet knows about a transaction",
+			params: TX_DETAIL_PARAMS,
+			body: None,
+			response: "The transaction with its inputs, outputs, messages and proof",
+			handler: tx_detail::<W, C, K>,
+		},
+		RestRoute {
+			method: Method::GET,
+			path: "/v2/txs/export",
+			summary: "Transaction history of the active account for bookkeeping",
+			params: EXPORT_PARAMS,
+			body: None,
+			response: "The history as JSON or CSV",
+			handler: txs_export::<W, C, K>,
+		},
+		RestRoute {
+			method: Method::GET,
+			path: "/v2/outputs",
+			summary: "Outputs of the active account, by derivation index",
+			params: OUTPUTS_PARAMS,
+			body: None,
+			response: "`validated` tells whether the node was reached, `outputs` holds the page",
+			handler: outputs::<W, C, K>,
+		},
+		RestRoute {
+			method: Method::POST,
+			path: "/v2/estimate",
+			summary: "Fees of a send with each selection strategy",
+			params: &[],
+			body: Some(
+				"`src_acct_name`, `amount`, `strategies` (`smallest` or `all`), \
+				 `minimum_confirmations`, `max_outputs` and `num_change_outputs`",
+			),
+			response: "One estimate per strategy, with the fee and the amount it locks",
+			handler: estimate::<W, C, K>,
+		},
+		RestRoute {
+			method: Method::GET,
+			path: SPEC_PATH,
+			summary: "This OpenAPI spec",
+			params: &[],
+			body: None,
+			response: "OpenAPI 3.0 document",
+			handler: spec_handler::<W, C, K>,
+		},
+	]
+}
+
+/// Response of a handler
+pub enum Reply {
+	Json(Value),
+	Text(Mime, String),
+}
+
+impl Reply {
+	fn into_body(self) -> (Mime, String) {
+		match self {
+			Reply::Json(value) => (mime::APPLICATION_JSON, value.to_string()),
+			Reply::Text(mime, text) => (mime, text),
+		}
+	}
+}
+
+/// Query string of a request, holding only the parameters of its route
+pub struct Query {
+	values: HashMap<String, String>,
+}
+
+impl Query {
+	fn parse(uri: &Uri, params: &[Param]) -> Result<Self, Error> {
+		let values: HashMap<String, String> = match uri.query() {
+			Some(q) => form_urlencoded::parse(q.as_bytes()).into_owned().collect(),
+			None => HashMap::new(),
+		};
+		if let Some(name) = values.keys().find(|n| !params.iter().any(|p| p.name == *n)) {
+			let msg = format!("unknown parameter '{}'", name);
+			return Err(ErrorKind::InvalidQuery(msg).into());
+		}
+		if let Some(p) = params
+			.iter()
+			.find(|p| p.required && !values.contains_key(p.name))
+		{
+			let msg = format!("parameter '{}' is required", p.name);
+			return Err(ErrorKind::InvalidQuery(msg).into());
+		}
+		Ok(Self { values })
+	}
+
+	pub fn get<T: FromStr>(&self, name: &str) -> Result<Option<T>, Error> {
+		match self.values.get(name) {
+			Some(v) => match v.parse() {
+				Ok(v) => Ok(Some(v)),
+				Err(_) => {
+					let msg = format!("invalid value '{}' of '{}'", v, name);
+					Err(ErrorKind::InvalidQuery(msg).into())
+				}
+			},
+			None => Ok(None),
+		}
+	}
+
+	fn page(&self) -> Result<Page, Error> {
+		let limit = self
+			.get("limit")?
+			.unwrap_or(MAX_PAGE_SIZE)
+			.min(MAX_PAGE_SIZE);
+		Ok(Page::new(self.get("offset")?.unwrap_or(0), Some(limit)))
+	}
+}
+
+/// Gotham handler running the handler of a route
+pub fn rest_handler(
+	mut state: State,
+	handler: RestFn,
+	params: &'static [Param],
+) -> Box<HandlerFuture> {
+	let future = Body::take_from(&mut state).concat2().then(move |body| {
+		let res = body.map_err(Error::from).and_then(|body| {
+			let query = Query::parse(Uri::borrow_from(&state), params)?;
+			handler(&state, &query, &body)
+		});
+		let res = match res.map(Reply::into_body) {
+			Ok((mime, body)) => create_response(&state, StatusCode::OK, mime, body),
+			Err(e) => error_response(&state, e),
+		};
+		future::ok((state, res))
+	});
+	Box::new(future)
+}
+
+/// Every error is answered with `{"error": {"status", "kind", "message"}}`
+fn error_response(state: &State, e: Error) -> Response<Body> {
+	let wallet_kind = e.downcast_ref::<WalletErrorKind>();
+	let (status, kind) = match (wallet_kind, e.downcast_ref::<ErrorKind>()) {
+		(Some(kind), _) => (wallet_error_status(kind), kind_name(kind)),
+		(None, Some(kind)) => (common_error_status(kind), kind_name(kind)),
+		(None, None) if e.downcast_ref::<serde_json::Error>().is_some() => {
+			(StatusCode::BAD_REQUEST, "InvalidBody".to_owned())
+		}
+		(None, None) => (StatusCode::INTERNAL_SERVER_ERROR, "Internal".to_owned()),
+	};
+	match status.is_server_error() {
+		true => error!("REST request failed: {}", e),
+		false => debug!("REST request refused: {}", e),
+	}
+	let body = json!({
+		"error": {
+			"status": status.as_u16(),
+			"kind": kind,
+			"message": e.to_string(),
+		}
+	});
+	create_response(state, status, mime::APPLICATION_JSON, body.to_string())
+}
+
+fn wallet_error_status(kind: &WalletErrorKind) -> StatusCode {
+	match kind {
+		WalletErrorKind::TransactionDoesntExist(_)
+		| WalletErrorKind::OutputNotFound(_)
+		| WalletErrorKind::UnknownAccountLabel(_) => StatusCode::NOT_FOUND,
+		WalletErrorKind::Conflict(_) => StatusCode::CONFLICT,
+		WalletErrorKind::NotEnoughFunds { .. } | WalletErrorKind::InvalidAmount(_) => {
+			StatusCode::UNPROCESSABLE_ENTITY
+		}
+		_ => StatusCode::INTERNAL_SERVER_ERROR,
+	}
+}
+
+fn common_error_status(kind: &ErrorKind) -> StatusCode {
+	match kind {
+		ErrorKind::InvalidQuery(_) | ErrorKind::UnknownExportFormat(_) => StatusCode::BAD_REQUEST,
+		ErrorKind::WalletLocked => StatusCode::SERVICE_UNAVAILABLE,
+		_ => StatusCode::INTERNAL_SERVER_ERROR,
+	}
+}
+
+/// Name of the variant of an error kind
+fn kind_name<D: std::fmt::Debug>(kind: &D) -> String {
+	format!("{:?}", kind)
+		.chars()
+		.take_while(|c| c.is_alphanumeric())
+		.collect()
+}
+
+/// OpenAPI 3.0 document of `routes`, served under `base_path`
+pub fn spec(routes: &[RestRoute], base_path: &str) -> Value {
+	let mut paths = Map::new();
+	for route in routes {
+		let parameters: Vec<Value> = route
+			.params
+			.iter()
+			.map(|p| {
+				json!({
+					"name": p.name,
+					"in": "query",
+					"required": p.required,
+					"description": p.description,
+					"schema": { "type": p.kind.schema_type() },
+				})
+			})
+			.collect();
+		let mut operation = json!({
+			"summary": route.summary,
+			"parameters": parameters,
+			"responses": {
+				"200": { "description": route.response },
+				"default": {
+					"description": "Error",
+					"content": {
+						"application/json": {
+							"schema": { "$ref": "#/components/schemas/Error" }
+						}
+					}
+				}
+			}
+		});
+		if let Some(body) = route.body {
+			operation["requestBody"] = json!({
+				"required": true,
+				"description": body,
+				"content": { "application/json": { "schema": { "type": "object" } } },
+			});
+		}
+		let method = route.method.as_str().to_lowercase();
+		let path = paths.entry(route.path).or_insert_with(|| json!({}));
+		if let Some(path) = path.as_object_mut() {
+			path.insert(method, operation);
+		}
+	}
+
+	json!({
+		"openapi": "3.0.0",
+		"info": {
+			"title": "wallet713 owner REST API",
+			"version": crate_version!(),
+		},
+		"servers": [{ "url": base_path }],
+		"security": [{ "basic": [] }],
+		"paths": paths,
+		"components": {
+			"securitySchemes": {
+				"basic": { "type": "http", "scheme": "basic" }
+			},
+			"schemas": {
+				"Error": {
+					"type": "object",
+					"properties": {
+						"error": {
+							"type": "object",
+							"properties": {
+								"status": { "type": "integer" },
+								"kind": { "type": "string" },
+								"message": { "type": "string" }
+							}
+						}
+					}
+				}
+			}
+		}
+	})
+}
+
+fn spec_handler<W, C, K>(state: &State, _query: &Query, _body: &[u8]) -> Result<Reply, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	// The path the spec was asked for tells where the API is mounted
+	let path = Uri::borrow_from(state).path();
+	let base_path = path.trim_end_matches(SPEC_PATH);
+	Ok(Reply::Json(spec(&routes::<W, C, K>(), base_path)))
+}
+
+fn accounts<W, C, K>(state: &State, _query: &Query, _body: &[u8]) -> Result<Reply, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let api = Owner::<W, C, K>::borrow_from(state);
+	Ok(Reply::Json(json!(api.accounts()?)))
+}
+
+fn info<W, C, K>(state: &State, query: &Query, _body: &[u8]) -> Result<Reply, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let api = Owner::<W, C, K>::borrow_from(state);
+	let refresh = query.get("refresh")?.unwrap_or(false);
+	let confirmations = query.get("minimum_confirmations")?.unwrap_or(10);
+	let (validated, info) = api.retrieve_summary_info(refresh, confirmations)?;
+	Ok(Reply::Json(json!({ "validated": validated, "info": info })))
+}
+
+fn height<W, C, K>(state: &State, _query: &Query, _body: &[u8]) -> Result<Reply, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let api = Owner::<W, C, K>::borrow_from(state);
+	Ok(Reply::Json(json!(api.node_height()?)))
+}
+
+fn txs<W, C, K>(state: &State, query: &Query, _body: &[u8]) -> Result<Reply, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let api = Owner::<W, C, K>::borrow_from(state);
+	let refresh = query.get("refresh")?.unwrap_or(false);
+	let tx_id = query.get("tx_id")?;
+	let page = Some(query.page()?);
+	let (validated, _, txs, _, _) =
+		api.retrieve_txs(refresh, false, false, tx_id, None, None, page)?;
+	Ok(Reply::Json(json!({ "validated": validated, "txs": txs })))
+}
+
+fn tx_detail<W, C, K>(state: &State, query: &Query, _body: &[u8]) -> Result<Reply, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let api = Owner::<W, C, K>::borrow_from(state);
+	// Required, so always there
+	let id = query.get("id")?.unwrap_or(0);
+	Ok(Reply::Json(json!(api.tx_detail(id)?)))
+}
+
+fn txs_export<W, C, K>(state: &State, query: &Query, _body: &[u8]) -> Result<Reply, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let api = Owner::<W, C, K>::borrow_from(state);
+	let format = match query.get::<String>("format")? {
+		Some(f) => TxExportFormat::from_str(&f)?,
+		None => TxExportFormat::Json,
+	};
+	let entries = api.export_txs(None)?;
+	Ok(Reply::Text(format.mime(), format.serialize(&entries)?))
+}
+
+fn outputs<W, C, K>(state: &State, query: &Query, _body: &[u8]) -> Result<Reply, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let api = Owner::<W, C, K>::borrow_from(state);
+	let include_spent = query.get("include_spent")?.unwrap_or(false);
+	let refresh = query.get("refresh")?.unwrap_or(false);
+	let tx_id = query.get("tx_id")?;
+	let page = Some(query.page()?);
+	let (validated, _, outputs) = api.retrieve_outputs(include_spent, refresh, tx_id, page)?;
+	let res = json!({ "validated": validated, "outputs": outputs });
+	Ok(Reply::Json(res))
+}
+
+fn estimate<W, C, K>(state: &State, _query: &Query, body: &[u8]) -> Result<Reply, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let api = Owner::<W, C, K>::borrow_from(state);
+	let args: EstimateFeeArgs = serde_json::from_slice(body)?;
+	Ok(Reply::Json(json!(api.estimate_fee(args)?)))
+}
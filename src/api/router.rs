@@ -14,30 +14,64 @@
 
 use easy_jsonrpc_mw::{Handler, MaybeReply};
 use failure::Error;
-use futures::future;
-use futures::{Future, Stream};
-use gotham::handler::{HandlerFuture, IntoHandlerError};
-use gotham::helpers::http::response::create_response;
+use futures::{future, stream};
+use futures::{Future, Sink, Stream};
+use gotham::handler::{Handler as _, HandlerFuture, IntoHandlerError, NewHandler};
+use gotham::helpers::http::response::{create_empty_response, create_response};
 use gotham::middleware::{Middleware, NewMiddleware};
 use gotham::pipeline::new_pipeline;
 use gotham::pipeline::single::single_pipeline;
 use gotham::router::builder::*;
 use gotham::router::Router;
 use gotham::state::{FromState, State};
+use gotham_derive::{StateData, StaticResponseExtender};
+use hyper::header::{
+	HeaderValue, AUTHORIZATION, CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE,
+};
 use hyper::{Body, Chunk, HeaderMap, Method, Response, StatusCode, Uri, Version};
-use log::trace;
+use log::{debug, trace, warn};
 use mime::Mime;
+use ring::constant_time::verify_slices_are_equal;
+use serde::Deserialize;
 use serde_json::{json, Value};
-use std::panic::RefUnwindSafe;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::panic::{AssertUnwindSafe, RefUnwindSafe};
+use std::str::FromStr;
+use tokio::codec::Decoder;
+use tokio::io::{copy, shutdown, AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+use tokio_rustls::rustls::{NoClientAuth, PrivateKey, ServerConfig};
+use tokio_rustls::ServerConfigExt;
 
-use super::rpc::{ForeignRpc, OwnerRpc};
+use super::rest::{self, rest_handler};
+use super::rpc::{ForeignRpc, OwnerReadRpc, OwnerRpc};
+use super::ws;
 use crate::api::auth::BasicAuthMiddleware;
+use crate::api::cors::{preflight_handler, CorsMiddleware, CorsPolicy};
 use crate::api::error::ApiError;
-use crate::common::Keychain;
-use crate::wallet::api::{Foreign, Owner};
-use crate::wallet::types::{Arc, Mutex, NodeClient, WalletBackend};
+use crate::api::limits::{ApiLimits, BodySizeMiddleware, RateLimitMiddleware};
+use crate::common::metrics::SharedMetrics;
+use crate::common::{ErrorKind, Keychain};
+use crate::wallet::api::{Foreign, Owner, Session};
+use crate::wallet::types::{
+	Arc, EstimateFeeArgs, EventFilter, Mutex, NodeClient, Page, TxExportFormat, WalletBackend,
+	WalletEvent,
+};
 use crate::wallet::Container;
 
+/// Most entries a listing endpoint returns in one response
+pub const MAX_PAGE_SIZE: usize = 1000;
+/// WebSocket streaming wallet events, on the owner API
+const EVENTS_PATH: &str = "/v1/wallet/owner/ws";
+/// Header naming the session of an owner API caller
+pub const SESSION_HEADER: &str = "X-Wallet713-Session";
+/// Most owner API sessions kept at once
+const MAX_SESSIONS: usize = 10_000;
+
 pub struct ForeignApiMiddleware<W, C, K>
 where
 	W: WalletBackend<C, K>,
@@ -99,13 +133,16 @@ where
 	}
 }
 
+/// Owner API callers each have a session with their own active account,
+/// named by this header or else by their address
 pub struct OwnerApiMiddleware<W, C, K>
 where
 	W: WalletBackend<C, K>,
 	C: NodeClient,
 	K: Keychain,
 {
-	api: Owner<W, C, K>,
+	container: Arc<Mutex<Container<W, C, K>>>,
+	sessions: Arc<Mutex<HashMap<String, Session>>>,
 }
 
 impl<W, C, K> RefUnwindSafe for OwnerApiMiddleware<W, C, K>
@@ -124,9 +161,32 @@ where
 {
 	fn new(container: Arc<Mutex<Container<W, C, K>>>) -> Self {
 		Self {
-			api: Owner::new(container),
+			container,
+			sessions: Arc::new(Mutex::new(HashMap::new())),
 		}
 	}
+
+	fn session(&self, state: &State) -> Session {
+		let key = match HeaderMap::borrow_from(state)
+			.get(SESSION_HEADER)
+			.and_then(|v| v.to_str().ok())
+		{
+			Some(id) => format!("id:{}", id),
+			None => match client_addr(state) {
+				Some(addr) => format!("addr:{}", addr.ip()),
+				None => String::new(),
+			},
+		};
+		let mut sessions = self.sessions.lock();
+		if sessions.len() >= MAX_SESSIONS && !sessions.contains_key(&key) {
+			// Sessions on the account of the wallet are the same as new ones
+			sessions.retain(|_, s| s.lock().is_some());
+		}
+		sessions
+			.entry(key)
+			.or_insert_with(|| Arc::new(Mutex::new(None)))
+			.clone()
+	}
 }
 
 impl<W, C, K> Middleware for OwnerApiMiddleware<W, C, K>
@@ -139,7 +199,8 @@ where
 	where
 		Chain: FnOnce(State) -> Box<HandlerFuture>,
 	{
-		state.put(self.api);
+		let session = self.session(&state);
+		state.put(Owner::in_session(self.container, session));
 		chain(state)
 	}
 }
@@ -155,7 +216,8 @@ where
 	/// Clones the current middleware to a new instance.
 	fn new_middleware(&self) -> std::io::Result<Self::Instance> {
 		Ok(Self {
-			api: self.api.clone(),
+			container: self.container.clone(),
+			sessions: self.sessions.clone(),
 		})
 	}
 }
@@ -163,14 +225,20 @@ where
 pub fn build_foreign_api_router<W, C, K>(
 	container: Arc<Mutex<Container<W, C, K>>>,
 	foreign_api_secret: Option<String>,
+	limits: &ApiLimits,
+	metrics: &SharedMetrics,
 ) -> Router
 where
 	W: WalletBackend<C, K>,
 	C: NodeClient,
 	K: Keychain,
 {
+	let rate_limit = RateLimitMiddleware::new("foreign", limits, metrics.clone());
+	let body_size = BodySizeMiddleware::new("foreign", limits, metrics.clone());
 	let (chain, pipelines) = single_pipeline(
 		new_pipeline()
+			.add(rate_limit)
+			.add(body_size)
 			.add(BasicAuthMiddleware::new(foreign_api_secret))
 			.add(ForeignApiMiddleware::new(container))
 			.build(),
@@ -180,9 +248,32 @@ where
 		route
 			.request(vec![Method::POST], "/v2/foreign")
 			.to(foreign_api_handler::<W, C, K>);
+		route
+			.request(vec![Method::GET], "/v1/wallet/foreign/pubkey")
+			.to(foreign_pubkey_handler::<W, C, K>);
 	})
 }
 
+/// Public key senders seal slates to, see `receive_tx_sealed`
+fn foreign_pubkey_handler<W, C, K>(state: State) -> Box<HandlerFuture>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let api = Foreign::<W, C, K>::borrow_from(&state);
+	let future = match api.http_public_key() {
+		Ok(public_key) => {
+			let body = json!({ "public_key": public_key }).to_string();
+			let res = trace_create_response(&state, StatusCode::OK, mime::APPLICATION_JSON, body);
+			future::ok((state, res))
+		}
+		Err(e) => future::err((state, ApiError::new(e).into_handler_error())),
+	};
+
+	Box::new(future)
+}
+
 fn foreign_api_handler<W, C, K>(mut state: State) -> Box<HandlerFuture>
 where
 	W: WalletBackend<C, K>,
@@ -234,23 +325,69 @@ where
 pub fn build_owner_api_router<W, C, K>(
 	container: Arc<Mutex<Container<W, C, K>>>,
 	owner_api_secret: Option<String>,
+	limits: &ApiLimits,
+	metrics: &SharedMetrics,
+	cors: &CorsPolicy,
+	base_path: &str,
 ) -> Router
 where
 	W: WalletBackend<C, K>,
 	C: NodeClient,
 	K: Keychain,
 {
+	let rate_limit = RateLimitMiddleware::new("owner", limits, metrics.clone());
+	let body_size = BodySizeMiddleware::new("owner", limits, metrics.clone());
+	let path = |p: &str| format!("{}{}", base_path, p);
+	// Browsers can't set headers on a WebSocket, the stream checks a token
+	let events_path = path(EVENTS_PATH);
 	let (chain, pipelines) = single_pipeline(
 		new_pipeline()
-			.add(BasicAuthMiddleware::new(owner_api_secret))
+			.add(CorsMiddleware::new(cors))
+			.add(rate_limit)
+			.add(body_size)
+			.add(BasicAuthMiddleware::new(owner_api_secret).except(&events_path))
 			.add(OwnerApiMiddleware::new(container))
 			.build(),
 	);
 
 	build_router(chain, pipelines, |route| {
+		if cors.is_enabled() {
+			route.options(&path("/*")).to(preflight_handler);
+		}
 		route
-			.request(vec![Method::POST], "/v2/owner")
+			.request(vec![Method::POST], &path("/v2/owner"))
 			.to(owner_api_handler::<W, C, K>);
+		route
+			.request(vec![Method::POST], &path("/v1/wallet/owner/estimate"))
+			.to(estimate_handler::<W, C, K>);
+		route
+			.request(vec![Method::GET], &path("/v1/wallet/owner/txs/export"))
+			.with_query_string_extractor::<TxExportQuery>()
+			.to(txs_export_handler::<W, C, K>);
+		route
+			.request(vec![Method::GET], &path("/v1/wallet/owner/retrieve_txs"))
+			.with_query_string_extractor::<RetrieveQuery>()
+			.to(retrieve_txs_handler::<W, C, K>);
+		route
+			.request(
+				vec![Method::GET],
+				&path("/v1/wallet/owner/retrieve_outputs"),
+			)
+			.with_query_string_extractor::<RetrieveQuery>()
+			.to(retrieve_outputs_handler::<W, C, K>);
+		route
+			.request(vec![Method::GET], &events_path)
+			.with_query_string_extractor::<EventsQuery>()
+			.to(events_handler::<W, C, K>);
+		for r in rest::routes::<W, C, K>() {
+			let (handler, params) = (r.handler, r.params);
+			route
+				.request(vec![r.method], &path(r.path))
+				.to(move |state| rest_handler(state, handler, params));
+		}
+		route
+			.request(vec![Method::GET], &path("/metrics"))
+			.to(metrics_handler::<W, C, K>);
 	})
 }
 
@@ -302,6 +439,438 @@ where
 	))
 }
 
+/// Owner API without any endpoint that can spend or change the wallet
+pub fn build_owner_read_api_router<W, C, K>(
+	container: Arc<Mutex<Container<W, C, K>>>,
+	owner_read_api_secret: Option<String>,
+	limits: &ApiLimits,
+	metrics: &SharedMetrics,
+	cors: &CorsPolicy,
+	base_path: &str,
+) -> Router
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let rate_limit = RateLimitMiddleware::new("owner_read", limits, metrics.clone());
+	let body_size = BodySizeMiddleware::new("owner_read", limits, metrics.clone());
+	let (chain, pipelines) = single_pipeline(
+		new_pipeline()
+			.add(CorsMiddleware::new(cors))
+			.add(rate_limit)
+			.add(body_size)
+			.add(BasicAuthMiddleware::new(owner_read_api_secret))
+			.add(OwnerApiMiddleware::new(container))
+			.build(),
+	);
+
+	let path = |p: &str| format!("{}{}", base_path, p);
+	build_router(chain, pipelines, |route| {
+		if cors.is_enabled() {
+			route.options(&path("/*")).to(preflight_handler);
+		}
+		route
+			.request(vec![Method::POST], &path("/v2/owner"))
+			.to(owner_read_api_handler::<W, C, K>);
+		route
+			.request(vec![Method::GET], &path("/v1/wallet/owner/txs/export"))
+			.with_query_string_extractor::<TxExportQuery>()
+			.to(txs_export_handler::<W, C, K>);
+		route
+			.request(vec![Method::GET], &path("/v1/wallet/owner/retrieve_txs"))
+			.with_query_string_extractor::<RetrieveQuery>()
+			.to(retrieve_txs_handler::<W, C, K>);
+		route
+			.request(
+				vec![Method::GET],
+				&path("/v1/wallet/owner/retrieve_outputs"),
+			)
+			.with_query_string_extractor::<RetrieveQuery>()
+			.to(retrieve_outputs_handler::<W, C, K>);
+		for r in rest::routes::<W, C, K>() {
+			let (handler, params) = (r.handler, r.params);
+			route
+				.request(vec![r.method], &path(r.path))
+				.to(move |state| rest_handler(state, handler, params));
+		}
+	})
+}
+
+fn owner_read_api_handler<W, C, K>(mut state: State) -> Box<HandlerFuture>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let future = Body::take_from(&mut state)
+		.concat2()
+		.then(|body| match body {
+			Ok(body) => match owner_read_api_handler_inner::<W, C, K>(&state, &body) {
+				Ok(res) => future::ok((state, res)),
+				Err(e) => future::err((state, ApiError::new(e).into_handler_error())),
+			},
+			Err(e) => future::err((state, e.into_handler_error())),
+		});
+
+	Box::new(future)
+}
+
+fn owner_read_api_handler_inner<W, C, K>(
+	state: &State,
+	body: &Chunk,
+) -> Result<Response<Body>, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	trace_state_and_body(state, body);
+
+	let val: Value = serde_json::from_reader(&body.to_vec()[..])?;
+	let api = Owner::<W, C, K>::borrow_from(&state);
+
+	let owner_read_api = api as &dyn OwnerReadRpc;
+	let res = match owner_read_api.handle_request(val) {
+		MaybeReply::Reply(r) => r,
+		MaybeReply::DontReply => json!([]),
+	};
+
+	Ok(trace_create_response(
+		state,
+		StatusCode::OK,
+		mime::APPLICATION_JSON,
+		res.to_string(),
+	))
+}
+
+/// Query of the event stream: the token, the last event a resuming client
+/// saw and comma separated event names and accounts to stream
+#[derive(Deserialize, StateData, StaticResponseExtender)]
+struct EventsQuery {
+	token: Option<String>,
+	since: Option<u64>,
+	events: Option<String>,
+	accounts: Option<String>,
+}
+
+/// Upgrade the connection to a WebSocket that receives the wallet events
+/// the client asked for as JSON text messages, numbered by `seq`, until it
+/// closes the connection or goes away. Pings are answered
+fn events_handler<W, C, K>(mut state: State) -> Box<HandlerFuture>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let query = EventsQuery::borrow_from(&state);
+	let config = Owner::<W, C, K>::borrow_from(&state).config();
+	let token = match config.owner_api_events_token() {
+		Some(token) => token,
+		None => {
+			let msg = "Set owner_api_events_token or owner_api_secret to stream events";
+			return refuse(state, StatusCode::FORBIDDEN, msg);
+		}
+	};
+	let bearer = HeaderMap::borrow_from(&state)
+		.get(AUTHORIZATION)
+		.and_then(|v| v.to_str().ok())
+		.and_then(|v| {
+			let mut parts = v.trim().splitn(2, ' ');
+			match (parts.next(), parts.next()) {
+				(Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("bearer") => {
+					Some(token.trim())
+				}
+				_ => None,
+			}
+		});
+	let given = query.token.as_ref().map(|t| t.as_str()).or(bearer);
+	let valid = given
+		.map(|t| verify_slices_are_equal(token.as_bytes(), t.as_bytes()).is_ok())
+		.unwrap_or(false);
+	if !valid {
+		return refuse(state, StatusCode::UNAUTHORIZED, "Invalid or missing token");
+	}
+
+	let list = |v: &Option<String>| {
+		v.as_ref().map(|v| {
+			v.split(',')
+				.map(|s| s.trim().to_owned())
+				.filter(|s| !s.is_empty())
+				.collect::<Vec<_>>()
+		})
+	};
+	let filter = EventFilter {
+		events: list(&query.events),
+		accounts: list(&query.accounts),
+	};
+	let unknown = filter
+		.events
+		.iter()
+		.flatten()
+		.find(|e| !WalletEvent::NAMES.contains(&e.as_str()));
+	if let Some(unknown) = unknown {
+		let msg = format!("Unknown event '{}'", unknown);
+		return refuse(state, StatusCode::BAD_REQUEST, &msg);
+	}
+	let since = query.since;
+
+	let headers = HeaderMap::borrow_from(&state);
+	let is_websocket = headers
+		.get(UPGRADE)
+		.and_then(|v| v.to_str().ok())
+		.map(|v| v.eq_ignore_ascii_case("websocket"))
+		.unwrap_or(false);
+	let key = headers
+		.get(SEC_WEBSOCKET_KEY)
+		.and_then(|v| v.to_str().ok())
+		.map(ws::accept_key);
+	let accept = match (is_websocket, key) {
+		(true, Some(accept)) => accept,
+		_ => {
+			let msg = "Expected a WebSocket upgrade request";
+			return refuse(state, StatusCode::BAD_REQUEST, msg);
+		}
+	};
+
+	let events = Owner::<W, C, K>::borrow_from(&state).subscribe_events(since);
+	// Tells a resuming client to catch up through the API instead
+	let missed = if events.missed_events() {
+		Some(json!({ "event": "events_missed", "since": since }).to_string())
+	} else {
+		None
+	};
+	let pushed = stream::iter_ok(missed.map(ws::Message::Text)).chain(
+		events.filter(move |e| filter.matches(&e.event)).map(|e| {
+			let mut message = json!(e.event);
+			message["seq"] = json!(e.seq);
+			ws::Message::Text(message.to_string())
+		}),
+	);
+	let connection = Body::take_from(&mut state)
+		.on_upgrade()
+		.map_err(|e| warn!("WebSocket upgrade failed: {}", e))
+		.and_then(|io| {
+			let (sink, incoming) = ws::Codec.framed(io).split();
+			let incoming = incoming.map_err(|e| debug!("WebSocket client gone: {}", e));
+			sink.sink_map_err(|e| debug!("WebSocket client gone: {}", e))
+				.send_all(ws::Outgoing::new(incoming, pushed))
+				.map(|_| ())
+		});
+	tokio::spawn(connection);
+
+	let mut res = create_empty_response(&state, StatusCode::SWITCHING_PROTOCOLS);
+	{
+		let headers = res.headers_mut();
+		headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+		headers.insert(CONNECTION, HeaderValue::from_static("Upgrade"));
+		if let Ok(accept) = HeaderValue::from_str(&accept) {
+			headers.insert(SEC_WEBSOCKET_ACCEPT, accept);
+		}
+	}
+	Box::new(future::ok((state, res)))
+}
+
+/// Plain text answer to a request that can't be served
+fn refuse(state: State, status: StatusCode, msg: &str) -> Box<HandlerFuture> {
+	let res = create_response(&state, status, mime::TEXT_PLAIN, msg.to_owned());
+	Box::new(future::ok((state, res)))
+}
+
+fn estimate_handler<W, C, K>(mut state: State) -> Box<HandlerFuture>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let future = Body::take_from(&mut state)
+		.concat2()
+		.then(|body| match body {
+			Ok(body) => match estimate_handler_inner::<W, C, K>(&state, &body) {
+				Ok(res) => future::ok((state, res)),
+				Err(e) => future::err((state, ApiError::new(e).into_handler_error())),
+			},
+			Err(e) => future::err((state, e.into_handler_error())),
+		});
+
+	Box::new(future)
+}
+
+fn estimate_handler_inner<W, C, K>(state: &State, body: &Chunk) -> Result<Response<Body>, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	trace_state_and_body(state, body);
+
+	let args: EstimateFeeArgs = serde_json::from_reader(&body.to_vec()[..])?;
+	let api = Owner::<W, C, K>::borrow_from(&state);
+	let estimates = api.estimate_fee(args)?;
+
+	Ok(trace_create_response(
+		state,
+		StatusCode::OK,
+		mime::APPLICATION_JSON,
+		serde_json::to_string(&estimates)?,
+	))
+}
+
+/// Prometheus scrape endpoint
+fn metrics_handler<W, C, K>(state: State) -> Box<HandlerFuture>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let api = Owner::<W, C, K>::borrow_from(&state);
+	let res = trace_create_response(
+		&state,
+		StatusCode::OK,
+		mime::TEXT_PLAIN,
+		api.render_metrics(),
+	);
+
+	Box::new(future::ok((state, res)))
+}
+
+#[derive(Deserialize, StateData, StaticResponseExtender)]
+struct TxExportQuery {
+	format: Option<String>,
+}
+
+fn txs_export_handler<W, C, K>(state: State) -> Box<HandlerFuture>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let future = match txs_export_handler_inner::<W, C, K>(&state) {
+		Ok(res) => future::ok((state, res)),
+		Err(e) => future::err((state, ApiError::new(e).into_handler_error())),
+	};
+
+	Box::new(future)
+}
+
+fn txs_export_handler_inner<W, C, K>(state: &State) -> Result<Response<Body>, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let query = TxExportQuery::borrow_from(&state);
+	let format = match &query.format {
+		Some(f) => TxExportFormat::from_str(f)?,
+		None => TxExportFormat::Json,
+	};
+	let api = Owner::<W, C, K>::borrow_from(&state);
+	let entries = api.export_txs(None)?;
+
+	Ok(trace_create_response(
+		state,
+		StatusCode::OK,
+		format.mime(),
+		format.serialize(&entries)?,
+	))
+}
+
+/// Query of the paginated listing endpoints. Without a limit at most
+/// `MAX_PAGE_SIZE` entries are returned
+#[derive(Deserialize, StateData, StaticResponseExtender)]
+struct RetrieveQuery {
+	refresh: Option<bool>,
+	tx_id: Option<u32>,
+	include_spent: Option<bool>,
+	offset: Option<usize>,
+	limit: Option<usize>,
+}
+
+impl RetrieveQuery {
+	fn page(&self) -> Page {
+		let limit = self.limit.unwrap_or(MAX_PAGE_SIZE).min(MAX_PAGE_SIZE);
+		Page::new(self.offset.unwrap_or(0), Some(limit))
+	}
+}
+
+fn retrieve_txs_handler<W, C, K>(state: State) -> Box<HandlerFuture>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let future = match retrieve_txs_handler_inner::<W, C, K>(&state) {
+		Ok(res) => future::ok((state, res)),
+		Err(e) => future::err((state, ApiError::new(e).into_handler_error())),
+	};
+
+	Box::new(future)
+}
+
+fn retrieve_txs_handler_inner<W, C, K>(state: &State) -> Result<Response<Body>, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let query = RetrieveQuery::borrow_from(&state);
+	let api = Owner::<W, C, K>::borrow_from(&state);
+	let refresh = query.refresh.unwrap_or(false);
+	let (validated, _, txs, _, _) = api.retrieve_txs(
+		refresh,
+		false,
+		false,
+		query.tx_id,
+		None,
+		None,
+		Some(query.page()),
+	)?;
+
+	Ok(trace_create_response(
+		state,
+		StatusCode::OK,
+		mime::APPLICATION_JSON,
+		serde_json::to_string(&(validated, txs))?,
+	))
+}
+
+fn retrieve_outputs_handler<W, C, K>(state: State) -> Box<HandlerFuture>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let future = match retrieve_outputs_handler_inner::<W, C, K>(&state) {
+		Ok(res) => future::ok((state, res)),
+		Err(e) => future::err((state, ApiError::new(e).into_handler_error())),
+	};
+
+	Box::new(future)
+}
+
+fn retrieve_outputs_handler_inner<W, C, K>(state: &State) -> Result<Response<Body>, Error>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let query = RetrieveQuery::borrow_from(&state);
+	let api = Owner::<W, C, K>::borrow_from(&state);
+	let (validated, _, outputs) = api.retrieve_outputs(
+		query.include_spent.unwrap_or(false),
+		query.refresh.unwrap_or(false),
+		query.tx_id,
+		Some(query.page()),
+	)?;
+
+	Ok(trace_create_response(
+		state,
+		StatusCode::OK,
+		mime::APPLICATION_JSON,
+		serde_json::to_string(&(validated, outputs))?,
+	))
+}
+
 fn trace_state_and_body(state: &State, body: &Chunk) {
 	let method = Method::borrow_from(state);
 	let uri = Uri::borrow_from(state);
@@ -333,3 +902,208 @@ fn trace_create_response(
 	);
 	create_response(state, status, mime, body)
 }
+
+/// Server side of TLS connections
+pub type TlsAcceptor = Arc<ServerConfig>;
+
+fn invalid_tls(msg: String) -> ErrorKind {
+	ErrorKind::InvalidTlsConfig(msg)
+}
+
+fn load_key(key_file: &str) -> Result<PrivateKey, Error> {
+	let open = || {
+		File::open(key_file)
+			.map(BufReader::new)
+			.map_err(|e| invalid_tls(format!("unable to open '{}': {}", key_file, e)))
+	};
+	let mut keys = pkcs8_private_keys(&mut open()?).unwrap_or(vec![]);
+	if keys.is_empty() {
+		keys = rsa_private_keys(&mut open()?).unwrap_or(vec![]);
+	}
+	if keys.is_empty() {
+		Err(invalid_tls(format!(
+			"no private key found in '{}'",
+			key_file
+		)))?;
+	}
+	Ok(keys.remove(0))
+}
+
+/// Build a TLS acceptor from a PEM certificate chain and private key
+pub fn tls_acceptor(cert_file: &str, key_file: &str) -> Result<TlsAcceptor, Error> {
+	let mut reader = File::open(cert_file)
+		.map(BufReader::new)
+		.map_err(|e| invalid_tls(format!("unable to open '{}': {}", cert_file, e)))?;
+	let chain = certs(&mut reader)
+		.map_err(|_| invalid_tls(format!("unable to read certificates from '{}'", cert_file)))?;
+	if chain.is_empty() {
+		Err(invalid_tls(format!(
+			"no certificate found in '{}'",
+			cert_file
+		)))?;
+	}
+	let key = load_key(key_file)?;
+
+	let mut config = ServerConfig::new(NoClientAuth::new());
+	config
+		.set_single_cert(chain, key)
+		.map_err(|e| invalid_tls(format!("{}", e)))?;
+	Ok(Arc::new(config))
+}
+
+/// Address of the client of a request, or of the TLS connection it was
+/// relayed from
+pub fn client_addr(state: &State) -> Option<SocketAddr> {
+	RelayedClient::try_borrow_from(state)
+		.map(|c| c.0)
+		.or_else(|| gotham::state::client_addr(state))
+}
+
+/// Address of the client of the TLS connection a request was relayed from
+#[derive(StateData)]
+struct RelayedClient(SocketAddr);
+
+/// Clients of the relayed TLS connections, by the local address of the
+/// connection to gotham
+type RelayedClients = Arc<Mutex<HashMap<SocketAddr, SocketAddr>>>;
+
+/// Serve `new_handler` over HTTPS. gotham only serves the TCP listeners
+/// it binds itself, so it listens on a loopback port the decrypted
+/// connections are relayed to. Failed handshakes only drop the offending
+/// connection
+pub fn init_tls_server<NH>(
+	address: &str,
+	new_handler: NH,
+	acceptor: TlsAcceptor,
+) -> Result<impl Future<Item = (), Error = ()>, Error>
+where
+	NH: NewHandler + 'static,
+{
+	let addr: SocketAddr = address
+		.to_socket_addrs()?
+		.next()
+		.ok_or(ErrorKind::HttpAddressParsingError(address.to_owned()))?;
+	let listener = TcpListener::bind(&addr)?;
+	let plain_addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+	let clients: RelayedClients = Arc::new(Mutex::new(HashMap::new()));
+
+	let handler_clients = AssertUnwindSafe(clients.clone());
+	let new_handler = move || -> std::io::Result<_> {
+		let handler = new_handler.new_handler()?;
+		let clients = handler_clients.clone();
+		Ok(move |mut state: State| {
+			let client =
+				gotham::state::client_addr(&state).and_then(|a| clients.lock().get(&a).cloned());
+			if let Some(client) = client {
+				state.put(RelayedClient(client));
+			}
+			handler.handle(state)
+		})
+	};
+	let server = gotham::init_server(plain_addr, new_handler);
+
+	let relays = listener
+		.incoming()
+		.map_err(|e| warn!("TLS listener failed: {}", e))
+		.for_each(move |socket| {
+			let client = socket.peer_addr().ok();
+			let clients = clients.clone();
+			let connection = acceptor
+				.accept_async(socket)
+				.map_err(|e| warn!("TLS handshake failed: {}", e))
+				.and_then(move |tls| {
+					TcpStream::connect(&plain_addr)
+						.map_err(|e| warn!("unable to relay a TLS connection: {}", e))
+						.and_then(move |plain| {
+							let local = plain.local_addr().ok();
+							if let (Some(local), Some(client)) = (local, client) {
+								clients.lock().insert(local, client);
+							}
+							relay(tls, plain).then(move |_| {
+								if let Some(local) = local {
+									clients.lock().remove(&local);
+								}
+								Ok(())
+							})
+						})
+				});
+			tokio::spawn(connection);
+			Ok(())
+		});
+	Ok(server.join(relays).map(|_| ()))
+}
+
+/// Copy the bytes of each stream to the other until both are closed
+fn relay<A, B>(a: A, b: B) -> impl Future<Item = (), Error = ()>
+where
+	A: AsyncRead + AsyncWrite,
+	B: AsyncRead + AsyncWrite,
+{
+	let (a_read, a_write) = a.split();
+	let (b_read, b_write) = b.split();
+	let a_to_b = copy(a_read, b_write).and_then(|(_, _, w)| shutdown(w));
+	let b_to_a = copy(b_read, a_write).and_then(|(_, _, w)| shutdown(w));
+	a_to_b
+		.join(b_to_a)
+		.map(|_| ())
+		.map_err(|e| trace!("TLS relay closed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::api::limits::ApiLimits;
+	use crate::common::metrics::Metrics;
+	use crate::wallet::testing::{create_wallet, LoopbackNetwork, MockChain};
+	use gotham::test::TestServer;
+
+	fn call(server: &TestServer, session: &str, method: &str, params: Value) -> Value {
+		let request = json!({
+			"jsonrpc": "2.0",
+			"id": 1,
+			"method": method,
+			"params": params,
+		});
+		let response = server
+			.client()
+			.post(
+				"http://localhost/v2/owner",
+				request.to_string(),
+				mime::APPLICATION_JSON,
+			)
+			.with_header(SESSION_HEADER, HeaderValue::from_str(session).unwrap())
+			.perform()
+			.unwrap();
+		assert_eq!(response.status(), StatusCode::OK);
+		serde_json::from_str(&response.read_utf8_body().unwrap()).unwrap()
+	}
+
+	#[test]
+	fn owner_api_callers_have_their_own_account() {
+		let chain = MockChain::new();
+		let network = LoopbackNetwork::new();
+		let container = create_wallet("wendy", &chain, &network).unwrap();
+		let limits = ApiLimits {
+			rate: None,
+			max_body_size: 1 << 20,
+		};
+		let router = build_owner_api_router(
+			container,
+			None,
+			&limits,
+			&Metrics::new(),
+			&CorsPolicy::default(),
+			"",
+		);
+		let server = TestServer::new(router).unwrap();
+
+		call(&server, "first", "create_account_path", json!(["savings"]));
+		let reply = call(&server, "first", "set_active_account", json!(["savings"]));
+		assert!(reply["result"].get("Ok").is_some(), "{}", reply);
+
+		let reply = call(&server, "first", "active_account", json!([]));
+		assert_eq!(reply["result"]["Ok"], "savings");
+		let reply = call(&server, "second", "active_account", json!([]));
+		assert_eq!(reply["result"]["Ok"], "default");
+	}
+}
@@ -0,0 +1,192 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Conformance of the foreign API in strict mode with grin-wallet's, with
+//! JSON-RPC requests going through the same handler as the HTTP listener
+
+use super::ForeignRpc;
+use crate::common::{Arc, Mutex};
+use crate::wallet::api::{Foreign, Owner};
+use crate::wallet::testing::{create_wallet, LoopbackNetwork, MockChain, TestContainer};
+use crate::wallet::types::{Amount, InitTxArgs, Slate, SlateVersion, VersionedSlate};
+use easy_jsonrpc_mw::{Handler, MaybeReply};
+use serde_json::{json, Value};
+
+/// Fields of a V2 slate as sent by grin-wallet
+const V2_SLATE_FIELDS: [&str; 9] = [
+	"version_info",
+	"num_participants",
+	"id",
+	"tx",
+	"amount",
+	"fee",
+	"height",
+	"lock_height",
+	"participant_data",
+];
+
+type Wallet = Arc<Mutex<TestContainer>>;
+
+/// A funded sender and a receiver with the foreign API in strict mode
+fn wallets(sender: &str, receiver: &str) -> (MockChain, Wallet, Wallet) {
+	let chain = MockChain::new();
+	let network = LoopbackNetwork::new();
+	let sender = create_wallet(sender, &chain, &network).unwrap();
+	let receiver = create_wallet(receiver, &chain, &network).unwrap();
+	receiver.lock().config.foreign_api_strict = Some(true);
+	chain.mine_to(&Foreign::new(sender.clone())).unwrap();
+	chain.mine(3);
+	(chain, sender, receiver)
+}
+
+fn sender_slate(sender: &Wallet, payment_proof_recipient_address: Option<String>) -> Slate {
+	let sender = Owner::new(sender.clone());
+	let args = InitTxArgs {
+		amount: Amount::from_nano(1_000_000),
+		minimum_confirmations: 1,
+		payment_proof_recipient_address,
+		..Default::default()
+	};
+	let slate = sender.init_send_tx(args).unwrap();
+	sender.tx_lock_outputs(&slate, 0, None).unwrap();
+	slate
+}
+
+fn to_json(slate: &Slate) -> Value {
+	serde_json::to_value(VersionedSlate::into_version(
+		slate.clone(),
+		SlateVersion::V2,
+	))
+	.unwrap()
+}
+
+/// Reply of the foreign API of `container` to `request`
+fn handle(container: &Wallet, request: Value) -> Value {
+	let foreign = Foreign::new(container.clone());
+	match (&foreign as &dyn ForeignRpc).handle_request(request) {
+		MaybeReply::Reply(reply) => reply,
+		MaybeReply::DontReply => panic!("no reply"),
+	}
+}
+
+fn call(container: &Wallet, method: &str, params: Value) -> Value {
+	let request = json!({
+		"jsonrpc": "2.0",
+		"id": 1,
+		"method": method,
+		"params": params,
+	});
+	handle(container, request)
+}
+
+fn receive(container: &Wallet, slate: Value) -> Value {
+	call(container, "receive_tx", json!([slate, null, null]))
+}
+
+fn error_code(reply: &Value) -> i64 {
+	assert!(reply.get("result").is_none(), "unexpected result {}", reply);
+	reply["error"]["code"].as_i64().unwrap()
+}
+
+#[test]
+fn receives_valid_slates_like_grin_wallet() {
+	let (_chain, sender, receiver) = wallets("ivan", "judy");
+	let slate = sender_slate(&sender, None);
+
+	let reply = receive(&receiver, to_json(&slate));
+	assert_eq!(reply["jsonrpc"], "2.0");
+	assert_eq!(reply["id"], 1);
+	let response = &reply["result"]["Ok"];
+	let fields: Vec<&str> = response
+		.as_object()
+		.unwrap()
+		.keys()
+		.map(String::as_str)
+		.collect();
+	let mut expected = V2_SLATE_FIELDS.to_vec();
+	expected.sort();
+	assert_eq!(fields, expected);
+	assert_eq!(response["version_info"]["version"], 2);
+	assert_eq!(response["participant_data"].as_array().unwrap().len(), 2);
+
+	let response: VersionedSlate = serde_json::from_value(response.clone()).unwrap();
+	Owner::new(sender)
+		.finalize_tx(&response.into(), None)
+		.unwrap();
+}
+
+#[test]
+fn strict_mode_leaves_out_payment_proofs() {
+	let (_chain, sender, receiver) = wallets("kevin", "laura");
+	let address = Owner::new(receiver.clone()).grinbox_address().unwrap();
+
+	let slate = sender_slate(&sender, Some(address.to_string()));
+	let reply = receive(&receiver, to_json(&slate));
+	assert!(reply["result"]["Ok"].get("payment_proof").is_none());
+
+	receiver.lock().config.foreign_api_strict = Some(false);
+	let slate = sender_slate(&sender, Some(address.to_string()));
+	let reply = receive(&receiver, to_json(&slate));
+	assert!(reply["result"]["Ok"].get("payment_proof").is_some());
+}
+
+#[test]
+fn refuses_slates_of_other_versions() {
+	let (_chain, sender, receiver) = wallets("mallory", "niaj");
+
+	let mut slate = to_json(&sender_slate(&sender, None));
+	slate["version_info"]["version"] = json!(1);
+	slate["version_info"]["orig_version"] = json!(1);
+	let reply = receive(&receiver, slate);
+	assert!(reply["result"]["Err"]["GenericError"].is_string());
+
+	let mut slate = to_json(&sender_slate(&sender, None));
+	slate["version_info"]["block_header_version"] = json!(1);
+	let reply = receive(&receiver, slate);
+	assert!(reply["result"]["Err"]["GenericError"].is_string());
+}
+
+#[test]
+fn rejects_malformed_requests() {
+	let (_chain, sender, receiver) = wallets("olivia", "peggy");
+	let slate = to_json(&sender_slate(&sender, None));
+
+	// Method not found
+	let reply = call(&receiver, "receive_invoice", json!([slate, null, null]));
+	assert_eq!(error_code(&reply), -32601);
+
+	// Invalid params: not a slate, missing and extra parameters
+	let mut incomplete = slate.clone();
+	incomplete
+		.as_object_mut()
+		.unwrap()
+		.remove("participant_data");
+	for params in vec![
+		json!([{ "id": "not a slate" }, null, null]),
+		json!([incomplete, null, null]),
+		json!([slate.clone()]),
+		json!([slate.clone(), null, null, null]),
+	] {
+		let reply = call(&receiver, "receive_tx", params);
+		assert_eq!(error_code(&reply), -32602);
+	}
+
+	// Invalid request
+	let reply = handle(&receiver, json!({ "jsonrpc": "2.0", "id": 1, "method": 7 }));
+	assert_eq!(error_code(&reply), -32600);
+
+	// The slate is still accepted afterwards
+	let reply = receive(&receiver, slate);
+	assert!(reply["result"]["Ok"].is_object());
+}
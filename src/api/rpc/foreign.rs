@@ -14,12 +14,15 @@
 
 //! JSON-RPC Stub generation for the Foreign API
 
+use crate::common::metrics::SLATES_RECEIVED;
+use crate::common::sealed::SealedMessage;
 use crate::common::Keychain;
 use crate::wallet::api::types::VersionInfo;
 use crate::wallet::api::Foreign;
 use crate::wallet::types::{BlockFees, CbData, NodeClient, Slate, VersionedSlate, WalletBackend};
 use crate::wallet::ErrorKind;
 use easy_jsonrpc_mw;
+use failure::Error;
 
 /// Public definition used to generate Foreign jsonrpc api.
 /// * When running with defaults, the V2 api is available at
@@ -36,6 +39,12 @@ pub trait ForeignRpc {
 		dest_acct_name: Option<String>,
 		message: Option<String>,
 	) -> Result<VersionedSlate, ErrorKind>;
+	fn receive_tx_sealed(
+		&self,
+		slate: SealedMessage,
+		dest_acct_name: Option<String>,
+		message: Option<String>,
+	) -> Result<SealedMessage, ErrorKind>;
 	//	fn finalize_invoice_tx(&self, slate: &Slate) -> Result<Slate, ErrorKind>;
 }
 
@@ -46,18 +55,16 @@ where
 	K: Keychain,
 {
 	fn check_version(&self) -> Result<VersionInfo, ErrorKind> {
-		Foreign::check_version(self).map_err(|e| ErrorKind::GenericError(e.to_string()))
+		Foreign::check_version(self).map_err(|e| rpc_error(e, self.strict_compat()))
 		// TODO: use ErrorKind everywhere
 	}
 
 	fn build_coinbase(&self, block_fees: &BlockFees) -> Result<CbData, ErrorKind> {
-		Foreign::build_coinbase(self, block_fees)
-			.map_err(|e| ErrorKind::GenericError(e.to_string()))
+		Foreign::build_coinbase(self, block_fees).map_err(|e| rpc_error(e, self.strict_compat()))
 	}
 
 	fn verify_slate_messages(&self, slate: &Slate) -> Result<(), ErrorKind> {
-		Foreign::verify_slate_messages(self, slate)
-			.map_err(|e| ErrorKind::GenericError(e.to_string()))
+		Foreign::verify_slate_messages(self, slate).map_err(|e| rpc_error(e, self.strict_compat()))
 	}
 
 	fn receive_tx(
@@ -66,22 +73,176 @@ where
 		dest_acct_name: Option<String>,
 		message: Option<String>,
 	) -> Result<VersionedSlate, ErrorKind> {
+		let strict = self.strict_compat();
 		let version = slate.version();
 		let slate: Slate = slate.into();
-		let slate = Foreign::receive_tx(
+		let mut slate = Foreign::receive_tx(
 			self,
 			&slate,
 			dest_acct_name.as_ref().map(String::as_str),
 			Some("http".to_owned()),
 			message,
 		)
-		.map_err(|e| ErrorKind::GenericError(e.to_string()))?;
+		.map_err(|e| rpc_error(e, strict))?;
+		self.metrics()
+			.lock()
+			.inc(SLATES_RECEIVED, &[("adapter", "http")]);
 
+		// V2 slates of grin-wallet have no payment proof
+		if strict {
+			slate.payment_proof = None;
+		}
 		Ok(VersionedSlate::into_version(slate, version))
 	}
 
+	/// Receive a slate sealed to the key at `/v1/wallet/foreign/pubkey`,
+	/// sealing the reply to the sender
+	fn receive_tx_sealed(
+		&self,
+		slate: SealedMessage,
+		dest_acct_name: Option<String>,
+		message: Option<String>,
+	) -> Result<SealedMessage, ErrorKind> {
+		let strict = self.strict_compat();
+		let secret = self.http_key().map_err(|e| rpc_error(e, strict))?;
+		let (json, key) = slate.open(&secret).map_err(|e| rpc_error(e, strict))?;
+		let received = serde_json::from_str(&json).map_err(|_| ErrorKind::SlateDeser)?;
+		let reply = ForeignRpc::receive_tx(self, received, dest_acct_name, message)?;
+		let json = serde_json::to_string(&reply).map_err(|_| ErrorKind::Format)?;
+		slate.reply(&json, &key).map_err(|e| rpc_error(e, strict))
+	}
+
 	/*fn finalize_invoice_tx(&self, slate: &Slate) -> Result<Slate, ErrorKind> {
 		Foreign::finalize_invoice_tx(self, slate)
 			.map_err(|e| ErrorKind::GenericError(e.to_string()))
 	}*/
 }
+
+/// Error returned to the caller. In strict mode, error kinds that also
+/// exist in grin-wallet are passed through as is, all others are reported
+/// as a generic error the way grin-wallet does
+fn rpc_error(e: Error, strict: bool) -> ErrorKind {
+	if !strict {
+		return ErrorKind::GenericError(e.to_string());
+	}
+	match e.downcast::<ErrorKind>() {
+		Ok(kind) => match upstream_kind(&kind) {
+			true => kind,
+			false => ErrorKind::GenericError(kind.to_string()),
+		},
+		Err(e) => ErrorKind::GenericError(e.to_string()),
+	}
+}
+
+/// Whether grin-wallet has an error kind with the same name and payload
+fn upstream_kind(kind: &ErrorKind) -> bool {
+	match kind {
+		ErrorKind::NotEnoughFunds { .. }
+		| ErrorKind::Fee(_)
+		| ErrorKind::ClientCallback(_)
+		| ErrorKind::CallbackImpl(_)
+		| ErrorKind::Backend(_)
+		| ErrorKind::Node
+		| ErrorKind::WalletComms(_)
+		| ErrorKind::Hyper
+		| ErrorKind::Uri
+		| ErrorKind::Signature(_)
+		| ErrorKind::DuplicateTransactionId
+		| ErrorKind::TransactionDoesntExist(_)
+		| ErrorKind::TransactionNotCancellable(_)
+		| ErrorKind::TransactionAlreadyConfirmed
+		| ErrorKind::TransactionAlreadyReceived(_)
+		| ErrorKind::TransactionBuildingNotCompleted(_)
+		| ErrorKind::AccountLabelAlreadyExists(_)
+		| ErrorKind::UnknownAccountLabel(_)
+		| ErrorKind::SlateVersionParse
+		| ErrorKind::SlateDeser
+		| ErrorKind::SlateVersion(_)
+		| ErrorKind::GenericError(_) => true,
+		_ => false,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::common::x25519;
+	use crate::wallet::types::SlateVersion;
+	use serde_json::json;
+	use uuid::Uuid;
+
+	#[test]
+	fn sealed_slates_open_with_the_receiver_key() {
+		let secret = [7u8; 32];
+		let public_key = x25519::public_key(&secret);
+		let slate = VersionedSlate::into_version(Slate::blank(2, Uuid::new_v4()), SlateVersion::V2);
+		let json = serde_json::to_string(&slate).unwrap();
+
+		let (sealed, key) = SealedMessage::seal(&json, &public_key).unwrap();
+		assert!(sealed.open(&[8u8; 32]).is_err());
+		let (opened, reply_key) = sealed.open(&secret).unwrap();
+		assert_eq!(opened, json);
+		assert_eq!(reply_key, key);
+
+		let reply = sealed.reply("reply", &reply_key).unwrap();
+		assert_eq!(reply.open_reply(&key).unwrap(), "reply");
+		assert!(reply.open_reply(&[0u8; 32]).is_err());
+	}
+
+	/// Responses as produced by grin-wallet's foreign API
+	#[test]
+	fn check_version_matches_grin_wallet() {
+		let info = VersionInfo {
+			foreign_api_version: 2,
+			supported_slate_versions: vec![SlateVersion::V2],
+		};
+		let expected = json!({
+			"foreign_api_version": 2,
+			"supported_slate_versions": ["V2"],
+		});
+		assert_eq!(serde_json::to_value(&info).unwrap(), expected);
+	}
+
+	#[test]
+	fn strict_errors_match_grin_wallet() {
+		let e: Error = ErrorKind::NotEnoughFunds {
+			available: 1_000_000_000,
+			available_disp: "1.000000000".to_owned(),
+			needed: 2_000_000_000,
+			needed_disp: "2.000000000".to_owned(),
+		}
+		.into();
+		let expected = json!({
+			"NotEnoughFunds": {
+				"available": 1_000_000_000u64,
+				"available_disp": "1.000000000",
+				"needed": 2_000_000_000u64,
+				"needed_disp": "2.000000000",
+			}
+		});
+		assert_eq!(serde_json::to_value(&rpc_error(e, true)).unwrap(), expected);
+
+		let e: Error = ErrorKind::TransactionAlreadyReceived("abc".to_owned()).into();
+		let expected = json!({ "TransactionAlreadyReceived": "abc" });
+		assert_eq!(serde_json::to_value(&rpc_error(e, true)).unwrap(), expected);
+	}
+
+	#[test]
+	fn strict_errors_hide_wallet713_kinds() {
+		let e: Error = ErrorKind::NoForwardDestination.into();
+		let expected = json!({
+			"GenericError": "No foreign API configured to forward slates to"
+		});
+		assert_eq!(serde_json::to_value(&rpc_error(e, true)).unwrap(), expected);
+	}
+
+	#[test]
+	fn lenient_errors_are_generic() {
+		let e: Error = ErrorKind::TransactionAlreadyConfirmed.into();
+		let expected = json!({ "GenericError": "Transaction already confirmed" });
+		assert_eq!(
+			serde_json::to_value(&rpc_error(e, false)).unwrap(),
+			expected
+		);
+	}
+}
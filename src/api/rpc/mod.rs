@@ -12,8 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+#[cfg(test)]
+mod conformance;
 mod foreign;
 mod owner;
+mod owner_read;
 
 pub use self::foreign::ForeignRpc;
 pub use self::owner::OwnerRpc;
+pub use self::owner_read::OwnerReadRpc;
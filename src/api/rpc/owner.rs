@@ -14,14 +14,20 @@
 
 //! JSON-RPC Stub generation for the Owner API
 
+use crate::common::features::Feature;
+use crate::common::timing::StageTiming;
 use crate::common::Keychain;
 use crate::wallet::api::Owner;
 use crate::wallet::types::{
-	AcctPathMapping, Identifier, InitTxArgs, NodeClient, NodeHeightResult, OutputCommitMapping,
-	Slate, Transaction, TxLogEntry, WalletBackend, WalletInfo,
+	AcctPathMapping, DustAnalysis, EstimateFeeArgs, FeeEstimate, Identifier, InitTxArgs,
+	KernelReconciliation, NodeClient, NodeHeightResult, NodeStatus, NotificationRoute,
+	OutputCommitMapping, PaymentProof, SendPreview, Slate, Transaction, TxDetail, TxFilter,
+	TxLogEntry, VersionedSlate, WalletBackend, WalletInfo,
 };
 use crate::wallet::ErrorKind;
 use easy_jsonrpc_mw;
+use failure::Error;
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 /// Public definition used to generate Owner jsonrpc api.
@@ -32,7 +38,13 @@ use uuid::Uuid;
 pub trait OwnerRpc {
 	fn accounts(&self) -> Result<Vec<AcctPathMapping>, ErrorKind>;
 	fn create_account_path(&self, label: &String) -> Result<Identifier, ErrorKind>;
+	fn active_account(&self) -> Result<String, ErrorKind>;
 	fn set_active_account(&self, label: &String) -> Result<(), ErrorKind>;
+	fn set_account_notifications(
+		&self,
+		label: &String,
+		route: NotificationRoute,
+	) -> Result<(), ErrorKind>;
 	fn retrieve_outputs(
 		&self,
 		include_spent: bool,
@@ -45,23 +57,54 @@ pub trait OwnerRpc {
 		tx_id: Option<u32>,
 		tx_slate_id: Option<Uuid>,
 	) -> Result<(bool, Vec<TxLogEntry>), ErrorKind>;
+	fn retrieve_txs_filtered(
+		&self,
+		refresh_from_node: bool,
+		filter: TxFilter,
+	) -> Result<(bool, Vec<TxLogEntry>), ErrorKind>;
+	fn retrieve_tx_detail(&self, tx_id: u32) -> Result<TxDetail, ErrorKind>;
 	fn retrieve_summary_info(
 		&self,
 		refresh_from_node: bool,
 		minimum_confirmations: u64,
 	) -> Result<(bool, WalletInfo), ErrorKind>;
 	fn init_send_tx(&self, args: InitTxArgs) -> Result<Slate, ErrorKind>;
+	fn confirm_send_tx(&self, token: Uuid) -> Result<Slate, ErrorKind>;
+	fn estimate_fee(&self, args: EstimateFeeArgs) -> Result<Vec<FeeEstimate>, ErrorKind>;
+	fn analyze_dust(
+		&self,
+		threshold: Option<u64>,
+		minimum_confirmations: u64,
+	) -> Result<DustAnalysis, ErrorKind>;
+	fn preview_send_tx(&self, args: InitTxArgs) -> Result<SendPreview, ErrorKind>;
 	//	fn issue_invoice_tx(&self, args: IssueInvoiceTxArgs) -> Result<Slate, ErrorKind>;
 	//	fn process_invoice_tx(&self, slate: &Slate, args: InitTxArgs) -> Result<Slate, ErrorKind>;
 	fn tx_lock_outputs(&self, slate: Slate, participant_id: usize) -> Result<(), ErrorKind>;
 	fn finalize_tx(&self, slate: Slate) -> Result<Slate, ErrorKind>;
 	fn post_tx(&self, tx: &Transaction, fluff: bool) -> Result<(), ErrorKind>;
+	fn forward_slate(&self, slate: VersionedSlate) -> Result<VersionedSlate, ErrorKind>;
 	fn cancel_tx(&self, tx_id: Option<u32>, tx_slate_id: Option<Uuid>) -> Result<(), ErrorKind>;
+	fn bump_tx(&self, tx_id: u32, fee_factor: f64) -> Result<Slate, ErrorKind>;
+	fn set_tx_notes(
+		&self,
+		tx_id: u32,
+		notes: Option<BTreeMap<String, String>>,
+	) -> Result<(), ErrorKind>;
 	fn get_stored_tx(&self, slate_id: &Uuid) -> Result<Option<Transaction>, ErrorKind>;
 	fn verify_slate_messages(&self, slate: &Slate) -> Result<(), ErrorKind>;
+	fn get_payment_proof(
+		&self,
+		tx_id: Option<u32>,
+		tx_slate_id: Option<Uuid>,
+	) -> Result<Option<PaymentProof>, ErrorKind>;
+	fn verify_payment_proof(&self, proof: PaymentProof) -> Result<(), ErrorKind>;
 	fn restore(&self) -> Result<(), ErrorKind>;
 	fn check_repair(&self, delete_unconfirmed: bool) -> Result<(), ErrorKind>;
+	fn reconcile_kernels(&self, days: u64) -> Result<KernelReconciliation, ErrorKind>;
 	fn node_height(&self) -> Result<NodeHeightResult, ErrorKind>;
+	fn node_status(&self) -> Result<NodeStatus, ErrorKind>;
+	fn timings(&self) -> Result<Vec<StageTiming>, ErrorKind>;
+	fn features(&self) -> Result<Vec<Feature>, ErrorKind>;
 }
 
 impl<W, C, K> OwnerRpc for Owner<W, C, K>
@@ -78,17 +121,30 @@ where
 		Owner::create_account_path(self, label).map_err(|e| ErrorKind::GenericError(e.to_string()))
 	}
 
+	fn active_account(&self) -> Result<String, ErrorKind> {
+		Owner::active_account(self).map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
 	fn set_active_account(&self, label: &String) -> Result<(), ErrorKind> {
 		Owner::set_active_account(self, label).map_err(|e| ErrorKind::GenericError(e.to_string()))
 	}
 
+	fn set_account_notifications(
+		&self,
+		label: &String,
+		route: NotificationRoute,
+	) -> Result<(), ErrorKind> {
+		Owner::set_account_notifications(self, label, route)
+			.map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
 	fn retrieve_outputs(
 		&self,
 		include_spent: bool,
 		refresh_from_node: bool,
 		tx_id: Option<u32>,
 	) -> Result<(bool, Vec<OutputCommitMapping>), ErrorKind> {
-		Owner::retrieve_outputs(self, include_spent, refresh_from_node, tx_id)
+		Owner::retrieve_outputs(self, include_spent, refresh_from_node, tx_id, None)
 			.map(|x| (x.0, x.2))
 			.map_err(|e| ErrorKind::GenericError(e.to_string()))
 	}
@@ -99,9 +155,41 @@ where
 		tx_id: Option<u32>,
 		tx_slate_id: Option<Uuid>,
 	) -> Result<(bool, Vec<TxLogEntry>), ErrorKind> {
-		Owner::retrieve_txs(self, refresh_from_node, false, false, tx_id, tx_slate_id)
-			.map(|x| (x.0, x.2))
-			.map_err(|e| ErrorKind::GenericError(e.to_string()))
+		Owner::retrieve_txs(
+			self,
+			refresh_from_node,
+			false,
+			false,
+			tx_id,
+			tx_slate_id,
+			None,
+			None,
+		)
+		.map(|x| (x.0, x.2))
+		.map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn retrieve_txs_filtered(
+		&self,
+		refresh_from_node: bool,
+		filter: TxFilter,
+	) -> Result<(bool, Vec<TxLogEntry>), ErrorKind> {
+		Owner::retrieve_txs(
+			self,
+			refresh_from_node,
+			false,
+			false,
+			None,
+			None,
+			Some(filter),
+			None,
+		)
+		.map(|x| (x.0, x.2))
+		.map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn retrieve_tx_detail(&self, tx_id: u32) -> Result<TxDetail, ErrorKind> {
+		Owner::tx_detail(self, tx_id).map_err(|e| ErrorKind::GenericError(e.to_string()))
 	}
 
 	fn retrieve_summary_info(
@@ -114,7 +202,28 @@ where
 	}
 
 	fn init_send_tx(&self, args: InitTxArgs) -> Result<Slate, ErrorKind> {
-		Owner::init_send_tx(self, args).map_err(|e| ErrorKind::GenericError(e.to_string()))
+		Owner::init_send_tx(self, args).map_err(send_error)
+	}
+
+	fn confirm_send_tx(&self, token: Uuid) -> Result<Slate, ErrorKind> {
+		Owner::confirm_send_tx(self, &token).map_err(send_error)
+	}
+
+	fn estimate_fee(&self, args: EstimateFeeArgs) -> Result<Vec<FeeEstimate>, ErrorKind> {
+		Owner::estimate_fee(self, args).map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn analyze_dust(
+		&self,
+		threshold: Option<u64>,
+		minimum_confirmations: u64,
+	) -> Result<DustAnalysis, ErrorKind> {
+		Owner::analyze_dust(self, threshold, minimum_confirmations)
+			.map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn preview_send_tx(&self, args: InitTxArgs) -> Result<SendPreview, ErrorKind> {
+		Owner::preview_send_tx(self, &args).map_err(|e| ErrorKind::GenericError(e.to_string()))
 	}
 
 	/*fn issue_invoice_tx(&self, args: IssueInvoiceTxArgs) -> Result<Slate, ErrorKind> {
@@ -144,11 +253,27 @@ where
 		Owner::post_tx(self, tx, fluff).map_err(|e| ErrorKind::GenericError(e.to_string()))
 	}
 
+	fn forward_slate(&self, slate: VersionedSlate) -> Result<VersionedSlate, ErrorKind> {
+		Owner::forward_slate(self, &slate).map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
 	fn cancel_tx(&self, tx_id: Option<u32>, tx_slate_id: Option<Uuid>) -> Result<(), ErrorKind> {
 		Owner::cancel_tx(self, tx_id, tx_slate_id)
 			.map_err(|e| ErrorKind::GenericError(e.to_string()))
 	}
 
+	fn bump_tx(&self, tx_id: u32, fee_factor: f64) -> Result<Slate, ErrorKind> {
+		Owner::bump_tx(self, tx_id, fee_factor).map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn set_tx_notes(
+		&self,
+		tx_id: u32,
+		notes: Option<BTreeMap<String, String>>,
+	) -> Result<(), ErrorKind> {
+		Owner::set_tx_notes(self, tx_id, notes).map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
 	fn get_stored_tx(&self, slate_id: &Uuid) -> Result<Option<Transaction>, ErrorKind> {
 		Owner::get_stored_tx(self, slate_id).map_err(|e| ErrorKind::GenericError(e.to_string()))
 	}
@@ -158,16 +283,59 @@ where
 			.map_err(|e| ErrorKind::GenericError(e.to_string()))
 	}
 
+	fn get_payment_proof(
+		&self,
+		tx_id: Option<u32>,
+		tx_slate_id: Option<Uuid>,
+	) -> Result<Option<PaymentProof>, ErrorKind> {
+		Owner::get_payment_proof(self, tx_id, tx_slate_id)
+			.map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn verify_payment_proof(&self, proof: PaymentProof) -> Result<(), ErrorKind> {
+		Owner::verify_payment_proof(self, &proof)
+			.map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
 	fn restore(&self) -> Result<(), ErrorKind> {
-		Owner::restore(self).map_err(|e| ErrorKind::GenericError(e.to_string()))
+		Owner::restore(self, None)
+			.map(|_| ())
+			.map_err(|e| ErrorKind::GenericError(e.to_string()))
 	}
 
 	fn check_repair(&self, delete_unconfirmed: bool) -> Result<(), ErrorKind> {
-		Owner::check_repair(self, delete_unconfirmed)
+		Owner::check_repair(self, delete_unconfirmed, None)
 			.map_err(|e| ErrorKind::GenericError(e.to_string()))
 	}
 
+	fn reconcile_kernels(&self, days: u64) -> Result<KernelReconciliation, ErrorKind> {
+		Owner::reconcile_kernels(self, days).map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
 	fn node_height(&self) -> Result<NodeHeightResult, ErrorKind> {
 		Owner::node_height(self).map_err(|e| ErrorKind::GenericError(e.to_string()))
 	}
+
+	fn node_status(&self) -> Result<NodeStatus, ErrorKind> {
+		Owner::node_status(self).map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn timings(&self) -> Result<Vec<StageTiming>, ErrorKind> {
+		Ok(Owner::timings(self))
+	}
+
+	fn features(&self) -> Result<Vec<Feature>, ErrorKind> {
+		Ok(Owner::features(self))
+	}
+}
+
+/// Error of a send. A send waiting for confirmation or looking like a
+/// duplicate payment is passed through as is, for the caller to act on
+fn send_error(e: Error) -> ErrorKind {
+	match e.downcast::<ErrorKind>() {
+		Ok(kind @ ErrorKind::ConfirmationRequired(..)) => kind,
+		Ok(kind @ ErrorKind::DuplicatePayment { .. }) => kind,
+		Ok(kind) => ErrorKind::GenericError(kind.to_string()),
+		Err(e) => ErrorKind::GenericError(e.to_string()),
+	}
 }
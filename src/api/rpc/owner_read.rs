@@ -0,0 +1,154 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! JSON-RPC Stub generation for the read-only Owner API
+
+use crate::api::listener::ListenerStatus;
+use crate::common::features::Feature;
+use crate::common::Keychain;
+use crate::wallet::api::Owner;
+use crate::wallet::types::{
+	AcctPathMapping, NodeClient, NodeHeightResult, NodeStatus, OutputCommitMapping, TxDetail,
+	TxFilter, TxLogEntry, WalletBackend, WalletInfo,
+};
+use crate::wallet::ErrorKind;
+use easy_jsonrpc_mw;
+use uuid::Uuid;
+
+/// Subset of the Owner jsonrpc api that can't move funds or change the
+/// wallet, for dashboards and monitoring.
+/// * When running with defaults, the V2 api is available at
+/// `localhost:3421/v2/owner`
+/// * The endpoint only supports POST operations, with the json-rpc request as the body
+#[easy_jsonrpc_mw::rpc]
+pub trait OwnerReadRpc {
+	fn accounts(&self) -> Result<Vec<AcctPathMapping>, ErrorKind>;
+	fn retrieve_outputs(
+		&self,
+		include_spent: bool,
+		refresh_from_node: bool,
+		tx_id: Option<u32>,
+	) -> Result<(bool, Vec<OutputCommitMapping>), ErrorKind>;
+	fn retrieve_txs(
+		&self,
+		refresh_from_node: bool,
+		tx_id: Option<u32>,
+		tx_slate_id: Option<Uuid>,
+	) -> Result<(bool, Vec<TxLogEntry>), ErrorKind>;
+	fn retrieve_txs_filtered(
+		&self,
+		refresh_from_node: bool,
+		filter: TxFilter,
+	) -> Result<(bool, Vec<TxLogEntry>), ErrorKind>;
+	fn retrieve_tx_detail(&self, tx_id: u32) -> Result<TxDetail, ErrorKind>;
+	fn retrieve_summary_info(
+		&self,
+		refresh_from_node: bool,
+		minimum_confirmations: u64,
+	) -> Result<(bool, WalletInfo), ErrorKind>;
+	fn node_height(&self) -> Result<NodeHeightResult, ErrorKind>;
+	fn node_status(&self) -> Result<NodeStatus, ErrorKind>;
+	fn listener_status(&self) -> Result<Vec<ListenerStatus>, ErrorKind>;
+	fn features(&self) -> Result<Vec<Feature>, ErrorKind>;
+}
+
+impl<W, C, K> OwnerReadRpc for Owner<W, C, K>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	fn accounts(&self) -> Result<Vec<AcctPathMapping>, ErrorKind> {
+		Owner::accounts(self).map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn retrieve_outputs(
+		&self,
+		include_spent: bool,
+		refresh_from_node: bool,
+		tx_id: Option<u32>,
+	) -> Result<(bool, Vec<OutputCommitMapping>), ErrorKind> {
+		Owner::retrieve_outputs(self, include_spent, refresh_from_node, tx_id, None)
+			.map(|x| (x.0, x.2))
+			.map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn retrieve_txs(
+		&self,
+		refresh_from_node: bool,
+		tx_id: Option<u32>,
+		tx_slate_id: Option<Uuid>,
+	) -> Result<(bool, Vec<TxLogEntry>), ErrorKind> {
+		Owner::retrieve_txs(
+			self,
+			refresh_from_node,
+			false,
+			false,
+			tx_id,
+			tx_slate_id,
+			None,
+			None,
+		)
+		.map(|x| (x.0, x.2))
+		.map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn retrieve_txs_filtered(
+		&self,
+		refresh_from_node: bool,
+		filter: TxFilter,
+	) -> Result<(bool, Vec<TxLogEntry>), ErrorKind> {
+		Owner::retrieve_txs(
+			self,
+			refresh_from_node,
+			false,
+			false,
+			None,
+			None,
+			Some(filter),
+			None,
+		)
+		.map(|x| (x.0, x.2))
+		.map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn retrieve_tx_detail(&self, tx_id: u32) -> Result<TxDetail, ErrorKind> {
+		Owner::tx_detail(self, tx_id).map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn retrieve_summary_info(
+		&self,
+		refresh_from_node: bool,
+		minimum_confirmations: u64,
+	) -> Result<(bool, WalletInfo), ErrorKind> {
+		Owner::retrieve_summary_info(self, refresh_from_node, minimum_confirmations)
+			.map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn node_height(&self) -> Result<NodeHeightResult, ErrorKind> {
+		Owner::node_height(self).map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn node_status(&self) -> Result<NodeStatus, ErrorKind> {
+		Owner::node_status(self).map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn listener_status(&self) -> Result<Vec<ListenerStatus>, ErrorKind> {
+		Owner::listener_status(self).map_err(|e| ErrorKind::GenericError(e.to_string()))
+	}
+
+	fn features(&self) -> Result<Vec<Feature>, ErrorKind> {
+		Ok(Owner::features(self))
+	}
+}
@@ -0,0 +1,378 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Server side of the WebSocket protocol (RFC 6455) over an upgraded HTTP
+//! connection: client frames are read and unmasked so pings and closes can
+//! be answered, and text messages are pushed to the client
+
+use crate::common::base64;
+use bytes::{BufMut, BytesMut};
+use futures::stream::FilterMap;
+use futures::{Async, Poll, Stream};
+use ring::digest;
+use std::io;
+use tokio::codec::{Decoder, Encoder};
+
+/// Appended to the key of the client before hashing, as set by the RFC
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Largest payload accepted from a client. Clients only send control frames
+/// and short text messages
+const MAX_PAYLOAD: u64 = 64 * 1024;
+/// Control frames carry at most this many bytes
+const MAX_CONTROL_PAYLOAD: usize = 125;
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xa;
+
+/// Status code of a normal closure
+pub const CLOSE_NORMAL: u16 = 1000;
+
+/// Value of the `Sec-WebSocket-Accept` header answering the `Sec-WebSocket-Key` of a client
+pub fn accept_key(key: &str) -> String {
+	let hash = digest::digest(
+		&digest::SHA1,
+		format!("{}{}", key.trim(), ACCEPT_GUID).as_bytes(),
+	);
+	base64::encode(hash.as_ref())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+	Text(String),
+	Ping(Vec<u8>),
+	Pong(Vec<u8>),
+	Close(Option<(u16, String)>),
+	/// Binary and fragmented messages, which the wallet has no use for
+	Ignored,
+}
+
+/// Reads masked client frames and writes unmasked server frames
+#[derive(Default)]
+pub struct Codec;
+
+impl Decoder for Codec {
+	type Item = Message;
+	type Error = io::Error;
+
+	fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Message>, io::Error> {
+		if src.len() < 2 {
+			return Ok(None);
+		}
+		let (first, second) = (src[0], src[1]);
+		let fin = first & 0x80 != 0;
+		let opcode = first & 0x0f;
+		if first & 0x70 != 0 {
+			return Err(protocol_error("reserved bits are set"));
+		}
+		if second & 0x80 == 0 {
+			return Err(protocol_error("client frames must be masked"));
+		}
+
+		let (len, mut offset) = match second & 0x7f {
+			126 if src.len() >= 4 => (u64::from(src[2]) << 8 | u64::from(src[3]), 4),
+			127 if src.len() >= 10 => {
+				let len = src[2..10]
+					.iter()
+					.fold(0u64, |acc, b| (acc << 8) | u64::from(*b));
+				(len, 10)
+			}
+			126 | 127 => return Ok(None),
+			len => (u64::from(len), 2),
+		};
+		if len > MAX_PAYLOAD {
+			return Err(protocol_error("frame is too large"));
+		}
+		let is_control = opcode & 0x8 != 0;
+		if is_control && (!fin || len as usize > MAX_CONTROL_PAYLOAD) {
+			return Err(protocol_error("invalid control frame"));
+		}
+		let len = len as usize;
+		if src.len() < offset + 4 + len {
+			src.reserve(offset + 4 + len - src.len());
+			return Ok(None);
+		}
+
+		let mut mask = [0u8; 4];
+		mask.copy_from_slice(&src[offset..offset + 4]);
+		offset += 4;
+		let frame = src.split_to(offset + len);
+		let payload: Vec<u8> = frame[offset..]
+			.iter()
+			.enumerate()
+			.map(|(i, b)| b ^ mask[i % 4])
+			.collect();
+
+		let message = match opcode {
+			OP_TEXT if fin => Message::Text(
+				String::from_utf8(payload).map_err(|_| protocol_error("text is not UTF-8"))?,
+			),
+			OP_TEXT | OP_BINARY | OP_CONTINUATION => Message::Ignored,
+			OP_PING => Message::Ping(payload),
+			OP_PONG => Message::Pong(payload),
+			OP_CLOSE => Message::Close(close_reason(&payload)?),
+			_ => return Err(protocol_error("unknown opcode")),
+		};
+		Ok(Some(message))
+	}
+}
+
+impl Encoder for Codec {
+	type Item = Message;
+	type Error = io::Error;
+
+	fn encode(&mut self, message: Message, dst: &mut BytesMut) -> Result<(), io::Error> {
+		let (opcode, payload) = match message {
+			Message::Text(text) => (OP_TEXT, text.into_bytes()),
+			Message::Ping(data) => (OP_PING, data),
+			Message::Pong(data) => (OP_PONG, data),
+			Message::Close(None) => (OP_CLOSE, vec![]),
+			Message::Close(Some((code, reason))) => {
+				let mut payload = code.to_be_bytes().to_vec();
+				payload.extend_from_slice(reason.as_bytes());
+				(OP_CLOSE, payload)
+			}
+			Message::Ignored => return Ok(()),
+		};
+
+		// Final and unmasked, only clients mask their frames
+		dst.reserve(payload.len() + 10);
+		dst.put_u8(0x80 | opcode);
+		match payload.len() {
+			len if len < 126 => dst.put_u8(len as u8),
+			len if len <= 0xffff => {
+				dst.put_u8(126);
+				dst.put_slice(&(len as u16).to_be_bytes());
+			}
+			len => {
+				dst.put_u8(127);
+				dst.put_slice(&(len as u64).to_be_bytes());
+			}
+		}
+		dst.put_slice(&payload);
+		Ok(())
+	}
+}
+
+/// Messages to send on a connection: the answers to client frames, and
+/// `pushed` messages in between. Ends with the client's close, or with a
+/// close of our own when `pushed` ends
+pub struct Outgoing<I, P> {
+	replies: FilterMap<I, fn(Message) -> Option<Message>>,
+	pushed: P,
+	closed: bool,
+}
+
+impl<I, P> Outgoing<I, P>
+where
+	I: Stream<Item = Message, Error = ()>,
+	P: Stream<Item = Message, Error = ()>,
+{
+	/// Answer the pings and the close read from `incoming`
+	pub fn new(incoming: I, pushed: P) -> Self {
+		Self {
+			replies: incoming.filter_map(reply as fn(Message) -> Option<Message>),
+			pushed,
+			closed: false,
+		}
+	}
+}
+
+impl<I, P> Stream for Outgoing<I, P>
+where
+	I: Stream<Item = Message, Error = ()>,
+	P: Stream<Item = Message, Error = ()>,
+{
+	type Item = Message;
+	type Error = ();
+
+	fn poll(&mut self) -> Poll<Option<Message>, ()> {
+		if self.closed {
+			return Ok(Async::Ready(None));
+		}
+		match self.replies.poll()? {
+			Async::Ready(Some(message)) => {
+				self.closed = is_close(&message);
+				return Ok(Async::Ready(Some(message)));
+			}
+			// The connection dropped without a close
+			Async::Ready(None) => return Ok(Async::Ready(None)),
+			Async::NotReady => {}
+		}
+		match self.pushed.poll()? {
+			Async::Ready(Some(message)) => {
+				self.closed = is_close(&message);
+				Ok(Async::Ready(Some(message)))
+			}
+			Async::Ready(None) => {
+				self.closed = true;
+				Ok(Async::Ready(Some(Message::Close(None))))
+			}
+			Async::NotReady => Ok(Async::NotReady),
+		}
+	}
+}
+
+fn reply(message: Message) -> Option<Message> {
+	match message {
+		Message::Ping(data) => Some(Message::Pong(data)),
+		Message::Close(_) => Some(Message::Close(Some((CLOSE_NORMAL, String::new())))),
+		_ => None,
+	}
+}
+
+fn is_close(message: &Message) -> bool {
+	match message {
+		Message::Close(_) => true,
+		_ => false,
+	}
+}
+
+fn close_reason(payload: &[u8]) -> Result<Option<(u16, String)>, io::Error> {
+	match payload.len() {
+		0 => Ok(None),
+		1 => Err(protocol_error("invalid close frame")),
+		_ => {
+			let code = u16::from(payload[0]) << 8 | u16::from(payload[1]);
+			let reason = String::from_utf8(payload[2..].to_vec())
+				.map_err(|_| protocol_error("close reason is not UTF-8"))?;
+			Ok(Some((code, reason)))
+		}
+	}
+}
+
+fn protocol_error(reason: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Frame as sent by a client, masked with `mask`
+	fn client_frame(first: u8, payload: &[u8], mask: [u8; 4]) -> BytesMut {
+		let mut frame = BytesMut::new();
+		frame.put_u8(first);
+		frame.put_u8(0x80 | payload.len() as u8);
+		frame.put_slice(&mask);
+		for (i, b) in payload.iter().enumerate() {
+			frame.put_u8(b ^ mask[i % 4]);
+		}
+		frame
+	}
+
+	#[test]
+	fn accept_key_matches_rfc_6455() {
+		assert_eq!(
+			accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+			"s3pPLMBiTxaQ9kYGzhZRbK+xOo="
+		);
+	}
+
+	#[test]
+	fn decodes_masked_client_frames() {
+		// Masked "Hello" from section 5.7 of RFC 6455
+		let mut src = BytesMut::from(
+			&[
+				0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+			][..],
+		);
+		let message = Codec.decode(&mut src).unwrap();
+		assert_eq!(message, Some(Message::Text("Hello".to_owned())));
+		assert!(src.is_empty());
+
+		let mut src = client_frame(0x89, b"are you there", [1, 2, 3, 4]);
+		let message = Codec.decode(&mut src).unwrap();
+		assert_eq!(message, Some(Message::Ping(b"are you there".to_vec())));
+
+		let mut src = client_frame(0x88, &[0x03, 0xe8, b'b', b'y', b'e'], [9, 8, 7, 6]);
+		let message = Codec.decode(&mut src).unwrap();
+		assert_eq!(
+			message,
+			Some(Message::Close(Some((1000, "bye".to_owned()))))
+		);
+	}
+
+	#[test]
+	fn waits_for_a_whole_frame() {
+		let frame = client_frame(0x81, b"partial", [5, 6, 7, 8]);
+		let mut src = BytesMut::from(&frame[..frame.len() - 1]);
+		assert_eq!(Codec.decode(&mut src).unwrap(), None);
+		src.put_slice(&frame[frame.len() - 1..]);
+		let message = Codec.decode(&mut src).unwrap();
+		assert_eq!(message, Some(Message::Text("partial".to_owned())));
+	}
+
+	#[test]
+	fn refuses_unmasked_and_oversized_frames() {
+		let mut unmasked = BytesMut::from(&[0x81, 0x02, b'h', b'i'][..]);
+		assert!(Codec.decode(&mut unmasked).is_err());
+
+		let mut huge = BytesMut::from(&[0x82, 0xff, 0, 0, 0, 0, 0xff, 0, 0, 0][..]);
+		assert!(Codec.decode(&mut huge).is_err());
+
+		let mut fragmented_ping = client_frame(0x09, b"", [0; 4]);
+		assert!(Codec.decode(&mut fragmented_ping).is_err());
+	}
+
+	#[test]
+	fn encodes_unmasked_server_frames() {
+		let mut dst = BytesMut::new();
+		Codec
+			.encode(Message::Text("Hello".to_owned()), &mut dst)
+			.unwrap();
+		assert_eq!(&dst[..], &[0x81, 0x05, b'H', b'e', b'l', b'l', b'o'][..]);
+
+		let mut dst = BytesMut::new();
+		Codec
+			.encode(Message::Text("x".repeat(300)), &mut dst)
+			.unwrap();
+		assert_eq!(&dst[..4], &[0x81, 126, 0x01, 0x2c][..]);
+
+		let mut dst = BytesMut::new();
+		Codec
+			.encode(
+				Message::Close(Some((CLOSE_NORMAL, String::new()))),
+				&mut dst,
+			)
+			.unwrap();
+		assert_eq!(&dst[..], &[0x88, 0x02, 0x03, 0xe8][..]);
+	}
+
+	#[test]
+	fn answers_pings_and_stops_after_a_close() {
+		use futures::stream;
+
+		let incoming = stream::iter_ok(vec![
+			Message::Ping(b"1".to_vec()),
+			Message::Close(None),
+			Message::Ping(b"2".to_vec()),
+		]);
+		let pushed = stream::iter_ok(vec![Message::Text("event".to_owned())]);
+		let sent: Vec<Message> = Outgoing::new(incoming, pushed)
+			.wait()
+			.map(|m| m.unwrap())
+			.collect();
+		assert_eq!(
+			sent,
+			vec![
+				Message::Pong(b"1".to_vec()),
+				Message::Close(Some((CLOSE_NORMAL, String::new()))),
+			]
+		);
+	}
+}
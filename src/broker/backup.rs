@@ -0,0 +1,139 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::types::{ConnectionStatus, SharedConnectionStatus};
+use crate::cli_message;
+use crate::common::{Arc, Error, Keychain, Mutex};
+use crate::wallet::api::Owner;
+use crate::wallet::types::{NodeClient, SharedEventBus, WalletBackend, WalletEvent};
+use chrono::Duration;
+use colored::Colorize;
+use futures::Stream;
+use std::thread;
+
+const POLL_INTERVAL: u32 = 30;
+const SLEEP_DURATION: std::time::Duration = std::time::Duration::from_millis(1000);
+
+/// Backs the wallet up every `interval` hours and after every `every_txs`
+/// received or finalized transactions, counted from the event bus
+#[derive(Clone)]
+pub struct BackupScheduler {
+	interval: Option<Duration>,
+	every_txs: Option<u32>,
+	events: SharedEventBus,
+	/// Transactions since the last backup
+	txs: Arc<Mutex<u32>>,
+	stop_signal: Arc<Mutex<bool>>,
+	status: SharedConnectionStatus,
+}
+
+impl BackupScheduler {
+	pub fn new(interval: Option<u64>, every_txs: Option<u32>, events: SharedEventBus) -> Self {
+		Self {
+			interval: interval.map(|h| Duration::hours(h as i64)),
+			every_txs,
+			events,
+			txs: Arc::new(Mutex::new(0)),
+			stop_signal: Arc::new(Mutex::new(true)),
+			status: SharedConnectionStatus::new(),
+		}
+	}
+
+	pub fn start<W, C, K>(&self, owner: Owner<W, C, K>) -> Result<(), Error>
+	where
+		W: WalletBackend<C, K>,
+		C: NodeClient,
+		K: Keychain,
+	{
+		{
+			let mut guard = self.stop_signal.lock();
+			*guard = false;
+		}
+		if self.every_txs.is_some() {
+			self.count_txs();
+		}
+
+		let mut last_backup = owner.last_backup()?;
+		self.status.connected("backup");
+		while !self.is_stopped() {
+			let now = owner.clock().now();
+			let time_due = match (self.interval, last_backup) {
+				(Some(interval), Some(last)) => now >= last + interval,
+				(Some(_), None) => true,
+				(None, _) => false,
+			};
+			let txs_due = self.every_txs.map(|n| *self.txs.lock() >= n);
+			if time_due || txs_due.unwrap_or(false) {
+				*self.txs.lock() = 0;
+				// Also after a failure, so it is only tried again on schedule
+				last_backup = Some(now);
+				match owner.backup_now() {
+					Ok(backup) => {
+						self.status.connected("backup");
+						cli_message!(
+							owner.runtime(),
+							"Wallet backed up to {}",
+							backup.path.bright_green()
+						);
+					}
+					Err(e) => self.status.error("backup", e.to_string()),
+				}
+			}
+
+			// Sleep in short steps so stopping doesn't wait for a full interval
+			for _ in 0..POLL_INTERVAL {
+				if self.is_stopped() {
+					break;
+				}
+				thread::sleep(SLEEP_DURATION);
+			}
+		}
+		self.status.disconnected("backup");
+		Ok(())
+	}
+
+	pub fn stop(&self) {
+		let mut guard = self.stop_signal.lock();
+		*guard = true;
+	}
+
+	pub fn status(&self) -> ConnectionStatus {
+		self.status.get()
+	}
+
+	fn is_stopped(&self) -> bool {
+		*self.stop_signal.lock()
+	}
+
+	/// Count transactions on a background thread, which ends with the
+	/// first event after the scheduler is stopped
+	fn count_txs(&self) {
+		let receiver = self.events.subscribe();
+		let scheduler = self.clone();
+		thread::spawn(move || {
+			for event in receiver.wait() {
+				match event.map(|e| e.event) {
+					Ok(WalletEvent::SlateReceived(_)) | Ok(WalletEvent::TxFinalized(_)) => {
+						*scheduler.txs.lock() += 1
+					}
+					Ok(_) => {}
+					Err(_) => break,
+				}
+				if scheduler.is_stopped() {
+					break;
+				}
+			}
+		});
+	}
+}
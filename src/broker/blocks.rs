@@ -0,0 +1,130 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::types::{ConnectionStatus, SharedConnectionStatus};
+use crate::cli_message;
+use crate::common::{Arc, Error, Keychain, Mutex};
+use crate::wallet::api::Owner;
+use crate::wallet::types::{NodeClient, SharedEventBus, WalletBackend, WalletEvent};
+use chrono::Utc;
+use colored::Colorize;
+use std::time::Duration;
+
+const SLEEP_DURATION: Duration = Duration::from_millis(1000);
+
+/// Watches the chain height of the node and refreshes the outputs of
+/// outstanding transactions on every new block, so confirmations show up
+/// without waiting for the next command. Grin nodes don't push new blocks,
+/// so the height is polled
+#[derive(Clone)]
+pub struct BlockWatcher {
+	interval: u64,
+	events: SharedEventBus,
+	stop_signal: Arc<Mutex<bool>>,
+	status: SharedConnectionStatus,
+}
+
+impl BlockWatcher {
+	/// Poll the node every `interval` seconds
+	pub fn new(interval: u64, events: SharedEventBus) -> Self {
+		Self {
+			interval,
+			events,
+			stop_signal: Arc::new(Mutex::new(true)),
+			status: SharedConnectionStatus::new(),
+		}
+	}
+
+	pub fn start<W, C, K>(&self, owner: Owner<W, C, K>) -> Result<(), Error>
+	where
+		W: WalletBackend<C, K>,
+		C: NodeClient,
+		K: Keychain,
+	{
+		{
+			let mut guard = self.stop_signal.lock();
+			*guard = false;
+		}
+
+		let mut last_height = None;
+		while !self.is_stopped() {
+			match owner.node_height() {
+				Ok(res) if res.updated_from_node => {
+					self.status.connected("blocks");
+					if last_height.map(|h| res.height > h).unwrap_or(true) {
+						self.on_block(&owner, res.height);
+						last_height = Some(res.height);
+					}
+				}
+				Ok(_) => {
+					let error = String::from("node unreachable");
+					self.status.error("blocks", error);
+					self.status.disconnected("blocks");
+				}
+				Err(e) => {
+					self.status.error("blocks", e.to_string());
+					self.status.disconnected("blocks");
+				}
+			}
+
+			// Sleep in short steps so stopping doesn't wait for a full interval
+			for _ in 0..self.interval {
+				if self.is_stopped() {
+					break;
+				}
+				std::thread::sleep(SLEEP_DURATION);
+			}
+		}
+		self.status.disconnected("blocks");
+		Ok(())
+	}
+
+	pub fn stop(&self) {
+		let mut guard = self.stop_signal.lock();
+		*guard = true;
+	}
+
+	pub fn status(&self) -> ConnectionStatus {
+		self.status.get()
+	}
+
+	fn is_stopped(&self) -> bool {
+		*self.stop_signal.lock()
+	}
+
+	fn on_block<W, C, K>(&self, owner: &Owner<W, C, K>, height: u64)
+	where
+		W: WalletBackend<C, K>,
+		C: NodeClient,
+		K: Keychain,
+	{
+		self.events.publish(WalletEvent::NewBlock {
+			height,
+			timestamp: Utc::now(),
+		});
+		match owner.refresh_pending() {
+			Ok(confirmed) => {
+				for tx in confirmed {
+					cli_message!(
+						owner.runtime(),
+						"Transaction {} confirmed at height {}",
+						tx.id.to_string().bright_green(),
+						tx.confirmation_height.unwrap_or(height)
+					);
+				}
+			}
+			Err(e) => self.status.error("blocks", e.to_string()),
+		}
+	}
+}
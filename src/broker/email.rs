@@ -0,0 +1,406 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Slate exchange by email, for counterparties that only do file exchange.
+//! Slates are sent as attachments over SMTP and `.response` attachments are
+//! picked up from an IMAP inbox, through `lettre` and `imap`. Both servers
+//! are reached over implicit TLS
+
+use super::types::{
+	CloseReason, ConnectionStatus, Controller, Publisher, SharedConnectionStatus, Subscriber,
+	SubscriptionHandler,
+};
+use crate::common::base64;
+use crate::common::config::Wallet713Config;
+use crate::common::{Arc, ErrorKind, Keychain, Mutex, Result};
+use crate::contacts::{Address, EmailAddress};
+use crate::wallet::types::{NodeClient, Slate, VersionedSlate, WalletBackend};
+use lettre::smtp::authentication::Credentials;
+use lettre::{ClientSecurity, ClientTlsParameters, SendableEmail, SmtpClient, Transport};
+use lettre_email::EmailBuilder;
+use log::{debug, warn};
+use native_tls::{TlsConnector, TlsStream};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+const DEFAULT_SMTP_PORT: u16 = 465;
+const DEFAULT_IMAP_PORT: u16 = 993;
+const RESPONSE_EXTENSION: &str = ".response";
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+const SLEEP_DURATION: Duration = Duration::from_millis(1000);
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+type ImapSession = imap::Session<TlsStream<TcpStream>>;
+
+/// Mailbox and servers slates are exchanged through
+#[derive(Clone)]
+pub struct EmailSettings {
+	pub address: String,
+	smtp_server: String,
+	imap_server: String,
+	username: String,
+	password: String,
+}
+
+impl EmailSettings {
+	pub fn from_config(config: &Wallet713Config) -> Result<Self> {
+		let missing = |key: &str| ErrorKind::EmailServer(format!("{} is not set", key));
+		let address = config
+			.email_address
+			.clone()
+			.ok_or_else(|| missing("email_address"))?;
+		Ok(Self {
+			smtp_server: config
+				.email_smtp_server
+				.clone()
+				.ok_or_else(|| missing("email_smtp_server"))?,
+			imap_server: config
+				.email_imap_server
+				.clone()
+				.ok_or_else(|| missing("email_imap_server"))?,
+			username: config.email_username.clone().unwrap_or(address.clone()),
+			password: config
+				.email_password
+				.clone()
+				.ok_or_else(|| missing("email_password"))?,
+			address,
+		})
+	}
+}
+
+#[derive(Clone)]
+pub struct EmailPublisher {
+	settings: EmailSettings,
+}
+
+impl EmailPublisher {
+	pub fn new(settings: EmailSettings) -> Self {
+		Self { settings }
+	}
+}
+
+impl Publisher for EmailPublisher {
+	fn post_slate(&self, slate: &VersionedSlate, to: &dyn Address) -> Result<()> {
+		let to = EmailAddress::from_str(&to.to_string())?;
+		let content = serde_json::to_string(slate)?;
+		let inner: Slate = slate.clone().into();
+		let file_name = match inner.participant_data.len() < inner.num_participants {
+			true => format!("{}.tx", inner.id),
+			false => format!("{}.tx{}", inner.id, RESPONSE_EXTENSION),
+		};
+		let text = format!(
+			"Process the attached slate with your Grin wallet and send the resulting \
+			 {}{} back to {}.",
+			file_name, RESPONSE_EXTENSION, self.settings.address
+		);
+		let message = EmailBuilder::new()
+			.from(self.settings.address.as_str())
+			.to(to.address.as_str())
+			.subject(format!("Grin slate {}", inner.id))
+			.text(text)
+			.attachment(content.as_bytes(), &file_name, &mime::APPLICATION_JSON)
+			.and_then(|builder| builder.build())
+			.map_err(|e| ErrorKind::EmailServer(e.to_string()))?;
+		send_mail(&self.settings, message.into())?;
+		debug!("email: sent {} to {}", file_name, to);
+		Ok(())
+	}
+}
+
+/// Polls the inbox for unseen messages with `.response` attachments
+#[derive(Clone)]
+pub struct EmailSubscriber {
+	settings: EmailSettings,
+	stop_signal: Arc<Mutex<bool>>,
+	status: SharedConnectionStatus,
+}
+
+impl EmailSubscriber {
+	pub fn new(settings: EmailSettings) -> Self {
+		Self {
+			settings,
+			stop_signal: Arc::new(Mutex::new(true)),
+			status: SharedConnectionStatus::new(),
+		}
+	}
+
+	fn wait(&self) {
+		let start = Instant::now();
+		while !*self.stop_signal.lock() && start.elapsed() < POLL_INTERVAL {
+			std::thread::sleep(SLEEP_DURATION);
+		}
+	}
+}
+
+impl Subscriber for EmailSubscriber {
+	fn start<W, C, K, P>(&mut self, handler: Controller<W, C, K, P>) -> Result<()>
+	where
+		W: WalletBackend<C, K>,
+		C: NodeClient,
+		K: Keychain,
+		P: Publisher,
+	{
+		{
+			let mut guard = self.stop_signal.lock();
+			*guard = false;
+		}
+
+		let mut subscribed = false;
+		let mut dropped = false;
+		let result: Result<()> = loop {
+			if *self.stop_signal.lock() {
+				break Ok(());
+			}
+			match fetch_responses(&self.settings) {
+				Ok(responses) => {
+					self.status.connected("email");
+					if !subscribed {
+						subscribed = true;
+						handler.on_open();
+					}
+					if dropped {
+						dropped = false;
+						handler.on_reestablished();
+					}
+					for (from, file_name, content) in &responses {
+						match serde_json::from_str::<VersionedSlate>(content) {
+							Ok(slate) => handler.on_slate(from, &slate, None),
+							Err(_) => warn!("email: {} from {} is not a slate", file_name, from),
+						}
+					}
+				}
+				Err(e) => {
+					self.status.error("email", e.to_string());
+					self.status.disconnected("email");
+					if !subscribed {
+						break Err(e);
+					}
+					self.status.reconnecting("email");
+					if !dropped {
+						dropped = true;
+						handler.on_dropped();
+					}
+				}
+			}
+			self.wait();
+		};
+		self.status.disconnected("email");
+		match result {
+			Err(e) => handler.on_close(CloseReason::Abnormal(e)),
+			_ => handler.on_close(CloseReason::Normal),
+		}
+		Ok(())
+	}
+
+	fn stop(&self) {
+		let mut guard = self.stop_signal.lock();
+		*guard = true;
+	}
+
+	fn is_running(&self) -> bool {
+		let guard = self.stop_signal.lock();
+		!*guard
+	}
+
+	fn status(&self) -> ConnectionStatus {
+		self.status.get()
+	}
+}
+
+/// Host and port of `server`, given as `host` or `host:port`
+fn host_and_port(server: &str, default_port: u16) -> Result<(&str, u16)> {
+	match server.rfind(':') {
+		Some(i) => {
+			let port = server[i + 1..]
+				.parse()
+				.map_err(|_| ErrorKind::EmailServer(format!("invalid server {}", server)))?;
+			Ok((&server[..i], port))
+		}
+		None => Ok((server, default_port)),
+	}
+}
+
+fn send_mail(settings: &EmailSettings, message: SendableEmail) -> Result<()> {
+	let smtp_error = |e: lettre::smtp::error::Error| ErrorKind::EmailServer(e.to_string());
+	let (host, port) = host_and_port(&settings.smtp_server, DEFAULT_SMTP_PORT)?;
+	let tls = ClientTlsParameters::new(host.to_owned(), TlsConnector::new()?);
+	let credentials = Credentials::new(settings.username.clone(), settings.password.clone());
+	let mut transport = SmtpClient::new((host, port), ClientSecurity::Wrapper(tls))
+		.map_err(smtp_error)?
+		.credentials(credentials)
+		.timeout(Some(TIMEOUT))
+		.transport();
+	let sent = transport.send(message);
+	transport.close();
+	sent.map_err(smtp_error)?;
+	Ok(())
+}
+
+fn imap_error(e: imap::error::Error) -> ErrorKind {
+	ErrorKind::EmailServer(e.to_string())
+}
+
+fn imap_login(settings: &EmailSettings) -> Result<ImapSession> {
+	let (host, port) = host_and_port(&settings.imap_server, DEFAULT_IMAP_PORT)?;
+	let tcp = TcpStream::connect((host, port))?;
+	tcp.set_read_timeout(Some(TIMEOUT))?;
+	tcp.set_write_timeout(Some(TIMEOUT))?;
+	let tls = TlsConnector::new()?
+		.connect(host, tcp)
+		.map_err(|e| ErrorKind::EmailServer(format!("{}: {}", host, e)))?;
+
+	let mut client = imap::Client::new(tls);
+	client.read_greeting().map_err(imap_error)?;
+	let session = client
+		.login(&settings.username, &settings.password)
+		.map_err(|(e, _)| imap_error(e))?;
+	Ok(session)
+}
+
+/// Sender, file name and content of the `.response` attachments of unseen
+/// messages. Messages with a response are marked as seen, others are left
+/// untouched
+fn fetch_responses(settings: &EmailSettings) -> Result<Vec<(EmailAddress, String, String)>> {
+	let mut session = imap_login(settings)?;
+	session.select("INBOX").map_err(imap_error)?;
+	let mut uids: Vec<u32> = session
+		.uid_search("UNSEEN")
+		.map_err(imap_error)?
+		.into_iter()
+		.collect();
+	uids.sort();
+
+	let mut responses = Vec::new();
+	for uid in uids {
+		let fetches = session
+			.uid_fetch(uid.to_string(), "BODY.PEEK[]")
+			.map_err(imap_error)?;
+		let mut found = false;
+		for message in fetches.iter().filter_map(|f| f.body()) {
+			let message = String::from_utf8_lossy(message);
+			let mut attachments = Vec::new();
+			response_attachments(&message, &mut attachments);
+			if attachments.is_empty() {
+				continue;
+			}
+			found = true;
+			let (headers, _) = split_entity(&message);
+			let from = match sender(&headers) {
+				Some(from) => from,
+				None => {
+					warn!("email: skipping response without a valid sender");
+					continue;
+				}
+			};
+			for (file_name, content) in attachments {
+				responses.push((from.clone(), file_name, content));
+			}
+		}
+		if found {
+			session
+				.uid_store(uid.to_string(), "+FLAGS (\\Seen)")
+				.map_err(imap_error)?;
+		}
+	}
+	let _ = session.logout();
+	Ok(responses)
+}
+
+/// Unfolded headers, with lowercase names, and body of a MIME entity
+fn split_entity(entity: &str) -> (Vec<(String, String)>, &str) {
+	let (head, body) = match entity.find("\r\n\r\n") {
+		Some(i) => (&entity[..i], &entity[i + 4..]),
+		None => (entity, ""),
+	};
+	let mut headers: Vec<(String, String)> = Vec::new();
+	for line in head.split("\r\n") {
+		if line.starts_with(' ') || line.starts_with('\t') {
+			if let Some(last) = headers.last_mut() {
+				last.1.push(' ');
+				last.1.push_str(line.trim());
+			}
+		} else if let Some(i) = line.find(':') {
+			headers.push((
+				line[..i].trim().to_lowercase(),
+				line[i + 1..].trim().to_owned(),
+			));
+		}
+	}
+	(headers, body)
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+	headers
+		.iter()
+		.find(|(n, _)| n == name)
+		.map(|(_, v)| v.as_str())
+}
+
+/// Parameter of a header value, such as `boundary` or `filename`
+fn parameter(value: &str, name: &str) -> Option<String> {
+	value.split(';').skip(1).find_map(|p| {
+		let mut pair = p.splitn(2, '=');
+		let key = pair.next()?.trim();
+		let value = pair.next()?.trim().trim_matches('"');
+		match key.eq_ignore_ascii_case(name) {
+			true => Some(value.to_owned()),
+			false => None,
+		}
+	})
+}
+
+fn sender(headers: &[(String, String)]) -> Option<EmailAddress> {
+	let from = header(headers, "from")?;
+	let address = match (from.rfind('<'), from.rfind('>')) {
+		(Some(start), Some(end)) if start < end => &from[start + 1..end],
+		_ => from,
+	};
+	EmailAddress::from_str(address.trim()).ok()
+}
+
+/// Collect the `.response` attachments of an entity, looking into nested
+/// multiparts
+fn response_attachments(entity: &str, found: &mut Vec<(String, String)>) {
+	let (headers, body) = split_entity(entity);
+	let content_type = header(&headers, "content-type").unwrap_or("text/plain");
+	if content_type.to_lowercase().starts_with("multipart/") {
+		if let Some(boundary) = parameter(content_type, "boundary") {
+			let delimiter = format!("--{}", boundary);
+			for part in body.split(delimiter.as_str()).skip(1) {
+				if part.starts_with("--") {
+					break;
+				}
+				response_attachments(part.get(2..).unwrap_or(""), found);
+			}
+		}
+		return;
+	}
+
+	let file_name = header(&headers, "content-disposition")
+		.and_then(|d| parameter(d, "filename"))
+		.or_else(|| parameter(content_type, "name"));
+	let file_name = match file_name {
+		Some(f) if f.ends_with(RESPONSE_EXTENSION) => f,
+		_ => return,
+	};
+	let encoding = header(&headers, "content-transfer-encoding").unwrap_or("7bit");
+	let content = match encoding.eq_ignore_ascii_case("base64") {
+		true => match base64::decode(body) {
+			Ok(content) => String::from_utf8_lossy(&content).into_owned(),
+			Err(_) => return,
+		},
+		false => body.to_owned(),
+	};
+	found.push((file_name, content.trim().to_owned()));
+}
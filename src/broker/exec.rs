@@ -0,0 +1,612 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transports run as external executables, so new ones can be added
+//! without changing the wallet. The adapter is started once and kept
+//! running. Both sides write one JSON object per line, the wallet on the
+//! stdin of the adapter and the adapter on its stdout. Its stderr is logged.
+//!
+//! Requests of the wallet carry an `id` the adapter answers with, either
+//! `{"id": 1, "result": ...}` or `{"id": 1, "error": "reason"}`:
+//!
+//! * `init` with `name` and `version` of the protocol, answered with the
+//!   `address` the wallet can be reached at, if any
+//! * `post_slate` with the `to` address and the `slate`
+//! * `listen` and `unlisten`, between which the adapter passes on the
+//!   slates it receives
+//!
+//! Received slates are sent without an id, as
+//! `{"method": "slate", "params": {"from": "<address>", "slate": {...}}}`.
+//! Addresses have the name of the adapter as scheme, as in
+//! `matrix://@bob:matrix.org`. The adapter should exit once its stdin is closed
+
+use super::types::{
+	CloseReason, ConnectionStatus, Controller, Publisher, SharedConnectionStatus, Subscriber,
+	SubscriptionHandler,
+};
+use crate::common::config::ExecAdapterConfig;
+use crate::common::{Arc, ErrorKind, Keychain, Mutex, Result};
+use crate::contacts::{Address, ExecAddress};
+use crate::wallet::adapter::Adapter;
+use crate::wallet::types::{NodeClient, VersionedSlate, WalletBackend};
+use log::{debug, warn};
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread::spawn;
+use std::time::{Duration, Instant};
+
+pub const EXEC_PROTOCOL_VERSION: u32 = 1;
+const NAME_REGEX: &str = r"^[a-z][a-z0-9+.-]*$";
+/// Names of the built-in transports, which can't be taken by an adapter
+const RESERVED_NAMES: &[&str] = &["grinbox", "keybase", "http", "https", "email"];
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+const EXIT_TIMEOUT: Duration = Duration::from_secs(2);
+const SLEEP_DURATION: Duration = Duration::from_millis(1000);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+type Responses = Arc<Mutex<HashMap<u64, Sender<std::result::Result<Value, String>>>>>;
+/// Sender address and slate of a received message
+type Incoming = Arc<Mutex<Option<Sender<(String, Value)>>>>;
+
+/// A running adapter process
+struct ExecProcess {
+	child: Child,
+	stdin: Option<ChildStdin>,
+	next_id: u64,
+	responses: Responses,
+	alive: Arc<AtomicBool>,
+	address: Option<String>,
+}
+
+impl ExecProcess {
+	fn is_alive(&self) -> bool {
+		self.alive.load(Ordering::SeqCst)
+	}
+
+	/// Write a request, returning where its response arrives
+	fn send(
+		&mut self,
+		method: &str,
+		params: Value,
+	) -> Result<Receiver<std::result::Result<Value, String>>> {
+		self.next_id += 1;
+		let id = self.next_id;
+		let (sender, receiver) = channel();
+		self.responses.lock().insert(id, sender);
+		let request = json!({"id": id, "method": method, "params": params});
+		let stdin = self
+			.stdin
+			.as_mut()
+			.ok_or(ErrorKind::ClosedListener("adapter".to_owned()))?;
+		writeln!(stdin, "{}", request)?;
+		stdin.flush()?;
+		Ok(receiver)
+	}
+}
+
+impl Drop for ExecProcess {
+	fn drop(&mut self) {
+		// Closing stdin asks the adapter to exit
+		self.stdin.take();
+		let start = Instant::now();
+		while start.elapsed() < EXIT_TIMEOUT {
+			if let Ok(Some(_)) = self.child.try_wait() {
+				return;
+			}
+			std::thread::sleep(Duration::from_millis(50));
+		}
+		let _ = self.child.kill();
+		let _ = self.child.wait();
+	}
+}
+
+/// Transport run by an external executable. The process is started on
+/// first use and shared by all clones, so sends and the listener go
+/// through the same one
+#[derive(Clone)]
+pub struct ExecAdapter {
+	name: String,
+	config: ExecAdapterConfig,
+	process: Arc<Mutex<Option<ExecProcess>>>,
+	incoming: Incoming,
+	stop_signal: Arc<Mutex<bool>>,
+	status: SharedConnectionStatus,
+}
+
+impl ExecAdapter {
+	/// Adapter for addresses with `name` as scheme
+	pub fn new(name: &str, config: ExecAdapterConfig) -> Result<Self> {
+		let re = Regex::new(NAME_REGEX).unwrap();
+		if !re.is_match(name) || RESERVED_NAMES.contains(&name) {
+			Err(ErrorKind::ExecAdapter(
+				name.to_owned(),
+				"not a valid adapter name".to_owned(),
+			))?;
+		}
+		Ok(Self {
+			name: name.to_owned(),
+			config,
+			process: Arc::new(Mutex::new(None)),
+			incoming: Arc::new(Mutex::new(None)),
+			stop_signal: Arc::new(Mutex::new(true)),
+			status: SharedConnectionStatus::new(),
+		})
+	}
+
+	/// Adapters of the `[adapter.<name>]` tables of the config. Invalid ones
+	/// are skipped with a warning
+	pub fn from_config(adapters: HashMap<String, ExecAdapterConfig>) -> HashMap<String, Self> {
+		adapters
+			.into_iter()
+			.filter_map(|(name, config)| match ExecAdapter::new(&name, config) {
+				Ok(adapter) => Some((name, adapter)),
+				Err(e) => {
+					warn!("{}", e);
+					None
+				}
+			})
+			.collect()
+	}
+
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// Start the adapter if it isn't running. Returns the address it reported
+	pub fn connect(&self) -> Result<Option<String>> {
+		let mut guard = self.process.lock();
+		if guard.as_ref().map(|p| !p.is_alive()).unwrap_or(true) {
+			*guard = Some(self.spawn()?);
+		}
+		Ok(guard.as_ref().and_then(|p| p.address.clone()))
+	}
+
+	/// Address the running adapter reported, if any
+	pub fn address(&self) -> Option<String> {
+		self.process
+			.lock()
+			.as_ref()
+			.filter(|p| p.is_alive())
+			.and_then(|p| p.address.clone())
+	}
+
+	fn error(&self, reason: &str) -> ErrorKind {
+		ErrorKind::ExecAdapter(self.name.clone(), reason.to_owned())
+	}
+
+	fn spawn(&self) -> Result<ExecProcess> {
+		let mut child = Command::new(&self.config.command)
+			.args(&self.config.args)
+			.stdin(Stdio::piped())
+			.stdout(Stdio::piped())
+			.stderr(Stdio::piped())
+			.spawn()
+			.map_err(|e| self.error(&format!("unable to run {}: {}", self.config.command, e)))?;
+
+		let responses: Responses = Arc::new(Mutex::new(HashMap::new()));
+		let alive = Arc::new(AtomicBool::new(true));
+		let stdout = child.stdout.take().unwrap();
+		let (name, r, i, a) = (
+			self.name.clone(),
+			responses.clone(),
+			self.incoming.clone(),
+			alive.clone(),
+		);
+		spawn(move || read_output(name, stdout, r, i, a));
+		let stderr = child.stderr.take().unwrap();
+		let name = self.name.clone();
+		spawn(move || {
+			for line in BufReader::new(stderr).lines() {
+				match line {
+					Ok(line) => debug!("adapter {}: {}", name, line),
+					Err(_) => break,
+				}
+			}
+		});
+
+		let mut process = ExecProcess {
+			stdin: child.stdin.take(),
+			child,
+			next_id: 0,
+			responses,
+			alive,
+			address: None,
+		};
+		let params = json!({"name": self.name, "version": EXEC_PROTOCOL_VERSION});
+		let receiver = process.send("init", params)?;
+		let result = self.response(receiver)?;
+		process.address = result["address"].as_str().map(|a| a.to_owned());
+		Ok(process)
+	}
+
+	fn response(&self, receiver: Receiver<std::result::Result<Value, String>>) -> Result<Value> {
+		match receiver.recv_timeout(REQUEST_TIMEOUT) {
+			Ok(Ok(result)) => Ok(result),
+			Ok(Err(e)) => Err(self.error(&e))?,
+			Err(RecvTimeoutError::Timeout) => Err(self.error("no response"))?,
+			Err(RecvTimeoutError::Disconnected) => Err(self.error("exited"))?,
+		}
+	}
+
+	/// Send a request, starting the adapter first if needed
+	fn request(&self, method: &str, params: Value) -> Result<Value> {
+		let receiver = {
+			let mut guard = self.process.lock();
+			if guard.as_ref().map(|p| !p.is_alive()).unwrap_or(true) {
+				*guard = Some(self.spawn()?);
+			}
+			guard.as_mut().unwrap().send(method, params)?
+		};
+		self.response(receiver)
+	}
+
+	/// Ask the adapter to pass on the slates it receives
+	fn listen(&self) -> Result<Receiver<(String, Value)>> {
+		let (sender, receiver) = channel();
+		*self.incoming.lock() = Some(sender);
+		if let Err(e) = self.request("listen", json!({})) {
+			*self.incoming.lock() = None;
+			return Err(e);
+		}
+		Ok(receiver)
+	}
+
+	/// Hand the received slates to the handler until stopped or the adapter exits
+	fn receive<W, C, K, P>(
+		&self,
+		handler: &Controller<W, C, K, P>,
+		receiver: &Receiver<(String, Value)>,
+	) where
+		W: WalletBackend<C, K>,
+		C: NodeClient,
+		K: Keychain,
+		P: Publisher,
+	{
+		while !*self.stop_signal.lock() {
+			let (from, slate) = match receiver.recv_timeout(SLEEP_DURATION) {
+				Ok(message) => message,
+				Err(RecvTimeoutError::Timeout) => continue,
+				Err(RecvTimeoutError::Disconnected) => return,
+			};
+			let address = match ExecAddress::from_str(&from) {
+				Ok(address) if address.scheme == self.name => address,
+				_ => {
+					warn!("adapter {}: skipping slate from {}", self.name, from);
+					continue;
+				}
+			};
+			match serde_json::from_value::<VersionedSlate>(slate) {
+				Ok(slate) => handler.on_slate(&address, &slate, None),
+				Err(_) => warn!(
+					"adapter {}: message from {} is not a slate",
+					self.name, from
+				),
+			}
+		}
+	}
+
+	fn wait(&self, duration: Duration) {
+		let start = Instant::now();
+		while !*self.stop_signal.lock() && start.elapsed() < duration {
+			std::thread::sleep(SLEEP_DURATION);
+		}
+	}
+}
+
+/// Pass responses on to the requests waiting for them and received slates
+/// on to the listener, until the adapter closes its stdout
+fn read_output(
+	name: String,
+	stdout: ChildStdout,
+	responses: Responses,
+	incoming: Incoming,
+	alive: Arc<AtomicBool>,
+) {
+	for line in BufReader::new(stdout).lines() {
+		let line = match line {
+			Ok(line) => line,
+			Err(_) => break,
+		};
+		if line.trim().is_empty() {
+			continue;
+		}
+		let message: Value = match serde_json::from_str(&line) {
+			Ok(message) => message,
+			Err(_) => {
+				warn!("adapter {}: ignoring output {}", name, line);
+				continue;
+			}
+		};
+
+		if let Some(id) = message["id"].as_u64() {
+			if let Some(sender) = responses.lock().remove(&id) {
+				let error = &message["error"];
+				let result = if error.is_null() {
+					Ok(message["result"].clone())
+				} else {
+					Err(error
+						.as_str()
+						.map(|e| e.to_owned())
+						.unwrap_or_else(|| error.to_string()))
+				};
+				let _ = sender.send(result);
+			}
+		} else if message["method"] == "slate" {
+			let params = &message["params"];
+			let from = params["from"].as_str().unwrap_or("").to_owned();
+			match incoming.lock().as_ref() {
+				Some(sender) => {
+					let _ = sender.send((from, params["slate"].clone()));
+				}
+				None => warn!(
+					"adapter {}: not listening, dropping slate from {}",
+					name, from
+				),
+			}
+		}
+	}
+
+	alive.store(false, Ordering::SeqCst);
+	// Fail the requests still waiting and end the listener's receive loop
+	responses.lock().clear();
+	incoming.lock().take();
+	debug!("adapter {} exited", name);
+}
+
+impl Publisher for ExecAdapter {
+	fn post_slate(&self, slate: &VersionedSlate, to: &dyn Address) -> Result<()> {
+		let to = ExecAddress::from_str(&to.to_string())?;
+		if to.scheme != self.name {
+			Err(self.error(&format!("can't send to {}", to)))?;
+		}
+		self.request("post_slate", json!({"to": to.to_string(), "slate": slate}))?;
+		Ok(())
+	}
+}
+
+impl Subscriber for ExecAdapter {
+	fn start<W, C, K, P>(&mut self, handler: Controller<W, C, K, P>) -> Result<()>
+	where
+		W: WalletBackend<C, K>,
+		C: NodeClient,
+		K: Keychain,
+		P: Publisher,
+	{
+		{
+			let mut guard = self.stop_signal.lock();
+			*guard = false;
+		}
+
+		let mut subscribed = false;
+		let mut dropped = false;
+		let result: Result<()> = loop {
+			if *self.stop_signal.lock() {
+				break Ok(());
+			}
+			match self.listen() {
+				Ok(receiver) => {
+					self.status.connected(&self.name);
+					if !subscribed {
+						subscribed = true;
+						handler.on_open();
+					}
+					if dropped {
+						dropped = false;
+						handler.on_reestablished();
+					}
+					self.receive(&handler, &receiver);
+					if *self.stop_signal.lock() {
+						break Ok(());
+					}
+					self.status.error(&self.name, "exited".to_owned());
+				}
+				Err(e) => {
+					self.status.error(&self.name, e.to_string());
+					if !subscribed {
+						break Err(e);
+					}
+				}
+			}
+			self.status.disconnected(&self.name);
+			self.status.reconnecting(&self.name);
+			if !dropped {
+				dropped = true;
+				handler.on_dropped();
+			}
+			self.wait(RECONNECT_DELAY);
+		};
+		if self.address().is_some() {
+			let _ = self.request("unlisten", json!({}));
+		}
+		self.incoming.lock().take();
+		self.status.disconnected(&self.name);
+		match result {
+			Err(e) => handler.on_close(CloseReason::Abnormal(e)),
+			_ => handler.on_close(CloseReason::Normal),
+		}
+		Ok(())
+	}
+
+	fn stop(&self) {
+		let mut guard = self.stop_signal.lock();
+		*guard = true;
+	}
+
+	fn is_running(&self) -> bool {
+		let guard = self.stop_signal.lock();
+		!*guard
+	}
+
+	fn status(&self) -> ConnectionStatus {
+		self.status.get()
+	}
+}
+
+impl Adapter for ExecAdapter {
+	fn supports_sync(&self) -> bool {
+		false
+	}
+
+	fn send_tx_sync(&self, _dest: &str, _slate: &VersionedSlate) -> Result<VersionedSlate> {
+		unimplemented!();
+	}
+
+	fn send_tx_async(&self, dest: &str, slate: &VersionedSlate) -> Result<()> {
+		let to = ExecAddress::from_str(dest)?;
+		self.post_slate(slate, &to)
+	}
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+	use super::*;
+	use crate::wallet::testing::{create_wallet, LoopbackNetwork, MockChain};
+	use crate::wallet::types::{Slate, SlateVersion};
+	use std::env;
+	use std::fs;
+	use std::os::unix::fs::PermissionsExt;
+	use std::path::PathBuf;
+	use std::thread;
+	use uuid::Uuid;
+
+	/// Stand-in adapter logging every request. Sends to `@nobody` fail and
+	/// `incoming.json` is passed on once listening
+	const MOCK_ADAPTER: &str = r#"#!/bin/sh
+dir=$(dirname "$0")
+while IFS= read -r line; do
+	echo "$line" >> "$dir/requests"
+	id=$(printf '%s' "$line" | sed -n 's/^{"id":\([0-9]*\).*/\1/p')
+	case "$line" in
+	*'"method":"init"'*) echo "{\"id\":$id,\"result\":{\"address\":\"matrix://@bob:matrix.org\"}}" ;;
+	*'"to":"matrix://@nobody'*) echo "{\"id\":$id,\"error\":\"unknown user\"}" ;;
+	*'"method":"listen"'*)
+		echo "{\"id\":$id,\"result\":null}"
+		cat "$dir/incoming.json" 2>/dev/null
+		;;
+	*) echo "{\"id\":$id,\"result\":null}" ;;
+	esac
+done
+"#;
+
+	struct MockAdapter {
+		dir: PathBuf,
+	}
+
+	impl MockAdapter {
+		fn new() -> Self {
+			let dir = env::temp_dir().join(format!("wallet713-adapter-{}", Uuid::new_v4()));
+			fs::create_dir_all(&dir).unwrap();
+			let command = dir.join("adapter");
+			fs::write(&command, MOCK_ADAPTER).unwrap();
+			fs::set_permissions(&command, fs::Permissions::from_mode(0o755)).unwrap();
+			Self { dir }
+		}
+
+		fn adapter(&self) -> ExecAdapter {
+			let config = ExecAdapterConfig {
+				command: self.dir.join("adapter").to_string_lossy().into_owned(),
+				args: vec![],
+			};
+			ExecAdapter::new("matrix", config).unwrap()
+		}
+
+		fn deliver(&self, from: &str, slate: &VersionedSlate) {
+			let message = json!({"method": "slate", "params": {"from": from, "slate": slate}});
+			fs::write(self.dir.join("incoming.json"), format!("{}\n", message)).unwrap();
+		}
+
+		fn requests(&self, method: &str) -> Vec<Value> {
+			fs::read_to_string(self.dir.join("requests"))
+				.unwrap_or_default()
+				.lines()
+				.filter_map(|l| serde_json::from_str::<Value>(l).ok())
+				.filter(|r| r["method"] == method)
+				.collect()
+		}
+	}
+
+	fn slate() -> VersionedSlate {
+		VersionedSlate::into_version(Slate::blank(2, Uuid::new_v4()), SlateVersion::V2)
+	}
+
+	#[test]
+	fn rejects_names_of_built_in_transports() {
+		let config = ExecAdapterConfig {
+			command: String::from("true"),
+			args: vec![],
+		};
+		assert!(ExecAdapter::new("keybase", config.clone()).is_err());
+		assert!(ExecAdapter::new("Matrix", config.clone()).is_err());
+		assert!(ExecAdapter::new("matrix", config).is_ok());
+	}
+
+	#[test]
+	fn posts_slates_through_one_process() {
+		let mock = MockAdapter::new();
+		let adapter = mock.adapter();
+		assert_eq!(
+			adapter.connect().unwrap(),
+			Some(String::from("matrix://@bob:matrix.org"))
+		);
+
+		adapter
+			.send_tx_async("matrix://@alice:matrix.org", &slate())
+			.unwrap();
+		let error = adapter
+			.send_tx_async("matrix://@nobody:matrix.org", &slate())
+			.unwrap_err();
+		assert!(error.to_string().contains("unknown user"));
+		assert!(adapter.send_tx_async("keybase://alice", &slate()).is_err());
+
+		let posts = mock.requests("post_slate");
+		assert_eq!(posts.len(), 2);
+		assert_eq!(posts[0]["params"]["to"], "matrix://@alice:matrix.org");
+		assert_eq!(mock.requests("init").len(), 1);
+	}
+
+	#[test]
+	fn answers_slates_received_by_the_adapter() {
+		let mock = MockAdapter::new();
+		let container =
+			create_wallet("exec-receive", &MockChain::new(), &LoopbackNetwork::new()).unwrap();
+		let adapter = mock.adapter();
+		mock.deliver("matrix://@alice:matrix.org", &slate());
+
+		let (events, runtime) = {
+			let c = container.lock();
+			(c.events.clone(), c.runtime.clone())
+		};
+		let controller =
+			Controller::new("matrix", container, adapter.clone(), events, runtime).unwrap();
+		let mut subscriber = adapter.clone();
+		let handle = thread::spawn(move || subscriber.start(controller).unwrap());
+
+		let start = Instant::now();
+		while mock.requests("post_slate").is_empty() && start.elapsed() < REQUEST_TIMEOUT {
+			thread::sleep(Duration::from_millis(100));
+		}
+		adapter.stop();
+		handle.join().unwrap();
+
+		let posts = mock.requests("post_slate");
+		assert_eq!(posts.len(), 1);
+		assert_eq!(posts[0]["params"]["to"], "matrix://@alice:matrix.org");
+		assert_eq!(mock.requests("unlisten").len(), 1);
+	}
+}
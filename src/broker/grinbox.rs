@@ -13,15 +13,21 @@
 // limitations under the License.
 
 use super::protocol::{ProtocolRequest, ProtocolResponse};
-use super::types::{CloseReason, Controller, Publisher, Subscriber, SubscriptionHandler};
+use super::types::{
+	CloseReason, ConnectionStatus, Controller, Publisher, SharedConnectionStatus, Subscriber,
+	SubscriptionHandler,
+};
 use crate::cli_message;
+use crate::common::clock::SharedClock;
 use crate::common::crypto::{sign_challenge, Hex, SecretKey};
 use crate::common::message::EncryptedMessage;
-use crate::common::{Arc, ErrorKind, Keychain, Mutex, Result};
-use crate::contacts::{Address, GrinboxAddress, DEFAULT_GRINBOX_PORT};
+use crate::common::metrics::GRINBOX_RECONNECTS;
+use crate::common::{Arc, ErrorKind, Keychain, Mutex, Result, RuntimeContext};
+use crate::contacts::{Address, GrinboxAddress, GrinboxRelay, SharedRelays};
 use crate::wallet::types::{NodeClient, TxProof, VersionedSlate, WalletBackend};
 use colored::Colorize;
 use log::error;
+use std::collections::HashSet;
 use ws::util::Token;
 use ws::{
 	connect, CloseCode, Error as WsError, ErrorKind as WsErrorKind, Handler, Handshake, Message,
@@ -30,12 +36,15 @@ use ws::{
 
 const KEEPALIVE_TOKEN: Token = Token(1);
 const KEEPALIVE_INTERVAL_MS: u64 = 30_000;
+const POST_TIMEOUT_TOKEN: Token = Token(2);
+const POST_TIMEOUT_MS: u64 = 30_000;
 
 #[derive(Clone)]
 pub struct GrinboxPublisher {
 	address: GrinboxAddress,
 	broker: GrinboxBroker,
 	secret_key: SecretKey,
+	relays: SharedRelays,
 }
 
 impl GrinboxPublisher {
@@ -43,20 +52,36 @@ impl GrinboxPublisher {
 		address: &GrinboxAddress,
 		secret_key: &SecretKey,
 		protocol_unsecure: bool,
+		clock: SharedClock,
+		relays: SharedRelays,
 	) -> Result<Self> {
 		Ok(Self {
 			address: address.clone(),
-			broker: GrinboxBroker::new(protocol_unsecure)?,
+			broker: GrinboxBroker::new(protocol_unsecure, clock)?,
 			secret_key: secret_key.clone(),
+			relays,
 		})
 	}
+
+	/// Relay to post to: the one set on the contact, or else the one in the
+	/// address, over the same protocol as our own relay
+	fn relay(&self, to: &GrinboxAddress) -> GrinboxRelay {
+		match self.relays.lock().get(&to.public_key) {
+			Some(relay) => relay.clone(),
+			None => GrinboxRelay::of_address(to, !self.broker.protocol_unsecure),
+		}
+	}
 }
 
 impl Publisher for GrinboxPublisher {
 	fn post_slate(&self, slate: &VersionedSlate, to: &dyn Address) -> Result<()> {
-		let to = GrinboxAddress::from_str(&to.to_string())?;
+		let mut to = GrinboxAddress::from_str(&to.to_string())?;
+		let relay = self.relay(&to);
+		// The recipient is addressed on the relay we post to
+		to.domain = relay.domain.clone();
+		to.port = Some(relay.port);
 		self.broker
-			.post_slate(slate, &to, &self.address, &self.secret_key)?;
+			.post_slate(slate, &to, &relay, &self.address, &self.secret_key)?;
 		Ok(())
 	}
 }
@@ -98,12 +123,21 @@ impl Subscriber for GrinboxSubscriber {
 	fn is_running(&self) -> bool {
 		self.broker.is_running()
 	}
+
+	fn status(&self) -> ConnectionStatus {
+		self.broker.status.get()
+	}
 }
 
 #[derive(Clone)]
 struct GrinboxBroker {
 	inner: Arc<Mutex<Option<Sender>>>,
 	protocol_unsecure: bool,
+	status: SharedConnectionStatus,
+	clock: SharedClock,
+	/// Public keys of the peers we received messages from that read
+	/// compressed payloads. Others are sent uncompressed ones
+	compressing_peers: Arc<Mutex<HashSet<String>>>,
 }
 
 struct ConnectionMetadata {
@@ -121,10 +155,13 @@ impl ConnectionMetadata {
 }
 
 impl GrinboxBroker {
-	fn new(protocol_unsecure: bool) -> Result<Self> {
+	fn new(protocol_unsecure: bool, clock: SharedClock) -> Result<Self> {
 		Ok(Self {
 			inner: Arc::new(Mutex::new(None)),
 			protocol_unsecure,
+			status: SharedConnectionStatus::new(),
+			clock,
+			compressing_peers: Arc::new(Mutex::new(HashSet::new())),
 		})
 	}
 
@@ -132,16 +169,15 @@ impl GrinboxBroker {
 		&self,
 		slate: &VersionedSlate,
 		to: &GrinboxAddress,
+		relay: &GrinboxRelay,
 		from: &GrinboxAddress,
 		secret_key: &SecretKey,
 	) -> Result<()> {
-		if !self.is_running() {
-			return Err(ErrorKind::ClosedListener("grinbox".to_string()).into());
-		}
-
 		let pkey = to.public_key()?;
 		let skey = secret_key.clone();
-		let message = EncryptedMessage::new(serde_json::to_string(&slate)?, &to, &pkey, &skey)
+		let compress = self.compressing_peers.lock().contains(&to.public_key);
+		let slate = serde_json::to_string(&slate)?;
+		let message = EncryptedMessage::new(slate, &to, &pkey, &skey, compress)
 			.map_err(|_| WsError::new(WsErrorKind::Protocol, "could not encrypt slate!"))?;
 		let message_ser = serde_json::to_string(&message)?;
 
@@ -156,6 +192,15 @@ impl GrinboxBroker {
 			signature,
 		};
 
+		let own_relay = GrinboxRelay::of_address(from, !self.protocol_unsecure);
+		if !relay.same_server(&own_relay) {
+			return post_to_relay(relay, &request);
+		}
+
+		if !self.is_running() {
+			return Err(ErrorKind::ClosedListener("grinbox".to_string()).into());
+		}
+
 		if let Some(ref sender) = *self.inner.lock() {
 			sender
 				.send(serde_json::to_string(&request).unwrap())
@@ -177,22 +222,9 @@ impl GrinboxBroker {
 		K: Keychain,
 		P: Publisher,
 	{
+		let runtime = handler.runtime().clone();
 		let handler = Arc::new(Mutex::new(handler));
-		let url = {
-			let cloned_address = address.clone();
-			match self.protocol_unsecure {
-				true => format!(
-					"ws://{}:{}",
-					cloned_address.domain,
-					cloned_address.port.unwrap_or(DEFAULT_GRINBOX_PORT)
-				),
-				false => format!(
-					"wss://{}:{}",
-					cloned_address.domain,
-					cloned_address.port.unwrap_or(DEFAULT_GRINBOX_PORT)
-				),
-			}
-		};
+		let url = GrinboxRelay::of_address(address, !self.protocol_unsecure).to_string();
 		let cloned_address = address.clone();
 		let cloned_inner = self.inner.clone();
 		let cloned_handler = handler.clone();
@@ -215,10 +247,17 @@ impl GrinboxBroker {
 					address: cloned_address.clone(),
 					secret_key: secret_key.clone(),
 					connection_meta_data: cloned_connection_meta_data.clone(),
+					status: self.status.clone(),
+					runtime: runtime.clone(),
+					compressing_peers: self.compressing_peers.clone(),
 				};
 				client
 			});
 
+			self.status.disconnected("grinbox");
+			if let Err(e) = &result {
+				self.status.error("grinbox", e.to_string());
+			}
 			let is_stopped = cloned_inner.lock().is_none();
 
 			if is_stopped {
@@ -236,8 +275,10 @@ impl GrinboxBroker {
 				}
 				let secs = std::cmp::min(32, 2u64.pow(guard.retries));
 				let duration = std::time::Duration::from_secs(secs);
-				std::thread::sleep(duration);
+				self.clock.sleep(duration);
 				guard.retries += 1;
+				self.status.reconnecting("grinbox");
+				handler.lock().metrics().lock().inc(GRINBOX_RECONNECTS, &[]);
 			}
 		}
 		let mut guard = cloned_inner.lock();
@@ -259,6 +300,63 @@ impl GrinboxBroker {
 	}
 }
 
+/// Post a slate through a short lived connection to another relay than ours,
+/// waiting for it to be accepted
+fn post_to_relay(relay: &GrinboxRelay, request: &ProtocolRequest) -> Result<()> {
+	let request = serde_json::to_string(request)?;
+	let result = Arc::new(Mutex::new(None));
+	let cresult = result.clone();
+	connect(relay.to_string(), |sender| RelayPost {
+		sender,
+		request: request.clone(),
+		result: cresult.clone(),
+	})
+	.map_err(|e| ErrorKind::GenericError(format!("failed posting slate to {}: {}", relay, e)))?;
+
+	let result = result.lock().take();
+	match result {
+		Some(Ok(())) => Ok(()),
+		Some(Err(e)) => Err(ErrorKind::GenericError(format!(
+			"{} refused slate: {}",
+			relay, e
+		)))?,
+		None => Err(ErrorKind::GenericError(format!("no answer from {}", relay)))?,
+	}
+}
+
+struct RelayPost {
+	sender: Sender,
+	request: String,
+	result: Arc<Mutex<Option<std::result::Result<(), String>>>>,
+}
+
+impl RelayPost {
+	fn finish(&mut self, result: std::result::Result<(), String>) -> WsResult<()> {
+		*self.result.lock() = Some(result);
+		self.sender.close(CloseCode::Normal)
+	}
+}
+
+impl Handler for RelayPost {
+	fn on_open(&mut self, _shake: Handshake) -> WsResult<()> {
+		self.sender.timeout(POST_TIMEOUT_MS, POST_TIMEOUT_TOKEN)?;
+		self.sender.send(self.request.clone())
+	}
+
+	fn on_timeout(&mut self, _event: Token) -> WsResult<()> {
+		self.sender.close(CloseCode::Normal)
+	}
+
+	fn on_message(&mut self, msg: Message) -> WsResult<()> {
+		match serde_json::from_str::<ProtocolResponse>(&msg.to_string()) {
+			Ok(ProtocolResponse::Ok) => self.finish(Ok(())),
+			Ok(response @ ProtocolResponse::Error { .. }) => self.finish(Err(response.to_string())),
+			// Relays greet new connections with a challenge, only needed to subscribe
+			_ => Ok(()),
+		}
+	}
+}
+
 struct GrinboxClient<W, C, K, P>
 where
 	W: WalletBackend<C, K>,
@@ -272,6 +370,9 @@ where
 	address: GrinboxAddress,
 	secret_key: SecretKey,
 	connection_meta_data: Arc<Mutex<ConnectionMetadata>>,
+	status: SharedConnectionStatus,
+	runtime: RuntimeContext,
+	compressing_peers: Arc<Mutex<HashSet<String>>>,
 }
 
 impl<W, C, K, P> GrinboxClient<W, C, K, P>
@@ -317,6 +418,7 @@ where
 		}
 
 		guard.retries = 0;
+		self.status.connected("grinbox");
 
 		self.sender
 			.timeout(KEEPALIVE_INTERVAL_MS, KEEPALIVE_TOKEN)?;
@@ -340,7 +442,11 @@ where
 		let response = match serde_json::from_str::<ProtocolResponse>(&msg.to_string()) {
 			Ok(x) => x,
 			Err(_) => {
-				cli_message!("{} Could not parse response", "ERROR:".bright_red());
+				cli_message!(
+					self.runtime,
+					"{} Could not parse response",
+					"ERROR:".bright_red()
+				);
 				return Ok(());
 			}
 		};
@@ -358,6 +464,9 @@ where
 				challenge,
 				signature,
 			} => {
+				let supports_compression = serde_json::from_str::<EncryptedMessage>(&str)
+					.map(|m| m.supports_compression())
+					.unwrap_or(false);
 				let (slate, mut tx_proof) = match TxProof::from_response(
 					from,
 					str,
@@ -368,12 +477,16 @@ where
 				) {
 					Ok(x) => x,
 					Err(e) => {
-						cli_message!("{} {}", "ERROR:".bright_red(), e);
+						cli_message!(self.runtime, "{} {}", "ERROR:".bright_red(), e);
 						return Ok(());
 					}
 				};
 
 				let address = tx_proof.address.clone();
+				if supports_compression {
+					let mut peers = self.compressing_peers.lock();
+					peers.insert(address.public_key.clone());
+				}
 				self.handler
 					.lock()
 					.on_slate(&address, &slate, Some(&mut tx_proof));
@@ -382,7 +495,8 @@ where
 				kind: _,
 				description: _,
 			} => {
-				cli_message!("{} {}", "ERROR:".bright_red(), response);
+				self.status.error("grinbox", response.to_string());
+				cli_message!(self.runtime, "{} {}", "ERROR:".bright_red(), response);
 			}
 			_ => {}
 		}
@@ -398,5 +512,166 @@ where
 		}
 
 		error!("{:?}", err);
+		self.status.error("grinbox", err.to_string());
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::broker::protocol::ProtocolError;
+	use crate::common::clock::SystemClock;
+	use crate::common::crypto::{PublicKey, Secp256k1};
+	use crate::contacts::DEFAULT_GRINBOX_PORT;
+	use crate::wallet::types::{Slate, SlateVersion};
+	use rand::thread_rng;
+	use std::collections::HashMap;
+	use std::sync::mpsc::{channel, Receiver, Sender as ChannelSender};
+	use std::thread;
+	use uuid::Uuid;
+	use ws::WebSocket;
+
+	/// Relay answering every request with `response`, and passing the
+	/// requests on to the test
+	struct MockRelay {
+		sender: Sender,
+		requests: ChannelSender<ProtocolRequest>,
+		response: String,
+	}
+
+	impl Handler for MockRelay {
+		fn on_message(&mut self, msg: Message) -> WsResult<()> {
+			let request = serde_json::from_str(&msg.to_string()).unwrap();
+			let _ = self.requests.send(request);
+			self.sender.send(self.response.clone())
+		}
+	}
+
+	fn mock_relay(response: ProtocolResponse) -> (u16, Receiver<ProtocolRequest>) {
+		let (requests, received) = channel();
+		let response = serde_json::to_string(&response).unwrap();
+		let socket = WebSocket::new(move |sender| MockRelay {
+			sender,
+			requests: requests.clone(),
+			response: response.clone(),
+		})
+		.unwrap()
+		.bind("127.0.0.1:0")
+		.unwrap();
+		let port = socket.local_addr().unwrap().port();
+		thread::spawn(move || socket.run());
+		(port, received)
+	}
+
+	fn address(domain: &str, port: u16) -> (GrinboxAddress, SecretKey) {
+		let secp = Secp256k1::new();
+		let secret_key = SecretKey::new(&secp, &mut thread_rng());
+		let public_key = PublicKey::from_secret_key(&secp, &secret_key).unwrap();
+		let address = GrinboxAddress::new(public_key, Some(domain.to_owned()), Some(port));
+		(address, secret_key)
+	}
+
+	fn publisher(
+		protocol_unsecure: bool,
+		relays: HashMap<String, GrinboxRelay>,
+	) -> GrinboxPublisher {
+		let (address, secret_key) = address("grinbox.io", 443);
+		GrinboxPublisher::new(
+			&address,
+			&secret_key,
+			protocol_unsecure,
+			SystemClock::shared(),
+			Arc::new(Mutex::new(relays)),
+		)
+		.unwrap()
+	}
+
+	fn slate() -> VersionedSlate {
+		VersionedSlate::into_version(Slate::blank(2, Uuid::new_v4()), SlateVersion::V2)
+	}
+
+	fn posted_to(requests: &Receiver<ProtocolRequest>) -> String {
+		match requests.try_recv().unwrap() {
+			ProtocolRequest::PostSlate { to, .. } => to,
+			request => panic!("unexpected request {}", request),
+		}
+	}
+
+	#[test]
+	fn posts_to_the_relay_of_the_recipient() {
+		let (port, requests) = mock_relay(ProtocolResponse::Ok);
+		let (to, _) = address("127.0.0.1", port);
+
+		publisher(true, HashMap::new())
+			.post_slate(&slate(), &to)
+			.unwrap();
+		assert_eq!(posted_to(&requests), to.stripped());
+	}
+
+	#[test]
+	fn posts_to_the_relay_of_the_contact() {
+		let (port, requests) = mock_relay(ProtocolResponse::Ok);
+		let (to, _) = address("grinbox.io", 443);
+		let mut relays = HashMap::new();
+		let relay = GrinboxRelay::from_str(&format!("ws://127.0.0.1:{}", port)).unwrap();
+		relays.insert(to.public_key.clone(), relay);
+
+		publisher(false, relays).post_slate(&slate(), &to).unwrap();
+		assert_eq!(
+			posted_to(&requests),
+			format!("{}@127.0.0.1:{}", to.public_key, port)
+		);
+	}
+
+	#[test]
+	fn compresses_slates_to_peers_reading_them() {
+		let (port, requests) = mock_relay(ProtocolResponse::Ok);
+		let (to, to_key) = address("127.0.0.1", port);
+		let publisher = publisher(true, HashMap::new());
+		let posted_message = || match requests.try_recv().unwrap() {
+			ProtocolRequest::PostSlate { str, .. } => {
+				serde_json::from_str::<EncryptedMessage>(&str).unwrap()
+			}
+			request => panic!("unexpected request {}", request),
+		};
+
+		publisher.post_slate(&slate(), &to).unwrap();
+		let message = posted_message();
+		assert!(message.supports_compression());
+		assert!(!message.is_compressed());
+
+		let peers = publisher.broker.compressing_peers.clone();
+		peers.lock().insert(to.public_key.clone());
+		publisher.post_slate(&slate(), &to).unwrap();
+		let message = posted_message();
+		assert!(message.is_compressed());
+		let from = publisher.address.public_key().unwrap();
+		let key = message.key(&from, &to_key).unwrap();
+		let slate = message.decrypt_with_key(&key).unwrap();
+		serde_json::from_str::<VersionedSlate>(&slate).unwrap();
+	}
+
+	#[test]
+	fn reports_slates_refused_by_the_relay() {
+		let (port, requests) = mock_relay(ProtocolResponse::Error {
+			kind: ProtocolError::InvalidSignature,
+			description: String::from("invalid signature"),
+		});
+		let (to, _) = address("127.0.0.1", port);
+
+		assert!(publisher(true, HashMap::new())
+			.post_slate(&slate(), &to)
+			.is_err());
+		posted_to(&requests);
+	}
+
+	#[test]
+	fn parses_relays() {
+		let relay = GrinboxRelay::from_str("relay.example.com").unwrap();
+		let expected = format!("wss://relay.example.com:{}", DEFAULT_GRINBOX_PORT);
+		assert_eq!(relay.to_string(), expected);
+		let relay = GrinboxRelay::from_str("ws://127.0.0.1:13420").unwrap();
+		assert_eq!(relay.to_string(), "ws://127.0.0.1:13420");
+		assert!(GrinboxRelay::from_str("http://relay.example.com").is_err());
 	}
 }
@@ -12,10 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::types::{CloseReason, Controller, Publisher, Subscriber, SubscriptionHandler};
+use super::types::{
+	CloseReason, ConnectionStatus, Controller, Publisher, SharedConnectionStatus, Subscriber,
+	SubscriptionHandler,
+};
 use crate::common::{Arc, ErrorKind, Keychain, Mutex, Result};
 use crate::contacts::{Address, KeybaseAddress};
-use crate::wallet::types::{NodeClient, VersionedSlate, WalletBackend};
+use crate::wallet::types::{NodeClient, ReceivedMessage, Slate, VersionedSlate, WalletBackend};
+use log::{debug, warn};
+use regex::Regex;
 use serde::Serialize;
 use serde_json::{json, Value};
 use std::borrow::Borrow;
@@ -28,30 +33,149 @@ pub const TOPIC_SLATE_NEW: &str = "grin_slate_new";
 pub const TOPIC_WALLET713_SLATES: &str = "wallet713_grin_slate";
 const TOPIC_SLATE_SIGNED: &str = "grin_slate_signed";
 const SLEEP_DURATION: Duration = Duration::from_millis(5000);
+const METHOD: &str = "keybase";
+
+const TTL_REGEX: &str = r"^[0-9]+[smhd]$";
 
 #[derive(Clone)]
 pub struct KeybasePublisher {
+	broker: KeybaseBroker,
 	ttl: Option<String>,
+	team_ttl: Option<String>,
 }
 
 impl KeybasePublisher {
-	pub fn new(ttl: Option<String>) -> Result<Self> {
-		let _broker = KeybaseBroker::new()?;
-		Ok(Self { ttl })
+	/// Publisher exploding slates after `ttl` for users and `team_ttl` for
+	/// team channels, `None` to keep them
+	pub fn new(binary: &str, ttl: Option<String>, team_ttl: Option<String>) -> Result<Self> {
+		let broker = KeybaseBroker::new(binary)?;
+		let re = Regex::new(TTL_REGEX).unwrap();
+		for t in ttl.iter().chain(team_ttl.iter()) {
+			if !re.is_match(t) {
+				Err(ErrorKind::KeybaseTtl(t.clone()))?;
+			}
+		}
+		Ok(Self {
+			broker,
+			ttl,
+			team_ttl,
+		})
 	}
 }
 
 #[derive(Clone)]
 pub struct KeybaseSubscriber {
+	broker: KeybaseBroker,
 	stop_signal: Arc<Mutex<bool>>,
+	status: SharedConnectionStatus,
+	team_channels: Vec<KeybaseAddress>,
 }
 
 impl KeybaseSubscriber {
-	pub fn new() -> Result<Self> {
+	/// Subscriber for direct messages and the given team channels
+	pub fn new(binary: &str, team_channels: Vec<KeybaseAddress>) -> Result<Self> {
 		Ok(Self {
+			broker: KeybaseBroker {
+				binary: binary.to_owned(),
+			},
 			stop_signal: Arc::new(Mutex::new(true)),
+			status: SharedConnectionStatus::new(),
+			team_channels,
 		})
 	}
+
+	pub fn team_channels(&self) -> &[KeybaseAddress] {
+		&self.team_channels
+	}
+
+	/// Keybase username slates can be sent to
+	pub fn username(&self) -> Option<String> {
+		self.broker.username()
+	}
+
+	/// Read the unread slates and store them in the wallet before marking
+	/// them as read, so they survive a restart. Messages seen before and
+	/// slates already received are marked as read without being stored again
+	fn fetch<W, C, K, P>(&self, handler: &Controller<W, C, K, P>) -> Result<()>
+	where
+		W: WalletBackend<C, K>,
+		C: NodeClient,
+		K: Keychain,
+		P: Publisher,
+	{
+		let unread = self.broker.get_unread(HashSet::from_iter(vec![
+			TOPIC_WALLET713_SLATES,
+			TOPIC_SLATE_NEW,
+			TOPIC_SLATE_SIGNED,
+		]))?;
+		let team_unread = self.broker.get_team_unread(&self.team_channels)?;
+
+		let owner = handler.owner();
+		for (channel, message) in unread.iter().chain(team_unread.iter()) {
+			let address = match &channel.channel {
+				Some(_) => channel.clone(),
+				None => {
+					let reply_topic = match channel.topic.as_ref().map(String::as_str) {
+						Some(TOPIC_SLATE_NEW) => TOPIC_SLATE_SIGNED,
+						_ => TOPIC_WALLET713_SLATES,
+					};
+					KeybaseAddress {
+						username: message.sender.clone(),
+						topic: Some(reply_topic.to_owned()),
+						channel: None,
+					}
+				}
+			};
+
+			// Operators may chat in team channels, so only slates are picked up
+			match serde_json::from_str::<VersionedSlate>(&message.body) {
+				Ok(slate) => {
+					let slate: Slate = slate.into();
+					let received = ReceivedMessage::new(
+						message.id.clone(),
+						METHOD.to_owned(),
+						address.to_string(),
+						message.body.clone(),
+						&slate,
+						owner.clock().now(),
+					);
+					if let Err(e) = owner.record_received_message(&received) {
+						// Left unread, so it is picked up again on the next poll
+						warn!("keybase: could not store message {}: {}", message.id, e);
+						continue;
+					}
+				}
+				Err(_) if channel.is_team() => {
+					debug!("Skipping message from {} in {}", message.sender, channel)
+				}
+				Err(_) => warn!("keybase: message from {} is not a slate", message.sender),
+			}
+			self.broker.mark_read(channel, message.msg_id)?;
+		}
+		Ok(())
+	}
+
+	/// Hand the stored slates that were not processed yet to the handler,
+	/// oldest first, including the ones left over from before a restart
+	fn process_pending<W, C, K, P>(&self, handler: &Controller<W, C, K, P>) -> Result<()>
+	where
+		W: WalletBackend<C, K>,
+		C: NodeClient,
+		K: Keychain,
+		P: Publisher,
+	{
+		let owner = handler.owner();
+		for message in owner.pending_received_messages(METHOD)? {
+			let slate = serde_json::from_str::<VersionedSlate>(&message.body);
+			let address = KeybaseAddress::from_str(&message.address);
+			match (slate, address) {
+				(Ok(slate), Ok(address)) => handler.on_slate(address.borrow(), &slate, None),
+				_ => warn!("keybase: dropping unreadable message {}", message.id),
+			}
+			owner.mark_message_processed(METHOD, &message.id)?;
+		}
+		Ok(())
+	}
 }
 
 impl Publisher for KeybasePublisher {
@@ -60,16 +184,12 @@ impl Publisher for KeybasePublisher {
 
 		// make sure we don't send message with ttl to wallet713 as keybase oneshot does not support exploding lifetimes
 		let ttl = match keybase_address.username.as_ref() {
+			_ if keybase_address.is_team() => &self.team_ttl,
 			"wallet713" => &None,
 			_ => &self.ttl,
 		};
 
-		let topic = match &keybase_address.topic {
-			Some(t) => t,
-			None => TOPIC_WALLET713_SLATES,
-		};
-
-		KeybaseBroker::send(&slate, &to.stripped(), topic, ttl)?;
+		self.broker.send(&slate, &keybase_address, ttl)?;
 
 		Ok(())
 	}
@@ -94,12 +214,9 @@ impl Subscriber for KeybaseSubscriber {
 			if *self.stop_signal.lock() {
 				break Ok(());
 			};
-			let result = KeybaseBroker::get_unread(HashSet::from_iter(vec![
-				TOPIC_WALLET713_SLATES,
-				TOPIC_SLATE_NEW,
-				TOPIC_SLATE_SIGNED,
-			]));
-			if let Ok(unread) = result {
+			let result = self.fetch(&handler);
+			if result.is_ok() {
+				self.status.connected("keybase");
 				if !subscribed {
 					subscribed = true;
 					handler.on_open();
@@ -108,22 +225,17 @@ impl Subscriber for KeybaseSubscriber {
 					dropped = false;
 					handler.on_reestablished();
 				}
-				for (sender, topic, msg) in &unread {
-					let reply_topic = match topic.as_ref() {
-						TOPIC_SLATE_NEW => TOPIC_SLATE_SIGNED.to_string(),
-						_ => TOPIC_WALLET713_SLATES.to_string(),
-					};
-
-					let slate: VersionedSlate =
-						serde_json::from_str(&msg).map_err(|_| ErrorKind::ParseSlate)?;
-
-					let address = KeybaseAddress {
-						username: sender.to_string(),
-						topic: Some(reply_topic),
-					};
-					handler.on_slate(address.borrow(), &slate, None);
+				if let Err(e) = self.process_pending(&handler) {
+					self.status.error("keybase", e.to_string());
 				}
 			} else {
+				if let Err(e) = &result {
+					self.status.error("keybase", e.to_string());
+				}
+				self.status.disconnected("keybase");
+				if subscribed {
+					self.status.reconnecting("keybase");
+				}
 				if !dropped {
 					dropped = true;
 					if subscribed {
@@ -135,6 +247,7 @@ impl Subscriber for KeybaseSubscriber {
 			}
 			std::thread::sleep(SLEEP_DURATION);
 		};
+		self.status.disconnected("keybase");
 		match result {
 			Err(e) => handler.on_close(CloseReason::Abnormal(e)),
 			_ => handler.on_close(CloseReason::Normal),
@@ -151,62 +264,127 @@ impl Subscriber for KeybaseSubscriber {
 		let guard = self.stop_signal.lock();
 		!*guard
 	}
+
+	fn status(&self) -> ConnectionStatus {
+		self.status.get()
+	}
+}
+
+/// An unread chat message
+struct KeybaseMessage {
+	/// Conversation and message id, unique across channels
+	id: String,
+	/// Message id within the conversation, to mark it as read
+	msg_id: u64,
+	sender: String,
+	body: String,
 }
 
-struct KeybaseBroker {}
+/// Runs `keybase chat api` commands through the client at `binary`
+#[derive(Clone)]
+struct KeybaseBroker {
+	binary: String,
+}
 
 impl KeybaseBroker {
-	pub fn new() -> Result<Self> {
+	pub fn new(binary: &str) -> Result<Self> {
 		let mut proc = if cfg!(target_os = "windows") {
 			Command::new("where")
 		} else {
 			Command::new("which")
 		};
 
-		let status = proc.arg("keybase").stdout(Stdio::null()).status()?;
+		let status = proc.arg(binary).stdout(Stdio::null()).status()?;
 
 		if status.success() {
-			Ok(Self {})
+			Ok(Self {
+				binary: binary.to_owned(),
+			})
 		} else {
 			Err(ErrorKind::KeybaseNotFound)?
 		}
 	}
 
-	pub fn api_send(payload: &str) -> Result<Value> {
-		let mut proc = Command::new("keybase");
+	/// Username the local keybase client is logged in with
+	pub fn username(&self) -> Option<String> {
+		let output = Command::new(&self.binary)
+			.args(&["status", "-j"])
+			.output()
+			.ok()?;
+		let status: Value = serde_json::from_slice(&output.stdout).ok()?;
+		status["Username"]
+			.as_str()
+			.filter(|u| !u.is_empty())
+			.map(|u| u.to_owned())
+	}
+
+	pub fn api_send(&self, payload: &str) -> Result<Value> {
+		let mut proc = Command::new(&self.binary);
 		proc.args(&["chat", "api", "-m", &payload]);
-		let output = proc.output().expect("No output").stdout;
+		let output = proc.output()?.stdout;
 		let response = std::str::from_utf8(&output)?;
 		let response: Value = serde_json::from_str(response)?;
 		Ok(response)
 	}
 
-	pub fn read_from_channel(channel: &str, topic: &str) -> Result<Vec<(String, String, String)>> {
+	/// Chat API channel of an address. Team channels are regular chat channels,
+	/// so the slates can be followed by all members
+	fn channel(address: &KeybaseAddress) -> Value {
+		match &address.channel {
+			Some(channel) => json!({
+				"name": address.username,
+				"members_type": "team",
+				"topic_type": "chat",
+				"topic_name": channel
+			}),
+			None => {
+				let topic = match &address.topic {
+					Some(t) => t,
+					None => TOPIC_WALLET713_SLATES,
+				};
+				json!({
+					"name": address.username,
+					"topic_type": "dev",
+					"topic_name": topic
+				})
+			}
+		}
+	}
+
+	/// Unread messages of a channel. They are only peeked at, and stay
+	/// unread until marked with `mark_read`
+	fn read(&self, address: &KeybaseAddress) -> Result<Vec<KeybaseMessage>> {
 		let payload = json!({
 			"method": "read",
 			"params": {
 				"options": {
-					"channel": {
-						"name": channel,
-						"topic_type": "dev",
-						"topic_name": topic
-					},
+					"channel": KeybaseBroker::channel(address),
 					"unread_only": true,
-					"peek": false
+					"peek": true
 				},
 			}
 		});
 		let payload = serde_json::to_string(&payload)?;
-		let response = KeybaseBroker::api_send(&payload)?;
-		let mut unread: Vec<(String, String, String)> = Vec::new();
+		let response = self.api_send(&payload)?;
+		let mut unread = Vec::new();
 		let messages = response["result"]["messages"].as_array();
 		if let Some(messages) = messages {
 			for msg in messages.iter() {
-				if (msg["msg"]["content"]["type"] == "text") && (msg["msg"]["unread"] == true) {
-					let message = msg["msg"]["content"]["text"]["body"].as_str().unwrap_or("");
-					let sender: &str = msg["msg"]["sender"]["username"].as_str().unwrap_or("");
-					if !message.is_empty() && !sender.is_empty() {
-						unread.push((sender.to_string(), topic.to_string(), message.to_string()));
+				let msg = &msg["msg"];
+				if (msg["content"]["type"] == "text") && (msg["unread"] == true) {
+					let body = msg["content"]["text"]["body"].as_str().unwrap_or("");
+					let sender = msg["sender"]["username"].as_str().unwrap_or("");
+					let conversation = msg["conversation_id"].as_str().unwrap_or("");
+					let msg_id = msg["id"].as_u64();
+					if let (false, false, Some(msg_id)) =
+						(body.is_empty(), sender.is_empty(), msg_id)
+					{
+						unread.push(KeybaseMessage {
+							id: format!("{}:{}", conversation, msg_id),
+							msg_id,
+							sender: sender.to_string(),
+							body: body.to_string(),
+						});
 					}
 				}
 			}
@@ -214,7 +392,44 @@ impl KeybaseBroker {
 		Ok(unread)
 	}
 
-	pub fn get_unread(topics: HashSet<&str>) -> Result<Vec<(String, String, String)>> {
+	/// Mark a message of a channel, and the ones before it, as read
+	fn mark_read(&self, address: &KeybaseAddress, msg_id: u64) -> Result<()> {
+		let payload = json!({
+			"method": "mark",
+			"params": {
+				"options": {
+					"channel": KeybaseBroker::channel(address),
+					"message_id": msg_id
+				},
+			}
+		});
+		let payload = serde_json::to_string(&payload)?;
+		let response = self.api_send(&payload)?;
+		if let Some(error) = response["error"]["message"].as_str() {
+			warn!(
+				"keybase: could not mark message {} as read: {}",
+				msg_id, error
+			);
+		}
+		Ok(())
+	}
+
+	/// Unread messages of the team channels, with their channel
+	fn get_team_unread(
+		&self,
+		channels: &[KeybaseAddress],
+	) -> Result<Vec<(KeybaseAddress, KeybaseMessage)>> {
+		let mut unread = Vec::new();
+		for channel in channels {
+			for message in self.read(channel)? {
+				unread.push((channel.clone(), message));
+			}
+		}
+		Ok(unread)
+	}
+
+	/// Unread direct messages on `topics`, with the channel they came in
+	fn get_unread(&self, topics: HashSet<&str>) -> Result<Vec<(KeybaseAddress, KeybaseMessage)>> {
 		let payload = json!({
 			"method": "list",
 			"params": {
@@ -224,7 +439,7 @@ impl KeybaseBroker {
 			}
 		});
 		let payload = serde_json::to_string(&payload)?;
-		let response = KeybaseBroker::api_send(&payload)?;
+		let response = self.api_send(&payload)?;
 
 		let mut channels = HashSet::new();
 		let messages = response["result"]["conversations"].as_array();
@@ -238,29 +453,31 @@ impl KeybaseBroker {
 			}
 		}
 
-		let mut unread: Vec<(String, String, String)> = Vec::new();
+		let mut unread = Vec::new();
 		for (channel, topic) in channels.iter() {
-			let mut messages = KeybaseBroker::read_from_channel(channel, topic)?;
-			unread.append(&mut messages);
+			let address = KeybaseAddress {
+				username: channel.to_string(),
+				topic: Some(topic.to_string()),
+				channel: None,
+			};
+			for message in self.read(&address)? {
+				unread.push((address.clone(), message));
+			}
 		}
 		Ok(unread)
 	}
 
 	pub fn send<T: Serialize>(
+		&self,
 		message: &T,
-		channel: &str,
-		topic: &str,
+		to: &KeybaseAddress,
 		ttl: &Option<String>,
 	) -> Result<()> {
 		let mut payload = json!({
 			"method": "send",
 			"params": {
 				"options": {
-					"channel": {
-						"name": channel,
-						"topic_name": topic,
-						"topic_type": "dev"
-					},
+					"channel": KeybaseBroker::channel(to),
 					"message": {
 						"body": serde_json::to_string(&message)?
 					}
@@ -273,10 +490,213 @@ impl KeybaseBroker {
 		}
 
 		let payload = serde_json::to_string(&payload)?;
-		let response = KeybaseBroker::api_send(&payload)?;
+		let response = self.api_send(&payload)?;
 		match response["result"]["message"].as_str() {
 			Some("message sent") => Ok(()),
 			_ => Err(ErrorKind::KeybaseMessageSendError)?,
 		}
 	}
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+	use super::*;
+	use crate::wallet::testing::{create_wallet, LoopbackNetwork, MockChain};
+	use crate::wallet::types::SlateVersion;
+	use crate::wallet::Container;
+	use std::env;
+	use std::fs;
+	use std::os::unix::fs::PermissionsExt;
+	use std::path::PathBuf;
+	use uuid::Uuid;
+
+	/// Stand-in for the keybase client, answering every `chat api` method
+	/// with `<method>.json` next to it and logging the methods called
+	const MOCK_KEYBASE: &str = r#"#!/bin/sh
+dir=$(dirname "$0")
+if [ "$1" = "status" ]; then
+	echo '{"Username":"bob"}'
+	exit 0
+fi
+method=$(printf '%s' "$4" | sed -n 's/^{"method":"\([a-z]*\)".*/\1/p')
+echo "$method" >> "$dir/calls"
+cat "$dir/$method.json" 2>/dev/null || echo '{}'
+"#;
+
+	struct MockKeybase {
+		dir: PathBuf,
+	}
+
+	impl MockKeybase {
+		fn new() -> Self {
+			let dir = env::temp_dir().join(format!("wallet713-keybase-{}", Uuid::new_v4()));
+			fs::create_dir_all(&dir).unwrap();
+			let binary = dir.join("keybase");
+			fs::write(&binary, MOCK_KEYBASE).unwrap();
+			fs::set_permissions(&binary, fs::Permissions::from_mode(0o755)).unwrap();
+			let mock = Self { dir };
+			mock.respond("send", json!({"result": {"message": "message sent"}}));
+			mock.respond("mark", json!({"result": {}}));
+			mock
+		}
+
+		fn binary(&self) -> String {
+			self.dir.join("keybase").to_string_lossy().into_owned()
+		}
+
+		fn respond(&self, method: &str, response: Value) {
+			let path = self.dir.join(format!("{}.json", method));
+			fs::write(path, response.to_string()).unwrap();
+		}
+
+		/// Leave `slates` unread from alice, with the given message ids
+		fn deliver(&self, slates: &[(u64, &VersionedSlate)]) {
+			self.respond(
+				"list",
+				json!({"result": {"conversations": [{
+					"id": "c0",
+					"unread": true,
+					"channel": {"name": "alice", "topic_name": TOPIC_WALLET713_SLATES}
+				}]}}),
+			);
+			let messages: Vec<Value> = slates
+				.iter()
+				.map(|(id, slate)| {
+					json!({"msg": {
+						"id": id,
+						"conversation_id": "c0",
+						"unread": true,
+						"sender": {"username": "alice"},
+						"content": {
+							"type": "text",
+							"text": {"body": serde_json::to_string(slate).unwrap()}
+						}
+					}})
+				})
+				.collect();
+			self.respond("read", json!({"result": {"messages": messages}}));
+		}
+
+		fn calls(&self, method: &str) -> usize {
+			fs::read_to_string(self.dir.join("calls"))
+				.unwrap_or_default()
+				.lines()
+				.filter(|l| *l == method)
+				.count()
+		}
+	}
+
+	fn controller<W, C, K>(
+		container: &Arc<Mutex<Container<W, C, K>>>,
+		mock: &MockKeybase,
+	) -> Controller<W, C, K, KeybasePublisher>
+	where
+		W: WalletBackend<C, K>,
+		C: NodeClient,
+		K: Keychain,
+	{
+		let publisher = KeybasePublisher::new(&mock.binary(), None, None).unwrap();
+		let (events, runtime) = {
+			let c = container.lock();
+			(c.events.clone(), c.runtime.clone())
+		};
+		Controller::new("keybase", container.clone(), publisher, events, runtime).unwrap()
+	}
+
+	fn poll<W, C, K, P>(subscriber: &KeybaseSubscriber, controller: &Controller<W, C, K, P>)
+	where
+		W: WalletBackend<C, K>,
+		C: NodeClient,
+		K: Keychain,
+		P: Publisher,
+	{
+		subscriber.fetch(controller).unwrap();
+		subscriber.process_pending(controller).unwrap();
+	}
+
+	/// A slate the controller answers without touching the wallet, so every
+	/// time it is processed shows up as a `send`
+	fn slate() -> VersionedSlate {
+		VersionedSlate::into_version(Slate::blank(2, Uuid::new_v4()), SlateVersion::V2)
+	}
+
+	#[test]
+	fn processes_each_slate_once() {
+		let mock = MockKeybase::new();
+		let container =
+			create_wallet("kb-once", &MockChain::new(), &LoopbackNetwork::new()).unwrap();
+		let controller = controller(&container, &mock);
+		let subscriber = KeybaseSubscriber::new(&mock.binary(), vec![]).unwrap();
+
+		let first = slate();
+		mock.deliver(&[(1, &first)]);
+		poll(&subscriber, &controller);
+		assert_eq!(mock.calls("send"), 1);
+		assert_eq!(mock.calls("mark"), 1);
+
+		// Still unread, as if marking it had failed
+		poll(&subscriber, &controller);
+		assert_eq!(mock.calls("send"), 1);
+
+		// Posted again by the sender in a new message
+		mock.deliver(&[(2, &first)]);
+		poll(&subscriber, &controller);
+		assert_eq!(mock.calls("send"), 1);
+		assert_eq!(mock.calls("mark"), 3);
+
+		mock.deliver(&[(3, &slate())]);
+		poll(&subscriber, &controller);
+		assert_eq!(mock.calls("send"), 2);
+	}
+
+	#[test]
+	fn processes_slates_stored_before_a_restart() {
+		let mock = MockKeybase::new();
+		let container =
+			create_wallet("kb-restart", &MockChain::new(), &LoopbackNetwork::new()).unwrap();
+		let subscriber = KeybaseSubscriber::new(&mock.binary(), vec![]).unwrap();
+
+		mock.deliver(&[(1, &slate())]);
+		subscriber.fetch(&controller(&container, &mock)).unwrap();
+		assert_eq!(mock.calls("mark"), 1);
+		assert_eq!(mock.calls("send"), 0);
+
+		// Restarted with nothing unread left on keybase
+		mock.deliver(&[]);
+		let controller = controller(&container, &mock);
+		let subscriber = KeybaseSubscriber::new(&mock.binary(), vec![]).unwrap();
+		poll(&subscriber, &controller);
+		assert_eq!(mock.calls("send"), 1);
+		poll(&subscriber, &controller);
+		assert_eq!(mock.calls("send"), 1);
+	}
+
+	#[test]
+	fn skips_chat_in_team_channels() {
+		let mock = MockKeybase::new();
+		let container =
+			create_wallet("kb-team", &MockChain::new(), &LoopbackNetwork::new()).unwrap();
+		let controller = controller(&container, &mock);
+		let team = KeybaseAddress::from_str("acme.treasury/payments").unwrap();
+		let subscriber = KeybaseSubscriber::new(&mock.binary(), vec![team]).unwrap();
+
+		mock.respond(
+			"read",
+			json!({"result": {"messages": [{"msg": {
+				"id": 7,
+				"conversation_id": "t0",
+				"unread": true,
+				"sender": {"username": "carol"},
+				"content": {"type": "text", "text": {"body": "morning all"}}
+			}}]}}),
+		);
+		poll(&subscriber, &controller);
+		assert_eq!(mock.calls("mark"), 1);
+		assert_eq!(mock.calls("send"), 0);
+		assert!(controller
+			.owner()
+			.pending_received_messages(METHOD)
+			.unwrap()
+			.is_empty());
+	}
+}
@@ -12,11 +12,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod backup;
+mod blocks;
+mod email;
+mod exec;
 mod grinbox;
 mod keybase;
+mod outbox;
 mod protocol;
+mod retry;
 mod types;
 
+pub use self::backup::BackupScheduler;
+pub use self::blocks::BlockWatcher;
+pub use self::email::{EmailPublisher, EmailSettings, EmailSubscriber};
+pub use self::exec::ExecAdapter;
 pub use self::grinbox::{GrinboxPublisher, GrinboxSubscriber};
 pub use self::keybase::{KeybasePublisher, KeybaseSubscriber, TOPIC_SLATE_NEW};
-pub use self::types::{CloseReason, Controller, Publisher, Subscriber, SubscriptionHandler};
+pub use self::outbox::OutboxWatcher;
+pub use self::retry::SlateRetrier;
+pub use self::types::{
+	CloseReason, ConnectionStatus, Controller, Publisher, SharedConnectionStatus, Subscriber,
+	SubscriptionHandler,
+};
@@ -0,0 +1,190 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::types::{ConnectionStatus, SharedConnectionStatus};
+use crate::cli_message;
+use crate::common::{Arc, Error, ErrorKind, Keychain, Mutex};
+use crate::wallet::api::Owner;
+use crate::wallet::types::{
+	NodeClient, Slate, TxLogEntry, TxLogEntryType, VersionedSlate, WalletBackend,
+};
+use crate::wallet::ErrorKind as WalletErrorKind;
+use colored::Colorize;
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+const RESPONSE_EXTENSION: &str = "response";
+const ARCHIVE_DIR: &str = "archive";
+const SLEEP_DURATION: Duration = Duration::from_millis(5000);
+
+/// Polls a directory for `*.response` slates belonging to outstanding
+/// file based sends, finalizes and posts them, and archives the files
+#[derive(Clone)]
+pub struct OutboxWatcher {
+	dir: PathBuf,
+	stop_signal: Arc<Mutex<bool>>,
+	status: SharedConnectionStatus,
+}
+
+impl OutboxWatcher {
+	pub fn new(dir: PathBuf) -> Self {
+		Self {
+			dir,
+			stop_signal: Arc::new(Mutex::new(true)),
+			status: SharedConnectionStatus::new(),
+		}
+	}
+
+	pub fn dir(&self) -> &Path {
+		&self.dir
+	}
+
+	pub fn start<W, C, K>(&self, owner: Owner<W, C, K>) -> Result<(), Error>
+	where
+		W: WalletBackend<C, K>,
+		C: NodeClient,
+		K: Keychain,
+	{
+		{
+			let mut guard = self.stop_signal.lock();
+			*guard = false;
+		}
+
+		let archive = self.dir.join(ARCHIVE_DIR);
+		fs::create_dir_all(&archive)?;
+
+		// Files we failed to process, only retried once they are modified
+		let mut skipped: HashMap<PathBuf, Option<SystemTime>> = HashMap::new();
+		while !*self.stop_signal.lock() {
+			match self.responses() {
+				Ok(files) => {
+					self.status.connected("outbox");
+					skipped.retain(|path, _| files.contains(path));
+					for path in files {
+						let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+						if skipped.get(&path) == Some(&modified) {
+							continue;
+						}
+						match self.process(&owner, &path, &archive) {
+							Ok(()) => {
+								skipped.remove(&path);
+							}
+							Err(e) => {
+								self.status
+									.error("outbox", format!("{}: {}", path.display(), e));
+								skipped.insert(path, modified);
+							}
+						}
+					}
+				}
+				Err(e) => {
+					self.status.error("outbox", e.to_string());
+					self.status.disconnected("outbox");
+				}
+			}
+			std::thread::sleep(SLEEP_DURATION);
+		}
+		self.status.disconnected("outbox");
+		Ok(())
+	}
+
+	pub fn stop(&self) {
+		let mut guard = self.stop_signal.lock();
+		*guard = true;
+	}
+
+	pub fn status(&self) -> ConnectionStatus {
+		self.status.get()
+	}
+
+	fn responses(&self) -> Result<Vec<PathBuf>, Error> {
+		let mut files = Vec::new();
+		for entry in fs::read_dir(&self.dir)? {
+			let path = entry?.path();
+			let is_response = path.extension().map(|e| e == RESPONSE_EXTENSION) == Some(true);
+			if is_response && path.is_file() {
+				files.push(path);
+			}
+		}
+		files.sort();
+		Ok(files)
+	}
+
+	fn process<W, C, K>(
+		&self,
+		owner: &Owner<W, C, K>,
+		path: &Path,
+		archive: &Path,
+	) -> Result<(), Error>
+	where
+		W: WalletBackend<C, K>,
+		C: NodeClient,
+		K: Keychain,
+	{
+		let content = fs::read_to_string(path)?;
+		let slate: VersionedSlate =
+			serde_json::from_str(&content).map_err(|_| ErrorKind::ParseSlate)?;
+		let slate: Slate = slate.into();
+		debug!("outbox: found response for slate {}", slate.id);
+
+		let (_, _, txs, _, _) =
+			owner.retrieve_txs(false, false, false, None, Some(slate.id), None, None)?;
+		check_response(&slate, &txs)?;
+
+		let slate = owner.finalize_tx(&slate, None)?;
+		owner.post_tx(&slate.tx, false)?;
+		cli_message!(
+			owner.runtime(),
+			"Slate {} from outbox posted successfully",
+			slate.id.to_string().bright_green()
+		);
+
+		let file_name = path.file_name().unwrap();
+		if let Err(e) = fs::rename(path, archive.join(file_name)) {
+			warn!("outbox: unable to archive {}: {}", path.display(), e);
+		}
+		Ok(())
+	}
+}
+
+/// The response has to complete an outstanding send from the active
+/// account, for the amount and fee we originally offered
+fn check_response(slate: &Slate, txs: &[TxLogEntry]) -> Result<(), Error> {
+	let reject = |reason: &str| -> Result<(), Error> {
+		Err(WalletErrorKind::ResponseRejected(format!("{} ({})", reason, slate.id)).into())
+	};
+
+	let tx = match txs.iter().find(|t| t.tx_type == TxLogEntryType::TxSent) {
+		Some(tx) => tx,
+		None => return reject("no outstanding send"),
+	};
+	if tx.confirmed {
+		return reject("transaction already confirmed");
+	}
+	if slate.participant_data.len() < slate.num_participants {
+		return reject("slate is missing the recipient's signature data");
+	}
+	if tx.fee.map(|f| f != slate.fee) == Some(true) {
+		return reject("fee does not match");
+	}
+	// The fee is not part of this entry when it was paid from another account
+	let fee = tx.fee.unwrap_or(0);
+	if tx.amount_debited.saturating_sub(tx.amount_credited) != slate.amount + fee {
+		return reject("amount does not match");
+	}
+	Ok(())
+}
@@ -0,0 +1,101 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::types::{ConnectionStatus, SharedConnectionStatus};
+use crate::cli_message;
+use crate::common::{Arc, Error, Keychain, Mutex};
+use crate::wallet::api::Owner;
+use crate::wallet::types::{NodeClient, WalletBackend};
+use colored::Colorize;
+use std::time::Duration;
+
+const POLL_INTERVAL: u32 = 30;
+const SLEEP_DURATION: Duration = Duration::from_millis(1000);
+
+/// Posts the slates in the retry queue again once they are due, until
+/// their response is finalized or they run out of attempts
+#[derive(Clone)]
+pub struct SlateRetrier {
+	stop_signal: Arc<Mutex<bool>>,
+	status: SharedConnectionStatus,
+}
+
+impl SlateRetrier {
+	pub fn new() -> Self {
+		Self {
+			stop_signal: Arc::new(Mutex::new(true)),
+			status: SharedConnectionStatus::new(),
+		}
+	}
+
+	pub fn start<W, C, K>(&self, owner: Owner<W, C, K>) -> Result<(), Error>
+	where
+		W: WalletBackend<C, K>,
+		C: NodeClient,
+		K: Keychain,
+	{
+		{
+			let mut guard = self.stop_signal.lock();
+			*guard = false;
+		}
+
+		while !self.is_stopped() {
+			match owner.retry_queued_slates() {
+				Ok(attempted) => {
+					self.status.connected("retry");
+					for queued in attempted {
+						match &queued.last_error {
+							Some(e) => self
+								.status
+								.error("retry", format!("{}: {}", queued.slate_id(), e)),
+							None => cli_message!(
+								owner.runtime(),
+								"Slate {} posted again to {}",
+								queued.slate_id().to_string().bright_green(),
+								queued.dest.bright_green()
+							),
+						}
+					}
+				}
+				Err(e) => {
+					self.status.error("retry", e.to_string());
+					self.status.disconnected("retry");
+				}
+			}
+
+			// Sleep in short steps so stopping doesn't wait for a full interval
+			for _ in 0..POLL_INTERVAL {
+				if self.is_stopped() {
+					break;
+				}
+				std::thread::sleep(SLEEP_DURATION);
+			}
+		}
+		self.status.disconnected("retry");
+		Ok(())
+	}
+
+	pub fn stop(&self) {
+		let mut guard = self.stop_signal.lock();
+		*guard = true;
+	}
+
+	pub fn status(&self) -> ConnectionStatus {
+		self.status.get()
+	}
+
+	fn is_stopped(&self) -> bool {
+		*self.stop_signal.lock()
+	}
+}
@@ -13,12 +13,18 @@
 // limitations under the License.
 
 use crate::cli_message;
-use crate::common::{Arc, Error, Keychain, Mutex};
+use crate::common::metrics::{SharedMetrics, SLATES_RECEIVED, SLATES_SENT};
+use crate::common::{Arc, Error, Keychain, Mutex, RuntimeContext};
 use crate::contacts::{Address, AddressType, GrinboxAddress};
 use crate::wallet::api::{Foreign, Owner};
-use crate::wallet::types::{NodeClient, Slate, TxProof, VersionedSlate, WalletBackend};
-use crate::wallet::Container;
+use crate::wallet::types::{
+	Identifier, NodeClient, SharedEventBus, Slate, TxProof, VersionedSlate, WalletBackend,
+	WalletEvent,
+};
+use crate::wallet::{Container, ErrorKind};
+use chrono::{DateTime, Utc};
 use colored::Colorize;
+use log::{info, warn};
 use std::marker::Send;
 
 pub enum CloseReason {
@@ -26,6 +32,63 @@ pub enum CloseReason {
 	Abnormal(Error),
 }
 
+/// Connection state of a subscriber
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionStatus {
+	pub connected: bool,
+	/// Reconnect attempts since the connection was last lost
+	pub reconnect_attempts: u32,
+	pub last_error: Option<String>,
+	pub connected_since: Option<DateTime<Utc>>,
+}
+
+/// Connection state updated by the broker threads, readable from anywhere
+#[derive(Clone, Default)]
+pub struct SharedConnectionStatus(Arc<Mutex<ConnectionStatus>>);
+
+impl SharedConnectionStatus {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn get(&self) -> ConnectionStatus {
+		self.0.lock().clone()
+	}
+
+	pub fn connected(&self, name: &str) {
+		let mut status = self.0.lock();
+		if !status.connected {
+			info!("listener {} connected", name);
+		}
+		status.connected = true;
+		status.reconnect_attempts = 0;
+		status.connected_since = Some(Utc::now());
+	}
+
+	pub fn disconnected(&self, name: &str) {
+		let mut status = self.0.lock();
+		if status.connected {
+			warn!("listener {} disconnected", name);
+		}
+		status.connected = false;
+		status.connected_since = None;
+	}
+
+	pub fn reconnecting(&self, name: &str) {
+		let mut status = self.0.lock();
+		status.reconnect_attempts += 1;
+		info!(
+			"listener {} reconnect attempt {}",
+			name, status.reconnect_attempts
+		);
+	}
+
+	pub fn error(&self, name: &str, error: String) {
+		warn!("listener {} error: {}", name, error);
+		self.0.lock().last_error = Some(error);
+	}
+}
+
 pub trait Publisher: Send {
 	fn post_slate(&self, slate: &VersionedSlate, to: &dyn Address) -> Result<(), Error>;
 }
@@ -39,6 +102,7 @@ pub trait Subscriber {
 		P: Publisher;
 	fn stop(&self);
 	fn is_running(&self) -> bool;
+	fn status(&self) -> ConnectionStatus;
 }
 
 pub trait SubscriptionHandler: Send {
@@ -60,6 +124,10 @@ where
 	owner: Owner<W, C, K>,
 	foreign: Foreign<W, C, K>,
 	publisher: P,
+	events: SharedEventBus,
+	runtime: RuntimeContext,
+	/// Account received slates are booked into, the active one if not set
+	account: Option<Identifier>,
 }
 
 impl<W, C, K, P> Controller<W, C, K, P>
@@ -73,15 +141,47 @@ where
 		name: &str,
 		container: Arc<Mutex<Container<W, C, K>>>,
 		publisher: P,
+		events: SharedEventBus,
+		runtime: RuntimeContext,
 	) -> Result<Self, Error> {
 		Ok(Self {
 			name: name.to_string(),
 			owner: Owner::new(container.clone()),
 			foreign: Foreign::new(container),
 			publisher,
+			events,
+			runtime,
+			account: None,
 		})
 	}
 
+	/// Book received slates into the account at `parent_key_id`
+	pub fn with_account(mut self, parent_key_id: Identifier) -> Self {
+		self.account = Some(parent_key_id);
+		self
+	}
+
+	pub fn runtime(&self) -> &RuntimeContext {
+		&self.runtime
+	}
+
+	/// Owner API of the wallet slates are received into
+	pub fn owner(&self) -> &Owner<W, C, K> {
+		&self.owner
+	}
+
+	pub fn metrics(&self) -> SharedMetrics {
+		self.owner.metrics()
+	}
+
+	fn listener_down(&self, error: Option<String>) {
+		self.events.publish(WalletEvent::ListenerDown {
+			listener: self.name.clone(),
+			error,
+			timestamp: Utc::now(),
+		});
+	}
+
 	fn process_incoming_slate(
 		&self,
 		address: Option<String>,
@@ -92,7 +192,18 @@ where
 			if slate.tx.inputs().len() == 0 {
 				// TODO: invoicing
 			} else {
-				*slate = self.foreign.receive_tx(slate, None, address, None)?;
+				let account = match &self.account {
+					Some(id) => self
+						.owner
+						.all_accounts()?
+						.into_iter()
+						.find(|a| a.path == *id),
+					None => None,
+				};
+				let dest_acct_name = account.as_ref().map(|a| a.label.as_str());
+				*slate = self
+					.foreign
+					.receive_tx(slate, dest_acct_name, address, None)?;
 			}
 			Ok(false)
 		} else {
@@ -116,6 +227,9 @@ where
 	fn on_slate(&self, from: &dyn Address, slate: &VersionedSlate, tx_proof: Option<&mut TxProof>) {
 		let version = slate.version();
 		let mut slate: Slate = slate.clone().into();
+		let address_type = from.address_type();
+		let adapter = [("adapter", address_type.method())];
+		self.metrics().lock().inc(SLATES_RECEIVED, &adapter);
 
 		/*if slate.num_participants > slate.participant_data.len() {
 			cli_message!(
@@ -147,11 +261,13 @@ where
 					self.publisher
 						.post_slate(&slate, from)
 						.map_err(|e| {
-							cli_message!("{}: {}", "ERROR".bright_red(), e);
+							cli_message!(self.runtime, "{}: {}", "ERROR".bright_red(), e);
 							e
 						})
 						.expect("failed posting slate!");
+					self.metrics().lock().inc(SLATES_SENT, &adapter);
 					cli_message!(
+						self.runtime,
 						"Slate {} sent back to {} successfully",
 						id.to_string().bright_green(),
 						from.stripped().bright_green()
@@ -168,7 +284,9 @@ where
 
 		match result {
 			Ok(()) => {}
-			Err(e) => cli_message!("{}", e),
+			// Already reported, the response goes out once approved
+			Err(ref e) if awaiting_approval(e) => {}
+			Err(e) => cli_message!(self.runtime, "{}", e),
 		}
 	}
 
@@ -177,20 +295,35 @@ where
 			CloseReason::Normal => {
 				//println!("Listener for {} stopped", self.name.bright_green())
 			}
-			CloseReason::Abnormal(_) => {
-				cli_message!("Listener {} stopped unexpectedly", self.name.bright_green())
+			CloseReason::Abnormal(e) => {
+				cli_message!(
+					self.runtime,
+					"Listener {} stopped unexpectedly",
+					self.name.bright_green()
+				);
+				self.listener_down(Some(e.to_string()));
 			}
 		}
 	}
 
 	fn on_dropped(&self) {
-		cli_message!("Listener {} lost connection. it will keep trying to restore connection in the background.", self.name.bright_green())
+		cli_message!(self.runtime, "Listener {} lost connection. it will keep trying to restore connection in the background.", self.name.bright_green());
+		self.listener_down(None);
 	}
 
 	fn on_reestablished(&self) {
 		cli_message!(
+			self.runtime,
 			"Listener {} reestablished connection.",
 			self.name.bright_green()
 		)
 	}
 }
+
+/// Whether the slate was held for approval by the `receive_*` rules
+fn awaiting_approval(e: &Error) -> bool {
+	match e.downcast_ref::<ErrorKind>() {
+		Some(ErrorKind::SlateAwaitingApproval(_)) => true,
+		_ => false,
+	}
+}
@@ -0,0 +1,166 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Amounts as typed by people: a number with an optional unit, e.g. `5.2grin`,
+//! `1200milli` or `100000nano`. The digits are parsed as integers, never
+//! through floating point, so every amount is exact to the nanogrin
+
+use super::ErrorKind;
+
+/// Amount spending everything that can be spent
+pub const MAX: &str = "max";
+
+/// Unit of an amount, all multiples of a nanogrin
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Unit {
+	Grin,
+	Milli,
+	Micro,
+	Nano,
+}
+
+impl Unit {
+	fn from_suffix(suffix: &str) -> Option<Self> {
+		match suffix {
+			"grin" | "g" => Some(Unit::Grin),
+			"milli" | "mgrin" => Some(Unit::Milli),
+			"micro" | "ugrin" => Some(Unit::Micro),
+			"nano" | "ngrin" => Some(Unit::Nano),
+			_ => None,
+		}
+	}
+
+	fn name(self) -> &'static str {
+		match self {
+			Unit::Grin => "grin",
+			Unit::Milli => "milli",
+			Unit::Micro => "micro",
+			Unit::Nano => "nano",
+		}
+	}
+
+	/// Decimals the unit can have, down to the nanogrin
+	fn decimals(self) -> usize {
+		match self {
+			Unit::Grin => 9,
+			Unit::Milli => 6,
+			Unit::Micro => 3,
+			Unit::Nano => 0,
+		}
+	}
+}
+
+/// A parsed amount, either in nanogrin or everything spendable
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParsedAmount {
+	Nano(u64),
+	Max,
+}
+
+/// Parse an amount that may also be `max`, in `default_unit` unless one
+/// is given
+pub fn parse(amount: &str, default_unit: Unit) -> Result<ParsedAmount, ErrorKind> {
+	match amount.trim().to_lowercase().as_str() {
+		MAX => Ok(ParsedAmount::Max),
+		_ => parse_nano(amount, default_unit).map(ParsedAmount::Nano),
+	}
+}
+
+/// Parse an amount into nanogrin, in `default_unit` unless one is given.
+/// Either `.` or `,` is taken as the decimal mark, but not both, and a
+/// comma that could separate thousands is refused instead of guessed at
+pub fn parse_nano(amount: &str, default_unit: Unit) -> Result<u64, ErrorKind> {
+	let invalid = |reason: &str| ErrorKind::InvalidAmount(amount.to_owned(), reason.to_owned());
+	let trimmed = amount.trim().to_lowercase();
+	if trimmed.is_empty() {
+		return Err(invalid("no amount given"));
+	}
+	if trimmed.starts_with('-') {
+		return Err(invalid("amounts can't be negative"));
+	}
+	if trimmed == MAX {
+		return Err(invalid("max is not accepted here"));
+	}
+
+	let split = trimmed
+		.find(|c: char| !c.is_ascii_digit() && c != '.' && c != ',' && c != '_')
+		.unwrap_or(trimmed.len());
+	let (number, suffix) = trimmed.split_at(split);
+	let unit = match suffix.trim() {
+		"" => default_unit,
+		suffix => Unit::from_suffix(suffix).ok_or_else(|| {
+			invalid(&format!(
+				"unknown unit '{}', use grin, milli, micro or nano",
+				suffix
+			))
+		})?,
+	};
+
+	let has_dot = number.contains('.');
+	let has_comma = number.contains(',');
+	if has_dot && has_comma {
+		return Err(invalid(
+			"use either '.' or ',' as the decimal mark, not both",
+		));
+	}
+	let mut parts = number.split(&['.', ','][..]);
+	let whole = parts.next().unwrap_or("");
+	let fraction = parts.next().unwrap_or("");
+	if parts.next().is_some() {
+		return Err(invalid("more than one decimal mark"));
+	}
+	let grouped = !whole.is_empty() && !whole.starts_with('0') && whole.len() <= 3;
+	if has_comma && grouped && fraction.len() == 3 {
+		return Err(invalid(
+			"ambiguous, use '.' for decimals and '_' to group digits",
+		));
+	}
+
+	let whole = digits(whole).ok_or_else(|| invalid("not a number"))?;
+	let fraction = digits(fraction).ok_or_else(|| invalid("not a number"))?;
+	if whole.is_empty() && fraction.is_empty() {
+		return Err(invalid("not a number"));
+	}
+	if fraction.len() > unit.decimals() {
+		return Err(invalid(&format!(
+			"{} allows at most {} decimals",
+			unit.name(),
+			unit.decimals()
+		)));
+	}
+
+	// Both parts as an integer of the smallest unit, the fraction padded
+	let scaled = format!("{}{:0<width$}", whole, fraction, width = unit.decimals());
+	let scaled = scaled.trim_start_matches('0');
+	let value = match scaled.is_empty() {
+		true => 0,
+		false => scaled.parse::<u64>().map_err(|_| invalid("too large"))?,
+	};
+	let factor = 10u64.pow(9 - unit.decimals() as u32);
+	value
+		.checked_mul(factor)
+		.ok_or_else(|| invalid("too large"))
+}
+
+/// The digits of a number, allowing `_` between them to group digits
+fn digits(s: &str) -> Option<String> {
+	if s.starts_with('_') || s.ends_with('_') || s.contains("__") {
+		return None;
+	}
+	let digits: String = s.chars().filter(|c| *c != '_').collect();
+	match digits.chars().all(|c| c.is_ascii_digit()) {
+		true => Some(digits),
+		false => None,
+	}
+}
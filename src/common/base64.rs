@@ -0,0 +1,62 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Standard base64 (RFC 4648) with padding
+
+use super::ErrorKind;
+
+const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub fn encode(data: &[u8]) -> String {
+	let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+	for chunk in data.chunks(3) {
+		let bytes = [
+			chunk[0],
+			chunk.get(1).cloned().unwrap_or(0),
+			chunk.get(2).cloned().unwrap_or(0),
+		];
+		let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+		for i in 0..4 {
+			match i <= chunk.len() {
+				true => encoded.push(CHARS[(n >> (18 - 6 * i) & 63) as usize] as char),
+				false => encoded.push('='),
+			}
+		}
+	}
+	encoded
+}
+
+/// Decode, skipping whitespace so line wrapped input (as in MIME) is accepted
+pub fn decode(data: &str) -> Result<Vec<u8>, ErrorKind> {
+	let mut decoded = Vec::with_capacity(data.len() / 4 * 3);
+	let mut n: u32 = 0;
+	let mut bits = 0;
+	for c in data.bytes().filter(|c| !c.is_ascii_whitespace()) {
+		if c == b'=' {
+			break;
+		}
+		let value = CHARS
+			.iter()
+			.position(|&x| x == c)
+			.ok_or(ErrorKind::Base64Decoding)?;
+		n = n << 6 | value as u32;
+		bits += 6;
+		if bits >= 8 {
+			bits -= 8;
+			decoded.push((n >> bits) as u8);
+			n &= (1 << bits) - 1;
+		}
+	}
+	Ok(decoded)
+}
@@ -19,14 +19,23 @@ use failure::{Backtrace, Context, Fail, ResultExt};
 use futures::future::{err, ok, Either};
 use grin_util::to_base64;
 use http::uri::{InvalidUri, Uri};
+use hyper::client::HttpConnector;
 use hyper::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, USER_AGENT};
 use hyper::rt::{Future, Stream};
 use hyper::{Body, Client, Request};
-use hyper_rustls;
+use hyper_rustls::HttpsConnector;
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::fmt::{self, Display};
+use std::fs::File;
+use std::io::BufReader;
+use std::time::Duration;
 use tokio::runtime::Runtime;
+use tokio::timer::Timeout;
+use tokio_rustls::rustls::ClientConfig;
+
+/// Time a request gets unless configured otherwise
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
 
 #[derive(Debug)]
 pub struct Error {
@@ -77,6 +86,25 @@ impl From<Context<ErrorKind>> for Error {
 
 pub type ClientResponseFuture<T> = Box<dyn Future<Item = T, Error = Error> + Send>;
 
+/// Certificates trusted and time allowed for requests
+#[derive(Clone, Debug)]
+pub struct ClientOptions {
+	/// PEM file with the CA certificates to trust instead of the public web
+	/// roots, for servers with a certificate of their own
+	pub ca_file: Option<String>,
+	/// Time from connecting until the whole response has been read
+	pub timeout: Duration,
+}
+
+impl Default for ClientOptions {
+	fn default() -> Self {
+		Self {
+			ca_file: None,
+			timeout: DEFAULT_TIMEOUT,
+		}
+	}
+}
+
 /// Helper function to easily issue a HTTP GET request against a given URL that
 /// returns a JSON object. Handles request building, JSON deserialization and
 /// response code checking.
@@ -84,18 +112,42 @@ pub fn get<'a, T>(url: &'a str, api_secret: Option<String>) -> Result<T, Error>
 where
 	for<'de> T: Deserialize<'de>,
 {
-	handle_request(build_request(url, "GET", api_secret, None)?)
+	get_with(url, api_secret, &ClientOptions::default())
+}
+
+/// Same as `get`, with the trust and timeout of `options`
+pub fn get_with<'a, T>(
+	url: &'a str,
+	api_secret: Option<String>,
+	options: &ClientOptions,
+) -> Result<T, Error>
+where
+	for<'de> T: Deserialize<'de>,
+{
+	handle_request(build_request(url, "GET", api_secret, None)?, options)
 }
 
 /// Helper function to easily issue an async HTTP GET request against a given
 /// URL that returns a future. Handles request building, JSON deserialization
 /// and response code checking.
 pub fn get_async<'a, T>(url: &'a str, api_secret: Option<String>) -> ClientResponseFuture<T>
+where
+	for<'de> T: Deserialize<'de> + Send + 'static,
+{
+	get_async_with(url, api_secret, &ClientOptions::default())
+}
+
+/// Same as `get_async`, with the trust and timeout of `options`
+pub fn get_async_with<'a, T>(
+	url: &'a str,
+	api_secret: Option<String>,
+	options: &ClientOptions,
+) -> ClientResponseFuture<T>
 where
 	for<'de> T: Deserialize<'de> + Send + 'static,
 {
 	match build_request(url, "GET", api_secret, None) {
-		Ok(req) => Box::new(handle_request_async(req)),
+		Ok(req) => Box::new(handle_request_async(req, options)),
 		Err(e) => Box::new(err(e)),
 	}
 }
@@ -105,11 +157,24 @@ where
 /// building, JSON serialization, and response code
 /// checking.
 pub fn post_no_ret<IN>(url: &str, api_secret: Option<String>, input: &IN) -> Result<(), Error>
+where
+	IN: Serialize,
+{
+	post_no_ret_with(url, api_secret, input, &ClientOptions::default())
+}
+
+/// Same as `post_no_ret`, with the trust and timeout of `options`
+pub fn post_no_ret_with<IN>(
+	url: &str,
+	api_secret: Option<String>,
+	input: &IN,
+	options: &ClientOptions,
+) -> Result<(), Error>
 where
 	IN: Serialize,
 {
 	let req = create_post_request(url, api_secret, input)?;
-	send_request(req)?;
+	send_request_with(req, options)?;
 	Ok(())
 }
 
@@ -158,22 +223,22 @@ where
 	build_request(url, "POST", api_secret, Some(json))
 }
 
-fn handle_request<T>(req: Request<Body>) -> Result<T, Error>
+fn handle_request<T>(req: Request<Body>, options: &ClientOptions) -> Result<T, Error>
 where
 	for<'de> T: Deserialize<'de>,
 {
-	let data = send_request(req)?;
+	let data = send_request_with(req, options)?;
 	serde_json::from_str(&data).map_err(|e| {
 		e.context(ErrorKind::ResponseError("Cannot parse response".to_owned()))
 			.into()
 	})
 }
 
-fn handle_request_async<T>(req: Request<Body>) -> ClientResponseFuture<T>
+fn handle_request_async<T>(req: Request<Body>, options: &ClientOptions) -> ClientResponseFuture<T>
 where
 	for<'de> T: Deserialize<'de> + Send + 'static,
 {
-	Box::new(send_request_async(req).and_then(|data| {
+	Box::new(send_request_async(req, options).and_then(|data| {
 		serde_json::from_str(&data).map_err(|e| {
 			e.context(ErrorKind::ResponseError("Cannot parse response".to_owned()))
 				.into()
@@ -181,10 +246,38 @@ where
 	}))
 }
 
-fn send_request_async(req: Request<Body>) -> Box<dyn Future<Item = String, Error = Error> + Send> {
-	let https = hyper_rustls::HttpsConnector::new(1);
+/// Connector trusting the CAs of `ca_file`, or the public web roots
+fn connector(ca_file: &Option<String>) -> Result<HttpsConnector<HttpConnector>, Error> {
+	let ca_file = match ca_file {
+		Some(ca_file) => ca_file,
+		None => return Ok(HttpsConnector::new(1)),
+	};
+	let file = File::open(ca_file)
+		.map_err(|e| ErrorKind::Argument(format!("Unable to open CA file {}: {}", ca_file, e)))?;
+	let mut config = ClientConfig::new();
+	let (added, _) = config
+		.root_store
+		.add_pem_file(&mut BufReader::new(file))
+		.map_err(|_| ErrorKind::Argument(format!("Unable to read CA file {}", ca_file)))?;
+	if added == 0 {
+		let msg = format!("No certificate found in CA file {}", ca_file);
+		return Err(ErrorKind::Argument(msg).into());
+	}
+	let mut http = HttpConnector::new(1);
+	http.enforce_http(false);
+	Ok(HttpsConnector::from((http, config)))
+}
+
+fn send_request_async(
+	req: Request<Body>,
+	options: &ClientOptions,
+) -> Box<dyn Future<Item = String, Error = Error> + Send> {
+	let https = match connector(&options.ca_file) {
+		Ok(https) => https,
+		Err(e) => return Box::new(err(e)),
+	};
 	let client = Client::builder().build::<_, Body>(https);
-	Box::new(
+	let response = Box::new(
 		client
 			.request(req)
 			.map_err(|e| ErrorKind::RequestError(format!("Cannot make request: {}", e)).into())
@@ -208,11 +301,31 @@ fn send_request_async(req: Request<Body>) -> Box<dyn Future<Item = String, Error
 					)
 				}
 			}),
-	)
+	);
+
+	// Without a limit, a server that accepts the connection but never
+	// answers would block the caller forever
+	let timeout = options.timeout;
+	Box::new(Timeout::new(response, timeout).map_err(move |e| {
+		match e.is_elapsed() {
+			true => {
+				let msg = format!("No response within {}s", timeout.as_secs());
+				ErrorKind::RequestError(msg).into()
+			}
+			false => e
+				.into_inner()
+				.unwrap_or_else(|| ErrorKind::Internal("request timer failed".to_owned()).into()),
+		}
+	}))
 }
 
 pub fn send_request(req: Request<Body>) -> Result<String, Error> {
-	let task = send_request_async(req);
+	send_request_with(req, &ClientOptions::default())
+}
+
+/// Same as `send_request`, with the trust and timeout of `options`
+pub fn send_request_with(req: Request<Body>, options: &ClientOptions) -> Result<String, Error> {
+	let task = send_request_async(req, options);
 	let mut rt =
 		Runtime::new().context(ErrorKind::Internal("can't create Tokio runtime".to_owned()))?;
 	Ok(rt.block_on(task)?)
@@ -0,0 +1,82 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::{Arc, Mutex};
+use chrono::{DateTime, Duration, Utc};
+use std::time::Duration as StdDuration;
+
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Source of the current time and of waiting. Everything that timestamps
+/// transactions, schedules retries or names files asks the clock, so the
+/// time can be controlled from tests
+pub trait Clock: Send + Sync {
+	fn now(&self) -> DateTime<Utc>;
+	fn sleep(&self, duration: StdDuration);
+}
+
+/// Wall clock of the system
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl SystemClock {
+	pub fn shared() -> SharedClock {
+		Arc::new(SystemClock)
+	}
+}
+
+impl Clock for SystemClock {
+	fn now(&self) -> DateTime<Utc> {
+		Utc::now()
+	}
+
+	fn sleep(&self, duration: StdDuration) {
+		std::thread::sleep(duration);
+	}
+}
+
+/// Clock that only moves when told to. Sleeping advances it immediately
+#[derive(Debug)]
+pub struct ManualClock {
+	now: Mutex<DateTime<Utc>>,
+}
+
+impl ManualClock {
+	pub fn new(start: DateTime<Utc>) -> Self {
+		Self {
+			now: Mutex::new(start),
+		}
+	}
+
+	pub fn set(&self, now: DateTime<Utc>) {
+		*self.now.lock() = now;
+	}
+
+	pub fn advance(&self, duration: Duration) {
+		let mut now = self.now.lock();
+		*now = *now + duration;
+	}
+}
+
+impl Clock for ManualClock {
+	fn now(&self) -> DateTime<Utc> {
+		*self.now.lock()
+	}
+
+	fn sleep(&self, duration: StdDuration) {
+		if let Ok(duration) = Duration::from_std(duration) {
+			self.advance(duration);
+		}
+	}
+}
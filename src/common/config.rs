@@ -12,16 +12,23 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::is_cli;
-use super::Result;
+use super::client::{self, ClientOptions};
+use super::logging::{LogConfig, LogFormat};
+use super::{ErrorKind, Result, RuntimeContext};
+use crate::api::cors::CorsPolicy;
+use crate::api::limits::ApiLimits;
 use crate::contacts::DEFAULT_GRINBOX_PORT;
+use crate::internal::updater::RepostPolicy;
+use crate::wallet::types::{NotificationRoute, ReceivePolicy, ScanOptions};
 use grin_core::global::ChainTypes;
 use grin_util::logger::LoggingConfig;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 const WALLET713_HOME: &str = ".wallet713";
 const WALLET713_DEFAULT_CONFIG_FILENAME: &str = "wallet713.toml";
@@ -42,22 +49,111 @@ pub struct Wallet713Config {
 	pub grinbox_address_index: Option<u32>,
 	pub grin_node_uri: Option<String>,
 	pub grin_node_secret: Option<String>,
+	pub grin_node_secret_file: Option<String>,
+	pub grin_node_ca_file: Option<String>,
+	pub grin_node_timeout: Option<u64>,
 	pub grinbox_listener_auto_start: Option<bool>,
+	pub grinbox_listener_accounts: Option<Vec<String>>,
 	pub keybase_listener_auto_start: Option<bool>,
 	pub max_auto_accept_invoice: Option<u64>,
 	pub default_keybase_ttl: Option<String>,
+	pub keybase_team_ttl: Option<String>,
+	pub keybase_team_channels: Option<Vec<String>>,
+	pub keybase_binary: Option<String>,
+	pub email_address: Option<String>,
+	pub email_smtp_server: Option<String>,
+	pub email_imap_server: Option<String>,
+	pub email_username: Option<String>,
+	pub email_password: Option<String>,
+	pub email_listener_auto_start: Option<bool>,
+	pub adapters_listener_auto_start: Option<bool>,
 	pub owner_api: Option<bool>,
 	pub owner_api_address: Option<String>,
 	pub owner_api_secret: Option<String>,
 	pub owner_api_include_foreign: Option<bool>,
+	pub owner_api_allowed_origins: Option<Vec<String>>,
+	pub owner_api_events_token: Option<String>,
+	pub api_base_path: Option<String>,
+	pub owner_read_api: Option<bool>,
+	pub owner_read_api_address: Option<String>,
+	pub owner_read_api_secret: Option<String>,
 	pub foreign_api: Option<bool>,
 	pub foreign_api_address: Option<String>,
 	pub foreign_api_secret: Option<String>,
+	pub foreign_api_strict: Option<bool>,
+	pub foreign_api_public_url: Option<String>,
+	pub http_seal_slates: Option<bool>,
+	pub tls_cert_file: Option<String>,
+	pub tls_key_file: Option<String>,
+	pub owner_api_tls_cert_file: Option<String>,
+	pub owner_api_tls_key_file: Option<String>,
+	pub foreign_api_tls_cert_file: Option<String>,
+	pub foreign_api_tls_key_file: Option<String>,
+	pub api_rate_limit: Option<u32>,
+	pub api_rate_burst: Option<u32>,
+	pub api_max_body_size: Option<u64>,
 	pub check_updates: Option<bool>,
+	pub default_change_outputs: Option<u32>,
+	pub forward_foreign_api_url: Option<String>,
+	pub forward_foreign_api_secret: Option<String>,
+	pub auto_repost_blocks: Option<u64>,
+	pub auto_repost_max_attempts: Option<u32>,
+	pub tx_auto_cancel_after: Option<u64>,
+	pub confirm_threshold: Option<u64>,
+	pub daily_spend_limit: Option<u64>,
+	pub receive_min_amount: Option<u64>,
+	pub receive_allowed_senders: Option<Vec<String>>,
+	pub receive_max_per_hour: Option<u32>,
+	pub receive_manual_approval: Option<bool>,
+	pub duplicate_send_window: Option<u64>,
+	pub shutdown_grace_seconds: Option<u64>,
+	pub outbox_dir: Option<String>,
+	pub outbox_listener_auto_start: Option<bool>,
+	pub notify_webhook: Option<String>,
+	pub notify_script: Option<String>,
+	pub restore_threads: Option<usize>,
+	pub restore_batch_size: Option<u64>,
+	pub kernel_check_days: Option<u64>,
+	pub history_retention_days: Option<u64>,
+	pub backup_dir: Option<String>,
+	pub backup_interval_hours: Option<u64>,
+	pub backup_every_txs: Option<u32>,
+	pub backup_keep: Option<usize>,
+	pub auto_lock_minutes: Option<u64>,
+	pub adaptive_selection: Option<bool>,
+	pub min_output_value: Option<u64>,
+	pub fee_base: Option<u64>,
+	pub payjoin: Option<bool>,
+	pub slate_retry: Option<bool>,
+	pub slate_retry_max_attempts: Option<u32>,
+	pub block_listener: Option<bool>,
+	pub block_listener_interval: Option<u64>,
+	pub auto_add_contacts: Option<bool>,
+	pub hook_slate_received: Option<String>,
+	pub hook_tx_finalized: Option<String>,
+	pub hook_tx_confirmed: Option<String>,
+	pub hook_listener_down: Option<String>,
+	pub log_format: Option<String>,
+	pub log_level: Option<String>,
+	pub log_file: Option<String>,
+	pub log_file_max_size: Option<u64>,
+	pub log_file_max_files: Option<u32>,
+	/// External transport adapters by name, as `[adapter.<name>]` tables.
+	/// Kept last, as tables have to follow the plain values in the file
+	pub adapter: Option<HashMap<String, ExecAdapterConfig>>,
 	#[serde(skip)]
 	pub config_home: Option<String>,
 }
 
+/// Executable speaking the adapter protocol over its stdin and stdout,
+/// see `broker::exec`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ExecAdapterConfig {
+	pub command: String,
+	#[serde(default)]
+	pub args: Vec<String>,
+}
+
 impl Wallet713Config {
 	pub fn exists(config_path: Option<&str>, chain: &Option<ChainTypes>) -> Result<bool> {
 		let default_path_buf = Wallet713Config::default_config_path(chain)?;
@@ -130,6 +226,12 @@ impl Wallet713Config {
 		wallet_config.chain_type = self.chain.clone();
 		wallet_config.data_file_dir = data_path.to_string();
 		wallet_config.check_node_api_http_addr = self.grin_node_uri().clone();
+		wallet_config.restore_threads = self.restore_threads;
+		wallet_config.restore_batch_size = self.restore_batch_size;
+		wallet_config.adaptive_selection = self.adaptive_selection;
+		wallet_config.min_output_value = self.min_output_value;
+		wallet_config.fee_base = self.fee_base;
+		wallet_config.payjoin = self.payjoin;
 		Ok(wallet_config)
 	}
 
@@ -167,25 +269,104 @@ impl Wallet713Config {
 		})
 	}
 
-	pub fn grin_node_secret(&self) -> Option<String> {
+	/// Secret of the node API, `grin_node_secret` or else the contents of
+	/// `grin_node_secret_file`, like the `.api_secret` file of a grin node
+	pub fn grin_node_secret(&self) -> Result<Option<String>> {
 		let chain_type = self.chain.as_ref().unwrap_or(&ChainTypes::Mainnet);
-		match self.grin_node_uri {
-			Some(_) => self.grin_node_secret.clone(),
-			None => match chain_type {
+		if self.grin_node_uri.is_none() {
+			return Ok(match chain_type {
 				ChainTypes::Mainnet => Some(String::from("thanksvault713kizQ4ZVv")),
 				_ => Some(String::from("thanksvault713EcRXKbYS")),
-			},
+			});
+		}
+		match (&self.grin_node_secret, &self.grin_node_secret_file) {
+			(Some(secret), _) => Ok(Some(secret.clone())),
+			(None, Some(file)) => {
+				let secret = std::fs::read_to_string(file)
+					.map_err(|e| ErrorKind::NodeSecretFile(file.clone(), e.to_string()))?;
+				Ok(Some(secret.trim().to_owned()))
+			}
+			(None, None) => Ok(None),
+		}
+	}
+
+	/// CA certificates trusted for the node and time allowed for each
+	/// request to it, 60 seconds unless set
+	pub fn grin_node_client_options(&self) -> ClientOptions {
+		ClientOptions {
+			ca_file: self.grin_node_ca_file.clone(),
+			timeout: self
+				.grin_node_timeout
+				.filter(|t| *t > 0)
+				.map(Duration::from_secs)
+				.unwrap_or(client::DEFAULT_TIMEOUT),
 		}
 	}
 
-	pub fn grinbox_listener_auto_start(&self) -> bool {
-		self.grinbox_listener_auto_start.unwrap_or(is_cli())
+	/// Defaults to on when run interactively
+	pub fn grinbox_listener_auto_start(&self, runtime: &RuntimeContext) -> bool {
+		self.grinbox_listener_auto_start.unwrap_or(runtime.is_cli())
 	}
 
 	pub fn keybase_listener_auto_start(&self) -> bool {
 		self.keybase_listener_auto_start.unwrap_or(false)
 	}
 
+	/// Exploding lifetime of slates sent to keybase users, `none` to keep them
+	pub fn keybase_ttl(&self) -> Option<String> {
+		keybase_ttl(&self.default_keybase_ttl)
+	}
+
+	/// Exploding lifetime of slates posted to team channels, defaults to the
+	/// one for users
+	pub fn keybase_team_ttl(&self) -> Option<String> {
+		match self.keybase_team_ttl {
+			Some(_) => keybase_ttl(&self.keybase_team_ttl),
+			None => self.keybase_ttl(),
+		}
+	}
+
+	/// Accounts a grinbox listener is started for, only the active one when empty
+	pub fn grinbox_listener_accounts(&self) -> Vec<String> {
+		self.grinbox_listener_accounts.clone().unwrap_or(vec![])
+	}
+
+	/// Team channels the keybase listener reads slates from, as `team/channel`
+	pub fn keybase_team_channels(&self) -> Vec<String> {
+		self.keybase_team_channels.clone().unwrap_or(vec![])
+	}
+
+	/// Keybase client the listener runs, looked up on the PATH unless a path is given
+	pub fn keybase_binary(&self) -> String {
+		self.keybase_binary
+			.clone()
+			.unwrap_or_else(|| String::from("keybase"))
+	}
+
+	pub fn email_listener_auto_start(&self) -> bool {
+		self.email_address.is_some() && self.email_listener_auto_start.unwrap_or(false)
+	}
+
+	/// External transport adapters, by the address scheme they send to
+	pub fn exec_adapters(&self) -> HashMap<String, ExecAdapterConfig> {
+		self.adapter.clone().unwrap_or_default()
+	}
+
+	pub fn adapters_listener_auto_start(&self) -> bool {
+		self.adapter.as_ref().map(|a| !a.is_empty()) == Some(true)
+			&& self.adapters_listener_auto_start.unwrap_or(false)
+	}
+
+	/// Directory watched for `*.response` slates of file based sends.
+	/// Processed files are moved to its `archive` subdirectory
+	pub fn outbox_dir(&self) -> Option<PathBuf> {
+		self.outbox_dir.as_ref().map(PathBuf::from)
+	}
+
+	pub fn outbox_listener_auto_start(&self) -> bool {
+		self.outbox_dir.is_some() && self.outbox_listener_auto_start.unwrap_or(false)
+	}
+
 	pub fn owner_api_address(&self) -> String {
 		let chain_type = self.chain.as_ref().unwrap_or(&ChainTypes::Mainnet);
 		self.owner_api_address
@@ -197,6 +378,17 @@ impl Wallet713Config {
 			})
 	}
 
+	pub fn owner_read_api_address(&self) -> String {
+		let chain_type = self.chain.as_ref().unwrap_or(&ChainTypes::Mainnet);
+		self.owner_read_api_address
+			.as_ref()
+			.map(|a| a.clone())
+			.unwrap_or_else(|| match chain_type {
+				ChainTypes::Mainnet => String::from("127.0.0.1:3421"),
+				_ => String::from("127.0.0.1:13421"),
+			})
+	}
+
 	pub fn foreign_api_address(&self) -> String {
 		let chain_type = self.chain.as_ref().unwrap_or(&ChainTypes::Mainnet);
 		self.foreign_api_address
@@ -212,12 +404,289 @@ impl Wallet713Config {
 		self.owner_api.unwrap_or(false)
 	}
 
+	pub fn owner_read_api(&self) -> bool {
+		self.owner_read_api.unwrap_or(false)
+	}
+
 	pub fn foreign_api(&self) -> bool {
 		self.foreign_api.unwrap_or(false)
 	}
 
-	pub fn check_updates(&self) -> bool {
-		self.check_updates.unwrap_or(is_cli())
+	/// Whether foreign API responses should match grin-wallet's exactly
+	pub fn foreign_api_strict(&self) -> bool {
+		self.foreign_api_strict.unwrap_or(false)
+	}
+
+	/// Whether slates sent over http are sealed to the key of the receiver
+	pub fn http_seal_slates(&self) -> bool {
+		self.http_seal_slates.unwrap_or(false)
+	}
+
+	/// Origins allowed to call the owner APIs from a browser, none unless set
+	/// Token a client of the owner event stream has to pass, the owner API
+	/// secret unless one is set. The stream is refused without either
+	pub fn owner_api_events_token(&self) -> Option<String> {
+		self.owner_api_events_token
+			.clone()
+			.or(self.owner_api_secret.clone())
+			.filter(|t| !t.is_empty())
+	}
+
+	pub fn owner_api_cors(&self) -> CorsPolicy {
+		CorsPolicy {
+			allowed_origins: self.owner_api_allowed_origins.clone().unwrap_or(vec![]),
+		}
+	}
+
+	/// Path the owner APIs are served under, for a reverse proxy forwarding
+	/// a prefix as is. Empty unless set, otherwise with a leading slash and
+	/// without a trailing one
+	pub fn api_base_path(&self) -> String {
+		let path = self.api_base_path.as_ref().map(|p| p.trim_matches('/'));
+		match path {
+			Some(p) if !p.is_empty() => format!("/{}", p),
+			_ => String::new(),
+		}
+	}
+
+	/// Limits of the HTTP APIs: 300 requests per minute of a client, 60 at
+	/// once, and bodies of 1 MiB unless set. A rate of 0 turns the rate
+	/// limit off
+	pub fn api_limits(&self) -> ApiLimits {
+		let burst = self.api_rate_burst.filter(|b| *b > 0).unwrap_or(60);
+		let rate = match self.api_rate_limit.unwrap_or(300) {
+			0 => None,
+			per_minute => Some((per_minute, burst)),
+		};
+		ApiLimits {
+			rate,
+			max_body_size: self.api_max_body_size.filter(|s| *s > 0).unwrap_or(1 << 20),
+		}
+	}
+
+	/// Whether unknown senders of received slates are added as contacts
+	pub fn auto_add_contacts(&self) -> bool {
+		self.auto_add_contacts.unwrap_or(false)
+	}
+
+	/// Worker threads and node batch size used to scan the UTXO set
+	pub fn scan_options(&self) -> ScanOptions {
+		ScanOptions::new(self.restore_threads, self.restore_batch_size)
+	}
+
+	/// Days of confirmed transactions whose kernels are looked up at
+	/// startup, 0 to skip the check
+	pub fn kernel_check_days(&self) -> u64 {
+		self.kernel_check_days.unwrap_or(7)
+	}
+
+	/// Days after which the addresses, labels, notes, proofs and stored
+	/// files of finished transactions are wiped, kept forever unless set
+	pub fn history_retention_days(&self) -> Option<u64> {
+		self.history_retention_days.filter(|d| *d > 0)
+	}
+
+	/// Directory backups are written to, `backups` in the data directory
+	/// unless set
+	pub fn backup_dir(&self) -> Result<PathBuf> {
+		match &self.backup_dir {
+			Some(dir) => Ok(PathBuf::from(dir)),
+			None => Ok(self.get_data_path()?.join("backups")),
+		}
+	}
+
+	/// Hours between scheduled backups, none unless set
+	pub fn backup_interval_hours(&self) -> Option<u64> {
+		self.backup_interval_hours.filter(|h| *h > 0)
+	}
+
+	/// Received and finalized transactions after which the wallet is
+	/// backed up, none unless set
+	pub fn backup_every_txs(&self) -> Option<u32> {
+		self.backup_every_txs.filter(|n| *n > 0)
+	}
+
+	/// Backups kept in the backup directory, 0 to keep them all
+	pub fn backup_keep(&self) -> usize {
+		self.backup_keep.unwrap_or(10)
+	}
+
+	/// Whether backups are taken without being asked for
+	pub fn backup_schedule(&self) -> bool {
+		self.backup_interval_hours().is_some() || self.backup_every_txs().is_some()
+	}
+
+	/// Minutes without commands after which the command line locks the
+	/// wallet, never unless set
+	pub fn auto_lock_minutes(&self) -> Option<u64> {
+		self.auto_lock_minutes.filter(|m| *m > 0)
+	}
+
+	/// Whether unanswered grinbox and keybase slates are queued and posted again
+	pub fn slate_retry(&self) -> bool {
+		self.slate_retry.unwrap_or(true)
+	}
+
+	/// Posts of a queued slate before giving up, 0 to keep trying
+	pub fn slate_retry_max_attempts(&self) -> u32 {
+		self.slate_retry_max_attempts.unwrap_or(10)
+	}
+
+	/// Whether outstanding transactions are refreshed on every new block
+	pub fn block_listener(&self) -> bool {
+		self.block_listener.unwrap_or(false)
+	}
+
+	/// Seconds between looking for a new block, 10 unless set
+	pub fn block_listener_interval(&self) -> u64 {
+		self.block_listener_interval
+			.filter(|s| *s > 0)
+			.unwrap_or(10)
+	}
+
+	/// Where events are delivered for accounts without their own route
+	pub fn notification_route(&self) -> NotificationRoute {
+		NotificationRoute {
+			webhook: self.notify_webhook.clone(),
+			script: self.notify_script.clone(),
+		}
+	}
+
+	/// Scripts run on wallet events, by event name
+	pub fn event_hooks(&self) -> HashMap<&'static str, String> {
+		let hooks = vec![
+			("slate_received", &self.hook_slate_received),
+			("tx_finalized", &self.hook_tx_finalized),
+			("tx_confirmed", &self.hook_tx_confirmed),
+			("listener_down", &self.hook_listener_down),
+		];
+		hooks
+			.into_iter()
+			.filter_map(|(event, script)| script.clone().map(|s| (event, s)))
+			.collect()
+	}
+
+	/// Certificate chain and private key (both PEM) the owner APIs are served
+	/// over TLS with, `tls_cert_file` and `tls_key_file` unless set for them
+	pub fn owner_api_tls_files(&self) -> Result<Option<(String, String)>> {
+		tls_pair(
+			("owner_api_tls_cert_file", &self.owner_api_tls_cert_file),
+			("owner_api_tls_key_file", &self.owner_api_tls_key_file),
+		)?
+		.map_or_else(|| self.tls_files(), |files| Ok(Some(files)))
+	}
+
+	/// Certificate chain and private key (both PEM) the foreign API is served
+	/// over TLS with, `tls_cert_file` and `tls_key_file` unless set for it
+	pub fn foreign_api_tls_files(&self) -> Result<Option<(String, String)>> {
+		tls_pair(
+			("foreign_api_tls_cert_file", &self.foreign_api_tls_cert_file),
+			("foreign_api_tls_key_file", &self.foreign_api_tls_key_file),
+		)?
+		.map_or_else(|| self.tls_files(), |files| Ok(Some(files)))
+	}
+
+	/// Certificate chain and private key shared by the HTTP listeners. Plain
+	/// HTTP is used when neither these nor the listener's own are set
+	pub fn tls_files(&self) -> Result<Option<(String, String)>> {
+		tls_pair(
+			("tls_cert_file", &self.tls_cert_file),
+			("tls_key_file", &self.tls_key_file),
+		)
+	}
+
+	/// Logging of the daemon: text to stderr at `warn` unless configured.
+	/// The rotation size is given in megabytes
+	pub fn log_config(&self) -> Result<LogConfig> {
+		let format = match &self.log_format {
+			Some(f) => f.parse()?,
+			None => LogFormat::Text,
+		};
+		Ok(LogConfig {
+			format,
+			levels: self.log_level.clone().unwrap_or("warn".to_owned()),
+			file: self.log_file.clone().filter(|f| !f.is_empty()),
+			max_size: self.log_file_max_size.unwrap_or(10) * 1024 * 1024,
+			max_files: self.log_file_max_files.unwrap_or(5),
+		})
+	}
+
+	pub fn check_updates(&self, runtime: &RuntimeContext) -> bool {
+		self.check_updates.unwrap_or(runtime.is_cli())
+	}
+
+	/// Time the daemon gives the operation in progress to finish when asked
+	/// to stop, 10 seconds unless set
+	pub fn shutdown_grace(&self) -> Duration {
+		Duration::from_secs(self.shutdown_grace_seconds.unwrap_or(10))
+	}
+
+	pub fn default_change_outputs(&self) -> u32 {
+		self.default_change_outputs.unwrap_or(1).max(1)
+	}
+
+	/// Blocks after which an unconfirmed send is cancelled, disabled unless set
+	pub fn tx_auto_cancel_after(&self) -> Option<u64> {
+		self.tx_auto_cancel_after.filter(|b| *b > 0)
+	}
+
+	/// Amount in nanogrin above which a send has to be confirmed a second
+	/// time, disabled unless set
+	pub fn confirm_threshold(&self) -> Option<u64> {
+		self.confirm_threshold.filter(|a| *a > 0)
+	}
+
+	/// Amount in nanogrin that can be sent per day (UTC), unlimited unless set
+	pub fn daily_spend_limit(&self) -> Option<u64> {
+		self.daily_spend_limit.filter(|a| *a > 0)
+	}
+
+	/// Rules incoming slates have to pass, nothing is checked unless set
+	pub fn receive_policy(&self) -> ReceivePolicy {
+		ReceivePolicy {
+			min_amount: self.receive_min_amount.filter(|a| *a > 0),
+			allowed_senders: self.receive_allowed_senders.clone(),
+			max_per_hour: self.receive_max_per_hour.filter(|m| *m > 0),
+			manual_approval: self.receive_manual_approval.unwrap_or(false),
+		}
+	}
+
+	/// Minutes in which paying the same amount to the same address again
+	/// needs to be forced, an hour unless set. 0 disables the check
+	pub fn duplicate_send_window(&self) -> Option<u64> {
+		Some(self.duplicate_send_window.unwrap_or(60)).filter(|m| *m > 0)
+	}
+
+	/// Automatic repost of unconfirmed transactions, disabled unless
+	/// `auto_repost_blocks` is set
+	pub fn repost_policy(&self) -> Option<RepostPolicy> {
+		match self.auto_repost_blocks {
+			Some(after_blocks) if after_blocks > 0 => Some(RepostPolicy {
+				after_blocks,
+				max_attempts: self.auto_repost_max_attempts.unwrap_or(3),
+				fluff: false,
+			}),
+			_ => None,
+		}
+	}
+}
+
+fn keybase_ttl(ttl: &Option<String>) -> Option<String> {
+	ttl.clone().filter(|t| !t.is_empty() && t != "none")
+}
+
+/// Certificate and key settings, which have to be set together
+fn tls_pair(
+	cert: (&str, &Option<String>),
+	key: (&str, &Option<String>),
+) -> Result<Option<(String, String)>> {
+	match (cert.1, key.1) {
+		(Some(cert), Some(key)) => Ok(Some((cert.clone(), key.clone()))),
+		(None, None) => Ok(None),
+		_ => Err(ErrorKind::InvalidTlsConfig(format!(
+			"both {} and {} are required",
+			cert.0, key.0
+		)))?,
 	}
 }
 
@@ -268,6 +737,24 @@ pub struct WalletConfig {
 	pub dark_background_color_scheme: Option<bool>,
 	/// The exploding lifetime (minutes) for keybase notification on coins received
 	pub keybase_notify_ttl: Option<u16>,
+	/// Number of threads rewinding range proofs during restore and check
+	#[serde(default)]
+	pub restore_threads: Option<usize>,
+	/// Number of outputs requested from the node per call during restore
+	#[serde(default)]
+	pub restore_batch_size: Option<u64>,
+	/// Select inputs to keep the number of outputs low instead of smallest first
+	#[serde(default)]
+	pub adaptive_selection: Option<bool>,
+	/// Smallest output, in nanogrin, a send creates as change or a receive accepts
+	#[serde(default)]
+	pub min_output_value: Option<u64>,
+	/// Fee per unit of transaction weight, in nanogrin. Grin's default when not set
+	#[serde(default)]
+	pub fee_base: Option<u64>,
+	/// Spend one of our outputs into every received transaction
+	#[serde(default)]
+	pub payjoin: Option<bool>,
 }
 
 impl Default for WalletConfig {
@@ -287,6 +774,12 @@ impl Default for WalletConfig {
 			tls_certificate_key: None,
 			dark_background_color_scheme: Some(true),
 			keybase_notify_ttl: Some(1440),
+			restore_threads: None,
+			restore_batch_size: None,
+			adaptive_selection: None,
+			min_output_value: None,
+			fee_base: None,
+			payjoin: None,
 		}
 	}
 }
@@ -0,0 +1,73 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Raw DEFLATE (RFC 1951) streams, through flate2
+
+use super::ErrorKind;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+/// Compress `data` into a raw DEFLATE stream
+pub fn compress(data: &[u8]) -> Vec<u8> {
+	let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+	// Writing to a vector can't fail
+	encoder.write_all(data).expect("compressing in memory");
+	encoder.finish().expect("compressing in memory")
+}
+
+/// Decompress a raw DEFLATE stream, refusing to produce more than `max_len`
+/// bytes. Only one byte past the limit is ever inflated
+pub fn decompress(data: &[u8], max_len: usize) -> Result<Vec<u8>, ErrorKind> {
+	let mut out = Vec::new();
+	DeflateDecoder::new(data)
+		.take(max_len as u64 + 1)
+		.read_to_end(&mut out)
+		.map_err(|_| ErrorKind::InvalidCompressedData)?;
+	if out.len() > max_len {
+		return Err(ErrorKind::InvalidCompressedData);
+	}
+	Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn round_trips() {
+		let data = b"BEGINSLATE_COMPACT. abcabcabcabc, abcabcabc ENDSLATE_COMPACT.".repeat(50);
+		let compressed = compress(&data);
+		assert!(compressed.len() < data.len());
+		assert_eq!(decompress(&compressed, data.len()).unwrap(), data);
+		assert_eq!(decompress(&compress(b""), 0).unwrap(), b"");
+	}
+
+	#[test]
+	fn reads_stored_blocks() {
+		// Final stored block of "hi", as written by other encoders
+		let stored = [0x01, 0x02, 0x00, 0xfd, 0xff, b'h', b'i'];
+		assert_eq!(decompress(&stored, 2).unwrap(), b"hi");
+	}
+
+	#[test]
+	fn refuses_output_over_the_limit() {
+		let bomb = compress(&vec![0; 1024 * 1024]);
+		assert!(bomb.len() < 2048);
+		assert!(decompress(&bomb, 1024 * 1024).is_ok());
+		assert!(decompress(&bomb, 1024 * 1024 - 1).is_err());
+		assert!(decompress(b"not deflate", 1024).is_err());
+	}
+}
@@ -0,0 +1,99 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::{Arc, Mutex};
+use grin_util::secp::key::SecretKey;
+use grin_util::secp::Secp256k1;
+use rand::rngs::StdRng;
+use rand::{thread_rng, RngCore, SeedableRng};
+use uuid::Uuid;
+
+pub type SharedEntropy = Arc<dyn Entropy>;
+
+/// Source of the randomness behind seeds, slate ids, kernel offsets and
+/// encryption salts and nonces. Keeping it in one place makes it possible
+/// to audit where entropy comes from and to make it deterministic in tests
+pub trait Entropy: Send + Sync {
+	/// Short description of the source, for logging
+	fn name(&self) -> &'static str;
+
+	fn fill_bytes(&self, dest: &mut [u8]);
+
+	fn bytes(&self, len: usize) -> Vec<u8> {
+		let mut bytes = vec![0; len];
+		self.fill_bytes(&mut bytes);
+		bytes
+	}
+
+	fn uuid(&self) -> Uuid {
+		let mut bytes = [0; 16];
+		self.fill_bytes(&mut bytes);
+		Uuid::from_random_bytes(bytes)
+	}
+
+	fn secret_key(&self, secp: &Secp256k1) -> SecretKey {
+		// Out of range values are so unlikely that retrying never loops in practice
+		let mut bytes = [0; 32];
+		loop {
+			self.fill_bytes(&mut bytes);
+			if let Ok(key) = SecretKey::from_slice(secp, &bytes) {
+				return key;
+			}
+		}
+	}
+}
+
+/// Randomness of the operating system, through the thread local generator
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OsEntropy;
+
+impl OsEntropy {
+	pub fn shared() -> SharedEntropy {
+		Arc::new(OsEntropy)
+	}
+}
+
+impl Entropy for OsEntropy {
+	fn name(&self) -> &'static str {
+		"os"
+	}
+
+	fn fill_bytes(&self, dest: &mut [u8]) {
+		thread_rng().fill_bytes(dest);
+	}
+}
+
+/// Deterministic generator, the same seed always gives the same bytes.
+/// Never use it for a wallet holding funds
+pub struct SeededEntropy {
+	rng: Mutex<StdRng>,
+}
+
+impl SeededEntropy {
+	pub fn new(seed: [u8; 32]) -> Self {
+		Self {
+			rng: Mutex::new(StdRng::from_seed(seed)),
+		}
+	}
+}
+
+impl Entropy for SeededEntropy {
+	fn name(&self) -> &'static str {
+		"seeded"
+	}
+
+	fn fill_bytes(&self, dest: &mut [u8]) {
+		self.rng.lock().fill_bytes(dest);
+	}
+}
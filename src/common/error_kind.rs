@@ -24,8 +24,17 @@ pub enum ErrorKind {
 	WalletNoSeed,
 	#[fail(display = "Wallet already connected")]
 	WalletConnected,
+	#[fail(
+		display = "Wallet {} is in use by {}, close it or use --force-unlock if it is gone",
+		0, 1
+	)]
+	WalletInUse(String, String),
+	#[fail(display = "Unable to lock the wallet: {}", 0)]
+	LockFile(String),
 	#[fail(display = "Unable to open wallet")]
 	OpenWalletError,
+	#[fail(display = "Wallet is locked")]
+	WalletLocked,
 	#[fail(display = "Unable derive keychain")]
 	DeriveKeychainError,
 	#[fail(display = "Wallet should be empty before attempting restore")]
@@ -38,12 +47,22 @@ pub enum ErrorKind {
 	ClosedListener(String),
 	#[fail(display = "Contact '{}' already exists!", 0)]
 	ContactAlreadyExists(String),
+	#[fail(display = "Unknown contacts file format '{}'", 0)]
+	UnknownContactsFormat(String),
+	#[fail(display = "Unknown export format '{}'", 0)]
+	UnknownExportFormat(String),
+	#[fail(display = "Unknown merge strategy '{}'", 0)]
+	UnknownMergeStrategy(String),
+	#[fail(display = "Invalid contacts file: {}", 0)]
+	InvalidContactsFile(String),
 	#[fail(display = "Invalid base58 character!")]
 	InvalidBase58Character(char, usize),
 	#[fail(display = "Invalid base58 length")]
 	InvalidBase58Length,
 	#[fail(display = "Invalid base58 checksum")]
 	InvalidBase58Checksum,
+	#[fail(display = "Invalid base64 data")]
+	Base64Decoding,
 	#[fail(display = "Invalid base58 version bytes")]
 	InvalidBase58Version,
 	#[fail(display = "Invalid key")]
@@ -54,16 +73,33 @@ pub enum ErrorKind {
 	UnknownAddressType(String),
 	#[fail(display = "Could not parse '{}' to a grinbox address", 0)]
 	GrinboxAddressParsingError(String),
+	#[fail(display = "Could not parse '{}' to a grinbox relay", 0)]
+	GrinboxRelayParsingError(String),
+	#[fail(display = "Contact '{}' has a relay but no grinbox address", 0)]
+	RelayOnNonGrinboxContact(String),
 	#[fail(display = "Could not parse '{}' to a keybase address", 0)]
 	KeybaseAddressParsingError(String),
 	#[fail(display = "Could not parse `{}` to a http address", 0)]
 	HttpAddressParsingError(String),
+	#[fail(display = "Could not parse '{}' to an email address", 0)]
+	EmailAddressParsingError(String),
+	#[fail(display = "Could not parse '{}' to an adapter address", 0)]
+	ExecAddressParsingError(String),
 	#[fail(display = "Unable to parse address")]
 	ParseAddress,
+	#[fail(
+		display = "Invalid keybase exploding lifetime '{}', use e.g. 30s, 10m, 24h or 7d",
+		0
+	)]
+	KeybaseTtl(String),
+	#[fail(display = "Email server error: {}", 0)]
+	EmailServer(String),
 	#[fail(display = "Could not send keybase message")]
 	KeybaseMessageSendError,
 	#[fail(display = "Keybase not found! Consider installing it first")]
 	KeybaseNotFound,
+	#[fail(display = "Adapter {} error: {}", 0, 1)]
+	ExecAdapter(String, String),
 	#[fail(display = "Grinbox websocket terminated unexpectedly")]
 	GrinboxWebsocketAbnormalTermination,
 	#[fail(display = "Unable to encrypt message")]
@@ -74,6 +110,8 @@ pub enum ErrorKind {
 	Restore,
 	#[fail(display = "Unknown account '{}'", 0)]
 	UnknownAccountLabel(String),
+	#[fail(display = "Passwords don't match")]
+	PasswordMismatch,
 	#[fail(display = "{}", 0)]
 	GenericError(String),
 	#[fail(display = "{}", 0)]
@@ -82,8 +120,43 @@ pub enum ErrorKind {
 	Argument(String),
 	#[fail(display = "Unable to parse number '{}'", 0)]
 	ParseNumber(String),
+	#[fail(display = "Invalid amount '{}': {}", 0, 1)]
+	InvalidAmount(String, String),
+	#[fail(display = "Unable to handle signals: {}", 0)]
+	Signal(String),
+	#[fail(display = "Unable to parse date '{}', expected YYYY-MM-DD", 0)]
+	ParseDate(String),
+	#[fail(display = "Invalid note '{}', expected key=value", 0)]
+	InvalidNote(String),
+	#[fail(
+		display = "Invalid recipient '{}', expected amount or amount=address",
+		0
+	)]
+	InvalidRecipient(String),
 	#[fail(display = "Unable to parse slate")]
 	ParseSlate,
+	#[fail(display = "Unable to parse exported context")]
+	ParseContext,
+	#[fail(display = "Unable to parse seed backup")]
+	ParseSeedBackup,
+	#[fail(display = "Unable to parse multisig message")]
+	ParseMultisig,
+	#[fail(display = "Unable to parse ID '{}'", 0)]
+	ParseId(String),
 	#[fail(display = "Incorrect listener interface")]
 	IncorrectListenerInterface,
+	#[fail(display = "Invalid TLS configuration: {}", 0)]
+	InvalidTlsConfig(String),
+	#[fail(display = "Unable to read node secret from '{}': {}", 0, 1)]
+	NodeSecretFile(String, String),
+	#[fail(display = "No watch key imported")]
+	NoWatchKey,
+	#[fail(display = "Invalid log configuration: {}", 0)]
+	InvalidLogConfig(String),
+	#[fail(display = "Invalid compressed data")]
+	InvalidCompressedData,
+	#[fail(display = "{} bytes don't fit in a QR code", 0)]
+	QrCodeTooLong(usize),
+	#[fail(display = "Invalid query: {}", 0)]
+	InvalidQuery(String),
 }
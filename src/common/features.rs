@@ -0,0 +1,67 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Optional capabilities of this build and config, as reported by the
+//! subsystems providing them
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Feature {
+	pub name: &'static str,
+	/// Whether this binary supports the feature at all
+	pub compiled: bool,
+	/// Whether the config turns the feature on
+	pub enabled: bool,
+	/// Why the feature is off, or how it is set up
+	pub detail: String,
+}
+
+impl Feature {
+	pub fn new(name: &'static str, compiled: bool, enabled: bool, detail: &str) -> Self {
+		Self {
+			name,
+			compiled,
+			enabled: compiled && enabled,
+			detail: detail.to_owned(),
+		}
+	}
+}
+
+/// Features registered by the subsystems at startup, by name
+#[derive(Clone, Debug, Default)]
+pub struct FeatureRegistry {
+	features: BTreeMap<&'static str, Feature>,
+}
+
+impl FeatureRegistry {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add a feature, replacing an earlier registration with the same name
+	pub fn register(&mut self, feature: Feature) {
+		self.features.insert(feature.name, feature);
+	}
+
+	pub fn get(&self, name: &str) -> Option<&Feature> {
+		self.features.get(name)
+	}
+
+	/// All features, sorted by name
+	pub fn list(&self) -> Vec<Feature> {
+		self.features.values().cloned().collect()
+	}
+}
@@ -16,7 +16,7 @@ use crate::common::Result;
 use digest::generic_array::GenericArray;
 use grin_core::global::is_floonet;
 use grin_keychain::extkey_bip32::{BIP32Hasher, ChildNumber, ExtendedPrivKey};
-use grin_keychain::{Keychain, SwitchCommitmentType};
+use grin_keychain::{Identifier, Keychain, SwitchCommitmentType};
 use grin_util::secp::key::SecretKey;
 use hmac::{Hmac, Mac};
 use ripemd160::Ripemd160;
@@ -92,3 +92,42 @@ pub fn derive_address_key<K: Keychain>(keychain: &K, index: u32) -> Result<Secre
 		.ckd_priv(secp, &mut hasher, ChildNumber::from_normal_idx(index))?
 		.secret_key)
 }
+
+/// Key of the grinbox address at `index` of the account at `parent_key_id`.
+/// The default account keeps the addresses of `derive_address_key`, every
+/// other account derives its addresses from a hardened child of its own
+pub fn derive_account_address_key<K: Keychain>(
+	keychain: &K,
+	parent_key_id: &Identifier,
+	index: u32,
+) -> Result<SecretKey> {
+	let account = account_number(parent_key_id);
+	if account == 0 {
+		return derive_address_key(keychain, index);
+	}
+	let root = keychain.derive_key(713, &K::root_key_id(), &SwitchCommitmentType::Regular)?;
+	let mut hasher = BIP32GrinboxHasher::new(is_floonet());
+	let secp = keychain.secp();
+	let master = ExtendedPrivKey::new_master(secp, &mut hasher, &root.0)?;
+	Ok(master
+		.ckd_priv(secp, &mut hasher, ChildNumber::from_hardened_idx(account))?
+		.ckd_priv(secp, &mut hasher, ChildNumber::from_normal_idx(index))?
+		.secret_key)
+}
+
+/// Secret of the X25519 key HTTP senders seal slates to. It doesn't depend
+/// on the grinbox address, so switching address keeps it
+pub fn derive_http_key<K: Keychain>(keychain: &K) -> Result<[u8; 32]> {
+	let root = keychain.derive_key(713, &K::root_key_id(), &SwitchCommitmentType::Regular)?;
+	let mut hmac =
+		Hmac::<Sha256>::new_varkey(b"wallet713 http key").expect("HMAC can take key of any size");
+	hmac.input(&root.0);
+	let mut key = [0; 32];
+	key.copy_from_slice(hmac.result().code().as_slice());
+	Ok(key)
+}
+
+/// Number of the account at `parent_key_id`, 0 for the default account
+pub fn account_number(parent_key_id: &Identifier) -> u32 {
+	<u32>::from(parent_key_id.to_path().path[0])
+}
@@ -0,0 +1,244 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{ErrorKind, Mutex, Result};
+use chrono::Utc;
+use log::{LevelFilter, Log, Metadata, Record};
+use serde_json::json;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Overrides the configured levels, same syntax as `log_level`
+const LOG_ENV: &str = "RUST_LOG";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+	Text,
+	/// One JSON object per line
+	Json,
+}
+
+impl FromStr for LogFormat {
+	type Err = ErrorKind;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		match s {
+			"text" => Ok(LogFormat::Text),
+			"json" => Ok(LogFormat::Json),
+			_ => Err(ErrorKind::InvalidLogConfig(format!(
+				"unknown format '{}'",
+				s
+			))),
+		}
+	}
+}
+
+/// Logging of the daemon, from the `log_*` entries of the config
+#[derive(Clone, Debug)]
+pub struct LogConfig {
+	pub format: LogFormat,
+	/// Default level followed by per-module levels, e.g. `info,ws=warn`
+	pub levels: String,
+	/// Log to this file instead of stderr
+	pub file: Option<String>,
+	/// Size in bytes at which the log file is rotated
+	pub max_size: u64,
+	/// Number of rotated files kept next to the log file
+	pub max_files: u32,
+}
+
+/// Level of each module, the most specific one applies
+struct Filter {
+	default: LevelFilter,
+	modules: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+	fn parse(levels: &str) -> Result<Self> {
+		let mut filter = Filter {
+			default: LevelFilter::Warn,
+			modules: Vec::new(),
+		};
+		for directive in levels.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+			let invalid = || ErrorKind::InvalidLogConfig(format!("invalid level '{}'", directive));
+			match directive.find('=') {
+				Some(i) => {
+					let level =
+						LevelFilter::from_str(&directive[i + 1..]).map_err(|_| invalid())?;
+					filter.modules.push((directive[..i].to_owned(), level));
+				}
+				None => filter.default = LevelFilter::from_str(directive).map_err(|_| invalid())?,
+			}
+		}
+		// Longest module first, so the first match is the most specific
+		filter.modules.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+		Ok(filter)
+	}
+
+	fn level(&self, target: &str) -> LevelFilter {
+		self.modules
+			.iter()
+			.find(|(module, _)| {
+				target.starts_with(module.as_str())
+					&& (target.len() == module.len() || target[module.len()..].starts_with("::"))
+			})
+			.map(|(_, level)| *level)
+			.unwrap_or(self.default)
+	}
+
+	fn max_level(&self) -> LevelFilter {
+		self.modules
+			.iter()
+			.map(|(_, level)| *level)
+			.fold(self.default, |a, b| a.max(b))
+	}
+}
+
+/// Log file renamed to `<file>.1`, `<file>.2`, .. once it grows too large
+struct RotatingFile {
+	path: String,
+	file: File,
+	size: u64,
+	max_size: u64,
+	max_files: u32,
+}
+
+impl RotatingFile {
+	fn open(path: &str, max_size: u64, max_files: u32) -> io::Result<Self> {
+		let file = OpenOptions::new().create(true).append(true).open(path)?;
+		let size = file.metadata()?.len();
+		Ok(Self {
+			path: path.to_owned(),
+			file,
+			size,
+			max_size,
+			max_files,
+		})
+	}
+
+	fn rotate(&mut self) -> io::Result<()> {
+		for i in (1..self.max_files).rev() {
+			let from = format!("{}.{}", self.path, i);
+			if fs::metadata(&from).is_ok() {
+				fs::rename(&from, format!("{}.{}", self.path, i + 1))?;
+			}
+		}
+		if self.max_files > 0 {
+			fs::rename(&self.path, format!("{}.1", self.path))?;
+		}
+		self.file = OpenOptions::new()
+			.create(true)
+			.write(true)
+			.truncate(true)
+			.open(&self.path)?;
+		self.size = 0;
+		Ok(())
+	}
+
+	fn write_line(&mut self, line: &str) -> io::Result<()> {
+		if self.max_size > 0 && self.size > 0 && self.size + line.len() as u64 > self.max_size {
+			self.rotate()?;
+		}
+		self.file.write_all(line.as_bytes())?;
+		self.size += line.len() as u64;
+		Ok(())
+	}
+}
+
+struct Logger {
+	format: LogFormat,
+	filter: Filter,
+	file: Option<Mutex<RotatingFile>>,
+}
+
+impl Logger {
+	fn format(&self, record: &Record) -> String {
+		let now = Utc::now();
+		match self.format {
+			LogFormat::Text => format!(
+				"{} {:<5} {}: {}\n",
+				now.format("%Y-%m-%d %H:%M:%S%.3f"),
+				record.level(),
+				record.target(),
+				record.args()
+			),
+			LogFormat::Json => {
+				let line = json!({
+					"ts": now.to_rfc3339(),
+					"level": record.level().to_string(),
+					"target": record.target(),
+					"message": record.args().to_string(),
+				});
+				format!("{}\n", line)
+			}
+		}
+	}
+}
+
+impl Log for Logger {
+	fn enabled(&self, metadata: &Metadata) -> bool {
+		metadata.level() <= self.filter.level(metadata.target())
+	}
+
+	fn log(&self, record: &Record) {
+		if !self.enabled(record.metadata()) {
+			return;
+		}
+		let line = self.format(record);
+		let res = match &self.file {
+			Some(file) => file.lock().write_line(&line),
+			None => io::stderr().write_all(line.as_bytes()),
+		};
+		if let Err(e) = res {
+			eprintln!("Unable to write log: {}", e);
+		}
+	}
+
+	fn flush(&self) {
+		match &self.file {
+			Some(file) => {
+				let _ = file.lock().file.flush();
+			}
+			None => {
+				let _ = io::stderr().flush();
+			}
+		}
+	}
+}
+
+/// Install the logger of the daemon. `RUST_LOG`, when set, takes the place
+/// of the configured levels
+pub fn init(config: &LogConfig) -> Result<()> {
+	let levels = std::env::var(LOG_ENV).unwrap_or_else(|_| config.levels.clone());
+	let filter = Filter::parse(&levels)?;
+	let file = match &config.file {
+		Some(path) => Some(Mutex::new(RotatingFile::open(
+			path,
+			config.max_size,
+			config.max_files,
+		)?)),
+		None => None,
+	};
+	let max_level = filter.max_level();
+	let logger = Logger {
+		format: config.format,
+		filter,
+		file,
+	};
+	log::set_logger(Box::leak(Box::new(logger)))
+		.map_err(|_| ErrorKind::InvalidLogConfig("logger already set".to_owned()))?;
+	log::set_max_level(max_level);
+	Ok(())
+}
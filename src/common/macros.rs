@@ -12,43 +12,49 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+/// Print a message above the prompt when `$runtime` is interactive, to
+/// stderr for single commands, log it otherwise
 #[macro_export]
 macro_rules! cli_message {
-        () => {
+        ($runtime:expr) => {
             {
                 use std::io::Write;
-                use crate::common::{is_cli, COLORED_PROMPT};
-                if is_cli() {
+                use $crate::common::COLORED_PROMPT;
+                if $runtime.is_cli() {
                     print!("\r{}", COLORED_PROMPT);
                     std::io::stdout().flush().unwrap();
                 }
             }
         };
 
-        ($fmt_string:expr, $( $arg:expr ),+) => {
+        ($runtime:expr, $fmt_string:expr, $( $arg:expr ),+) => {
             {
                 use std::io::Write;
-                use crate::common::{is_cli, COLORED_PROMPT};
-                if is_cli() {
+                use $crate::common::COLORED_PROMPT;
+                if $runtime.is_cli() {
                     print!("\r");
                     print!($fmt_string, $( $arg ),*);
                     print!("\n{}", COLORED_PROMPT);
                     std::io::stdout().flush().unwrap();
+                } else if $runtime.is_exec() {
+                    eprintln!($fmt_string, $( $arg ),*);
                 } else {
                     log::info!($fmt_string, $( $arg ),*);
                 }
             }
         };
 
-        ($fmt_string:expr) => {
+        ($runtime:expr, $fmt_string:expr) => {
             {
                 use std::io::Write;
-                use crate::common::{is_cli, COLORED_PROMPT};
-                if is_cli() {
+                use $crate::common::COLORED_PROMPT;
+                if $runtime.is_cli() {
                     print!("\r");
                     print!($fmt_string);
                     print!("\n{}", COLORED_PROMPT);
                     std::io::stdout().flush().unwrap();
+                } else if $runtime.is_exec() {
+                    eprintln!($fmt_string);
                 } else {
                     log::info!($fmt_string);
                 }
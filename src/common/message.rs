@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use crate::common::crypto::{from_hex, to_hex};
-use crate::common::{ErrorKind, Result};
+use crate::common::{deflate, ErrorKind, Result};
 use crate::contacts::GrinboxAddress;
 use grin_util::secp::key::{PublicKey, SecretKey};
 use grin_util::secp::Secp256k1;
@@ -23,20 +23,42 @@ use ring::aead;
 use ring::{digest, pbkdf2};
 use serde::{Deserialize, Serialize};
 
+/// Version of the messages written by this wallet. Peers writing at least
+/// version 2 read compressed payloads, older ones write no version at all
+const MESSAGE_VERSION: u8 = 2;
+const COMPRESSION_VERSION: u8 = 2;
+/// Decompressed payloads larger than this are refused
+const MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+	Deflate,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EncryptedMessage {
 	pub destination: GrinboxAddress,
 	encrypted_message: String,
 	salt: String,
 	nonce: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	version: Option<u8>,
+	/// Compression of the payload, applied before encrypting it
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	compression: Option<Compression>,
 }
 
 impl EncryptedMessage {
+	/// Encrypt `message` for the receiver, compressing it first if `compress`
+	/// is set and that makes it smaller. Only set it for receivers known to
+	/// read compressed payloads
 	pub fn new(
 		message: String,
 		destination: &GrinboxAddress,
 		receiver_public_key: &PublicKey,
 		secret_key: &SecretKey,
+		compress: bool,
 	) -> Result<EncryptedMessage> {
 		let secp = Secp256k1::new();
 		let mut common_secret = receiver_public_key.clone();
@@ -50,7 +72,12 @@ impl EncryptedMessage {
 		let nonce: [u8; 12] = thread_rng().gen();
 		let mut key = [0; 32];
 		pbkdf2::derive(&digest::SHA512, 100, &salt, common_secret_slice, &mut key);
-		let mut enc_bytes = message.as_bytes().to_vec();
+		let compressed = match compress {
+			true => Some(deflate::compress(message.as_bytes())).filter(|c| c.len() < message.len()),
+			false => None,
+		};
+		let compression = compressed.as_ref().map(|_| Compression::Deflate);
+		let mut enc_bytes = compressed.unwrap_or_else(|| message.into_bytes());
 		let suffix_len = aead::CHACHA20_POLY1305.tag_len();
 		for _ in 0..suffix_len {
 			enc_bytes.push(0);
@@ -65,9 +92,25 @@ impl EncryptedMessage {
 			encrypted_message: to_hex(enc_bytes),
 			salt: to_hex(salt.to_vec()),
 			nonce: to_hex(nonce.to_vec()),
+			version: Some(MESSAGE_VERSION),
+			compression,
 		})
 	}
 
+	/// Version of the sender, 1 for senders that don't write one
+	pub fn version(&self) -> u8 {
+		self.version.unwrap_or(1)
+	}
+
+	/// Whether the sender reads compressed payloads as well
+	pub fn supports_compression(&self) -> bool {
+		self.version() >= COMPRESSION_VERSION
+	}
+
+	pub fn is_compressed(&self) -> bool {
+		self.compression.is_some()
+	}
+
 	pub fn key(&self, sender_public_key: &PublicKey, secret_key: &SecretKey) -> Result<[u8; 32]> {
 		let salt = from_hex(self.salt.clone()).map_err(|_| ErrorKind::Decryption)?;
 
@@ -95,7 +138,59 @@ impl EncryptedMessage {
 		let decrypted_data =
 			aead::open_in_place(&opening_key, &nonce, &[], 0, &mut encrypted_message)
 				.map_err(|_| ErrorKind::Decryption)?;
+		let decrypted_data = match self.compression {
+			Some(Compression::Deflate) => deflate::decompress(decrypted_data, MAX_MESSAGE_SIZE)
+				.map_err(|_| ErrorKind::Decryption)?,
+			None => decrypted_data.to_vec(),
+		};
+
+		String::from_utf8(decrypted_data).map_err(|_| ErrorKind::Decryption.into())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn keys() -> (GrinboxAddress, PublicKey, SecretKey) {
+		let secp = Secp256k1::new();
+		let secret_key = SecretKey::new(&secp, &mut thread_rng());
+		let public_key = PublicKey::from_secret_key(&secp, &secret_key).unwrap();
+		let address = GrinboxAddress::new(public_key.clone(), None, None);
+		(address, public_key, secret_key)
+	}
+
+	fn open(message: &EncryptedMessage, from: &PublicKey, to: &SecretKey) -> Result<String> {
+		message.decrypt_with_key(&message.key(from, to)?)
+	}
+
+	#[test]
+	fn decrypts_compressed_messages() {
+		let (_, from, from_key) = keys();
+		let (to, to_public, to_key) = keys();
+		let text = "slate ".repeat(1000);
+		let message =
+			EncryptedMessage::new(text.clone(), &to, &to_public, &from_key, true).unwrap();
+		assert!(message.is_compressed());
+		assert_eq!(open(&message, &from, &to_key).unwrap(), text);
+	}
+
+	#[test]
+	fn refuses_messages_decompressing_beyond_the_limit() {
+		let (_, from, from_key) = keys();
+		let (to, to_public, to_key) = keys();
+		// A few KiB on the wire that inflate past the limit
+		let bomb = "0".repeat(MAX_MESSAGE_SIZE + 1);
+		let message = EncryptedMessage::new(bomb, &to, &to_public, &from_key, true).unwrap();
+		assert!(message.is_compressed());
+		assert!(message.encrypted_message.len() < 64 * 1024);
+		assert!(open(&message, &from, &to_key).is_err());
 
-		String::from_utf8(decrypted_data.to_vec()).map_err(|_| ErrorKind::Decryption.into())
+		let fits = "0".repeat(MAX_MESSAGE_SIZE);
+		let message = EncryptedMessage::new(fits, &to, &to_public, &from_key, true).unwrap();
+		assert_eq!(
+			open(&message, &from, &to_key).unwrap().len(),
+			MAX_MESSAGE_SIZE
+		);
 	}
 }
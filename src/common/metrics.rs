@@ -0,0 +1,197 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::{Arc, Mutex};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+pub const SLATES_RECEIVED: &str = "wallet713_slates_received_total";
+pub const SLATES_SENT: &str = "wallet713_slates_sent_total";
+pub const FINALIZE: &str = "wallet713_finalize_total";
+pub const GRINBOX_RECONNECTS: &str = "wallet713_grinbox_reconnects_total";
+pub const NODE_REQUEST_SECONDS: &str = "wallet713_node_request_seconds";
+pub const BALANCE: &str = "wallet713_balance_nanogrin";
+pub const HTTP_REJECTED: &str = "wallet713_http_rejected_total";
+pub const STAGE_SECONDS: &str = "wallet713_stage_seconds";
+pub const STAGE_MAX_SECONDS: &str = "wallet713_stage_max_seconds";
+
+pub type SharedMetrics = Arc<Mutex<Metrics>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MetricType {
+	Counter,
+	Gauge,
+	Summary,
+}
+
+impl MetricType {
+	fn name(self) -> &'static str {
+		match self {
+			MetricType::Counter => "counter",
+			MetricType::Gauge => "gauge",
+			MetricType::Summary => "summary",
+		}
+	}
+}
+
+#[derive(Debug)]
+struct Family {
+	help: &'static str,
+	metric_type: MetricType,
+	/// Values by rendered label set. Summaries keep their sum and count
+	series: BTreeMap<String, (f64, u64)>,
+}
+
+/// Counters and gauges of the wallet, rendered in the Prometheus text
+/// format by the `/metrics` endpoint of the owner API
+#[derive(Debug)]
+pub struct Metrics {
+	families: BTreeMap<&'static str, Family>,
+}
+
+impl Metrics {
+	pub fn new() -> SharedMetrics {
+		let mut metrics = Self {
+			families: BTreeMap::new(),
+		};
+		metrics.register(
+			SLATES_RECEIVED,
+			MetricType::Counter,
+			"Slates received, by adapter",
+		);
+		metrics.register(SLATES_SENT, MetricType::Counter, "Slates sent, by adapter");
+		metrics.register(
+			FINALIZE,
+			MetricType::Counter,
+			"Finalized transactions, by result",
+		);
+		metrics.register(
+			GRINBOX_RECONNECTS,
+			MetricType::Counter,
+			"Grinbox reconnect attempts",
+		);
+		metrics.register(
+			NODE_REQUEST_SECONDS,
+			MetricType::Summary,
+			"Duration of requests to the node, by method",
+		);
+		metrics.register(
+			BALANCE,
+			MetricType::Gauge,
+			"Balance of an account, by state",
+		);
+		metrics.register(
+			HTTP_REJECTED,
+			MetricType::Counter,
+			"HTTP requests refused by the API limits, by API and reason",
+		);
+		metrics.register(
+			STAGE_SECONDS,
+			MetricType::Summary,
+			"Execution time of commands and their stages, by stage",
+		);
+		metrics.register(
+			STAGE_MAX_SECONDS,
+			MetricType::Gauge,
+			"Longest execution time of a stage, by stage",
+		);
+		Arc::new(Mutex::new(metrics))
+	}
+
+	fn register(&mut self, name: &'static str, metric_type: MetricType, help: &'static str) {
+		self.families.insert(
+			name,
+			Family {
+				help,
+				metric_type,
+				series: BTreeMap::new(),
+			},
+		);
+	}
+
+	fn series(&mut self, name: &str, labels: &[(&str, &str)]) -> Option<&mut (f64, u64)> {
+		let labels = render_labels(labels);
+		self.families
+			.get_mut(name)
+			.map(|f| f.series.entry(labels).or_insert((0.0, 0)))
+	}
+
+	/// Increment a counter
+	pub fn inc(&mut self, name: &str, labels: &[(&str, &str)]) {
+		if let Some(s) = self.series(name, labels) {
+			s.0 += 1.0;
+		}
+	}
+
+	/// Set the value of a gauge
+	pub fn set(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+		if let Some(s) = self.series(name, labels) {
+			s.0 = value;
+		}
+	}
+
+	/// Add a duration to a summary
+	pub fn observe(&mut self, name: &str, labels: &[(&str, &str)], elapsed: Duration) {
+		if let Some(s) = self.series(name, labels) {
+			s.0 += elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9;
+			s.1 += 1;
+		}
+	}
+
+	/// Set the sum and count of a summary kept elsewhere
+	pub fn set_summary(&mut self, name: &str, labels: &[(&str, &str)], sum: f64, count: u64) {
+		if let Some(s) = self.series(name, labels) {
+			*s = (sum, count);
+		}
+	}
+
+	/// All metrics in the Prometheus text exposition format
+	pub fn render(&self) -> String {
+		let mut out = String::new();
+		for (name, family) in &self.families {
+			let _ = writeln!(out, "# HELP {} {}", name, family.help);
+			let _ = writeln!(out, "# TYPE {} {}", name, family.metric_type.name());
+			for (labels, (value, count)) in &family.series {
+				match family.metric_type {
+					MetricType::Summary => {
+						let _ = writeln!(out, "{}_sum{} {}", name, labels, value);
+						let _ = writeln!(out, "{}_count{} {}", name, labels, count);
+					}
+					_ => {
+						let _ = writeln!(out, "{}{} {}", name, labels, value);
+					}
+				}
+			}
+		}
+		out
+	}
+}
+
+fn render_labels(labels: &[(&str, &str)]) -> String {
+	if labels.is_empty() {
+		return String::new();
+	}
+	let labels: Vec<String> = labels
+		.iter()
+		.map(|(k, v)| {
+			let v = v
+				.replace('\\', "\\\\")
+				.replace('"', "\\\"")
+				.replace('\n', "\\n");
+			format!("{}=\"{}\"", k, v)
+		})
+		.collect();
+	format!("{{{}}}", labels.join(","))
+}
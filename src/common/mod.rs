@@ -14,18 +14,33 @@
 
 #[macro_use]
 pub mod macros;
+pub mod amount;
 pub mod base58;
+pub mod base64;
 pub mod client;
+pub mod clock;
 pub mod config;
 pub mod crypto;
+pub mod deflate;
+pub mod entropy;
 mod error_kind;
+pub mod features;
 pub mod hasher;
+pub mod logging;
 pub mod message;
+pub mod metrics;
 pub mod motd;
+pub mod qr;
+pub mod runtime;
+pub mod sealed;
 pub mod ser;
+pub mod shutdown;
+pub mod timing;
+pub mod x25519;
 
 pub use self::error_kind::ErrorKind;
 pub use self::macros::*;
+pub use self::runtime::{RuntimeContext, RuntimeMode};
 pub use failure::Error;
 pub use parking_lot::{Mutex, MutexGuard};
 use std::result::Result as StdResult;
@@ -35,20 +50,4 @@ pub type Result<T> = StdResult<T, Error>;
 pub trait Keychain: grin_keychain::Keychain + Clone + 'static {}
 impl Keychain for grin_keychain::ExtKeychain {}
 
-#[derive(Clone, PartialEq)]
-pub enum RuntimeMode {
-	Cli,
-	Daemon,
-}
-
-static mut RUNTIME_MODE: RuntimeMode = RuntimeMode::Cli;
-
-pub unsafe fn set_runtime_mode(runtime_mode: &RuntimeMode) {
-	RUNTIME_MODE = runtime_mode.clone();
-}
-
-pub fn is_cli() -> bool {
-	unsafe { RUNTIME_MODE == RuntimeMode::Cli }
-}
-
 pub const COLORED_PROMPT: &'static str = "\x1b[36mwallet713>\x1b[0m ";
@@ -0,0 +1,104 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! QR codes of addresses and slates, drawn in a terminal with the qrcode crate
+
+use super::ErrorKind;
+use qrcode::render::unicode::Dense1x2;
+use qrcode::types::QrError;
+
+/// Error correction level, trading capacity for robustness
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EcLevel {
+	/// Recovers about 7% of the symbol
+	Low,
+	/// Recovers about 15% of the symbol
+	Medium,
+}
+
+impl From<EcLevel> for qrcode::EcLevel {
+	fn from(level: EcLevel) -> Self {
+		match level {
+			EcLevel::Low => qrcode::EcLevel::L,
+			EcLevel::Medium => qrcode::EcLevel::M,
+		}
+	}
+}
+
+pub struct QrCode {
+	code: qrcode::QrCode,
+}
+
+impl QrCode {
+	/// Encode `data` in the smallest version that holds it
+	pub fn encode(data: &[u8], level: EcLevel) -> Result<Self, ErrorKind> {
+		let code = qrcode::QrCode::with_error_correction_level(data, level.into());
+		let code = code.map_err(|e| match e {
+			QrError::DataTooLong => ErrorKind::QrCodeTooLong(data.len()),
+			e => ErrorKind::GenericError(format!("unable to encode QR code: {}", e)),
+		})?;
+		Ok(Self { code })
+	}
+
+	/// Modules per side, without the quiet zone
+	pub fn size(&self) -> usize {
+		self.code.width()
+	}
+
+	/// Two rows of modules per line of text. Dark modules are drawn as
+	/// blanks, so the code reads on terminals with a dark background
+	pub fn render_terminal(&self) -> String {
+		let mut out = self
+			.code
+			.render::<Dense1x2>()
+			.dark_color(Dense1x2::Light)
+			.light_color(Dense1x2::Dark)
+			.build();
+		out.push('\n');
+		out
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn picks_the_smallest_version() {
+		let qr = QrCode::encode(
+			b"xd7sCQ9bQuQXp4yCn8GSELcuSxnpcPrPoEWJzvPBc5vxyXPQz6PJ",
+			EcLevel::Medium,
+		)
+		.unwrap();
+		assert_eq!(qr.size(), 33);
+
+		let lines: Vec<_> = qr
+			.render_terminal()
+			.lines()
+			.map(|l| l.chars().count())
+			.collect();
+		// A quiet zone of four modules around the symbol
+		assert_eq!(lines.len(), (33 + 8 + 1) / 2);
+		assert!(lines.iter().all(|len| *len == 33 + 8));
+	}
+
+	#[test]
+	fn refuses_data_over_the_capacity() {
+		assert!(QrCode::encode(&[b'a'; 2953], EcLevel::Low).is_ok());
+		match QrCode::encode(&[b'a'; 2954], EcLevel::Low) {
+			Err(ErrorKind::QrCodeTooLong(2954)) => {}
+			_ => panic!("expected QrCodeTooLong"),
+		}
+	}
+}
@@ -0,0 +1,88 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::wallet::types::NodeSession;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RuntimeMode {
+	Cli,
+	Daemon,
+	/// A single command given with `--exec`, messages go to stderr
+	Exec,
+}
+
+/// How a wallet instance is run. Handed to the container and everything that
+/// reports to the user, so several wallets can share a process, each in its
+/// own mode
+#[derive(Clone)]
+pub struct RuntimeContext {
+	mode: RuntimeMode,
+	node_session: Option<NodeSession>,
+	force_unlock: bool,
+}
+
+impl RuntimeContext {
+	pub fn new(mode: RuntimeMode) -> Self {
+		Self {
+			mode,
+			node_session: None,
+			force_unlock: false,
+		}
+	}
+
+	/// Record all node calls to, or replay them from, a session bundle
+	pub fn with_node_session(mut self, node_session: Option<NodeSession>) -> Self {
+		self.node_session = node_session;
+		self
+	}
+
+	/// Open the wallet even if its lock file is held, see `Backend::connect`
+	pub fn with_force_unlock(mut self, force_unlock: bool) -> Self {
+		self.force_unlock = force_unlock;
+		self
+	}
+
+	pub fn mode(&self) -> RuntimeMode {
+		self.mode
+	}
+
+	/// Whether messages go to an interactive prompt rather than the log
+	pub fn is_cli(&self) -> bool {
+		self.mode == RuntimeMode::Cli
+	}
+
+	/// Whether the wallet runs in the background, logging instead of printing
+	pub fn is_daemon(&self) -> bool {
+		self.mode == RuntimeMode::Daemon
+	}
+
+	/// Whether a single command is run for a script
+	pub fn is_exec(&self) -> bool {
+		self.mode == RuntimeMode::Exec
+	}
+
+	pub fn node_session(&self) -> Option<NodeSession> {
+		self.node_session.clone()
+	}
+
+	pub fn force_unlock(&self) -> bool {
+		self.force_unlock
+	}
+}
+
+impl Default for RuntimeContext {
+	fn default() -> Self {
+		Self::new(RuntimeMode::Cli)
+	}
+}
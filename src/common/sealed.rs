@@ -0,0 +1,176 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Messages sealed to the X25519 key of the receiving wallet, so that slates
+//! sent over HTTP can't be read by anyone in between. The reply is sealed
+//! with the same key, which only the sender and the receiver know
+
+use crate::common::crypto::{from_hex, to_hex};
+use crate::common::{x25519, ErrorKind, Result};
+use rand::{thread_rng, Rng};
+use ring::{aead, digest, hkdf, hmac};
+use serde::{Deserialize, Serialize};
+
+const KEY_INFO: &[u8] = b"wallet713 sealed message";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SealedMessage {
+	/// Public key of the one-time key of the sender
+	ephemeral_key: String,
+	nonce: String,
+	ciphertext: String,
+}
+
+impl SealedMessage {
+	/// Seal `message` to the public key of the receiver. Also returns the
+	/// key opening the reply
+	pub fn seal(message: &str, receiver_key: &[u8; 32]) -> Result<(Self, [u8; 32])> {
+		let secret: [u8; 32] = thread_rng().gen();
+		let ephemeral_key = x25519::public_key(&secret);
+		let shared = x25519::x25519(&secret, receiver_key);
+		let key = derive_key(&shared, &ephemeral_key, receiver_key).ok_or(ErrorKind::Encryption)?;
+		let sealed = Self::seal_with_key(message, &ephemeral_key, &key)?;
+		Ok((sealed, key))
+	}
+
+	/// Open a message sealed to the public key of `secret`. Also returns the
+	/// key to seal the reply with
+	pub fn open(&self, secret: &[u8; 32]) -> Result<(String, [u8; 32])> {
+		let ephemeral_key = parse_key(&self.ephemeral_key).map_err(|_| ErrorKind::Decryption)?;
+		let shared = x25519::x25519(secret, &ephemeral_key);
+		let public_key = x25519::public_key(secret);
+		let key = derive_key(&shared, &ephemeral_key, &public_key).ok_or(ErrorKind::Decryption)?;
+		let message = self.open_with_key(&key)?;
+		Ok((message, key))
+	}
+
+	/// Seal the reply to this message
+	pub fn reply(&self, message: &str, key: &[u8; 32]) -> Result<Self> {
+		let ephemeral_key = parse_key(&self.ephemeral_key).map_err(|_| ErrorKind::Encryption)?;
+		Self::seal_with_key(message, &ephemeral_key, key)
+	}
+
+	/// Open a reply, with the key returned when sealing the message
+	pub fn open_reply(&self, key: &[u8; 32]) -> Result<String> {
+		self.open_with_key(key)
+	}
+
+	fn seal_with_key(message: &str, ephemeral_key: &[u8; 32], key: &[u8; 32]) -> Result<Self> {
+		let nonce: [u8; 12] = thread_rng().gen();
+		let data = seal(key, &nonce, ephemeral_key, message.as_bytes())?;
+		Ok(Self {
+			ephemeral_key: to_hex(ephemeral_key.to_vec()),
+			nonce: to_hex(nonce.to_vec()),
+			ciphertext: to_hex(data),
+		})
+	}
+
+	fn open_with_key(&self, key: &[u8; 32]) -> Result<String> {
+		let ephemeral_key =
+			from_hex(self.ephemeral_key.clone()).map_err(|_| ErrorKind::Decryption)?;
+		let nonce = from_hex(self.nonce.clone()).map_err(|_| ErrorKind::Decryption)?;
+		let mut data = from_hex(self.ciphertext.clone()).map_err(|_| ErrorKind::Decryption)?;
+
+		let opening_key = aead::OpeningKey::new(&aead::CHACHA20_POLY1305, key)
+			.map_err(|_| ErrorKind::Decryption)?;
+		let message = aead::open_in_place(&opening_key, &nonce, &ephemeral_key, 0, &mut data)
+			.map_err(|_| ErrorKind::Decryption)?;
+
+		String::from_utf8(message.to_vec()).map_err(|_| ErrorKind::Decryption.into())
+	}
+}
+
+/// Parse a public key in hex
+pub fn parse_key(hex: &str) -> Result<[u8; 32]> {
+	let bytes = from_hex(hex.to_owned())?;
+	if bytes.len() != 32 {
+		return Err(ErrorKind::NumberParsingError.into());
+	}
+	let mut key = [0; 32];
+	key.copy_from_slice(&bytes);
+	Ok(key)
+}
+
+/// ChaCha20-Poly1305 (RFC 8439) encryption of `message`, followed by its tag
+fn seal(key: &[u8; 32], nonce: &[u8; 12], aad: &[u8], message: &[u8]) -> Result<Vec<u8>> {
+	let suffix_len = aead::CHACHA20_POLY1305.tag_len();
+	let mut data = message.to_vec();
+	data.resize(data.len() + suffix_len, 0);
+	let sealing_key =
+		aead::SealingKey::new(&aead::CHACHA20_POLY1305, key).map_err(|_| ErrorKind::Encryption)?;
+	aead::seal_in_place(&sealing_key, nonce, aad, &mut data, suffix_len)
+		.map_err(|_| ErrorKind::Encryption)?;
+	Ok(data)
+}
+
+/// HKDF-SHA256 (RFC 5869) of the shared secret, salted with both public
+/// keys. None for keys of small order, which give away the shared secret
+fn derive_key(
+	shared: &[u8; 32],
+	ephemeral_key: &[u8; 32],
+	receiver_key: &[u8; 32],
+) -> Option<[u8; 32]> {
+	if shared.iter().all(|b| *b == 0) {
+		return None;
+	}
+	let mut salt = ephemeral_key.to_vec();
+	salt.extend_from_slice(receiver_key);
+	let salt = hmac::SigningKey::new(&digest::SHA256, &salt);
+	let mut key = [0; 32];
+	hkdf::extract_and_expand(&salt, shared, KEY_INFO, &mut key);
+	Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Section 2.8.2 of RFC 8439
+	#[test]
+	fn seals_the_rfc_8439_vector() {
+		let plaintext = "Ladies and Gentlemen of the class of '99: If I could offer you only \
+		                 one tip for the future, sunscreen would be it.";
+		let aad = from_hex("50515253c0c1c2c3c4c5c6c7".to_owned()).unwrap();
+		let mut key = [0; 32];
+		for (i, b) in key.iter_mut().enumerate() {
+			*b = 0x80 + i as u8;
+		}
+		let mut nonce = [0; 12];
+		nonce.copy_from_slice(&from_hex("070000004041424344454647".to_owned()).unwrap());
+
+		let sealed = seal(&key, &nonce, &aad, plaintext.as_bytes()).unwrap();
+		let expected = "d31a8d34648e60db7b86afbc53ef7ec2a4aded51296e08fea9e2b5a736ee62d6\
+		                3dbea45e8ca9671282fafb69da92728b1a71de0a9e060b2905d6a5b67ecd3b36\
+		                92ddbd7f2d778b8c9803aee328091b58fab324e4fad675945585808b4831d7bc\
+		                3ff4def08e4b7a9de576d26586cec64b6116\
+		                1ae10b594f09e26a7e902ecbd0600691";
+		assert_eq!(to_hex(sealed), expected);
+	}
+
+	#[test]
+	fn refuses_keys_of_small_order() {
+		let secret = [7u8; 32];
+		let public_key = x25519::public_key(&secret);
+		// The u-coordinate 0 is a point of order 4
+		let shared = x25519::x25519(&secret, &[0; 32]);
+		assert!(derive_key(&shared, &[0; 32], &public_key).is_none());
+
+		let sealed = SealedMessage {
+			ephemeral_key: to_hex(vec![0; 32]),
+			nonce: to_hex(vec![0; 12]),
+			ciphertext: to_hex(vec![0; 16]),
+		};
+		assert!(sealed.open(&secret).is_err());
+	}
+}
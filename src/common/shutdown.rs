@@ -0,0 +1,135 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Termination signals of the daemon. The handlers only record the signal,
+//! the shutdown itself runs on a regular thread waiting for it
+
+use super::{ErrorKind, Result};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// How often `wait` looks for a received signal
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+const NONE: usize = 0;
+const INTERRUPT: usize = 1;
+const TERMINATE: usize = 2;
+
+static RECEIVED: AtomicUsize = AtomicUsize::new(NONE);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Signal {
+	/// SIGINT, or Ctrl-C on Windows
+	Interrupt,
+	/// SIGTERM, or the console closing on Windows
+	Terminate,
+}
+
+impl fmt::Display for Signal {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Signal::Interrupt => write!(f, "SIGINT"),
+			Signal::Terminate => write!(f, "SIGTERM"),
+		}
+	}
+}
+
+/// Catch SIGINT and SIGTERM instead of dying on them
+pub fn install() -> Result<()> {
+	imp::install().map_err(|e| ErrorKind::Signal(e.to_string()).into())
+}
+
+/// Signal received so far, if any
+pub fn received() -> Option<Signal> {
+	match RECEIVED.load(Ordering::SeqCst) {
+		INTERRUPT => Some(Signal::Interrupt),
+		TERMINATE => Some(Signal::Terminate),
+		_ => None,
+	}
+}
+
+/// Block until a signal is received
+pub fn wait() -> Signal {
+	loop {
+		if let Some(signal) = received() {
+			return signal;
+		}
+		thread::sleep(POLL_INTERVAL);
+	}
+}
+
+#[cfg(unix)]
+mod imp {
+	use super::{INTERRUPT, RECEIVED, TERMINATE};
+	use std::io;
+	use std::os::raw::c_int;
+	use std::sync::atomic::Ordering;
+
+	const SIGINT: c_int = 2;
+	const SIGTERM: c_int = 15;
+	const SIG_ERR: usize = !0;
+
+	extern "C" {
+		fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+	}
+
+	extern "C" fn handle(signum: c_int) {
+		let received = match signum {
+			SIGINT => INTERRUPT,
+			_ => TERMINATE,
+		};
+		RECEIVED.store(received, Ordering::SeqCst);
+	}
+
+	pub fn install() -> io::Result<()> {
+		for signum in &[SIGINT, SIGTERM] {
+			if unsafe { signal(*signum, handle) } == SIG_ERR {
+				return Err(io::Error::last_os_error());
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(windows)]
+mod imp {
+	use super::{INTERRUPT, RECEIVED, TERMINATE};
+	use std::io;
+	use std::sync::atomic::Ordering;
+
+	const CTRL_C_EVENT: u32 = 0;
+	const CTRL_BREAK_EVENT: u32 = 1;
+
+	extern "system" {
+		fn SetConsoleCtrlHandler(handler: extern "system" fn(u32) -> i32, add: i32) -> i32;
+	}
+
+	extern "system" fn handle(ctrl_type: u32) -> i32 {
+		let received = match ctrl_type {
+			CTRL_C_EVENT | CTRL_BREAK_EVENT => INTERRUPT,
+			_ => TERMINATE,
+		};
+		RECEIVED.store(received, Ordering::SeqCst);
+		1
+	}
+
+	pub fn install() -> io::Result<()> {
+		match unsafe { SetConsoleCtrlHandler(handle, 1) } {
+			0 => Err(io::Error::last_os_error()),
+			_ => Ok(()),
+		}
+	}
+}
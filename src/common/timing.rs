@@ -0,0 +1,135 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::{Arc, Mutex};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Stages taking longer than this are logged as slow
+const SLOW_STAGE_MS: u64 = 5_000;
+/// Recent stages kept until they are taken, the oldest are dropped first
+const MAX_RECENT: usize = 256;
+
+pub type SharedTimings = Arc<Mutex<Timings>>;
+
+/// Aggregated execution time of a single stage
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StageTiming {
+	pub stage: String,
+	pub count: u64,
+	pub total_ms: u64,
+	pub max_ms: u64,
+	pub last_ms: u64,
+}
+
+impl StageTiming {
+	pub fn avg_ms(&self) -> u64 {
+		if self.count == 0 {
+			0
+		} else {
+			self.total_ms / self.count
+		}
+	}
+}
+
+/// Collects the execution time of commands and their internal stages
+/// (refresh, selection, signing, adapter, ..)
+#[derive(Debug, Default)]
+pub struct Timings {
+	stages: BTreeMap<String, StageTiming>,
+	recent: VecDeque<(String, u64)>,
+}
+
+impl Timings {
+	pub fn new() -> SharedTimings {
+		Arc::new(Mutex::new(Self::default()))
+	}
+
+	pub fn record(&mut self, stage: &str, elapsed: Duration) {
+		let ms = as_millis(elapsed);
+		if ms >= SLOW_STAGE_MS {
+			warn!("Slow operation: stage '{}' took {} ms", stage, ms);
+		}
+
+		let entry = self
+			.stages
+			.entry(stage.to_owned())
+			.or_insert_with(|| StageTiming {
+				stage: stage.to_owned(),
+				..StageTiming::default()
+			});
+		entry.count += 1;
+		entry.total_ms += ms;
+		entry.last_ms = ms;
+		if ms > entry.max_ms {
+			entry.max_ms = ms;
+		}
+
+		if self.recent.len() == MAX_RECENT {
+			self.recent.pop_front();
+		}
+		self.recent.push_back((stage.to_owned(), ms));
+	}
+
+	/// Stages recorded since the previous call, in order of completion. Only
+	/// the last `MAX_RECENT` of them are kept
+	pub fn take_recent(&mut self) -> Vec<(String, u64)> {
+		self.recent.drain(..).collect()
+	}
+
+	/// Aggregated timings of all stages since startup
+	pub fn summary(&self) -> Vec<StageTiming> {
+		self.stages.values().cloned().collect()
+	}
+
+	pub fn reset(&mut self) {
+		self.stages.clear();
+		self.recent.clear();
+	}
+}
+
+/// Run `f` and record its execution time under `stage`
+pub fn timed<F, X>(timings: &SharedTimings, stage: &str, f: F) -> X
+where
+	F: FnOnce() -> X,
+{
+	let start = Instant::now();
+	let res = f();
+	timings.lock().record(stage, start.elapsed());
+	res
+}
+
+pub fn as_millis(duration: Duration) -> u64 {
+	duration.as_secs() * 1_000 + duration.subsec_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn keeps_only_the_most_recent_stages() {
+		let mut timings = Timings::default();
+		for i in 0..MAX_RECENT + 10 {
+			timings.record(&format!("stage{}", i), Duration::from_millis(1));
+		}
+		let recent = timings.take_recent();
+		assert_eq!(recent.len(), MAX_RECENT);
+		assert_eq!(recent[0].0, "stage10");
+		assert!(timings.take_recent().is_empty());
+		assert_eq!(timings.summary().len(), MAX_RECENT + 10);
+	}
+}
@@ -0,0 +1,98 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! X25519 key agreement (RFC 7748) on top of x25519-dalek, for keys that
+//! stay the same. Ring only offers ephemeral keys
+
+use x25519_dalek::X25519_BASEPOINT_BYTES;
+
+/// Public key of `secret`
+pub fn public_key(secret: &[u8; 32]) -> [u8; 32] {
+	x25519(secret, &X25519_BASEPOINT_BYTES)
+}
+
+/// Multiply the point with u-coordinate `u` by `scalar`. The result is all
+/// zeros for points of small order, which callers have to refuse
+pub fn x25519(scalar: &[u8; 32], u: &[u8; 32]) -> [u8; 32] {
+	x25519_dalek::x25519(*scalar, *u)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::common::crypto::from_hex;
+
+	fn bytes(hex: &str) -> [u8; 32] {
+		let mut bytes = [0; 32];
+		bytes.copy_from_slice(&from_hex(hex.to_owned()).unwrap());
+		bytes
+	}
+
+	/// Section 5.2 of RFC 7748
+	#[test]
+	fn matches_the_rfc_7748_vectors() {
+		let vectors = [
+			(
+				"a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4",
+				"e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c",
+				"c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552",
+			),
+			(
+				"4b66e9d4d1b4673c5ad22691957d6af5c11b6421e0ea01d42ca4169e7918ba0d",
+				"e5210f12786811d3f4b7959d0538ae2c31dbe7106fc03c3efc4cd549c715a493",
+				"95cbde9476e8907d7ade45cb4b873f88b595a68799fa152e6f8f7647aac7957c",
+			),
+		];
+		for (scalar, u, expected) in vectors.iter() {
+			assert_eq!(x25519(&bytes(scalar), &bytes(u)), bytes(expected));
+		}
+	}
+
+	/// Section 5.2 of RFC 7748, the result of each step is the next scalar
+	#[test]
+	fn matches_the_rfc_7748_iterations() {
+		let (mut k, mut u) = (X25519_BASEPOINT_BYTES, X25519_BASEPOINT_BYTES);
+		for i in 1..=1000 {
+			let result = x25519(&k, &u);
+			u = k;
+			k = result;
+			if i == 1 {
+				let expected = "422c8e7a6227d7bca1350b3e2bb7279f7897b87bb6854b783c60e80311ae3079";
+				assert_eq!(k, bytes(expected));
+			}
+		}
+		let expected = "684cf59ba83309552800ef566f2f4d3c1c3887c49360e3875f2eb94d99532c51";
+		assert_eq!(k, bytes(expected));
+	}
+
+	/// Section 6.1 of RFC 7748
+	#[test]
+	fn agrees_on_the_rfc_7748_shared_secret() {
+		let alice = bytes("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a");
+		let bob = bytes("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb");
+		let alice_public = public_key(&alice);
+		let bob_public = public_key(&bob);
+		assert_eq!(
+			alice_public,
+			bytes("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a")
+		);
+		assert_eq!(
+			bob_public,
+			bytes("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f")
+		);
+		let shared = bytes("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+		assert_eq!(x25519(&alice, &bob_public), shared);
+		assert_eq!(x25519(&bob, &alice_public), shared);
+	}
+}
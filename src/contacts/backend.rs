@@ -25,6 +25,9 @@ use std::path::Path;
 
 const DB_DIR: &'static str = "contacts";
 const CONTACT_PREFIX: u8 = 'X' as u8;
+const SCHEMA_VERSION_PREFIX: u8 = 'V' as u8;
+/// Version 1 added the optional `group` and `notes` fields
+const SCHEMA_VERSION: u32 = 1;
 
 pub struct Backend {
 	db: Store,
@@ -38,8 +41,49 @@ impl Backend {
 		let store = Store::new(db_path.to_str().unwrap(), None, Some(DB_DIR), None)?;
 
 		let res = Backend { db: store };
+		res.migrate()?;
 		Ok(res)
 	}
+
+	/// Rewrite all contacts in the current format if the
+	/// address book was created by an older version
+	fn migrate(&self) -> Result<(), Error> {
+		let key = to_key(SCHEMA_VERSION_PREFIX, &mut b"schema".to_vec());
+		let version = self
+			.db
+			.get_ser::<SchemaVersion>(&key)?
+			.map(|v| v.0)
+			.unwrap_or(0);
+		if version >= SCHEMA_VERSION {
+			return Ok(());
+		}
+
+		let contacts: Vec<Contact> = self.contacts().collect();
+		let mut batch = self.batch()?;
+		for contact in &contacts {
+			batch.save_contact(contact)?;
+		}
+		batch.commit()?;
+
+		let batch = self.db.batch()?;
+		batch.put_ser(&key, &SchemaVersion(SCHEMA_VERSION))?;
+		batch.commit()?;
+		Ok(())
+	}
+}
+
+struct SchemaVersion(u32);
+
+impl Writeable for SchemaVersion {
+	fn write<W: Writer>(&self, writer: &mut W) -> Result<(), CoreError> {
+		writer.write_u32(self.0)
+	}
+}
+
+impl Readable for SchemaVersion {
+	fn read(reader: &mut dyn Reader) -> Result<SchemaVersion, CoreError> {
+		Ok(SchemaVersion(reader.read_u32()?))
+	}
 }
 
 impl AddressBookBackend for Backend {
@@ -102,6 +146,9 @@ impl Writeable for Contact {
 		let json = json!({
 			"name": self.name,
 			"address": self.address.to_string(),
+			"group": self.group,
+			"notes": self.notes,
+			"relay": self.relay,
 		});
 		writer.write_bytes(&json.to_string().as_bytes())
 	}
@@ -118,8 +165,11 @@ impl Readable for Contact {
 		let address = parse_address(json["address"].as_str().unwrap())
 			.map_err(|_| CoreError::CorruptedData)?;
 
-		let contact = Contact::new(json["name"].as_str().unwrap(), address)
+		let mut contact = Contact::new(json["name"].as_str().unwrap(), address)
 			.map_err(|_| CoreError::CorruptedData)?;
+		contact.group = json["group"].as_str().map(|g| g.to_owned());
+		contact.notes = json["notes"].as_str().map(|n| n.to_owned());
+		contact.relay = json["relay"].as_str().map(|r| r.to_owned());
 
 		Ok(contact)
 	}
@@ -17,6 +17,7 @@ mod types;
 
 pub use self::backend::Backend;
 pub use self::types::{
-	parse_address, Address, AddressBook, AddressBookBackend, AddressType, Contact, GrinboxAddress,
-	KeybaseAddress, DEFAULT_GRINBOX_PORT,
+	parse_address, Address, AddressBook, AddressBookBackend, AddressType, Contact, ContactsFormat,
+	EmailAddress, ExecAddress, GrinboxAddress, GrinboxRelay, ImportSummary, KeybaseAddress,
+	MergeStrategy, SharedRelays, DEFAULT_GRINBOX_PORT,
 };
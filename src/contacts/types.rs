@@ -15,16 +15,27 @@
 use crate::common::crypto::{
 	Base58, PublicKey, GRINBOX_ADDRESS_VERSION_MAINNET, GRINBOX_ADDRESS_VERSION_TESTNET,
 };
-use crate::common::{ErrorKind, Result};
+use crate::common::{Arc, ErrorKind, Mutex, Result};
 use grin_core::global::is_floonet;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fmt::{self, Debug, Display};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
 use url::Url;
 
-const ADDRESS_REGEX: &str = r"^((?P<address_type>keybase|grinbox|http|https)://).+$";
+const ADDRESS_REGEX: &str = r"^((?P<address_type>keybase|grinbox|http|https|email)://).+$";
 const GRINBOX_ADDRESS_REGEX: &str = r"^(grinbox://)?(?P<public_key>[123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz]{52})(@(?P<domain>[a-zA-Z0-9\.]+)(:(?P<port>[0-9]*))?)?$";
 const KEYBASE_ADDRESS_REGEX: &str = r"^(keybase://)?(?P<username>[0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz_]{1,16})(:(?P<topic>[a-zA-Z0-9_-]+))?$";
+const KEYBASE_TEAM_ADDRESS_REGEX: &str = r"^(keybase://)?(?P<team>[a-zA-Z0-9_]{2,16}(\.[a-zA-Z0-9_]{2,16})*)/(?P<channel>[a-zA-Z0-9_-]{1,20})$";
+const EMAIL_ADDRESS_REGEX: &str =
+	r"^(email://)?(?P<address>[^@\s<>]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,})$";
+const EXEC_ADDRESS_REGEX: &str = r"^(?P<scheme>[a-z][a-z0-9+.-]*)://(?P<address>\S+)$";
+const GRINBOX_RELAY_REGEX: &str =
+	r"^((?P<scheme>wss?)://)?(?P<domain>[a-zA-Z0-9\.-]+)(:(?P<port>[0-9]{1,5}))?$";
 const DEFAULT_GRINBOX_DOMAIN: &str = "grinbox.io";
 #[cfg(not(windows))]
 pub const DEFAULT_GRINBOX_PORT: u16 = 443;
@@ -36,6 +47,22 @@ pub enum AddressType {
 	Grinbox,
 	Keybase,
 	Http,
+	Email,
+	/// Sent through the external adapter named after the scheme
+	Exec(String),
+}
+
+impl AddressType {
+	/// Name of the adapter sending to this type of address
+	pub fn method(&self) -> &str {
+		match self {
+			AddressType::Grinbox => "grinbox",
+			AddressType::Keybase => "keybase",
+			AddressType::Http => "http",
+			AddressType::Email => "email",
+			AddressType::Exec(name) => name,
+		}
+	}
 }
 
 pub trait Address: Debug + Display {
@@ -50,6 +77,10 @@ pub fn parse_address(address: &str) -> Result<Box<dyn Address>> {
 	let re = Regex::new(ADDRESS_REGEX)?;
 	let captures = re.captures(address);
 	if captures.is_none() {
+		// Other schemes are left to the external adapters
+		if let Ok(address) = ExecAddress::from_str(address) {
+			return Ok(Box::new(address));
+		}
 		return Ok(Box::new(
 			GrinboxAddress::from_str(address).map_err(|_| ErrorKind::ParseAddress)?,
 		));
@@ -61,6 +92,7 @@ pub fn parse_address(address: &str) -> Result<Box<dyn Address>> {
 		"keybase" => Box::new(KeybaseAddress::from_str(address)?),
 		"grinbox" => Box::new(GrinboxAddress::from_str(address)?),
 		"http" | "https" => Box::new(HttpAddress::from_str(address)?),
+		"email" => Box::new(EmailAddress::from_str(address)?),
 		x => Err(ErrorKind::UnknownAddressType(x.to_string()))?,
 	};
 	Ok(address)
@@ -78,24 +110,51 @@ pub trait AddressBookBatch {
 	fn commit(&mut self) -> Result<()>;
 }
 
+/// Relay overrides of the contacts, by grinbox public key
+pub type SharedRelays = Arc<Mutex<HashMap<String, GrinboxRelay>>>;
+
 pub struct AddressBook {
 	backend: Box<dyn AddressBookBackend + Send>,
+	relays: SharedRelays,
 }
 
 impl AddressBook {
 	pub fn new(backend: Box<dyn AddressBookBackend + Send>) -> Result<Self> {
-		let address_book = Self { backend };
+		let address_book = Self {
+			backend,
+			relays: Arc::new(Mutex::new(HashMap::new())),
+		};
+		address_book.refresh_relays();
 		Ok(address_book)
 	}
 
+	/// Relay overrides, kept up to date as contacts change
+	pub fn relays(&self) -> SharedRelays {
+		self.relays.clone()
+	}
+
+	fn refresh_relays(&self) {
+		let relays = self
+			.contacts()
+			.filter_map(|c| {
+				let address = GrinboxAddress::from_str(&c.address).ok()?;
+				let relay = GrinboxRelay::from_str(c.relay.as_ref()?).ok()?;
+				Some((address.public_key, relay))
+			})
+			.collect();
+		*self.relays.lock() = relays;
+	}
+
 	pub fn add_contact(&mut self, contact: &Contact) -> Result<()> {
 		let result = self.get_contact(&contact.name)?;
 		if result.is_some() {
 			return Err(ErrorKind::ContactAlreadyExists(contact.name.clone()))?;
 		}
+		contact.check_relay()?;
 		let mut batch = self.backend.batch()?;
 		batch.save_contact(contact)?;
 		batch.commit()?;
+		self.refresh_relays();
 		Ok(())
 	}
 
@@ -103,6 +162,7 @@ impl AddressBook {
 		let mut batch = self.backend.batch()?;
 		batch.delete_contact(name.as_bytes())?;
 		batch.commit()?;
+		self.refresh_relays();
 		Ok(())
 	}
 
@@ -119,15 +179,309 @@ impl AddressBook {
 		Ok(None)
 	}
 
+	/// Add the unknown sender of a slate as a contact, named after its
+	/// keybase user or the start of its grinbox public key. Other address
+	/// types are not added
+	pub fn add_sender(&mut self, address: &str) -> Result<Option<Contact>> {
+		let name = if let Ok(a) = GrinboxAddress::from_str(address) {
+			a.public_key[..8].to_string()
+		} else if let Ok(a) = KeybaseAddress::from_str(address) {
+			match a.channel {
+				Some(channel) => format!("{}-{}", a.username, channel),
+				None => a.username,
+			}
+		} else {
+			return Ok(None);
+		};
+
+		let mut unique = name.clone();
+		let mut n = 1;
+		while self.get_contact(&unique)?.is_some() {
+			n += 1;
+			unique = format!("{}-{}", name, n);
+		}
+
+		let contact = Contact::new(&unique, parse_address(address)?)?;
+		self.add_contact(&contact)?;
+		Ok(Some(contact))
+	}
+
 	pub fn contacts(&self) -> Box<dyn Iterator<Item = Contact>> {
 		self.backend.contacts()
 	}
+
+	/// Write all contacts to a file. Returns the number of exported contacts
+	pub fn export(&self, path: &str, format: ContactsFormat) -> Result<usize> {
+		let mut contacts: Vec<Contact> = self.contacts().collect();
+		contacts.sort_by(|a, b| a.name.cmp(&b.name));
+
+		let data = match format {
+			ContactsFormat::Json => serde_json::to_string_pretty(&contacts)?,
+			ContactsFormat::Csv => {
+				let mut data = String::from("name,address,group,notes,relay\n");
+				for contact in &contacts {
+					data.push_str(&format!(
+						"{},{},{},{},{}\n",
+						csv_escape(&contact.name),
+						csv_escape(&contact.address),
+						csv_escape(contact.group.as_ref().map(|g| g.as_str()).unwrap_or("")),
+						csv_escape(contact.notes.as_ref().map(|n| n.as_str()).unwrap_or("")),
+						csv_escape(contact.relay.as_ref().map(|r| r.as_str()).unwrap_or(""))
+					));
+				}
+				data
+			}
+		};
+
+		let mut file = File::create(path)?;
+		file.write_all(data.as_bytes())?;
+		Ok(contacts.len())
+	}
+
+	/// Read contacts from a file and merge them into the address book.
+	/// All contacts are validated before any changes are made
+	pub fn import(
+		&mut self,
+		path: &str,
+		format: ContactsFormat,
+		strategy: MergeStrategy,
+	) -> Result<ImportSummary> {
+		let mut data = String::new();
+		File::open(path)?.read_to_string(&mut data)?;
+
+		let contacts: Vec<Contact> = match format {
+			ContactsFormat::Json => serde_json::from_str(&data)
+				.map_err(|e| ErrorKind::InvalidContactsFile(e.to_string()))?,
+			ContactsFormat::Csv => parse_csv(&data)?,
+		};
+
+		let mut names = HashSet::new();
+		for contact in &contacts {
+			if contact.name.is_empty() {
+				return Err(ErrorKind::InvalidContactsFile(
+					"empty contact name".to_owned(),
+				))?;
+			}
+			if !names.insert(contact.name.clone()) {
+				return Err(ErrorKind::InvalidContactsFile(format!(
+					"duplicate contact '{}'",
+					contact.name
+				)))?;
+			}
+			parse_address(&contact.address).map_err(|_| {
+				ErrorKind::InvalidContactsFile(format!(
+					"invalid address '{}' for contact '{}'",
+					contact.address, contact.name
+				))
+			})?;
+			contact.check_relay().map_err(|e| {
+				ErrorKind::InvalidContactsFile(format!("{} for contact '{}'", e, contact.name))
+			})?;
+		}
+
+		let mut summary = ImportSummary::default();
+		let existing: Vec<Contact> = self.contacts().collect();
+		let mut batch = self.backend.batch()?;
+		if strategy == MergeStrategy::Replace {
+			for contact in &existing {
+				if !names.contains(&contact.name) {
+					batch.delete_contact(contact.name.as_bytes())?;
+					summary.removed += 1;
+				}
+			}
+		}
+
+		for contact in &contacts {
+			match existing.iter().find(|c| c.name == contact.name) {
+				Some(c) if c == contact => summary.unchanged += 1,
+				Some(_) if strategy == MergeStrategy::Skip => summary.skipped += 1,
+				Some(_) => {
+					batch.save_contact(contact)?;
+					summary.updated += 1;
+				}
+				None => {
+					batch.save_contact(contact)?;
+					summary.added += 1;
+				}
+			}
+		}
+		batch.commit()?;
+		self.refresh_relays();
+
+		Ok(summary)
+	}
+}
+
+/// File format used to export and import the address book
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContactsFormat {
+	Json,
+	Csv,
+}
+
+impl ContactsFormat {
+	/// Guess the format from the file extension, defaulting to JSON
+	pub fn from_path(path: &str) -> Self {
+		match Path::new(path).extension().and_then(|e| e.to_str()) {
+			Some(e) if e.eq_ignore_ascii_case("csv") => ContactsFormat::Csv,
+			_ => ContactsFormat::Json,
+		}
+	}
+}
+
+impl FromStr for ContactsFormat {
+	type Err = ErrorKind;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"json" => Ok(ContactsFormat::Json),
+			"csv" => Ok(ContactsFormat::Csv),
+			_ => Err(ErrorKind::UnknownContactsFormat(s.to_owned())),
+		}
+	}
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// How to handle imported contacts whose name already exists
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MergeStrategy {
+	/// Keep the existing contact
+	Skip,
+	/// Replace the existing contact with the imported one
+	Overwrite,
+	/// Make the address book identical to the imported file
+	Replace,
+}
+
+impl FromStr for MergeStrategy {
+	type Err = ErrorKind;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"skip" => Ok(MergeStrategy::Skip),
+			"overwrite" => Ok(MergeStrategy::Overwrite),
+			"replace" => Ok(MergeStrategy::Replace),
+			_ => Err(ErrorKind::UnknownMergeStrategy(s.to_owned())),
+		}
+	}
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImportSummary {
+	pub added: usize,
+	pub updated: usize,
+	pub unchanged: usize,
+	pub skipped: usize,
+	pub removed: usize,
+}
+
+fn csv_escape(field: &str) -> String {
+	if field.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+		format!("\"{}\"", field.replace("\"", "\"\""))
+	} else {
+		field.to_owned()
+	}
+}
+
+fn csv_fields(line: &str) -> Result<Vec<String>> {
+	let mut fields = Vec::new();
+	let mut field = String::new();
+	let mut quoted = false;
+	let mut chars = line.chars().peekable();
+	while let Some(c) = chars.next() {
+		match c {
+			'"' if quoted && chars.peek() == Some(&'"') => {
+				field.push('"');
+				chars.next();
+			}
+			'"' if quoted => quoted = false,
+			'"' if field.is_empty() => quoted = true,
+			',' if !quoted => {
+				fields.push(field.trim().to_owned());
+				field = String::new();
+			}
+			c => field.push(c),
+		}
+	}
+	if quoted {
+		return Err(ErrorKind::InvalidContactsFile(format!(
+			"unterminated quote in '{}'",
+			line
+		)))?;
+	}
+	fields.push(field.trim().to_owned());
+	Ok(fields)
+}
+
+/// Records of `data` with the line they start on. Line breaks inside a
+/// quoted field are part of the field
+fn csv_records(data: &str) -> Vec<(usize, String)> {
+	let mut records = Vec::new();
+	let mut record = String::new();
+	let mut quoted = false;
+	let mut line = 1;
+	let mut start = 1;
+	for c in data.chars() {
+		match c {
+			'"' => quoted = !quoted,
+			'\n' if !quoted => {
+				let record = std::mem::replace(&mut record, String::new());
+				records.push((start, record.trim_end_matches('\r').to_owned()));
+				line += 1;
+				start = line;
+				continue;
+			}
+			'\n' => line += 1,
+			_ => {}
+		}
+		record.push(c);
+	}
+	if !record.is_empty() {
+		records.push((start, record));
+	}
+	records
+}
+
+fn parse_csv(data: &str) -> Result<Vec<Contact>> {
+	let mut contacts = Vec::new();
+	for (i, (line, record)) in csv_records(data).into_iter().enumerate() {
+		let record = record.trim();
+		if record.is_empty() {
+			continue;
+		}
+		let fields = csv_fields(record)?;
+		if i == 0 && fields[0].eq_ignore_ascii_case("name") {
+			continue;
+		}
+		if fields.len() < 2 || fields.len() > 5 {
+			return Err(ErrorKind::InvalidContactsFile(format!(
+				"expected 2 to 5 fields on line {}, found {}",
+				line,
+				fields.len()
+			)))?;
+		}
+		let optional = |i: usize| fields.get(i).filter(|f| !f.is_empty()).cloned();
+		contacts.push(Contact {
+			name: fields[0].clone(),
+			address: fields[1].clone(),
+			group: optional(2),
+			notes: optional(3),
+			relay: optional(4),
+		});
+	}
+	Ok(contacts)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Contact {
 	pub name: String,
 	pub address: String,
+	#[serde(default)]
+	pub group: Option<String>,
+	#[serde(default)]
+	pub notes: Option<String>,
+	/// Grinbox relay to post to instead of the one in the address
+	#[serde(default)]
+	pub relay: Option<String>,
 }
 
 impl Contact {
@@ -135,8 +489,28 @@ impl Contact {
 		Ok(Self {
 			name: name.to_string(),
 			address: address.to_string(),
+			group: None,
+			notes: None,
+			relay: None,
 		})
 	}
+
+	/// A relay can only be set on grinbox contacts, and has to be valid
+	pub fn check_relay(&self) -> Result<()> {
+		if let Some(relay) = &self.relay {
+			GrinboxAddress::from_str(&self.address)
+				.map_err(|_| ErrorKind::RelayOnNonGrinboxContact(self.name.clone()))?;
+			GrinboxRelay::from_str(relay)?;
+		}
+		Ok(())
+	}
+
+	pub fn in_group(&self, group: &str) -> bool {
+		match &self.group {
+			Some(g) => g.eq_ignore_ascii_case(group),
+			None => false,
+		}
+	}
 }
 
 impl Display for Contact {
@@ -148,12 +522,31 @@ impl Display for Contact {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct KeybaseAddress {
+	/// User name, or team name for a team channel
 	pub username: String,
 	pub topic: Option<String>,
+	/// Team channel, as in `keybase://team/channel`
+	#[serde(default)]
+	pub channel: Option<String>,
+}
+
+impl KeybaseAddress {
+	pub fn is_team(&self) -> bool {
+		self.channel.is_some()
+	}
 }
 
 impl Address for KeybaseAddress {
 	fn from_str(s: &str) -> Result<Self> {
+		let re = Regex::new(KEYBASE_TEAM_ADDRESS_REGEX).unwrap();
+		if let Some(captures) = re.captures(s) {
+			return Ok(Self {
+				username: captures.name("team").unwrap().as_str().to_string(),
+				topic: None,
+				channel: Some(captures.name("channel").unwrap().as_str().to_string()),
+			});
+		}
+
 		let re = Regex::new(KEYBASE_ADDRESS_REGEX).unwrap();
 		let captures = re.captures(s);
 		if captures.is_none() {
@@ -163,7 +556,11 @@ impl Address for KeybaseAddress {
 		let captures = captures.unwrap();
 		let username = captures.name("username").unwrap().as_str().to_string();
 		let topic = captures.name("topic").map(|m| m.as_str().to_string());
-		Ok(Self { username, topic })
+		Ok(Self {
+			username,
+			topic,
+			channel: None,
+		})
 	}
 
 	fn address_type(&self) -> AddressType {
@@ -171,13 +568,19 @@ impl Address for KeybaseAddress {
 	}
 
 	fn stripped(&self) -> String {
-		format!("{}", self.username)
+		match &self.channel {
+			Some(channel) => format!("{}/{}", self.username, channel),
+			None => format!("{}", self.username),
+		}
 	}
 }
 
 impl Display for KeybaseAddress {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		write!(f, "keybase://{}", self.username)?;
+		if let Some(ref channel) = self.channel {
+			write!(f, "/{}", channel)?;
+		}
 		if let Some(ref topic) = self.topic {
 			write!(f, ":{}", topic)?;
 		}
@@ -258,6 +661,57 @@ impl Display for GrinboxAddress {
 	}
 }
 
+/// Relay a grinbox address is reached through: its domain and port, over
+/// TLS (`wss`) unless the relay is given as `ws://`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GrinboxRelay {
+	pub domain: String,
+	pub port: u16,
+	pub secure: bool,
+}
+
+impl GrinboxRelay {
+	pub fn from_str(s: &str) -> Result<Self> {
+		let re = Regex::new(GRINBOX_RELAY_REGEX).unwrap();
+		let captures = re
+			.captures(s)
+			.ok_or(ErrorKind::GrinboxRelayParsingError(s.to_string()))?;
+		let port = match captures.name("port") {
+			Some(p) => p
+				.as_str()
+				.parse()
+				.map_err(|_| ErrorKind::GrinboxRelayParsingError(s.to_string()))?,
+			None => DEFAULT_GRINBOX_PORT,
+		};
+		Ok(Self {
+			domain: captures.name("domain").unwrap().as_str().to_string(),
+			port,
+			secure: captures.name("scheme").map(|m| m.as_str()) != Some("ws"),
+		})
+	}
+
+	/// Relay named by the domain and port of an address
+	pub fn of_address(address: &GrinboxAddress, secure: bool) -> Self {
+		Self {
+			domain: address.domain.clone(),
+			port: address.port.unwrap_or(DEFAULT_GRINBOX_PORT),
+			secure,
+		}
+	}
+
+	/// Whether both name the same server, whatever the protocol
+	pub fn same_server(&self, other: &GrinboxRelay) -> bool {
+		self.domain.eq_ignore_ascii_case(&other.domain) && self.port == other.port
+	}
+}
+
+impl Display for GrinboxRelay {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let scheme = if self.secure { "wss" } else { "ws" };
+		write!(f, "{}://{}:{}", scheme, self.domain, self.port)
+	}
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct HttpAddress {
 	pub uri: String,
@@ -285,3 +739,135 @@ impl Display for HttpAddress {
 		Ok(())
 	}
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EmailAddress {
+	pub address: String,
+}
+
+impl Address for EmailAddress {
+	fn from_str(s: &str) -> Result<Self> {
+		let re = Regex::new(EMAIL_ADDRESS_REGEX).unwrap();
+		let captures = re
+			.captures(s)
+			.ok_or(ErrorKind::EmailAddressParsingError(s.to_string()))?;
+		Ok(Self {
+			address: captures.name("address").unwrap().as_str().to_string(),
+		})
+	}
+
+	fn address_type(&self) -> AddressType {
+		AddressType::Email
+	}
+
+	fn stripped(&self) -> String {
+		self.address.clone()
+	}
+}
+
+impl Display for EmailAddress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "email://{}", self.address)?;
+		Ok(())
+	}
+}
+
+/// Address handled by an external adapter, as in `matrix://@bob:matrix.org`.
+/// The scheme is the name of the adapter
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExecAddress {
+	pub scheme: String,
+	pub address: String,
+}
+
+impl Address for ExecAddress {
+	fn from_str(s: &str) -> Result<Self> {
+		let re = Regex::new(EXEC_ADDRESS_REGEX).unwrap();
+		let captures = re
+			.captures(s)
+			.ok_or(ErrorKind::ExecAddressParsingError(s.to_string()))?;
+		Ok(Self {
+			scheme: captures.name("scheme").unwrap().as_str().to_string(),
+			address: captures.name("address").unwrap().as_str().to_string(),
+		})
+	}
+
+	fn address_type(&self) -> AddressType {
+		AddressType::Exec(self.scheme.clone())
+	}
+
+	/// The scheme is kept, as it picks the adapter
+	fn stripped(&self) -> String {
+		self.to_string()
+	}
+}
+
+impl Display for ExecAddress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}://{}", self.scheme, self.address)?;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::contacts::Backend;
+	use std::env;
+	use std::fs;
+	use uuid::Uuid;
+
+	fn address_book(dir: &Path, name: &str) -> AddressBook {
+		let path = dir.join(name);
+		let backend = Backend::new(path.to_str().unwrap()).unwrap();
+		AddressBook::new(Box::new(backend)).unwrap()
+	}
+
+	#[test]
+	fn csv_export_imports_back() {
+		let dir = env::temp_dir().join(format!("wallet713-contacts-{}", Uuid::new_v4()));
+		let contacts = vec![
+			Contact {
+				name: "alice".to_owned(),
+				address: "keybase://alice".to_owned(),
+				group: Some("friends, family".to_owned()),
+				notes: Some("met at \"the\" meetup\nowes me 2 grin\r\nlives abroad".to_owned()),
+				relay: None,
+			},
+			Contact {
+				name: "bob".to_owned(),
+				address: "email://bob@example.com".to_owned(),
+				group: None,
+				notes: None,
+				relay: None,
+			},
+		];
+		let mut exported = address_book(&dir, "exported");
+		for contact in &contacts {
+			exported.add_contact(contact).unwrap();
+		}
+		let file = dir.join("contacts.csv");
+		let file = file.to_str().unwrap();
+		assert_eq!(exported.export(file, ContactsFormat::Csv).unwrap(), 2);
+
+		let mut imported = address_book(&dir, "imported");
+		let summary = imported
+			.import(file, ContactsFormat::Csv, MergeStrategy::Skip)
+			.unwrap();
+		assert_eq!(summary.added, 2);
+		let mut result: Vec<Contact> = imported.contacts().collect();
+		result.sort_by(|a, b| a.name.cmp(&b.name));
+		assert_eq!(result, contacts);
+		fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn csv_errors_count_lines_inside_quoted_fields() {
+		let data = "name,address\nalice,keybase://alice,,\"two\nlines\"\nbob\n";
+		let e = parse_csv(data).unwrap_err();
+		match e.downcast_ref::<ErrorKind>() {
+			Some(ErrorKind::InvalidContactsFile(reason)) => assert!(reason.contains("line 4")),
+			_ => panic!("unexpected error {}", e),
+		}
+	}
+}
@@ -12,11 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::common::amount::{self, ParsedAmount, Unit};
 use crate::common::ErrorKind;
-use crate::wallet::types::{InitTxArgs, InitTxSendArgs};
-use clap::ArgMatches;
-use grin_core::core::amount_from_hr_string;
+use crate::contacts::{ContactsFormat, MergeStrategy};
+use crate::wallet::types::{
+	Amount, InitTxArgs, InitTxSendArgs, NotificationRoute, TxDirection, TxExportFormat, TxFilter,
+	TxRecipient,
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use clap::{ArgMatches, Values};
+use std::collections::BTreeMap;
 use std::str::FromStr;
+use uuid::Uuid;
 
 macro_rules! usage {
 	( $r:expr ) => {
@@ -28,13 +35,68 @@ macro_rules! usage {
 pub enum AccountArgs<'a> {
 	Create(&'a str),
 	Switch(&'a str),
+	Notify(&'a str, NotificationRoute),
+	Rename(&'a str, &'a str),
+	Archive(&'a str, bool),
+}
+
+#[derive(Clone, Debug)]
+pub enum DustArgs {
+	Analyze,
+	Sweep(Option<u64>, bool),
+}
+
+#[derive(Clone, Debug)]
+pub enum CheckArgs {
+	Repair(bool),
+	Kernels(Option<u64>),
+}
+
+#[derive(Clone, Debug)]
+pub enum BackupArgs<'a> {
+	Now,
+	Restore(&'a str),
+}
+
+#[derive(Clone, Debug)]
+pub enum MaintenanceArgs {
+	FixDerivation,
+	WipeHistory(Option<u64>),
+}
+
+#[derive(Clone, Debug)]
+pub enum IncomingArgs {
+	List,
+	Approve(Uuid),
+	Reject(Uuid),
+}
+
+#[derive(Clone, Debug)]
+pub enum NodeArgs {
+	Status,
+}
+
+#[derive(Clone, Debug)]
+pub enum MultisigArgs<'a> {
+	List,
+	Init(Amount, &'a str, Option<u64>),
+	Join(&'a str),
+	Spend(Uuid, &'a str),
+}
+
+#[derive(Clone, Debug)]
+pub enum OutboxArgs {
+	List,
+	Cancel(u32),
 }
 
 #[derive(Clone, Debug)]
 pub enum SendCommandType<'a> {
 	Estimate,
 	File(&'a str),
+	Qr,
 	Address,
+	Pick,
 }
 
 #[derive(Clone, Debug)]
@@ -43,24 +105,66 @@ pub enum ProofArgs<'a> {
 	Verify(&'a str),
 }
 
+#[derive(Clone, Debug)]
+pub enum TxArgs<'a> {
+	Label(u32, Option<String>),
+	Note(u32, Option<BTreeMap<String, String>>),
+	ExportContext(u32, &'a str),
+	ImportContext(&'a str, &'a str),
+	Show(u32),
+	Bump(u32, f64),
+}
+
+#[derive(Clone, Debug)]
+pub enum WalletArgs<'a> {
+	ExportData(&'a str),
+	ImportData(&'a str),
+}
+
+#[derive(Clone, Debug)]
+pub enum WatchArgs<'a> {
+	Export(&'a str),
+	Import(&'a str),
+	Scan,
+	Outputs(bool),
+	Txs,
+}
+
 #[derive(Clone, Debug)]
 pub enum ContactArgs<'a> {
-	Add(&'a str, &'a str),
+	Add(
+		&'a str,
+		&'a str,
+		Option<&'a str>,
+		Option<&'a str>,
+		Option<&'a str>,
+	),
 	Remove(&'a str),
 }
 
+#[derive(Clone, Debug)]
+pub enum ContactsArgs<'a> {
+	Display(Option<&'a str>),
+	Export(&'a str, ContactsFormat),
+	Import(&'a str, ContactsFormat, MergeStrategy),
+}
+
 #[derive(Clone, Debug)]
 pub enum AddressArgs {
 	Display,
+	Qr,
+	All,
 	Next,
 	Prev,
 	Index(u32),
 }
 
 #[derive(Clone, Debug)]
-pub enum SeedArgs {
+pub enum SeedArgs<'a> {
 	Display,
 	Recover,
+	Export(&'a str),
+	Import(&'a str),
 }
 
 fn required<'a>(args: &'a ArgMatches, name: &str) -> Result<&'a str, ErrorKind> {
@@ -76,10 +180,27 @@ where
 		.map_err(|_| ErrorKind::ParseNumber(arg.to_owned()))
 }
 
+/// Parse an amount, in grin unless a unit is given
+fn parse_amount(arg: &str) -> Result<Amount, ErrorKind> {
+	amount::parse_nano(arg, Unit::Grin).map(Amount::from_nano)
+}
+
 pub fn account_command<'a>(args: &'a ArgMatches) -> Result<AccountArgs<'a>, ErrorKind> {
 	let account_args = match args.subcommand() {
 		("create", Some(args)) => AccountArgs::Create(required(args, "name")?),
 		("switch", Some(args)) => AccountArgs::Switch(required(args, "name")?),
+		("notify", Some(args)) => AccountArgs::Notify(
+			required(args, "name")?,
+			NotificationRoute {
+				webhook: args.value_of("webhook").map(|w| w.to_owned()),
+				script: args.value_of("script").map(|s| s.to_owned()),
+			},
+		),
+		("rename", Some(args)) => {
+			AccountArgs::Rename(required(args, "name")?, required(args, "new_name")?)
+		}
+		("archive", Some(args)) => AccountArgs::Archive(required(args, "name")?, true),
+		("unarchive", Some(args)) => AccountArgs::Archive(required(args, "name")?, false),
 		(_, _) => {
 			usage!(args);
 		}
@@ -89,17 +210,28 @@ pub fn account_command<'a>(args: &'a ArgMatches) -> Result<AccountArgs<'a>, Erro
 
 pub fn send_command<'a>(
 	args: &'a ArgMatches,
+	default_change_outputs: u32,
 ) -> Result<(SendCommandType<'a>, InitTxArgs), ErrorKind> {
 	let mut init_args = InitTxArgs::default();
+	init_args.num_change_outputs = default_change_outputs;
 
-	let amount = required(args, "amount")?;
-	init_args.amount =
-		amount_from_hr_string(amount).map_err(|_| ErrorKind::ParseNumber(amount.to_owned()))?;
+	match amount::parse(required(args, "amount")?, Unit::Grin)? {
+		ParsedAmount::Nano(nano) => init_args.amount = Amount::from_nano(nano),
+		ParsedAmount::Max if args.is_present("estimate") => {
+			return Err(ErrorKind::Usage(
+				"use sweep --estimate to send max".to_owned(),
+			));
+		}
+		ParsedAmount::Max => init_args.sweep = true,
+	}
 	if let Some(confirmations) = args.value_of("confirmations") {
 		init_args.minimum_confirmations = parse(confirmations)?;
 	}
 	if let Some(change_outputs) = args.value_of("change_outputs") {
 		init_args.num_change_outputs = parse(change_outputs)?;
+		if init_args.num_change_outputs == 0 {
+			return Err(ErrorKind::ParseNumber(change_outputs.to_owned()));
+		}
 	}
 	init_args.selection_strategy_is_use_all = match args.value_of("strategy") {
 		Some("all") => true,
@@ -109,8 +241,28 @@ pub fn send_command<'a>(
 	if let Some(version) = args.value_of("version") {
 		init_args.target_slate_version = Some(parse(version)?);
 	}
+	init_args.payment_proof_recipient_address =
+		args.value_of("proof_address").map(|a| a.to_owned());
+	init_args.fee_acct_name = args.value_of("fee_account").map(|a| a.to_owned());
+	init_args.allow_dust = args.is_present("allow_dust");
+	init_args.late_lock = args.is_present("late_lock");
+	init_args.force = args.is_present("force");
+	if let Some(notes) = args.values_of("note") {
+		init_args.notes = parse_notes(notes)?;
+	}
+
+	// The amount and address of the command itself are the first recipient
+	if let Some(recipients) = args.values_of("recipient") {
+		init_args.recipients.push(TxRecipient {
+			amount: init_args.amount,
+			dest: args.value_of("address").map(|a| a.to_owned()),
+		});
+		init_args.recipients.extend(parse_recipients(recipients)?);
+	}
 
-	let cmd_type = if let Some(address) = args.value_of("address") {
+	let cmd_type = if !init_args.recipients.is_empty() && args.value_of("address").is_some() {
+		SendCommandType::Address
+	} else if let Some(address) = args.value_of("address") {
 		init_args.send_args = Some(InitTxSendArgs {
 			method: None,
 			dest: address.to_owned(),
@@ -121,16 +273,105 @@ pub fn send_command<'a>(
 		SendCommandType::Address
 	} else if let Some(file) = args.value_of("file_name") {
 		SendCommandType::File(file)
+	} else if args.is_present("qr") {
+		SendCommandType::Qr
 	} else if args.is_present("estimate") {
 		init_args.estimate_only = Some(true);
 		SendCommandType::Estimate
+	} else if !init_args.recipients.is_empty() {
+		return Err(ErrorKind::Argument("address".to_owned()));
 	} else {
-		usage!(args);
+		SendCommandType::Pick
 	};
 
 	Ok((cmd_type, init_args))
 }
 
+/// Parse `amount` or `amount=address` recipients
+fn parse_recipients(recipients: Values) -> Result<Vec<TxRecipient>, ErrorKind> {
+	let mut parsed = vec![];
+	for recipient in recipients {
+		let mut parts = recipient.splitn(2, '=');
+		let amount = parts.next().map(|a| a.trim()).unwrap_or("");
+		let amount =
+			parse_amount(amount).map_err(|_| ErrorKind::InvalidRecipient(recipient.to_owned()))?;
+		let dest = parts.next().map(|d| d.trim().to_owned());
+		if dest.as_ref().map(|d| d.is_empty()) == Some(true) {
+			return Err(ErrorKind::InvalidRecipient(recipient.to_owned()));
+		}
+		parsed.push(TxRecipient { amount, dest });
+	}
+	Ok(parsed)
+}
+
+/// Parse `key=value` notes. The value may be empty
+fn parse_notes(notes: Values) -> Result<BTreeMap<String, String>, ErrorKind> {
+	let mut map = BTreeMap::new();
+	for note in notes {
+		let mut parts = note.splitn(2, '=');
+		match (parts.next().map(|k| k.trim()), parts.next()) {
+			(Some(key), Some(value)) if !key.is_empty() => {
+				map.insert(key.to_owned(), value.trim().to_owned());
+			}
+			_ => return Err(ErrorKind::InvalidNote(note.to_owned())),
+		}
+	}
+	Ok(map)
+}
+
+pub fn sweep_command(args: &ArgMatches) -> Result<(Option<InitTxSendArgs>, InitTxArgs), ErrorKind> {
+	let mut init_args = InitTxArgs::default();
+	if let Some(confirmations) = args.value_of("confirmations") {
+		init_args.minimum_confirmations = parse(confirmations)?;
+	}
+	init_args.message = args.value_of("message").map(|m| m.to_owned());
+	init_args.estimate_only = Some(args.is_present("estimate"));
+
+	let dest = args.value_of("address").map(|address| InitTxSendArgs {
+		method: None,
+		dest: address.to_owned(),
+		finalize: true,
+		post_tx: true,
+		fluff: args.is_present("fluff"),
+	});
+	Ok((dest, init_args))
+}
+
+pub fn consolidate_command(args: &ArgMatches) -> Result<(usize, u64, bool), ErrorKind> {
+	let target_outputs = match args.value_of("target_outputs") {
+		Some(target_outputs) => parse(target_outputs)?,
+		None => 1,
+	};
+	let minimum_confirmations = match args.value_of("confirmations") {
+		Some(confirmations) => parse(confirmations)?,
+		None => InitTxArgs::default().minimum_confirmations,
+	};
+	Ok((
+		target_outputs,
+		minimum_confirmations,
+		args.is_present("fluff"),
+	))
+}
+
+pub fn dust_command(args: &ArgMatches) -> Result<(Option<u64>, u64, DustArgs), ErrorKind> {
+	let amount = |name: &str| -> Result<Option<u64>, ErrorKind> {
+		match args.value_of(name) {
+			Some(a) => Ok(Some(parse_amount(a)?.nano())),
+			None => Ok(None),
+		}
+	};
+	let threshold = amount("threshold")?;
+	let minimum_confirmations = match args.value_of("confirmations") {
+		Some(confirmations) => parse(confirmations)?,
+		None => InitTxArgs::default().minimum_confirmations,
+	};
+	let dust_args = match args.is_present("sweep") {
+		true => DustArgs::Sweep(amount("max_fee")?, args.is_present("fluff")),
+		false => DustArgs::Analyze,
+	};
+	Ok((threshold, minimum_confirmations, dust_args))
+}
+
 pub fn finalize_command<'a>(args: &'a ArgMatches) -> Result<(&'a str, bool), ErrorKind> {
 	Ok((required(args, "file_name")?, args.is_present("fluff")))
 }
@@ -139,22 +380,59 @@ pub fn repost_command(args: &ArgMatches) -> Result<(u32, bool), ErrorKind> {
 	Ok((parse(required(args, "index")?)?, args.is_present("fluff")))
 }
 
+pub fn resend_command(args: &ArgMatches) -> Result<u32, ErrorKind> {
+	Ok(parse(required(args, "index")?)?)
+}
+
 pub fn cancel_command(args: &ArgMatches) -> Result<u32, ErrorKind> {
 	Ok(parse(required(args, "index")?)?)
 }
 
-pub fn repair_command(args: &ArgMatches) -> Result<bool, ErrorKind> {
-	Ok(args.is_present("delete_unconfirmed"))
+pub fn check_command(args: &ArgMatches) -> Result<CheckArgs, ErrorKind> {
+	if args.is_present("kernels") {
+		let days = match args.value_of("days") {
+			Some(days) => Some(parse(days)?),
+			None => None,
+		};
+		return Ok(CheckArgs::Kernels(days));
+	}
+	Ok(CheckArgs::Repair(args.is_present("delete_unconfirmed")))
 }
 
-pub fn listen_command<'a>(args: &'a ArgMatches) -> Result<(&'a str, bool), ErrorKind> {
+pub fn listen_command<'a>(
+	args: &'a ArgMatches,
+) -> Result<(&'a str, bool, bool, Option<u32>, Option<&'a str>), ErrorKind> {
+	let index = match args.value_of("index") {
+		Some(index) => Some(parse(index)?),
+		None => None,
+	};
 	Ok((
 		args.value_of("type").unwrap_or(""),
 		args.is_present("owner"),
+		args.is_present("read-only"),
+		index,
+		args.value_of("account"),
+	))
+}
+
+pub fn receive_command<'a>(
+	args: &'a ArgMatches,
+) -> Result<(&'a str, Option<&'a str>, bool, Option<u64>, bool, bool), ErrorKind> {
+	let height = match args.value_of("height") {
+		Some(height) => Some(parse(height)?),
+		None => None,
+	};
+	Ok((
+		required(args, "file_name")?,
+		args.value_of("message"),
+		args.is_present("offline"),
+		height,
+		args.is_present("allow_dust"),
+		args.is_present("payjoin"),
 	))
 }
 
-pub fn receive_command<'a>(args: &'a ArgMatches) -> Result<(&'a str, Option<&'a str>), ErrorKind> {
+pub fn sign_command<'a>(args: &'a ArgMatches) -> Result<(&'a str, Option<&'a str>), ErrorKind> {
 	Ok((required(args, "file_name")?, args.value_of("message")))
 }
 
@@ -172,11 +450,245 @@ pub fn proof_command<'a>(args: &'a ArgMatches) -> Result<ProofArgs<'a>, ErrorKin
 	Ok(proof_args)
 }
 
+pub fn backup_command<'a>(args: &'a ArgMatches) -> Result<BackupArgs<'a>, ErrorKind> {
+	let backup_args = match args.subcommand() {
+		("now", _) => BackupArgs::Now,
+		("restore", Some(args)) => BackupArgs::Restore(required(args, "file")?),
+		(_, _) => {
+			usage!(args);
+		}
+	};
+	Ok(backup_args)
+}
+
+pub fn maintenance_command(args: &ArgMatches) -> Result<MaintenanceArgs, ErrorKind> {
+	let maintenance_args = match args.subcommand() {
+		("fix-derivation", _) => MaintenanceArgs::FixDerivation,
+		("wipe-history", Some(args)) => {
+			let days = match args.value_of("days") {
+				Some(days) => Some(parse(days)?),
+				None => None,
+			};
+			MaintenanceArgs::WipeHistory(days)
+		}
+		(_, _) => {
+			usage!(args);
+		}
+	};
+	Ok(maintenance_args)
+}
+
+pub fn node_command(args: &ArgMatches) -> Result<NodeArgs, ErrorKind> {
+	let node_args = match args.subcommand() {
+		("status", _) => NodeArgs::Status,
+		(_, _) => {
+			usage!(args);
+		}
+	};
+	Ok(node_args)
+}
+
+pub fn multisig_command<'a>(args: &'a ArgMatches) -> Result<MultisigArgs<'a>, ErrorKind> {
+	let multisig_args = match args.subcommand() {
+		("list", _) => MultisigArgs::List,
+		("init", Some(args)) => {
+			let amount = required(args, "amount")?;
+			let amount = parse_amount(amount)?;
+			let confirmations = match args.value_of("confirmations") {
+				Some(confirmations) => Some(parse(confirmations)?),
+				None => None,
+			};
+			MultisigArgs::Init(amount, required(args, "file_name")?, confirmations)
+		}
+		("join", Some(args)) => MultisigArgs::Join(required(args, "file_name")?),
+		("spend", Some(args)) => {
+			let id = required(args, "id")?;
+			let id = Uuid::parse_str(id).map_err(|_| ErrorKind::ParseId(id.to_owned()))?;
+			MultisigArgs::Spend(id, required(args, "file_name")?)
+		}
+		(_, _) => {
+			usage!(args);
+		}
+	};
+	Ok(multisig_args)
+}
+
+pub fn incoming_command(args: &ArgMatches) -> Result<IncomingArgs, ErrorKind> {
+	let parse_id = |args: &ArgMatches| -> Result<Uuid, ErrorKind> {
+		let id = required(args, "id")?;
+		Uuid::parse_str(id).map_err(|_| ErrorKind::ParseId(id.to_owned()))
+	};
+	let incoming_args = match args.subcommand() {
+		("list", _) => IncomingArgs::List,
+		("approve", Some(args)) => IncomingArgs::Approve(parse_id(args)?),
+		("reject", Some(args)) => IncomingArgs::Reject(parse_id(args)?),
+		(_, _) => {
+			usage!(args);
+		}
+	};
+	Ok(incoming_args)
+}
+
+pub fn outbox_command(args: &ArgMatches) -> Result<OutboxArgs, ErrorKind> {
+	let outbox_args = match args.subcommand() {
+		("list", _) => OutboxArgs::List,
+		("cancel", Some(args)) => OutboxArgs::Cancel(parse(required(args, "index")?)?),
+		(_, _) => {
+			usage!(args);
+		}
+	};
+	Ok(outbox_args)
+}
+
+pub fn wallet_command<'a>(args: &'a ArgMatches) -> Result<WalletArgs<'a>, ErrorKind> {
+	let wallet_args = match args.subcommand() {
+		("export-data", Some(args)) => WalletArgs::ExportData(required(args, "dir")?),
+		("import-data", Some(args)) => WalletArgs::ImportData(required(args, "dir")?),
+		(_, _) => {
+			usage!(args);
+		}
+	};
+	Ok(wallet_args)
+}
+
+pub fn watch_command<'a>(args: &'a ArgMatches) -> Result<WatchArgs<'a>, ErrorKind> {
+	let watch_args = match args.subcommand() {
+		("export", Some(args)) => WatchArgs::Export(required(args, "file_name")?),
+		("import", Some(args)) => WatchArgs::Import(required(args, "file_name")?),
+		("scan", _) => WatchArgs::Scan,
+		("outputs", Some(args)) => WatchArgs::Outputs(args.is_present("spent")),
+		("txs", _) => WatchArgs::Txs,
+		(_, _) => {
+			usage!(args);
+		}
+	};
+	Ok(watch_args)
+}
+
+pub fn tx_command<'a>(args: &'a ArgMatches) -> Result<TxArgs<'a>, ErrorKind> {
+	let tx_args = match args.subcommand() {
+		("label", Some(args)) => {
+			let index = parse(required(args, "index")?)?;
+			let text = args
+				.values_of("text")
+				.map(|t| t.collect::<Vec<&str>>().join(" "));
+			TxArgs::Label(index, text)
+		}
+		("note", Some(args)) => {
+			let index = parse(required(args, "index")?)?;
+			let notes = match args.values_of("notes") {
+				Some(notes) => Some(parse_notes(notes)?),
+				None => None,
+			};
+			TxArgs::Note(index, notes)
+		}
+		("export-context", Some(args)) => TxArgs::ExportContext(
+			parse(required(args, "index")?)?,
+			required(args, "file_name")?,
+		),
+		("import-context", Some(args)) => TxArgs::ImportContext(
+			required(args, "file_name")?,
+			required(args, "slate_file_name")?,
+		),
+		("show", Some(args)) => TxArgs::Show(parse(required(args, "index")?)?),
+		("bump", Some(args)) => TxArgs::Bump(
+			parse(required(args, "index")?)?,
+			parse(required(args, "fee_factor")?)?,
+		),
+		(_, _) => {
+			usage!(args);
+		}
+	};
+	Ok(tx_args)
+}
+
+pub fn txs_command<'a>(
+	args: &'a ArgMatches,
+) -> Result<(Option<(&'a str, TxExportFormat)>, TxFilter), ErrorKind> {
+	let min_amount = match args.value_of("min_amount") {
+		Some(a) => Some(parse_amount(a)?),
+		None => None,
+	};
+	let direction = match args.value_of("type") {
+		Some(t) => {
+			Some(TxDirection::from_str(t).map_err(|_| ErrorKind::Argument("type".to_owned()))?)
+		}
+		None => None,
+	};
+	let filter = TxFilter {
+		search: args.value_of("search").map(|s| s.to_owned()),
+		address: args.value_of("address").map(|a| a.to_owned()),
+		contact: args.value_of("contact").map(|c| c.to_owned()),
+		min_amount,
+		since: args
+			.value_of("since")
+			.map(|d| parse_date(d, false))
+			.transpose()?,
+		until: args
+			.value_of("until")
+			.map(|d| parse_date(d, true))
+			.transpose()?,
+		direction,
+		unconfirmed: args.is_present("unconfirmed"),
+	};
+
+	let file_name = match args.value_of("export") {
+		Some(f) => f,
+		None => return Ok((None, filter)),
+	};
+	let format = match args.value_of("format") {
+		Some(f) => TxExportFormat::from_str(f)?,
+		None => TxExportFormat::from_path(file_name),
+	};
+	Ok((Some((file_name, format)), filter))
+}
+
+/// Parse a date as YYYY-MM-DD or RFC 3339. A plain date either means the
+/// start of that day, or the end of it when `end_of_day` is set
+fn parse_date(date: &str, end_of_day: bool) -> Result<DateTime<Utc>, ErrorKind> {
+	if let Ok(d) = DateTime::parse_from_rfc3339(date) {
+		return Ok(d.with_timezone(&Utc));
+	}
+	let day = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+		.map_err(|_| ErrorKind::ParseDate(date.to_owned()))?;
+	let mut start = DateTime::<Utc>::from_utc(day.and_hms(0, 0, 0), Utc);
+	if end_of_day {
+		start = start + Duration::days(1);
+	}
+	Ok(start)
+}
+
+pub fn prove_ownership_command<'a>(
+	args: &'a ArgMatches,
+) -> Result<(&'a str, &'a str, Option<&'a str>), ErrorKind> {
+	Ok((
+		required(args, "commit")?,
+		required(args, "file_name")?,
+		args.value_of("message"),
+	))
+}
+
+pub fn attest_command<'a>(args: &'a ArgMatches) -> Result<(&'a str, Option<&'a str>), ErrorKind> {
+	Ok((required(args, "file_name")?, args.value_of("message")))
+}
+
+pub fn verify_attestation_command<'a>(args: &'a ArgMatches) -> Result<&'a str, ErrorKind> {
+	required(args, "file_name")
+}
+
+pub fn verify_ownership_command<'a>(args: &'a ArgMatches) -> Result<&'a str, ErrorKind> {
+	required(args, "file_name")
+}
+
 pub fn contact_command<'a>(args: &'a ArgMatches) -> Result<ContactArgs<'a>, ErrorKind> {
 	let contact_args = match args.subcommand() {
-		("add", Some(args)) => {
-			ContactArgs::Add(required(args, "name")?, required(args, "address")?)
-		}
+		("add", Some(args)) => ContactArgs::Add(
+			required(args, "name")?,
+			required(args, "address")?,
+			args.value_of("group"),
+			args.value_of("notes"),
+			args.value_of("relay"),
+		),
 		("remove", Some(args)) => ContactArgs::Remove(required(args, "name")?),
 		(_, _) => {
 			usage!(args);
@@ -185,23 +697,59 @@ pub fn contact_command<'a>(args: &'a ArgMatches) -> Result<ContactArgs<'a>, Erro
 	Ok(contact_args)
 }
 
+pub fn contacts_command<'a>(args: &'a ArgMatches) -> Result<ContactsArgs<'a>, ErrorKind> {
+	let contacts_args = match args.subcommand() {
+		("export", Some(args)) => {
+			let file_name = required(args, "file_name")?;
+			ContactsArgs::Export(file_name, contacts_format(args, file_name)?)
+		}
+		("import", Some(args)) => {
+			let file_name = required(args, "file_name")?;
+			ContactsArgs::Import(
+				file_name,
+				contacts_format(args, file_name)?,
+				MergeStrategy::from_str(args.value_of("strategy").unwrap_or("skip"))?,
+			)
+		}
+		_ => ContactsArgs::Display(args.value_of("group")),
+	};
+	Ok(contacts_args)
+}
+
+fn contacts_format(args: &ArgMatches, file_name: &str) -> Result<ContactsFormat, ErrorKind> {
+	match args.value_of("format") {
+		Some(f) => ContactsFormat::from_str(f),
+		None => Ok(ContactsFormat::from_path(file_name)),
+	}
+}
+
 pub fn address_command(args: &ArgMatches) -> Result<AddressArgs, ErrorKind> {
-	let address_args = if args.is_present("next") {
+	let address_args = if args.is_present("all") {
+		AddressArgs::All
+	} else if args.is_present("next") {
 		AddressArgs::Next
 	} else if args.is_present("prev") {
 		AddressArgs::Prev
 	} else if let Some(index) = args.value_of("index") {
 		AddressArgs::Index(parse(index)?)
+	} else if args.is_present("qr") {
+		AddressArgs::Qr
 	} else {
 		AddressArgs::Display
 	};
 	Ok(address_args)
 }
 
-pub fn seed_command(args: &ArgMatches) -> Result<SeedArgs, ErrorKind> {
+pub fn restore_command(args: &ArgMatches) -> Result<bool, ErrorKind> {
+	Ok(args.is_present("name_accounts"))
+}
+
+pub fn seed_command<'a>(args: &'a ArgMatches) -> Result<SeedArgs<'a>, ErrorKind> {
 	let seed_args = match args.subcommand() {
 		("display", _) => SeedArgs::Display,
 		("recover", _) => SeedArgs::Recover,
+		("export", Some(args)) => SeedArgs::Export(required(args, "file_name")?),
+		("import", Some(args)) => SeedArgs::Import(required(args, "file_name")?),
 		(_, _) => {
 			usage!(args);
 		}
@@ -13,33 +13,58 @@
 // limitations under the License.
 
 use super::args::{
-	self, AccountArgs, AddressArgs, ContactArgs, ProofArgs, SeedArgs, SendCommandType,
+	self, AccountArgs, AddressArgs, BackupArgs, CheckArgs, ContactArgs, ContactsArgs, DustArgs,
+	IncomingArgs, MaintenanceArgs, MultisigArgs, NodeArgs, OutboxArgs, ProofArgs, SeedArgs,
+	SendCommandType, TxArgs, WalletArgs, WatchArgs,
 };
 use super::display::{self, InitialPromptOption};
+use super::picker;
 use crate::api::listener::ListenerInterface;
+use crate::common::crypto::Hex;
 use crate::common::motd::get_motd;
-use crate::common::{Arc, ErrorKind, Keychain, Mutex};
+use crate::common::timing::as_millis;
+use crate::common::{Arc, ErrorKind, Keychain, Mutex, RuntimeContext};
 use crate::contacts::Address;
 use crate::wallet::api::{Foreign, Owner};
-use crate::wallet::types::{NodeClient, TxProof, VersionedSlate, WalletBackend};
-use crate::wallet::Container;
+use crate::wallet::types::{
+	compact, create_private_file, AccountLabeler, BalanceAttestation, EncryptedContext,
+	EstimateFeeArgs, InitTxArgs, InitTxSendArgs, MultisigSlate, NodeClient, OutputCommitMapping,
+	OwnershipProof, PaymentProof, SeedBackup, Slate, SlateVersion, TxLogEntryType, TxProof,
+	VersionedSlate, WalletBackend, WatchKey,
+};
+use crate::wallet::{Container, ErrorKind as WalletErrorKind};
 use clap::{crate_version, load_yaml, App, ArgMatches};
 use colored::Colorize;
 use failure::Error;
 use grin_core::core::amount_to_hr_string;
+use grin_util::secp::pedersen::Commitment;
+use grin_util::ZeroingString;
 use rustyline::completion::{Completer, FilenameCompleter, Pair};
 use rustyline::error::ReadlineError;
 use rustyline::highlight::{Highlighter, MatchingBracketHighlighter};
 use rustyline::hint::Hinter;
 use rustyline::{CompletionType, Config, Context, EditMode, Editor, Helper, OutputStreamType};
 use semver::Version;
+use serde_json::{json, Value};
 use std::borrow::Cow::{self, Borrowed, Owned};
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
 
 const COLORED_PROMPT: &'static str = "\x1b[36mwallet713>\x1b[0m ";
 const PROMPT: &'static str = "wallet713> ";
 const HISTORY_PATH: &str = ".history";
+/// How often the idle time is checked against `auto_lock_minutes`
+const AUTO_LOCK_INTERVAL: Duration = Duration::from_secs(10);
+/// Commands that don't ask for the password when the wallet is locked
+const LOCK_FREE_COMMANDS: [&str; 6] = ["exit", "features", "listeners", "lock", "passwd", "perf"];
+/// File name of slates read from stdin or written to stdout
+const STDIO_FILE: &str = "-";
+/// Characters of a compact slate shown in each QR frame
+const QR_FRAME_LEN: usize = 300;
 
 pub struct CLI<W, C, K>
 where
@@ -49,6 +74,9 @@ where
 {
 	api: Owner<W, C, K>,
 	foreign: Foreign<W, C, K>,
+	runtime: RuntimeContext,
+	/// Since when no command has been running
+	idle_since: Arc<Mutex<Option<Instant>>>,
 }
 
 impl<W, C, K> CLI<W, C, K>
@@ -58,9 +86,12 @@ where
 	K: Keychain,
 {
 	pub fn new(container: Arc<Mutex<Container<W, C, K>>>) -> Self {
+		let runtime = container.lock().runtime.clone();
 		Self {
 			api: Owner::new(container.clone()),
 			foreign: Foreign::new(container),
+			runtime,
+			idle_since: Arc::new(Mutex::new(Some(Instant::now()))),
 		}
 	}
 
@@ -87,7 +118,7 @@ where
 			return Ok(());
 		}
 
-		if self.api.config().check_updates() {
+		if self.api.config().check_updates(&self.runtime) {
 			let _ = get_motd();
 		}
 
@@ -95,14 +126,123 @@ where
 			return Ok(());
 		}
 
+		let days = self.api.config().kernel_check_days();
+		if days > 0 {
+			if let Err(e) = self.reconcile_kernels(days, true) {
+				display::error(e);
+			}
+		}
+
+		if let Some(days) = self.api.config().history_retention_days() {
+			match self.api.wipe_history(days) {
+				Ok(wipe) if !wipe.is_empty() => display::history_wipe(&wipe),
+				Ok(_) => {}
+				Err(e) => display::error(e),
+			}
+		}
+
 		println!("Use `help` to see available commands");
 		println!();
 
 		self.start_listeners()?;
+		self.start_auto_lock();
 		self.command_loop();
 		Ok(())
 	}
 
+	/// Run a single command and return, for scripts. Listeners are not
+	/// started, so slates sent over grinbox or keybase are finalized by
+	/// whichever instance is listening
+	pub fn exec(
+		&self,
+		command: &str,
+		password: Option<ZeroingString>,
+		json: bool,
+	) -> Result<(), Error> {
+		if !self.api.has_seed()? {
+			return Err(ErrorKind::WalletNoSeed.into());
+		}
+		let password = match password {
+			Some(p) => p,
+			None => display::password_prompt()?,
+		};
+		self.api.set_password(password)?;
+		self.api.connect()?;
+
+		let yml = load_yaml!("commands.yml");
+		let app = App::from_yaml(yml).version(crate_version!());
+		let args = match app.get_matches_from_safe(command.trim().split_whitespace()) {
+			Ok(args) => args,
+			Err(e) if e.kind == clap::ErrorKind::HelpDisplayed => {
+				println!("{}", e);
+				return Ok(());
+			}
+			Err(e) => return Err(ErrorKind::Usage(e.message).into()),
+		};
+		if json {
+			let output = self.json_command(&args)?;
+			println!("{}", serde_json::to_string_pretty(&output)?);
+		} else {
+			self.command(args)?;
+		}
+		Ok(())
+	}
+
+	/// Lock the wallet once no command has run for `auto_lock_minutes`
+	fn start_auto_lock(&self) {
+		let minutes = match self.api.config().auto_lock_minutes() {
+			Some(m) if self.runtime.is_cli() => m,
+			_ => return,
+		};
+		let timeout = Duration::from_secs(minutes * 60);
+		let api = self.api.clone();
+		let idle_since = self.idle_since.clone();
+		let runtime = self.runtime.clone();
+		let _ = thread::Builder::new()
+			.name("auto-lock".to_owned())
+			.spawn(move || loop {
+				thread::sleep(AUTO_LOCK_INTERVAL);
+				// Held while locking, so a command can't start halfway through
+				let idle_since = idle_since.lock();
+				let idle = idle_since.map(|s| s.elapsed() >= timeout).unwrap_or(false);
+				if idle && !api.is_locked().unwrap_or(true) && api.lock().is_ok() {
+					cli_message!(
+						runtime,
+						"Wallet locked after {} minutes without commands",
+						minutes
+					);
+				}
+			});
+	}
+
+	/// Ask for the password if the wallet was locked in the meantime
+	fn ensure_unlocked(&self, command: &str) -> Result<(), Error> {
+		if LOCK_FREE_COMMANDS.contains(&command) || !self.api.is_locked()? {
+			return Ok(());
+		}
+		println!("The wallet is locked");
+		self.api.unlock(display::password_prompt()?)
+	}
+
+	/// Look for recently confirmed transactions whose kernel is gone from the
+	/// chain, and offer to check the outputs they touched. At startup nothing
+	/// is shown as long as all kernels are found
+	fn reconcile_kernels(&self, days: u64, quiet: bool) -> Result<(), Error> {
+		let reconciliation = self.api.reconcile_kernels(days)?;
+		if quiet && reconciliation.is_consistent() {
+			return Ok(());
+		}
+		display::kernel_reconciliation(&reconciliation);
+		if !reconciliation.is_consistent()
+			&& display::confirm("Check the affected outputs against the chain now?")
+		{
+			println!("Checking and repairing wallet..");
+			self.api.check_repair(false, reconciliation.repair_from)?;
+			println!("Wallet repaired successfully");
+		}
+		Ok(())
+	}
+
 	fn initial_prompt(&self) -> Result<bool, Error> {
 		match display::initial_prompt()? {
 			InitialPromptOption::Init => {
@@ -113,6 +253,13 @@ where
 				self.recover_wallet(false)?;
 				Ok(false)
 			}
+			InitialPromptOption::WatchOnly => {
+				println!("Running without a seed, only the `watch` commands are available");
+				println!("Use `watch import <file>` to follow a wallet from its watch key");
+				println!();
+				self.command_loop();
+				Ok(true)
+			}
 			InitialPromptOption::Exit => Ok(true),
 		}
 	}
@@ -140,14 +287,60 @@ where
 		println!();
 		let password = display::password_prompt()?;
 		self.api.set_seed(Some(mnemonic), password, overwrite)?;
+		self.restore_recovered()
+	}
+
+	/// Rebuild the wallet contents from the chain after the seed was replaced
+	fn restore_recovered(&self) -> Result<(), Error> {
 		self.api.connect()?;
 		self.api.clear()?;
 		println!("Recovering wallet..");
-		self.api.restore()?;
+		self.api.restore(None)?;
 		println!("Wallet recovered successfully");
 		Ok(())
 	}
 
+	/// Sign a slate file as the recipient and write the response next to it,
+	/// or to stdout for a slate read from stdin. Offline, the node isn't
+	/// contacted at all
+	fn receive_file(
+		&self,
+		file_name: &str,
+		message: Option<&str>,
+		offline: bool,
+		height: Option<u64>,
+		allow_dust: bool,
+		payjoin: bool,
+	) -> Result<(), Error> {
+		let slate = read_slate(file_name)?;
+		let version = slate.version().clone();
+		let slate = slate.into();
+		let message = message.map(|m| m.to_owned());
+		let foreign = self
+			.foreign
+			.clone()
+			.with_dust_allowed(allow_dust)
+			.with_payjoin(payjoin);
+		let slate = match offline {
+			true => foreign.receive_tx_offline(&slate, message, height)?,
+			false => foreign.receive_tx(&slate, None, Some("file".to_owned()), message)?,
+		};
+		let slate = VersionedSlate::into_version(slate, version);
+		let serialized = serde_json::to_string(&slate)?;
+		if file_name == STDIO_FILE {
+			write_slate(STDIO_FILE, &serialized)?;
+			cli_message!(self.runtime, "Response slate written to stdout");
+			return Ok(());
+		}
+		write_slate(&format!("{}.response", file_name), &serialized)?;
+		cli_message!(
+			self.runtime,
+			"Response slate file {} created successfully",
+			format!("{}.response", file_name.bright_green())
+		);
+		Ok(())
+	}
+
 	fn check_node_version(&self) -> bool {
 		if let Some(v) = self.api.node_version() {
 			if Version::parse(&v.node_version) < Version::parse("2.0.0-beta.1") {
@@ -166,9 +359,21 @@ where
 
 	fn start_listeners(&self) -> Result<(), Error> {
 		let config = self.api.config();
-		if config.grinbox_listener_auto_start() {
-			if let Err(e) = self.api.start_listener(ListenerInterface::Grinbox) {
-				display::error(e);
+		if config.grinbox_listener_auto_start(&self.runtime) {
+			let index = config.grinbox_address_index();
+			let accounts = config.grinbox_listener_accounts();
+			let accounts = match accounts.is_empty() {
+				true => vec![None],
+				false => accounts.iter().map(|a| Some(a.as_str())).collect(),
+			};
+			for account in accounts {
+				let res = self
+					.api
+					.grinbox_interface(account, index)
+					.and_then(|interface| self.api.start_listener(interface));
+				if let Err(e) = res {
+					display::error(e);
+				}
 			}
 		}
 		if config.keybase_listener_auto_start() {
@@ -176,6 +381,16 @@ where
 				display::error(e);
 			}
 		}
+		if config.email_listener_auto_start() {
+			if let Err(e) = self.api.start_listener(ListenerInterface::Email) {
+				display::error(e);
+			}
+		}
+		if config.adapters_listener_auto_start() {
+			if let Err(e) = self.api.start_listener(ListenerInterface::Exec) {
+				display::error(e);
+			}
+		}
 		if config.foreign_api() {
 			if let Err(e) = self.api.start_listener(ListenerInterface::ForeignHttp) {
 				display::error(e);
@@ -186,6 +401,31 @@ where
 				display::error(e);
 			}
 		}
+		if config.owner_read_api() {
+			if let Err(e) = self.api.start_listener(ListenerInterface::OwnerReadHttp) {
+				display::error(e);
+			}
+		}
+		if config.outbox_listener_auto_start() {
+			if let Err(e) = self.api.start_listener(ListenerInterface::Outbox) {
+				display::error(e);
+			}
+		}
+		if config.slate_retry() {
+			if let Err(e) = self.api.start_listener(ListenerInterface::Retry) {
+				display::error(e);
+			}
+		}
+		if config.block_listener() {
+			if let Err(e) = self.api.start_listener(ListenerInterface::Blocks) {
+				display::error(e);
+			}
+		}
+		if config.backup_schedule() {
+			if let Err(e) = self.api.start_listener(ListenerInterface::Backup) {
+				display::error(e);
+			}
+		}
 
 		Ok(())
 	}
@@ -228,20 +468,44 @@ where
 
 					let args = app.get_matches_from_safe_borrow(command.trim().split_whitespace());
 					let done = match args {
-						Ok(args) => match self.command(args) {
-							Ok(done) => done,
-							Err(err) => {
-								cli_message!("{} {}", "Error:".bright_red(), err);
-								false
+						Ok(args) => {
+							let show_timings = args.is_present("timings")
+								|| args
+									.subcommand()
+									.1
+									.map(|m| m.is_present("timings"))
+									.unwrap_or(false);
+							let name = args.subcommand_name().unwrap_or("").to_owned();
+
+							// Discard stages recorded by background activity
+							let _ = self.api.take_recent_timings();
+							let start = Instant::now();
+							*self.idle_since.lock() = None;
+							let res = self.command(args);
+							*self.idle_since.lock() = Some(Instant::now());
+							let elapsed = start.elapsed();
+							let stages = self.api.take_recent_timings();
+							self.api
+								.record_timing(&format!("command {}", name), elapsed);
+							if show_timings {
+								display::command_timings(&name, as_millis(elapsed), stages);
+							}
+
+							match res {
+								Ok(done) => done,
+								Err(err) => {
+									cli_message!(self.runtime, "{} {}", "Error:".bright_red(), err);
+									false
+								}
 							}
-						},
+						}
 						Err(err) => {
 							match err.kind {
 								clap::ErrorKind::HelpDisplayed => {
-									cli_message!("{}", err);
+									cli_message!(self.runtime, "{}", err);
 								}
 								_ => {
-									cli_message!("{} {}", "Error:".bright_red(), err);
+									cli_message!(self.runtime, "{} {}", "Error:".bright_red(), err);
 								}
 							}
 							false
@@ -263,11 +527,189 @@ where
 		let _ = reader.save_history(&history_file);
 	}
 
+	fn listener_interface(&self, args: &ArgMatches) -> Result<ListenerInterface, Error> {
+		let interface = match args::listen_command(args)? {
+			("grinbox", _, _, index, account) | ("", _, _, index, account) => {
+				let index = index.unwrap_or(self.api.config().grinbox_address_index());
+				self.api.grinbox_interface(account, index)?
+			}
+			("keybase", _, _, _, _) => ListenerInterface::Keybase,
+			("email", _, _, _, _) => ListenerInterface::Email,
+			("adapters", _, _, _, _) => ListenerInterface::Exec,
+			("http", _, true, _, _) => ListenerInterface::OwnerReadHttp,
+			("http", true, false, _, _) => ListenerInterface::OwnerHttp,
+			("http", false, false, _, _) => ListenerInterface::ForeignHttp,
+			("outbox", _, _, _, _) => ListenerInterface::Outbox,
+			("retry", _, _, _, _) => ListenerInterface::Retry,
+			("blocks", _, _, _, _) => ListenerInterface::Blocks,
+			("backups", _, _, _, _) => ListenerInterface::Backup,
+			_ => {
+				return Err(ErrorKind::IncorrectListenerInterface.into());
+			}
+		};
+		Ok(interface)
+	}
+
+	/// Show the inputs, change and balance of a send and ask to go ahead.
+	/// Single commands can't be answered, so they go ahead right away
+	fn confirm_send(&self, args: &InitTxArgs) -> Result<bool, Error> {
+		if self.runtime.is_exec() {
+			return Ok(true);
+		}
+		let preview = self.api.preview_send_tx(args)?;
+		display::send_preview(&preview);
+		let confirmed = display::confirm("Sign and send this transaction?");
+		if !confirmed {
+			println!("Transaction cancelled");
+		}
+		Ok(confirmed)
+	}
+
+	/// Ask again for a send above `confirm_threshold`, then complete or drop
+	/// it. `None` when it isn't confirmed
+	fn confirm_policy(&self, res: Result<Slate, Error>) -> Result<Option<Slate>, Error> {
+		let e = match res {
+			Ok(slate) => return Ok(Some(slate)),
+			Err(e) => e,
+		};
+		let (token, amount) = match e.downcast_ref::<WalletErrorKind>() {
+			Some(WalletErrorKind::ConfirmationRequired(token, amount)) => {
+				let id = Uuid::parse_str(token).map_err(|_| ErrorKind::ParseId(token.clone()))?;
+				(id, amount.clone())
+			}
+			_ => return Err(e),
+		};
+		if self.runtime.is_exec() {
+			self.api.discard_send_tx(&token);
+			return Err(e);
+		}
+		let question = format!(
+			"{} grin is above your confirmation threshold, really send it?",
+			amount
+		);
+		if !display::confirm(&question) {
+			self.api.discard_send_tx(&token);
+			println!("Transaction cancelled");
+			return Ok(None);
+		}
+		self.api.confirm_send_tx(&token).map(Some)
+	}
+
+	/// Output of a command for `--json`, for the commands scripts read from
+	fn json_command(&self, args: &ArgMatches) -> Result<Value, Error> {
+		let output = match args.subcommand() {
+			("accounts", Some(m)) => {
+				let accounts = match m.is_present("all") {
+					true => self.api.all_accounts()?,
+					false => self.api.accounts()?,
+				};
+				json!(accounts)
+			}
+			("address", Some(m)) if m.subcommand_name().is_none() => json!({
+				"address": self.api.grinbox_address()?.stripped(),
+				"index": self.api.config().grinbox_address_index(),
+			}),
+			("info", _) => {
+				let (validated, info) = self.api.retrieve_summary_info(true, 10)?;
+				json!({
+					"account": self.api.active_account()?,
+					"validated": validated,
+					"info": info,
+				})
+			}
+			("node", Some(m)) => match args::node_command(m)? {
+				NodeArgs::Status => json!(self.api.node_status()?),
+			},
+			("outputs", Some(m)) => {
+				let (validated, height, outputs) =
+					self.api
+						.retrieve_outputs(m.is_present("spent"), true, None, None)?;
+				json!({
+					"account": self.api.active_account()?,
+					"validated": validated,
+					"height": height,
+					"outputs": outputs,
+				})
+			}
+			("pending", _) => json!(self.api.pending_txs()?),
+			("send", Some(m)) => {
+				let (cmd_type, args) =
+					args::send_command(m, self.api.config().default_change_outputs())?;
+				let slate = match cmd_type {
+					SendCommandType::Address => self.api.init_send_tx(args)?,
+					SendCommandType::File(STDIO_FILE) => {
+						let msg = "--json can't write the slate to stdout".to_owned();
+						return Err(ErrorKind::Usage(msg).into());
+					}
+					SendCommandType::Qr => {
+						let msg = "--json can't show QR codes".to_owned();
+						return Err(ErrorKind::Usage(msg).into());
+					}
+					SendCommandType::File(file_name) => {
+						let slate = self.api.init_send_tx(args)?;
+						write_slate(file_name, &serde_json::to_string_pretty(&slate)?)?;
+						self.api
+							.tx_lock_outputs(&slate, 0, Some("file".to_owned()))?;
+						slate
+					}
+					SendCommandType::Estimate => {
+						let estimates = self.api.estimate_fee(EstimateFeeArgs {
+							src_acct_name: args.src_acct_name.clone(),
+							amount: args.amount,
+							minimum_confirmations: args.minimum_confirmations,
+							max_outputs: args.max_outputs,
+							num_change_outputs: args.num_change_outputs,
+							..EstimateFeeArgs::default()
+						})?;
+						return Ok(json!(estimates));
+					}
+					SendCommandType::Pick => {
+						return Err(ErrorKind::Usage("--pick needs the prompt".to_owned()).into());
+					}
+				};
+				json!({
+					"slate_id": slate.id,
+					"amount": slate.amount,
+					"fee": slate.fee,
+				})
+			}
+			("txs", Some(m)) => match args::txs_command(m)? {
+				(None, filter) => {
+					let (validated, height, txs, _, _) = self.api.retrieve_txs(
+						true,
+						false,
+						false,
+						None,
+						None,
+						Some(filter),
+						None,
+					)?;
+					json!({
+						"account": self.api.active_account()?,
+						"validated": validated,
+						"height": height,
+						"txs": txs,
+					})
+				}
+				(Some(_), _) => {
+					return Err(ErrorKind::Usage("--export has no JSON output".to_owned()).into());
+				}
+			},
+			(name, _) => {
+				let msg = format!("`{}` has no JSON output", name);
+				return Err(ErrorKind::Usage(msg).into());
+			}
+		};
+		Ok(output)
+	}
+
 	fn command(&self, args: ArgMatches) -> Result<bool, Error> {
 		let home_dir = dirs::home_dir()
 			.map(|p| p.to_str().unwrap().to_string())
 			.unwrap_or("~".to_string());
 
+		self.ensure_unlocked(args.subcommand_name().unwrap_or(""))?;
+
 		match args.subcommand() {
 			("account", Some(m)) => match args::account_command(m)? {
 				AccountArgs::Create(name) => {
@@ -278,19 +720,51 @@ where
 					self.api.set_active_account(name)?;
 					println!("Switched to account '{}'", name);
 				}
+				AccountArgs::Notify(name, route) => {
+					let cleared = route.is_empty();
+					self.api.set_account_notifications(name, route)?;
+					match cleared {
+						true => println!("Notification route of account '{}' cleared", name),
+						false => println!("Notification route of account '{}' set", name),
+					}
+				}
+				AccountArgs::Rename(name, new_name) => {
+					self.api.rename_account(name, new_name)?;
+					println!("Account '{}' renamed to '{}'", name, new_name);
+				}
+				AccountArgs::Archive(name, archived) => {
+					self.api.archive_account(name, archived)?;
+					match archived {
+						true => println!("Account '{}' archived", name),
+						false => println!("Account '{}' unarchived", name),
+					}
+				}
 			},
-			("accounts", _) => {
-				display::accounts(self.api.accounts()?);
+			("accounts", Some(m)) => {
+				let accounts = match m.is_present("all") {
+					true => self.api.all_accounts()?,
+					false => self.api.accounts()?,
+				};
+				display::accounts(accounts);
 			}
 			("address", Some(m)) => {
 				let mut idx = self.api.config().grinbox_address_index();
 				match args::address_command(m)? {
+					AddressArgs::All => {
+						display::reachable_addresses(&self.api.reachable_addresses()?);
+						return Ok(false);
+					}
 					AddressArgs::Display => {
 						println!(
 							"Your grinbox address is {}",
 							self.api.grinbox_address()?.stripped().bright_green()
 						);
 					}
+					AddressArgs::Qr => {
+						let address = self.api.grinbox_address()?.stripped();
+						display::qr_code(&address)?;
+						println!("Your grinbox address is {}", address.bright_green());
+					}
 					AddressArgs::Next => {
 						idx = idx.saturating_add(1);
 						self.api.set_grinbox_address_index(idx)?;
@@ -305,24 +779,120 @@ where
 					}
 				};
 				cli_message!(
+					self.runtime,
 					"Using grinbox address index {}",
 					idx.to_string().bright_green()
 				);
 			}
+			("attest", Some(m)) => {
+				let (file_name, message) = args::attest_command(m)?;
+				let attestation = self.api.attest_balance(message.unwrap_or("").to_owned())?;
+				let mut file = File::create(file_name.replace("~", &home_dir))?;
+				file.write_all(serde_json::to_string(&attestation)?.as_bytes())?;
+				println!(
+					"Attestation of {} outputs worth {} grin exported to {}",
+					attestation.commits.len(),
+					amount_to_hr_string(attestation.total, false).bright_green(),
+					file_name.bright_green()
+				);
+			}
+			("backup", Some(m)) => match args::backup_command(m)? {
+				BackupArgs::Now => {
+					let backup = self.api.backup_now()?;
+					display::backup(&backup);
+				}
+				BackupArgs::Restore(file) => {
+					let question = format!(
+						"Replace the wallet database, stored transactions and proofs with {}?",
+						file
+					);
+					if display::confirm(&question) {
+						let previous = self.api.restore_backup(&file.replace("~", &home_dir))?;
+						println!("Backup {} restored", file.bright_green());
+						println!(
+							"The replaced wallet was backed up to {}",
+							previous.path.bright_green()
+						);
+					}
+				}
+			},
 			("cancel", Some(m)) => {
 				let index = args::cancel_command(m)?;
 				self.api.cancel_tx(Some(index), None)?;
 				println!("Transaction cancelled successfully");
 			}
-			("check", Some(m)) => {
-				let delete_unconfirmed = args::repair_command(m)?;
-				println!("Checking and repairing wallet..");
-				self.api.check_repair(delete_unconfirmed)?;
-				println!("Wallet repaired successfully");
+			("check", Some(m)) => match args::check_command(m)? {
+				CheckArgs::Repair(delete_unconfirmed) => {
+					println!("Checking and repairing wallet..");
+					self.api.check_repair(delete_unconfirmed, None)?;
+					println!("Wallet repaired successfully");
+				}
+				CheckArgs::Kernels(days) => {
+					let days = days.unwrap_or(self.api.config().kernel_check_days());
+					self.reconcile_kernels(days, false)?;
+				}
+			},
+			("cleanup", _) => {
+				let account = self.api.active_account()?;
+				let (_, _, txs, contacts, _) = self
+					.api
+					.retrieve_txs(true, true, false, None, None, None, None)?;
+				let txs: Vec<_> = txs
+					.into_iter()
+					.filter(|t| {
+						!t.confirmed
+							&& (t.tx_type == TxLogEntryType::TxSent
+								|| t.tx_type == TxLogEntryType::TxReceived)
+					})
+					.collect();
+				if txs.is_empty() {
+					println!("No outstanding transactions in account '{}'", account);
+					return Ok(false);
+				}
+
+				display::cleanup(&txs, contacts);
+				let candidates: Vec<u32> = txs.iter().map(|t| t.id).collect();
+				let selected = display::cleanup_prompt(&candidates)?;
+				if selected.is_empty() {
+					println!("Nothing cancelled");
+					return Ok(false);
+				}
+
+				let (_, before) = self.api.retrieve_summary_info(false, 1)?;
+				let mut cancelled = 0;
+				for (id, res) in self.api.cancel_txs(&selected)? {
+					match res {
+						Ok(_) => cancelled += 1,
+						Err(e) => {
+							display::error(format!("Unable to cancel transaction {}: {}", id, e))
+						}
+					}
+				}
+				let (_, after) = self.api.retrieve_summary_info(false, 1)?;
+
+				println!(
+					"Cancelled {} of {} transactions, unlocking {} grin",
+					cancelled.to_string().bright_green(),
+					selected.len(),
+					amount_to_hr_string(
+						before.amount_locked.saturating_sub(after.amount_locked),
+						false
+					)
+					.bright_green()
+				);
+				if cancelled < selected.len() {
+					println!("Run `check` to repair the wallet if transactions remain stuck");
+				}
 			}
 			("contact", Some(m)) => match args::contact_command(m)? {
-				ContactArgs::Add(name, address) => {
-					self.api.add_contact(name, address)?;
+				ContactArgs::Add(name, address, group, notes, relay) => {
+					self.api.add_contact(
+						name,
+						address,
+						group.map(|g| g.to_owned()),
+						notes.map(|n| n.to_owned()),
+						relay.map(|r| r.to_owned()),
+					)?;
 					println!("Contact {} added", name.bright_green());
 				}
 				ContactArgs::Remove(name) => {
@@ -330,101 +900,320 @@ where
 					println!("Contact {} removed", name.bright_green());
 				}
 			},
-			("contacts", _) => {
-				display::contacts(self.api.contacts()?);
-			}
+			("contacts", Some(m)) => match args::contacts_command(m)? {
+				ContactsArgs::Display(group) => {
+					let contacts = self.api.contacts()?;
+					let contacts = match group {
+						Some(g) => contacts.into_iter().filter(|c| c.in_group(g)).collect(),
+						None => contacts,
+					};
+					display::contacts(contacts);
+				}
+				ContactsArgs::Export(file_name, format) => {
+					let count = self
+						.api
+						.export_contacts(&file_name.replace("~", &home_dir), format)?;
+					println!(
+						"{} contacts exported to {}",
+						count,
+						file_name.bright_green()
+					);
+				}
+				ContactsArgs::Import(file_name, format, strategy) => {
+					let summary = self.api.import_contacts(
+						&file_name.replace("~", &home_dir),
+						format,
+						strategy,
+					)?;
+					println!(
+						"Contacts imported: {} added, {} updated, {} unchanged, {} skipped, {} removed",
+						summary.added,
+						summary.updated,
+						summary.unchanged,
+						summary.skipped,
+						summary.removed
+					);
+				}
+			},
 			("exit", _) => {
 				let _ = self.api.stop_listeners();
 				return Ok(true);
 			}
+			("consolidate", Some(m)) => {
+				let (target_outputs, minimum_confirmations, fluff) = args::consolidate_command(m)?;
+				let slate = self
+					.api
+					.consolidate(target_outputs, minimum_confirmations, fluff)?;
+				println!(
+					"Consolidated {} outputs into {} with a fee of {} grin",
+					slate.tx.inputs().len(),
+					slate.tx.outputs().len(),
+					amount_to_hr_string(slate.fee, false).bright_green()
+				);
+			}
+			("dust", Some(m)) => {
+				let (threshold, minimum_confirmations, dust_args) = args::dust_command(m)?;
+				match dust_args {
+					DustArgs::Analyze => {
+						let analysis = self.api.analyze_dust(threshold, minimum_confirmations)?;
+						display::dust(&analysis);
+					}
+					DustArgs::Sweep(max_fee, fluff) => {
+						let slate = self.api.sweep_dust(
+							threshold,
+							minimum_confirmations,
+							max_fee,
+							fluff,
+						)?;
+						println!(
+							"Swept {} dust outputs into 1 with a fee of {} grin",
+							slate.tx.inputs().len(),
+							amount_to_hr_string(slate.fee, false).bright_green()
+						);
+					}
+				}
+			}
+			("features", _) => {
+				display::features(&self.api.features());
+			}
 			("finalize", Some(m)) => {
 				let (file_name, fluff) = args::finalize_command(m)?;
-				let mut file = File::open(file_name.replace("~", &home_dir))?;
-				let mut slate = String::new();
-				file.read_to_string(&mut slate)?;
-				let slate: VersionedSlate =
-					serde_json::from_str(&slate).map_err(|_| ErrorKind::ParseSlate)?;
+				let slate = read_slate(file_name)?;
 				let slate = self.api.finalize_tx(&slate.into(), None)?;
 				self.api.post_tx(&slate.tx, fluff)?;
 				println!("Transaction finalized and posted successfully");
 			}
+			("incoming", Some(m)) => match args::incoming_command(m)? {
+				IncomingArgs::List => display::incoming_slates(&self.api.incoming_slates()?),
+				IncomingArgs::Approve(id) => {
+					self.api.approve_incoming(&id)?;
+				}
+				IncomingArgs::Reject(id) => {
+					let incoming = self.api.reject_incoming(&id)?;
+					println!(
+						"Slate {} from {} rejected",
+						id.to_string().bright_green(),
+						incoming.address.bright_green()
+					);
+				}
+			},
 			("info", _) => {
 				let account = self.api.active_account()?;
 				let (validated, wallet_info) = self.api.retrieve_summary_info(true, 10)?;
 				display::info(&account, &wallet_info, validated, true);
 			}
 			("listen", Some(m)) => {
-				let interface = match args::listen_command(m)? {
-					("grinbox", _) | ("", _) => ListenerInterface::Grinbox,
-					("keybase", _) => ListenerInterface::Keybase,
-					("http", true) => ListenerInterface::OwnerHttp,
-					("http", false) => ListenerInterface::ForeignHttp,
-					_ => {
-						return Err(ErrorKind::IncorrectListenerInterface.into());
-					}
-				};
+				let interface = self.listener_interface(m)?;
 				self.api.start_listener(interface)?;
 			}
+			("listeners", _) => {
+				let statuses = self.api.listener_status()?;
+				display::listeners(&statuses);
+			}
+			("lock", _) => {
+				self.api.lock()?;
+				println!("Wallet locked, the password is asked for again on the next command");
+			}
+			("maintenance", Some(m)) => match args::maintenance_command(m)? {
+				MaintenanceArgs::FixDerivation => {
+					let fixes = self.api.fix_derivation()?;
+					display::derivation_fixes(&fixes);
+				}
+				MaintenanceArgs::WipeHistory(days) => {
+					match days.or(self.api.config().history_retention_days()) {
+						Some(days) => {
+							let question =
+								format!("Wipe the history of transactions over {} days old?", days);
+							if display::confirm(&question) {
+								let wipe = self.api.wipe_history(days)?;
+								display::history_wipe(&wipe);
+							}
+						}
+						None => println!("Pass --days or set history_retention_days in the config"),
+					}
+				}
+			},
+			("multisig", Some(m)) => match args::multisig_command(m)? {
+				MultisigArgs::List => {
+					display::multisig_outputs(&self.api.multisig_outputs()?);
+				}
+				MultisigArgs::Init(amount, file_name, confirmations) => {
+					let msg = self.api.multisig_init(
+						amount.nano(),
+						confirmations.unwrap_or(InitTxArgs::default().minimum_confirmations),
+						self.api.config().default_change_outputs() as usize,
+					)?;
+					let mut file = File::create(file_name.replace("~", &home_dir))?;
+					file.write_all(serde_json::to_string_pretty(&msg)?.as_bytes())?;
+					println!(
+						"Multisig output {} for {} grin started, round 1 saved to {}",
+						msg.id.to_string().bright_green(),
+						amount.to_hr_string(false).bright_green(),
+						file_name.bright_green()
+					);
+				}
+				MultisigArgs::Join(file_name) => {
+					let mut file = File::open(file_name.replace("~", &home_dir))?;
+					let mut msg = String::new();
+					file.read_to_string(&mut msg)?;
+					let msg: MultisigSlate =
+						serde_json::from_str(&msg).map_err(|_| ErrorKind::ParseMultisig)?;
+					match self.api.multisig_join(&msg)? {
+						Some(response) => {
+							let response_file = format!("{}.response", file_name);
+							let mut file = File::create(response_file.replace("~", &home_dir))?;
+							file.write_all(serde_json::to_string_pretty(&response)?.as_bytes())?;
+							if response.is_final() {
+								println!(
+									"Multisig transaction {} posted",
+									response.id.to_string().bright_green()
+								);
+							}
+							println!(
+								"Round {} saved to {}",
+								response.round,
+								response_file.bright_green()
+							);
+						}
+						None => println!(
+							"Multisig exchange {} complete",
+							msg.id.to_string().bright_green()
+						),
+					}
+				}
+				MultisigArgs::Spend(id, file_name) => {
+					let msg = self.api.multisig_spend(&id)?;
+					let mut file = File::create(file_name.replace("~", &home_dir))?;
+					file.write_all(serde_json::to_string_pretty(&msg)?.as_bytes())?;
+					println!(
+						"Spending multisig output {}, round 1 saved to {}",
+						id.to_string().bright_green(),
+						file_name.bright_green()
+					);
+				}
+			},
+			("node", Some(m)) => match args::node_command(m)? {
+				NodeArgs::Status => {
+					let status = self.api.node_status()?;
+					display::node_status(&self.api.config().grin_node_uri(), &status);
+				}
+			},
+			("outbox", Some(m)) => match args::outbox_command(m)? {
+				OutboxArgs::List => {
+					let account = self.api.active_account()?;
+					let (_, _, txs, _, _) = self
+						.api
+						.retrieve_txs(false, false, false, None, None, None, None)?;
+					let queued: Vec<_> = self
+						.api
+						.queued_slates()?
+						.into_iter()
+						.map(|q| {
+							let id = txs
+								.iter()
+								.find(|t| t.tx_slate_id == Some(q.slate_id()))
+								.map(|t| t.id);
+							(id, q)
+						})
+						.collect();
+					let max_attempts = self.api.config().slate_retry_max_attempts();
+					display::queued_slates(&account, &queued, max_attempts);
+				}
+				OutboxArgs::Cancel(index) => {
+					let queued = self.api.unqueue_slate(index)?;
+					println!(
+						"Slate {} removed from the outbox, use `cancel {}` to unlock its outputs",
+						queued.slate_id().to_string().bright_green(),
+						index
+					);
+				}
+			},
 			("outputs", Some(m)) => {
 				let account = self.api.active_account()?;
 				let (validated, height, outputs) =
 					self.api
-						.retrieve_outputs(m.is_present("spent"), true, None)?;
+						.retrieve_outputs(m.is_present("spent"), true, None, None)?;
 				let height = match height {
 					Some(h) => h,
 					None => self.api.node_height()?.height,
 				};
 				display::outputs(&account, height, validated, outputs, true);
 			}
-			("proof", Some(m)) => {
-				let (sender, receiver, amount, outputs, excess) = match args::proof_command(m)? {
-					ProofArgs::Export(index, file_name) => {
-						println!("A");
-						let tx_proof = self
-							.api
-							.get_stored_tx_proof(Some(index), None)?
-							.ok_or(ErrorKind::TransactionHasNoProof)?;
-						println!("B");
-						let verify = self.api.verify_tx_proof(&tx_proof)?;
-						println!("C");
-						let mut file = File::create(file_name.replace("~", &home_dir))?;
-						file.write_all(serde_json::to_string(&tx_proof)?.as_bytes())?;
-						println!("Proof exported to {}", file_name.bright_green());
-						verify
-					}
-					ProofArgs::Verify(file_name) => {
-						let mut file = File::open(file_name.replace("~", &home_dir))?;
-						let mut tx_proof = String::new();
-						file.read_to_string(&mut tx_proof)?;
-						let tx_proof: TxProof = serde_json::from_str(&tx_proof)?;
-						self.api.verify_tx_proof(&tx_proof)?
+			("pending", _) => {
+				let account = self.api.active_account()?;
+				let pending = self.api.pending_txs()?;
+				display::pending_txs(&account, &pending);
+			}
+			("passwd", _) => {
+				println!("Enter the current password");
+				let old_password = display::password_prompt()?;
+				println!("Choose a new password. Leave blank for no password.");
+				let new_password = display::new_password_prompt()?;
+				self.api.change_password(old_password, new_password)?;
+				println!("Password changed successfully");
+			}
+			("perf", Some(m)) => {
+				if m.is_present("reset") {
+					self.api.reset_timings();
+					println!("Timings cleared");
+				} else {
+					display::timings(self.api.timings());
+				}
+			}
+			("proof", Some(m)) => match args::proof_command(m)? {
+				ProofArgs::Export(index, file_name) => {
+					let mut file = File::create(file_name.replace("~", &home_dir))?;
+					match self.api.get_stored_tx_proof(Some(index), None)? {
+						Some(tx_proof) => {
+							let (sender, receiver, amount, outputs, excess) =
+								self.api.verify_tx_proof(&tx_proof)?;
+							file.write_all(serde_json::to_string(&tx_proof)?.as_bytes())?;
+							println!("Proof exported to {}", file_name.bright_green());
+							display::proof(sender, receiver, amount, outputs, excess);
+						}
+						None => {
+							let payment_proof = self
+								.api
+								.get_payment_proof(Some(index), None)?
+								.ok_or(ErrorKind::TransactionHasNoProof)?;
+							file.write_all(serde_json::to_string(&payment_proof)?.as_bytes())?;
+							println!("Proof exported to {}", file_name.bright_green());
+							display::payment_proof(&payment_proof);
+						}
 					}
-				};
-				display::proof(sender, receiver, amount, outputs, excess);
+				}
+				ProofArgs::Verify(file_name) => {
+					let mut file = File::open(file_name.replace("~", &home_dir))?;
+					let mut proof = String::new();
+					file.read_to_string(&mut proof)?;
+					match serde_json::from_str::<TxProof>(&proof) {
+						Ok(tx_proof) => {
+							let (sender, receiver, amount, outputs, excess) =
+								self.api.verify_tx_proof(&tx_proof)?;
+							display::proof(sender, receiver, amount, outputs, excess);
+						}
+						Err(_) => {
+							let payment_proof: PaymentProof = serde_json::from_str(&proof)?;
+							self.api.verify_payment_proof(&payment_proof)?;
+							display::payment_proof(&payment_proof);
+						}
+					}
+				}
+			},
+			("prove-ownership", Some(m)) => {
+				let (commit, file_name, message) = args::prove_ownership_command(m)?;
+				let commit = Commitment::from_hex(commit)?;
+				let proof = self
+					.api
+					.prove_ownership(&commit, message.unwrap_or("").to_owned())?;
+				let mut file = File::create(file_name.replace("~", &home_dir))?;
+				file.write_all(serde_json::to_string(&proof)?.as_bytes())?;
+				println!("Ownership proof exported to {}", file_name.bright_green());
 			}
 			("receive", Some(m)) => {
-				let (file_name, message) = args::receive_command(m)?;
-				let mut file = File::open(file_name.replace("~", &home_dir))?;
-				let mut slate = String::new();
-				file.read_to_string(&mut slate)?;
-				let slate: VersionedSlate =
-					serde_json::from_str(&slate).map_err(|_| ErrorKind::ParseSlate)?;
-				let version = slate.version().clone();
-				let slate = slate.into();
-				let slate = self.foreign.receive_tx(
-					&slate,
-					None,
-					Some("file".to_owned()),
-					message.map(|m| m.to_owned()),
-				)?;
-				let mut file_out =
-					File::create(&format!("{}.response", file_name.replace("~", &home_dir)))?;
-				let slate = VersionedSlate::into_version(slate, version);
-				file_out.write_all(serde_json::to_string(&slate)?.as_bytes())?;
-				cli_message!(
-					"Response slate file {} created successfully",
-					format!("{}.response", file_name.bright_green())
-				);
+				let (file_name, message, offline, height, allow_dust, payjoin) =
+					args::receive_command(m)?;
+				self.receive_file(file_name, message, offline, height, allow_dust, payjoin)?;
 			}
 			("repost", Some(m)) => {
 				let (index, fluff) = args::repost_command(m)?;
@@ -434,9 +1223,31 @@ where
 					slate_id.to_string().bright_green()
 				);
 			}
-			("restore", _) => {
+			("resend", Some(m)) => {
+				let index = args::resend_command(m)?;
+				let queued = self.api.resend_tx(index)?;
+				match queued.last_error {
+					Some(e) => println!(
+						"Unable to send slate {} to {}: {}",
+						queued.slate_id().to_string().bright_green(),
+						queued.dest.bright_green(),
+						e
+					),
+					None => println!(
+						"Slate {} sent again to {}",
+						queued.slate_id().to_string().bright_green(),
+						queued.dest.bright_green()
+					),
+				}
+			}
+			("restore", Some(m)) => {
+				let labeler: Option<&AccountLabeler> = match args::restore_command(m)? {
+					true => Some(&display::account_label_prompt),
+					false => None,
+				};
 				println!("Restoring wallet..");
-				self.api.restore()?;
+				let accounts = self.api.restore(labeler)?;
+				display::discovered_accounts(&accounts);
 				println!("Wallet restored successfully");
 			}
 			("seed", Some(m)) => {
@@ -449,69 +1260,335 @@ where
 						self.api.disconnect()?;
 						self.recover_wallet(true)?;
 					}
+					SeedArgs::Export(file_name) => {
+						println!("Choose a password for the backup file");
+						let password = display::new_password_prompt()?;
+						let backup = self.api.export_seed(&password)?;
+						let mut file = create_private_file(file_name.replace("~", &home_dir))?;
+						file.write_all(serde_json::to_string_pretty(&backup)?.as_bytes())?;
+						println!("Seed exported to {}", file_name.bright_green());
+					}
+					SeedArgs::Import(file_name) => {
+						let mut file = File::open(file_name.replace("~", &home_dir))?;
+						let mut backup = String::new();
+						file.read_to_string(&mut backup)?;
+						let backup: SeedBackup = serde_json::from_str(&backup)
+							.map_err(|_| ErrorKind::ParseSeedBackup)?;
+						let question = format!(
+							"Replace the seed of this wallet with the one in {}? Funds of the current seed can only be recovered with its mnemonic",
+							file_name
+						);
+						if self.api.has_seed()? && !display::confirm(&question) {
+							println!("Seed import cancelled");
+							return Ok(false);
+						}
+						println!("Enter the password of the backup file");
+						let backup_password = display::password_prompt()?;
+						println!(
+							"Set an optional password to secure your wallet with. Leave blank for no password."
+						);
+						let password = display::password_prompt()?;
+						self.api.stop_listeners()?;
+						self.api.import_seed(&backup, &backup_password, password)?;
+						self.api.disconnect()?;
+						self.restore_recovered()?;
+					}
 				};
 			}
+			("sign", Some(m)) => {
+				let (file_name, message) = args::sign_command(m)?;
+				self.receive_file(file_name, message, true, None, false, false)?;
+			}
 			("send", Some(m)) => {
-				let (cmd_type, args) = args::send_command(m)?;
+				let (cmd_type, mut args) =
+					args::send_command(m, self.api.config().default_change_outputs())?;
+				let confirmed = m.is_present("yes");
 
 				match cmd_type {
 					SendCommandType::Address => {
-						self.api.init_send_tx(args)?;
+						if !confirmed && !self.confirm_send(&args)? {
+							return Ok(false);
+						}
+						if self.confirm_policy(self.api.init_send_tx(args))?.is_none() {
+							return Ok(false);
+						}
+					}
+					SendCommandType::Pick => {
+						let contacts = self.api.contacts()?;
+						let (_, _, txs, _, _) = self
+							.api
+							.retrieve_txs(false, false, false, None, None, None, None)?;
+						let destinations = picker::destinations(&contacts, &txs);
+						let destination = match picker::pick(destinations)? {
+							Some(d) => d,
+							None => return Ok(false),
+						};
+						args.send_args = Some(InitTxSendArgs {
+							method: Some(destination.method),
+							dest: destination.dest,
+							finalize: true,
+							post_tx: true,
+							fluff: m.is_present("fluff"),
+						});
+						if !confirmed && !self.confirm_send(&args)? {
+							return Ok(false);
+						}
+						if self.confirm_policy(self.api.init_send_tx(args))?.is_none() {
+							return Ok(false);
+						}
 					}
 					SendCommandType::File(file_name) => {
-						let slate = self.api.init_send_tx(args)?;
-						let mut file = File::create(file_name.replace("~", &home_dir))?;
-						file.write_all(serde_json::to_string_pretty(&slate)?.as_bytes())?;
+						if !confirmed && !self.confirm_send(&args)? {
+							return Ok(false);
+						}
+						let slate = match self.confirm_policy(self.api.init_send_tx(args))? {
+							Some(slate) => slate,
+							None => return Ok(false),
+						};
+						write_slate(file_name, &serde_json::to_string_pretty(&slate)?)?;
 						self.api
 							.tx_lock_outputs(&slate, 0, Some("file".to_owned()))?;
 
-						println!(
+						let destination = match file_name {
+							STDIO_FILE => "stdout",
+							_ => file_name,
+						};
+						cli_message!(
+							self.runtime,
 							"Slate {} for {} grin saved to {}",
 							slate.id.to_string().bright_green(),
 							amount_to_hr_string(slate.amount, false).bright_green(),
-							file_name.bright_green()
+							destination.bright_green()
 						);
 					}
+					SendCommandType::Qr => {
+						if !confirmed && !self.confirm_send(&args)? {
+							return Ok(false);
+						}
+						let slate = match self.confirm_policy(self.api.init_send_tx(args))? {
+							Some(slate) => slate,
+							None => return Ok(false),
+						};
+						let versioned =
+							VersionedSlate::into_version(slate.clone(), SlateVersion::default());
+						let frames = compact::frames(&compact::encode(&versioned)?, QR_FRAME_LEN);
+						self.api.tx_lock_outputs(&slate, 0, Some("qr".to_owned()))?;
+
+						cli_message!(
+							self.runtime,
+							"Slate {} for {} grin, scan the {} frames with the receiving wallet",
+							slate.id.to_string().bright_green(),
+							amount_to_hr_string(slate.amount, false).bright_green(),
+							frames.len()
+						);
+						display::qr_frames(&frames)?;
+					}
 					SendCommandType::Estimate => {
-						let strategies = vec!["smallest", "all"]
-							.into_iter()
-							.map(|strategy| {
-								let mut init_args = args.clone();
-								init_args.selection_strategy_is_use_all = strategy == "all";
-								let slate = self.api.init_send_tx(init_args).unwrap();
-								(strategy, slate.amount, slate.fee)
-							})
-							.collect();
-						display::estimate(args.amount, strategies, true);
+						let estimates = self.api.estimate_fee(EstimateFeeArgs {
+							src_acct_name: args.src_acct_name.clone(),
+							amount: args.amount,
+							minimum_confirmations: args.minimum_confirmations,
+							max_outputs: args.max_outputs,
+							num_change_outputs: args.num_change_outputs,
+							..EstimateFeeArgs::default()
+						})?;
+						display::estimate(args.amount, estimates, true);
 					}
 				}
 			}
 			("stop", Some(m)) => {
-				let interface = match args::listen_command(m)? {
-					("grinbox", _) | ("", _) => ListenerInterface::Grinbox,
-					("keybase", _) => ListenerInterface::Keybase,
-					("http", true) => ListenerInterface::OwnerHttp,
-					("http", false) => ListenerInterface::ForeignHttp,
-					_ => {
-						return Err(ErrorKind::IncorrectListenerInterface.into());
-					}
-				};
+				let interface = self.listener_interface(m)?;
 				self.api.stop_listener(interface)?;
 			}
-			("txs", _) => {
-				let account = self.api.active_account()?;
-				let (validated, height, txs, contacts, proofs) =
-					self.api.retrieve_txs(true, true, true, None, None)?;
-				let height = match height {
-					Some(h) => h,
-					None => self.api.node_height()?.height,
+			("sweep", Some(m)) => {
+				let (dest, args) = args::sweep_command(m)?;
+				let estimate = args.estimate_only == Some(true);
+				let slate = match self.confirm_policy(self.api.sweep(dest, args))? {
+					Some(slate) => slate,
+					None => return Ok(false),
 				};
-				display::txs(
-					&account, height, validated, &txs, proofs, contacts, true, true,
-				);
+				if estimate {
+					println!(
+						"Sweeping {} grin would send {} grin with a fee of {} grin",
+						amount_to_hr_string(slate.amount, false).bright_green(),
+						amount_to_hr_string(slate.amount - slate.fee, false).bright_green(),
+						amount_to_hr_string(slate.fee, false).bright_green()
+					);
+				}
+			}
+			("tx", Some(m)) => match args::tx_command(m)? {
+				TxArgs::Label(index, label) => {
+					let cleared = label.is_none();
+					self.api.set_tx_label(index, label)?;
+					match cleared {
+						true => println!("Label of transaction {} cleared", index),
+						false => println!("Label of transaction {} set", index),
+					}
+				}
+				TxArgs::Note(index, notes) => {
+					let cleared = notes.is_none();
+					self.api.set_tx_notes(index, notes)?;
+					match cleared {
+						true => println!("Notes of transaction {} cleared", index),
+						false => println!("Notes of transaction {} updated", index),
+					}
+				}
+				TxArgs::ExportContext(index, file_name) => {
+					let password = display::password_prompt()?;
+					let export = self.api.export_private_context(index, &password)?;
+					let mut file = File::create(file_name.replace("~", &home_dir))?;
+					file.write_all(serde_json::to_string_pretty(&export)?.as_bytes())?;
+					println!(
+						"Private context of transaction {} exported to {}",
+						index,
+						file_name.bright_green()
+					);
+				}
+				TxArgs::ImportContext(file_name, slate_file_name) => {
+					let mut file = File::open(file_name.replace("~", &home_dir))?;
+					let mut export = String::new();
+					file.read_to_string(&mut export)?;
+					let export: EncryptedContext =
+						serde_json::from_str(&export).map_err(|_| ErrorKind::ParseContext)?;
+					let mut file = File::open(slate_file_name.replace("~", &home_dir))?;
+					let mut slate = String::new();
+					file.read_to_string(&mut slate)?;
+					let slate: VersionedSlate =
+						serde_json::from_str(&slate).map_err(|_| ErrorKind::ParseSlate)?;
+					let password = display::password_prompt()?;
+					self.api
+						.import_private_context(&export, &slate.into(), &password)?;
+					println!(
+						"Context of slate {} imported, the response can now be finalized",
+						export.slate_id.to_string().bright_green()
+					);
+				}
+				TxArgs::Show(index) => {
+					let detail = self.api.tx_detail(index)?;
+					display::tx_detail(&detail);
+				}
+				TxArgs::Bump(index, fee_factor) => {
+					let slate = self.api.bump_tx(index, fee_factor)?;
+					println!(
+						"Transaction {} re-issued with a fee of {} grin",
+						index,
+						amount_to_hr_string(slate.fee, false).bright_green()
+					);
+				}
+			},
+			("txs", Some(m)) => match args::txs_command(m)? {
+				(Some((file_name, format)), filter) => {
+					let entries = self.api.export_txs(Some(filter))?;
+					let mut file = File::create(file_name.replace("~", &home_dir))?;
+					file.write_all(format.serialize(&entries)?.as_bytes())?;
+					println!(
+						"{} transactions exported to {}",
+						entries.len(),
+						file_name.bright_green()
+					);
+				}
+				(None, filter) => {
+					let account = self.api.active_account()?;
+					let (validated, height, txs, contacts, proofs) =
+						self.api
+							.retrieve_txs(true, true, true, None, None, Some(filter), None)?;
+					let height = match height {
+						Some(h) => h,
+						None => self.api.node_height()?.height,
+					};
+					display::txs(
+						&account, height, validated, &txs, proofs, contacts, true, true,
+					);
+				}
+			},
+			("verify-attestation", Some(m)) => {
+				let file_name = args::verify_attestation_command(m)?;
+				let mut file = File::open(file_name.replace("~", &home_dir))?;
+				let mut attestation = String::new();
+				file.read_to_string(&mut attestation)?;
+				let attestation: BalanceAttestation = serde_json::from_str(&attestation)?;
+				let status = self.api.verify_balance_attestation(&attestation)?;
+				display::balance_attestation(&attestation, &status);
+			}
+			("wallet", Some(m)) => match args::wallet_command(m)? {
+				WalletArgs::ExportData(dir) => {
+					let summary = self.api.export_data(&dir.replace("~", &home_dir))?;
+					display::wallet_data(&summary);
+					println!("Wallet data exported to {}", dir.bright_green());
+					println!(
+						"{}: the directory holds the keys of unfinished transactions in the clear",
+						"WARNING".bright_yellow()
+					);
+				}
+				WalletArgs::ImportData(dir) => {
+					let summary = self.api.import_data(&dir.replace("~", &home_dir))?;
+					display::wallet_data(&summary);
+					println!(
+						"Wallet data imported, {} records the wallet already had were skipped",
+						summary.skipped
+					);
+				}
+			},
+			("watch", Some(m)) => match args::watch_command(m)? {
+				WatchArgs::Export(file_name) => {
+					let watch_key = self.api.export_watch_key()?;
+					let mut file = File::create(file_name.replace("~", &home_dir))?;
+					file.write_all(serde_json::to_string(&watch_key)?.as_bytes())?;
+					println!("Watch key exported to {}", file_name.bright_green());
+				}
+				WatchArgs::Import(file_name) => {
+					let mut file = File::open(file_name.replace("~", &home_dir))?;
+					let mut watch_key = String::new();
+					file.read_to_string(&mut watch_key)?;
+					let watch_key: WatchKey = serde_json::from_str(&watch_key)?;
+					self.api.import_watch_key(&watch_key)?;
+					println!("Watch key imported, use `watch scan` to find its outputs");
+				}
+				WatchArgs::Scan => {
+					println!("Scanning the UTXO set, this can take a while..");
+					let summary = self.api.watch_scan()?;
+					println!(
+						"Scanned up to height {}: {} outputs received, {} spent",
+						summary.height, summary.received, summary.spent
+					);
+				}
+				WatchArgs::Outputs(include_spent) => {
+					let (height, outputs) = self.api.watch_outputs(include_spent)?;
+					let mut mappings = Vec::with_capacity(outputs.len());
+					for output in outputs {
+						let commit = output.commit.clone().unwrap_or_default();
+						mappings.push(OutputCommitMapping {
+							commit: Commitment::from_hex(&commit)?,
+							output,
+						});
+					}
+					display::outputs("watch-only", height, false, mappings, true);
+				}
+				WatchArgs::Txs => {
+					let (height, txs) = self.api.watch_txs()?;
+					display::txs(
+						"watch-only",
+						height,
+						false,
+						&txs,
+						HashMap::new(),
+						HashMap::new(),
+						true,
+						true,
+					);
+				}
+			},
+			("verify-ownership", Some(m)) => {
+				let file_name = args::verify_ownership_command(m)?;
+				let mut file = File::open(file_name.replace("~", &home_dir))?;
+				let mut proof = String::new();
+				file.read_to_string(&mut proof)?;
+				let proof: OwnershipProof = serde_json::from_str(&proof)?;
+				let on_chain = self.api.verify_ownership(&proof)?;
+				display::ownership_proof(&proof, on_chain);
 			}
 			_ => {
-				cli_message!("Unknown command");
+				cli_message!(self.runtime, "Unknown command");
 			}
 		}
 
@@ -519,6 +1596,41 @@ where
 	}
 }
 
+/// Case insensitive search in a transaction label
+/// Read a slate from a file, or from stdin for `-`
+fn read_slate(file_name: &str) -> Result<VersionedSlate, Error> {
+	let mut slate = String::new();
+	if file_name == STDIO_FILE {
+		io::stdin().read_to_string(&mut slate)?;
+	} else {
+		File::open(expand_home(file_name))?.read_to_string(&mut slate)?;
+	}
+	let slate = match compact::is_compact(&slate) {
+		true => compact::decode(&slate).map_err(|_| ErrorKind::ParseSlate)?,
+		false => serde_json::from_str(&slate).map_err(|_| ErrorKind::ParseSlate)?,
+	};
+	Ok(slate)
+}
+
+/// Write a serialized slate to a file, or to stdout for `-`
+fn write_slate(file_name: &str, slate: &str) -> Result<(), Error> {
+	if file_name == STDIO_FILE {
+		let mut stdout = io::stdout();
+		writeln!(stdout, "{}", slate)?;
+		stdout.flush()?;
+	} else {
+		File::create(expand_home(file_name))?.write_all(slate.as_bytes())?;
+	}
+	Ok(())
+}
+
+fn expand_home(file_name: &str) -> String {
+	let home_dir = dirs::home_dir()
+		.map(|p| p.to_str().unwrap().to_string())
+		.unwrap_or("~".to_string());
+	file_name.replace("~", &home_dir)
+}
+
 struct EditorHelper(FilenameCompleter, MatchingBracketHighlighter);
 
 impl Completer for EditorHelper {
@@ -0,0 +1,282 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! `config init` and `config check`: write a config by answering a few
+//! questions, and check one against the machine and network it runs on,
+//! instead of finding its mistakes one failed start at a time
+
+use super::display;
+use crate::common::config::Wallet713Config;
+use crate::common::ErrorKind;
+use crate::contacts::DEFAULT_GRINBOX_PORT;
+use crate::wallet::types::{HTTPNodeClient, NodeClient};
+use failure::Error;
+use grin_core::global::ChainTypes;
+use std::fs;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+use url::Url;
+
+/// How long the grinbox relay gets to accept a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CheckStatus {
+	Ok,
+	/// Works, but not everything the config asks for will
+	Warning,
+	/// The wallet won't work as configured
+	Error,
+}
+
+/// Outcome of checking one part of the config
+pub struct ConfigCheck {
+	pub name: &'static str,
+	pub status: CheckStatus,
+	pub message: String,
+}
+
+impl ConfigCheck {
+	fn new(name: &'static str, status: CheckStatus, message: String) -> Self {
+		Self {
+			name,
+			status,
+			message,
+		}
+	}
+}
+
+/// Check the config: the data directory, node, grinbox relay, keybase
+/// client and files it refers to
+pub fn check(config: &Wallet713Config) -> Vec<ConfigCheck> {
+	let mut checks = vec![
+		check_data_path(config),
+		check_node(config),
+		check_grinbox(config),
+		check_keybase(config),
+	];
+	checks.extend(check_files(config));
+	checks
+}
+
+/// Ask for the settings that differ between setups and write the config to
+/// `config_path`, or the default path of `chain`. An existing config is only
+/// replaced when confirmed. Returns whether a config was written
+pub fn init(config_path: Option<&str>, chain: &Option<ChainTypes>) -> Result<bool, Error> {
+	if Wallet713Config::exists(config_path, chain)? {
+		let question = "A config already exists, replace it?";
+		if !display::confirm(question) {
+			return Ok(false);
+		}
+	}
+	let mut config = Wallet713Config::default(chain)?;
+
+	let node_uri = display::ask("Grin node URI", &config.grin_node_uri());
+	if node_uri != config.grin_node_uri() {
+		config.grin_node_uri = Some(node_uri);
+		let secret = display::ask("Node API secret, empty for none", "");
+		config.grin_node_secret = Some(secret).filter(|s| !s.is_empty());
+	}
+	config.grinbox_domain = display::ask("Grinbox domain", &config.grinbox_domain);
+	let default_port = DEFAULT_GRINBOX_PORT.to_string();
+	let port = display::ask("Grinbox port", &default_port);
+	if port != default_port {
+		let port = port.parse().map_err(|_| ErrorKind::ParseNumber(port))?;
+		config.grinbox_port = Some(port);
+	}
+
+	config.grinbox_listener_auto_start = Some(display::ask_yes_no(
+		"Listen for slates over grinbox on startup?",
+		true,
+	));
+	config.keybase_listener_auto_start = Some(display::ask_yes_no(
+		"Listen for slates over keybase on startup?",
+		false,
+	));
+	if display::ask_yes_no("Receive slates over http with the foreign API?", false) {
+		config.foreign_api = Some(true);
+		let address = display::ask("Foreign API address", &config.foreign_api_address());
+		config.foreign_api_address = Some(address);
+	}
+	if display::ask_yes_no("Run the owner API for other applications?", false) {
+		config.owner_api = Some(true);
+		let address = display::ask("Owner API address", &config.owner_api_address());
+		config.owner_api_address = Some(address);
+	}
+
+	println!();
+	let checks = check(&config);
+	display::config_checks(&checks);
+	if checks.iter().any(|c| c.status == CheckStatus::Error)
+		&& !display::confirm("Save the config anyway?")
+	{
+		return Ok(false);
+	}
+	config.to_file(config_path.map(|p| p.to_owned()))?;
+	Ok(true)
+}
+
+fn check_data_path(config: &Wallet713Config) -> ConfigCheck {
+	let name = "data directory";
+	let res = config.get_data_path().and_then(|path| {
+		fs::create_dir_all(&path)?;
+		// Creating the directory succeeds when it exists read-only
+		let probe = path.join(".config_check");
+		fs::write(&probe, b"")?;
+		fs::remove_file(&probe)?;
+		Ok(path)
+	});
+	match res {
+		Ok(path) => ConfigCheck::new(name, CheckStatus::Ok, path.display().to_string()),
+		Err(e) => ConfigCheck::new(
+			name,
+			CheckStatus::Error,
+			format!(
+				"{} is not writable: {}. Set wallet713_data_path to a directory you can write to",
+				config.wallet713_data_path, e
+			),
+		),
+	}
+}
+
+fn check_node(config: &Wallet713Config) -> ConfigCheck {
+	let name = "node";
+	let uri = config.grin_node_uri();
+	if let Err(e) = Url::parse(&uri) {
+		let msg = format!("grin_node_uri '{}' is not a URL: {}", uri, e);
+		return ConfigCheck::new(name, CheckStatus::Error, msg);
+	}
+	let secret = match config.grin_node_secret() {
+		Ok(secret) => secret,
+		Err(e) => return ConfigCheck::new(name, CheckStatus::Error, e.to_string()),
+	};
+	let client = HTTPNodeClient::new(&uri, secret).with_options(config.grin_node_client_options());
+	match client.get_status() {
+		Ok(status) if status.is_synced() => {
+			let msg = format!("{} at height {}, {}", uri, status.height, status.user_agent);
+			ConfigCheck::new(name, CheckStatus::Ok, msg)
+		}
+		Ok(status) => {
+			let msg = format!(
+				"{} is {}, balances are behind until it has synced",
+				uri, status.sync_status
+			);
+			ConfigCheck::new(name, CheckStatus::Warning, msg)
+		}
+		Err(e) => {
+			let msg = format!(
+				"unable to reach {}: {}. Check grin_node_uri, grin_node_secret if the node \
+				 asks for one and grin_node_ca_file if it has its own certificate",
+				uri, e
+			);
+			ConfigCheck::new(name, CheckStatus::Error, msg)
+		}
+	}
+}
+
+fn check_grinbox(config: &Wallet713Config) -> ConfigCheck {
+	let name = "grinbox relay";
+	let port = config.grinbox_port.unwrap_or(DEFAULT_GRINBOX_PORT);
+	let relay = format!("{}:{}", config.grinbox_domain, port);
+	let connected = (config.grinbox_domain.as_str(), port)
+		.to_socket_addrs()
+		.map_err(|e| e.to_string())
+		.and_then(|mut addrs| addrs.next().ok_or_else(|| "no address found".to_owned()))
+		.and_then(|addr| {
+			TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).map_err(|e| e.to_string())
+		});
+	match connected {
+		Ok(_) => ConfigCheck::new(name, CheckStatus::Ok, relay),
+		Err(e) => {
+			// Without the relay nothing can be sent or received over grinbox,
+			// the wallet itself still works
+			let msg = format!(
+				"unable to connect to {}: {}. Check grinbox_domain and grinbox_port, and that \
+				 outgoing connections to port {} are allowed",
+				relay, e, port
+			);
+			ConfigCheck::new(name, CheckStatus::Warning, msg)
+		}
+	}
+}
+
+fn check_keybase(config: &Wallet713Config) -> ConfigCheck {
+	let name = "keybase";
+	let finder = match cfg!(target_os = "windows") {
+		true => "where",
+		false => "which",
+	};
+	let found = Command::new(finder)
+		.arg(config.keybase_binary())
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.status()
+		.map(|s| s.success())
+		.unwrap_or(false);
+	match (found, config.keybase_listener_auto_start()) {
+		(true, _) => ConfigCheck::new(name, CheckStatus::Ok, "keybase client found".to_owned()),
+		(false, true) => ConfigCheck::new(
+			name,
+			CheckStatus::Error,
+			"keybase_listener_auto_start is set, but keybase is not on the PATH. Install the \
+			 keybase client or turn the listener off"
+				.to_owned(),
+		),
+		(false, false) => ConfigCheck::new(
+			name,
+			CheckStatus::Warning,
+			"keybase is not on the PATH, sending to keybase users won't work".to_owned(),
+		),
+	}
+}
+
+/// Files the config refers to, and the logging settings
+fn check_files(config: &Wallet713Config) -> Vec<ConfigCheck> {
+	let mut checks = vec![];
+	let files = [
+		("tls_cert_file", &config.tls_cert_file),
+		("tls_key_file", &config.tls_key_file),
+		("owner_api_tls_cert_file", &config.owner_api_tls_cert_file),
+		("owner_api_tls_key_file", &config.owner_api_tls_key_file),
+		(
+			"foreign_api_tls_cert_file",
+			&config.foreign_api_tls_cert_file,
+		),
+		("foreign_api_tls_key_file", &config.foreign_api_tls_key_file),
+		("notify_script", &config.notify_script),
+		("grin_node_secret_file", &config.grin_node_secret_file),
+		("grin_node_ca_file", &config.grin_node_ca_file),
+	];
+	for (setting, file) in files.iter() {
+		if let Some(file) = file {
+			if !Path::new(file).is_file() {
+				let msg = format!("{} '{}' does not exist", setting, file);
+				checks.push(ConfigCheck::new("files", CheckStatus::Error, msg));
+			}
+		}
+	}
+	let tls = config
+		.owner_api_tls_files()
+		.and(config.foreign_api_tls_files());
+	if let Err(e) = tls {
+		checks.push(ConfigCheck::new("files", CheckStatus::Error, e.to_string()));
+	}
+	if let Err(e) = config.log_config() {
+		let msg = format!("{}. Check the log_* settings", e);
+		checks.push(ConfigCheck::new("logging", CheckStatus::Error, msg));
+	}
+	checks
+}
@@ -12,11 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::config_wizard::{CheckStatus, ConfigCheck};
+use crate::api::listener::{ListenerStatus, ReachableAddress};
+use crate::common::features::Feature;
+use crate::common::qr::{EcLevel, QrCode};
+use crate::common::timing::StageTiming;
 use crate::common::ErrorKind;
 use crate::contacts::{Contact, GrinboxAddress};
 use crate::wallet::types::{
-	AcctPathMapping, OutputCommitMapping, OutputStatus, TxLogEntry, WalletInfo,
+	AcctPathMapping, Amount, AttestationStatus, BackupInfo, BalanceAttestation, DerivationFix,
+	DiscoveredAccount, DustAdvice, DustAnalysis, FeeEstimate, HistoryWipe, IncomingSlate,
+	KernelReconciliation, MultisigOutput, MultisigStatus, NodeStatus, OutputCommitMapping,
+	OutputStatus, OwnershipProof, PaymentProof, QueuedSlate, SendPreview, TxDetail, TxLogEntry,
+	TxLogEntryType, WalletDataSummary, WalletInfo,
 };
+use chrono::Utc;
 use clap::crate_version;
 use colored::Colorize;
 use failure::Error;
@@ -36,22 +46,28 @@ use uuid::Uuid;
 pub enum InitialPromptOption {
 	Init,
 	Recover,
+	WatchOnly,
 	Exit,
 }
 
 pub fn password_prompt() -> Result<ZeroingString, Error> {
-	let password = match prompt_password_stdout("Password: ") {
-		Ok(p) => p,
-		Err(_) => {
-			return Err(
-				ErrorKind::GenericError("Unable to read password prompt".to_owned()).into(),
-			);
-		}
-	};
+	Ok(password_prompt_with("Password: ")?.into())
+}
 
+pub fn new_password_prompt() -> Result<ZeroingString, Error> {
+	let password = password_prompt_with("New password: ")?;
+	let confirmation = password_prompt_with("Confirm new password: ")?;
+	if password != confirmation {
+		return Err(ErrorKind::PasswordMismatch.into());
+	}
 	Ok(password.into())
 }
 
+fn password_prompt_with(prompt: &str) -> Result<String, Error> {
+	prompt_password_stdout(prompt)
+		.map_err(|_| ErrorKind::GenericError("Unable to read password prompt".to_owned()).into())
+}
+
 pub fn error<D>(msg: D)
 where
 	D: Display,
@@ -71,7 +87,8 @@ pub fn initial_prompt() -> Result<InitialPromptOption, Error> {
 	println!("{}", "Please choose an option".bright_green().bold());
 	println!(" 1) {} a new wallet", "init".bold());
 	println!(" 2) {} from mnemonic", "recover".bold());
-	println!(" 3) {}", "exit".bold());
+	println!(" 3) {} using a watch key", "watch-only".bold());
+	println!(" 4) {}", "exit".bold());
 	println!();
 	print!("{}", "> ".cyan());
 	io::stdout().flush().unwrap();
@@ -85,7 +102,8 @@ pub fn initial_prompt() -> Result<InitialPromptOption, Error> {
 	Ok(match line {
 		"1" | "init" | "" => InitialPromptOption::Init,
 		"2" | "recover" | "restore" => InitialPromptOption::Recover,
-		"3" | "exit" => InitialPromptOption::Exit,
+		"3" | "watch-only" | "watch" => InitialPromptOption::WatchOnly,
+		"4" | "exit" => InitialPromptOption::Exit,
 		_ => {
 			return Err(ErrorKind::GenericError("Invalid option".to_owned()).into());
 		}
@@ -122,19 +140,11 @@ pub fn mnemonic(mnemonic: ZeroingString, confirm: bool) {
 	}
 }
 
-/// Display summary info in a pretty way
-pub fn estimate(
-	amount: u64,
-	strategies: Vec<(
-		&str, // strategy
-		u64,  // total amount to be locked
-		u64,  // fee
-	)>,
-	dark_background_color_scheme: bool,
-) {
+/// Display fee estimates in a pretty way
+pub fn estimate(amount: Amount, estimates: Vec<FeeEstimate>, dark_background_color_scheme: bool) {
 	println!(
 		"\n____ Estimation for sending {} ____\n",
-		amount_to_hr_string(amount, true)
+		amount.to_hr_string(true)
 	);
 
 	let mut table = table!();
@@ -143,20 +153,23 @@ pub fn estimate(
 		bMG->"Selection strategy",
 		bMG->"Fee",
 		bMG->"Amount locked",
+		bMG->"Change outputs",
 	]);
 
-	for (strategy, total, fee) in strategies {
+	for e in estimates {
 		if dark_background_color_scheme {
 			table.add_row(row![
-				bFC->strategy,
-				FR->amount_to_hr_string(fee, true),
-				FY->amount_to_hr_string(total, false),
+				bFC->e.strategy,
+				FR->e.fee.to_hr_string(true),
+				FY->e.total.to_hr_string(false),
+				e.change_outputs,
 			]);
 		} else {
 			table.add_row(row![
-				bFD->strategy,
-				FR->amount_to_hr_string(fee, true),
-				FY->amount_to_hr_string(total, false),
+				bFD->e.strategy,
+				FR->e.fee.to_hr_string(true),
+				FY->e.total.to_hr_string(false),
+				e.change_outputs,
 			]);
 		}
 	}
@@ -165,6 +178,242 @@ pub fn estimate(
 	println!();
 }
 
+/// Display what a send would do before it is signed
+pub fn send_preview(preview: &SendPreview) {
+	println!(
+		"\n____ Sending {} with a fee of {} ____\n",
+		preview.amount.to_hr_string(true),
+		preview.fee.to_hr_string(true)
+	);
+	let mut table = table!();
+
+	table.set_titles(row![
+		bMG->"Locked input",
+		bMG->"Block Height",
+		bMG->"Value",
+	]);
+	for i in &preview.inputs {
+		let value = match (i.pays_fee, &preview.fee_account) {
+			(true, Some(account)) => format!("{} (fee, from '{}')", i.value, account),
+			_ => i.value.to_hr_string(false),
+		};
+		table.add_row(row![
+			bFC->i.commit.clone().unwrap_or("-".to_owned()),
+			bFB->i.height,
+			FY->value,
+		]);
+	}
+	for c in &preview.change_outputs {
+		table.add_row(row![
+			bFG->"change output",
+			"",
+			FG->c.to_hr_string(false),
+		]);
+	}
+	table.set_format(*FORMAT_NO_COLSEP);
+	table.printstd();
+	println!();
+	println!(
+		"Balance: {} -> {}",
+		preview.balance_before.to_hr_string(false),
+		preview.balance_after.to_hr_string(false).bright_green()
+	);
+	println!();
+}
+
+pub fn dust(analysis: &DustAnalysis) {
+	if analysis.outputs.is_empty() {
+		println!(
+			"No outputs worth {} grin or less",
+			analysis.threshold.to_hr_string(false)
+		);
+		return;
+	}
+
+	println!(
+		"\n____ Outputs worth {} grin or less ____\n",
+		analysis.threshold.to_hr_string(false)
+	);
+	let mut table = table!();
+
+	table.set_titles(row![
+		bMG->"Output Commitment",
+		bMG->"Block Height",
+		bMG->"Value",
+	]);
+	for o in &analysis.outputs {
+		table.add_row(row![
+			bFC->o.commit.clone().unwrap_or("-".to_owned()),
+			bFB->o.height,
+			FY->o.value.to_hr_string(false),
+		]);
+	}
+	table.set_format(*FORMAT_NO_COLSEP);
+	table.printstd();
+	println!();
+
+	let piggyback = match analysis.piggyback_fee {
+		f if f < 0 => format!(
+			"lowers its fee by {}",
+			amount_to_hr_string(-f as u64, false)
+		),
+		f => format!("raises its fee by {}", amount_to_hr_string(f as u64, false)),
+	};
+	println!(
+		"{} outputs worth {} grin in total",
+		analysis.outputs.len(),
+		analysis.total.to_hr_string(false)
+	);
+	println!(
+		"Sweeping them now costs {} grin, the fee is at its minimum of {} grin from {} inputs",
+		analysis.sweep_fee.to_hr_string(false),
+		analysis.min_fee.to_hr_string(false),
+		analysis.min_fee_inputs
+	);
+	println!("Spending them along with your next send {} grin", piggyback);
+	println!();
+
+	let advice = match analysis.advice {
+		DustAdvice::Clean => return,
+		DustAdvice::SweepNow => {
+			"Sweeping now recovers value at the lowest possible fee".bright_green()
+		}
+		DustAdvice::Wait => {
+			"Sweeping recovers value, but the fee drops if you wait for more dust".bright_yellow()
+		}
+		DustAdvice::Uneconomic => "The dust is worth less than the fee to sweep it".bright_red(),
+	};
+	println!("{}", advice);
+}
+
+/// Ask a yes/no question, defaulting to no
+pub fn confirm(question: &str) -> bool {
+	print!("{} [y/N] ", question.bright_green().bold());
+	io::stdout().flush().unwrap();
+	let mut line = String::new();
+	if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+		return false;
+	}
+	match line.trim().to_lowercase().as_str() {
+		"y" | "yes" => true,
+		_ => false,
+	}
+}
+
+/// Ask for a value, an empty answer keeps `default`
+pub fn ask(question: &str, default: &str) -> String {
+	print!("{} [{}]: ", question.bright_green().bold(), default);
+	io::stdout().flush().unwrap();
+	let mut line = String::new();
+	let _ = io::stdin().read_line(&mut line);
+	match line.trim() {
+		"" => default.to_owned(),
+		answer => answer.to_owned(),
+	}
+}
+
+/// Ask a yes/no question, an empty answer takes `default`
+pub fn ask_yes_no(question: &str, default: bool) -> bool {
+	let options = match default {
+		true => "Y/n",
+		false => "y/N",
+	};
+	print!("{} [{}] ", question.bright_green().bold(), options);
+	io::stdout().flush().unwrap();
+	let mut line = String::new();
+	let _ = io::stdin().read_line(&mut line);
+	match line.trim().to_lowercase().as_str() {
+		"y" | "yes" => true,
+		"n" | "no" => false,
+		_ => default,
+	}
+}
+
+/// Results of `config check`
+pub fn config_checks(checks: &[ConfigCheck]) {
+	let mut table = table!();
+	for check in checks {
+		let status = match check.status {
+			CheckStatus::Ok => "ok".bright_green(),
+			CheckStatus::Warning => "warning".bright_yellow(),
+			CheckStatus::Error => "error".bright_red(),
+		};
+		table.add_row(row![bFC->check.name, status, check.message]);
+	}
+	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+	table.printstd();
+	println!();
+}
+
+/// Show text as a QR code
+pub fn qr_code(text: &str) -> Result<(), Error> {
+	let qr = QrCode::encode(text.as_bytes(), EcLevel::Medium)?;
+	print!("{}", qr.render_terminal());
+	Ok(())
+}
+
+/// Show frames as QR codes one at a time, moving to the next one on Enter
+pub fn qr_frames(frames: &[String]) -> Result<(), Error> {
+	for (i, frame) in frames.iter().enumerate() {
+		let qr = QrCode::encode(frame.as_bytes(), EcLevel::Low)?;
+		print!("{}", qr.render_terminal());
+		let position = format!("{}/{}", i + 1, frames.len());
+		if i + 1 == frames.len() {
+			println!("Frame {}", position.bright_green());
+			break;
+		}
+		print!(
+			"Frame {}, press Enter for the next one ",
+			position.bright_green()
+		);
+		io::stdout().flush()?;
+		let mut line = String::new();
+		io::stdin().read_line(&mut line)?;
+	}
+	Ok(())
+}
+
+/// Ask for the label of an account found on chain, empty keeps the default
+pub fn account_label_prompt(account: &DiscoveredAccount) -> Option<String> {
+	print!(
+		"Found account {} with {} outputs worth {} grin. Label [{}]: ",
+		account.index.to_string().bright_green(),
+		account.num_outputs,
+		amount_to_hr_string(account.amount, false).bright_green(),
+		account.label
+	);
+	io::stdout().flush().unwrap();
+	let mut line = String::new();
+	if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+		return None;
+	}
+	Some(line.trim().to_owned()).filter(|l| !l.is_empty())
+}
+
+/// Display the accounts found on chain while restoring
+pub fn discovered_accounts(accounts: &[DiscoveredAccount]) {
+	println!("\n____ Accounts Found ____\n",);
+	let mut table = table!();
+
+	table.set_titles(row![
+		bMG->"Index",
+		mMG->"Name",
+		bMG->"Outputs",
+		bMG->"Amount",
+	]);
+	for a in accounts {
+		table.add_row(row![
+			a.index,
+			bFC->a.label,
+			a.num_outputs,
+			amount_to_hr_string(a.amount, false),
+		]);
+	}
+	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+	table.printstd();
+	println!();
+}
+
 /// Display list of wallet accounts in a pretty way
 pub fn accounts(acct_mappings: Vec<AcctPathMapping>) {
 	println!("\n____ Wallet Accounts ____\n",);
@@ -173,11 +422,443 @@ pub fn accounts(acct_mappings: Vec<AcctPathMapping>) {
 	table.set_titles(row![
 		mMG->"Name",
 		bMG->"Parent BIP-32 Derivation Path",
+		bMG->"Notifications",
 	]);
 	for m in acct_mappings {
+		let notifications = match m.notifications {
+			Some(r) => vec![r.webhook, r.script]
+				.into_iter()
+				.filter_map(|x| x)
+				.collect::<Vec<String>>()
+				.join("\n"),
+			None => "config".to_owned(),
+		};
+		let label = match m.archived {
+			true => format!("{} (archived)", m.label),
+			false => m.label,
+		};
 		table.add_row(row![
-			bFC->m.label,
+			bFC->label,
 			bGC->m.path.to_bip_32_string(),
+			bFD->notifications,
+		]);
+	}
+	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+	table.printstd();
+	println!();
+}
+
+pub fn features(features: &[Feature]) {
+	println!("\n____ Features ____\n",);
+	let mut table = table!();
+
+	table.set_titles(row![
+		bMG->"Feature",
+		bMG->"State",
+		bMG->"Details",
+	]);
+	for f in features {
+		let state = match (f.compiled, f.enabled) {
+			(false, _) => "unavailable".bright_red(),
+			(true, false) => "disabled".bright_yellow(),
+			(true, true) => "enabled".bright_green(),
+		};
+		table.add_row(row![
+			bFC->f.name,
+			state,
+			f.detail,
+		]);
+	}
+	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+	table.printstd();
+	println!();
+}
+
+pub fn reachable_addresses(addresses: &[ReachableAddress]) {
+	if addresses.is_empty() {
+		println!("Not reachable, start a listener with `listen`");
+		return;
+	}
+
+	println!("\n____ Reachable at ____\n",);
+	let mut table = table!();
+
+	table.set_titles(row![
+		bMG->"Interface",
+		bMG->"Address",
+		bMG->"Via",
+		bMG->"State",
+	]);
+	for a in addresses {
+		let state = match a.connected {
+			true => "connected".bright_green(),
+			false => "disconnected".bright_red(),
+		};
+		table.add_row(row![
+			bFC->a.interface,
+			bFB->a.uri,
+			a.note,
+			state,
+		]);
+	}
+	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+	table.printstd();
+	println!();
+}
+
+pub fn listeners(statuses: &[ListenerStatus]) {
+	if statuses.is_empty() {
+		println!("No listeners running");
+		return;
+	}
+
+	println!("\n____ Listeners ____\n",);
+	let mut table = table!();
+
+	table.set_titles(row![
+		bMG->"Interface",
+		bMG->"Address",
+		bMG->"State",
+		bMG->"Reconnects",
+		bMG->"Uptime",
+		bMG->"Last error",
+	]);
+	for s in statuses {
+		let state = match (s.connected, s.reconnect_attempts) {
+			(true, _) => "connected".bright_green(),
+			(false, 0) => "disconnected".bright_red(),
+			(false, _) => "reconnecting".bright_yellow(),
+		};
+		let uptime = s
+			.uptime
+			.map(|secs| age(secs / 60))
+			.unwrap_or("-".to_owned());
+		table.add_row(row![
+			bFC->s.interface,
+			bFB->s.address,
+			state,
+			s.reconnect_attempts,
+			uptime,
+			s.last_error.clone().unwrap_or("-".to_owned()),
+		]);
+	}
+	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+	table.printstd();
+	println!();
+}
+
+/// Queued slates with the index of their transaction, if still in the log
+pub fn queued_slates(account: &str, queued: &[(Option<u32>, QueuedSlate)], max_attempts: u32) {
+	if queued.is_empty() {
+		println!("No slates waiting for a response in account '{}'", account);
+		return;
+	}
+
+	println!("\n____ Outbox - Account '{}' ____\n", account);
+	let mut table = table!();
+
+	table.set_titles(row![
+		bMG->"Id",
+		bMG->"Slate ID",
+		bMG->"Recipient",
+		bMG->"Amount",
+		bMG->"State",
+		bMG->"Attempts",
+		bMG->"Next Attempt",
+		bMG->"Last Error",
+	]);
+	for (id, q) in queued {
+		let id = id.map(|i| i.to_string()).unwrap_or("-".to_owned());
+		let state = match (q.is_exhausted(max_attempts), q.delivered) {
+			(true, _) => "given up".bright_red(),
+			(false, true) => "awaiting response".bright_green(),
+			(false, false) => "not delivered".bright_yellow(),
+		};
+		let next_attempt = if q.is_exhausted(max_attempts) {
+			"-".to_owned()
+		} else {
+			format!("{}", q.next_attempt.format("%Y-%m-%d %H:%M:%S"))
+		};
+		table.add_row(row![
+			bFC->id,
+			bFC->q.slate_id(),
+			bFB->q.dest,
+			amount_to_hr_string(q.slate.amount, true),
+			state,
+			q.attempts,
+			next_attempt,
+			q.last_error.clone().unwrap_or("-".to_owned()),
+		]);
+	}
+	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+	table.printstd();
+	println!();
+}
+
+/// Received slates held for approval, and the rejected ones
+pub fn incoming_slates(incoming: &[IncomingSlate]) {
+	if incoming.is_empty() {
+		println!("No slates waiting for approval");
+		return;
+	}
+
+	println!("\n____ Incoming slates ____\n");
+	let mut table = table!();
+
+	table.set_titles(row![
+		bMG->"Slate ID",
+		bMG->"Sender",
+		bMG->"Account",
+		bMG->"Amount",
+		bMG->"Received",
+		bMG->"State",
+		bMG->"Message",
+	]);
+	for i in incoming {
+		let amount = i.slate.recipient_amount(i.slate.participant_data.len());
+		let state = match i.rejected {
+			true => "rejected".bright_red(),
+			false => "waiting".bright_yellow(),
+		};
+		table.add_row(row![
+			bFC->i.slate_id(),
+			bFB->i.address,
+			i.account.clone().unwrap_or("-".to_owned()),
+			amount_to_hr_string(amount, true),
+			format!("{}", i.received.format("%Y-%m-%d %H:%M:%S")),
+			state,
+			i.message.clone().unwrap_or("-".to_owned()),
+		]);
+	}
+	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+	table.printstd();
+	println!();
+}
+
+/// Outputs shared with another wallet
+pub fn multisig_outputs(outputs: &[MultisigOutput]) {
+	if outputs.is_empty() {
+		println!("No multisig outputs");
+		return;
+	}
+
+	println!("\n____ Multisig outputs ____\n");
+	let mut table = table!();
+
+	table.set_titles(row![
+		bMG->"ID",
+		bMG->"Created",
+		bMG->"Role",
+		bMG->"Value",
+		bMG->"Status",
+		bMG->"Commitment",
+	]);
+	for o in outputs {
+		let role = match o.participant_id {
+			0 => "funder",
+			_ => "cosigner",
+		};
+		let status = match o.status {
+			MultisigStatus::Building => o.status.to_string().bright_yellow(),
+			MultisigStatus::Funded => o.status.to_string().bright_green(),
+			MultisigStatus::Spending => o.status.to_string().bright_yellow(),
+			MultisigStatus::Spent => o.status.to_string().bright_red(),
+		};
+		let commit = match &o.commit {
+			Some(c) => to_hex(c.0.to_vec()),
+			None => "-".to_owned(),
+		};
+		table.add_row(row![
+			bFC->o.id,
+			format!("{}", o.created.format("%Y-%m-%d %H:%M:%S")),
+			role,
+			amount_to_hr_string(o.value, true),
+			status,
+			commit,
+		]);
+	}
+	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+	table.printstd();
+	println!();
+}
+
+/// Sends waiting for a response, with the slate stored to resend them
+pub fn pending_txs(account: &str, pending: &[(TxLogEntry, Option<QueuedSlate>)]) {
+	if pending.is_empty() {
+		println!("No sends waiting for a response in account '{}'", account);
+		return;
+	}
+
+	println!("\n____ Pending Sends - Account '{}' ____\n", account);
+	let mut table = table!();
+
+	table.set_titles(row![
+		bMG->"Id",
+		bMG->"Slate ID",
+		bMG->"Age",
+		bMG->"Address",
+		bMG->"Amount",
+		bMG->"Method",
+		bMG->"Sent",
+	]);
+	let now = Utc::now();
+	for (t, queued) in pending {
+		let slate_id = match t.tx_slate_id {
+			Some(id) => id.to_string(),
+			None => "-".to_owned(),
+		};
+		let age = age(now.signed_duration_since(t.creation_ts).num_minutes());
+		let amount = t
+			.amount_debited
+			.saturating_sub(t.amount_credited)
+			.saturating_sub(t.fee.unwrap_or(0));
+		let (method, sent) = match queued {
+			Some(q) => (q.method.clone(), format!("{}x", q.attempts)),
+			None => ("-".to_owned(), "-".to_owned()),
+		};
+		table.add_row(row![
+			bFC->t.id,
+			bFC->slate_id,
+			age,
+			bFB->t.address.clone().unwrap_or("-".to_owned()),
+			amount_to_hr_string(amount, true),
+			method,
+			sent,
+		]);
+	}
+	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+	table.printstd();
+	println!();
+}
+
+pub fn derivation_fixes(fixes: &[DerivationFix]) {
+	println!("\n____ Derivation Indices ____\n",);
+	let mut table = table!();
+
+	table.set_titles(row![
+		bMG->"Account",
+		bMG->"Parent BIP-32 Derivation Path",
+		bMG->"Highest Output Index",
+		bMG->"Before",
+		bMG->"After",
+	]);
+	for f in fixes {
+		let after = if f.fixed() {
+			f.after.to_string().bright_green()
+		} else {
+			f.after.to_string().normal()
+		};
+		table.add_row(row![
+			bFC->f.account.clone().unwrap_or("-".to_owned()),
+			bGC->f.parent_key_id.to_bip_32_string(),
+			f.highest_output_index.map(|i| i.to_string()).unwrap_or("-".to_owned()),
+			f.before,
+			after,
+		]);
+	}
+	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+	table.printstd();
+	println!();
+
+	let fixed = fixes.iter().filter(|f| f.fixed()).count();
+	if fixed == 0 {
+		println!("All derivation indices are up to date");
+	} else {
+		println!("Fixed the derivation index of {} account(s)", fixed);
+	}
+}
+
+pub fn history_wipe(wipe: &HistoryWipe) {
+	let before = wipe.before.format("%Y-%m-%d %H:%M:%S");
+	if wipe.is_empty() {
+		println!("No transaction history from before {} left to wipe", before);
+		return;
+	}
+	println!(
+		"Wiped {} transaction(s) from before {}: {} stored transaction(s), {} proof(s) removed",
+		wipe.entries, before, wipe.stored_txs, wipe.proofs
+	);
+}
+
+pub fn wallet_data(summary: &WalletDataSummary) {
+	println!(
+		"{} account(s), {} output(s), {} transaction(s), {} context(s), {} stored transaction(s), {} proof(s)",
+		summary.accounts,
+		summary.outputs,
+		summary.txs,
+		summary.contexts,
+		summary.stored_txs,
+		summary.proofs
+	);
+}
+
+pub fn backup(backup: &BackupInfo) {
+	println!(
+		"Backed up {} file(s) to {} ({} bytes)",
+		backup.files,
+		backup.path.bright_green(),
+		backup.size
+	);
+	if backup.pruned > 0 {
+		println!("Removed {} older backup(s)", backup.pruned);
+	}
+}
+
+pub fn kernel_reconciliation(reconciliation: &KernelReconciliation) {
+	println!(
+		"Checked the kernels of transactions confirmed since {}: {} found on chain, {} missing",
+		reconciliation.since.format("%Y-%m-%d %H:%M:%S"),
+		reconciliation.found,
+		reconciliation.missing.len()
+	);
+	if reconciliation.unverifiable > 0 {
+		println!(
+			"{} transaction(s) have no stored kernel excess and were skipped",
+			reconciliation.unverifiable
+		);
+	}
+	if reconciliation.is_consistent() {
+		return;
+	}
+
+	println!(
+		"\n{}: the following transactions are marked confirmed but their kernel is not on chain. \
+		 This happens after a deep reorg, or when an older backup was restored",
+		"WARNING".bright_yellow()
+	);
+	let mut table = table!();
+	table.set_titles(row![
+		bMG->"Index",
+		bMG->"Type",
+		bMG->"Confirmation Time",
+		bMG->"Amount",
+		bMG->"Kernel Excess",
+	]);
+	for t in &reconciliation.missing {
+		let confirmation_ts = match t.confirmation_ts {
+			Some(m) => format!("{}", m.format("%Y-%m-%d %H:%M:%S")),
+			None => "".to_owned(),
+		};
+		let mut amount: i64 = t.amount_credited as i64 - t.amount_debited as i64;
+		if let Some(fee) = t.fee {
+			amount += fee as i64;
+		}
+		let amount = if amount > 0 {
+			format!(" {}", amount_to_hr_string(amount as u64, true))
+		} else {
+			format!("-{}", amount_to_hr_string((-amount) as u64, true))
+		};
+		let excess = t
+			.excess
+			.as_ref()
+			.map(|e| to_hex(e.0.to_vec()))
+			.unwrap_or_default();
+		table.add_row(row![
+			bFC->t.id,
+			bFC->t.tx_type,
+			bFB->confirmation_ts,
+			bFY->amount,
+			excess,
 		]);
 	}
 	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
@@ -185,6 +866,32 @@ pub fn accounts(acct_mappings: Vec<AcctPathMapping>) {
 	println!();
 }
 
+pub fn node_status(node_url: &str, status: &NodeStatus) {
+	println!("\n____ Node Status ____\n",);
+	let mut table = table!();
+
+	let sync = if status.is_synced() {
+		"synced".bright_green()
+	} else {
+		status.sync_status.bright_yellow()
+	};
+	let connections = if status.connections == 0 {
+		status.connections.to_string().bright_red()
+	} else {
+		status.connections.to_string().normal()
+	};
+	table.add_row(row![bFC->"Node", node_url]);
+	table.add_row(row![bFC->"Version", status.user_agent]);
+	table.add_row(row![bFC->"Protocol", status.protocol_version]);
+	table.add_row(row![bFC->"Chain height", bFG->status.height]);
+	table.add_row(row![bFC->"Sync status", sync]);
+	table.add_row(row![bFC->"Peers", connections]);
+	table.add_row(row![bFC->"Latency", format!("{} ms", status.latency)]);
+	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+	table.printstd();
+	println!();
+}
+
 /// Display outputs in a pretty way
 pub fn outputs(
 	account: &str,
@@ -301,6 +1008,7 @@ pub fn txs(
 		bMG->"Amount",
 		bMG->"Fee",
 		bMG->"Proof?",
+		bMG->"Label",
 	]);
 
 	for t in txs {
@@ -311,7 +1019,7 @@ pub fn txs(
 			None => "".to_owned(),
 		};
 		let address = match &t.address {
-			Some(a) => match contacts.get(a) {
+			Some(a) => match contacts.get(a).or(t.contact.as_ref()) {
 				Some(c) => format!("@{}", c),
 				None => a.clone(),
 			},
@@ -340,6 +1048,14 @@ pub fn txs(
 			Some(m) if proofs.contains_key(m) => "yes".to_owned(),
 			_ => "".to_owned(),
 		};
+		// Private notes go below the label, one per line
+		let label = t
+			.label
+			.iter()
+			.cloned()
+			.chain(t.notes.iter().map(|(k, v)| format!("{}: {}", k, v)))
+			.collect::<Vec<String>>()
+			.join("\n");
 		if dark_background_color_scheme {
 			table.add_row(row![
 				bFC->id,
@@ -352,6 +1068,7 @@ pub fn txs(
 				bFY->amount,
 				bFC->fee,
 				bFG->proof,
+				bFW->label,
 			]);
 		} else {
 			table.add_row(row![
@@ -365,6 +1082,7 @@ pub fn txs(
 				bFG->amount,
 				bFD->fee,
 				bFg->proof,
+				bFD->label,
 			]);
 		}
 	}
@@ -382,6 +1100,209 @@ pub fn txs(
 	}
 }
 
+/// Display everything known about a single transaction
+pub fn tx_detail(detail: &TxDetail) {
+	let t = &detail.tx;
+	println!("\n____ Transaction {} ____\n", t.id);
+	let mut table = table!();
+	table.add_row(row![bFC->"Type", t.tx_type]);
+	if let Some(slate_id) = &t.tx_slate_id {
+		table.add_row(row![bFC->"Slate id", slate_id]);
+	}
+	if let Some(address) = &t.address {
+		let address = match &t.contact {
+			Some(c) => format!("@{} ({})", c, address),
+			None => address.clone(),
+		};
+		table.add_row(row![bFC->"Address", address]);
+	}
+	table.add_row(row![bFC->"Created", t.creation_ts.format("%Y-%m-%d %H:%M:%S")]);
+	let confirmed = match t.confirmation_ts {
+		Some(ts) if t.confirmed => format!("{}", ts.format("%Y-%m-%d %H:%M:%S")),
+		_ if t.confirmed => "yes".to_owned(),
+		_ => "no".to_owned(),
+	};
+	let confirmed = match t.confirmation_height {
+		Some(height) if t.confirmed => format!("{} at height {}", confirmed, height),
+		_ => confirmed,
+	};
+	table.add_row(row![bFC->"Confirmed", confirmed]);
+	if let Some(hash) = t.confirmation_hash.as_ref().filter(|_| t.confirmed) {
+		table.add_row(row![bFC->"Block hash", hash]);
+	}
+	table.add_row(row![bFC->"Credited", amount_to_hr_string(t.amount_credited, false)]);
+	table.add_row(row![bFC->"Debited", amount_to_hr_string(t.amount_debited, false)]);
+	if let Some(fee) = t.fee {
+		table.add_row(row![bFC->"Fee", amount_to_hr_string(fee, false)]);
+	}
+	if let Some(id) = t.replaces {
+		table.add_row(row![bFC->"Replaces", id]);
+	}
+	if let Some(id) = t.replaced_by {
+		table.add_row(row![bFC->"Replaced by", id]);
+	}
+	if let Some(excess) = &detail.excess {
+		let excess = to_hex(excess.0.to_vec());
+		let prefix = if is_floonet() { "floonet." } else { "" };
+		let explorer = format!("https://{}grinscan.net/kernel/{}", prefix, excess);
+		table.add_row(row![bFC->"Kernel excess", excess]);
+		table.add_row(row![bFC->"Explorer", explorer]);
+	}
+	if let Some(label) = &t.label {
+		table.add_row(row![bFC->"Label", label]);
+	}
+	for (key, value) in &t.notes {
+		table.add_row(row![bFC->format!("Note {}", key), value]);
+	}
+	let yes_no = |b: bool| if b { "yes" } else { "no" };
+	table.add_row(row![bFC->"Stored transaction", yes_no(detail.stored_tx)]);
+	table.add_row(row![bFC->"Transaction proof", yes_no(detail.tx_proof)]);
+	table.add_row(row![bFC->"Payment proof", yes_no(t.payment_proof.is_some())]);
+	let slates = detail
+		.slates
+		.iter()
+		.map(|s| format!("{} ({:?})", s.source, s.version))
+		.collect::<Vec<_>>()
+		.join(", ");
+	if !slates.is_empty() {
+		table.add_row(row![bFC->"Stored slates", slates]);
+	}
+	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
+	table.printstd();
+
+	if !detail.messages.iter().all(|m| m.message.is_none()) {
+		println!("\nMessages:");
+		for m in detail.messages.iter().filter(|m| m.message.is_some()) {
+			let verified = match m.verified {
+				true => "verified".bright_green(),
+				false => "NOT VERIFIED".bright_red(),
+			};
+			println!(
+				"   #{} [{}] {}",
+				m.participant_id,
+				verified,
+				m.message.as_ref().unwrap()
+			);
+		}
+	}
+
+	for (title, commits) in &[("Inputs", &detail.inputs), ("Outputs", &detail.outputs)] {
+		if !commits.is_empty() {
+			println!("\n{}:", title);
+			for commit in commits.iter() {
+				println!("   {}", to_hex(commit.0.to_vec()).bright_magenta());
+			}
+		}
+	}
+	println!();
+}
+
+/// Display the outstanding transactions that can be cancelled
+pub fn cleanup(txs: &Vec<TxLogEntry>, contacts: HashMap<String, String>) {
+	println!("\n____ Outstanding transactions ____\n");
+
+	let mut table = table!();
+	table.set_titles(row![
+		bMG->"Index",
+		bMG->"Type",
+		bMG->"TXID",
+		bMG->"Address",
+		bMG->"Age",
+		bMG->"Amount",
+		bMG->"Locked",
+	]);
+
+	let now = Utc::now();
+	for t in txs {
+		let id = format!("{}", t.id);
+		let entry_type = format!("{}", t.tx_type);
+		let slate_id = match &t.tx_slate_id {
+			Some(m) => to_hex(m.as_bytes()[..4].to_vec()),
+			None => "".to_owned(),
+		};
+		let address = match &t.address {
+			Some(a) => match contacts.get(a).or(t.contact.as_ref()) {
+				Some(c) => format!("@{}", c),
+				None => a.clone(),
+			},
+			None => "".to_owned(),
+		};
+		let age = age(now.signed_duration_since(t.creation_ts).num_minutes());
+		let (amount, locked) = match t.tx_type {
+			TxLogEntryType::TxSent => (
+				t.amount_debited - t.amount_credited - t.fee.unwrap_or(0),
+				t.amount_debited,
+			),
+			_ => (t.amount_credited, 0),
+		};
+		table.add_row(row![
+			bFC->id,
+			bFC->entry_type,
+			bFB->slate_id,
+			bFY->address,
+			bFB->age,
+			bFY->amount_to_hr_string(amount, true),
+			FR->amount_to_hr_string(locked, true),
+		]);
+	}
+
+	table.set_format(*FORMAT_NO_COLSEP);
+	table.printstd();
+	println!();
+}
+
+/// Ask the user which of the listed transactions to cancel
+pub fn cleanup_prompt(candidates: &[u32]) -> Result<Vec<u32>, Error> {
+	println!(
+		"Enter the indices of the transactions to cancel, separated by spaces, or '{}'",
+		"all".bold()
+	);
+	println!("Leave empty to abort");
+	print!("{}", "> ".cyan());
+	io::stdout().flush()?;
+
+	let mut line = String::new();
+	if io::stdin().read_line(&mut line)? == 0 {
+		return Ok(vec![]);
+	}
+	let line = line.trim();
+	if line == "all" {
+		return Ok(candidates.to_vec());
+	}
+
+	let mut selected = Vec::new();
+	for part in line.split(|c: char| c == ',' || c.is_whitespace()) {
+		if part.is_empty() {
+			continue;
+		}
+		let index: u32 = part
+			.parse()
+			.map_err(|_| ErrorKind::ParseNumber(part.to_owned()))?;
+		if !candidates.contains(&index) {
+			return Err(ErrorKind::GenericError(format!(
+				"Transaction {} is not in the list",
+				index
+			)))?;
+		}
+		if !selected.contains(&index) {
+			selected.push(index);
+		}
+	}
+	Ok(selected)
+}
+
+/// Human readable age from a number of minutes
+fn age(minutes: i64) -> String {
+	let minutes = minutes.max(0);
+	if minutes < 60 {
+		format!("{}m", minutes)
+	} else if minutes < 60 * 24 {
+		format!("{}h {}m", minutes / 60, minutes % 60)
+	} else {
+		format!("{}d {}h", minutes / (60 * 24), (minutes / 60) % 24)
+	}
+}
+
 /// Display summary info in a pretty way
 pub fn info(
 	account: &str,
@@ -500,7 +1421,83 @@ pub fn proof(
 	println!("\n{}: this proof should only be considered valid if the kernel is actually on-chain with sufficient confirmations", "WARNING".bright_yellow());
 	println!("Please use a grin block explorer to verify this is the case. for example:");
 	let prefix = if is_floonet() { "floonet." } else { "" };
-	cli_message!("   https://{}grinscan.net/kernel/{}", prefix, excess);
+	println!("   https://{}grinscan.net/kernel/{}", prefix, excess);
+}
+
+pub fn payment_proof(proof: &PaymentProof) {
+	let excess = to_hex(proof.excess.0.to_vec());
+	println!(
+		"This file proves that {} grin was sent to {} from {}",
+		proof.amount.to_hr_string(false).bright_green(),
+		format!(
+			"{}",
+			GrinboxAddress::new(proof.recipient_address.clone(), None, None)
+		)
+		.bright_green(),
+		format!(
+			"{}",
+			GrinboxAddress::new(proof.sender_address.clone(), None, None)
+		)
+		.bright_green()
+	);
+
+	println!("Kernel excess:");
+	println!("   {}", excess.bright_magenta());
+	println!("\n{}: this proof should only be considered valid if the kernel is actually on-chain with sufficient confirmations", "WARNING".bright_yellow());
+	println!("Please use a grin block explorer to verify this is the case. for example:");
+	let prefix = if is_floonet() { "floonet." } else { "" };
+	println!("   https://{}grinscan.net/kernel/{}", prefix, excess);
+}
+
+pub fn ownership_proof(proof: &OwnershipProof, on_chain: bool) {
+	println!(
+		"This file proves that the owner controls output {} worth {} grin",
+		to_hex(proof.commit.0.to_vec()).bright_magenta(),
+		amount_to_hr_string(proof.value, false).bright_green()
+	);
+	if !proof.message.is_empty() {
+		println!("Signed message: {}", proof.message.bright_green());
+	}
+	if on_chain {
+		println!("The output is currently unspent on chain");
+	} else {
+		println!(
+			"{}: the output was not found in the UTXO set, it is either spent or not confirmed yet",
+			"WARNING".bright_yellow()
+		);
+	}
+}
+
+pub fn balance_attestation(attestation: &BalanceAttestation, status: &AttestationStatus) {
+	println!(
+		"This file proves that the owner controlled {} outputs worth {} grin at height {}",
+		attestation.commits.len(),
+		amount_to_hr_string(attestation.total, false).bright_green(),
+		attestation.height
+	);
+	if !attestation.message.is_empty() {
+		println!("Signed message: {}", attestation.message.bright_green());
+	}
+	if attestation.holds(status) {
+		println!("All outputs are currently unspent on chain");
+		return;
+	}
+	if status.unspent < attestation.commits.len() {
+		println!(
+			"{}: {} of {} outputs were not found in the UTXO set, they are either spent or not confirmed yet",
+			"WARNING".bright_yellow(),
+			attestation.commits.len() - status.unspent,
+			attestation.commits.len()
+		);
+	}
+	if status.mined_after > 0 {
+		println!(
+			"{}: {} outputs were mined after height {}, the attestation is not valid at that height",
+			"WARNING".bright_yellow(),
+			status.mined_after,
+			attestation.height
+		);
+	}
 }
 
 /// Display list of contacts in a pretty way
@@ -511,14 +1508,76 @@ pub fn contacts(contacts: Vec<Contact>) {
 	table.set_titles(row![
 		mMG->"Name",
 		bMG->"Address",
+		bMG->"Group",
+		bMG->"Notes",
+		bMG->"Relay",
 	]);
 	for c in contacts {
+		let group = c.group.unwrap_or(String::new());
+		let notes = c.notes.unwrap_or(String::new());
+		let relay = c.relay.unwrap_or(String::new());
 		table.add_row(row![
 			bFC->c.name,
 			bGC->c.address,
+			bFY->group,
+			notes,
+			relay,
 		]);
 	}
 	table.set_format(*FORMAT_NO_BORDER_LINE_SEPARATOR);
 	table.printstd();
 	println!();
 }
+
+/// Display execution time of a single command and its stages
+pub fn command_timings(command: &str, total_ms: u64, stages: Vec<(String, u64)>) {
+	println!("\n____ Timings for `{}` ____\n", command);
+	let mut table = table!();
+
+	table.set_titles(row![
+		bMG->"Stage",
+		bMG->"Time (ms)",
+	]);
+	for (stage, ms) in stages {
+		table.add_row(row![
+			bFC->stage,
+			FY->ms,
+		]);
+	}
+	table.add_row(row![
+		bFG->"total",
+		bFG->total_ms,
+	]);
+	table.set_format(*FORMAT_NO_COLSEP);
+	table.printstd();
+	println!();
+}
+
+/// Display aggregated execution times in a pretty way
+pub fn timings(timings: Vec<StageTiming>) {
+	println!("\n____ Timings ____\n",);
+	let mut table = table!();
+
+	table.set_titles(row![
+		bMG->"Stage",
+		bMG->"Count",
+		bMG->"Total (ms)",
+		bMG->"Avg (ms)",
+		bMG->"Max (ms)",
+		bMG->"Last (ms)",
+	]);
+	for t in timings {
+		let avg = t.avg_ms();
+		table.add_row(row![
+			bFC->t.stage,
+			t.count,
+			t.total_ms,
+			FY->avg,
+			FR->t.max_ms,
+			t.last_ms,
+		]);
+	}
+	table.set_format(*FORMAT_NO_COLSEP);
+	table.printstd();
+	println!();
+}
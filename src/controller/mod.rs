@@ -14,4 +14,6 @@
 
 pub mod args;
 pub mod cli;
+pub mod config_wizard;
 pub mod display;
+mod picker;
@@ -0,0 +1,231 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::contacts::{parse_address, Contact};
+use crate::wallet::types::{TxLogEntry, TxLogEntryType};
+use colored::Colorize;
+use failure::Error;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::{CompletionType, Config, Context, EditMode, Editor, Helper, OutputStreamType};
+use std::borrow::Cow::{self, Owned};
+
+const PICKER_PROMPT: &str = "to> ";
+const MAX_RECENT: usize = 10;
+const MAX_LISTED: usize = 20;
+
+/// A destination the user can pick for a send
+#[derive(Clone, Debug)]
+pub struct Destination {
+	/// Contact name, if the destination is in the address book
+	pub name: Option<String>,
+	/// Stripped address, as expected by `InitTxSendArgs::dest`
+	pub dest: String,
+	pub method: String,
+}
+
+impl Destination {
+	fn new(name: Option<String>, address: &str) -> Option<Self> {
+		let address = parse_address(address).ok()?;
+		Some(Self {
+			name,
+			dest: address.stripped(),
+			method: address.address_type().method().to_owned(),
+		})
+	}
+
+	fn label(&self) -> String {
+		match &self.name {
+			Some(name) => format!("@{} {}", name, self.dest),
+			None => self.dest.clone(),
+		}
+	}
+}
+
+/// Contacts first, followed by the most recent addresses we sent to
+/// that are not in the address book
+pub fn destinations(contacts: &[Contact], txs: &[TxLogEntry]) -> Vec<Destination> {
+	let mut destinations: Vec<Destination> = contacts
+		.iter()
+		.filter_map(|c| Destination::new(Some(c.name.clone()), &c.address))
+		.collect();
+
+	let mut sent: Vec<&TxLogEntry> = txs
+		.iter()
+		.filter(|t| t.tx_type == TxLogEntryType::TxSent && t.address.is_some())
+		.collect();
+	sent.sort_by(|a, b| b.creation_ts.cmp(&a.creation_ts));
+
+	let mut recent = 0;
+	for tx in sent {
+		if recent >= MAX_RECENT {
+			break;
+		}
+		let destination = match Destination::new(None, tx.address.as_ref().unwrap()) {
+			Some(d) => d,
+			None => continue,
+		};
+		if destinations.iter().all(|d| d.dest != destination.dest) {
+			destinations.push(destination);
+			recent += 1;
+		}
+	}
+
+	destinations
+}
+
+/// Case insensitive subsequence match. Higher scores for consecutive
+/// matches and matches at the start of the text
+fn fuzzy_score(pattern: &str, text: &str) -> Option<i64> {
+	let text: Vec<char> = text.to_lowercase().chars().collect();
+	let mut score = 0;
+	let mut pos = 0;
+	let mut last: Option<usize> = None;
+	for p in pattern
+		.to_lowercase()
+		.chars()
+		.filter(|c| !c.is_whitespace())
+	{
+		let found = text[pos..].iter().position(|c| *c == p)? + pos;
+		score += match last {
+			Some(l) if l + 1 == found => 5,
+			_ if found == 0 => 3,
+			_ => 1,
+		};
+		last = Some(found);
+		pos = found + 1;
+	}
+	Some(score - text.len() as i64 / 10)
+}
+
+fn matches<'a>(query: &str, destinations: &'a [Destination]) -> Vec<&'a Destination> {
+	let mut scored: Vec<(i64, &Destination)> = destinations
+		.iter()
+		.filter_map(|d| fuzzy_score(query, &d.label()).map(|s| (s, d)))
+		.collect();
+	scored.sort_by(|a, b| b.0.cmp(&a.0));
+	scored.into_iter().map(|(_, d)| d).collect()
+}
+
+fn list(destinations: &[&Destination]) {
+	for (i, d) in destinations.iter().take(MAX_LISTED).enumerate() {
+		let name = match &d.name {
+			Some(name) => format!("@{}", name).bright_green().to_string(),
+			None => "(recent)".dimmed().to_string(),
+		};
+		println!("{:>3}  {}  {} [{}]", i + 1, name, d.dest, d.method);
+	}
+	if destinations.len() > MAX_LISTED {
+		println!(
+			"     ... {} more, refine your search",
+			destinations.len() - MAX_LISTED
+		);
+	}
+}
+
+struct PickerHelper(Vec<Destination>);
+
+impl Completer for PickerHelper {
+	type Candidate = Pair;
+
+	fn complete(
+		&self,
+		line: &str,
+		_pos: usize,
+		_ctx: &Context<'_>,
+	) -> std::result::Result<(usize, Vec<Pair>), ReadlineError> {
+		let candidates = matches(line, &self.0)
+			.into_iter()
+			.map(|d| Pair {
+				display: d.label(),
+				replacement: d.label(),
+			})
+			.collect();
+		Ok((0, candidates))
+	}
+}
+
+impl Hinter for PickerHelper {
+	fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+		if line.is_empty() || pos < line.len() {
+			return None;
+		}
+		let best = matches(line, &self.0).into_iter().next()?;
+		Some(format!("  → {}", best.label()))
+	}
+}
+
+impl Highlighter for PickerHelper {
+	fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+		Owned("\x1b[2m".to_owned() + hint + "\x1b[m")
+	}
+}
+
+impl Helper for PickerHelper {}
+
+/// Interactively pick a send destination. Type to fuzzy search, <tab> to
+/// complete, or enter the number of a listed entry. Returns `None` when
+/// the user aborts with an empty line
+pub fn pick(destinations: Vec<Destination>) -> Result<Option<Destination>, Error> {
+	if destinations.is_empty() {
+		println!("No contacts or recent addresses, use --to to specify a destination");
+		return Ok(None);
+	}
+
+	let config = Config::builder()
+		.completion_type(CompletionType::List)
+		.edit_mode(EditMode::Emacs)
+		.output_stream(OutputStreamType::Stdout)
+		.build();
+	let mut reader = Editor::with_config(config);
+	reader.set_helper(Some(PickerHelper(destinations.clone())));
+
+	let mut listed: Vec<&Destination> = destinations.iter().collect();
+	println!("Select a destination, type to search or leave empty to abort");
+	list(&listed);
+
+	loop {
+		let line = match reader.readline(PICKER_PROMPT) {
+			Ok(line) => line,
+			Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => return Ok(None),
+			Err(e) => return Err(e.into()),
+		};
+		let query = line.trim();
+		if query.is_empty() {
+			return Ok(None);
+		}
+
+		if let Ok(index) = query.parse::<usize>() {
+			if index >= 1 && index <= listed.len().min(MAX_LISTED) {
+				return Ok(Some(listed[index - 1].clone()));
+			}
+		}
+
+		if let Some(d) = destinations.iter().find(|d| d.label() == query) {
+			return Ok(Some(d.clone()));
+		}
+
+		let found = matches(query, &destinations);
+		match found.len() {
+			0 => println!("No match for '{}'", query),
+			1 => return Ok(Some(found[0].clone())),
+			_ => {
+				list(&found);
+				listed = found;
+			}
+		}
+	}
+}
@@ -12,10 +12,32 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::updater;
+use crate::common::hasher::derive_account_address_key;
 use crate::wallet::error::ErrorKind;
-use crate::wallet::types::{AcctPathMapping, NodeClient, WalletBackend};
+use crate::wallet::types::{
+	AcctPathMapping, BalanceAttestation, BalanceAttestationErrorKind, DerivationFix, NodeClient,
+	NotificationRoute, OutputStatus, OwnershipProof, WalletBackend, DEFAULT_ACCOUNT,
+};
 use failure::Error;
-use grin_keychain::{ChildNumber, Identifier, Keychain};
+use grin_keychain::{ChildNumber, Identifier, Keychain, SwitchCommitmentType};
+use grin_util::secp::key::{PublicKey, SecretKey};
+use grin_util::secp::pedersen::Commitment;
+use grin_util::to_hex;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Number of grinbox address indices around the active one that are
+/// searched when looking up one of our own addresses
+const ADDRESS_SEARCH_WINDOW: u32 = 100;
+/// Second path element of the partial keys of multisig outputs. Regular
+/// outputs are derived under 0
+const MULTISIG_KEY_BRANCH: u32 = 1;
+/// Second path element of the private nonces of multisig range proofs
+const MULTISIG_NONCE_BRANCH: u32 = 2;
+/// Second path element of the keys two multisig wallets agree on the common
+/// nonce of the range proof with
+const MULTISIG_EXCHANGE_BRANCH: u32 = 3;
 
 /// Get next available key in the wallet for a given parent
 pub fn next_available_key<T: ?Sized, C, K>(wallet: &mut T) -> Result<Identifier, Error>
@@ -88,6 +110,8 @@ where
 	let save_path = AcctPathMapping {
 		label: label.to_string(),
 		path: return_id.clone(),
+		notifications: None,
+		archived: false,
 	};
 
 	let mut batch = wallet.batch()?;
@@ -108,9 +132,12 @@ where
 	K: Keychain,
 {
 	let label = label.to_owned();
+	let existing = wallet.get_acct_path(&label)?;
 	let save_path = AcctPathMapping {
 		label: label.to_owned(),
 		path: path.clone(),
+		notifications: existing.as_ref().and_then(|m| m.notifications.clone()),
+		archived: existing.map(|m| m.archived).unwrap_or(false),
 	};
 
 	let mut batch = wallet.batch()?;
@@ -118,3 +145,264 @@ where
 	batch.commit()?;
 	Ok(())
 }
+
+/// Sets where the events of an account are delivered. An empty route
+/// falls back to the routes in the config
+pub fn set_acct_notifications<T: ?Sized, C, K>(
+	wallet: &mut T,
+	label: &str,
+	route: NotificationRoute,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let mut mapping = wallet
+		.get_acct_path(label)?
+		.ok_or_else(|| ErrorKind::UnknownAccountLabel(label.to_owned()))?;
+	mapping.notifications = match route.is_empty() {
+		true => None,
+		false => Some(route),
+	};
+
+	let mut batch = wallet.batch()?;
+	batch.save_acct_path(&mapping)?;
+	batch.commit()?;
+	Ok(())
+}
+
+/// Gives an account a new label, keeping its derivation path, so its
+/// outputs and transactions move along
+pub fn rename_acct_path<T: ?Sized, C, K>(
+	wallet: &mut T,
+	label: &str,
+	new_label: &str,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if label == DEFAULT_ACCOUNT {
+		return Err(ErrorKind::DefaultAccount("renamed".to_owned()).into());
+	}
+	let mut mapping = wallet
+		.get_acct_path(label)?
+		.ok_or_else(|| ErrorKind::UnknownAccountLabel(label.to_owned()))?;
+	if wallet.get_acct_path(new_label)?.is_some() {
+		return Err(ErrorKind::AccountLabelAlreadyExists(new_label.to_owned()).into());
+	}
+	mapping.label = new_label.to_owned();
+
+	let mut batch = wallet.batch()?;
+	batch.delete_acct_path(label)?;
+	batch.save_acct_path(&mapping)?;
+	batch.commit()?;
+	Ok(())
+}
+
+/// Hides an account from the account listings, or shows it again
+pub fn set_acct_archived<T: ?Sized, C, K>(
+	wallet: &mut T,
+	label: &str,
+	archived: bool,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if label == DEFAULT_ACCOUNT {
+		return Err(ErrorKind::DefaultAccount("archived".to_owned()).into());
+	}
+	let mut mapping = wallet
+		.get_acct_path(label)?
+		.ok_or_else(|| ErrorKind::UnknownAccountLabel(label.to_owned()))?;
+	mapping.archived = archived;
+
+	let mut batch = wallet.batch()?;
+	batch.save_acct_path(&mapping)?;
+	batch.commit()?;
+	Ok(())
+}
+
+/// Raise the stored child derivation index of every account that lags
+/// behind its outputs, so `next_child` never hands out an index that is
+/// already used. Parent keys of outputs without an account are included
+pub fn fix_derivation<T: ?Sized, C, K>(wallet: &mut T) -> Result<Vec<DerivationFix>, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let mut highest: HashMap<Identifier, u32> = HashMap::new();
+	for output in wallet.outputs()? {
+		let entry = highest.entry(output.root_key_id).or_insert(output.n_child);
+		*entry = (*entry).max(output.n_child);
+	}
+
+	let mut parents: Vec<(Option<String>, Identifier)> = wallet
+		.accounts()?
+		.map(|m| (Some(m.label), m.path))
+		.collect();
+	for parent_key_id in highest.keys() {
+		if !parents.iter().any(|(_, p)| p == parent_key_id) {
+			parents.push((None, parent_key_id.clone()));
+		}
+	}
+
+	let mut fixes = Vec::with_capacity(parents.len());
+	let mut batch = wallet.batch()?;
+	for (account, parent_key_id) in parents {
+		let highest_output_index = highest.get(&parent_key_id).cloned();
+		let before = wallet.child_index(&parent_key_id)?;
+		let after = match highest_output_index {
+			Some(i) if i >= before => i + 1,
+			_ => before,
+		};
+		if after != before {
+			batch.save_child_index(&parent_key_id, after)?;
+		}
+		fixes.push(DerivationFix {
+			account,
+			parent_key_id,
+			highest_output_index,
+			before,
+			after,
+		});
+	}
+	batch.commit()?;
+	Ok(fixes)
+}
+
+/// Create a proof that the wallet knows the blinding factor of an output
+pub fn ownership_proof<T: ?Sized, C, K>(
+	wallet: &mut T,
+	commit: &Commitment,
+	message: String,
+) -> Result<OwnershipProof, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let output = updater::retrieve_outputs(wallet, true, None, None, None)?
+		.into_iter()
+		.find(|m| m.commit == *commit)
+		.ok_or(ErrorKind::OutputNotFound(to_hex(commit.0.to_vec())))?
+		.output;
+
+	let blinding = wallet.keychain().derive_key(
+		output.value,
+		&output.key_id,
+		&SwitchCommitmentType::Regular,
+	)?;
+	OwnershipProof::new(commit.clone(), output.value, message, &blinding)
+}
+
+/// Attest to the unspent outputs of the active account at the current
+/// chain height, signed with the sum of their blinding factors
+pub fn balance_attestation<T: ?Sized, C, K>(
+	wallet: &mut T,
+	message: String,
+) -> Result<BalanceAttestation, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let parent_key_id = wallet.get_parent_key_id();
+	let height = updater::refresh_outputs(wallet, &parent_key_id, false)?;
+	let outputs: Vec<_> =
+		updater::retrieve_outputs(wallet, false, None, Some(&parent_key_id), None)?
+			.into_iter()
+			.filter(|m| m.output.status == OutputStatus::Unspent)
+			.collect();
+
+	let keychain = wallet.keychain();
+	let mut blinding_sum = None;
+	for m in &outputs {
+		let blinding = keychain.derive_key(
+			m.output.value,
+			&m.output.key_id,
+			&SwitchCommitmentType::Regular,
+		)?;
+		blinding_sum = match blinding_sum {
+			None => Some(blinding),
+			Some(mut sum) => {
+				sum.add_assign(keychain.secp(), &blinding)?;
+				Some(sum)
+			}
+		};
+	}
+	let blinding_sum = blinding_sum.ok_or(BalanceAttestationErrorKind::Empty)?;
+
+	let total = outputs.iter().map(|m| m.output.value).sum();
+	let commits = outputs.into_iter().map(|m| m.commit).collect();
+	BalanceAttestation::new(height, total, commits, message, &blinding_sum)
+}
+
+/// Our partial key of the multisig output `id` and the private nonce of its
+/// range proof. Both are derived from the id, so every round of the
+/// exchange derives the same ones again
+pub fn multisig_keys<K: Keychain>(
+	keychain: &K,
+	parent_key_id: &Identifier,
+	id: &Uuid,
+) -> Result<(SecretKey, SecretKey), Error> {
+	let key_id = multisig_key_id::<K>(parent_key_id, id, MULTISIG_KEY_BRANCH);
+	let nonce_id = multisig_key_id::<K>(parent_key_id, id, MULTISIG_NONCE_BRANCH);
+	let key = keychain.derive_key(0, &key_id, &SwitchCommitmentType::None)?;
+	let nonce = keychain.derive_key(0, &nonce_id, &SwitchCommitmentType::None)?;
+	Ok((key, nonce))
+}
+
+/// Our key for the Diffie-Hellman exchange that gives both wallets of the
+/// multisig output `id` the common nonce of its range proof
+pub fn multisig_exchange_key<K: Keychain>(
+	keychain: &K,
+	parent_key_id: &Identifier,
+	id: &Uuid,
+) -> Result<SecretKey, Error> {
+	let key_id = multisig_key_id::<K>(parent_key_id, id, MULTISIG_EXCHANGE_BRANCH);
+	Ok(keychain.derive_key(0, &key_id, &SwitchCommitmentType::None)?)
+}
+
+fn multisig_key_id<K: Keychain>(parent_key_id: &Identifier, id: &Uuid, branch: u32) -> Identifier {
+	let account = <u32>::from(parent_key_id.to_path().path[0]);
+	let bytes = id.as_bytes();
+	let index = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) & 0x7fff_ffff;
+	K::derive_key_id(3, account, branch, index, 0)
+}
+
+/// Find the account and index of a grinbox address derived from the wallet
+/// seed, searching around the currently active index. The active account
+/// is searched first
+pub fn address_path<T: ?Sized, C, K>(
+	wallet: &mut T,
+	public_key: &PublicKey,
+	current_index: u32,
+) -> Result<Option<(Identifier, u32)>, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let active = wallet.get_parent_key_id();
+	let mut accounts = vec![active.clone()];
+	accounts.extend(wallet.accounts()?.map(|a| a.path).filter(|p| *p != active));
+
+	let keychain = wallet.keychain();
+	let start = current_index.saturating_sub(ADDRESS_SEARCH_WINDOW);
+	let end = current_index.saturating_add(ADDRESS_SEARCH_WINDOW);
+	for parent_key_id in accounts {
+		for i in start..=end {
+			let sec_key = derive_account_address_key(keychain, &parent_key_id, i)?;
+			if PublicKey::from_secret_key(keychain.secp(), &sec_key)? == *public_key {
+				return Ok(Some((parent_key_id, i)));
+			}
+		}
+	}
+	Ok(None)
+}
@@ -0,0 +1,316 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Moving the contents of a wallet to and from grin-wallet, or another
+//! wallet713, without scanning the chain. A data directory holds one JSON
+//! file per kind of record, in the layout of grin-wallet's database, and
+//! the stored transactions and proofs in the format of both wallets.
+//! Imports need a wallet with the same seed
+
+use crate::wallet::types::{
+	AcctPathMapping, Context, GrinContext, GrinOutputData, GrinTxLogEntry, Identifier, NodeClient,
+	OutputData, StoredContext, Transaction, TxLogEntry, TxProof, WalletBackend, WalletDataSummary,
+	ACCT_PATHS_FILE, CONTEXTS_FILE, OUTPUTS_FILE, SAVED_PROOFS_DIR, SAVED_TXS_DIR, TX_LOG_FILE,
+};
+use crate::wallet::ErrorKind;
+use failure::Error;
+use grin_core::ser;
+use grin_keychain::{Keychain, SwitchCommitmentType};
+use grin_util::secp::pedersen::Commitment;
+use grin_util::{from_hex, to_hex};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Participants a context is looked up for, the sender of a transaction
+/// and the receiver of an invoice
+const CONTEXT_PARTICIPANTS: usize = 2;
+
+/// Write all accounts, outputs, transactions, open contexts, stored
+/// transactions and proofs to `dir`, which has to be empty
+pub fn export_data<T: ?Sized, C, K>(wallet: &mut T, dir: &Path) -> Result<WalletDataSummary, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if dir.exists() && fs::read_dir(dir)?.next().is_some() {
+		return Err(ErrorKind::DataDirNotEmpty(dir.display().to_string()).into());
+	}
+	fs::create_dir_all(dir.join(SAVED_TXS_DIR))?;
+	fs::create_dir_all(dir.join(SAVED_PROOFS_DIR))?;
+
+	let accounts: Vec<AcctPathMapping> = wallet.accounts()?.collect();
+	let outputs: Vec<GrinOutputData> = wallet.outputs()?.map(|o| (&o).into()).collect();
+	let txs: Vec<TxLogEntry> = wallet.tx_logs()?.collect();
+	let slate_ids: HashSet<Uuid> = txs.iter().filter_map(|t| t.tx_slate_id).collect();
+
+	let mut contexts = vec![];
+	for tx in txs.iter().filter(|t| !t.confirmed) {
+		let slate_id = match tx.tx_slate_id {
+			Some(id) => id,
+			None => continue,
+		};
+		for participant_id in 0..CONTEXT_PARTICIPANTS {
+			let exported = contexts.iter().any(|c: &StoredContext| {
+				c.slate_id == slate_id && c.participant_id == participant_id
+			});
+			if exported {
+				continue;
+			}
+			if let Ok(context) = wallet.get_private_context(slate_id.as_bytes(), participant_id) {
+				contexts.push(StoredContext {
+					slate_id,
+					participant_id,
+					context: GrinContext::from(&context),
+				});
+			}
+		}
+	}
+
+	let mut summary = WalletDataSummary::default();
+	for slate_id in &slate_ids {
+		let uuid = slate_id.to_string();
+		if let Some(tx) = wallet.get_stored_tx(&uuid)? {
+			let tx_hex = to_hex(ser::ser_vec(&tx, ser::ProtocolVersion(1))?);
+			let path = dir.join(SAVED_TXS_DIR).join(format!("{}.grintx", uuid));
+			File::create(path)?.write_all(tx_hex.as_bytes())?;
+			summary.stored_txs += 1;
+		}
+		if let Some(proof) = wallet.get_stored_tx_proof(&uuid)? {
+			let path = dir.join(SAVED_PROOFS_DIR).join(format!("{}.proof", uuid));
+			write_json(&path, &proof)?;
+			summary.proofs += 1;
+		}
+	}
+
+	let entries: Vec<GrinTxLogEntry> = txs.iter().map(|t| t.into()).collect();
+	write_json(&dir.join(ACCT_PATHS_FILE), &accounts)?;
+	write_json(&dir.join(OUTPUTS_FILE), &outputs)?;
+	write_json(&dir.join(TX_LOG_FILE), &entries)?;
+	write_json(&dir.join(CONTEXTS_FILE), &contexts)?;
+
+	summary.accounts = accounts.len();
+	summary.outputs = outputs.len();
+	summary.txs = entries.len();
+	summary.contexts = contexts.len();
+	Ok(summary)
+}
+
+/// Add the records in `dir` the wallet doesn't have yet. Transactions get
+/// new ids, as the wallet may already use the exported ones, and the
+/// derivation of new keys continues past the imported outputs
+pub fn import_data<T: ?Sized, C, K>(wallet: &mut T, dir: &Path) -> Result<WalletDataSummary, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let accounts: Vec<AcctPathMapping> = read_json(&dir.join(ACCT_PATHS_FILE))?;
+	let outputs: Vec<GrinOutputData> = read_json(&dir.join(OUTPUTS_FILE))?;
+	let entries: Vec<GrinTxLogEntry> = read_json(&dir.join(TX_LOG_FILE))?;
+	let contexts_path = dir.join(CONTEXTS_FILE);
+	let contexts: Vec<StoredContext> = match contexts_path.exists() {
+		true => read_json(&contexts_path)?,
+		false => vec![],
+	};
+	let mut summary = WalletDataSummary::default();
+
+	// Everything is checked before the first write
+	let existing: Vec<AcctPathMapping> = wallet.accounts()?.collect();
+	let mut new_accounts = vec![];
+	for account in accounts {
+		if existing.iter().any(|a| a.path == account.path) {
+			summary.skipped += 1;
+		} else if existing.iter().any(|a| a.label == account.label) {
+			return Err(ErrorKind::AccountLabelAlreadyExists(account.label).into());
+		} else {
+			new_accounts.push(account);
+		}
+	}
+
+	// Both sides of a transfer between accounts have the same slate id
+	let known_txs: HashMap<(Identifier, Uuid), u32> = wallet
+		.tx_logs()?
+		.filter_map(|t| Some(((t.parent_key_id, t.tx_slate_id?), t.id)))
+		.collect();
+
+	let mut new_outputs: Vec<OutputData> = vec![];
+	for output in outputs.into_iter().map(OutputData::from) {
+		if wallet.get_output(&output.key_id, &output.mmr_index).is_ok() {
+			summary.skipped += 1;
+		} else {
+			new_outputs.push(output);
+		}
+	}
+	let mut child_indices: HashMap<Identifier, u32> = HashMap::new();
+	for output in &new_outputs {
+		let index = child_indices.entry(output.root_key_id.clone()).or_insert(0);
+		*index = (*index).max(output.n_child + 1);
+	}
+	let mut advanced = vec![];
+	for (parent_key_id, index) in child_indices {
+		if wallet.child_index(&parent_key_id)? < index {
+			advanced.push((parent_key_id, index));
+		}
+	}
+
+	let mut new_contexts: Vec<(Uuid, usize, Context)> = vec![];
+	for stored in contexts {
+		let slate_id = stored.slate_id.as_bytes();
+		if wallet
+			.get_private_context(slate_id, stored.participant_id)
+			.is_ok()
+		{
+			summary.skipped += 1;
+			continue;
+		}
+		let keychain = wallet.keychain();
+		let commits = |ids: &[(Identifier, Option<u64>, u64)]| -> Result<Vec<Commitment>, Error> {
+			let mut commits = vec![];
+			for (id, _, amount) in ids {
+				commits.push(keychain.commit(*amount, id, &SwitchCommitmentType::Regular)?);
+			}
+			Ok(commits)
+		};
+		let input_commits = commits(&stored.context.input_ids)?;
+		let output_commits = commits(&stored.context.output_ids)?;
+		let context = stored.context.into_context(input_commits, output_commits);
+		new_contexts.push((stored.slate_id, stored.participant_id, context));
+	}
+
+	let mut batch = wallet.batch()?;
+	for account in &new_accounts {
+		batch.save_acct_path(account)?;
+	}
+
+	let mut ids: HashMap<(Identifier, u32), u32> = HashMap::new();
+	let mut slate_ids = HashSet::new();
+	for entry in entries {
+		let mut tx = TxLogEntry::from(entry);
+		let key = (tx.parent_key_id.clone(), tx.id);
+		if let Some(slate_id) = tx.tx_slate_id {
+			if let Some(id) = known_txs.get(&(tx.parent_key_id.clone(), slate_id)) {
+				ids.insert(key, *id);
+				summary.skipped += 1;
+				continue;
+			}
+			slate_ids.insert(slate_id);
+		}
+		tx.id = batch.next_tx_log_id(&tx.parent_key_id)?;
+		ids.insert(key, tx.id);
+		batch.save_tx_log_entry(&tx)?;
+		summary.txs += 1;
+	}
+
+	for mut output in new_outputs {
+		output.tx_log_entry = output
+			.tx_log_entry
+			.and_then(|id| ids.get(&(output.root_key_id.clone(), id)).cloned());
+		batch.save_output(&output)?;
+		summary.outputs += 1;
+	}
+	for (parent_key_id, index) in advanced {
+		batch.save_child_index(&parent_key_id, index)?;
+	}
+	for (slate_id, participant_id, context) in &new_contexts {
+		batch.save_private_context(slate_id.as_bytes(), *participant_id, context)?;
+	}
+
+	for slate_id in slate_ids {
+		let uuid = slate_id.to_string();
+		let path = dir.join(SAVED_TXS_DIR).join(format!("{}.grintx", uuid));
+		if path.exists() {
+			let tx_hex = fs::read_to_string(&path)?;
+			let tx_bin = from_hex(tx_hex.trim().to_owned()).map_err(|e| invalid(&path, e))?;
+			let tx: Transaction = ser::deserialize(&mut &tx_bin[..], ser::ProtocolVersion(1))
+				.map_err(|e| invalid(&path, e))?;
+			batch.store_tx(&uuid, &tx)?;
+			summary.stored_txs += 1;
+		}
+		let path = dir.join(SAVED_PROOFS_DIR).join(format!("{}.proof", uuid));
+		if path.exists() {
+			let proof: TxProof = read_json(&path)?;
+			batch.store_tx_proof(&uuid, &proof)?;
+			summary.proofs += 1;
+		}
+	}
+	batch.commit()?;
+
+	summary.accounts = new_accounts.len();
+	summary.contexts = new_contexts.len();
+	Ok(summary)
+}
+
+fn write_json<S: Serialize>(path: &Path, value: &S) -> Result<(), Error> {
+	let mut file = File::create(path)?;
+	file.write_all(serde_json::to_string_pretty(value)?.as_bytes())?;
+	file.sync_all()?;
+	Ok(())
+}
+
+fn read_json<D: DeserializeOwned>(path: &Path) -> Result<D, Error> {
+	let data = fs::read_to_string(path).map_err(|e| invalid(path, e))?;
+	Ok(serde_json::from_str(&data).map_err(|e| invalid(path, e))?)
+}
+
+fn invalid<E: Display>(path: &Path, e: E) -> ErrorKind {
+	ErrorKind::InvalidWalletData(path.display().to_string(), e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::wallet::api::{Foreign, Owner};
+	use crate::wallet::testing::{create_wallet, LoopbackNetwork, MockChain};
+	use grin_util::ZeroingString;
+	use std::env;
+	use uuid::Uuid;
+
+	#[test]
+	fn moves_outputs_and_history_to_a_wallet_with_the_same_seed() {
+		let chain = MockChain::new();
+		let network = LoopbackNetwork::new();
+		let container = create_wallet("export", &chain, &network).unwrap();
+		let owner = Owner::new(container.clone());
+		let foreign = Foreign::new(container);
+		for _ in 0..3 {
+			chain.mine_to(&foreign).unwrap();
+		}
+		let (_, info) = owner.retrieve_summary_info(true, 1).unwrap();
+
+		let dir = env::temp_dir().join(format!("wallet713-data-{}", Uuid::new_v4()));
+		let dir = dir.to_string_lossy().into_owned();
+		let exported = owner.export_data(&dir).unwrap();
+		assert!(exported.outputs > 0);
+		assert!(owner.export_data(&dir).is_err());
+
+		let copy = Owner::new(create_wallet("import", &chain, &network).unwrap());
+		let seed = owner.get_seed().unwrap();
+		copy.set_seed(Some(seed), ZeroingString::from("password"), true)
+			.unwrap();
+		let imported = copy.import_data(&dir).unwrap();
+		assert_eq!(imported.outputs, exported.outputs);
+		assert_eq!(imported.txs, exported.txs);
+		let (_, copied) = copy.retrieve_summary_info(true, 1).unwrap();
+		assert_eq!(copied.total, info.total);
+
+		let again = copy.import_data(&dir).unwrap();
+		assert_eq!(again.outputs + again.txs, 0);
+	}
+}
@@ -13,6 +13,8 @@
 // limitations under the License.
 
 pub mod keys;
+pub mod migration;
+pub mod multisig;
 pub mod restore;
 pub mod selection;
 pub mod tx;
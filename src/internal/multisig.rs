@@ -0,0 +1,797 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Outputs shared by two wallets, with a blinding factor that is the sum of
+//! a partial key of each. Neither wallet knows the whole blinding factor, so
+//! the range proof and every kernel signature take both, over several rounds.
+//!
+//! Funding, initiated by the wallet paying for the output:
+//! 1. Funder: selects inputs and change, commits to the value with its key
+//! 2. Cosigner: commits with its key, computes its `T1` and `T2`, signs
+//! 3. Funder: sums `T1` and `T2`, computes its share of `tau_x`, signs
+//! 4. Cosigner: completes the range proof, adds the output and posts
+//!
+//! Spending, initiated by the wallet that gets the value:
+//! 1. Spender: spends the output into a new output of its own
+//! 2. Other wallet: signs for its partial key
+//! 3. Spender: signs and posts
+//!
+//! The common nonce of the range proof comes from a Diffie-Hellman exchange
+//! over keys derived from the seed and the output id, so holding the
+//! exchange files isn't enough to rewind the proof. The partial signature of
+//! the other wallet is checked before it is added to ours.
+//!
+//! Recovery: the partial key is derived from the seed, the account and the
+//! output id, but the output can't be found on the chain, since its proof
+//! doesn't rewind with our keys alone. The `MultisigOutput` record in the
+//! wallet database is what lets the output be spent, so it has to be kept
+//! with a wallet backup. After a restore from seed, restoring such a backup
+//! brings the record back and `multisig spend` works again with the same
+//! other wallet.
+
+use super::{keys, selection, tx, updater};
+use crate::wallet::types::{
+	Context, MultisigKind, MultisigOutput, MultisigSlate, MultisigStatus, NodeClient, OutputData,
+	OutputStatus, PublicKey, SecretKey, Slate, SlateVersion, TxLogEntry, TxLogEntryType,
+	VersionedSlate, WalletBackend,
+};
+use crate::wallet::ErrorKind;
+use blake2_rfc::blake2b::blake2b;
+use failure::Error;
+use grin_core::core::amount_to_hr_string;
+use grin_core::core::{Input, Output, OutputFeatures};
+use grin_core::libtx::build;
+use grin_core::libtx::proof::ProofBuilder;
+use grin_core::libtx::tx_fee;
+use grin_keychain::{BlindSum, BlindingFactor, Keychain};
+use grin_util::secp::constants::SECRET_KEY_SIZE;
+use grin_util::secp::pedersen::{Commitment, RangeProof};
+use grin_util::secp::Secp256k1;
+use grin_util::{from_hex, to_hex};
+use uuid::Uuid;
+
+/// Address recorded on the log entries of multisig transactions
+const MULTISIG_ADDRESS: &str = "multisig";
+/// Key of the hash turning the Diffie-Hellman secret into the common nonce
+const COMMON_NONCE_DOMAIN: &[u8] = b"wallet713-multisig-common-nonce";
+
+/// Start funding a multisig output of `amount` from the active account
+pub fn init_fund<T: ?Sized, C, K>(
+	w: &mut T,
+	amount: u64,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	num_change_outputs: usize,
+	selection_strategy_is_use_all: bool,
+) -> Result<MultisigSlate, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let parent_key_id = w.get_parent_key_id();
+	updater::refresh_outputs(w, &parent_key_id, false)?;
+	let mut slate = tx::new_tx_slate(w, amount, 2)?;
+	let min_output_value = w.min_output_value();
+	let fee_base = w.fee_base();
+	let mut context = selection::build_send_tx(
+		w,
+		&mut slate,
+		minimum_confirmations,
+		max_outputs,
+		num_change_outputs,
+		selection_strategy_is_use_all,
+		parent_key_id.clone(),
+		None,
+		false,
+		min_output_value,
+		fee_base,
+	)?;
+
+	// The output is only added once its range proof is done, but our
+	// partial key already counts towards our excess
+	let (key, _) = keys::multisig_keys(w.keychain(), &parent_key_id, &slate.id)?;
+	let exchange_key = keys::multisig_exchange_key(w.keychain(), &parent_key_id, &slate.id)?;
+	let (commit, exchange_key) = {
+		let secp = w.keychain().secp();
+		context.sec_key.add_assign(secp, &key)?;
+		let exchange_key = PublicKey::from_secret_key(secp, &exchange_key)?;
+		(
+			secp.commit(amount, key)?,
+			public_key_to_hex(secp, &exchange_key),
+		)
+	};
+
+	let entropy = w.entropy();
+	slate.fill_round_1(
+		w.signer(),
+		&mut context.sec_key,
+		&context.sec_nonce,
+		0,
+		None,
+		&*entropy,
+	)?;
+
+	let now = w.clock().now();
+	{
+		let mut batch = w.batch()?;
+		batch.save_private_context(slate.id.as_bytes(), 0, &context)?;
+		batch.save_multisig_output(&MultisigOutput {
+			id: slate.id,
+			parent_key_id: parent_key_id.clone(),
+			participant_id: 0,
+			value: amount,
+			partial_commits: vec![commit.clone()],
+			commit: None,
+			common_nonce: String::new(),
+			status: MultisigStatus::Building,
+			created: now,
+			spend_slate_id: None,
+		})?;
+		batch.commit()?;
+	}
+	selection::lock_tx_context(w, &slate, Some(MULTISIG_ADDRESS.to_owned()), &context)?;
+
+	Ok(MultisigSlate {
+		id: slate.id,
+		kind: MultisigKind::Fund,
+		round: 1,
+		slate: VersionedSlate::into_version(slate, SlateVersion::V2),
+		commits: vec![commit],
+		exchange_key: Some(exchange_key),
+		t_one: None,
+		t_two: None,
+		tau_x: None,
+	})
+}
+
+/// Start spending the funded multisig output `id` into a new output of the
+/// account it belongs to. The fee comes out of its value
+pub fn init_spend<T: ?Sized, C, K>(w: &mut T, id: &Uuid) -> Result<MultisigSlate, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let mut record = funded_output(w, id)?;
+	let commit = record.commit.clone().ok_or(ErrorKind::MultisigInvalid(
+		"output without a commitment".to_owned(),
+	))?;
+	let parent_key_id = record.parent_key_id.clone();
+	let fee = tx_fee(1, 1, 1, w.fee_base());
+	if fee >= record.value {
+		return Err(ErrorKind::NotEnoughFunds {
+			available: record.value,
+			available_disp: amount_to_hr_string(record.value, false),
+			needed: fee,
+			needed_disp: amount_to_hr_string(fee, false),
+		})?;
+	}
+	let amount = record.value - fee;
+
+	let mut slate = tx::new_tx_slate(w, amount, 2)?;
+	slate.fee = fee;
+	slate.tx = slate
+		.tx
+		.clone()
+		.with_input(Input::new(OutputFeatures::Plain, commit));
+	let key_id = keys::next_available_key(w)?;
+	let (key, _) = keys::multisig_keys(w.keychain(), &parent_key_id, id)?;
+	let sec_key = {
+		let keychain = w.keychain();
+		let blinding = slate.add_transaction_elements(
+			keychain,
+			&ProofBuilder::new(keychain),
+			vec![build::output(amount, key_id.clone())],
+		)?;
+		keychain
+			.blind_sum(
+				&BlindSum::new()
+					.add_blinding_factor(blinding)
+					.sub_blinding_factor(BlindingFactor::from_secret_key(key)),
+			)?
+			.secret_key(keychain.secp())?
+	};
+
+	let mut context = Context::new(w.signer(), sec_key, &parent_key_id, 0)?;
+	context.amount = amount;
+	context.fee = fee;
+	context.add_output(&key_id, &None, amount);
+	let entropy = w.entropy();
+	slate.fill_round_1(
+		w.signer(),
+		&mut context.sec_key,
+		&context.sec_nonce,
+		0,
+		None,
+		&*entropy,
+	)?;
+
+	let output_commit = w.calc_commit_for_cache(amount, &key_id)?;
+	let now = w.clock().now();
+	let mut batch = w.batch()?;
+	let log_id = batch.next_tx_log_id(&parent_key_id)?;
+	let mut t = TxLogEntry::new(
+		parent_key_id.clone(),
+		TxLogEntryType::TxReceived,
+		log_id,
+		now,
+	);
+	t.tx_slate_id = Some(slate.id);
+	t.address = Some(MULTISIG_ADDRESS.to_owned());
+	t.amount_credited = amount;
+	t.num_outputs = 1;
+	t.fee = Some(fee);
+	t.stored_tx = Some(format!("{}.grintx", slate.id));
+	batch.save_output(&OutputData {
+		root_key_id: parent_key_id.clone(),
+		key_id: key_id.clone(),
+		n_child: key_id.to_path().last_path_index(),
+		commit: output_commit,
+		mmr_index: None,
+		value: amount,
+		status: OutputStatus::Unconfirmed,
+		height: slate.height,
+		lock_height: 0,
+		is_coinbase: false,
+		tx_log_entry: Some(log_id),
+	})?;
+	batch.save_tx_log_entry(&t)?;
+	batch.save_private_context(slate.id.as_bytes(), 0, &context)?;
+	record.status = MultisigStatus::Spending;
+	record.spend_slate_id = Some(slate.id);
+	batch.save_multisig_output(&record)?;
+	batch.commit()?;
+
+	Ok(MultisigSlate {
+		id: *id,
+		kind: MultisigKind::Spend,
+		round: 1,
+		slate: VersionedSlate::into_version(slate, SlateVersion::V2),
+		commits: record.partial_commits.clone(),
+		exchange_key: None,
+		t_one: None,
+		t_two: None,
+		tau_x: None,
+	})
+}
+
+/// Take the next round of a multisig exchange. Returns the message for the
+/// other wallet, or nothing once our side of the exchange is complete
+pub fn join<T: ?Sized, C, K>(w: &mut T, msg: &MultisigSlate) -> Result<Option<MultisigSlate>, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let slate: Slate = msg.slate.clone().into();
+	if slate.num_participants != 2 {
+		return Err(ErrorKind::MultisigInvalid(
+			"slate is not for two participants".to_owned(),
+		))?;
+	}
+	let record = w.get_multisig_output(&msg.id)?;
+	match (&msg.kind, msg.round, record) {
+		(MultisigKind::Fund, 1, None) => fund_round_2(w, msg, slate).map(Some),
+		(MultisigKind::Fund, 2, Some(r)) if r.participant_id == 0 => {
+			fund_round_3(w, msg, slate, r).map(Some)
+		}
+		(MultisigKind::Fund, 3, Some(r)) if r.participant_id == 1 => {
+			fund_round_4(w, msg, slate, r).map(Some)
+		}
+		(MultisigKind::Fund, 4, Some(r)) if r.participant_id == 0 => {
+			fund_complete(w, slate, r).map(|_| None)
+		}
+		(MultisigKind::Spend, 1, Some(r)) => spend_round_2(w, msg, slate, r).map(Some),
+		(MultisigKind::Spend, 2, Some(r)) => spend_round_3(w, msg, slate, r).map(Some),
+		(MultisigKind::Spend, 3, Some(r)) => spend_complete(w, slate, r).map(|_| None),
+		_ => Err(ErrorKind::MultisigRound(msg.id.to_string(), msg.round))?,
+	}
+}
+
+/// Cosigner: commit with our key, compute our `T1` and `T2` and sign
+fn fund_round_2<T: ?Sized, C, K>(
+	w: &mut T,
+	msg: &MultisigSlate,
+	mut slate: Slate,
+) -> Result<MultisigSlate, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if msg.commits.len() != 1 || slate.participant_data.len() != 1 {
+		return Err(ErrorKind::MultisigInvalid(
+			"expected the funder's data only".to_owned(),
+		))?;
+	}
+	let parent_key_id = w.get_parent_key_id();
+	let (key, nonce) = keys::multisig_keys(w.keychain(), &parent_key_id, &msg.id)?;
+	let exchange_key = keys::multisig_exchange_key(w.keychain(), &parent_key_id, &msg.id)?;
+	let (commits, commit, common_nonce, t_one, t_two) = {
+		let secp = w.keychain().secp();
+		let their_key = parse_public_key(secp, msg.exchange_key.as_ref())?;
+		let common_nonce = common_nonce(secp, &their_key, &exchange_key, &msg.id)?;
+		let mut commits = msg.commits.clone();
+		commits.push(secp.commit(0, key.clone())?);
+		let commit = secp.commit_sum(commits.clone(), vec![])?;
+		let mut t_one = PublicKey::new();
+		let mut t_two = PublicKey::new();
+		secp.bullet_proof_multisig(
+			slate.amount,
+			key.clone(),
+			common_nonce.clone(),
+			None,
+			None,
+			None,
+			Some(&mut t_one),
+			Some(&mut t_two),
+			vec![commit.clone()],
+			Some(&nonce),
+			1,
+		);
+		(commits, commit, common_nonce, t_one, t_two)
+	};
+
+	let mut context = Context::new(w.signer(), key, &parent_key_id, 1)?;
+	let entropy = w.entropy();
+	slate.fill_round_1(
+		w.signer(),
+		&mut context.sec_key,
+		&context.sec_nonce,
+		1,
+		None,
+		&*entropy,
+	)?;
+	slate.fill_round_2(w.signer(), &context.sec_key, &context.sec_nonce, 1)?;
+
+	let now = w.clock().now();
+	let mut batch = w.batch()?;
+	batch.save_multisig_output(&MultisigOutput {
+		id: msg.id,
+		parent_key_id,
+		participant_id: 1,
+		value: slate.amount,
+		partial_commits: commits.clone(),
+		commit: Some(commit),
+		common_nonce: to_hex(common_nonce.0.to_vec()),
+		status: MultisigStatus::Building,
+		created: now,
+		spend_slate_id: None,
+	})?;
+	batch.commit()?;
+
+	let secp = w.keychain().secp();
+	let exchange_key = PublicKey::from_secret_key(secp, &exchange_key)?;
+	Ok(MultisigSlate {
+		id: msg.id,
+		kind: MultisigKind::Fund,
+		round: 2,
+		slate: VersionedSlate::into_version(slate, SlateVersion::V2),
+		commits,
+		exchange_key: Some(public_key_to_hex(secp, &exchange_key)),
+		t_one: Some(public_key_to_hex(secp, &t_one)),
+		t_two: Some(public_key_to_hex(secp, &t_two)),
+		tau_x: None,
+	})
+}
+
+/// Funder: sum `T1` and `T2`, compute our share of `tau_x` and sign
+fn fund_round_3<T: ?Sized, C, K>(
+	w: &mut T,
+	msg: &MultisigSlate,
+	mut slate: Slate,
+	mut record: MultisigOutput,
+) -> Result<MultisigSlate, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if record.status != MultisigStatus::Building
+		|| msg.commits.len() != 2
+		|| msg.commits[0] != record.partial_commits[0]
+	{
+		return Err(ErrorKind::MultisigRound(msg.id.to_string(), msg.round))?;
+	}
+	verify_their_signature(w.keychain().secp(), &slate, 1)?;
+	let (key, nonce) = keys::multisig_keys(w.keychain(), &record.parent_key_id, &msg.id)?;
+	let exchange_key = keys::multisig_exchange_key(w.keychain(), &record.parent_key_id, &msg.id)?;
+	let (commit, common_nonce, t_one, t_two, tau_x) = {
+		let secp = w.keychain().secp();
+		let commit = secp.commit_sum(msg.commits.clone(), vec![])?;
+		let their_key = parse_public_key(secp, msg.exchange_key.as_ref())?;
+		let common_nonce = common_nonce(secp, &their_key, &exchange_key, &msg.id)?;
+		let mut t_one = PublicKey::new();
+		let mut t_two = PublicKey::new();
+		secp.bullet_proof_multisig(
+			record.value,
+			key.clone(),
+			common_nonce.clone(),
+			None,
+			None,
+			None,
+			Some(&mut t_one),
+			Some(&mut t_two),
+			vec![commit.clone()],
+			Some(&nonce),
+			1,
+		);
+		let their_t_one = parse_public_key(secp, msg.t_one.as_ref())?;
+		let their_t_two = parse_public_key(secp, msg.t_two.as_ref())?;
+		let mut t_one = PublicKey::from_combination(secp, vec![&t_one, &their_t_one])?;
+		let mut t_two = PublicKey::from_combination(secp, vec![&t_two, &their_t_two])?;
+		let mut tau_x = SecretKey([0; SECRET_KEY_SIZE]);
+		secp.bullet_proof_multisig(
+			record.value,
+			key,
+			common_nonce.clone(),
+			None,
+			None,
+			Some(&mut tau_x),
+			Some(&mut t_one),
+			Some(&mut t_two),
+			vec![commit.clone()],
+			Some(&nonce),
+			2,
+		);
+		(commit, common_nonce, t_one, t_two, tau_x)
+	};
+
+	let context = w.get_private_context(slate.id.as_bytes(), 0)?;
+	slate.fill_round_2(w.signer(), &context.sec_key, &context.sec_nonce, 0)?;
+
+	record.partial_commits = msg.commits.clone();
+	record.commit = Some(commit);
+	record.common_nonce = to_hex(common_nonce.0.to_vec());
+	let mut batch = w.batch()?;
+	batch.save_multisig_output(&record)?;
+	batch.delete_private_context(slate.id.as_bytes(), 0)?;
+	batch.commit()?;
+
+	let secp = w.keychain().secp();
+	Ok(MultisigSlate {
+		id: msg.id,
+		kind: MultisigKind::Fund,
+		round: 3,
+		slate: VersionedSlate::into_version(slate, SlateVersion::V2),
+		commits: msg.commits.clone(),
+		exchange_key: None,
+		t_one: Some(public_key_to_hex(secp, &t_one)),
+		t_two: Some(public_key_to_hex(secp, &t_two)),
+		tau_x: Some(to_hex(tau_x.0.to_vec())),
+	})
+}
+
+/// Cosigner: add our share of `tau_x`, complete the range proof and the
+/// transaction. The caller posts it
+fn fund_round_4<T: ?Sized, C, K>(
+	w: &mut T,
+	msg: &MultisigSlate,
+	mut slate: Slate,
+	mut record: MultisigOutput,
+) -> Result<MultisigSlate, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let commit = match (&record.status, record.commit.clone()) {
+		(MultisigStatus::Building, Some(commit)) => commit,
+		_ => return Err(ErrorKind::MultisigRound(msg.id.to_string(), msg.round))?,
+	};
+	verify_their_signature(w.keychain().secp(), &slate, 0)?;
+	let (key, nonce) = keys::multisig_keys(w.keychain(), &record.parent_key_id, &msg.id)?;
+	let proof = {
+		let secp = w.keychain().secp();
+		let common_nonce = parse_secret_key(secp, &record.common_nonce)?;
+		let mut t_one = parse_public_key(secp, msg.t_one.as_ref())?;
+		let mut t_two = parse_public_key(secp, msg.t_two.as_ref())?;
+		let their_tau_x = parse_secret_key(
+			secp,
+			msg.tau_x
+				.as_ref()
+				.ok_or(ErrorKind::MultisigInvalid("missing tau_x".to_owned()))?,
+		)?;
+		let mut tau_x = SecretKey([0; SECRET_KEY_SIZE]);
+		secp.bullet_proof_multisig(
+			record.value,
+			key.clone(),
+			common_nonce.clone(),
+			None,
+			None,
+			Some(&mut tau_x),
+			Some(&mut t_one),
+			Some(&mut t_two),
+			vec![commit.clone()],
+			Some(&nonce),
+			2,
+		);
+		tau_x.add_assign(secp, &their_tau_x)?;
+		let proof = secp
+			.bullet_proof_multisig(
+				record.value,
+				key,
+				common_nonce,
+				None,
+				None,
+				Some(&mut tau_x),
+				Some(&mut t_one),
+				Some(&mut t_two),
+				vec![commit.clone()],
+				Some(&nonce),
+				0,
+			)
+			.ok_or(ErrorKind::MultisigInvalid("range proof failed".to_owned()))?;
+		verify_proof(secp, commit.clone(), proof.clone())?;
+		proof
+	};
+
+	slate.tx = slate.tx.clone().with_output(Output {
+		features: OutputFeatures::Plain,
+		commit,
+		proof,
+	});
+	slate.finalize(w.keychain())?;
+
+	record.status = MultisigStatus::Funded;
+	let mut batch = w.batch()?;
+	batch.save_multisig_output(&record)?;
+	batch.commit()?;
+
+	Ok(MultisigSlate {
+		id: msg.id,
+		kind: MultisigKind::Fund,
+		round: 4,
+		slate: VersionedSlate::into_version(slate, SlateVersion::V2),
+		commits: msg.commits.clone(),
+		exchange_key: None,
+		t_one: None,
+		t_two: None,
+		tau_x: None,
+	})
+}
+
+/// Funder: the cosigner posted the transaction
+fn fund_complete<T: ?Sized, C, K>(
+	w: &mut T,
+	slate: Slate,
+	mut record: MultisigOutput,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let commit = record
+		.commit
+		.clone()
+		.ok_or(ErrorKind::MultisigRound(record.id.to_string(), 4))?;
+	if !slate.tx.outputs().iter().any(|o| o.commit == commit) {
+		return Err(ErrorKind::MultisigInvalid(
+			"output missing from the transaction".to_owned(),
+		))?;
+	}
+	record.status = MultisigStatus::Funded;
+	let mut batch = w.batch()?;
+	batch.save_multisig_output(&record)?;
+	batch.store_tx(&slate.id.to_string(), &slate.tx)?;
+	batch.commit()?;
+	Ok(())
+}
+
+/// Other wallet: sign the spend for our partial key
+fn spend_round_2<T: ?Sized, C, K>(
+	w: &mut T,
+	msg: &MultisigSlate,
+	mut slate: Slate,
+	mut record: MultisigOutput,
+) -> Result<MultisigSlate, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if record.status != MultisigStatus::Funded {
+		return Err(ErrorKind::MultisigStatus(
+			record.id.to_string(),
+			record.status.to_string(),
+		))?;
+	}
+	let commit = record.commit.clone();
+	if slate.participant_data.len() != 1
+		|| slate.tx.inputs().len() != 1
+		|| commit.as_ref() != slate.tx.inputs().first().map(|i| &i.commit)
+	{
+		return Err(ErrorKind::MultisigInvalid(
+			"slate doesn't spend the output".to_owned(),
+		))?;
+	}
+
+	let (key, _) = keys::multisig_keys(w.keychain(), &record.parent_key_id, &msg.id)?;
+	let sec_key = {
+		let keychain = w.keychain();
+		keychain
+			.blind_sum(&BlindSum::new().sub_blinding_factor(BlindingFactor::from_secret_key(key)))?
+			.secret_key(keychain.secp())?
+	};
+	let mut context = Context::new(w.signer(), sec_key, &record.parent_key_id, 1)?;
+	let entropy = w.entropy();
+	slate.fill_round_1(
+		w.signer(),
+		&mut context.sec_key,
+		&context.sec_nonce,
+		1,
+		None,
+		&*entropy,
+	)?;
+	slate.fill_round_2(w.signer(), &context.sec_key, &context.sec_nonce, 1)?;
+
+	record.status = MultisigStatus::Spending;
+	record.spend_slate_id = Some(slate.id);
+	let mut batch = w.batch()?;
+	batch.save_multisig_output(&record)?;
+	batch.commit()?;
+
+	Ok(MultisigSlate {
+		id: msg.id,
+		kind: MultisigKind::Spend,
+		round: 2,
+		slate: VersionedSlate::into_version(slate, SlateVersion::V2),
+		commits: msg.commits.clone(),
+		exchange_key: None,
+		t_one: None,
+		t_two: None,
+		tau_x: None,
+	})
+}
+
+/// Spender: sign and complete the transaction. The caller posts it
+fn spend_round_3<T: ?Sized, C, K>(
+	w: &mut T,
+	msg: &MultisigSlate,
+	mut slate: Slate,
+	mut record: MultisigOutput,
+) -> Result<MultisigSlate, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if record.status != MultisigStatus::Spending || record.spend_slate_id != Some(slate.id) {
+		return Err(ErrorKind::MultisigRound(msg.id.to_string(), msg.round))?;
+	}
+	verify_their_signature(w.keychain().secp(), &slate, 1)?;
+	let context = w.get_private_context(slate.id.as_bytes(), 0)?;
+	tx::complete_tx(w, &mut slate, 0, &context)?;
+
+	record.status = MultisigStatus::Spent;
+	let mut batch = w.batch()?;
+	batch.save_multisig_output(&record)?;
+	batch.store_tx(&slate.id.to_string(), &slate.tx)?;
+	batch.delete_private_context(slate.id.as_bytes(), 0)?;
+	batch.commit()?;
+
+	Ok(MultisigSlate {
+		id: msg.id,
+		kind: MultisigKind::Spend,
+		round: 3,
+		slate: VersionedSlate::into_version(slate, SlateVersion::V2),
+		commits: msg.commits.clone(),
+		exchange_key: None,
+		t_one: None,
+		t_two: None,
+		tau_x: None,
+	})
+}
+
+/// Other wallet: the spender posted the transaction
+fn spend_complete<T: ?Sized, C, K>(
+	w: &mut T,
+	slate: Slate,
+	mut record: MultisigOutput,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if record.spend_slate_id != Some(slate.id) {
+		return Err(ErrorKind::MultisigRound(record.id.to_string(), 3))?;
+	}
+	record.status = MultisigStatus::Spent;
+	let mut batch = w.batch()?;
+	batch.save_multisig_output(&record)?;
+	batch.commit()?;
+	Ok(())
+}
+
+fn funded_output<T: ?Sized, C, K>(w: &mut T, id: &Uuid) -> Result<MultisigOutput, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let record = w
+		.get_multisig_output(id)?
+		.ok_or(ErrorKind::MultisigNotFound(id.to_string()))?;
+	if record.status != MultisigStatus::Funded {
+		return Err(ErrorKind::MultisigStatus(
+			id.to_string(),
+			record.status.to_string(),
+		))?;
+	}
+	Ok(record)
+}
+
+/// Common nonce of the range proof, hashed from the Diffie-Hellman secret of
+/// our exchange key and theirs. Both wallets get the same one
+fn common_nonce(
+	secp: &Secp256k1,
+	their_key: &PublicKey,
+	our_key: &SecretKey,
+	id: &Uuid,
+) -> Result<SecretKey, Error> {
+	let mut shared = their_key.clone();
+	shared.mul_assign(secp, our_key)?;
+	let mut data = shared.serialize_vec(secp, true).to_vec();
+	data.extend_from_slice(id.as_bytes());
+	let hash = blake2b(SECRET_KEY_SIZE, COMMON_NONCE_DOMAIN, &data);
+	Ok(SecretKey::from_slice(secp, hash.as_bytes())?)
+}
+
+/// Check the partial signature of the other wallet, at `participant_id`,
+/// before it is aggregated with ours
+fn verify_their_signature(
+	secp: &Secp256k1,
+	slate: &Slate,
+	participant_id: usize,
+) -> Result<(), Error> {
+	let signed = slate
+		.participant_data
+		.get(participant_id)
+		.map(|p| p.is_complete())
+		.unwrap_or(false);
+	if !signed || slate.verify_part_sigs(secp).is_err() {
+		return Err(ErrorKind::MultisigInvalid(
+			"partial signature of the other wallet doesn't verify".to_owned(),
+		))?;
+	}
+	Ok(())
+}
+
+fn verify_proof(secp: &Secp256k1, commit: Commitment, proof: RangeProof) -> Result<(), Error> {
+	secp.verify_bullet_proof(commit, proof, None)
+		.map_err(|_| ErrorKind::MultisigInvalid("range proof doesn't verify".to_owned()))?;
+	Ok(())
+}
+
+fn parse_secret_key(secp: &Secp256k1, hex: &str) -> Result<SecretKey, Error> {
+	let bytes = from_hex(hex.to_owned())
+		.map_err(|_| ErrorKind::MultisigInvalid("secret key is not hex".to_owned()))?;
+	Ok(SecretKey::from_slice(secp, &bytes)?)
+}
+
+fn parse_public_key(secp: &Secp256k1, hex: Option<&String>) -> Result<PublicKey, Error> {
+	let hex = hex.ok_or(ErrorKind::MultisigInvalid(
+		"missing range proof data".to_owned(),
+	))?;
+	let bytes = from_hex(hex.to_owned())
+		.map_err(|_| ErrorKind::MultisigInvalid("public key is not hex".to_owned()))?;
+	Ok(PublicKey::from_slice(secp, &bytes)?)
+}
+
+fn public_key_to_hex(secp: &Secp256k1, key: &PublicKey) -> String {
+	to_hex(key.serialize_vec(secp, true).to_vec())
+}
@@ -13,11 +13,12 @@
 // limitations under the License.
 //! Functions to restore a wallet's outputs from just the master seed
 
-use super::{keys, updater};
-use crate::common::ErrorKind;
+use super::updater;
+use crate::common::{Arc, ErrorKind, Mutex};
 use crate::wallet::types::{
-	NodeClient, OutputCommitMapping, OutputData, OutputStatus, TxLogEntry, TxLogEntryType,
-	WalletBackend,
+	AccountLabeler, AcctPathMapping, DiscoveredAccount, NodeClient, OutputCommitMapping,
+	OutputData, OutputStatus, ScanOptions, TxLogEntry, TxLogEntryType, WalletBackend,
+	WatchKeychain,
 };
 use failure::Error;
 use grin_core::consensus::{valid_header_version, WEEK_HEIGHT};
@@ -26,8 +27,12 @@ use grin_core::global::coinbase_maturity;
 use grin_core::libtx::proof;
 use grin_keychain::{Identifier, Keychain, SwitchCommitmentType};
 use grin_util::secp::pedersen::{Commitment, RangeProof};
+use grin_util::to_hex;
 use log::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
 use std::time::Instant;
 
 /// Utility struct for return values from below
@@ -63,49 +68,42 @@ struct RestoredTxStats {
 	pub num_outputs: usize,
 }
 
-fn identify_utxo_outputs<T, C, K>(
-	wallet: &mut T,
-	outputs: Vec<(Commitment, RangeProof, bool, u64, u64)>,
+/// Outputs of an account seen while restoring
+#[derive(Debug, Clone, Default)]
+struct FoundParent {
+	/// Highest child index in use
+	pub max_child_index: u32,
+	///
+	pub num_outputs: usize,
+	///
+	pub amount: u64,
+}
+
+/// Result of rewinding a range proof: value, key id and switch type
+type RewindResult = Option<(u64, Identifier, SwitchCommitmentType)>;
+
+/// UTXO set entry: commitment, range proof, coinbase flag, height and MMR index
+type ChainOutput = (Commitment, RangeProof, bool, u64, u64);
+
+fn identify_utxo_outputs<F>(
+	outputs: Vec<ChainOutput>,
+	rewind: &F,
 ) -> Result<Vec<OutputResult>, Error>
 where
-	T: WalletBackend<C, K>,
-	C: NodeClient,
-	K: Keychain,
+	F: Fn(&Commitment, &RangeProof, u64) -> Result<RewindResult, Error>,
 {
 	let mut wallet_outputs: Vec<OutputResult> = Vec::new();
 
-	warn!(
+	debug!(
 		"Scanning {} outputs in the current Grin utxo set",
-		outputs.len(),
+		outputs.len()
 	);
 
-	let keychain = wallet.keychain();
-	let legacy_builder = proof::LegacyProofBuilder::new(keychain);
-	let builder = proof::ProofBuilder::new(keychain);
-	let legacy_version = HeaderVersion(1);
-
 	for output in outputs.iter() {
 		let (commit, proof, is_coinbase, height, mmr_index) = output;
 		// attempt to unwind message from the RP and get a value
 		// will fail if it's not ours
-		let info = {
-			// Before HF+2wk, try legacy rewind first
-			let info_legacy =
-				if valid_header_version(height.saturating_sub(2 * WEEK_HEIGHT), legacy_version) {
-					proof::rewind(keychain.secp(), &legacy_builder, *commit, None, *proof)?
-				} else {
-					None
-				};
-
-			// If legacy didn't work, try new rewind
-			if info_legacy.is_none() {
-				proof::rewind(keychain.secp(), &builder, *commit, None, *proof)?
-			} else {
-				info_legacy
-			}
-		};
-
-		let (amount, key_id, switch) = match info {
+		let (amount, key_id, switch) = match rewind(commit, proof, *height)? {
 			Some(i) => i,
 			None => {
 				continue;
@@ -141,41 +139,187 @@ where
 	Ok(wallet_outputs)
 }
 
-fn collect_chain_outputs<T, C, K>(wallet: &mut T) -> Result<Vec<OutputResult>, Error>
+/// Walk the whole UTXO set and keep the outputs `identify` recognises.
+/// Batches are fetched from the node on the calling thread while a pool of
+/// workers runs `identify` on chunks of the previous batches
+fn scan_chain_outputs<C, F>(
+	client: &C,
+	options: ScanOptions,
+	identify: F,
+) -> Result<Vec<OutputResult>, Error>
 where
-	T: WalletBackend<C, K>,
 	C: NodeClient,
-	K: Keychain,
+	F: Fn(Vec<ChainOutput>) -> Result<Vec<OutputResult>, Error> + Send + Sync + 'static,
 {
-	let batch_size = 1000;
-	let mut start_index = 1;
-	let mut result_vec: Vec<OutputResult> = vec![];
-	loop {
-		let (highest_index, last_retrieved_index, outputs) = wallet
-			.w2n_client()
-			.get_outputs_by_pmmr_index(start_index, batch_size)?;
+	let identify = Arc::new(identify);
+	let found = Arc::new(AtomicUsize::new(0));
+	// Bounded, so fetching never runs far ahead of the workers
+	let (work_tx, work_rx) = mpsc::sync_channel::<Vec<ChainOutput>>(options.threads * 2);
+	let work_rx = Arc::new(Mutex::new(work_rx));
+
+	let workers: Vec<_> = (0..options.threads)
+		.map(|_| {
+			let identify = identify.clone();
+			let found = found.clone();
+			let work_rx = work_rx.clone();
+			thread::spawn(move || -> Result<Vec<OutputResult>, Error> {
+				let mut result_vec = Vec::new();
+				loop {
+					let chunk = match work_rx.lock().recv() {
+						Ok(chunk) => chunk,
+						Err(_) => break,
+					};
+					let mut outputs = identify(chunk)?;
+					found.fetch_add(outputs.len(), Ordering::Relaxed);
+					result_vec.append(&mut outputs);
+				}
+				Ok(result_vec)
+			})
+		})
+		.collect();
+
+	let chunk_size = (options.batch_size as usize + options.threads - 1) / options.threads;
+	let mut start_index = options.start_index;
+	let mut scanned = 0;
+	let fetched = 'fetch: loop {
+		let (highest_index, last_retrieved_index, mut outputs) =
+			match client.get_outputs_by_pmmr_index(start_index, options.batch_size) {
+				Ok(r) => r,
+				Err(e) => break Err(e),
+			};
+		scanned += outputs.len();
+
+		while !outputs.is_empty() {
+			let rest = outputs.split_off(chunk_size.min(outputs.len()));
+			// Sending only fails once every worker has stopped with an error
+			if work_tx.send(outputs).is_err() {
+				break 'fetch Ok(());
+			}
+			outputs = rest;
+		}
+
 		warn!(
-			"Checking {} outputs, up to index {}. (Highest index: {})",
-			outputs.len(),
-			highest_index,
+			"Scanned up to index {} of {} ({}%), {} outputs found so far",
 			last_retrieved_index,
+			highest_index,
+			last_retrieved_index * 100 / highest_index.max(1),
+			found.load(Ordering::Relaxed),
 		);
 
-		result_vec.append(&mut identify_utxo_outputs(wallet, outputs.clone())?);
-
 		if highest_index == last_retrieved_index {
-			break;
+			break Ok(());
 		}
 		start_index = last_retrieved_index + 1;
+	};
+	drop(work_tx);
+
+	let mut result_vec: Vec<OutputResult> = vec![];
+	for worker in workers {
+		let mut outputs = worker
+			.join()
+			.map_err(|_| ErrorKind::GenericError("Restore worker panicked".to_owned()))??;
+		result_vec.append(&mut outputs);
 	}
+	fetched?;
+
+	result_vec.sort_by_key(|o| o.mmr_index);
+	warn!(
+		"Scanned {} outputs on {} threads, {} outputs found",
+		scanned,
+		options.threads,
+		result_vec.len(),
+	);
 	Ok(result_vec)
 }
 
+fn collect_chain_outputs<T, C, K>(
+	wallet: &mut T,
+	options: ScanOptions,
+) -> Result<Vec<OutputResult>, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain + 'static,
+{
+	let client = wallet.w2n_client().clone();
+	let keychain = wallet.keychain().clone();
+	let legacy_version = HeaderVersion(1);
+
+	scan_chain_outputs(&client, options, move |outputs| {
+		let legacy_builder = proof::LegacyProofBuilder::new(&keychain);
+		let builder = proof::ProofBuilder::new(&keychain);
+
+		identify_utxo_outputs(outputs, &|commit, proof, height| {
+			// Before HF+2wk, try legacy rewind first
+			let info_legacy =
+				if valid_header_version(height.saturating_sub(2 * WEEK_HEIGHT), legacy_version) {
+					proof::rewind(keychain.secp(), &legacy_builder, *commit, None, *proof)?
+				} else {
+					None
+				};
+
+			// If legacy didn't work, try new rewind
+			if info_legacy.is_none() {
+				Ok(proof::rewind(
+					keychain.secp(),
+					&builder,
+					*commit,
+					None,
+					*proof,
+				)?)
+			} else {
+				Ok(info_legacy)
+			}
+		})
+	})
+}
+
+/// Find the outputs of a watch key in the UTXO set. Doesn't need a seed,
+/// the outputs are returned as unspent wallet outputs
+pub fn collect_watched_outputs<C>(
+	client: &C,
+	keychain: &WatchKeychain,
+	options: ScanOptions,
+) -> Result<Vec<OutputData>, Error>
+where
+	C: NodeClient,
+{
+	let keychain = keychain.clone();
+	let outputs = scan_chain_outputs(client, options, move |outputs| {
+		identify_utxo_outputs(outputs, &|commit, proof, _| {
+			Ok(proof::rewind(
+				keychain.secp(),
+				&keychain,
+				*commit,
+				None,
+				*proof,
+			)?)
+		})
+	})?;
+
+	Ok(outputs
+		.into_iter()
+		.map(|o| OutputData {
+			root_key_id: o.key_id.parent_path(),
+			key_id: o.key_id,
+			n_child: o.n_child,
+			commit: Some(to_hex(o.commit.0.to_vec())),
+			mmr_index: Some(o.mmr_index),
+			value: o.value,
+			status: OutputStatus::Unspent,
+			height: o.height,
+			lock_height: o.lock_height,
+			is_coinbase: o.is_coinbase,
+			tx_log_entry: None,
+		})
+		.collect())
+}
+
 ///
 fn restore_missing_output<T, C, K>(
 	wallet: &mut T,
 	output: OutputResult,
-	found_parents: &mut HashMap<Identifier, u32>,
+	found_parents: &mut HashMap<Identifier, FoundParent>,
 	tx_stats: &mut Option<&mut HashMap<Identifier, RestoredTxStats>>,
 ) -> Result<(), Error>
 where
@@ -184,11 +328,12 @@ where
 	K: Keychain,
 {
 	let commit = wallet.calc_commit_for_cache(output.value, &output.key_id)?;
+	let now = wallet.clock().now();
 	let mut batch = wallet.batch()?;
 
 	let parent_key_id = output.key_id.parent_path();
 	if !found_parents.contains_key(&parent_key_id) {
-		found_parents.insert(parent_key_id.clone(), 0);
+		found_parents.insert(parent_key_id.clone(), FoundParent::default());
 		if let Some(ref mut s) = tx_stats {
 			s.insert(
 				parent_key_id.clone(),
@@ -207,11 +352,11 @@ where
 			true => TxLogEntryType::ConfirmedCoinbase,
 			false => TxLogEntryType::TxReceived,
 		};
-		let mut t = TxLogEntry::new(parent_key_id.clone(), entry_type, log_id);
+		let mut t = TxLogEntry::new(parent_key_id.clone(), entry_type, log_id, now);
 		t.confirmed = true;
 		t.amount_credited = output.value;
 		t.num_outputs = 1;
-		t.update_confirmation_ts();
+		t.update_confirmation_ts(now);
 		batch.save_tx_log_entry(&t)?;
 		log_id
 	} else {
@@ -245,10 +390,10 @@ where
 		tx_log_entry: Some(log_id),
 	});
 
-	let max_child_index = found_parents.get(&parent_key_id).unwrap().clone();
-	if output.n_child >= max_child_index {
-		found_parents.insert(parent_key_id.clone(), output.n_child);
-	}
+	let found = found_parents.get_mut(&parent_key_id).unwrap();
+	found.max_child_index = found.max_child_index.max(output.n_child);
+	found.num_outputs += 1;
+	found.amount += output.value;
 
 	batch.commit()?;
 	Ok(())
@@ -270,6 +415,8 @@ where
 			Some(&parent_key_id),
 			false,
 			false,
+			None,
+			None,
 		)?;
 		if entries.len() > 0 {
 			let mut entry = entries[0].clone();
@@ -296,22 +443,26 @@ where
 /// Check / repair wallet contents
 /// assume wallet contents have been freshly updated with contents
 /// of latest block
-pub fn check_repair<T, C, K>(wallet: &mut T, delete_unconfirmed: bool) -> Result<(), Error>
+pub fn check_repair<T, C, K>(
+	wallet: &mut T,
+	delete_unconfirmed: bool,
+	options: ScanOptions,
+) -> Result<(), Error>
 where
 	T: WalletBackend<C, K>,
 	C: NodeClient,
-	K: Keychain,
+	K: Keychain + 'static,
 {
 	// First, get a definitive list of outputs we own from the chain
 	warn!("Starting wallet check.");
-	let chain_outs = collect_chain_outputs(wallet)?;
+	let chain_outs = collect_chain_outputs(wallet, options)?;
 	warn!(
 		"Identified {} wallet_outputs as belonging to this wallet",
 		chain_outs.len(),
 	);
 
 	// Now, get all outputs owned by this wallet (regardless of account)
-	let wallet_outputs = updater::retrieve_outputs(wallet, true, None, None)?;
+	let wallet_outputs = updater::retrieve_outputs(wallet, true, None, None, None)?;
 
 	let mut missing_outs = vec![];
 	let mut accidental_spend_outs = vec![];
@@ -349,7 +500,7 @@ where
 		batch.commit()?;
 	}
 
-	let mut found_parents: HashMap<Identifier, u32> = HashMap::new();
+	let mut found_parents: HashMap<Identifier, FoundParent> = HashMap::new();
 
 	// Restore missing outputs, adding transaction for it back to the log
 	for m in missing_outs.into_iter() {
@@ -396,30 +547,20 @@ where
 		}
 	}
 
-	// restore labels, account paths and child derivation indices
-	let label_base = "account";
-	let mut acct_index = 1;
-	for (path, max_child_index) in found_parents.iter() {
-		// default path already exists
-		if *path != K::derive_key_id(2, 0, 0, 0, 0) {
-			let label = format!("{}_{}", label_base, acct_index);
-			keys::set_acct_path(wallet, &label, path)?;
-			acct_index += 1;
-		}
-		let mut batch = wallet.batch()?;
-		debug!("Next child for account {} is {}", path, max_child_index + 1);
-		batch.save_child_index(path, max_child_index + 1)?;
-		batch.commit()?;
-	}
+	save_accounts(wallet, &found_parents, None, None)?;
 	Ok(())
 }
 
-/// Restore a wallet
-pub fn restore<T, C, K>(wallet: &mut T) -> Result<(), Error>
+/// Restore a wallet. Accounts found on chain are labelled by `labeler`, if set
+pub fn restore<T, C, K>(
+	wallet: &mut T,
+	options: ScanOptions,
+	labeler: Option<&AccountLabeler>,
+) -> Result<Vec<DiscoveredAccount>, Error>
 where
 	T: WalletBackend<C, K>,
 	C: NodeClient,
-	K: Keychain,
+	K: Keychain + 'static,
 {
 	// Don't proceed if wallet_data has anything in it
 	if wallet.outputs()?.next().is_some() {
@@ -430,14 +571,14 @@ where
 	let now = Instant::now();
 	warn!("Starting restore.");
 
-	let result_vec = collect_chain_outputs(wallet)?;
+	let result_vec = collect_chain_outputs(wallet, options)?;
 
 	warn!(
 		"Identified {} wallet_outputs as belonging to this wallet",
 		result_vec.len(),
 	);
 
-	let mut found_parents: HashMap<Identifier, u32> = HashMap::new();
+	let mut found_parents: HashMap<Identifier, FoundParent> = HashMap::new();
 	let mut restore_stats = HashMap::new();
 
 	// Now save what we have
@@ -450,37 +591,124 @@ where
 		)?;
 	}
 
-	// restore labels, account paths and child derivation indices
-	let label_base = "account";
-	let mut acct_index = 1;
-	for (path, max_child_index) in found_parents.iter() {
-		// default path already exists
-		if *path != K::derive_key_id(2, 0, 0, 0, 0) {
-			let label = format!("{}_{}", label_base, acct_index);
-			keys::set_acct_path(wallet, &label, path)?;
-			acct_index += 1;
+	let accounts = save_accounts(wallet, &found_parents, Some(&restore_stats), labeler)?;
+
+	let mut sec = now.elapsed().as_secs();
+	let min = sec / 60;
+	sec %= 60;
+	info!("Restored wallet in {}m{}s", min, sec);
+
+	Ok(accounts)
+}
+
+/// Index of an account in its `m/k/0` parent path
+fn account_index(path: &Identifier) -> u32 {
+	<u32>::from(path.to_path().path[0])
+}
+
+/// Store the labels, child derivation indices and restored transaction log
+/// entries of the accounts found on chain, in a single batch. Accounts are
+/// handled in the order of their index. New ones are labelled by `labeler`,
+/// or `account_<index>` by default, known ones keep their label
+fn save_accounts<T, C, K>(
+	wallet: &mut T,
+	found_parents: &HashMap<Identifier, FoundParent>,
+	restore_stats: Option<&HashMap<Identifier, RestoredTxStats>>,
+	labeler: Option<&AccountLabeler>,
+) -> Result<Vec<DiscoveredAccount>, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let mut found: Vec<_> = found_parents.iter().collect();
+	found.sort_by_key(|(path, _)| account_index(path));
+
+	let known: HashMap<Identifier, String> =
+		wallet.accounts()?.map(|m| (m.path, m.label)).collect();
+	let mut labels: HashSet<String> = known.values().cloned().collect();
+	let mut accounts = Vec::with_capacity(found.len());
+	let mut next_index = 0;
+	for (path, parent) in &found {
+		let index = account_index(path);
+		if index > next_index {
+			warn!(
+				"No outputs found for account index {} to {}, labels of later accounts keep \
+				 their index",
+				next_index,
+				index - 1
+			);
+		}
+		next_index = index + 1;
+
+		let mut account = DiscoveredAccount {
+			path: (*path).clone(),
+			index,
+			label: format!("account_{}", index),
+			existing: false,
+			num_outputs: parent.num_outputs,
+			amount: parent.amount,
+		};
+		match known.get(path) {
+			Some(label) => {
+				account.label = label.clone();
+				account.existing = true;
+			}
+			None => {
+				let chosen = labeler
+					.and_then(|l| l(&account))
+					.map(|l| l.trim().to_owned())
+					.filter(|l| !l.is_empty());
+				if let Some(label) = chosen {
+					match labels.contains(&label) {
+						true => warn!("Account label '{}' already in use", label),
+						false => account.label = label,
+					}
+				}
+				let mut suffix = 1;
+				let base = account.label.clone();
+				while labels.contains(&account.label) {
+					account.label = format!("{}_{}", base, suffix);
+					suffix += 1;
+				}
+				labels.insert(account.label.clone());
+			}
+		}
+		accounts.push(account);
+	}
+
+	let now = wallet.clock().now();
+	let mut batch = wallet.batch()?;
+	for (account, (path, parent)) in accounts.iter().zip(found.iter()) {
+		if !account.existing {
+			batch.save_acct_path(&AcctPathMapping {
+				label: account.label.clone(),
+				path: account.path.clone(),
+				notifications: None,
+				archived: false,
+			})?;
 		}
 		// restore tx log entry for non-coinbase outputs
-		if let Some(s) = restore_stats.get(path) {
-			let mut batch = wallet.batch()?;
-			let mut t = TxLogEntry::new(path.clone(), TxLogEntryType::TxReceived, s.log_id);
+		if let Some(s) = restore_stats.and_then(|s| s.get(*path)) {
+			let mut t = TxLogEntry::new(
+				account.path.clone(),
+				TxLogEntryType::TxReceived,
+				s.log_id,
+				now,
+			);
 			t.confirmed = true;
 			t.amount_credited = s.amount_credited;
 			t.num_outputs = s.num_outputs;
-			t.update_confirmation_ts();
+			t.update_confirmation_ts(now);
 			batch.save_tx_log_entry(&t)?;
-			batch.commit()?;
 		}
-		let mut batch = wallet.batch()?;
-		batch.save_child_index(path, max_child_index + 1)?;
-		debug!("Next child for account {} is {}", path, max_child_index + 1);
-		batch.commit()?;
+		debug!(
+			"Next child for account {} is {}",
+			path,
+			parent.max_child_index + 1
+		);
+		batch.save_child_index(path, parent.max_child_index + 1)?;
 	}
-
-	let mut sec = now.elapsed().as_secs();
-	let min = sec / 60;
-	sec %= 60;
-	info!("Restored wallet in {}m{}s", min, sec);
-
-	Ok(())
+	batch.commit()?;
+	Ok(accounts)
 }
@@ -41,36 +41,61 @@ pub fn build_send_tx<T: ?Sized, C, K>(
 	change_outputs: usize,
 	selection_strategy_is_use_all: bool,
 	parent_key_id: Identifier,
+	fee_parent_key_id: Option<Identifier>,
+	max_amount: bool,
+	min_output_value: u64,
+	fee_base: Option<u64>,
 ) -> Result<Context, Error>
 where
 	T: WalletBackend<C, K>,
 	C: NodeClient,
 	K: Keychain,
 {
-	let (elems, inputs, change_amounts_derivations, fee) = select_send_tx(
-		wallet,
-		slate.amount,
-		slate.height,
-		minimum_confirmations,
-		max_outputs,
-		change_outputs,
-		selection_strategy_is_use_all,
-		&parent_key_id,
-	)?;
+	let recipients = slate.num_participants - 1;
+	let (elems, inputs, change_amounts_derivations, fee) = match &fee_parent_key_id {
+		Some(fee_parent_key_id) => select_send_tx_split_fee(
+			wallet,
+			slate.amount,
+			slate.height,
+			minimum_confirmations,
+			max_outputs,
+			change_outputs,
+			recipients,
+			selection_strategy_is_use_all,
+			&parent_key_id,
+			fee_parent_key_id,
+			min_output_value,
+			fee_base,
+		)?,
+		None => select_send_tx(
+			wallet,
+			slate.amount,
+			slate.height,
+			minimum_confirmations,
+			max_outputs,
+			change_outputs,
+			recipients,
+			selection_strategy_is_use_all,
+			&parent_key_id,
+			max_amount,
+			min_output_value,
+			fee_base,
+		)?,
+	};
+	if max_amount {
+		slate.amount = inputs.iter().map(|i| i.value).sum::<u64>() - fee;
+	}
 	let keychain = wallet.keychain();
 	slate.fee = fee;
 	let blinding = slate.add_transaction_elements(keychain, &ProofBuilder::new(keychain), elems)?;
+	let sec_key = blinding.secret_key(&keychain.secp()).unwrap();
 
 	// Create our own private context
-	let mut context = Context::new(
-		keychain.secp(),
-		blinding.secret_key(&keychain.secp()).unwrap(),
-		&parent_key_id,
-		0,
-	);
+	let mut context = Context::new(wallet.signer(), sec_key, &parent_key_id, 0)?;
 
 	context.amount = slate.amount;
 	context.fee = fee;
+	context.fee_parent_key_id = fee_parent_key_id;
 
 	// Store our private identifiers for each input
 	for input in inputs {
@@ -85,6 +110,93 @@ where
 	Ok(context)
 }
 
+/// Build the sender side of a transaction spending exactly `coins` into a
+/// single output, without change. The amount is the total of the coins
+/// minus the fee, so the caller has to make sure they cover the fee
+pub fn build_sweep_tx<T: ?Sized, C, K>(
+	wallet: &mut T,
+	slate: &mut Slate,
+	coins: Vec<OutputData>,
+	parent_key_id: &Identifier,
+) -> Result<Context, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let fee = tx_fee(coins.len(), 1, 1, wallet.fee_base());
+	let total: u64 = coins.iter().map(|c| c.value).sum();
+	slate.amount = total - fee;
+	slate.fee = fee;
+	let (elems, _, _) = inputs_and_change(&coins, wallet, slate.amount, fee, 1, 0)?;
+
+	let keychain = wallet.keychain();
+	let blinding = slate.add_transaction_elements(keychain, &ProofBuilder::new(keychain), elems)?;
+	let sec_key = blinding.secret_key(&keychain.secp()).unwrap();
+	let mut context = Context::new(wallet.signer(), sec_key, parent_key_id, 0)?;
+	context.amount = slate.amount;
+	context.fee = fee;
+	for input in coins {
+		context.add_input(&input.key_id, &input.mmr_index, input.value);
+	}
+
+	Ok(context)
+}
+
+/// Build a send of `slate.amount` that spends exactly `coins`, paying change
+/// back to `change_outputs` new outputs
+pub fn build_send_tx_from_inputs<T: ?Sized, C, K>(
+	wallet: &mut T,
+	slate: &mut Slate,
+	coins: Vec<OutputData>,
+	change_outputs: usize,
+	parent_key_id: &Identifier,
+	min_output_value: u64,
+	fee_base: Option<u64>,
+) -> Result<Context, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let recipients = slate.num_participants - 1;
+	let fee = tx_fee(coins.len(), change_outputs + recipients, 1, fee_base);
+	let total: u64 = coins.iter().map(|c| c.value).sum();
+	let needed = slate.amount + fee;
+	if total < needed {
+		return Err(ErrorKind::NotEnoughFunds {
+			available: total,
+			available_disp: amount_to_hr_string(total, false),
+			needed,
+			needed_disp: amount_to_hr_string(needed, false),
+		})?;
+	}
+	let (elems, change_amounts_derivations, fee) = inputs_and_change(
+		&coins,
+		wallet,
+		slate.amount,
+		fee,
+		change_outputs,
+		min_output_value,
+	)?;
+	slate.fee = fee;
+
+	let keychain = wallet.keychain();
+	let blinding = slate.add_transaction_elements(keychain, &ProofBuilder::new(keychain), elems)?;
+	let sec_key = blinding.secret_key(&keychain.secp()).unwrap();
+	let mut context = Context::new(wallet.signer(), sec_key, parent_key_id, 0)?;
+	context.amount = slate.amount;
+	context.fee = slate.fee;
+	for input in coins {
+		context.add_input(&input.key_id, &input.mmr_index, input.value);
+	}
+	for (change_amount, id, mmr_index) in change_amounts_derivations {
+		context.add_output(&id, &mmr_index, change_amount);
+	}
+
+	Ok(context)
+}
+
 /// Locks all corresponding outputs in the context, creates
 /// change outputs and tx log entry
 pub fn lock_tx_context<T: ?Sized, C, K>(
@@ -116,33 +228,94 @@ where
 		let slate_id = slate.id;
 		let height = slate.height;
 		let parent_key_id = context.parent_key_id.clone();
+		let fee_parent_key_id = context.fee_parent_key_id.clone();
+		let now = wallet.clock().now();
+		let mut replaced = match context.replaces {
+			Some(id) => wallet
+				.tx_logs()?
+				.find(|t| t.id == id && t.parent_key_id == parent_key_id),
+			None => None,
+		};
+		// At finalize, a late locked send already has an entry, which gets
+		// the inputs and change
+		let existing = match context.late_lock {
+			Some(_) => wallet.tx_logs()?.find(|t| {
+				t.tx_slate_id == Some(slate_id)
+					&& t.parent_key_id == parent_key_id
+					&& t.tx_type == TxLogEntryType::TxSent
+			}),
+			None => None,
+		};
 		let mut batch = wallet.batch()?;
-		let log_id = batch.next_tx_log_id(&parent_key_id)?;
-		let mut t = TxLogEntry::new(parent_key_id.clone(), TxLogEntryType::TxSent, log_id);
-		t.tx_slate_id = Some(slate_id.clone());
-		t.address = address;
-		let filename = format!("{}.grintx", slate_id);
-		t.stored_tx = Some(filename);
-		t.fee = Some(slate.fee);
-		let mut amount_debited = 0;
-		t.num_inputs = lock_inputs.len();
+		let mut t = match existing {
+			Some(t) => t,
+			None => {
+				let log_id = batch.next_tx_log_id(&parent_key_id)?;
+				let mut t =
+					TxLogEntry::new(parent_key_id.clone(), TxLogEntryType::TxSent, log_id, now);
+				t.tx_slate_id = Some(slate_id.clone());
+				t.address = address;
+				let filename = format!("{}.grintx", slate_id);
+				t.stored_tx = Some(filename);
+				t.fee = Some(slate.fee);
+				t.payment_proof = slate.payment_proof.clone();
+				t.notes = context.notes.clone();
+				t.creation_height = Some(height);
+				t.replaces = context.replaces;
+				t
+			}
+		};
+
+		// The fee paying account gets its own entry, linked to the main one
+		let mut fee_t = match &fee_parent_key_id {
+			Some(fee_parent_key_id) => {
+				let fee_log_id = batch.next_tx_log_id(fee_parent_key_id)?;
+				let mut fee_t = TxLogEntry::new(
+					fee_parent_key_id.clone(),
+					TxLogEntryType::TxSent,
+					fee_log_id,
+					now,
+				);
+				fee_t.tx_slate_id = t.tx_slate_id.clone();
+				fee_t.address = t.address.clone();
+				fee_t.stored_tx = t.stored_tx.clone();
+				fee_t.fee = t.fee.take();
+				fee_t.notes = t.notes.clone();
+				fee_t.creation_height = t.creation_height;
+				fee_t.linked_parent_key_id = Some(parent_key_id.clone());
+				t.linked_parent_key_id = Some(fee_parent_key_id.clone());
+				Some(fee_t)
+			}
+			None => None,
+		};
+
 		for id in lock_inputs {
 			let mut coin = wallet.get_output(&id.0, &id.1).unwrap();
-			coin.tx_log_entry = Some(log_id);
-			amount_debited = amount_debited + coin.value;
+			let entry = if fee_parent_key_id.as_ref() == Some(&coin.root_key_id) {
+				fee_t.as_mut().unwrap()
+			} else {
+				&mut t
+			};
+			coin.tx_log_entry = Some(entry.id);
+			entry.amount_debited += coin.value;
+			entry.num_inputs += 1;
 			batch.lock_output(&mut coin)?;
 		}
 
-		t.amount_debited = amount_debited;
 		//		t.messages = messages;
 
 		// write the output representing our change
 		for (id, _, _) in &context.get_outputs() {
-			t.num_outputs += 1;
+			let entry = if fee_parent_key_id.as_ref() == Some(&id.parent_path()) {
+				fee_t.as_mut().unwrap()
+			} else {
+				&mut t
+			};
+			entry.num_outputs += 1;
 			let (commit, change_amount) = output_commits.get(&id).unwrap().clone();
-			t.amount_credited += change_amount;
+			entry.amount_credited += change_amount;
 			batch.save_output(&OutputData {
-				root_key_id: parent_key_id.clone(),
+				root_key_id: entry.parent_key_id.clone(),
 				key_id: id.clone(),
 				n_child: id.to_path().last_path_index(),
 				commit: commit.clone(),
@@ -152,10 +325,17 @@ where
 				height,
 				lock_height: 0,
 				is_coinbase: false,
-				tx_log_entry: Some(log_id),
+				tx_log_entry: Some(entry.id),
 			})?;
 		}
 		batch.save_tx_log_entry(&t)?;
+		if let Some(fee_t) = fee_t {
+			batch.save_tx_log_entry(&fee_t)?;
+		}
+		if let Some(replaced) = &mut replaced {
+			replaced.replaced_by = Some(t.id);
+			batch.save_tx_log_entry(replaced)?;
+		}
 		batch.store_tx(&slate_id.to_string(), &slate.tx)?;
 		batch.commit()?;
 	}
@@ -169,7 +349,10 @@ pub fn build_recipient_output<T: ?Sized, C, K>(
 	wallet: &mut T,
 	slate: &mut Slate,
 	parent_key_id: Identifier,
+	participant_id: usize,
+	input: Option<OutputData>,
 	address: Option<String>,
+	contact: Option<String>,
 ) -> Result<(Identifier, Context), Error>
 where
 	T: WalletBackend<C, K>,
@@ -180,36 +363,71 @@ where
 	let key_id = keys::next_available_key(wallet).unwrap();
 	let keychain = wallet.keychain().clone();
 	let key_id_inner = key_id.clone();
-	let amount = slate.amount;
+	let amount = slate.recipient_amount(participant_id);
 	let height = slate.height;
 
+	// An input of our own is spent into the output. If our fee base asks
+	// for more than the sender pays, the difference comes out of the output
+	let mut elems = vec![];
+	let mut value = amount;
+	if let Some(coin) = &input {
+		let fee = tx_fee(
+			slate.tx.inputs().len() + 1,
+			slate.tx.outputs().len() + 1,
+			slate.tx.kernels().len(),
+			wallet.fee_base(),
+		)
+		.max(slate.fee);
+		let extra_fee = fee - slate.fee;
+		if extra_fee >= amount + coin.value {
+			return Err(ErrorKind::Fee(format!(
+				"Contributing an input needs a fee of {}, more than it adds",
+				amount_to_hr_string(fee, false)
+			)))?;
+		}
+		slate.fee = fee;
+		value = amount + coin.value - extra_fee;
+		elems.push(match coin.is_coinbase {
+			true => build::coinbase_input(coin.value, coin.key_id.clone()),
+			false => build::input(coin.value, coin.key_id.clone()),
+		});
+	}
+	elems.push(build::output(value, key_id.clone()));
+
 	let slate_id = slate.id.clone();
-	let blinding = slate.add_transaction_elements(
-		&keychain,
-		&ProofBuilder::new(&keychain),
-		vec![build::output(amount, key_id.clone())],
-	)?;
+	let blinding =
+		slate.add_transaction_elements(&keychain, &ProofBuilder::new(&keychain), elems)?;
 
 	// Add blinding sum to our context
-	let mut context = Context::new(
-		keychain.secp(),
-		blinding
-			.secret_key(wallet.keychain().clone().secp())
-			.unwrap(),
-		&parent_key_id,
-		1,
-	);
+	let sec_key = blinding.secret_key(keychain.secp()).unwrap();
+	let mut context = Context::new(wallet.signer(), sec_key, &parent_key_id, participant_id)?;
 
-	context.add_output(&key_id, &None, amount);
+	context.add_output(&key_id, &None, value);
+	if let Some(coin) = &input {
+		context.add_input(&coin.key_id, &coin.mmr_index, coin.value);
+	}
 	//	let messages = Some(slate.participant_messages());
-	let commit = wallet.calc_commit_for_cache(amount, &key_id_inner)?;
+	let commit = wallet.calc_commit_for_cache(value, &key_id_inner)?;
+	let now = wallet.clock().now();
 	let mut batch = wallet.batch()?;
 	let log_id = batch.next_tx_log_id(&parent_key_id)?;
-	let mut t = TxLogEntry::new(parent_key_id.clone(), TxLogEntryType::TxReceived, log_id);
+	let mut t = TxLogEntry::new(
+		parent_key_id.clone(),
+		TxLogEntryType::TxReceived,
+		log_id,
+		now,
+	);
 	t.tx_slate_id = Some(slate_id);
 	t.address = address;
-	t.amount_credited = amount;
+	t.contact = contact;
+	t.amount_credited = value;
 	t.num_outputs = 1;
+	if let Some(mut coin) = input {
+		coin.tx_log_entry = Some(log_id);
+		t.amount_debited = coin.value;
+		t.num_inputs = 1;
+		batch.lock_output(&mut coin)?;
+	}
 	//	t.messages = messages;
 	batch.save_output(&OutputData {
 		root_key_id: parent_key_id.clone(),
@@ -217,7 +435,7 @@ where
 		mmr_index: None,
 		n_child: key_id_inner.to_path().last_path_index(),
 		commit: commit,
-		value: amount,
+		value,
 		status: OutputStatus::Unconfirmed,
 		height: height,
 		lock_height: 0,
@@ -240,8 +458,12 @@ pub fn select_send_tx<T: ?Sized, C, K, B>(
 	minimum_confirmations: u64,
 	max_outputs: usize,
 	change_outputs: usize,
+	recipients: usize,
 	selection_strategy_is_use_all: bool,
 	parent_key_id: &Identifier,
+	max_amount: bool,
+	min_output_value: u64,
+	fee_base: Option<u64>,
 ) -> Result<
 	(
 		Vec<Box<build::Append<K, B>>>,
@@ -264,18 +486,170 @@ where
 		minimum_confirmations,
 		max_outputs,
 		change_outputs,
+		recipients,
 		selection_strategy_is_use_all,
 		&parent_key_id,
+		max_amount,
+		fee_base,
 	)?;
 
 	// build transaction skeleton with inputs and change
-	let (parts, change_amounts_derivations) =
-		inputs_and_change(&coins, wallet, amount, fee, change_outputs)?;
+	let (parts, change_amounts_derivations, fee) = inputs_and_change(
+		&coins,
+		wallet,
+		amount,
+		fee,
+		change_outputs,
+		min_output_value,
+	)?;
 
 	Ok((parts, coins, change_amounts_derivations, fee))
 }
 
-/// Select outputs and calculating fee.
+/// Same as `select_send_tx`, but the fee is paid from the outputs of a
+/// separate account. Change is returned to the account it was drawn from
+pub fn select_send_tx_split_fee<T: ?Sized, C, K, B>(
+	wallet: &mut T,
+	amount: u64,
+	current_height: u64,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	change_outputs: usize,
+	recipients: usize,
+	selection_strategy_is_use_all: bool,
+	parent_key_id: &Identifier,
+	fee_parent_key_id: &Identifier,
+	min_output_value: u64,
+	fee_base: Option<u64>,
+) -> Result<
+	(
+		Vec<Box<build::Append<K, B>>>,
+		Vec<OutputData>,
+		Vec<(u64, Identifier, Option<u64>)>, // change amounts and derivations
+		u64,                                 // fee
+	),
+	Error,
+>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+	B: ProofBuild,
+{
+	let (mut coins, fee_coins, fee) = select_coins_split_fee(
+		wallet,
+		amount,
+		current_height,
+		minimum_confirmations,
+		max_outputs,
+		change_outputs,
+		recipients,
+		selection_strategy_is_use_all,
+		parent_key_id,
+		fee_parent_key_id,
+		fee_base,
+	)?;
+
+	let (mut parts, mut change_amounts_derivations, dust) =
+		inputs_and_change(&coins, wallet, amount, 0, change_outputs, min_output_value)?;
+
+	// Derive the fee change from the fee account
+	let active_parent_key_id = wallet.get_parent_key_id();
+	wallet.set_parent_key_id(fee_parent_key_id);
+	let fee_result = inputs_and_change(&fee_coins, wallet, 0, fee, 1, min_output_value);
+	wallet.set_parent_key_id(&active_parent_key_id);
+	let (fee_parts, fee_change, fee) = fee_result?;
+	let fee = fee + dust;
+
+	parts.extend(fee_parts);
+	change_amounts_derivations.extend(fee_change);
+	coins.extend(fee_coins);
+	Ok((parts, coins, change_amounts_derivations, fee))
+}
+
+/// Select outputs for the amount from `parent_key_id` and outputs for the
+/// fee from `fee_parent_key_id`. Returns both sets and the fee
+pub fn select_coins_split_fee<T: ?Sized, C, K>(
+	wallet: &mut T,
+	amount: u64,
+	current_height: u64,
+	minimum_confirmations: u64,
+	max_outputs: usize,
+	change_outputs: usize,
+	recipients: usize,
+	selection_strategy_is_use_all: bool,
+	parent_key_id: &Identifier,
+	fee_parent_key_id: &Identifier,
+	fee_base: Option<u64>,
+) -> Result<
+	(
+		Vec<OutputData>,
+		Vec<OutputData>,
+		u64, // fee
+	),
+	Error,
+>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let (_, coins) = select_coins(
+		wallet,
+		amount,
+		current_height,
+		minimum_confirmations,
+		max_outputs,
+		selection_strategy_is_use_all,
+		parent_key_id,
+	);
+	let total: u64 = coins.iter().map(|c| c.value).sum();
+	if total < amount {
+		return Err(ErrorKind::NotEnoughFunds {
+			available: total,
+			available_disp: amount_to_hr_string(total, false),
+			needed: amount,
+			needed_disp: amount_to_hr_string(amount, false),
+		})?;
+	}
+
+	// Assume change for both the amount and the fee, so the fee is never too low
+	let num_outputs = change_outputs + recipients + 1;
+	let mut fee = tx_fee(coins.len() + 1, num_outputs, 1, fee_base);
+	loop {
+		let (max_fee_outputs, fee_coins) = select_coins(
+			wallet,
+			fee,
+			current_height,
+			minimum_confirmations,
+			max_outputs,
+			false,
+			fee_parent_key_id,
+		);
+		let fee_total: u64 = fee_coins.iter().map(|c| c.value).sum();
+		let needed = tx_fee(coins.len() + fee_coins.len(), num_outputs, 1, fee_base);
+		if fee_total >= needed {
+			return Ok((coins, fee_coins, needed));
+		}
+
+		// Selecting for a higher fee won't help if we already have everything
+		if needed <= fee || fee_coins.len() == max_fee_outputs {
+			return Err(ErrorKind::NotEnoughFunds {
+				available: fee_total,
+				available_disp: amount_to_hr_string(fee_total, false),
+				needed,
+				needed_disp: amount_to_hr_string(needed, false),
+			})?;
+		}
+		fee = needed;
+	}
+}
+
+/// Select outputs and calculating fee. With `max_amount` every eligible
+/// output is selected and `amount` is ignored: the returned amount is the
+/// total minus the fee, without any change. The fee is paid at `fee_base`
+/// per unit of weight, grin's default when not set, and covers an output
+/// for each of the `recipients`
 pub fn select_coins_and_fee<T: ?Sized, C, K>(
 	wallet: &mut T,
 	amount: u64,
@@ -283,8 +657,11 @@ pub fn select_coins_and_fee<T: ?Sized, C, K>(
 	minimum_confirmations: u64,
 	max_outputs: usize,
 	change_outputs: usize,
+	recipients: usize,
 	selection_strategy_is_use_all: bool,
 	parent_key_id: &Identifier,
+	max_amount: bool,
+	fee_base: Option<u64>,
 ) -> Result<
 	(
 		Vec<OutputData>,
@@ -299,6 +676,21 @@ where
 	C: NodeClient,
 	K: Keychain,
 {
+	if max_amount {
+		let coins = eligible_outputs(wallet, current_height, minimum_confirmations, parent_key_id);
+		let fee = tx_fee(coins.len(), 1, 1, fee_base);
+		let total: u64 = coins.iter().map(|c| c.value).sum();
+		if total <= fee {
+			return Err(ErrorKind::NotEnoughFunds {
+				available: total,
+				available_disp: amount_to_hr_string(total, false),
+				needed: fee + 1,
+				needed_disp: amount_to_hr_string(fee + 1, false),
+			})?;
+		}
+		return Ok((coins, total, total - fee, fee));
+	}
+
 	// select some spendable coins from the wallet
 	let (max_outputs, mut coins) = select_coins(
 		wallet,
@@ -315,7 +707,7 @@ where
 	// sender
 
 	// First attempt to spend without change
-	let mut fee = tx_fee(coins.len(), 1, 1, None);
+	let mut fee = tx_fee(coins.len(), recipients, 1, fee_base);
 	let mut total: u64 = coins.iter().map(|c| c.value).sum();
 	let mut amount_with_fee = amount + fee;
 
@@ -338,11 +730,11 @@ where
 		})?;
 	}
 
-	let num_outputs = change_outputs + 1;
+	let num_outputs = change_outputs + recipients;
 
 	// We need to add a change address or amount with fee is more than total
 	if total != amount_with_fee {
-		fee = tx_fee(coins.len(), num_outputs, 1, None);
+		fee = tx_fee(coins.len(), num_outputs, 1, fee_base);
 		amount_with_fee = amount + fee;
 
 		// Here check if we have enough outputs for the amount including fee otherwise
@@ -369,7 +761,7 @@ where
 				parent_key_id,
 			)
 			.1;
-			fee = tx_fee(coins.len(), num_outputs, 1, None);
+			fee = tx_fee(coins.len(), num_outputs, 1, fee_base);
 			total = coins.iter().map(|c| c.value).sum();
 			amount_with_fee = amount + fee;
 		}
@@ -377,17 +769,20 @@ where
 	Ok((coins, total, amount, fee))
 }
 
-/// Selects inputs and change for a transaction
+/// Selects inputs and change for a transaction. Change below `min_output_value`
+/// is added to the fee, which is returned along with the inputs and change
 pub fn inputs_and_change<T: ?Sized, C, K, B>(
 	coins: &Vec<OutputData>,
 	wallet: &mut T,
 	amount: u64,
 	fee: u64,
 	num_change_outputs: usize,
+	min_output_value: u64,
 ) -> Result<
 	(
 		Vec<Box<build::Append<K, B>>>,
 		Vec<(u64, Identifier, Option<u64>)>,
+		u64, // fee
 	),
 	Error,
 >
@@ -406,6 +801,8 @@ where
 	// if the fee is 80 then the recipient will receive 1000 and our change will be
 	// 8,920
 	let change = total - amount - fee;
+	let (change_amounts, dust) = change_without_dust(change, num_change_outputs, min_output_value);
+	let fee = fee + dust;
 
 	// build inputs using the appropriate derived key_ids
 	for coin in coins {
@@ -418,25 +815,21 @@ where
 
 	let mut change_amounts_derivations = vec![];
 
-	if change == 0 {
+	if dust > 0 {
+		debug!(
+			"Change of {} is below the minimum output value, adding it to the fee",
+			dust
+		);
+	} else if change == 0 {
 		debug!("No change (sending exactly amount + fee), no change outputs to build");
 	} else {
 		debug!(
 			"Building change outputs: total change: {} ({} outputs)",
-			change, num_change_outputs
+			change,
+			change_amounts.len()
 		);
 
-		let part_change = change / num_change_outputs as u64;
-		let remainder_change = change % part_change;
-
-		for x in 0..num_change_outputs {
-			// n-1 equal change_outputs and a final one accounting for any remainder
-			let change_amount = if x == (num_change_outputs - 1) {
-				part_change + remainder_change
-			} else {
-				part_change
-			};
-
+		for change_amount in change_amounts {
 			let change_key = wallet.next_child().unwrap();
 
 			change_amounts_derivations.push((change_amount, change_key.clone(), None));
@@ -444,7 +837,41 @@ where
 		}
 	}
 
-	Ok((parts, change_amounts_derivations))
+	Ok((parts, change_amounts_derivations, fee))
+}
+
+/// Split the change into `num_change_outputs` outputs: n-1 equal ones and
+/// a final one accounting for any remainder. No change means no outputs
+pub fn change_amounts(change: u64, num_change_outputs: usize) -> Vec<u64> {
+	if change == 0 {
+		return vec![];
+	}
+	let part_change = change / num_change_outputs as u64;
+	let remainder_change = change % num_change_outputs as u64;
+	let mut amounts = vec![part_change; num_change_outputs];
+	amounts[num_change_outputs - 1] += remainder_change;
+	amounts
+}
+
+/// Same as `change_amounts`, but without outputs below `min_output_value`.
+/// Fewer outputs are created if the parts would be too small, and change too
+/// small for a single output is returned as dust to be added to the fee
+pub fn change_without_dust(
+	change: u64,
+	num_change_outputs: usize,
+	min_output_value: u64,
+) -> (Vec<u64>, u64) {
+	if min_output_value == 0 {
+		return (change_amounts(change, num_change_outputs), 0);
+	}
+	if change < min_output_value {
+		return (vec![], change);
+	}
+	let max_outputs = (change / min_output_value) as usize;
+	(
+		change_amounts(change, num_change_outputs.min(max_outputs)),
+		0,
+	)
 }
 
 pub fn select_coins<T: ?Sized, C, K>(
@@ -463,20 +890,21 @@ where
 	K: Keychain,
 {
 	// first find all eligible outputs based on number of confirmations
-	let mut eligible = wallet
-		.outputs()
-		.unwrap()
-		.filter(|out| {
-			out.root_key_id == *parent_key_id
-				&& out.eligible_to_spend(current_height, minimum_confirmations)
-		})
-		.collect::<Vec<OutputData>>();
+	let mut eligible =
+		eligible_outputs(wallet, current_height, minimum_confirmations, parent_key_id);
 
 	let max_available = eligible.len();
 
 	// sort eligible outputs by increasing value
 	eligible.sort_by_key(|out| out.value);
 
+	if wallet.adaptive_selection() && !select_all {
+		let dust = tx_fee(1, 1, 1, wallet.fee_base());
+		if let Some(outputs) = select_adaptive(amount, &eligible, max_outputs, dust) {
+			return (max_available, outputs);
+		}
+	}
+
 	// use a sliding window to identify potential sets of possible outputs to spend
 	// Case of amount > total amount of max_outputs(500):
 	// The limit exists because by default, we always select as many inputs as
@@ -518,6 +946,48 @@ where
 	)
 }
 
+/// Outputs of the account worth at most `threshold` that can be spent at
+/// the current height
+pub fn dust_outputs<T: ?Sized, C, K>(
+	wallet: &mut T,
+	current_height: u64,
+	minimum_confirmations: u64,
+	threshold: u64,
+	parent_key_id: &Identifier,
+) -> Vec<OutputData>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	eligible_outputs(wallet, current_height, minimum_confirmations, parent_key_id)
+		.into_iter()
+		.filter(|out| out.value <= threshold)
+		.collect()
+}
+
+/// All outputs of the account that can be spent at the current height
+pub fn eligible_outputs<T: ?Sized, C, K>(
+	wallet: &mut T,
+	current_height: u64,
+	minimum_confirmations: u64,
+	parent_key_id: &Identifier,
+) -> Vec<OutputData>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	wallet
+		.account_outputs(
+			parent_key_id,
+			&[OutputStatus::Unspent, OutputStatus::Unconfirmed],
+		)
+		.unwrap()
+		.filter(|out| out.eligible_to_spend(current_height, minimum_confirmations))
+		.collect()
+}
+
 fn select_from(amount: u64, select_all: bool, outputs: Vec<OutputData>) -> Option<Vec<OutputData>> {
 	let total = outputs.iter().fold(0, |acc, x| acc + x.value);
 	if total >= amount {
@@ -541,3 +1011,181 @@ fn select_from(amount: u64, select_all: bool, outputs: Vec<OutputData>) -> Optio
 		None
 	}
 }
+
+/// Score of a set of inputs for `amount`, lower is better. Sets leaving
+/// change below `dust` come last, then sets are ranked by how many outputs
+/// they add to the wallet (negative when consuming more than they create)
+/// and finally by the size of the change
+fn selection_score(amount: u64, selected: &[OutputData], dust: u64) -> (bool, i64, u64) {
+	let total: u64 = selected.iter().map(|o| o.value).sum();
+	let change = total - amount;
+	let created = if change > 0 { 1 } else { 0 };
+	(
+		change > 0 && change < dust,
+		created - selected.len() as i64,
+		change,
+	)
+}
+
+/// Select inputs for `amount` from `outputs`, sorted by increasing value,
+/// so that the wallet ends up with as few outputs as possible. Candidates are
+/// the smallest outputs covering the amount, the same with the next output
+/// added, and the `k` smallest outputs completed by a single larger one,
+/// which finds exact matches
+fn select_adaptive(
+	amount: u64,
+	outputs: &[OutputData],
+	max_outputs: usize,
+	dust: u64,
+) -> Option<Vec<OutputData>> {
+	let smallest = select_from(amount, false, outputs.to_vec())?;
+	let mut candidates = vec![smallest.clone()];
+	if smallest.len() < outputs.len() && smallest.len() < max_outputs {
+		candidates.push(outputs[..smallest.len() + 1].to_vec());
+	}
+
+	// The first `k` outputs are worth less than the amount, by construction
+	// of `smallest`
+	let mut sum = 0;
+	for k in 0..smallest.len().min(max_outputs) {
+		let need = amount - sum;
+		let completing = outputs[k..]
+			.iter()
+			.find(|o| o.value >= need && (o.value == need || o.value - need >= dust));
+		if let Some(o) = completing {
+			let mut candidate = outputs[..k].to_vec();
+			candidate.push(o.clone());
+			candidates.push(candidate);
+		}
+		sum += outputs[k].value;
+	}
+
+	candidates
+		.into_iter()
+		.min_by_key(|c| selection_score(amount, c, dust))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const GRIN: u64 = 1_000_000_000;
+	const DUST: u64 = 4_000_000;
+
+	fn output(n_child: u32, value: u64) -> OutputData {
+		OutputData {
+			root_key_id: Identifier::zero(),
+			key_id: Identifier::zero(),
+			n_child,
+			commit: None,
+			mmr_index: None,
+			value,
+			status: OutputStatus::Unspent,
+			height: 1,
+			lock_height: 0,
+			is_coinbase: false,
+			tx_log_entry: None,
+		}
+	}
+
+	fn outputs(values: &[u64]) -> Vec<OutputData> {
+		values
+			.iter()
+			.enumerate()
+			.map(|(i, v)| output(i as u32, *v))
+			.collect()
+	}
+
+	fn values(outputs: &[OutputData]) -> Vec<u64> {
+		outputs.iter().map(|o| o.value).collect()
+	}
+
+	/// Alternate two payments received with one sent, from a fixed seed, and
+	/// return the number of outputs the wallet is left with
+	fn simulate<F>(select: F) -> usize
+	where
+		F: Fn(u64, &[OutputData]) -> Option<Vec<OutputData>>,
+	{
+		let mut state: u64 = 42;
+		let mut next = || {
+			state = state
+				.wrapping_mul(6364136223846793005)
+				.wrapping_add(1442695040888963407);
+			state >> 33
+		};
+
+		let mut wallet: Vec<OutputData> = Vec::new();
+		for step in 0..300 {
+			let value = next() % (5 * GRIN) + 1;
+			if step % 3 != 2 {
+				wallet.push(output(step, value));
+			} else if let Some(selected) = select(value, &wallet) {
+				let total: u64 = selected.iter().map(|o| o.value).sum();
+				wallet.retain(|o| !selected.iter().any(|s| s.n_child == o.n_child));
+				if total > value {
+					wallet.push(output(step, total - value));
+				}
+			}
+			wallet.sort_by_key(|o| o.value);
+		}
+		wallet.len()
+	}
+
+	#[test]
+	fn adaptive_avoids_dust_change() {
+		let available = outputs(&[GRIN, 2 * GRIN, 100 * GRIN]);
+		let amount = 3 * GRIN - 1_000_000;
+
+		let smallest = select_from(amount, false, available.clone()).unwrap();
+		assert_eq!(values(&smallest), vec![GRIN, 2 * GRIN]);
+
+		let adaptive = select_adaptive(amount, &available, 500, DUST).unwrap();
+		let change = values(&adaptive).iter().sum::<u64>() - amount;
+		assert!(change == 0 || change >= DUST);
+		assert_eq!(values(&adaptive), vec![GRIN, 2 * GRIN, 100 * GRIN]);
+	}
+
+	#[test]
+	fn adaptive_finds_exact_match() {
+		let available = outputs(&[2 * GRIN, 5 * GRIN, 7 * GRIN]);
+		let adaptive = select_adaptive(9 * GRIN, &available, 500, DUST).unwrap();
+		assert_eq!(values(&adaptive), vec![2 * GRIN, 7 * GRIN]);
+	}
+
+	#[test]
+	fn adaptive_respects_available_funds() {
+		let available = outputs(&[GRIN, 2 * GRIN]);
+		assert!(select_adaptive(4 * GRIN, &available, 500, DUST).is_none());
+		assert!(select_adaptive(3 * GRIN, &available, 500, DUST).is_some());
+	}
+
+	#[test]
+	fn adaptive_fragments_less_than_smallest_first() {
+		let smallest = simulate(|amount, wallet| select_from(amount, false, wallet.to_vec()));
+		let adaptive = simulate(|amount, wallet| select_adaptive(amount, wallet, 500, DUST));
+		assert!(
+			adaptive < smallest,
+			"adaptive selection left {} outputs, smallest first {}",
+			adaptive,
+			smallest
+		);
+	}
+
+	#[test]
+	fn dust_change_goes_to_the_fee() {
+		assert_eq!(change_without_dust(DUST - 1, 1, DUST), (vec![], DUST - 1));
+		assert_eq!(change_without_dust(DUST, 1, DUST), (vec![DUST], 0));
+		assert_eq!(change_without_dust(0, 1, DUST), (vec![], 0));
+		assert_eq!(change_without_dust(DUST - 1, 1, 0), (vec![DUST - 1], 0));
+	}
+
+	#[test]
+	fn dust_change_uses_fewer_outputs() {
+		assert_eq!(change_without_dust(3 * DUST, 3, DUST), (vec![DUST; 3], 0));
+		assert_eq!(
+			change_without_dust(2 * DUST, 3, DUST),
+			(vec![DUST, DUST], 0)
+		);
+		assert_eq!(change_without_dust(DUST + 1, 3, DUST), (vec![DUST + 1], 0));
+	}
+}
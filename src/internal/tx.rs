@@ -12,40 +12,56 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::keys;
 use super::selection;
 use super::updater;
-use crate::contacts::GrinboxAddress;
+use crate::common::crypto::{sign_challenge, verify_signature};
+use crate::common::hasher::derive_account_address_key;
+use crate::common::timing::{timed, SharedTimings};
+use crate::contacts::{parse_address, AddressType, GrinboxAddress};
 use crate::wallet::types::{
-	Context, InitTxArgs, NodeClient, Slate, TxLogEntryType, TxProof, WalletBackend,
+	payment_proof_message, Amount, Context, DailySpend, DustAdvice, DustAnalysis, DustOutput,
+	EstimateFeeArgs, FeeEstimate, IncomingSlate, InitTxArgs, LateLock, NodeClient, OutputData,
+	OutputStatus, PaymentInfo, PreviewInput, QueuedSlate, ReceiveDecision, ReceivePolicy,
+	ReceivedMessage, SendPreview, Slate, TxLogEntry, TxLogEntryType, TxProof, WalletBackend,
 };
 use crate::wallet::ErrorKind;
+use chrono::Duration;
 use failure::Error;
-use grin_keychain::{Identifier, Keychain};
+use grin_core::core::amount_to_hr_string;
+use grin_core::libtx::proof::ProofBuilder;
+use grin_core::libtx::tx_fee;
+use grin_keychain::{BlindSum, BlindingFactor, Identifier, Keychain};
 use grin_util::secp::key::PublicKey;
 use grin_util::secp::pedersen::Commitment;
 use grin_util::static_secp_instance;
-use std::collections::HashSet;
+use log::{info, warn};
+use std::collections::{BTreeMap, HashSet};
 use uuid::Uuid;
 
 const USER_MESSAGE_MAX_LEN: usize = 256;
+/// Confirmations an output needs before we contribute it to a payjoin
+const PAYJOIN_MIN_CONFIRMATIONS: u64 = 10;
+/// Days processed messages are remembered, to recognize them if they come back
+const RECEIVED_MESSAGE_RETENTION_DAYS: i64 = 30;
 
 /// Initiate tx as sender
-pub fn init_send_tx<T: ?Sized, C, K>(w: &mut T, args: InitTxArgs) -> Result<Slate, Error>
+pub fn init_send_tx<T: ?Sized, C, K>(
+	w: &mut T,
+	args: InitTxArgs,
+	timings: &SharedTimings,
+) -> Result<Slate, Error>
 where
 	T: WalletBackend<C, K>,
 	C: NodeClient,
 	K: Keychain,
 {
-	let parent_key_id = match args.src_acct_name {
-		Some(d) => {
-			let pm = w.get_acct_path(&d)?;
-			match pm {
-				Some(p) => p.path,
-				None => w.get_parent_key_id(),
-			}
-		}
-		None => w.get_parent_key_id(),
-	};
+	let (parent_key_id, fee_parent_key_id) = send_parent_key_ids(w, &args)?;
+
+	if args.num_change_outputs == 0 {
+		return Err(ErrorKind::InvalidChangeOutputs.into());
+	}
+	let (amount, recipients) = send_amount(&args, fee_parent_key_id.as_ref())?;
 
 	let message = args.message.map(|m| {
 		let mut m = m.clone();
@@ -53,47 +69,484 @@ where
 		m
 	});
 
-	let mut slate = new_tx_slate(w, args.amount, 2)?;
+	let mut slate = new_tx_slate(w, amount, recipients + 1)?;
+	if !args.recipients.is_empty() {
+		slate.recipient_amounts = args.recipients.iter().map(|r| r.amount.nano()).collect();
+	}
+	let fee_base = args.fee_base.or(w.fee_base());
 
 	// If we just want to estimate, just send the results back
 	if let Some(true) = args.estimate_only {
-		let (total, fee) = estimate_send_tx(
+		let (total, fee) = timed(timings, "selection", || {
+			estimate_send_tx(
+				w,
+				amount,
+				args.minimum_confirmations,
+				args.max_outputs as usize,
+				args.num_change_outputs as usize,
+				recipients,
+				args.selection_strategy_is_use_all,
+				&parent_key_id,
+				fee_parent_key_id.as_ref(),
+				args.sweep,
+				fee_base,
+			)
+		})?;
+		slate.amount = total;
+		slate.fee = fee;
+		return Ok(slate);
+	}
+
+	let min_output_value = match args.allow_dust {
+		true => 0,
+		false => w.min_output_value(),
+	};
+	let mut context = if args.late_lock {
+		add_late_lock_data(
+			w,
+			&mut slate,
+			&args,
+			&parent_key_id,
+			fee_parent_key_id.as_ref(),
+			fee_base,
+			message,
+			timings,
+		)?
+	} else if args.inputs.is_empty() {
+		add_inputs_to_slate(
 			w,
-			args.amount,
+			&mut slate,
 			args.minimum_confirmations,
 			args.max_outputs as usize,
 			args.num_change_outputs as usize,
 			args.selection_strategy_is_use_all,
 			&parent_key_id,
-		)?;
-		slate.amount = total;
-		slate.fee = fee;
-		return Ok(slate);
+			fee_parent_key_id.as_ref(),
+			args.sweep,
+			min_output_value,
+			fee_base,
+			0,
+			message,
+			true,
+			timings,
+		)?
+	} else {
+		add_given_inputs_to_slate(
+			w,
+			&mut slate,
+			&args.inputs,
+			args.num_change_outputs as usize,
+			&parent_key_id,
+			min_output_value,
+			fee_base,
+			message,
+			timings,
+		)?
+	};
+	context.notes = args.notes;
+	context.replaces = args.replaces;
+
+	// Save the aggsig context in our DB for when we receive the transaction back
+	{
+		let mut batch = w.batch()?;
+		batch.save_private_context(slate.id.as_bytes(), 0, &context)?;
+		batch.commit()?;
+	}
+	if let Some(v) = args.target_slate_version {
+		slate.version_info.orig_version = v;
+	}
+	Ok(slate)
+}
+
+/// Total amount of a send and the number of recipients it pays. Several
+/// recipients can't be combined with options that assume a single one
+fn send_amount(
+	args: &InitTxArgs,
+	fee_parent_key_id: Option<&Identifier>,
+) -> Result<(u64, usize), Error> {
+	if args.recipients.is_empty() {
+		return Ok((args.amount.nano(), 1));
+	}
+	let unsupported = if args.sweep {
+		Some("in a sweep")
+	} else if fee_parent_key_id.is_some() {
+		Some("with a fee account")
+	} else if args.payment_proof_recipient_address.is_some() {
+		Some("with a payment proof")
+	} else if !args.inputs.is_empty() {
+		Some("with given inputs")
+	} else {
+		None
+	};
+	if let Some(reason) = unsupported {
+		return Err(ErrorKind::RecipientsNotSupported(reason.to_owned()).into());
+	}
+	let amount = args.recipients.iter().map(|r| r.amount.nano()).sum();
+	Ok((amount, args.recipients.len()))
+}
+
+/// Accounts a send draws its amount and, if different, its fee from
+fn send_parent_key_ids<T: ?Sized, C, K>(
+	w: &mut T,
+	args: &InitTxArgs,
+) -> Result<(Identifier, Option<Identifier>), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let parent_key_id = match &args.src_acct_name {
+		Some(d) => {
+			let pm = w.get_acct_path(d)?;
+			match pm {
+				Some(p) => p.path,
+				None => w.get_parent_key_id(),
+			}
+		}
+		None => w.get_parent_key_id(),
+	};
+
+	let fee_parent_key_id = match &args.fee_acct_name {
+		Some(d) => {
+			let pm = w.get_acct_path(d)?;
+			match pm {
+				Some(p) if p.path != parent_key_id => Some(p.path),
+				Some(_) => None,
+				None => return Err(ErrorKind::UnknownAccountLabel(d.clone()))?,
+			}
+		}
+		None => None,
+	};
+	// A sweep pays the fee from the swept outputs
+	let fee_parent_key_id = fee_parent_key_id.filter(|_| !args.sweep);
+	Ok((parent_key_id, fee_parent_key_id))
+}
+
+/// Run the output selection of `init_send_tx` without building, signing or
+/// locking anything, and report the inputs, change and resulting balance
+pub fn preview_send_tx<T: ?Sized, C, K>(w: &mut T, args: &InitTxArgs) -> Result<SendPreview, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let (parent_key_id, fee_parent_key_id) = send_parent_key_ids(w, args)?;
+
+	if args.num_change_outputs == 0 {
+		return Err(ErrorKind::InvalidChangeOutputs.into());
+	}
+	let num_change_outputs = args.num_change_outputs as usize;
+	let (amount, recipients) = send_amount(args, fee_parent_key_id.as_ref())?;
+
+	let min_output_value = match args.allow_dust {
+		true => 0,
+		false => w.min_output_value(),
+	};
+	let current_height = w.w2n_client().get_chain_height()?;
+	updater::refresh_outputs(w, &parent_key_id, false)?;
+
+	let preview_input = |o: OutputData, pays_fee: bool| PreviewInput {
+		commit: o.commit,
+		value: Amount::from_nano(o.value),
+		height: o.height,
+		pays_fee,
+	};
+
+	let (amount, fee, inputs, change) = match &fee_parent_key_id {
+		Some(fee_parent_key_id) => {
+			updater::refresh_outputs(w, fee_parent_key_id, false)?;
+			let (coins, fee_coins, fee) = selection::select_coins_split_fee(
+				w,
+				amount,
+				current_height,
+				args.minimum_confirmations,
+				args.max_outputs as usize,
+				num_change_outputs,
+				recipients,
+				args.selection_strategy_is_use_all,
+				&parent_key_id,
+				fee_parent_key_id,
+				args.fee_base.or(w.fee_base()),
+			)?;
+			let total: u64 = coins.iter().map(|c| c.value).sum();
+			let fee_total: u64 = fee_coins.iter().map(|c| c.value).sum();
+			let (mut change, dust) = selection::change_without_dust(
+				total - amount,
+				num_change_outputs,
+				min_output_value,
+			);
+			let (fee_change, fee_dust) =
+				selection::change_without_dust(fee_total - fee, 1, min_output_value);
+			change.extend(fee_change);
+			let fee = fee + dust + fee_dust;
+			let inputs = coins
+				.into_iter()
+				.map(|o| preview_input(o, false))
+				.chain(fee_coins.into_iter().map(|o| preview_input(o, true)))
+				.collect();
+			(amount, fee, inputs, change)
+		}
+		None => {
+			let (coins, total, amount, fee) = selection::select_coins_and_fee(
+				w,
+				amount,
+				current_height,
+				args.minimum_confirmations,
+				args.max_outputs as usize,
+				num_change_outputs,
+				recipients,
+				args.selection_strategy_is_use_all,
+				&parent_key_id,
+				args.sweep,
+				args.fee_base.or(w.fee_base()),
+			)?;
+			let (change, dust) = selection::change_without_dust(
+				total - amount - fee,
+				num_change_outputs,
+				min_output_value,
+			);
+			let fee = fee + dust;
+			let inputs = coins.into_iter().map(|o| preview_input(o, false)).collect();
+			(amount, fee, inputs, change)
+		}
+	};
+
+	let fee_account = match &fee_parent_key_id {
+		Some(id) => w.accounts()?.find(|m| m.path == *id).map(|m| m.label),
+		None => None,
+	};
+	let spent = match fee_parent_key_id {
+		Some(_) => amount,
+		None => amount + fee,
+	};
+	let balance = updater::retrieve_info(w, &parent_key_id, args.minimum_confirmations)?.total;
+	Ok(SendPreview {
+		amount: Amount::from_nano(amount),
+		fee: Amount::from_nano(fee),
+		inputs,
+		change_outputs: change.into_iter().map(Amount::from_nano).collect(),
+		fee_account,
+		balance_before: Amount::from_nano(balance),
+		balance_after: Amount::from_nano(balance.saturating_sub(spent)),
+	})
+}
+
+/// Build a transaction from the active account to itself that combines its
+/// eligible outputs into `target_outputs` outputs. The wallet fills both
+/// participant roles, so the returned slate is finalized and ready to post
+pub fn consolidate<T: ?Sized, C, K>(
+	w: &mut T,
+	minimum_confirmations: u64,
+	target_outputs: usize,
+	timings: &SharedTimings,
+) -> Result<Slate, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if target_outputs == 0 {
+		return Err(ErrorKind::InvalidTargetOutputs.into());
+	}
+
+	let parent_key_id = w.get_parent_key_id();
+	timed(timings, "refresh", || {
+		updater::refresh_outputs(w, &parent_key_id, false)
+	})?;
+	let mut slate = new_tx_slate(w, 0, 2)?;
+
+	let coins = selection::eligible_outputs(w, slate.height, minimum_confirmations, &parent_key_id);
+	if coins.len() <= target_outputs {
+		return Err(ErrorKind::NothingToConsolidate(coins.len()).into());
+	}
+	let fee_base = w.fee_base();
+	let fee = tx_fee(coins.len(), target_outputs, 1, fee_base);
+	let total: u64 = coins.iter().map(|c| c.value).sum();
+	let needed = fee + target_outputs as u64;
+	if total < needed {
+		return Err(ErrorKind::NotEnoughFunds {
+			available: total,
+			available_disp: amount_to_hr_string(total, false),
+			needed,
+			needed_disp: amount_to_hr_string(needed, false),
+		})?;
 	}
 
+	// Our "recipient" side gets one share, the change outputs split the rest.
+	// With a single target output everything minus the fee goes to the recipient
+	slate.amount = (total - fee) / target_outputs as u64;
 	let context = add_inputs_to_slate(
 		w,
 		&mut slate,
-		args.minimum_confirmations,
-		args.max_outputs as usize,
-		args.num_change_outputs as usize,
-		args.selection_strategy_is_use_all,
+		minimum_confirmations,
+		coins.len(),
+		(target_outputs - 1).max(1),
+		true,
 		&parent_key_id,
+		None,
+		target_outputs == 1,
 		0,
-		message,
+		fee_base,
+		0,
+		None,
 		true,
+		timings,
 	)?;
-
-	// Save the aggsig context in our DB for when we receive the transaction back
 	{
 		let mut batch = w.batch()?;
 		batch.save_private_context(slate.id.as_bytes(), 0, &context)?;
 		batch.commit()?;
 	}
-	if let Some(v) = args.target_slate_version {
-		slate.version_info.orig_version = v;
+
+	timed(timings, "signing", || {
+		add_output_to_slate(
+			w,
+			&mut slate,
+			&parent_key_id,
+			1,
+			None,
+			None,
+			None,
+			None,
+			false,
+		)?;
+		tx_lock_outputs(w, &slate, 0, None)?;
+		finalize_tx(w, &slate, None)
+	})
+}
+
+/// List the dust of the active account and compare the cost of sweeping it
+/// now with sweeping it later or spending it along with an ordinary send
+pub fn analyze_dust<T: ?Sized, C, K>(
+	w: &mut T,
+	minimum_confirmations: u64,
+	threshold: Option<u64>,
+) -> Result<DustAnalysis, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let parent_key_id = w.get_parent_key_id();
+	updater::refresh_outputs(w, &parent_key_id, false)?;
+	let height = w.w2n_client().get_chain_height()?;
+	let fee_base = w.fee_base();
+
+	let threshold = threshold.unwrap_or(tx_fee(1, 1, 1, fee_base));
+	let coins =
+		selection::dust_outputs(w, height, minimum_confirmations, threshold, &parent_key_id);
+	let total: u64 = coins.iter().map(|c| c.value).sum();
+	let sweep_fee = match coins.len() {
+		0 => 0,
+		n => tx_fee(n, 1, 1, fee_base),
+	};
+	// Every input lowers the fee until it bottoms out at the base fee
+	let min_fee_inputs = (1..)
+		.find(|n| tx_fee(*n, 1, 1, fee_base) == tx_fee(n + 1, 1, 1, fee_base))
+		.unwrap_or(1);
+	let min_fee = tx_fee(min_fee_inputs, 1, 1, fee_base);
+	let piggyback_fee =
+		tx_fee(1 + coins.len(), 2, 1, fee_base) as i64 - tx_fee(1, 2, 1, fee_base) as i64;
+
+	let advice = if coins.is_empty() {
+		DustAdvice::Clean
+	} else if total <= sweep_fee {
+		DustAdvice::Uneconomic
+	} else if sweep_fee > min_fee {
+		DustAdvice::Wait
+	} else {
+		DustAdvice::SweepNow
+	};
+
+	Ok(DustAnalysis {
+		threshold: Amount::from_nano(threshold),
+		outputs: coins
+			.into_iter()
+			.map(|o| DustOutput {
+				commit: o.commit,
+				value: Amount::from_nano(o.value),
+				height: o.height,
+			})
+			.collect(),
+		total: Amount::from_nano(total),
+		sweep_fee: Amount::from_nano(sweep_fee),
+		min_fee: Amount::from_nano(min_fee),
+		min_fee_inputs,
+		piggyback_fee,
+		advice,
+	})
+}
+
+/// Combine the dust of the active account into a single output with a
+/// transaction to ourselves. Fails if the fee is above `max_fee` or more
+/// than the dust is worth. The returned slate is finalized and ready to post
+pub fn sweep_dust<T: ?Sized, C, K>(
+	w: &mut T,
+	minimum_confirmations: u64,
+	threshold: Option<u64>,
+	max_fee: Option<u64>,
+) -> Result<Slate, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let parent_key_id = w.get_parent_key_id();
+	updater::refresh_outputs(w, &parent_key_id, false)?;
+	let mut slate = new_tx_slate(w, 0, 2)?;
+	let fee_base = w.fee_base();
+
+	let threshold = threshold.unwrap_or(tx_fee(1, 1, 1, fee_base));
+	let coins = selection::dust_outputs(
+		w,
+		slate.height,
+		minimum_confirmations,
+		threshold,
+		&parent_key_id,
+	);
+	if coins.is_empty() {
+		return Err(ErrorKind::NothingToConsolidate(0).into());
 	}
-	Ok(slate)
+	let fee = tx_fee(coins.len(), 1, 1, fee_base);
+	if let Some(max_fee) = max_fee {
+		if fee > max_fee {
+			return Err(ErrorKind::FeeBudgetExceeded(
+				amount_to_hr_string(fee, false),
+				amount_to_hr_string(max_fee, false),
+			))?;
+		}
+	}
+	let total: u64 = coins.iter().map(|c| c.value).sum();
+	if total <= fee {
+		return Err(ErrorKind::NotEnoughFunds {
+			available: total,
+			available_disp: amount_to_hr_string(total, false),
+			needed: fee + 1,
+			needed_disp: amount_to_hr_string(fee + 1, false),
+		})?;
+	}
+
+	let mut context = selection::build_sweep_tx(w, &mut slate, coins, &parent_key_id)?;
+	fill_sender_data(w, &mut slate, &mut context, 0, None, true)?;
+	{
+		let mut batch = w.batch()?;
+		batch.save_private_context(slate.id.as_bytes(), 0, &context)?;
+		batch.commit()?;
+	}
+
+	add_output_to_slate(
+		w,
+		&mut slate,
+		&parent_key_id,
+		1,
+		None,
+		None,
+		None,
+		None,
+		false,
+	)?;
+	tx_lock_outputs(w, &slate, 0, None)?;
+	finalize_tx(w, &slate, None)
 }
 
 /// Creates a new slate for a transaction, can be called by anyone involved in
@@ -109,7 +562,7 @@ where
 	K: Keychain,
 {
 	let current_height = wallet.w2n_client().get_chain_height()?;
-	let mut slate = Slate::blank(num_participants);
+	let mut slate = Slate::blank(num_participants, wallet.entropy().uuid());
 	slate.amount = amount;
 	slate.height = current_height;
 
@@ -120,6 +573,74 @@ where
 	Ok(slate)
 }
 
+/// Estimates fee, locked amount and change for each of the requested selection
+/// strategies. Doesn't create a slate or a private context
+pub fn estimate_fee<T: ?Sized, C, K>(
+	wallet: &mut T,
+	args: EstimateFeeArgs,
+) -> Result<Vec<FeeEstimate>, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let parent_key_id = match &args.src_acct_name {
+		Some(d) => {
+			let pm = wallet.get_acct_path(d)?;
+			match pm {
+				Some(p) => p.path,
+				None => wallet.get_parent_key_id(),
+			}
+		}
+		None => wallet.get_parent_key_id(),
+	};
+
+	if args.num_change_outputs == 0 {
+		return Err(ErrorKind::InvalidChangeOutputs.into());
+	}
+
+	let mut use_all = Vec::with_capacity(args.strategies.len());
+	for strategy in &args.strategies {
+		use_all.push(match strategy.as_str() {
+			"smallest" => false,
+			"all" => true,
+			_ => return Err(ErrorKind::UnknownSelectionStrategy(strategy.clone()).into()),
+		});
+	}
+
+	let current_height = wallet.w2n_client().get_chain_height()?;
+	updater::refresh_outputs(wallet, &parent_key_id, false)?;
+
+	let mut estimates = Vec::with_capacity(args.strategies.len());
+	for (strategy, use_all) in args.strategies.into_iter().zip(use_all) {
+		let (_, total, amount, fee) = selection::select_coins_and_fee(
+			wallet,
+			args.amount.nano(),
+			current_height,
+			args.minimum_confirmations,
+			args.max_outputs as usize,
+			args.num_change_outputs as usize,
+			1,
+			use_all,
+			&parent_key_id,
+			false,
+			wallet.fee_base(),
+		)?;
+		let change_outputs = if total == amount + fee {
+			0
+		} else {
+			args.num_change_outputs as usize
+		};
+		estimates.push(FeeEstimate {
+			strategy,
+			fee: Amount::from_nano(fee),
+			total: Amount::from_nano(total),
+			change_outputs,
+		});
+	}
+	Ok(estimates)
+}
+
 /// Estimates locked amount and fee for the transaction without creating one
 pub fn estimate_send_tx<T: ?Sized, C, K>(
 	wallet: &mut T,
@@ -127,8 +648,12 @@ pub fn estimate_send_tx<T: ?Sized, C, K>(
 	minimum_confirmations: u64,
 	max_outputs: usize,
 	num_change_outputs: usize,
+	recipients: usize,
 	selection_strategy_is_use_all: bool,
 	parent_key_id: &Identifier,
+	fee_parent_key_id: Option<&Identifier>,
+	max_amount: bool,
+	fee_base: Option<u64>,
 ) -> Result<
 	(
 		u64, // total
@@ -146,6 +671,25 @@ where
 	// ensure outputs we're selecting are up to date
 	updater::refresh_outputs(wallet, parent_key_id, false)?;
 
+	if let Some(fee_parent_key_id) = fee_parent_key_id {
+		updater::refresh_outputs(wallet, fee_parent_key_id, false)?;
+		let (coins, fee_coins, fee) = selection::select_coins_split_fee(
+			wallet,
+			amount,
+			current_height,
+			minimum_confirmations,
+			max_outputs,
+			num_change_outputs,
+			recipients,
+			selection_strategy_is_use_all,
+			parent_key_id,
+			fee_parent_key_id,
+			fee_base,
+		)?;
+		let total = coins.iter().chain(fee_coins.iter()).map(|c| c.value).sum();
+		return Ok((total, fee));
+	}
+
 	// Sender selects outputs into a new slate and save our corresponding keys in
 	// a transaction context. The secret key in our transaction context will be
 	// randomly selected. This returns the public slate, and a closure that locks
@@ -160,8 +704,11 @@ where
 		minimum_confirmations,
 		max_outputs,
 		num_change_outputs,
+		recipients,
 		selection_strategy_is_use_all,
 		parent_key_id,
+		max_amount,
+		fee_base,
 	)?;
 	Ok((total, fee))
 }
@@ -175,9 +722,14 @@ pub fn add_inputs_to_slate<T: ?Sized, C, K>(
 	num_change_outputs: usize,
 	selection_strategy_is_use_all: bool,
 	parent_key_id: &Identifier,
+	fee_parent_key_id: Option<&Identifier>,
+	max_amount: bool,
+	min_output_value: u64,
+	fee_base: Option<u64>,
 	participant_id: usize,
 	message: Option<String>,
 	is_initator: bool,
+	timings: &SharedTimings,
 ) -> Result<Context, Error>
 where
 	T: WalletBackend<C, K>,
@@ -185,7 +737,13 @@ where
 	K: Keychain,
 {
 	// sender should always refresh outputs
-	updater::refresh_outputs(wallet, parent_key_id, false)?;
+	timed(timings, "refresh", || -> Result<(), Error> {
+		updater::refresh_outputs(wallet, parent_key_id, false)?;
+		if let Some(fee_parent_key_id) = fee_parent_key_id {
+			updater::refresh_outputs(wallet, fee_parent_key_id, false)?;
+		}
+		Ok(())
+	})?;
 
 	// Sender selects outputs into a new slate and save our corresponding keys in
 	// a transaction context. The secret key in our transaction context will be
@@ -194,82 +752,330 @@ where
 	// according to plan
 	// This function is just a big helper to do all of that, in theory
 	// this process can be split up in any way
-	let mut context = selection::build_send_tx(
-		wallet,
-		slate,
-		minimum_confirmations,
-		max_outputs,
-		num_change_outputs,
-		selection_strategy_is_use_all,
-		parent_key_id.clone(),
-	)?;
-
-	// Store input and output commitments in context
-	// They will be added tp the transaction proof
-	for input in slate.tx.inputs() {
-		context.input_commits.push(input.commit.clone());
-	}
-
-	for output in slate.tx.outputs() {
-		context.output_commits.push(output.commit.clone());
-	}
-
-	// Generate a kernel offset and subtract from our context's secret key. Store
-	// the offset in the slate's transaction kernel, and adds our public key
-	// information to the slate
-	let _ = slate.fill_round_1(
-		wallet.keychain(),
-		&mut context.sec_key,
-		&context.sec_nonce,
-		participant_id,
-		message,
-	)?;
+	let mut context = timed(timings, "selection", || {
+		selection::build_send_tx(
+			wallet,
+			slate,
+			minimum_confirmations,
+			max_outputs,
+			num_change_outputs,
+			selection_strategy_is_use_all,
+			parent_key_id.clone(),
+			fee_parent_key_id.cloned(),
+			max_amount,
+			min_output_value,
+			fee_base,
+		)
+	})?;
 
-	if !is_initator {
-		// perform partial sig
-		let _ = slate.fill_round_2(
-			wallet.keychain(),
-			&context.sec_key,
-			&context.sec_nonce,
+	timed(timings, "signing", || {
+		fill_sender_data(
+			wallet,
+			slate,
+			&mut context,
 			participant_id,
-		)?;
-	}
-
+			message,
+			is_initator,
+		)
+	})?;
 	Ok(context)
 }
 
-/// Add receiver output to the slate
-pub fn add_output_to_slate<T: ?Sized, C, K>(
+/// Add the given unspent outputs of the account as inputs to the slate,
+/// instead of selecting them
+fn add_given_inputs_to_slate<T: ?Sized, C, K>(
 	wallet: &mut T,
 	slate: &mut Slate,
+	inputs: &[Identifier],
+	num_change_outputs: usize,
 	parent_key_id: &Identifier,
-	participant_id: usize,
-	address: Option<String>,
+	min_output_value: u64,
+	fee_base: Option<u64>,
 	message: Option<String>,
-	is_initiator: bool,
+	timings: &SharedTimings,
 ) -> Result<Context, Error>
 where
 	T: WalletBackend<C, K>,
 	C: NodeClient,
 	K: Keychain,
 {
-	// create an output using the amount in the slate
-	let (_, mut context) =
-		selection::build_recipient_output(wallet, slate, parent_key_id.clone(), address)?;
+	timed(timings, "refresh", || {
+		updater::refresh_outputs(wallet, parent_key_id, false)
+	})?;
+	let coins: Vec<OutputData> = wallet
+		.account_outputs(parent_key_id, &[OutputStatus::Unspent])?
+		.filter(|o| inputs.contains(&o.key_id))
+		.collect();
+	if coins.len() != inputs.len() {
+		return Err(ErrorKind::InputsNotAvailable.into());
+	}
 
-	// fill public keys
-	let _ = slate.fill_round_1(
-		wallet.keychain(),
-		&mut context.sec_key,
-		&context.sec_nonce,
-		participant_id,
-		message,
-	)?;
+	let mut context = timed(timings, "selection", || {
+		selection::build_send_tx_from_inputs(
+			wallet,
+			slate,
+			coins,
+			num_change_outputs,
+			parent_key_id,
+			min_output_value,
+			fee_base,
+		)
+	})?;
+	timed(timings, "signing", || {
+		fill_sender_data(wallet, slate, &mut context, 0, message, true)
+	})?;
+	Ok(context)
+}
+
+/// Add our public data to the slate of a late locked send, without selecting
+/// any inputs. Our excess is random for now, `finalize_tx` moves the kernel
+/// offset to match it once the inputs are known
+fn add_late_lock_data<T: ?Sized, C, K>(
+	wallet: &mut T,
+	slate: &mut Slate,
+	args: &InitTxArgs,
+	parent_key_id: &Identifier,
+	fee_parent_key_id: Option<&Identifier>,
+	fee_base: Option<u64>,
+	message: Option<String>,
+	timings: &SharedTimings,
+) -> Result<Context, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let unsupported = if args.sweep {
+		Some("in a sweep")
+	} else if fee_parent_key_id.is_some() {
+		Some("with a fee account")
+	} else if !args.inputs.is_empty() {
+		Some("with given inputs")
+	} else {
+		None
+	};
+	if let Some(reason) = unsupported {
+		return Err(ErrorKind::LateLockNotSupported(reason.to_owned()).into());
+	}
+
+	// Everyone signs the fee, so it's fixed now from the current selection
+	let (_, fee) = timed(timings, "selection", || {
+		estimate_send_tx(
+			wallet,
+			slate.amount,
+			args.minimum_confirmations,
+			args.max_outputs as usize,
+			args.num_change_outputs as usize,
+			slate.num_participants - 1,
+			args.selection_strategy_is_use_all,
+			parent_key_id,
+			None,
+			false,
+			fee_base,
+		)
+	})?;
+	slate.fee = fee;
+
+	let sec_key = wallet.entropy().secret_key(wallet.signer().secp());
+	let mut context = Context::new(wallet.signer(), sec_key, parent_key_id, 0)?;
+	context.amount = slate.amount;
+	context.fee = fee;
+	context.late_lock = Some(LateLock {
+		minimum_confirmations: args.minimum_confirmations,
+		max_outputs: args.max_outputs as usize,
+		num_change_outputs: args.num_change_outputs as usize,
+		selection_strategy_is_use_all: args.selection_strategy_is_use_all,
+		fee_base,
+	});
+	timed(timings, "signing", || {
+		fill_sender_data(wallet, slate, &mut context, 0, message, true)
+	})?;
+	Ok(context)
+}
+
+/// Select the inputs and change of a late locked send and add them to the
+/// slate. The kernel offset takes up the difference between their blinding
+/// factors and the excess we committed to when the slate was created
+fn add_late_inputs<T: ?Sized, C, K>(
+	wallet: &mut T,
+	slate: &mut Slate,
+	context: &mut Context,
+	late_lock: &LateLock,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let parent_key_id = context.parent_key_id.clone();
+	updater::refresh_outputs(wallet, &parent_key_id, false)?;
+	let current_height = wallet.w2n_client().get_chain_height()?;
+	let (coins, total, _, fee) = selection::select_coins_and_fee(
+		wallet,
+		context.amount,
+		current_height,
+		late_lock.minimum_confirmations,
+		late_lock.max_outputs,
+		late_lock.num_change_outputs,
+		slate.num_participants - 1,
+		late_lock.selection_strategy_is_use_all,
+		&parent_key_id,
+		false,
+		late_lock.fee_base,
+	)?;
+	if fee > context.fee {
+		return Err(ErrorKind::LateLockFee(
+			amount_to_hr_string(context.fee, false),
+			amount_to_hr_string(fee, false),
+		))?;
+	}
+	let needed = context.amount + context.fee;
+	if total < needed {
+		return Err(ErrorKind::NotEnoughFunds {
+			available: total,
+			available_disp: amount_to_hr_string(total, false),
+			needed,
+			needed_disp: amount_to_hr_string(needed, false),
+		})?;
+	}
+
+	// Change can't be added to the signed fee, so dust stays an output
+	let (elems, change, _) = selection::inputs_and_change(
+		&coins,
+		wallet,
+		context.amount,
+		context.fee,
+		late_lock.num_change_outputs,
+		0,
+	)?;
+	let their_inputs: Vec<Commitment> = slate.tx.inputs().iter().map(|i| i.commit).collect();
+	let their_outputs: Vec<Commitment> = slate.tx.outputs().iter().map(|o| o.commit).collect();
+	let keychain = wallet.keychain();
+	let blinding = slate.add_transaction_elements(keychain, &ProofBuilder::new(keychain), elems)?;
+	let excess = BlindingFactor::from_secret_key(context.sec_key.clone());
+	slate.tx.offset = keychain.blind_sum(
+		&BlindSum::new()
+			.add_blinding_factor(blinding)
+			.sub_blinding_factor(excess),
+	)?;
+
+	for coin in coins {
+		context.add_input(&coin.key_id, &coin.mmr_index, coin.value);
+	}
+	for (amount, id, mmr_index) in change {
+		context.add_output(&id, &mmr_index, amount);
+	}
+	context.input_commits = slate
+		.tx
+		.inputs()
+		.iter()
+		.map(|i| i.commit)
+		.filter(|c| !their_inputs.contains(c))
+		.collect();
+	context.output_commits = slate
+		.tx
+		.outputs()
+		.iter()
+		.map(|o| o.commit)
+		.filter(|c| !their_outputs.contains(c))
+		.collect();
+	Ok(())
+}
+
+/// Record our input and output commitments in the context and add our
+/// public data to the slate, signing it too if we're not the initiator
+fn fill_sender_data<T: ?Sized, C, K>(
+	wallet: &mut T,
+	slate: &mut Slate,
+	context: &mut Context,
+	participant_id: usize,
+	message: Option<String>,
+	is_initator: bool,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	// Store input and output commitments in context
+	// They will be added tp the transaction proof
+	for input in slate.tx.inputs() {
+		context.input_commits.push(input.commit.clone());
+	}
+
+	for output in slate.tx.outputs() {
+		context.output_commits.push(output.commit.clone());
+	}
+
+	// Generate a kernel offset and subtract from our context's secret key. Store
+	// the offset in the slate's transaction kernel, and adds our public key
+	// information to the slate
+	let entropy = wallet.entropy();
+	let _ = slate.fill_round_1(
+		wallet.signer(),
+		&mut context.sec_key,
+		&context.sec_nonce,
+		participant_id,
+		message,
+		&*entropy,
+	)?;
+
+	if !is_initator {
+		// perform partial sig
+		let _ = slate.fill_round_2(
+			wallet.signer(),
+			&context.sec_key,
+			&context.sec_nonce,
+			participant_id,
+		)?;
+	}
+
+	Ok(())
+}
+
+/// Add receiver output to the slate
+pub fn add_output_to_slate<T: ?Sized, C, K>(
+	wallet: &mut T,
+	slate: &mut Slate,
+	parent_key_id: &Identifier,
+	participant_id: usize,
+	input: Option<OutputData>,
+	address: Option<String>,
+	contact: Option<String>,
+	message: Option<String>,
+	is_initiator: bool,
+) -> Result<Context, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	// create an output using the amount in the slate
+	let (_, mut context) = selection::build_recipient_output(
+		wallet,
+		slate,
+		parent_key_id.clone(),
+		participant_id,
+		input,
+		address,
+		contact,
+	)?;
+
+	// fill public keys
+	let entropy = wallet.entropy();
+	let _ = slate.fill_round_1(
+		wallet.signer(),
+		&mut context.sec_key,
+		&context.sec_nonce,
+		participant_id,
+		message,
+		&*entropy,
+	)?;
 
 	if !is_initiator {
 		// perform partial sig
 		let _ = slate.fill_round_2(
-			wallet.keychain(),
+			wallet.signer(),
 			&context.sec_key,
 			&context.sec_nonce,
 			participant_id,
@@ -293,7 +1099,7 @@ where
 	K: Keychain,
 {
 	let _ = slate.fill_round_2(
-		wallet.keychain(),
+		wallet.signer(),
 		&context.sec_key,
 		&context.sec_nonce,
 		participant_id,
@@ -329,6 +1135,8 @@ where
 		Some(&parent_key_id),
 		false,
 		false,
+		None,
+		None,
 	)?;
 	let tx = match tx_vec.into_iter().next() {
 		Some(t) => t,
@@ -341,10 +1149,172 @@ where
 	{
 		return Err(ErrorKind::TransactionNotCancellable(tx_id_string))?;
 	}
+	let linked = match (&tx.linked_parent_key_id, tx.tx_slate_id) {
+		(Some(linked_parent_key_id), Some(slate_id)) => {
+			Some((linked_parent_key_id.clone(), slate_id))
+		}
+		_ => None,
+	};
+
 	// get outputs associated with tx
-	let res = updater::retrieve_outputs(wallet, false, Some(tx.id), Some(&parent_key_id))?;
+	let res = updater::retrieve_outputs(wallet, false, Some(tx.id), Some(&parent_key_id), None)?;
 	let outputs = res.iter().map(|m| m.output.clone()).collect();
 	updater::cancel_tx_and_outputs(wallet, tx, outputs, parent_key_id)?;
+
+	// The entry of the account that paid the fee (or the other way around)
+	if let Some((linked_parent_key_id, slate_id)) = linked {
+		let (tx_vec, _) = updater::retrieve_txs(
+			wallet,
+			None,
+			Some(slate_id),
+			Some(&linked_parent_key_id),
+			false,
+			false,
+			None,
+			None,
+		)?;
+		for tx in tx_vec {
+			if tx.tx_type != TxLogEntryType::TxSent || tx.confirmed {
+				continue;
+			}
+			let res = updater::retrieve_outputs(
+				wallet,
+				false,
+				Some(tx.id),
+				Some(&linked_parent_key_id),
+				None,
+			)?;
+			let outputs = res.iter().map(|m| m.output.clone()).collect();
+			updater::cancel_tx_and_outputs(wallet, tx, outputs, &linked_parent_key_id)?;
+		}
+	}
+	Ok(())
+}
+
+/// Cancel the sent transactions of an account that are still unconfirmed
+/// `after_blocks` blocks after they were created, unlocking their inputs.
+/// Returns the ids of the cancelled transactions
+pub fn auto_cancel_txs<T: ?Sized, C, K>(
+	wallet: &mut T,
+	parent_key_id: &Identifier,
+	height: u64,
+	after_blocks: u64,
+) -> Result<Vec<u32>, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	// Entries created before the height was recorded fall back on the
+	// height they were last posted at, if any
+	let stale: Vec<TxLogEntry> = wallet
+		.tx_logs()?
+		.filter(|t| {
+			&t.parent_key_id == parent_key_id
+				&& t.tx_type == TxLogEntryType::TxSent
+				&& !t.confirmed
+				&& match t.creation_height.or(t.posted_height) {
+					Some(h) => height >= h + after_blocks,
+					None => false,
+				}
+		})
+		.collect();
+
+	let mut cancelled = Vec::new();
+	for t in stale {
+		match cancel_tx(wallet, parent_key_id, Some(t.id), None) {
+			Ok(()) => {
+				info!(
+					"Automatically cancelled tx {}, unconfirmed after {} blocks",
+					t.id, after_blocks
+				);
+				cancelled.push(t.id);
+			}
+			Err(e) => warn!("Automatic cancellation of tx {} failed: {}", t.id, e),
+		}
+	}
+	Ok(cancelled)
+}
+
+/// Set or clear the label of a transaction
+/// Update the private notes of a transaction. Notes with an empty value are
+/// removed, and all notes are removed when `notes` is `None`
+pub fn set_tx_notes<T: ?Sized, C, K>(
+	wallet: &mut T,
+	parent_key_id: &Identifier,
+	tx_id: u32,
+	notes: Option<BTreeMap<String, String>>,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let (tx_vec, _) = updater::retrieve_txs(
+		wallet,
+		Some(tx_id),
+		None,
+		Some(&parent_key_id),
+		false,
+		false,
+		None,
+		None,
+	)?;
+	let mut tx = match tx_vec.into_iter().next() {
+		Some(t) => t,
+		None => return Err(ErrorKind::TransactionDoesntExist(tx_id.to_string()))?,
+	};
+	match notes {
+		Some(notes) => {
+			for (key, value) in notes {
+				if value.trim().is_empty() {
+					tx.notes.remove(&key);
+				} else {
+					tx.notes.insert(key, value);
+				}
+			}
+		}
+		None => tx.notes.clear(),
+	}
+	{
+		let mut batch = wallet.batch()?;
+		batch.save_tx_log_entry(&tx)?;
+		batch.commit()?;
+	}
+	Ok(())
+}
+
+pub fn set_tx_label<T: ?Sized, C, K>(
+	wallet: &mut T,
+	parent_key_id: &Identifier,
+	tx_id: u32,
+	label: Option<String>,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let (tx_vec, _) = updater::retrieve_txs(
+		wallet,
+		Some(tx_id),
+		None,
+		Some(&parent_key_id),
+		false,
+		false,
+		None,
+		None,
+	)?;
+	let mut tx = match tx_vec.into_iter().next() {
+		Some(t) => t,
+		None => return Err(ErrorKind::TransactionDoesntExist(tx_id.to_string()))?,
+	};
+	tx.label = label.filter(|l| !l.trim().is_empty());
+	{
+		let mut batch = wallet.batch()?;
+		batch.save_tx_log_entry(&tx)?;
+		batch.commit()?;
+	}
 	Ok(())
 }
 
@@ -361,7 +1331,8 @@ where
 	K: Keychain,
 {
 	// finalize command
-	let (tx_vec, _) = updater::retrieve_txs(wallet, None, Some(slate.id), None, false, false)?;
+	let (tx_vec, _) =
+		updater::retrieve_txs(wallet, None, Some(slate.id), None, false, false, None, None)?;
 	let mut tx = None;
 	// don't want to assume this is the right tx, in case of self-sending
 	for t in tx_vec {
@@ -374,13 +1345,19 @@ where
 			break;
 		}
 	}
-	let tx = match tx {
+	let mut tx = match tx {
 		Some(t) => t,
 		None => return Err(ErrorKind::TransactionDoesntExist(slate.id.to_string()))?,
 	};
+	tx.messages = Some(slate.participant_messages());
+	if !is_invoiced {
+		// A payjoin recipient may have raised it
+		tx.fee = Some(slate.fee);
+	}
 	{
 		let mut batch = wallet.batch()?;
 		let id = tx.tx_slate_id.unwrap().to_string();
+		batch.save_tx_log_entry(&tx)?;
 		batch.store_tx(&id, &slate.tx)?;
 		if let Some(proof) = tx_proof {
 			batch.store_tx_proof(&id, proof)?;
@@ -400,7 +1377,8 @@ where
 	C: NodeClient,
 	K: Keychain,
 {
-	let (tx_vec, _) = updater::retrieve_txs(wallet, None, Some(slate.id), None, false, false)?;
+	let (tx_vec, _) =
+		updater::retrieve_txs(wallet, None, Some(slate.id), None, false, false, None, None)?;
 	let mut tx = None;
 	// don't want to assume this is the right tx, in case of self-sending
 	for t in tx_vec {
@@ -448,50 +1426,516 @@ where
 	selection::lock_tx_context(wallet, slate, address, &context)
 }
 
-/// Finalize slate
-pub fn finalize_tx<T: ?Sized, C, K>(
+/// Private context of an unconfirmed send, to finalize it in another
+/// wallet holding the same seed
+pub fn export_private_context<T: ?Sized, C, K>(
 	wallet: &mut T,
-	slate: &Slate,
-	tx_proof: Option<&mut TxProof>,
-) -> Result<Slate, Error>
+	parent_key_id: &Identifier,
+	tx_id: u32,
+) -> Result<(Uuid, Context), Error>
 where
 	T: WalletBackend<C, K>,
 	C: NodeClient,
 	K: Keychain,
 {
-	let mut s = slate.clone();
-	let context = wallet.get_private_context(s.id.as_bytes(), 0)?;
+	let (txs, _) = updater::retrieve_txs(
+		wallet,
+		Some(tx_id),
+		None,
+		Some(parent_key_id),
+		false,
+		false,
+		None,
+		None,
+	)?;
+	let tx = txs
+		.into_iter()
+		.next()
+		.ok_or(ErrorKind::TransactionDoesntExist(tx_id.to_string()))?;
+	let slate_id = match tx.tx_slate_id {
+		Some(id) if tx.tx_type == TxLogEntryType::TxSent && !tx.confirmed => id,
+		_ => return Err(ErrorKind::NoPrivateContext(tx_id.to_string()))?,
+	};
+	let context = wallet
+		.get_private_context(slate_id.as_bytes(), 0)
+		.map_err(|_| ErrorKind::NoPrivateContext(tx_id.to_string()))?;
+	Ok((slate_id, context))
+}
 
-	let tx_proof = tx_proof.map(|proof| {
-		proof.amount = context.amount;
-		proof.fee = context.fee;
-		for input in &context.input_commits {
-			proof.inputs.push(input.clone());
+/// Store the private context of a send made by another wallet with the
+/// same seed, after checking its keys against those in the slate. If this
+/// wallet has no record of the send, its inputs are locked as they were on
+/// the sending side, so the response can be finalized here
+pub fn import_private_context<T: ?Sized, C, K>(
+	wallet: &mut T,
+	slate: &Slate,
+	context: &Context,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let participant = slate
+		.participant_data
+		.iter()
+		.find(|p| p.id == context.participant_id as u64)
+		.ok_or(ErrorKind::ContextMismatch(
+			"participant not in slate".to_owned(),
+		))?;
+	{
+		let secp = wallet.keychain().secp();
+		let public_blind_excess = PublicKey::from_secret_key(secp, &context.sec_key)?;
+		let public_nonce = PublicKey::from_secret_key(secp, &context.sec_nonce)?;
+		if public_blind_excess != participant.public_blind_excess
+			|| public_nonce != participant.public_nonce
+		{
+			return Err(ErrorKind::ContextMismatch("keys differ".to_owned()))?;
 		}
-		for output in &context.output_commits {
-			proof.outputs.push(output.clone());
+	}
+	for (id, mmr_index, _) in &context.input_ids {
+		if wallet.get_output(id, mmr_index).is_err() {
+			return Err(ErrorKind::ContextMismatch(format!(
+				"input {} not in this wallet, run restore first",
+				id
+			)))?;
 		}
-		proof
-	});
+	}
 
-	complete_tx(wallet, &mut s, 0, &context)?;
-	update_stored_excess(wallet, &s, true)?;
+	{
+		let mut batch = wallet.batch()?;
+		batch.save_private_context(slate.id.as_bytes(), context.participant_id, context)?;
+		batch.commit()?;
+	}
+
+	let (txs, _) = updater::retrieve_txs(
+		wallet,
+		None,
+		Some(slate.id),
+		Some(&context.parent_key_id),
+		false,
+		false,
+		None,
+		None,
+	)?;
+	if !txs.iter().any(|t| t.tx_type == TxLogEntryType::TxSent) {
+		selection::lock_tx_context(wallet, slate, None, context)?;
+	}
+	Ok(())
+}
+
+/// Unconfirmed sends of an account whose private context is still
+/// stored, meaning the response hasn't been finalized yet. Each comes
+/// with its queued slate, if any, oldest first
+pub fn pending_txs<T: ?Sized, C, K>(
+	wallet: &mut T,
+	parent_key_id: &Identifier,
+) -> Result<Vec<(TxLogEntry, Option<QueuedSlate>)>, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let (txs, _) = updater::retrieve_txs(
+		wallet,
+		None,
+		None,
+		Some(parent_key_id),
+		false,
+		false,
+		None,
+		None,
+	)?;
+
+	let mut pending = Vec::new();
+	for tx in txs {
+		let slate_id = match tx.tx_slate_id {
+			Some(id) if tx.tx_type == TxLogEntryType::TxSent && !tx.confirmed => id,
+			_ => continue,
+		};
+		if wallet.get_private_context(slate_id.as_bytes(), 0).is_err() {
+			continue;
+		}
+		let queued = wallet.get_queued_slate(&slate_id)?;
+		pending.push((tx, queued));
+	}
+	Ok(pending)
+}
+
+/// Store a slate in the retry queue, replacing an earlier version of it
+pub fn queue_slate<T: ?Sized, C, K>(wallet: &mut T, queued: &QueuedSlate) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let mut batch = wallet.batch()?;
+	batch.save_queued_slate(queued)?;
+	batch.commit()?;
+	Ok(())
+}
+
+/// Apply the `receive_*` rules to a slate from `sender` before it is
+/// signed. Returns whether the slate is held for approval, in which case it
+/// is stored until approved or rejected. Refused slates fail
+pub fn check_receive_policy<T: ?Sized, C, K>(
+	wallet: &mut T,
+	policy: &ReceivePolicy,
+	slate: &Slate,
+	sender: &str,
+	dest_acct_name: Option<&str>,
+	message: Option<String>,
+) -> Result<bool, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	// The sender posting a held slate again
+	match wallet.get_incoming_slate(&slate.id)? {
+		Some(incoming) if incoming.rejected => {
+			let reason = "rejected by the recipient".to_owned();
+			return Err(ErrorKind::SlateRefused(reason).into());
+		}
+		Some(_) => return Ok(true),
+		None => {}
+	}
+
+	let now = wallet.clock().now();
+	let since = now - Duration::hours(1);
+	let received = wallet
+		.tx_logs()?
+		.filter(|t| t.tx_type == TxLogEntryType::TxReceived && t.creation_ts >= since)
+		.count();
+	let amount = slate.recipient_amount(slate.participant_data.len());
+	match policy.decide(amount, sender, received) {
+		ReceiveDecision::Accept => Ok(false),
+		ReceiveDecision::Refuse(reason) => Err(ErrorKind::SlateRefused(reason).into()),
+		ReceiveDecision::Hold => {
+			// The response of an approved slate is sent back later, which a
+			// sender waiting on an http request won't see
+			match parse_address(sender) {
+				Ok(a) if a.address_type() != AddressType::Http => {}
+				_ => {
+					let reason = "slates need approval, send over grinbox or keybase".to_owned();
+					return Err(ErrorKind::SlateRefused(reason).into());
+				}
+			}
+			let account = dest_acct_name.map(|a| a.to_owned());
+			let sender = sender.to_owned();
+			let incoming = IncomingSlate::new(slate.clone(), sender, account, message, now);
+			let mut batch = wallet.batch()?;
+			batch.save_incoming_slate(&incoming)?;
+			batch.commit()?;
+			Ok(true)
+		}
+	}
+}
+
+/// Slates held for approval and the rejected ones, oldest first
+pub fn incoming_slates<T: ?Sized, C, K>(wallet: &mut T) -> Result<Vec<IncomingSlate>, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let mut incoming: Vec<IncomingSlate> = wallet.incoming_slates()?.collect();
+	incoming.sort_by_key(|i| i.received);
+	Ok(incoming)
+}
+
+/// Take a slate held for approval out of the queue once approved, or keep
+/// it as rejected so the sender posting it again is refused
+pub fn resolve_incoming_slate<T: ?Sized, C, K>(
+	wallet: &mut T,
+	slate_id: &Uuid,
+	rejected: bool,
+) -> Result<IncomingSlate, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let mut incoming = wallet
+		.get_incoming_slate(slate_id)?
+		.ok_or(ErrorKind::IncomingSlateNotFound(slate_id.to_string()))?;
+	let mut batch = wallet.batch()?;
+	match rejected {
+		true => {
+			incoming.rejected = true;
+			batch.save_incoming_slate(&incoming)?;
+		}
+		false => batch.delete_incoming_slate(slate_id)?,
+	}
+	batch.commit()?;
+	Ok(incoming)
+}
+
+/// Store a message read from a polled transport until it is processed.
+/// Returns false if the message was seen before, or carries a slate at a
+/// stage already received in another message. Processed messages past
+/// their retention are dropped on the way
+pub fn record_received_message<T: ?Sized, C, K>(
+	wallet: &mut T,
+	message: &ReceivedMessage,
+) -> Result<bool, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if wallet
+		.get_received_message(&message.method, &message.id)?
+		.is_some()
+	{
+		return Ok(false);
+	}
+
+	let before = wallet.clock().now() - Duration::days(RECEIVED_MESSAGE_RETENTION_DAYS);
+	let mut duplicate = false;
+	let mut expired = Vec::new();
+	for m in wallet.received_messages()? {
+		duplicate |= m.same_slate(message);
+		if m.processed && m.received < before {
+			expired.push(m);
+		}
+	}
+
+	let mut batch = wallet.batch()?;
+	for m in &expired {
+		batch.delete_received_message(&m.method, &m.id)?;
+	}
+	if duplicate {
+		// Remember the id all the same, so it is skipped without a lookup
+		let mut message = message.clone();
+		message.processed = true;
+		batch.save_received_message(&message)?;
+	} else {
+		batch.save_received_message(message)?;
+	}
+	batch.commit()?;
+	if duplicate {
+		info!(
+			"Skipping message {} from {}, slate {} was already received",
+			message.id, message.address, message.slate_id
+		);
+	}
+	Ok(!duplicate)
+}
+
+/// Messages of a transport waiting to be processed, oldest first
+pub fn pending_received_messages<T: ?Sized, C, K>(
+	wallet: &mut T,
+	method: &str,
+) -> Result<Vec<ReceivedMessage>, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let mut pending: Vec<ReceivedMessage> = wallet
+		.received_messages()?
+		.filter(|m| m.method == method && !m.processed)
+		.collect();
+	pending.sort_by_key(|m| m.received);
+	Ok(pending)
+}
+
+/// Flag a received message as processed, keeping it to recognize it later
+pub fn mark_message_processed<T: ?Sized, C, K>(
+	wallet: &mut T,
+	method: &str,
+	id: &str,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if let Some(mut message) = wallet.get_received_message(method, id)? {
+		message.processed = true;
+		let mut batch = wallet.batch()?;
+		batch.save_received_message(&message)?;
+		batch.commit()?;
+	}
+	Ok(())
+}
+
+/// Add a send to the total sent today
+pub fn record_daily_spend<T: ?Sized, C, K>(wallet: &mut T, amount: u64) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let today = wallet.clock().now().naive_utc().date();
+	let mut spend = wallet
+		.get_daily_spend(&today)?
+		.unwrap_or_else(|| DailySpend::new(today));
+	spend.amount = spend.amount.saturating_add(amount);
+	let mut batch = wallet.batch()?;
+	batch.save_daily_spend(&spend)?;
+	batch.commit()?;
+	Ok(())
+}
+
+/// Queued slates, oldest first, of a single account or of all accounts
+pub fn queued_slates<T: ?Sized, C, K>(
+	wallet: &mut T,
+	parent_key_id: Option<&Identifier>,
+) -> Result<Vec<QueuedSlate>, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let mut queued: Vec<QueuedSlate> = wallet
+		.queued_slates()?
+		.filter(|q| parent_key_id.map(|p| &q.parent_key_id == p).unwrap_or(true))
+		.collect();
+	queued.sort_by_key(|q| q.created);
+	Ok(queued)
+}
+
+/// Take the slate of a transaction out of the retry queue, leaving the
+/// transaction itself and its locked outputs untouched
+pub fn unqueue_slate<T: ?Sized, C, K>(
+	wallet: &mut T,
+	parent_key_id: &Identifier,
+	tx_id: u32,
+) -> Result<QueuedSlate, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let (txs, _) = updater::retrieve_txs(
+		wallet,
+		Some(tx_id),
+		None,
+		Some(parent_key_id),
+		false,
+		false,
+		None,
+		None,
+	)?;
+	let slate_id = txs
+		.into_iter()
+		.next()
+		.ok_or(ErrorKind::TransactionDoesntExist(tx_id.to_string()))?
+		.tx_slate_id
+		.ok_or(ErrorKind::SlateNotQueued(tx_id.to_string()))?;
+	let queued = wallet
+		.get_queued_slate(&slate_id)?
+		.ok_or(ErrorKind::SlateNotQueued(tx_id.to_string()))?;
+
+	let mut batch = wallet.batch()?;
+	batch.delete_queued_slate(&slate_id)?;
+	batch.commit()?;
+	Ok(queued)
+}
+
+/// Finalize slate
+pub fn finalize_tx<T: ?Sized, C, K>(
+	wallet: &mut T,
+	slate: &Slate,
+	tx_proof: Option<&mut TxProof>,
+) -> Result<Slate, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let mut s = slate.clone();
+	let mut context = wallet.get_private_context(s.id.as_bytes(), 0)?;
+	if s.participant_data.len() < s.num_participants
+		|| s.participant_data.iter().skip(1).any(|p| !p.is_complete())
+	{
+		return Err(ErrorKind::MissingRecipientSignatures(s.id.to_string()).into());
+	}
+	if let Some(late_lock) = context.late_lock.clone() {
+		add_late_inputs(wallet, &mut s, &mut context, &late_lock)?;
+		selection::lock_tx_context(wallet, &s, None, &context)?;
+	}
+	check_payjoin_fee(wallet, &s, &context)?;
+	context.fee = s.fee;
+
+	let tx_proof = tx_proof.map(|proof| {
+		proof.amount = context.amount;
+		proof.fee = context.fee;
+		for input in &context.input_commits {
+			proof.inputs.push(input.clone());
+		}
+		for output in &context.output_commits {
+			proof.outputs.push(output.clone());
+		}
+		proof
+	});
+
+	// Checked before signing, so a slate without the requested proof can
+	// still be finalized once the recipient signs it
+	update_payment_proof(wallet, &s)?;
+	complete_tx(wallet, &mut s, 0, &context)?;
+	update_stored_excess(wallet, &s, true)?;
 	update_stored_tx(wallet, &mut s, tx_proof, false)?;
 	{
 		let mut batch = wallet.batch()?;
 		batch.delete_private_context(s.id.as_bytes(), 0)?;
+		batch.delete_queued_slate(&s.id)?;
 		batch.commit()?;
 	}
 	Ok(s)
 }
 
-/// Receive a tx as recipient
+/// A payjoin recipient spends an input of its own and raises the fee to pay
+/// for it out of its output. Any other change of the fee we agreed to is refused
+fn check_payjoin_fee<T: ?Sized, C, K>(
+	wallet: &mut T,
+	slate: &Slate,
+	context: &Context,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if slate.fee == context.fee {
+		return Ok(());
+	}
+	let added_inputs = slate
+		.tx
+		.inputs()
+		.iter()
+		.filter(|i| !context.input_commits.contains(&i.commit))
+		.count();
+	let covered = tx_fee(
+		slate.tx.inputs().len(),
+		slate.tx.outputs().len(),
+		slate.tx.kernels().len(),
+		wallet.fee_base(),
+	);
+	if added_inputs != 1 || slate.fee < context.fee || slate.fee > covered {
+		return Err(ErrorKind::Fee(format!(
+			"Recipient changed the fee from {} to {}, more than its input explains",
+			amount_to_hr_string(context.fee, false),
+			amount_to_hr_string(slate.fee, false)
+		)))?;
+	}
+	Ok(())
+}
+
+/// Receive a tx as recipient. Amounts below `min_output_value` are refused.
+/// With `payjoin`, one of our outputs is spent into the received one
 pub fn receive_tx<T: ?Sized, C, K>(
 	w: &mut T,
 	slate: &Slate,
 	dest_acct_name: Option<&str>,
 	address: Option<String>,
+	contact: Option<String>,
 	message: Option<String>,
+	height: Option<u64>,
+	min_output_value: u64,
+	payjoin: bool,
 ) -> Result<Slate, Error>
 where
 	T: WalletBackend<C, K>,
@@ -517,12 +1961,34 @@ where
 		Some(&parent_key_id),
 		false,
 		false,
+		None,
+		None,
 	)?;
-	for t in &tx {
-		if t.tx_type == TxLogEntryType::TxReceived {
-			return Err(ErrorKind::TransactionAlreadyReceived(ret_slate.id.to_string()).into());
+	if tx.iter().any(|t| t.tx_type == TxLogEntryType::TxReceived) {
+		// A recipient of a transaction paying several gets the slate
+		// a second time to sign it
+		if slate.is_multi_recipient() {
+			if let Some(participant_id) = unsigned_participant_id(w, slate) {
+				return sign_multi_recipient_tx(w, slate, &parent_key_id, participant_id);
+			}
 		}
+		return Err(ErrorKind::TransactionAlreadyReceived(ret_slate.id.to_string()).into());
+	}
+
+	let participant_id = slate.participant_data.len();
+	if participant_id >= slate.num_participants {
+		return Err(ErrorKind::SlateFull(slate.id.to_string()).into());
 	}
+	let amount = slate.recipient_amount(participant_id);
+	if amount < min_output_value {
+		return Err(ErrorKind::BelowMinOutputValue(
+			amount_to_hr_string(amount, false),
+			amount_to_hr_string(min_output_value, false),
+		))?;
+	}
+	// Only the last recipient knows the public data of everyone, the
+	// others sign once the slate comes back complete
+	let is_last = participant_id + 1 == slate.num_participants;
 
 	let message = match message {
 		Some(mut m) => {
@@ -532,18 +1998,223 @@ where
 		None => None,
 	};
 
-	add_output_to_slate(
+	// Only a single recipient can contribute an input, as its signature
+	// over the renegotiated fee completes the slate
+	let input = match payjoin && !slate.is_multi_recipient() {
+		true => payjoin_input(w, &parent_key_id)?,
+		false => None,
+	};
+
+	// The output is recorded at the height of the slate, unless the caller
+	// provides one. The slate itself goes back unchanged
+	if let Some(height) = height {
+		ret_slate.height = height;
+	}
+	let context = add_output_to_slate(
 		w,
 		&mut ret_slate,
 		&parent_key_id,
-		1,
+		participant_id,
+		input,
 		address,
+		contact,
 		message,
-		false,
+		!is_last,
 	)?;
+	ret_slate.height = slate.height;
+	if !is_last {
+		let mut batch = w.batch()?;
+		batch.save_private_context(slate.id.as_bytes(), participant_id, &context)?;
+		batch.commit()?;
+	}
+
+	store_received_messages(w, &ret_slate, &parent_key_id)?;
 	Ok(ret_slate)
 }
 
+/// Smallest of our spendable outputs, to contribute to a received
+/// transaction. Spending it there consolidates it with the payment
+fn payjoin_input<T: ?Sized, C, K>(
+	w: &mut T,
+	parent_key_id: &Identifier,
+) -> Result<Option<OutputData>, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	updater::refresh_outputs(w, parent_key_id, false)?;
+	let height = w.w2n_client().get_chain_height()?;
+	let input = selection::eligible_outputs(w, height, PAYJOIN_MIN_CONFIRMATIONS, parent_key_id)
+		.into_iter()
+		.min_by_key(|o| o.value);
+	if input.is_none() {
+		info!("No output to contribute to the transaction, receiving without one");
+	}
+	Ok(input)
+}
+
+/// Keep the messages of all parties with the received entry of the slate
+fn store_received_messages<T: ?Sized, C, K>(
+	w: &mut T,
+	slate: &Slate,
+	parent_key_id: &Identifier,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let (tx, _) = updater::retrieve_txs(
+		w,
+		None,
+		Some(slate.id),
+		Some(parent_key_id),
+		false,
+		false,
+		None,
+		None,
+	)?;
+	if let Some(mut t) = tx
+		.into_iter()
+		.find(|t| t.tx_type == TxLogEntryType::TxReceived)
+	{
+		t.messages = Some(slate.participant_messages());
+		let mut batch = w.batch()?;
+		batch.save_tx_log_entry(&t)?;
+		batch.commit()?;
+	}
+	Ok(())
+}
+
+/// Participant id under which we added our data to a slate paying several
+/// recipients, if we haven't signed it yet
+fn unsigned_participant_id<T: ?Sized, C, K>(w: &mut T, slate: &Slate) -> Option<usize>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	(1..slate.participant_data.len())
+		.filter(|id| !slate.participant_data[*id].is_complete())
+		.find(|id| w.get_private_context(slate.id.as_bytes(), *id).is_ok())
+}
+
+/// Add our partial signature to a slate paying several recipients, once
+/// all of them have added their public data
+fn sign_multi_recipient_tx<T: ?Sized, C, K>(
+	w: &mut T,
+	slate: &Slate,
+	parent_key_id: &Identifier,
+	participant_id: usize,
+) -> Result<Slate, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if slate.participant_data.len() < slate.num_participants {
+		return Err(ErrorKind::MissingRecipients(slate.id.to_string()).into());
+	}
+	let context = w.get_private_context(slate.id.as_bytes(), participant_id)?;
+	let mut slate = slate.clone();
+	slate.fill_round_2(
+		w.signer(),
+		&context.sec_key,
+		&context.sec_nonce,
+		participant_id,
+	)?;
+	update_stored_excess(w, &slate, false)?;
+	{
+		let mut batch = w.batch()?;
+		batch.delete_private_context(slate.id.as_bytes(), participant_id)?;
+		batch.commit()?;
+	}
+	store_received_messages(w, &slate, parent_key_id)?;
+	Ok(slate)
+}
+
+/// Sign the payment proof requested by the sender, using the key of the
+/// receiver address. Does nothing if no proof was requested
+pub fn sign_payment_proof<T: ?Sized, C, K>(
+	wallet: &mut T,
+	slate: &mut Slate,
+	current_index: u32,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let receiver_address = match &slate.payment_proof {
+		Some(p) => p.receiver_address.clone(),
+		None => return Ok(()),
+	};
+	let (parent_key_id, index) = keys::address_path(wallet, &receiver_address, current_index)?
+		.ok_or(ErrorKind::PaymentProof(
+			"receiver address does not belong to this wallet".to_owned(),
+		))?;
+	let sec_key = derive_account_address_key(wallet.keychain(), &parent_key_id, index)?;
+	let excess = slate.sum_excess(wallet.keychain())?;
+
+	if let Some(p) = &mut slate.payment_proof {
+		let message = payment_proof_message(slate.amount, &excess, &p.sender_address);
+		p.receiver_signature = Some(sign_challenge(&message, &sec_key)?);
+	}
+	Ok(())
+}
+
+/// Verify the receiver signature on the payment proof and store it
+/// with the sender's tx log entry
+fn update_payment_proof<T: ?Sized, C, K>(wallet: &mut T, slate: &Slate) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let (tx_vec, _) =
+		updater::retrieve_txs(wallet, None, Some(slate.id), None, false, false, None, None)?;
+	let mut tx = match tx_vec
+		.into_iter()
+		.find(|t| t.tx_type == TxLogEntryType::TxSent)
+	{
+		Some(t) => t,
+		None => return Err(ErrorKind::TransactionDoesntExist(slate.id.to_string()))?,
+	};
+	let requested = match &tx.payment_proof {
+		Some(p) => p.clone(),
+		None => return Ok(()),
+	};
+
+	// Wallets without wallet713 payment proofs drop the request or leave it unsigned
+	let not_signed = || ErrorKind::PaymentProofNotSigned(slate.id.to_string());
+	let proof = slate.payment_proof.as_ref().ok_or_else(not_signed)?;
+	if proof.sender_address != requested.sender_address
+		|| proof.receiver_address != requested.receiver_address
+	{
+		return Err(ErrorKind::PaymentProof(
+			"addresses don't match request".to_owned(),
+		))?;
+	}
+	let signature = proof.receiver_signature.clone().ok_or_else(not_signed)?;
+
+	let excess = slate.sum_excess(wallet.keychain())?;
+	let message = payment_proof_message(slate.amount, &excess, &requested.sender_address);
+	verify_signature(&message, &signature, &requested.receiver_address)
+		.map_err(|_| ErrorKind::PaymentProof("invalid recipient signature".to_owned()))?;
+
+	tx.payment_proof = Some(PaymentInfo {
+		receiver_signature: Some(signature),
+		..requested
+	});
+	{
+		let mut batch = wallet.batch()?;
+		batch.save_tx_log_entry(&tx)?;
+		batch.commit()?;
+	}
+	Ok(())
+}
+
 /// Verifies a transaction proof and returns relevant information
 pub fn verify_tx_proof(
 	tx_proof: &TxProof,
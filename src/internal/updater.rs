@@ -14,28 +14,36 @@
 
 use super::keys;
 use crate::wallet::types::{
-	BlockFees, CbData, NodeClient, OutputCommitMapping, OutputData, OutputStatus, TxLogEntry,
-	TxLogEntryType, WalletBackend, WalletInfo,
+	BlockFees, BlockIdentifier, CbData, HistoryWipe, KernelReconciliation, NodeClient,
+	OutputCommitMapping, OutputData, OutputStatus, Page, TxEvent, TxFilter, TxLogEntry,
+	TxLogEntryType, TxWrapper, WalletBackend, WalletEvent, WalletInfo,
 };
+use chrono::Duration;
 use failure::Error;
-use grin_core::consensus::reward;
-use grin_core::core::{Output, TxKernel};
+use grin_core::consensus::{reward, DAY_HEIGHT};
+use grin_core::core::{Output, Transaction, TxKernel};
 use grin_core::global::coinbase_maturity;
 use grin_core::libtx::proof::ProofBuilder;
 use grin_core::libtx::reward;
+use grin_core::ser::{ser_vec, ProtocolVersion};
 use grin_keychain::{Identifier, Keychain, SwitchCommitmentType};
-use grin_util::from_hex;
 use grin_util::secp::pedersen::Commitment;
-use log::{debug, warn};
+use grin_util::{from_hex, to_hex};
+use log::{debug, info, warn};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+/// Number of blocks kept to detect reorgs, see `check_reorg`. A checkpoint is
+/// added on every refresh at a new height
+const MAX_CHECKPOINTS: usize = 30;
+
 /// Retrieve all of the outputs (doesn't attempt to update from node)
 pub fn retrieve_outputs<T: ?Sized, C, K>(
 	wallet: &mut T,
 	include_spent: bool,
 	tx_id: Option<u32>,
 	parent_key_id: Option<&Identifier>,
+	page: Option<Page>,
 ) -> Result<Vec<OutputCommitMapping>, Error>
 where
 	T: WalletBackend<C, K>,
@@ -43,32 +51,22 @@ where
 	K: Keychain,
 {
 	// just read the wallet here, no need for a write lock
-	let mut outputs = wallet
-		.outputs()?
+	let outputs = match parent_key_id {
+		Some(k) => wallet.account_outputs(k, &[])?,
+		None => wallet.outputs()?,
+	};
+	// only include outputs with a given tx_id if provided
+	let mut outputs = outputs
 		.filter(|out| include_spent || out.status != OutputStatus::Spent)
+		.filter(|out| tx_id.map(|id| out.tx_log_entry == Some(id)).unwrap_or(true))
 		.collect::<Vec<_>>();
 
-	// only include outputs with a given tx_id if provided
-	if let Some(id) = tx_id {
-		outputs = outputs
-			.into_iter()
-			.filter(|out| out.tx_log_entry == Some(id))
-			.collect::<Vec<_>>();
-	}
-
-	if let Some(k) = parent_key_id {
-		outputs = outputs
-			.iter()
-			.filter(|o| o.root_key_id == *k)
-			.map(|o| o.clone())
-			.collect();
-	}
-
 	outputs.sort_by_key(|out| out.n_child);
 	let keychain = wallet.keychain().clone();
 
-	let res = outputs
-		.into_iter()
+	let res = page
+		.unwrap_or_default()
+		.apply(outputs.into_iter())
 		.map(|output| {
 			let commit = match output.commit.clone() {
 				Some(c) => Commitment::from_vec(from_hex(c).unwrap()),
@@ -83,7 +81,9 @@ where
 }
 
 /// Retrieve all of the transaction entries, or a particular entry
-/// if `parent_key_id` is set, only return entries from that key
+/// if `parent_key_id` is set, only return entries from that key.
+/// Entries that don't match `filter` are left out, and only the entries
+/// in `page` are returned
 pub fn retrieve_txs<T: ?Sized, C, K>(
 	wallet: &mut T,
 	tx_id: Option<u32>,
@@ -91,6 +91,8 @@ pub fn retrieve_txs<T: ?Sized, C, K>(
 	parent_key_id: Option<&Identifier>,
 	outstanding_only: bool,
 	check_proofs: bool,
+	filter: Option<&TxFilter>,
+	page: Option<Page>,
 ) -> Result<(Vec<TxLogEntry>, HashMap<Uuid, bool>), Error>
 where
 	T: WalletBackend<C, K>,
@@ -120,10 +122,15 @@ where
 				}
 				false => true,
 			};
-			f_pk && f_tx_id && f_txs && f_outstanding
+			let f_filter = match filter {
+				Some(f) => f.matches(tx_entry),
+				None => true,
+			};
+			f_pk && f_tx_id && f_txs && f_outstanding && f_filter
 		})
 		.collect();
 	txs.sort_by_key(|tx| tx.creation_ts);
+	let txs: Vec<TxLogEntry> = page.unwrap_or_default().apply(txs.into_iter()).collect();
 
 	let mut proofs = HashMap::new();
 	if check_proofs {
@@ -155,10 +162,256 @@ where
 	K: Keychain,
 {
 	let height = wallet.w2n_client().get_chain_height()?;
+	if let Some(fork_height) = check_reorg(wallet, height)? {
+		warn!(
+			"The node switched to a fork below height {}, verifying the transactions \
+			 confirmed since again",
+			fork_height + 1
+		);
+	}
 	refresh_output_state(wallet, height, parent_key_id, update_all)?;
+	record_checkpoint(wallet, height)?;
 	Ok(height)
 }
 
+/// Look for a fork of the chain the wallet saw last, by comparing the
+/// blocks kept at checkpoint heights with the node's. Everything confirmed
+/// above the newest checkpoint still on the node's chain is rolled back to
+/// be verified again. Returns the height rolled back to, if any
+pub fn check_reorg<T: ?Sized, C, K>(wallet: &mut T, height: u64) -> Result<Option<u64>, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	// Newest first. Checkpoints above the node's height can't be compared,
+	// the node may still be syncing
+	let mut checkpoints: Vec<BlockIdentifier> = wallet
+		.checkpoints()?
+		.filter(|c| c.height <= height)
+		.collect();
+	checkpoints.reverse();
+
+	let mut forked = false;
+	let mut fork_height = 0;
+	for checkpoint in checkpoints {
+		let hash = wallet.w2n_client().get_header_hash(checkpoint.height)?;
+		if hash.as_ref() == Some(&checkpoint.hash) {
+			fork_height = checkpoint.height;
+			break;
+		}
+		forked = true;
+	}
+	if !forked {
+		return Ok(None);
+	}
+	rollback(wallet, fork_height)?;
+	Ok(Some(fork_height))
+}
+
+/// Undo what was confirmed above `height`: transactions become unconfirmed,
+/// their outputs unconfirmed and the inputs of sent ones locked again, so
+/// the next refresh checks them against the node's chain
+fn rollback<T: ?Sized, C, K>(wallet: &mut T, height: u64) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let txs: Vec<TxLogEntry> = wallet
+		.tx_logs()?
+		.filter(|t| t.confirmed && t.confirmation_height.map_or(false, |h| h > height))
+		.collect();
+
+	// Outputs spent by the transactions rolled back
+	let mut inputs = vec![];
+	for t in txs.iter().filter(|t| t.tx_type == TxLogEntryType::TxSent) {
+		let slate_id = match t.tx_slate_id {
+			Some(id) => id.to_string(),
+			None => continue,
+		};
+		if let Some(tx) = wallet.get_stored_tx(&slate_id)? {
+			inputs.extend(
+				tx.inputs()
+					.iter()
+					.map(|i| to_hex(i.commitment().0.to_vec())),
+			);
+		}
+	}
+
+	let outputs: Vec<OutputData> = wallet
+		.outputs()?
+		.filter(|o| match o.status {
+			OutputStatus::Unspent => o.height > height,
+			OutputStatus::Spent => o.commit.as_ref().map_or(false, |c| inputs.contains(c)),
+			_ => false,
+		})
+		.collect();
+	let checkpoints: Vec<u64> = wallet
+		.checkpoints()?
+		.map(|c| c.height)
+		.filter(|h| *h > height)
+		.collect();
+
+	let mut batch = wallet.batch()?;
+	for mut t in txs {
+		t.confirmed = false;
+		t.confirmation_ts = None;
+		t.confirmation_height = None;
+		t.confirmation_hash = None;
+		batch.save_tx_log_entry(&t)?;
+	}
+	for mut output in outputs {
+		output.status = match output.status {
+			OutputStatus::Spent => OutputStatus::Locked,
+			_ => OutputStatus::Unconfirmed,
+		};
+		batch.save_output(&output)?;
+	}
+	for checkpoint in checkpoints {
+		batch.delete_checkpoint(checkpoint)?;
+	}
+	batch.save_last_confirmed_height(height)?;
+	batch.commit()?;
+	Ok(())
+}
+
+/// Keep the block at `height` as the newest checkpoint, dropping the oldest
+/// beyond `MAX_CHECKPOINTS`
+fn record_checkpoint<T: ?Sized, C, K>(wallet: &mut T, height: u64) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let checkpoints: Vec<u64> = wallet.checkpoints()?.map(|c| c.height).collect();
+	if checkpoints.last().map_or(false, |h| *h >= height) {
+		return Ok(());
+	}
+	let hash = match wallet.w2n_client().get_header_hash(height)? {
+		Some(hash) => hash,
+		None => return Ok(()),
+	};
+
+	let mut batch = wallet.batch()?;
+	batch.save_checkpoint(&BlockIdentifier { height, hash })?;
+	let excess = (checkpoints.len() + 1).saturating_sub(MAX_CHECKPOINTS);
+	for old in &checkpoints[..excess] {
+		batch.delete_checkpoint(*old)?;
+	}
+	batch.commit()?;
+	Ok(())
+}
+
+/// When to automatically repost transactions that were posted
+/// but didn't confirm
+#[derive(Clone, Copy, Debug)]
+pub struct RepostPolicy {
+	/// Number of blocks to wait for confirmation before reposting
+	pub after_blocks: u64,
+	/// Maximum number of automatic reposts per transaction
+	pub max_attempts: u32,
+	/// Whether to skip the dandelion relay when reposting
+	pub fluff: bool,
+}
+
+/// Record the chain height at which a transaction was posted, so it
+/// can be reposted if it doesn't confirm. Matches log entries by kernel excess
+pub fn mark_posted<T: ?Sized, C, K>(wallet: &mut T, tx: &Transaction) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let height = wallet.get_last_confirmed_height()?;
+	let excesses: Vec<Commitment> = tx.kernels().iter().map(|k| k.excess.clone()).collect();
+	let entries: Vec<TxLogEntry> = wallet
+		.tx_logs()?
+		.filter(|t| match &t.excess {
+			Some(e) => excesses.contains(e),
+			None => false,
+		})
+		.collect();
+
+	let mut batch = wallet.batch()?;
+	for mut entry in entries {
+		entry.posted_height = Some(height);
+		batch.save_tx_log_entry(&entry)?;
+	}
+	batch.commit()?;
+	Ok(())
+}
+
+/// Repost stored transactions that were posted, but remain unconfirmed
+/// `after_blocks` blocks later. A transaction that is still in the node's
+/// pool is rejected by the node, which also counts as an attempt
+pub fn auto_repost<T: ?Sized, C, K>(
+	wallet: &mut T,
+	height: u64,
+	policy: &RepostPolicy,
+) -> Result<Vec<u32>, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let entries: Vec<TxLogEntry> = wallet
+		.tx_logs()?
+		.filter(|t| {
+			!t.confirmed
+				&& (t.tx_type == TxLogEntryType::TxSent || t.tx_type == TxLogEntryType::TxReceived)
+				&& t.repost_attempts < policy.max_attempts
+				&& match t.posted_height {
+					Some(h) => height >= h + policy.after_blocks,
+					None => false,
+				}
+		})
+		.collect();
+
+	let mut reposted = Vec::new();
+	for mut entry in entries {
+		let slate_id = match entry.tx_slate_id {
+			Some(id) => id.to_string(),
+			None => continue,
+		};
+		let tx = match wallet.get_stored_tx(&slate_id)? {
+			Some(tx) => tx,
+			None => continue,
+		};
+
+		entry.repost_attempts += 1;
+		entry.posted_height = Some(height);
+		let tx_hex = to_hex(ser_vec(&tx, ProtocolVersion(1))?);
+		match wallet
+			.w2n_client()
+			.post_tx(&TxWrapper { tx_hex }, policy.fluff)
+		{
+			Ok(_) => {
+				info!(
+					"Automatically reposted unconfirmed tx {} (attempt {}/{})",
+					entry.id, entry.repost_attempts, policy.max_attempts
+				);
+				reposted.push(entry.id);
+			}
+			Err(e) => warn!(
+				"Automatic repost of tx {} failed (attempt {}/{}): {}",
+				entry.id, entry.repost_attempts, policy.max_attempts, e
+			),
+		}
+		if entry.repost_attempts >= policy.max_attempts {
+			warn!(
+				"Tx {} is still unconfirmed after {} automatic reposts, giving up",
+				entry.id, entry.repost_attempts
+			);
+		}
+
+		let mut batch = wallet.batch()?;
+		batch.save_tx_log_entry(&entry)?;
+		batch.commit()?;
+	}
+	Ok(reposted)
+}
+
 /// build a local map of wallet outputs keyed by commit
 /// and a list of outputs we want to query the node for
 pub fn map_wallet_outputs<T: ?Sized, C, K>(
@@ -173,12 +426,23 @@ where
 {
 	let mut wallet_outputs: HashMap<Commitment, (Identifier, Option<u64>)> = HashMap::new();
 	let keychain = wallet.keychain().clone();
-	let unspents: Vec<OutputData> = wallet
-		.outputs()?
-		.filter(|x| x.root_key_id == *parent_key_id && x.status != OutputStatus::Spent)
-		.collect();
+	let statuses = [
+		OutputStatus::Unconfirmed,
+		OutputStatus::Unspent,
+		OutputStatus::Locked,
+	];
+	let unspents: Vec<OutputData> = wallet.account_outputs(parent_key_id, &statuses)?.collect();
 
-	let (tx_entries, _) = retrieve_txs(wallet, None, None, Some(&parent_key_id), true, false)?;
+	let (tx_entries, _) = retrieve_txs(
+		wallet,
+		None,
+		None,
+		Some(&parent_key_id),
+		true,
+		false,
+		None,
+		None,
+	)?;
 
 	// Only select outputs that are actually involved in an outstanding transaction
 	let unspents: Vec<OutputData> = match update_all {
@@ -237,6 +501,9 @@ where
 	let mut tx = tx.clone();
 	if tx.tx_type == TxLogEntryType::TxSent {
 		tx.tx_type = TxLogEntryType::TxSentCancelled;
+		if let Some(slate_id) = &tx.tx_slate_id {
+			batch.delete_queued_slate(slate_id)?;
+		}
 	}
 	if tx.tx_type == TxLogEntryType::TxReceived {
 		tx.tx_type = TxLogEntryType::TxReceivedCancelled;
@@ -274,39 +541,50 @@ where
 			warn!("Please wait for sync on node to complete or fork to resolve and try again.");
 			return Ok(());
 		}
+		let now = wallet.clock().now();
+		let mut confirmed: Vec<TxLogEntry> = vec![];
 		let mut batch = wallet.batch()?;
 		for (commit, (id, mmr_index)) in wallet_outputs.iter() {
 			if let Ok(mut output) = wallet.get_output(id, mmr_index) {
 				match api_outputs.get(&commit) {
 					Some(o) => {
-						// if this is a coinbase tx being confirmed, it's recordable in tx log
-						if output.is_coinbase && output.status == OutputStatus::Unconfirmed {
+						// if this is a coinbase tx being confirmed, it's recordable in tx log.
+						// One confirmed before a reorg rolled it back already is
+						let new_coinbase = output.is_coinbase && output.tx_log_entry.is_none();
+						if new_coinbase && output.status == OutputStatus::Unconfirmed {
 							let log_id = batch.next_tx_log_id(parent_key_id)?;
 							let mut t = TxLogEntry::new(
 								parent_key_id.clone(),
 								TxLogEntryType::ConfirmedCoinbase,
 								log_id,
+								now,
 							);
 							t.confirmed = true;
 							t.amount_credited = output.value;
 							t.amount_debited = 0;
 							t.num_outputs = 1;
-							t.update_confirmation_ts();
+							t.update_confirmation_ts(now);
+							t.confirmation_height = Some(o.1);
 							output.tx_log_entry = Some(log_id);
 							batch.save_tx_log_entry(&t)?;
+							confirmed.push(t);
 						}
 						// also mark the transaction in which this output is involved as confirmed
 						// note that one involved input/output confirmation SHOULD be enough
 						// to reliably confirm the tx
-						if !output.is_coinbase && output.status == OutputStatus::Unconfirmed {
+						if !new_coinbase && output.status == OutputStatus::Unconfirmed {
 							let tx = wallet.tx_logs()?.find(|t| {
 								Some(t.id) == output.tx_log_entry
 									&& t.parent_key_id == *parent_key_id
 							});
 							if let Some(mut t) = tx {
-								t.update_confirmation_ts();
+								t.update_confirmation_ts(now);
+								t.confirmation_height = Some(o.1);
 								t.confirmed = true;
 								batch.save_tx_log_entry(&t)?;
+								if confirmed.iter().all(|c| c.id != t.id) {
+									confirmed.push(t);
+								}
 							}
 						}
 						output.height = o.1;
@@ -319,6 +597,140 @@ where
 		}
 		batch.save_last_confirmed_height(height)?;
 		batch.commit()?;
+		record_block_hashes(wallet, &mut confirmed)?;
+		publish_confirmed(wallet, parent_key_id, confirmed)?;
+	}
+	Ok(())
+}
+
+/// Confirm the transactions whose kernel is on chain. Outputs only show a
+/// transaction confirmed while they are unspent, which misses the ones
+/// whose outputs were all spent since, or that had none of ours. Runs after
+/// the outputs of `height` were applied
+fn confirm_by_kernels<T: ?Sized, C, K>(
+	wallet: &mut T,
+	height: u64,
+	parent_key_id: &Identifier,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let txs: Vec<(TxLogEntry, Commitment)> = wallet
+		.tx_logs()?
+		.filter(|t| t.parent_key_id == *parent_key_id && !t.confirmed)
+		.filter(|t| match t.tx_type {
+			TxLogEntryType::TxSent | TxLogEntryType::TxReceived => true,
+			_ => false,
+		})
+		.filter_map(|t| t.excess.clone().map(|e| (t, e)))
+		.collect();
+
+	let mut confirmed = vec![];
+	for (t, excess) in txs {
+		let kernel = wallet
+			.w2n_client()
+			.get_kernel(&excess, t.creation_height, Some(height))?;
+		if let Some((_, kernel_height, _)) = kernel {
+			confirmed.push((t, kernel_height));
+		}
+	}
+	if confirmed.is_empty() {
+		return Ok(());
+	}
+
+	// Outputs of these transactions the node didn't report as unspent at
+	// `height` were spent since
+	let spent: Vec<OutputData> = wallet
+		.account_outputs(parent_key_id, &[OutputStatus::Unconfirmed])?
+		.filter(|o| !o.is_coinbase)
+		.filter(|o| confirmed.iter().any(|(t, _)| o.tx_log_entry == Some(t.id)))
+		.collect();
+
+	let now = wallet.clock().now();
+	let mut batch = wallet.batch()?;
+	for (t, kernel_height) in confirmed.iter_mut() {
+		t.confirmed = true;
+		t.update_confirmation_ts(now);
+		t.confirmation_height = Some(*kernel_height);
+		batch.save_tx_log_entry(t)?;
+	}
+	for mut output in spent {
+		output.status = OutputStatus::Spent;
+		batch.save_output(&output)?;
+	}
+	batch.commit()?;
+
+	let mut confirmed: Vec<TxLogEntry> = confirmed.into_iter().map(|(t, _)| t).collect();
+	record_block_hashes(wallet, &mut confirmed)?;
+	publish_confirmed(wallet, parent_key_id, confirmed)
+}
+
+/// Record the hash of the block each of `confirmed` was confirmed in, to
+/// look the block up on an explorer. A node that doesn't answer leaves the
+/// hash out, it doesn't hold up the confirmation
+fn record_block_hashes<T: ?Sized, C, K>(
+	wallet: &mut T,
+	confirmed: &mut [TxLogEntry],
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let mut hashes: HashMap<u64, Option<String>> = HashMap::new();
+	for t in confirmed.iter_mut() {
+		let height = match t.confirmation_height {
+			Some(h) => h,
+			None => continue,
+		};
+		if !hashes.contains_key(&height) {
+			let hash = wallet
+				.w2n_client()
+				.get_header_hash(height)
+				.unwrap_or_else(|e| {
+					debug!("Unable to get the hash of block {}: {}", height, e);
+					None
+				});
+			hashes.insert(height, hash);
+		}
+		t.confirmation_hash = hashes[&height].clone();
+	}
+
+	if confirmed.iter().all(|t| t.confirmation_hash.is_none()) {
+		return Ok(());
+	}
+	let mut batch = wallet.batch()?;
+	for t in confirmed.iter().filter(|t| t.confirmation_hash.is_some()) {
+		batch.save_tx_log_entry(t)?;
+	}
+	batch.commit()?;
+	Ok(())
+}
+
+fn publish_confirmed<T: ?Sized, C, K>(
+	wallet: &mut T,
+	parent_key_id: &Identifier,
+	confirmed: Vec<TxLogEntry>,
+) -> Result<(), Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if confirmed.is_empty() {
+		return Ok(());
+	}
+	let account = wallet
+		.accounts()?
+		.find(|m| m.path == *parent_key_id)
+		.map(|m| m.label)
+		.unwrap_or_default();
+	let now = wallet.clock().now();
+	let events = wallet.events();
+	for t in confirmed {
+		events.publish(WalletEvent::TxConfirmed(TxEvent::new(&account, &t, now)));
 	}
 	Ok(())
 }
@@ -348,6 +760,7 @@ where
 		.w2n_client()
 		.get_outputs_from_node(wallet_output_keys)?;
 	apply_api_outputs(wallet, &wallet_outputs, &api_outputs, height, parent_key_id)?;
+	confirm_by_kernels(wallet, height, parent_key_id)?;
 	clean_old_unconfirmed(wallet, height)?;
 	Ok(())
 }
@@ -392,9 +805,7 @@ where
 	K: Keychain,
 {
 	let current_height = wallet.get_last_confirmed_height()?;
-	let outputs = wallet
-		.outputs()?
-		.filter(|out| out.root_key_id == *parent_key_id);
+	let outputs = wallet.account_outputs(parent_key_id, &[])?;
 
 	let mut unspent_total = 0;
 	let mut immature_total = 0;
@@ -541,7 +952,7 @@ where
 	match res {
 		Ok(height) => Ok((true, height)),
 		Err(_) => {
-			let outputs = retrieve_outputs(wallet, true, None, None)?;
+			let outputs = retrieve_outputs(wallet, true, None, None, None)?;
 			let height = match outputs.iter().map(|m| m.output.height).max() {
 				Some(height) => height,
 				None => 0,
@@ -550,3 +961,151 @@ where
 		}
 	}
 }
+
+/// Check that the kernels of the transactions confirmed in the last `days`
+/// days are still on chain. A kernel can disappear after a deep reorg, or
+/// when an old backup was restored over a newer wallet
+pub fn reconcile_kernels<T: ?Sized, C, K>(
+	wallet: &mut T,
+	days: u64,
+) -> Result<KernelReconciliation, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let since = wallet.clock().now() - Duration::days(days as i64);
+	let height = wallet.w2n_client().get_chain_height()?;
+	// Block times vary, so look one day further back than needed
+	let min_height = height.saturating_sub((days + 1) * DAY_HEIGHT);
+
+	let txs: Vec<TxLogEntry> = wallet
+		.tx_logs()?
+		.filter(|t| t.confirmed)
+		.filter(|t| match t.tx_type {
+			TxLogEntryType::TxSent | TxLogEntryType::TxReceived => true,
+			_ => false,
+		})
+		.filter(|t| t.confirmation_ts.unwrap_or(t.creation_ts) >= since)
+		.collect();
+
+	let mut found = 0;
+	let mut unverifiable = 0;
+	let mut missing = Vec::new();
+	for tx in txs {
+		let excess = match &tx.excess {
+			Some(e) => e.clone(),
+			None => {
+				unverifiable += 1;
+				continue;
+			}
+		};
+		match wallet
+			.w2n_client()
+			.get_kernel(&excess, Some(min_height), None)?
+		{
+			Some(_) => found += 1,
+			None => {
+				warn!(
+					"Kernel of confirmed transaction {} ({:?}) not found on chain",
+					tx.id, excess
+				);
+				missing.push(tx);
+			}
+		}
+	}
+
+	let repair_from = wallet
+		.outputs()?
+		.filter(|o| {
+			missing
+				.iter()
+				.any(|t| o.root_key_id == t.parent_key_id && o.tx_log_entry == Some(t.id))
+		})
+		.filter_map(|o| o.mmr_index)
+		.min();
+
+	Ok(KernelReconciliation {
+		since,
+		found,
+		unverifiable,
+		missing,
+		repair_from,
+	})
+}
+
+/// Remove the counterparty address, label, notes, payment proof and stored
+/// transaction files of finished transactions created more than `days`
+/// days ago. Amounts, fees and kernel excesses are kept for accounting
+pub fn wipe_history<T: ?Sized, C, K>(wallet: &mut T, days: u64) -> Result<HistoryWipe, Error>
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	let before = wallet.clock().now() - Duration::days(days as i64);
+	let txs: Vec<TxLogEntry> = wallet
+		.tx_logs()?
+		.filter(|t| {
+			t.creation_ts < before
+				&& (t.confirmed
+					|| t.tx_type == TxLogEntryType::TxSentCancelled
+					|| t.tx_type == TxLogEntryType::TxReceivedCancelled)
+		})
+		.collect();
+
+	let mut wipe = HistoryWipe {
+		before,
+		entries: 0,
+		stored_txs: 0,
+		proofs: 0,
+	};
+	let mut batch = wallet.batch()?;
+	for mut t in txs {
+		let mut changed = false;
+		if let Some(slate_id) = t.tx_slate_id.map(|id| id.to_string()) {
+			if let Some(tx) = wallet.get_stored_tx(&slate_id)? {
+				// The excess is all that is needed to find the kernel later on
+				if t.excess.is_none() {
+					t.excess = tx.kernels().first().map(|k| k.excess.clone());
+					changed = true;
+				}
+				batch.delete_stored_tx(&slate_id)?;
+				wipe.stored_txs += 1;
+			}
+			if wallet.has_stored_tx_proof(&slate_id)? {
+				batch.delete_stored_tx_proof(&slate_id)?;
+				wipe.proofs += 1;
+			}
+		}
+
+		changed |= t.address.is_some()
+			|| t.contact.is_some()
+			|| t.messages.is_some()
+			|| t.stored_tx.is_some()
+			|| t.payment_proof.is_some()
+			|| t.label.is_some()
+			|| !t.notes.is_empty();
+		if !changed {
+			continue;
+		}
+		t.address = None;
+		t.contact = None;
+		t.messages = None;
+		t.stored_tx = None;
+		t.payment_proof = None;
+		t.label = None;
+		t.notes.clear();
+		batch.save_tx_log_entry(&t)?;
+		wipe.entries += 1;
+	}
+	batch.commit()?;
+
+	if !wipe.is_empty() {
+		info!(
+			"Wiped the history of {} transaction(s) created before {}",
+			wipe.entries, before
+		);
+	}
+	Ok(wipe)
+}
@@ -0,0 +1,155 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! wallet713-core, the wallet behind the `wallet713` binary.
+//!
+//! `run` starts a wallet the way the binary does, with the runtime of the
+//! caller's choosing. Wallets embedded in another application use `open`
+//! instead and drive the container through `Owner` and `Foreign`. The
+//! re-exports below are the stable surface, the modules expose the rest.
+
+pub mod api;
+pub mod broker;
+#[macro_use]
+pub mod common;
+pub mod contacts;
+mod controller;
+mod internal;
+pub mod wallet;
+
+pub use common::config::Wallet713Config;
+pub use common::{Error, ErrorKind, Result, RuntimeContext, RuntimeMode};
+pub use contacts::AddressBook;
+pub use wallet::api::{Foreign, Owner};
+pub use wallet::types::{NodeSession, WalletConfig};
+pub use wallet::{create_container, Container};
+
+use colored::*;
+use common::{shutdown, Arc, Mutex};
+use contacts::Backend as AddressBookStore;
+use controller::cli::CLI;
+use controller::config_wizard::{self, CheckStatus};
+use grin_core::global::{set_mining_mode, ChainTypes};
+use grin_keychain::ExtKeychain;
+use grin_util::ZeroingString;
+use log::{info, warn};
+use std::thread;
+use std::time::Duration;
+use wallet::types::{CachingNodeClient, HTTPNodeClient};
+use wallet::Backend;
+
+/// The container `open` returns, backed by lmdb and a grin node over HTTP
+pub type DefaultContainer =
+	Container<Backend<DefaultNodeClient, ExtKeychain>, DefaultNodeClient, ExtKeychain>;
+
+/// Node client of `DefaultContainer`, a grin node over HTTP with repeated
+/// queries answered from a cache
+pub type DefaultNodeClient = CachingNodeClient<HTTPNodeClient>;
+
+/// Run the wallet described by `config` on the command line until the user exits
+pub fn run(config: Wallet713Config) -> Result<()> {
+	run_with(config, RuntimeContext::default())
+}
+
+/// Same as `run`, in the mode and with the node session of `runtime`
+pub fn run_with(config: Wallet713Config, runtime: RuntimeContext) -> Result<()> {
+	if let Some(session) = runtime.node_session() {
+		let action = match session.is_replay() {
+			true => "Replaying",
+			false => "Recording",
+		};
+		cli_message!(
+			runtime,
+			"{} node calls with {}",
+			action,
+			session.path().bright_green()
+		);
+	}
+
+	let grace = config.shutdown_grace();
+	let container = open(config, runtime.clone())?;
+	if runtime.is_daemon() {
+		exit_on_signal(container.clone(), grace)?;
+	}
+	let cli = CLI::new(container);
+	cli.start();
+	Ok(())
+}
+
+/// Stop the daemon cleanly on SIGINT or SIGTERM instead of dying in the
+/// middle of a batch, then exit
+fn exit_on_signal(container: Arc<Mutex<DefaultContainer>>, grace: Duration) -> Result<()> {
+	shutdown::install()?;
+	thread::spawn(move || {
+		let signal = shutdown::wait();
+		info!("Received {}, shutting down", signal);
+		let code = match Owner::new(container).shutdown(grace) {
+			true => 0,
+			false => {
+				warn!(
+					"Wallet still busy after {}s, exiting anyway",
+					grace.as_secs()
+				);
+				1
+			}
+		};
+		log::logger().flush();
+		std::process::exit(code);
+	});
+	Ok(())
+}
+
+/// Ask for the main settings and write a config to `config_path`, or the
+/// default path of `chain`. Returns whether a config was written
+pub fn config_init(config_path: Option<&str>, chain: &Option<ChainTypes>) -> Result<bool> {
+	config_wizard::init(config_path, chain)
+}
+
+/// Check `config` against the node, grinbox relay and keybase client it
+/// uses and print what was found. Returns whether no errors were found
+pub fn config_check(config: &Wallet713Config) -> bool {
+	let checks = config_wizard::check(config);
+	controller::display::config_checks(&checks);
+	!checks.iter().any(|c| c.status == CheckStatus::Error)
+}
+
+/// Run a single command of the command line, as typed at the prompt, and
+/// return. Output is JSON with `json`, for the commands that support it.
+/// Without a password it is asked for. `runtime` should be in `Exec` mode
+pub fn exec(
+	config: Wallet713Config,
+	runtime: RuntimeContext,
+	command: &str,
+	password: Option<String>,
+	json: bool,
+) -> Result<()> {
+	let container = open(config, runtime)?;
+	let cli = CLI::new(container);
+	cli.exec(command, password.map(ZeroingString::from), json)
+}
+
+/// Set up the wallet described by `config` without a command line. The
+/// wallet still has to be unlocked with `Owner::set_seed` and `Owner::connect`
+pub fn open(
+	config: Wallet713Config,
+	runtime: RuntimeContext,
+) -> Result<Arc<Mutex<DefaultContainer>>> {
+	set_mining_mode(config.chain.clone().unwrap_or(ChainTypes::Mainnet));
+
+	let data_path_buf = config.get_data_path()?;
+	let data_path = data_path_buf.to_str().unwrap();
+	let address_book_backend = AddressBookStore::new(data_path)?;
+	let address_book = AddressBook::new(Box::new(address_book_backend))?;
+	create_container(config, address_book, runtime)
+}
@@ -12,26 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-mod api;
-mod broker;
-#[macro_use]
-mod common;
-mod contacts;
-mod controller;
-mod internal;
-mod wallet;
-
-use clap::{crate_version, App, Arg, ArgMatches};
+use clap::{crate_version, App, AppSettings, Arg, ArgMatches, SubCommand};
 use colored::*;
-use common::config::Wallet713Config;
-use common::{ErrorKind, Result, RuntimeMode};
-use contacts::{AddressBook, Backend};
-use controller::cli::CLI;
-use grin_core::global::{set_mining_mode, ChainTypes};
-use wallet::create_container;
+use grin_core::global::ChainTypes;
+use wallet713_core::cli_message;
+use wallet713_core::common::{logging, Error, ErrorKind, Result};
+use wallet713_core::{NodeSession, RuntimeContext, RuntimeMode, Wallet713Config};
+
+/// Password of the wallet for `--exec`, when no password file is given
+const PASSWORD_ENV: &str = "WALLET713_PASSWORD";
 
 fn do_config(
 	args: &ArgMatches,
+	runtime: &RuntimeContext,
 	chain: &Option<ChainTypes>,
 	silent: bool,
 	new_address_index: Option<u32>,
@@ -80,26 +73,57 @@ fn do_config(
 	config.to_file(config_path.map(|p| p.to_owned()))?;
 
 	if !any_matches && !silent {
-		cli_message!("{}", config);
+		cli_message!(runtime, "{}", config);
 	}
 
 	Ok(config)
 }
 
-fn welcome(args: &ArgMatches, runtime_mode: &RuntimeMode) -> Result<Wallet713Config> {
-	let chain: Option<ChainTypes> = match args.is_present("floonet") {
+fn chain(args: &ArgMatches) -> Option<ChainTypes> {
+	match args.is_present("floonet") {
 		true => Some(ChainTypes::Floonet),
 		false => Some(ChainTypes::Mainnet),
-	};
-
-	unsafe {
-		common::set_runtime_mode(runtime_mode);
-	};
+	}
+}
 
-	let config = do_config(args, &chain, true, None, args.value_of("config-path"))?;
-	set_mining_mode(config.chain.clone().unwrap_or(ChainTypes::Mainnet));
+fn welcome(args: &ArgMatches, runtime: &RuntimeContext) -> Result<Wallet713Config> {
+	do_config(
+		args,
+		runtime,
+		&chain(args),
+		true,
+		None,
+		args.value_of("config-path"),
+	)
+}
 
-	Ok(config)
+/// `config init` and `config check`, returns whether it succeeded. The
+/// arguments before `config`, e.g. `--config` and `--floonet`, apply to it
+fn config_command(
+	args: &ArgMatches,
+	config_args: &ArgMatches,
+	runtime: &RuntimeContext,
+) -> Result<bool> {
+	let config_path = args.value_of("config-path");
+	match config_args.subcommand_name() {
+		Some("init") => {
+			let chain = chain(args);
+			if wallet713_core::config_init(config_path, &chain)? {
+				let path = match config_path {
+					Some(path) => path.to_owned(),
+					None => Wallet713Config::default_config_path(&chain)?
+						.display()
+						.to_string(),
+				};
+				println!("Config written to {}", path.bright_green());
+			}
+			Ok(true)
+		}
+		_ => {
+			let config = welcome(args, runtime)?;
+			Ok(wallet713_core::config_check(&config))
+		}
+	}
 }
 
 fn main() {
@@ -110,16 +134,51 @@ fn main() {
         .arg(Arg::from_usage("[config-path] -c, --config=<config-path> 'the path to the config file'"))
         .arg(Arg::from_usage("[log-config-path] -l, --log-config-path=<log-config-path> 'the path to the log config file'"))
         .arg(Arg::from_usage("[account] -a, --account=<account> 'the account to use'"))
-        .arg(Arg::from_usage("[daemon] -d, --daemon 'run daemon'"))
+        .arg(Arg::from_usage("[daemon] -d, --daemon 'run daemon'").conflicts_with("exec"))
+        .arg(Arg::from_usage("[exec] -e, --exec=<command> 'run a single command and exit'"))
+        .arg(Arg::from_usage("[json] --json 'print the output of --exec as JSON'").requires("exec"))
+        .arg(Arg::from_usage("[password-file] --password-file=<file> 'read the password for --exec from a file'").requires("exec"))
         .arg(Arg::from_usage("[floonet] -f, --floonet 'use floonet'"))
+        .arg(Arg::from_usage("[record-node] --record-node=<file> 'record all node calls to a session bundle'").conflicts_with("replay-node"))
+        .arg(Arg::from_usage("[replay-node] --replay-node=<file> 'answer node calls from a recorded session bundle'"))
+        .arg(Arg::from_usage("[force-unlock] --force-unlock 'open the wallet even if another wallet713 process seems to have it open'"))
+        .subcommand(SubCommand::with_name("config")
+            .about("set up or check the config file")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(SubCommand::with_name("init").about("write a config by answering a few questions"))
+            .subcommand(SubCommand::with_name("check").about("check the node, grinbox relay, keybase client and files the config uses")))
         .get_matches();
 
-	let runtime_mode = match matches.is_present("daemon") {
-		true => RuntimeMode::Daemon,
-		false => RuntimeMode::Cli,
+	let runtime_mode = if matches.is_present("daemon") {
+		RuntimeMode::Daemon
+	} else if matches.is_present("exec") {
+		RuntimeMode::Exec
+	} else {
+		RuntimeMode::Cli
 	};
+	let node_session = if let Some(path) = matches.value_of("record-node") {
+		Some(NodeSession::record(path).expect("could not create session bundle!"))
+	} else if let Some(path) = matches.value_of("replay-node") {
+		Some(NodeSession::replay(path).expect("could not read session bundle!"))
+	} else {
+		None
+	};
+	let runtime = RuntimeContext::new(runtime_mode)
+		.with_node_session(node_session)
+		.with_force_unlock(matches.is_present("force-unlock"));
 
-	let config: Wallet713Config = welcome(&matches, &runtime_mode).unwrap_or_else(|e| {
+	if let Some(args) = matches.subcommand_matches("config") {
+		match config_command(&matches, args, &runtime) {
+			Ok(true) => return,
+			Ok(false) => std::process::exit(1),
+			Err(e) => {
+				eprintln!("{}: {}", "ERROR".bright_red(), e);
+				std::process::exit(1);
+			}
+		}
+	}
+
+	let config: Wallet713Config = welcome(&matches, &runtime).unwrap_or_else(|e| {
 		panic!(
 			"{}: could not read or create config! {}",
 			"ERROR".bright_red(),
@@ -128,23 +187,54 @@ fn main() {
 	});
 
 	if runtime_mode == RuntimeMode::Daemon {
-		env_logger::init();
+		let res = config.log_config().and_then(|c| logging::init(&c));
+		if let Err(e) = res {
+			panic!("{}: could not set up logging! {}", "ERROR".bright_red(), e);
+		}
 	}
 
-	let data_path_buf = config.get_data_path().unwrap();
-	let data_path = data_path_buf.to_str().unwrap();
+	if let Some(command) = matches.value_of("exec") {
+		let json = matches.is_present("json");
+		let res = exec_password(&matches)
+			.and_then(|password| wallet713_core::exec(config, runtime, command, password, json));
+		if let Err(e) = res {
+			match json {
+				true => println!("{}", serde_json::json!({ "error": e.to_string() })),
+				false => eprintln!("{}: {}", "ERROR".bright_red(), e),
+			}
+			std::process::exit(exit_code(&e));
+		}
+		return;
+	}
 
-	let address_book_backend =
-		Backend::new(data_path).expect("could not create address book backend!");
-	let address_book = AddressBook::new(Box::new(address_book_backend))
-		.expect("could not create an address book!");
+	if let Err(e) = wallet713_core::run_with(config, runtime) {
+		panic!(
+			"{}: could not start the wallet! {}",
+			"ERROR".bright_red(),
+			e
+		);
+	}
 
-	let container = create_container(config, address_book).unwrap();
+	press_any_key();
+}
 
-	let cli = CLI::new(container);
-	cli.start();
+/// Password for `--exec`, from `--password-file` or `WALLET713_PASSWORD`
+fn exec_password(args: &ArgMatches) -> Result<Option<String>> {
+	if let Some(path) = args.value_of("password-file") {
+		let password = std::fs::read_to_string(path)?;
+		return Ok(Some(
+			password.trim_end_matches(&['\r', '\n'][..]).to_owned(),
+		));
+	}
+	Ok(std::env::var(PASSWORD_ENV).ok())
+}
 
-	press_any_key();
+/// 2 for a command that couldn't be parsed, 1 for any other failure
+fn exit_code(e: &Error) -> i32 {
+	match e.downcast_ref::<ErrorKind>() {
+		Some(ErrorKind::Usage(_)) | Some(ErrorKind::Argument(_)) => 2,
+		_ => 1,
+	}
 }
 
 #[cfg(windows)]
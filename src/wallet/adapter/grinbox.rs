@@ -14,7 +14,6 @@
 
 /// Grinbox 'plugin' implementation
 use super::Adapter;
-use crate::api::listener::ListenerInterface;
 use crate::common::{Arc, Keychain, Mutex};
 use crate::wallet::types::{NodeClient, VersionedSlate, WalletBackend};
 use crate::wallet::Container;
@@ -28,6 +27,8 @@ where
 	K: Keychain,
 {
 	container: &'a Arc<Mutex<Container<W, C, K>>>,
+	/// Number of the account whose address the slate is sent from
+	account: u32,
 }
 
 impl<'a, W, C, K> GrinboxAdapter<'a, W, C, K>
@@ -37,8 +38,8 @@ where
 	K: Keychain,
 {
 	/// Create
-	pub fn new(container: &'a Arc<Mutex<Container<W, C, K>>>) -> Box<Self> {
-		Box::new(Self { container })
+	pub fn new(container: &'a Arc<Mutex<Container<W, C, K>>>, account: u32) -> Box<Self> {
+		Box::new(Self { container, account })
 	}
 }
 
@@ -58,7 +59,7 @@ where
 
 	fn send_tx_async(&self, dest: &str, slate: &VersionedSlate) -> Result<(), Error> {
 		let c = self.container.lock();
-		c.listener(ListenerInterface::Grinbox)?
+		c.grinbox_listener(self.account)?
 			.publish(slate, &dest.to_owned())
 	}
 }
@@ -14,6 +14,7 @@
 
 /// HTTP Wallet 'plugin' implementation
 use super::Adapter;
+use crate::common::sealed::{self, SealedMessage};
 use crate::wallet::types::VersionedSlate;
 use crate::wallet::ErrorKind;
 use failure::Error;
@@ -25,12 +26,43 @@ use serde_json::json;
 use serde_json::Value;
 
 #[derive(Clone)]
-pub struct HTTPAdapter {}
+pub struct HTTPAdapter {
+	api_secret: Option<String>,
+	/// Seal slates to the key of the other wallet
+	seal: bool,
+}
 
 impl HTTPAdapter {
 	/// Create
-	pub fn new() -> Box<Self> {
-		Box::new(Self {})
+	pub fn new(seal: bool) -> Box<Self> {
+		Box::new(Self {
+			api_secret: None,
+			seal,
+		})
+	}
+
+	/// Create, authenticating against the other wallet with the given secret
+	pub fn with_secret(api_secret: Option<String>, seal: bool) -> Box<Self> {
+		Box::new(Self { api_secret, seal })
+	}
+
+	/// Key of the other wallet to seal slates to
+	fn receiver_key(&self, dest: &str) -> Result<[u8; 32], Error> {
+		let url = format!("{}/v1/wallet/foreign/pubkey", dest);
+		let res: Value = client::get(&url, self.api_secret.clone()).map_err(|e| {
+			let report = format!(
+				"Getting the key to seal the slate to (set http_seal_slates = false for \
+				 wallets that don't support it): {}",
+				e
+			);
+			error!("{}", report);
+			ErrorKind::ClientCallback(report)
+		})?;
+		let key = res["public_key"].as_str().unwrap_or("");
+		sealed::parse_key(key).map_err(|_| {
+			let report = format!("Invalid key to seal the slate to: {}", key);
+			ErrorKind::ClientCallback(report).into()
+		})
 	}
 
 	/// Check version of the other wallet
@@ -42,7 +74,7 @@ impl HTTPAdapter {
 			"params": []
 		});
 
-		let res: String = post(url, None, &req).map_err(|e| {
+		let res: String = post(url, self.api_secret.clone(), &req).map_err(|e| {
 			let report = format!("Performing version check (is recipient listening?): {}", e);
 			error!("{}", report);
 			ErrorKind::ClientCallback(report)
@@ -102,10 +134,26 @@ impl Adapter for HTTPAdapter {
 
 		self.check_other_version(&url)?;
 
+		// Sealing hides the slate from anyone in between, such as proxies
+		// terminating TLS. The reply is sealed with the same key
+		let (slate, key) = match self.seal {
+			true => {
+				let receiver_key = self.receiver_key(dest)?;
+				let json = serde_json::to_string(slate)?;
+				let (sealed, key) = SealedMessage::seal(&json, &receiver_key)?;
+				(serde_json::to_value(sealed)?, Some(key))
+			}
+			false => (serde_json::to_value(slate)?, None),
+		};
+		let method = match key {
+			Some(_) => "receive_tx_sealed",
+			None => "receive_tx",
+		};
+
 		// Note: not using easy-jsonrpc as don't want the dependencies in this crate
 		let req = json!({
 			"jsonrpc": "2.0",
-			"method": "receive_tx",
+			"method": method,
 			"id": 1,
 			"params": [
 				slate,
@@ -115,7 +163,7 @@ impl Adapter for HTTPAdapter {
 		});
 		trace!("Sending receive_tx request: {}", req);
 
-		let res: String = post(url.as_str(), None, &req).map_err(|e| {
+		let res: String = post(url.as_str(), self.api_secret.clone(), &req).map_err(|e| {
 			let report = format!("Posting transaction slate (is recipient listening?): {}", e);
 			error!("{}", report);
 			ErrorKind::ClientCallback(report)
@@ -135,6 +183,13 @@ impl Adapter for HTTPAdapter {
 		let slate_value = res["result"]["Ok"].clone();
 		trace!("slate_value: {}", slate_value);
 
+		let slate_value = match key {
+			Some(key) => {
+				let sealed: SealedMessage = serde_json::from_value(slate_value)?;
+				serde_json::from_str(&sealed.open_reply(&key)?)?
+			}
+			None => slate_value,
+		};
 		let slate: VersionedSlate =
 			serde_json::from_str(&serde_json::to_string(&slate_value).unwrap())?;
 		Ok(slate)
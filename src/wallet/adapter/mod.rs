@@ -12,15 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod email;
 mod grinbox;
 mod http;
 mod keybase;
 //mod null;
 
+pub use self::email::EmailAdapter;
 pub use self::grinbox::GrinboxAdapter;
 pub use self::http::HTTPAdapter;
 pub use self::keybase::KeybaseAdapter;
 use super::types::VersionedSlate;
+use crate::common::Arc;
 use failure::Error;
 
 /// Encapsulate wallet to wallet communication functions
@@ -34,3 +37,20 @@ pub trait Adapter {
 	/// Send a transaction asynchronously (result will be returned via the listener)
 	fn send_tx_async(&self, addr: &str, slate: &VersionedSlate) -> Result<(), Error>;
 }
+
+impl<A> Adapter for Arc<A>
+where
+	A: Adapter + ?Sized,
+{
+	fn supports_sync(&self) -> bool {
+		(**self).supports_sync()
+	}
+
+	fn send_tx_sync(&self, addr: &str, slate: &VersionedSlate) -> Result<VersionedSlate, Error> {
+		(**self).send_tx_sync(addr, slate)
+	}
+
+	fn send_tx_async(&self, addr: &str, slate: &VersionedSlate) -> Result<(), Error> {
+		(**self).send_tx_async(addr, slate)
+	}
+}
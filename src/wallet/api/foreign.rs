@@ -13,16 +13,22 @@
 // limitations under the License.
 
 use super::{check_middleware, VersionInfo};
-use crate::common::{Arc, Keychain, Mutex, MutexGuard};
+use crate::common::crypto::to_hex;
+use crate::common::hasher::derive_http_key;
+use crate::common::metrics::SharedMetrics;
+use crate::common::timing::timed;
+use crate::common::{x25519, Arc, Keychain, Mutex, MutexGuard};
 use crate::internal::{tx, updater};
 use crate::wallet::types::{
-	BlockFees, CbData, NodeClient, NodeVersionInfo, Slate, SlateVersion, WalletBackend,
+	BlockFees, CbData, NodeClient, NodeVersionInfo, NotificationEvent, Slate, SlateVersion,
+	WalletBackend,
 };
-use crate::wallet::Container;
+use crate::wallet::{Container, ErrorKind};
 use colored::Colorize;
 use failure::Error;
 use gotham_derive::StateData;
 use grin_core::core::amount_to_hr_string;
+use log::warn;
 use std::marker::PhantomData;
 
 const FOREIGN_API_VERSION: u16 = 2;
@@ -53,6 +59,9 @@ where
 {
 	container: Arc<Mutex<Container<W, C, K>>>,
 	middleware: Option<ForeignCheckMiddleware>,
+	allow_dust: bool,
+	payjoin: bool,
+	skip_policy: bool,
 	phantom_k: PhantomData<K>,
 	phantom_c: PhantomData<C>,
 }
@@ -67,11 +76,44 @@ where
 		Foreign {
 			container,
 			middleware: Some(check_middleware),
+			allow_dust: false,
+			payjoin: false,
+			skip_policy: false,
 			phantom_k: PhantomData,
 			phantom_c: PhantomData,
 		}
 	}
 
+	/// Accept payments below the configured minimum output value
+	pub fn with_dust_allowed(mut self, allow_dust: bool) -> Self {
+		self.allow_dust = allow_dust;
+		self
+	}
+
+	/// Contribute one of our outputs to received transactions, even when
+	/// not turned on in the config
+	pub fn with_payjoin(mut self, payjoin: bool) -> Self {
+		self.payjoin = payjoin;
+		self
+	}
+
+	/// Receive without applying the `receive_*` rules of the config, for
+	/// slates the user approved
+	pub fn with_policy_skipped(mut self, skip_policy: bool) -> Self {
+		self.skip_policy = skip_policy;
+		self
+	}
+
+	/// Whether responses should be strictly compatible with grin-wallet
+	pub fn strict_compat(&self) -> bool {
+		self.container.lock().config.foreign_api_strict()
+	}
+
+	/// Counters and gauges of the wallet
+	pub fn metrics(&self) -> SharedMetrics {
+		self.container.lock().metrics.clone()
+	}
+
 	/// Convenience function that opens and closes the wallet with the stored credentials
 	fn open_and_close<F, X>(&self, f: F) -> Result<X, Error>
 	where
@@ -141,17 +183,64 @@ where
 		slate.verify_messages()
 	}
 
+	/// Secret of the key HTTP senders seal slates to
+	pub fn http_key(&self) -> Result<[u8; 32], Error> {
+		self.open_and_close(|c| derive_http_key(c.backend()?.keychain()))
+	}
+
+	/// Public key HTTP senders seal slates to, in hex
+	pub fn http_public_key(&self) -> Result<String, Error> {
+		Ok(to_hex(x25519::public_key(&self.http_key()?).to_vec()))
+	}
+
 	pub fn receive_tx(
 		&self,
 		slate: &Slate,
 		dest_acct_name: Option<&str>,
 		address: Option<String>,
 		message: Option<String>,
+	) -> Result<Slate, Error> {
+		self.receive(slate, dest_acct_name, address, message, false, None)
+	}
+
+	/// Receive a slate without any call to the node, for wallets on an
+	/// air-gapped machine. The node version check is skipped and the output
+	/// is recorded at `height`, or at the height of the slate if not given
+	pub fn receive_tx_offline(
+		&self,
+		slate: &Slate,
+		message: Option<String>,
+		height: Option<u64>,
+	) -> Result<Slate, Error> {
+		self.receive(slate, None, Some("file".to_owned()), message, true, height)
+	}
+
+	fn receive(
+		&self,
+		slate: &Slate,
+		dest_acct_name: Option<&str>,
+		address: Option<String>,
+		message: Option<String>,
+		offline: bool,
+		height: Option<u64>,
 	) -> Result<Slate, Error> {
 		self.open_and_close(|c| {
+			let timings = c.timings.clone();
+			let index = c.config.grinbox_address_index();
+			let policy = c.config.receive_policy();
+			let contact = match &address {
+				Some(a) => sender_contact(c, a),
+				None => None,
+			};
 			let w = c.backend()?;
+			let min_output_value = match self.allow_dust {
+				true => 0,
+				false => w.min_output_value(),
+			};
+			// Picking an input needs the node
+			let payjoin = (self.payjoin || w.payjoin()) && !offline;
 
-			if let Some(m) = self.middleware.as_ref() {
+			if let Some(m) = self.middleware.as_ref().filter(|_| !offline) {
 				m(
 					ForeignCheckMiddlewareFn::ReceiveTx,
 					w.w2n_client().get_version_info(),
@@ -159,19 +248,65 @@ where
 				)?;
 			}
 
-			let slate = tx::receive_tx(w, slate, dest_acct_name, address.clone(), message)?;
+			// A recipient of a transaction paying several gets the slate a
+			// second time, once everyone has joined, only to sign it
+			let joining = slate.participant_data.len() < slate.num_participants;
+			let amount = slate.recipient_amount(slate.participant_data.len());
 
-			let from = match address {
-				Some(a) => format!(" from {}", a.bright_green()),
-				None => String::new(),
+			// Files are received by the user, no rules needed
+			let sender = address.as_ref().filter(|a| a.as_str() != "file");
+			if let Some(sender) = sender.filter(|_| joining && !offline && !self.skip_policy) {
+				let msg = message.clone();
+				if tx::check_receive_policy(w, &policy, slate, sender, dest_acct_name, msg)? {
+					cli_message!(
+						c.runtime,
+						"Slate {} for {} grin from {} is waiting for approval, see `incoming list`",
+						slate.id.to_string().bright_green(),
+						amount_to_hr_string(amount, false).bright_green(),
+						sender.bright_green()
+					);
+					return Err(ErrorKind::SlateAwaitingApproval(slate.id.to_string()).into());
+				}
+			}
+
+			let slate = timed(&timings, "signing", || -> Result<Slate, Error> {
+				let mut slate = tx::receive_tx(
+					w,
+					slate,
+					dest_acct_name,
+					address.clone(),
+					contact.clone(),
+					message,
+					height,
+					min_output_value,
+					payjoin,
+				)?;
+				tx::sign_payment_proof(w, &mut slate, index)?;
+				Ok(slate)
+			})?;
+
+			let from = match (address, contact) {
+				(Some(a), Some(name)) => format!(" from @{} ({})", name.bright_green(), a),
+				(Some(a), None) => format!(" from {}", a.bright_green()),
+				(None, _) => String::new(),
 			};
 
-			cli_message!(
-				"Slate {} for {} grin received{}",
-				slate.id.to_string().bright_green(),
-				amount_to_hr_string(slate.amount, false).bright_green(),
-				from
-			);
+			match joining {
+				true => cli_message!(
+					c.runtime,
+					"Slate {} for {} grin received{}",
+					slate.id.to_string().bright_green(),
+					amount_to_hr_string(amount, false).bright_green(),
+					from
+				),
+				false => cli_message!(
+					c.runtime,
+					"Slate {} signed{}",
+					slate.id.to_string().bright_green(),
+					from
+				),
+			}
+			c.notify(NotificationEvent::TxReceived, &slate);
 
 			Ok(slate)
 		})
@@ -203,8 +338,49 @@ where
 		Self {
 			container: self.container.clone(),
 			middleware: self.middleware.clone(),
+			allow_dust: self.allow_dust,
+			payjoin: self.payjoin,
+			skip_policy: self.skip_policy,
 			phantom_k: PhantomData,
 			phantom_c: PhantomData,
 		}
 	}
 }
+
+/// Name of the contact a slate was received from, adding unknown senders to
+/// the address book if configured. Failures are only logged, they shouldn't
+/// stop the slate from being received
+fn sender_contact<W, C, K>(c: &mut Container<W, C, K>, address: &str) -> Option<String>
+where
+	W: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	match c.address_book.get_contact_by_address(address) {
+		Ok(Some(contact)) => return Some(contact.name),
+		Ok(None) => {}
+		Err(e) => {
+			warn!("Unable to look up contact of {}: {}", address, e);
+			return None;
+		}
+	}
+	if !c.config.auto_add_contacts() {
+		return None;
+	}
+	match c.address_book.add_sender(address) {
+		Ok(Some(contact)) => {
+			cli_message!(
+				c.runtime,
+				"Added {} to your contacts as {}",
+				address.bright_green(),
+				format!("@{}", contact.name).bright_green()
+			);
+			Some(contact.name)
+		}
+		Ok(None) => None,
+		Err(e) => {
+			warn!("Unable to add {} to your contacts: {}", address, e);
+			None
+		}
+	}
+}
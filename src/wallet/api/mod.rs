@@ -18,7 +18,7 @@ pub mod types;
 
 pub use self::foreign::Foreign;
 use self::foreign::ForeignCheckMiddlewareFn;
-pub use self::owner::Owner;
+pub use self::owner::{Owner, Session};
 pub use self::types::*;
 use crate::wallet::types::{
 	NodeVersionInfo, Slate, CURRENT_SLATE_VERSION, GRIN_BLOCK_HEADER_VERSION,
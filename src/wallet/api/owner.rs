@@ -12,35 +12,68 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::Foreign;
 use crate::api::listener::*;
 use crate::cli_message;
+use crate::common::clock::SharedClock;
 use crate::common::config::Wallet713Config;
-use crate::common::hasher::derive_address_key;
-use crate::common::{Arc, Keychain, Mutex, MutexGuard};
-use crate::contacts::{parse_address, AddressType, Contact, GrinboxAddress};
+use crate::common::features::Feature;
+use crate::common::hasher::{account_number, derive_account_address_key};
+use crate::common::metrics::{self, SharedMetrics};
+use crate::common::timing::{timed, StageTiming};
+use crate::common::{
+	Arc, ErrorKind as CommonErrorKind, Keychain, Mutex, MutexGuard, RuntimeContext,
+};
+use crate::contacts::{
+	parse_address, Address, AddressType, Contact, ContactsFormat, GrinboxAddress, ImportSummary,
+	MergeStrategy,
+};
+use crate::internal::updater::RepostPolicy;
 use crate::internal::*;
-use crate::wallet::adapter::{Adapter, GrinboxAdapter, HTTPAdapter, KeybaseAdapter};
+use crate::wallet::adapter::{Adapter, EmailAdapter, GrinboxAdapter, HTTPAdapter, KeybaseAdapter};
+use crate::wallet::backup;
 use crate::wallet::types::{
-	AcctPathMapping, InitTxArgs, NodeClient, NodeHeightResult, NodeVersionInfo,
-	OutputCommitMapping, Slate, SlateVersion, TxLogEntry, TxProof, TxWrapper, VersionedSlate,
-	WalletBackend, WalletInfo,
+	AccountLabeler, AcctPathMapping, Amount, AttestationStatus, BackupInfo, BalanceAttestation,
+	DerivationFix, DiscoveredAccount, DustAnalysis, EncryptedContext, EstimateFeeArgs, FeeEstimate,
+	HistoryWipe, IncomingSlate, InitTxArgs, InitTxSendArgs, KernelReconciliation, MultisigOutput,
+	MultisigSlate, NodeClient, NodeHeightResult, NodeStatus, NodeVersionInfo, NotificationEvent,
+	NotificationRoute, OutputCommitMapping, OutputData, OutputStatus, OwnershipProof, Page,
+	PaymentInfo, PaymentProof, QueuedSlate, ReceivedMessage, SeedBackup, SendPreview, Slate,
+	SlateVersion, StoredSlate, Subscription, TxDetail, TxExportEntry, TxFilter, TxLogEntry,
+	TxLogEntryType, TxMessage, TxProof, TxWrapper, VersionedSlate, WalletBackend,
+	WalletDataSummary, WalletInfo, WalletSeed, WatchKey, WatchKeychain,
 };
-use crate::wallet::{Container, ErrorKind};
+use crate::wallet::{Backend, Container, ErrorKind, WatchScanSummary};
+use chrono::{DateTime, Utc};
 use colored::Colorize;
 use failure::Error;
 use gotham_derive::StateData;
 use grin_core::core::hash::Hashed;
 use grin_core::core::{amount_to_hr_string, Transaction};
+use grin_core::libtx::tx_fee;
 use grin_core::ser::{ser_vec, ProtocolVersion};
 use grin_keychain::Identifier;
 use grin_util::secp::key::PublicKey;
 use grin_util::secp::pedersen::Commitment;
 use grin_util::{to_hex, ZeroingString};
-use log::{debug, error};
-use std::collections::{HashMap, HashSet};
+use log::{debug, error, info, warn};
+use parking_lot::{ReentrantMutex, ReentrantMutexGuard};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryFrom;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
+/// How long a spend waits for the one in progress before giving up
+const SPEND_QUEUE_TIMEOUT: Duration = Duration::from_secs(60);
+/// How long a send above `confirm_threshold` waits to be confirmed
+const PENDING_SEND_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Active account of a caller with its own session. `None` follows the
+/// account of the wallet
+pub type Session = Arc<Mutex<Option<String>>>;
+
 #[derive(StateData)]
 pub struct Owner<W, C, K>
 where
@@ -49,6 +82,7 @@ where
 	K: Keychain,
 {
 	container: Arc<Mutex<Container<W, C, K>>>,
+	session: Option<Session>,
 }
 
 impl<W, C, K> Owner<W, C, K>
@@ -58,7 +92,25 @@ where
 	K: Keychain,
 {
 	pub fn new(container: Arc<Mutex<Container<W, C, K>>>) -> Self {
-		Owner { container }
+		Owner {
+			container,
+			session: None,
+		}
+	}
+
+	/// Owner with its own active account, for callers sharing the wallet with
+	/// the command line. Switching accounts in the session leaves the account
+	/// of the wallet, and of its listeners, alone
+	pub fn with_session(container: Arc<Mutex<Container<W, C, K>>>) -> Self {
+		Self::in_session(container, Arc::new(Mutex::new(None)))
+	}
+
+	/// Owner in an existing session, shared with the other owners in it
+	pub fn in_session(container: Arc<Mutex<Container<W, C, K>>>, session: Session) -> Self {
+		Owner {
+			container,
+			session: Some(session),
+		}
 	}
 
 	pub fn has_seed(&self) -> Result<bool, Error> {
@@ -91,6 +143,59 @@ where
 		w.set_password(password)
 	}
 
+	/// Drop the password and keychain from memory. Nothing that needs the
+	/// keys, including receiving on the listeners, works until `unlock`
+	pub fn lock(&self) -> Result<(), Error> {
+		let mut c = self.container.lock();
+		let w = c.raw_backend();
+		w.lock()
+	}
+
+	/// Check `password` against the seed and keep it for opening the wallet
+	pub fn unlock(&self, password: ZeroingString) -> Result<(), Error> {
+		self.set_password(password)
+	}
+
+	/// Whether the wallet has a seed but no password to open it with
+	pub fn is_locked(&self) -> Result<bool, Error> {
+		let mut c = self.container.lock();
+		let w = c.raw_backend();
+		Ok(w.has_seed()? && w.is_locked())
+	}
+
+	/// Seed encrypted with `password`, which doesn't have to be the wallet password
+	pub fn export_seed(&self, password: &str) -> Result<SeedBackup, Error> {
+		let mut c = self.container.lock();
+		let w = c.raw_backend();
+		let seed = WalletSeed::from_mnemonic(&w.get_seed()?)?;
+		SeedBackup::new(&seed, password, &*w.entropy())
+	}
+
+	/// Replace the seed with the one in `backup`, encrypted with `password`
+	/// from now on. The contents of the wallet still have to be restored
+	pub fn import_seed(
+		&self,
+		backup: &SeedBackup,
+		backup_password: &str,
+		password: ZeroingString,
+	) -> Result<(), Error> {
+		let seed = backup.decrypt(backup_password)?;
+		let mut c = self.container.lock();
+		let w = c.raw_backend();
+		w.set_seed(Some(seed.to_mnemonic()?.into()), password, true)
+	}
+
+	/// Re-encrypt the seed, `old_password` has to match the current one
+	pub fn change_password(
+		&self,
+		old_password: ZeroingString,
+		new_password: ZeroingString,
+	) -> Result<(), Error> {
+		let mut c = self.container.lock();
+		let w = c.raw_backend();
+		w.change_password(old_password, new_password)
+	}
+
 	/// Connect to the backend
 	pub fn connect(&self) -> Result<(), Error> {
 		let mut c = self.container.lock();
@@ -117,6 +222,15 @@ where
 		c.config.clone()
 	}
 
+	pub fn runtime(&self) -> RuntimeContext {
+		self.container.lock().runtime.clone()
+	}
+
+	/// Clock of the wallet, for timestamps of records kept outside the backend
+	pub fn clock(&self) -> SharedClock {
+		self.container.lock().raw_backend().clock()
+	}
+
 	pub fn start_listener(&self, interface: ListenerInterface) -> Result<String, Error> {
 		let container = self.container.clone();
 		self.open_and_close(|c| {
@@ -125,10 +239,19 @@ where
 			}
 
 			let listener = match interface {
-				ListenerInterface::Grinbox => start_grinbox(container, c),
+				ListenerInterface::Grinbox(account, index) => {
+					start_grinbox(container, c, account, index)
+				}
 				ListenerInterface::Keybase => start_keybase(container, c),
 				ListenerInterface::ForeignHttp => start_foreign_http(container, c),
 				ListenerInterface::OwnerHttp => start_owner_http(container, c),
+				ListenerInterface::OwnerReadHttp => start_owner_read_http(container, c),
+				ListenerInterface::Email => start_email(container, c),
+				ListenerInterface::Exec => start_exec(container, c),
+				ListenerInterface::Outbox => start_outbox(container, c),
+				ListenerInterface::Retry => start_retry(container, c),
+				ListenerInterface::Blocks => start_blocks(container, c),
+				ListenerInterface::Backup => start_backup(container, c),
 			}?;
 
 			let address = listener.address();
@@ -150,6 +273,33 @@ where
 		}
 	}
 
+	/// State of all running listeners
+	pub fn listener_status(&self) -> Result<Vec<ListenerStatus>, Error> {
+		let c = self.container.lock();
+		let mut statuses: Vec<_> = c.listeners.values().map(|l| l.status()).collect();
+		statuses.sort_by_key(|s| s.interface.to_string());
+		Ok(statuses)
+	}
+
+	/// Stream of the wallet events published from now on, preceded by the
+	/// kept events published after the one numbered `since`
+	pub fn subscribe_events(&self, since: Option<u64>) -> Subscription {
+		self.container.lock().events.subscribe_since(since)
+	}
+
+	/// Addresses the wallet can be reached at through the running listeners
+	pub fn reachable_addresses(&self) -> Result<Vec<ReachableAddress>, Error> {
+		let c = self.container.lock();
+		let mut addresses: Vec<_> = c.listeners.values().flat_map(|l| l.reachable()).collect();
+		addresses.sort_by_key(|a| a.interface.to_string());
+		Ok(addresses)
+	}
+
+	/// Optional capabilities of this build and config
+	pub fn features(&self) -> Vec<Feature> {
+		self.container.lock().features.list()
+	}
+
 	/// Stop all running listeners
 	pub fn stop_listeners(&self) -> Result<HashSet<ListenerInterface>, Error> {
 		let mut c = self.container.lock();
@@ -161,11 +311,55 @@ where
 		Ok(interfaces)
 	}
 
+	/// Stop the wallet for the process to exit. Listeners are stopped first
+	/// so no new slates come in, then the operation in progress gets until
+	/// `grace` runs out to commit its batch before the database is closed.
+	/// Returns whether everything stopped within the grace period
+	pub fn shutdown(&self, grace: Duration) -> bool {
+		let deadline = Instant::now() + grace;
+		let remaining = || {
+			let now = Instant::now();
+			match now < deadline {
+				true => deadline - now,
+				false => Duration::from_secs(0),
+			}
+		};
+
+		match self.container.try_lock_for(remaining()) {
+			Some(mut c) => {
+				for (_, listener) in c.listeners.drain() {
+					let _ = listener.stop();
+				}
+			}
+			None => return false,
+		}
+		// Sends, finalizations and cancellations hold the spend lock between
+		// their batches, no new one starts once we have it
+		let spend_lock = self.spend_lock();
+		let spend = spend_lock.try_lock_for(remaining());
+		let mut c = match self.container.try_lock_for(remaining()) {
+			Some(c) => c,
+			None => return false,
+		};
+		if let Some(session) = c.runtime.node_session() {
+			if let Err(e) = session.flush() {
+				warn!("Unable to write node session {}: {}", session.path(), e);
+			}
+		}
+		let w = c.raw_backend();
+		let _ = w.lock();
+		let _ = w.disconnect();
+		spend.is_some()
+	}
+
+	/// Grinbox address of the active account at the configured index
 	pub fn grinbox_address(&self) -> Result<GrinboxAddress, Error> {
 		self.open_and_close(|c| {
 			let index = c.config.grinbox_address_index();
-			let keychain = c.backend()?.keychain();
-			let sec_key = derive_address_key(keychain, index)?;
+			let w = c.backend()?;
+			let parent_key_id = w.get_parent_key_id();
+			let keychain = w.keychain();
+			let sec_key = derive_account_address_key(keychain, &parent_key_id, index)?;
 			let pub_key = PublicKey::from_secret_key(keychain.secp(), &sec_key)?;
 
 			Ok(GrinboxAddress::new(
@@ -176,22 +370,86 @@ where
 		})
 	}
 
+	/// Stop all grinbox listeners, returns the ones that were running
+	fn stop_grinbox_listeners(&self) -> Result<Vec<ListenerInterface>, Error> {
+		let interfaces: Vec<_> = {
+			let c = self.container.lock();
+			c.listeners
+				.keys()
+				.filter(|i| i.is_grinbox())
+				.cloned()
+				.collect()
+		};
+		for interface in &interfaces {
+			self.stop_listener(*interface)?;
+		}
+		Ok(interfaces)
+	}
+
+	/// Grinbox listener for the address at `index` of `account`, or of the
+	/// active account when not given
+	pub fn grinbox_interface(
+		&self,
+		account: Option<&str>,
+		index: u32,
+	) -> Result<ListenerInterface, Error> {
+		let parent_key_id = self.open_and_close(|c| {
+			let w = c.backend()?;
+			match account {
+				Some(label) => Ok(w
+					.get_acct_path(label)?
+					.ok_or_else(|| ErrorKind::UnknownAccountLabel(label.to_owned()))?
+					.path),
+				None => Ok(w.get_parent_key_id()),
+			}
+		})?;
+		Ok(ListenerInterface::Grinbox(
+			account_number(&parent_key_id),
+			index,
+		))
+	}
+
+	/// Switch to the address at `index`. Listeners on the current address of
+	/// an account move to the new address of that account
 	pub fn set_grinbox_address_index(&self, index: u32) -> Result<GrinboxAddress, Error> {
-		let grinbox = self.stop_listener(ListenerInterface::Grinbox)?;
+		let (current, running) = {
+			let c = self.container.lock();
+			let running: HashSet<_> = c.listeners.keys().cloned().collect();
+			(c.config.grinbox_address_index(), running)
+		};
+		let mut moved = Vec::new();
+		if current != index {
+			for interface in &running {
+				if let ListenerInterface::Grinbox(account, i) = *interface {
+					if i == current && self.stop_listener(*interface)? {
+						moved.push(ListenerInterface::Grinbox(account, index));
+					}
+				}
+			}
+		}
 		{
 			let mut c = self.container.lock();
 			c.config.grinbox_address_index = Some(index);
 			c.config.save()?;
 		}
 
-		if grinbox {
-			self.start_listener(ListenerInterface::Grinbox)?;
+		for interface in moved {
+			if !running.contains(&interface) {
+				self.start_listener(interface)?;
+			}
 		}
 
 		self.grinbox_address()
 	}
 
+	/// Accounts that aren't archived
 	pub fn accounts(&self) -> Result<Vec<AcctPathMapping>, Error> {
+		let accounts = self.all_accounts()?;
+		Ok(accounts.into_iter().filter(|a| !a.archived).collect())
+	}
+
+	/// All accounts, including the archived ones
+	pub fn all_accounts(&self) -> Result<Vec<AcctPathMapping>, Error> {
 		let mut c = self.container.lock();
 		let w = c.backend()?;
 		keys::accounts(w)
@@ -203,29 +461,89 @@ where
 		keys::new_acct_path(w, label)
 	}
 
+	/// Route the events of an account to a webhook and/or script
+	pub fn set_account_notifications(
+		&self,
+		label: &str,
+		route: NotificationRoute,
+	) -> Result<(), Error> {
+		let mut c = self.container.lock();
+		let w = c.backend()?;
+		keys::set_acct_notifications(w, label, route)
+	}
+
+	/// Relabel an account. When it's the active account, it stays active
+	pub fn rename_account(&self, label: &str, new_label: &str) -> Result<(), Error> {
+		let mut c = self.container.lock();
+		keys::rename_acct_path(c.backend()?, label, new_label)?;
+		if c.account == label {
+			c.account = new_label.to_owned();
+		}
+		if let Some(session) = &self.session {
+			let mut session = session.lock();
+			if session.as_ref().map(|s| s == label).unwrap_or(false) {
+				*session = Some(new_label.to_owned());
+			}
+		}
+		Ok(())
+	}
+
+	/// Hide an account from the account listings, or show it again
+	pub fn archive_account(&self, label: &str, archived: bool) -> Result<(), Error> {
+		if archived && self.active_account()? == label {
+			return Err(ErrorKind::ActiveAccount(label.to_owned()).into());
+		}
+		let mut c = self.container.lock();
+		keys::set_acct_archived(c.backend()?, label, archived)
+	}
+
 	pub fn active_account(&self) -> Result<String, Error> {
 		let c = self.container.lock();
-		Ok(c.account.clone())
+		Ok(self.session_account().unwrap_or(c.account.clone()))
 	}
 
 	pub fn set_active_account(&self, label: &str) -> Result<(), Error> {
 		let mut c = self.container.lock();
 		let w = c.backend()?;
-		w.set_parent_key_id_by_name(label)?;
-		c.account = label.to_owned();
+		match &self.session {
+			Some(session) => {
+				if !w.accounts()?.any(|a| a.label == label) {
+					return Err(ErrorKind::UnknownAccountLabel(label.to_owned()).into());
+				}
+				*session.lock() = Some(label.to_owned());
+			}
+			None => {
+				w.set_parent_key_id_by_name(label)?;
+				c.account = label.to_owned();
+			}
+		}
 		Ok(())
 	}
 
+	fn session_account(&self) -> Option<String> {
+		self.session.as_ref().and_then(|s| s.lock().clone())
+	}
+
 	pub fn contacts(&self) -> Result<Vec<Contact>, Error> {
 		let c = self.container.lock();
 		let contacts: Vec<_> = c.address_book.contacts().collect();
 		Ok(contacts)
 	}
 
-	pub fn add_contact(&self, name: &str, address: &str) -> Result<(), Error> {
+	pub fn add_contact(
+		&self,
+		name: &str,
+		address: &str,
+		group: Option<String>,
+		notes: Option<String>,
+		relay: Option<String>,
+	) -> Result<(), Error> {
 		let address = parse_address(address)?;
 		let mut c = self.container.lock();
-		let contact = Contact::new(name, address)?;
+		let mut contact = Contact::new(name, address)?;
+		contact.group = group;
+		contact.notes = notes;
+		contact.relay = relay;
 		c.address_book.add_contact(&contact)?;
 		Ok(())
 	}
@@ -240,25 +558,50 @@ where
 		Ok(())
 	}
 
+	pub fn export_contacts(&self, path: &str, format: ContactsFormat) -> Result<usize, Error> {
+		let c = self.container.lock();
+		c.address_book.export(path, format)
+	}
+
+	pub fn import_contacts(
+		&self,
+		path: &str,
+		format: ContactsFormat,
+		strategy: MergeStrategy,
+	) -> Result<ImportSummary, Error> {
+		let mut c = self.container.lock();
+		c.address_book.import(path, format, strategy)
+	}
+
 	pub fn retrieve_outputs(
 		&self,
 		include_spent: bool,
 		refresh_from_node: bool,
 		tx_id: Option<u32>,
+		page: Option<Page>,
 	) -> Result<(bool, Option<u64>, Vec<OutputCommitMapping>), Error> {
 		self.open_and_close(|c| {
+			let timings = c.timings.clone();
+			let repost_policy = c.config.repost_policy();
+			let auto_cancel_after = c.config.tx_auto_cancel_after();
+			let runtime = c.runtime.clone();
 			let w = c.backend()?;
 			let parent_key_id = w.get_parent_key_id();
 			let mut validated = false;
 			let mut height = None;
 			if refresh_from_node {
-				if let Ok(h) = updater::refresh_outputs(w, &parent_key_id, false) {
+				if let Ok(h) = timed(&timings, "refresh", || {
+					updater::refresh_outputs(w, &parent_key_id, false)
+				}) {
 					validated = true;
 					height = Some(h);
+					auto_repost(w, h, repost_policy);
+					auto_cancel(w, &parent_key_id, h, auto_cancel_after, &runtime);
 				}
 			}
 
-			let outputs = updater::retrieve_outputs(w, include_spent, tx_id, Some(&parent_key_id))?;
+			let outputs =
+				updater::retrieve_outputs(w, include_spent, tx_id, Some(&parent_key_id), page)?;
 			Ok((validated, height, outputs))
 		})
 	}
@@ -270,6 +613,8 @@ where
 		check_proofs: bool,
 		tx_id: Option<u32>,
 		tx_slate_id: Option<Uuid>,
+		filter: Option<TxFilter>,
+		page: Option<Page>,
 	) -> Result<
 		(
 			bool,
@@ -280,16 +625,25 @@ where
 		),
 		Error,
 	> {
+		let filter = self.resolve_tx_filter(filter)?;
 		self.open_and_close(|c| {
+			let timings = c.timings.clone();
+			let repost_policy = c.config.repost_policy();
+			let auto_cancel_after = c.config.tx_auto_cancel_after();
+			let runtime = c.runtime.clone();
 			let w = c.backend()?;
 			let parent_key_id = w.get_parent_key_id();
 
 			let mut validated = false;
 			let mut height = None;
 			if refresh_from_node {
-				if let Ok(h) = updater::refresh_outputs(w, &parent_key_id, false) {
+				if let Ok(h) = timed(&timings, "refresh", || {
+					updater::refresh_outputs(w, &parent_key_id, false)
+				}) {
 					validated = true;
 					height = Some(h);
+					auto_repost(w, h, repost_policy);
+					auto_cancel(w, &parent_key_id, h, auto_cancel_after, &runtime);
 				}
 			}
 
@@ -300,6 +654,8 @@ where
 				Some(&parent_key_id),
 				false,
 				check_proofs,
+				filter.as_ref(),
+				page,
 			)?;
 
 			let mut contacts = HashMap::new();
@@ -320,6 +676,71 @@ where
 		})
 	}
 
+	/// Full transaction history of the active account, flattened for export
+	pub fn export_txs(&self, filter: Option<TxFilter>) -> Result<Vec<TxExportEntry>, Error> {
+		let filter = self.resolve_tx_filter(filter)?;
+		self.open_and_close(|c| {
+			let timings = c.timings.clone();
+			let w = c.backend()?;
+			let parent_key_id = w.get_parent_key_id();
+			let _ = timed(&timings, "refresh", || {
+				updater::refresh_outputs(w, &parent_key_id, false)
+			});
+
+			let (txs, _) = updater::retrieve_txs(
+				w,
+				None,
+				None,
+				Some(&parent_key_id),
+				false,
+				false,
+				filter.as_ref(),
+				None,
+			)?;
+			let outputs: Vec<OutputData> =
+				updater::retrieve_outputs(w, true, None, Some(&parent_key_id), None)?
+					.into_iter()
+					.map(|m| m.output)
+					.collect();
+			Ok(txs
+				.iter()
+				.map(|t| TxExportEntry::new(t, &outputs))
+				.collect())
+		})
+	}
+
+	/// Write the contents of the wallet to the empty directory `dir`, in
+	/// the layout of grin-wallet
+	pub fn export_data(&self, dir: &str) -> Result<WalletDataSummary, Error> {
+		self.open_and_close(|c| migration::export_data(c.backend()?, Path::new(dir)))
+	}
+
+	/// Add the contents exported to `dir` by `export_data` or grin-wallet.
+	/// The seed has to be the one of the exporting wallet
+	pub fn import_data(&self, dir: &str) -> Result<WalletDataSummary, Error> {
+		let spend_lock = self.spend_lock();
+		let _spend = queue_spend(&spend_lock)?;
+		self.open_and_close(|c| migration::import_data(c.backend()?, Path::new(dir)))
+	}
+
+	/// Replace the contact name of a filter with the contact's address
+	fn resolve_tx_filter(&self, filter: Option<TxFilter>) -> Result<Option<TxFilter>, Error> {
+		let mut filter = match filter {
+			Some(f) => f,
+			None => return Ok(None),
+		};
+		if let Some(name) = filter.contact.take() {
+			let contact = self
+				.container
+				.lock()
+				.address_book
+				.get_contact(&name)?
+				.ok_or(ErrorKind::ContactNotFound(name))?;
+			filter.address = Some(contact.address);
+		}
+		Ok(Some(filter))
+	}
+
 	fn retrieve_tx(
 		&self,
 		tx_id: Option<u32>,
@@ -332,7 +753,8 @@ where
 			tx_id_string = tx_slate_id.to_string();
 		}
 
-		let (_, _, txs, _, _) = self.retrieve_txs(true, false, false, tx_id, tx_slate_id)?;
+		let (_, _, txs, _, _) =
+			self.retrieve_txs(true, false, false, tx_id, tx_slate_id, None, None)?;
 		match txs.into_iter().next() {
 			Some(t) => Ok(t),
 			None => Err(ErrorKind::TransactionDoesntExist(tx_id_string).into()),
@@ -345,12 +767,23 @@ where
 		minimum_confirmations: u64,
 	) -> Result<(bool, WalletInfo), Error> {
 		self.open_and_close(|c| {
+			let timings = c.timings.clone();
+			let repost_policy = c.config.repost_policy();
+			let auto_cancel_after = c.config.tx_auto_cancel_after();
+			let runtime = c.runtime.clone();
 			let w = c.backend()?;
 			let parent_key_id = w.get_parent_key_id();
 
 			let mut validated = false;
 			if refresh_from_node {
-				validated = updater::refresh_outputs(w, &parent_key_id, false).is_ok();
+				let res = timed(&timings, "refresh", || {
+					updater::refresh_outputs(w, &parent_key_id, false)
+				});
+				if let Ok(h) = res {
+					validated = true;
+					auto_repost(w, h, repost_policy);
+					auto_cancel(w, &parent_key_id, h, auto_cancel_after, &runtime);
+				}
 			}
 
 			let wallet_info = updater::retrieve_info(w, &parent_key_id, minimum_confirmations)?;
@@ -358,7 +791,71 @@ where
 		})
 	}
 
-	pub fn init_send_tx(&self, mut args: InitTxArgs) -> Result<Slate, Error> {
+	/// Refresh only the outputs of outstanding transactions, in every
+	/// account. Returns the transactions that got confirmed
+	pub fn refresh_pending(&self) -> Result<Vec<TxLogEntry>, Error> {
+		self.open_and_close(|c| {
+			let timings = c.timings.clone();
+			let w = c.backend()?;
+			let accounts: Vec<AcctPathMapping> = w.accounts()?.collect();
+
+			let mut confirmed = vec![];
+			for account in accounts {
+				let (pending, _) = updater::retrieve_txs(
+					w,
+					None,
+					None,
+					Some(&account.path),
+					true,
+					false,
+					None,
+					None,
+				)?;
+				if pending.is_empty() {
+					continue;
+				}
+				timed(&timings, "refresh", || {
+					updater::refresh_outputs(w, &account.path, false)
+				})?;
+				confirmed.extend(w.tx_logs()?.filter(|t| {
+					t.confirmed
+						&& t.parent_key_id == account.path
+						&& pending.iter().any(|p| p.id == t.id)
+				}));
+			}
+			Ok(confirmed)
+		})
+	}
+
+	pub fn init_send_tx(&self, args: InitTxArgs) -> Result<Slate, Error> {
+		self.init_send_tx_with(args, false)
+	}
+
+	/// Complete a send `init_send_tx` put aside for being above
+	/// `confirm_threshold`
+	pub fn confirm_send_tx(&self, token: &Uuid) -> Result<Slate, Error> {
+		let pending = self.container.lock().pending_sends.remove(token);
+		match pending {
+			Some((created, args)) if created.elapsed() < PENDING_SEND_TIMEOUT => {
+				self.init_send_tx_with(args, true)
+			}
+			_ => Err(ErrorKind::UnknownConfirmation(token.to_string()).into()),
+		}
+	}
+
+	/// Drop a send waiting for confirmation
+	pub fn discard_send_tx(&self, token: &Uuid) {
+		self.container.lock().pending_sends.remove(token);
+	}
+
+	fn init_send_tx_with(&self, mut args: InitTxArgs, confirmed: bool) -> Result<Slate, Error> {
+		let spend_lock = self.spend_lock();
+		let _spend = queue_spend(&spend_lock)?;
+		let sends = args.send_args.is_some() || args.recipients.iter().any(|r| r.dest.is_some());
+		if !args.recipients.is_empty() && sends {
+			let spend = self.check_spend_policy(&args, confirmed)?;
+			return self.send_to_recipients(args, spend);
+		}
 		if let Some(sa) = &mut args.send_args {
 			if sa.dest.starts_with("@") {
 				// Look up contact by address
@@ -372,58 +869,149 @@ where
 			if sa.method.is_none() {
 				// Try to infer method from the address
 				let address = parse_address(&sa.dest)?;
-				sa.method = Some(
-					match address.address_type() {
-						AddressType::Http => "http",
-						AddressType::Grinbox => "grinbox",
-						AddressType::Keybase => "keybase",
-					}
-					.to_owned(),
-				);
+				sa.method = Some(address.address_type().method().to_owned());
 				sa.dest = address.stripped();
 			}
 		}
+		let self_send = match &args.send_args {
+			Some(sa) if sa.method.as_ref().map(|m| m.as_str()) == Some("grinbox") => {
+				self.is_own_grinbox_address(&GrinboxAddress::from_str(&sa.dest)?)?
+			}
+			_ => false,
+		};
+		if !self_send && !args.force {
+			self.check_duplicate_payment(&args)?;
+		}
+		// Sending to ourselves moves nothing out of the wallet
+		let spend = match self_send {
+			true => 0,
+			false => self.check_spend_policy(&args, confirmed)?,
+		};
+		let payment_proof = match &args.payment_proof_recipient_address {
+			Some(a) => Some(PaymentInfo {
+				sender_address: self.grinbox_address()?.public_key()?,
+				receiver_address: GrinboxAddress::from_str(a)?.public_key()?,
+				receiver_signature: None,
+			}),
+			None => None,
+		};
 		let mut send_args = args.send_args.clone();
 		let version = match args.target_slate_version {
 			Some(v) => SlateVersion::try_from(v)?,
 			None => SlateVersion::default(),
 		};
-		let mut slate = self.open_and_close(|c| {
+		let (mut slate, parent_key_id) = self.open_and_close(|c| {
+			let timings = c.timings.clone();
 			let w = c.backend()?;
-			tx::init_send_tx(w, args)
+			let parent_key_id = w.get_parent_key_id();
+			let slate = tx::init_send_tx(w, args, &timings)?;
+			Ok((slate, parent_key_id))
 		})?;
+		self.record_spend(spend)?;
+		slate.payment_proof = payment_proof;
 
 		// Helper functionality. If send arguments exist, attempt to send
 		match &mut send_args {
 			Some(sa) => {
-				let vslate = VersionedSlate::into_version(slate.clone(), version);
-				let adapter: Box<dyn Adapter> = match sa.method.clone().unwrap().as_ref() {
-					"http" => HTTPAdapter::new(),
-					"grinbox" => GrinboxAdapter::new(&self.container),
-					"keybase" => KeybaseAdapter::new(&self.container),
-					_ => {
-						error!("unsupported payment method");
-						return Err(ErrorKind::ClientCallback(
-							"unsupported payment method".to_owned(),
-						))?;
-					}
-				};
-
-				if adapter.supports_sync() {
-					slate = adapter.send_tx_sync(&sa.dest, &vslate)?.into();
+				let vslate = VersionedSlate::into_version(slate.clone(), version.clone());
+				let method = sa.method.clone().unwrap();
+				let adapter = self.adapter(&method, &parent_key_id)?;
+				let runtime = self.runtime();
+
+				let sync = self_send || adapter.supports_sync();
+				let mut queued = None;
+				if self_send {
+					// Sending through the relay to ourselves results in duplicate
+					// looking log entries, so receive the slate directly instead
+					cli_message!(
+						runtime,
+						"{}: {} is one of your own addresses, sending to self without relay",
+						"WARNING".bright_yellow(),
+						sa.dest.bright_green()
+					);
+					slate = self.open_and_close(|c| {
+						let timings = c.timings.clone();
+						let index = c.config.grinbox_address_index();
+						let w = c.backend()?;
+						timed(&timings, "signing", || -> Result<Slate, Error> {
+							let mut slate = tx::receive_tx(
+								w,
+								&slate,
+								None,
+								Some(sa.dest.clone()),
+								None,
+								None,
+								None,
+								0,
+								false,
+							)?;
+							tx::sign_payment_proof(w, &mut slate, index)?;
+							Ok(slate)
+						})
+					})?;
 				} else {
-					adapter.send_tx_async(&sa.dest, &vslate)?;
+					let timings = self.container.lock().timings.clone();
+					let res = timed(&timings, "adapter", || -> Result<(), Error> {
+						if adapter.supports_sync() {
+							slate = adapter.send_tx_sync(&sa.dest, &vslate)?.into();
+						} else {
+							adapter.send_tx_async(&sa.dest, &vslate)?;
+						}
+						Ok(())
+					});
+					if res.is_ok() {
+						let labels = [("adapter", method.as_str())];
+						self.metrics().lock().inc(metrics::SLATES_SENT, &labels);
+					}
+					if sync {
+						res?;
+					} else {
+						// Keep the slate to post it again until the response comes
+						// in. With retries on, a failed attempt doesn't fail the send
+						let error = match res {
+							Ok(()) => None,
+							Err(e) if self.config().slate_retry() => Some(e.to_string()),
+							Err(e) => return Err(e),
+						};
+						let parent_key_id =
+							self.open_and_close(|c| Ok(c.backend()?.get_parent_key_id()))?;
+						let now = self.clock().now();
+						let mut q = QueuedSlate::new(
+							parent_key_id,
+							slate.clone(),
+							version,
+							method,
+							sa.dest.clone(),
+							now,
+						);
+						q.record_attempt(error, now);
+						queued = Some(q);
+					}
 				}
 				self.tx_lock_outputs(&slate, 0, Some(sa.dest.clone()))?;
+				if let Some(q) = &queued {
+					self.open_and_close(|c| tx::queue_slate(c.backend()?, q))?;
+				}
 
-				cli_message!(
-					"Slate {} for {} grin sent successfully to {}",
-					slate.id.to_string().bright_green(),
-					amount_to_hr_string(slate.amount, false).bright_green(),
-					format!("{}", parse_address(&sa.dest)?).bright_green()
-				);
+				match queued.as_ref().and_then(|q| q.last_error.as_ref()) {
+					Some(e) => cli_message!(
+						runtime,
+						"{}: slate {} could not be sent to {} ({}), it will be retried",
+						"WARNING".bright_yellow(),
+						slate.id.to_string().bright_green(),
+						format!("{}", parse_address(&sa.dest)?).bright_green(),
+						e
+					),
+					None => cli_message!(
+						runtime,
+						"Slate {} for {} grin sent successfully to {}",
+						slate.id.to_string().bright_green(),
+						amount_to_hr_string(slate.amount, false).bright_green(),
+						format!("{}", parse_address(&sa.dest)?).bright_green()
+					),
+				}
 
-				if adapter.supports_sync() {
+				if sync {
 					if sa.finalize {
 						slate = self.finalize_tx(&slate, None)?;
 					};
@@ -439,56 +1027,376 @@ where
 		}
 	}
 
-	/*pub fn issue_invoice_tx(&self, args: IssueInvoiceTxArgs) -> Result<Slate, Error> {
-		let mut w = self.wallet.lock();
-		w.open_with_credentials()?;
-		let slate = owner::issue_invoice_tx(&mut *w, args, self.doctest_mode)?;
-		w.close()?;
+	/// Send a transaction paying several http recipients. Each of them adds
+	/// its output in turn, then all but the last sign in a second pass. The
+	/// transaction is finalized and posted once everyone has signed
+	fn send_to_recipients(&self, args: InitTxArgs, spend: u64) -> Result<Slate, Error> {
+		if args.send_args.is_some() {
+			let msg = "with send_args, every recipient has its own dest";
+			return Err(ErrorKind::RecipientsNotSupported(msg.to_owned()).into());
+		}
+		let mut dests = vec![];
+		for r in &args.recipients {
+			let address = match &r.dest {
+				Some(dest) => parse_address(dest)?,
+				None => {
+					let msg = "when only some of them have an address";
+					return Err(ErrorKind::RecipientsNotSupported(msg.to_owned()).into());
+				}
+			};
+			if address.address_type() != AddressType::Http {
+				let msg = format!("over anything but http, like {}", address);
+				return Err(ErrorKind::RecipientsNotSupported(msg).into());
+			}
+			dests.push(address.stripped());
+		}
+		let version = match args.target_slate_version {
+			Some(v) => SlateVersion::try_from(v)?,
+			None => SlateVersion::default(),
+		};
+		let (mut slate, parent_key_id) = self.open_and_close(|c| {
+			let timings = c.timings.clone();
+			let w = c.backend()?;
+			let parent_key_id = w.get_parent_key_id();
+			Ok((tx::init_send_tx(w, args, &timings)?, parent_key_id))
+		})?;
+		self.record_spend(spend)?;
+
+		// The last recipient to join signs right away
+		let adapter = self.adapter("http", &parent_key_id)?;
+		let signers = &dests[..dests.len() - 1];
+		for dest in dests.iter().chain(signers.iter()) {
+			let vslate = VersionedSlate::into_version(slate.clone(), version.clone());
+			slate = adapter.send_tx_sync(dest, &vslate)?.into();
+		}
+		self.tx_lock_outputs(&slate, 0, Some(dests.join(", ")))?;
+		cli_message!(
+			self.runtime(),
+			"Slate {} for {} grin signed by {} recipients",
+			slate.id.to_string().bright_green(),
+			amount_to_hr_string(slate.amount, false).bright_green(),
+			dests.len()
+		);
+
+		let slate = self.finalize_tx(&slate, None)?;
+		self.post_tx(&slate.tx, false)?;
 		Ok(slate)
-	}*/
+	}
 
-	/*pub fn process_invoice_tx(&self, slate: &Slate, args: InitTxArgs) -> Result<Slate, Error> {
-		let mut w = self.wallet.lock();
-		w.open_with_credentials()?;
-		let slate = owner::process_invoice_tx(&mut *w, slate, args, self.doctest_mode)?;
-		w.close()?;
+	/// Apply the spending policy of the config to a send, before anything
+	/// is built: a send that would go over `daily_spend_limit` fails, and one
+	/// above `confirm_threshold` is put aside until confirmed with the token of
+	/// the error. Returns the amount to count against the daily limit
+	fn check_spend_policy(&self, args: &InitTxArgs, confirmed: bool) -> Result<u64, Error> {
+		let config = self.config();
+		let (threshold, limit) = (config.confirm_threshold(), config.daily_spend_limit());
+		// A re-issued send was already counted when first sent
+		let exempt = args.estimate_only == Some(true) || args.replaces.is_some();
+		if exempt || (threshold.is_none() && limit.is_none()) {
+			return Ok(0);
+		}
+
+		let (amount, spent) = self.open_and_close(|c| {
+			let w = c.backend()?;
+			let amount = match args.sweep {
+				true => {
+					let parent_key_id = match &args.src_acct_name {
+						Some(label) => match w.get_acct_path(label)? {
+							Some(p) => p.path,
+							None => w.get_parent_key_id(),
+						},
+						None => w.get_parent_key_id(),
+					};
+					let minimum_confirmations = args.minimum_confirmations;
+					updater::retrieve_info(w, &parent_key_id, minimum_confirmations)?
+						.amount_currently_spendable
+				}
+				false if !args.recipients.is_empty() => {
+					args.recipients.iter().map(|r| r.amount.nano()).sum()
+				}
+				false => args.amount.nano(),
+			};
+			let today = w.clock().now().naive_utc().date();
+			let spent = w.get_daily_spend(&today)?.map(|s| s.amount).unwrap_or(0);
+			Ok((amount, spent))
+		})?;
+
+		if let Some(limit) = limit {
+			if spent.saturating_add(amount) > limit {
+				let msg = format!(
+					"{} grin sent today, {} grin more would go over the limit of {} grin",
+					amount_to_hr_string(spent, false),
+					amount_to_hr_string(amount, false),
+					amount_to_hr_string(limit, false)
+				);
+				return Err(ErrorKind::SpendLimitExceeded(msg).into());
+			}
+		}
+		match threshold {
+			Some(threshold) if amount > threshold && !confirmed => {
+				let token = Uuid::new_v4();
+				let now = Instant::now();
+				let mut c = self.container.lock();
+				c.pending_sends
+					.retain(|_, (created, _)| now.duration_since(*created) < PENDING_SEND_TIMEOUT);
+				c.pending_sends.insert(token, (now, args.clone()));
+				let amount = amount_to_hr_string(amount, false);
+				Err(ErrorKind::ConfirmationRequired(token.to_string(), amount).into())
+			}
+			_ => Ok(amount),
+		}
+	}
+
+	/// Refuse to pay the same amount to the same address as a send of the
+	/// last `duplicate_send_window` minutes, confirmed or not
+	fn check_duplicate_payment(&self, args: &InitTxArgs) -> Result<(), Error> {
+		let window = match self.config().duplicate_send_window() {
+			Some(window) => window,
+			None => return Ok(()),
+		};
+		let dest = match &args.send_args {
+			Some(_) if args.sweep || args.estimate_only == Some(true) => return Ok(()),
+			Some(sa) => normalize_address(&sa.dest),
+			None => return Ok(()),
+		};
+		let amount = args.amount.nano();
+		let duplicate = self.open_and_close(|c| {
+			let w = c.backend()?;
+			let since = w.clock().now() - chrono::Duration::minutes(window as i64);
+			let duplicate = w.tx_logs()?.find(|t| {
+				t.tx_type == TxLogEntryType::TxSent
+					&& t.creation_ts >= since
+					&& t.address
+						.as_ref()
+						.map_or(false, |a| normalize_address(a) == dest)
+					&& t.amount_debited
+						.saturating_sub(t.amount_credited)
+						.saturating_sub(t.fee.unwrap_or(0))
+						== amount
+			});
+			Ok(duplicate)
+		})?;
+		match duplicate {
+			Some(t) => Err(ErrorKind::DuplicatePayment {
+				tx_id: t.id,
+				amount,
+				amount_disp: amount_to_hr_string(amount, false),
+				address: dest,
+				window,
+			}
+			.into()),
+			None => Ok(()),
+		}
+	}
+
+	/// Count a built send against the daily limit of today
+	fn record_spend(&self, amount: u64) -> Result<(), Error> {
+		if amount == 0 || self.config().daily_spend_limit().is_none() {
+			return Ok(());
+		}
+		self.open_and_close(|c| tx::record_daily_spend(c.backend()?, amount))
+	}
+
+	/// Spend every eligible output of the account to `dest`, or back to our
+	/// own grinbox address to consolidate them into a single output. The
+	/// amount sent is the total of the outputs minus the fee
+	pub fn sweep(
+		&self,
+		dest: Option<InitTxSendArgs>,
+		mut args: InitTxArgs,
+	) -> Result<Slate, Error> {
+		args.amount = Amount::ZERO;
+		args.sweep = true;
+		if args.estimate_only != Some(true) {
+			args.send_args = match dest {
+				Some(dest) => Some(dest),
+				None => Some(InitTxSendArgs {
+					method: Some("grinbox".to_owned()),
+					dest: self.grinbox_address()?.stripped(),
+					finalize: true,
+					post_tx: true,
+					fluff: false,
+				}),
+			};
+		}
+		self.init_send_tx(args)
+	}
+
+	/// Combine the eligible outputs of the active account into `target_outputs`
+	/// outputs with a transaction to ourselves, and post it
+	pub fn consolidate(
+		&self,
+		target_outputs: usize,
+		minimum_confirmations: u64,
+		fluff: bool,
+	) -> Result<Slate, Error> {
+		let spend_lock = self.spend_lock();
+		let _spend = queue_spend(&spend_lock)?;
+		let slate = self.open_and_close(|c| {
+			let timings = c.timings.clone();
+			let w = c.backend()?;
+			tx::consolidate(w, minimum_confirmations, target_outputs, &timings)
+		})?;
+		self.post_tx(&slate.tx, fluff)?;
 		Ok(slate)
-	}*/
+	}
 
-	pub fn tx_lock_outputs(
+	/// Outputs of the active account worth at most `threshold`, with the cost
+	/// of sweeping them now or later
+	pub fn analyze_dust(
 		&self,
-		slate: &Slate,
-		participant_id: usize,
-		address: Option<String>,
-	) -> Result<(), Error> {
+		threshold: Option<u64>,
+		minimum_confirmations: u64,
+	) -> Result<DustAnalysis, Error> {
 		self.open_and_close(|c| {
+			let timings = c.timings.clone();
 			let w = c.backend()?;
-			tx::tx_lock_outputs(w, slate, participant_id, address)
+			timed(&timings, "selection", || {
+				tx::analyze_dust(w, minimum_confirmations, threshold)
+			})
 		})
 	}
 
-	pub fn finalize_tx(
+	/// Combine the dust of the active account into a single output with a
+	/// transaction to ourselves, and post it. Fails if the fee exceeds `max_fee`
+	pub fn sweep_dust(
 		&self,
-		slate: &Slate,
-		tx_proof: Option<&mut TxProof>,
+		threshold: Option<u64>,
+		minimum_confirmations: u64,
+		max_fee: Option<u64>,
+		fluff: bool,
 	) -> Result<Slate, Error> {
+		let spend_lock = self.spend_lock();
+		let _spend = queue_spend(&spend_lock)?;
+		let slate = self.open_and_close(|c| {
+			let timings = c.timings.clone();
+			let w = c.backend()?;
+			timed(&timings, "signing", || {
+				tx::sweep_dust(w, minimum_confirmations, threshold, max_fee)
+			})
+		})?;
+		self.post_tx(&slate.tx, fluff)?;
+		Ok(slate)
+	}
+
+	/// Estimate the fee and locked amount for each of the selection strategies,
+	/// without creating a slate or locking any outputs
+	pub fn estimate_fee(&self, args: EstimateFeeArgs) -> Result<Vec<FeeEstimate>, Error> {
 		self.open_and_close(|c| {
+			let timings = c.timings.clone();
 			let w = c.backend()?;
-			let mut slate = slate.clone();
-			slate = tx::finalize_tx(w, &slate, tx_proof)?;
-			cli_message!(
+			timed(&timings, "selection", || tx::estimate_fee(w, args))
+		})
+	}
+
+	/// Inputs, change outputs and resulting balance of a send, without
+	/// signing or locking anything
+	pub fn preview_send_tx(&self, args: &InitTxArgs) -> Result<SendPreview, Error> {
+		self.open_and_close(|c| {
+			let timings = c.timings.clone();
+			let w = c.backend()?;
+			timed(&timings, "selection", || tx::preview_send_tx(w, args))
+		})
+	}
+
+	/// Forward a slate to the configured foreign API without signing it.
+	/// Doesn't require a seed, so a relay wallet can be run without any keys
+	pub fn forward_slate(&self, slate: &VersionedSlate) -> Result<VersionedSlate, Error> {
+		let (url, secret, seal) = {
+			let c = self.container.lock();
+			(
+				c.config.forward_foreign_api_url.clone(),
+				c.config.forward_foreign_api_secret.clone(),
+				c.config.http_seal_slates(),
+			)
+		};
+		let url = url.ok_or(ErrorKind::NoForwardDestination)?;
+		let slate_id = Slate::from(slate).id;
+
+		info!("Forwarding slate {} to {}", slate_id, url);
+		let res = HTTPAdapter::with_secret(secret, seal).send_tx_sync(&url, slate);
+		match &res {
+			Ok(_) => info!("Slate {} forwarded successfully", slate_id),
+			Err(e) => error!("Unable to forward slate {}: {}", slate_id, e),
+		}
+		res
+	}
+
+	/// Whether the grinbox address is derived from our own seed
+	fn is_own_grinbox_address(&self, address: &GrinboxAddress) -> Result<bool, Error> {
+		let public_key = address.public_key()?;
+		self.open_and_close(|c| {
+			let index = c.config.grinbox_address_index();
+			let w = c.backend()?;
+			Ok(keys::address_path(w, &public_key, index)?.is_some())
+		})
+	}
+
+	/*pub fn issue_invoice_tx(&self, args: IssueInvoiceTxArgs) -> Result<Slate, Error> {
+		let mut w = self.wallet.lock();
+		w.open_with_credentials()?;
+		let slate = owner::issue_invoice_tx(&mut *w, args, self.doctest_mode)?;
+		w.close()?;
+		Ok(slate)
+	}*/
+
+	/*pub fn process_invoice_tx(&self, slate: &Slate, args: InitTxArgs) -> Result<Slate, Error> {
+		let mut w = self.wallet.lock();
+		w.open_with_credentials()?;
+		let slate = owner::process_invoice_tx(&mut *w, slate, args, self.doctest_mode)?;
+		w.close()?;
+		Ok(slate)
+	}*/
+
+	pub fn tx_lock_outputs(
+		&self,
+		slate: &Slate,
+		participant_id: usize,
+		address: Option<String>,
+	) -> Result<(), Error> {
+		let spend_lock = self.spend_lock();
+		let _spend = queue_spend(&spend_lock)?;
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			tx::tx_lock_outputs(w, slate, participant_id, address)
+		})
+	}
+
+	pub fn finalize_tx(
+		&self,
+		slate: &Slate,
+		tx_proof: Option<&mut TxProof>,
+	) -> Result<Slate, Error> {
+		let spend_lock = self.spend_lock();
+		let _spend = queue_spend(&spend_lock)?;
+		self.open_and_close(|c| {
+			let timings = c.timings.clone();
+			let w = c.backend()?;
+			let mut slate = slate.clone();
+			let res = timed(&timings, "signing", || tx::finalize_tx(w, &slate, tx_proof));
+			let result = if res.is_ok() { "success" } else { "failure" };
+			c.metrics
+				.lock()
+				.inc(metrics::FINALIZE, &[("result", result)]);
+			slate = res?;
+			cli_message!(
+				c.runtime,
 				"Slate {} finalized successfully",
 				slate.id.to_string().bright_green()
 			);
+			c.notify(NotificationEvent::TxFinalized, &slate);
 			Ok(slate)
 		})
 	}
 
 	pub fn post_tx(&self, tx: &Transaction, fluff: bool) -> Result<(), Error> {
 		self.open_and_close(|c| {
+			let timings = c.timings.clone();
 			let w = c.backend()?;
 			let tx_hex = to_hex(ser_vec(tx, ProtocolVersion(1)).unwrap());
-			let res = w.w2n_client().post_tx(&TxWrapper { tx_hex }, fluff);
+			let res = timed(&timings, "post", || {
+				w.w2n_client().post_tx(&TxWrapper { tx_hex }, fluff)
+			});
 			if let Err(e) = res {
 				error!("api: post_tx: failed with error: {}", e);
 				Err(e)
@@ -498,16 +1406,23 @@ where
 					tx.hash(),
 					fluff
 				);
-				Ok(())
+				updater::mark_posted(w, tx)
 			}
 		})
 	}
 
 	pub fn cancel_tx(&self, tx_id: Option<u32>, tx_slate_id: Option<Uuid>) -> Result<(), Error> {
+		let spend_lock = self.spend_lock();
+		let _spend = queue_spend(&spend_lock)?;
 		self.open_and_close(|c| {
+			let timings = c.timings.clone();
 			let w = c.backend()?;
 			let parent_key_id = w.get_parent_key_id();
-			if updater::refresh_outputs(w, &parent_key_id, false).is_err() {
+			if timed(&timings, "refresh", || {
+				updater::refresh_outputs(w, &parent_key_id, false)
+			})
+			.is_err()
+			{
 				return Err(ErrorKind::Node.into());
 			}
 
@@ -515,6 +1430,413 @@ where
 		})
 	}
 
+	/// Private context of an unconfirmed send, encrypted with the wallet
+	/// password so another wallet with the same seed can finalize it
+	pub fn export_private_context(
+		&self,
+		tx_id: u32,
+		password: &str,
+	) -> Result<EncryptedContext, Error> {
+		self.open_and_close(|c| {
+			// Fails unless the password is the one the seed is encrypted with
+			WalletSeed::from_file(&c.config.as_wallet_config()?, password)?;
+			let w = c.backend()?;
+			let parent_key_id = w.get_parent_key_id();
+			let (slate_id, context) = tx::export_private_context(w, &parent_key_id, tx_id)?;
+			EncryptedContext::new(slate_id, &context, password, &*w.entropy())
+		})
+	}
+
+	/// Decrypt a context exported by `export_private_context` and store it,
+	/// after checking that it belongs to `slate`
+	pub fn import_private_context(
+		&self,
+		export: &EncryptedContext,
+		slate: &Slate,
+		password: &str,
+	) -> Result<(), Error> {
+		if export.slate_id != slate.id {
+			return Err(ErrorKind::ContextMismatch(format!(
+				"exported for slate {}",
+				export.slate_id
+			)))?;
+		}
+		let context = export.decrypt(password)?;
+		self.open_and_close(|c| tx::import_private_context(c.backend()?, slate, &context))
+	}
+
+	/// Slates of the active account waiting for a response, see `QueuedSlate`
+	pub fn queued_slates(&self) -> Result<Vec<QueuedSlate>, Error> {
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			let parent_key_id = w.get_parent_key_id();
+			tx::queued_slates(w, Some(&parent_key_id))
+		})
+	}
+
+	/// Stop posting the slate of a transaction again. The transaction stays
+	/// pending, cancel it to unlock its outputs
+	pub fn unqueue_slate(&self, tx_id: u32) -> Result<QueuedSlate, Error> {
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			let parent_key_id = w.get_parent_key_id();
+			tx::unqueue_slate(w, &parent_key_id, tx_id)
+		})
+	}
+
+	/// Post the queued slates of all accounts that are due again. Returns
+	/// the slates that were posted, with the outcome recorded
+	pub fn retry_queued_slates(&self) -> Result<Vec<QueuedSlate>, Error> {
+		let max_attempts = self.config().slate_retry_max_attempts();
+		let now = self.clock().now();
+		let due: Vec<QueuedSlate> = self.open_and_close(|c| {
+			let queued = tx::queued_slates(c.backend()?, None)?;
+			Ok(queued
+				.into_iter()
+				.filter(|q| q.is_due(now, max_attempts))
+				.collect())
+		})?;
+
+		let mut attempted = Vec::with_capacity(due.len());
+		for mut queued in due {
+			self.post_queued_slate(&mut queued)?;
+			attempted.push(queued);
+		}
+		Ok(attempted)
+	}
+
+	/// Slates held for approval by the `receive_*` rules, and the rejected ones
+	pub fn incoming_slates(&self) -> Result<Vec<IncomingSlate>, Error> {
+		self.open_and_close(|c| tx::incoming_slates(c.backend()?))
+	}
+
+	/// Receive a slate held for approval and send the response back to its
+	/// sender, over the transport it came in with
+	pub fn approve_incoming(&self, slate_id: &Uuid) -> Result<Slate, Error> {
+		let (incoming, parent_key_id) = self.open_and_close(|c| {
+			let w = c.backend()?;
+			let incoming = w
+				.get_incoming_slate(slate_id)?
+				.filter(|i| !i.rejected)
+				.ok_or(ErrorKind::IncomingSlateNotFound(slate_id.to_string()))?;
+			let parent_key_id = match &incoming.account {
+				Some(label) => {
+					w.get_acct_path(label)?
+						.ok_or_else(|| ErrorKind::UnknownAccountLabel(label.clone()))?
+						.path
+				}
+				None => w.get_parent_key_id(),
+			};
+			Ok((incoming, parent_key_id))
+		})?;
+
+		let slate = Foreign::new(self.container.clone())
+			.with_policy_skipped(true)
+			.receive_tx(
+				&incoming.slate,
+				incoming.account.as_ref().map(String::as_str),
+				Some(incoming.address.clone()),
+				incoming.message.clone(),
+			)?;
+		self.open_and_close(|c| tx::resolve_incoming_slate(c.backend()?, slate_id, false))?;
+
+		let address_type = parse_address(&incoming.address)?.address_type();
+		let method = address_type.method();
+		let vslate = VersionedSlate::into_version(slate.clone(), SlateVersion::default());
+		self.adapter(method, &parent_key_id)?
+			.send_tx_async(&incoming.address, &vslate)?;
+		cli_message!(
+			self.runtime(),
+			"Slate {} sent back to {} successfully",
+			slate.id.to_string().bright_green(),
+			incoming.address.bright_green()
+		);
+		Ok(slate)
+	}
+
+	/// Refuse a slate held for approval. It is kept, so the sender posting
+	/// it again is refused right away
+	pub fn reject_incoming(&self, slate_id: &Uuid) -> Result<IncomingSlate, Error> {
+		self.open_and_close(|c| tx::resolve_incoming_slate(c.backend()?, slate_id, true))
+	}
+
+	/// Store a message read from a polled transport before it is processed.
+	/// Returns false if it was seen before or carries a slate already received
+	pub fn record_received_message(&self, message: &ReceivedMessage) -> Result<bool, Error> {
+		self.open_and_close(|c| tx::record_received_message(c.backend()?, message))
+	}
+
+	/// Messages read from `method` that were not processed yet, oldest first
+	pub fn pending_received_messages(&self, method: &str) -> Result<Vec<ReceivedMessage>, Error> {
+		self.open_and_close(|c| tx::pending_received_messages(c.backend()?, method))
+	}
+
+	pub fn mark_message_processed(&self, method: &str, id: &str) -> Result<(), Error> {
+		self.open_and_close(|c| tx::mark_message_processed(c.backend()?, method, id))
+	}
+
+	/// Sends of the active account that still wait for the response of
+	/// the other party, with their stored slate if there is one
+	pub fn pending_txs(&self) -> Result<Vec<(TxLogEntry, Option<QueuedSlate>)>, Error> {
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			let parent_key_id = w.get_parent_key_id();
+			tx::pending_txs(w, &parent_key_id)
+		})
+	}
+
+	/// Post the stored slate of a pending send again, through the same
+	/// transport and to the same address as the first time
+	pub fn resend_tx(&self, tx_id: u32) -> Result<QueuedSlate, Error> {
+		let mut queued = self.open_and_close(|c| {
+			let w = c.backend()?;
+			let parent_key_id = w.get_parent_key_id();
+			tx::pending_txs(w, &parent_key_id)?
+				.into_iter()
+				.find(|(t, _)| t.id == tx_id)
+				.ok_or(ErrorKind::TransactionNotPending(tx_id.to_string()))?
+				.1
+				.ok_or(ErrorKind::SlateNotQueued(tx_id.to_string()).into())
+		})?;
+		self.post_queued_slate(&mut queued)?;
+		Ok(queued)
+	}
+
+	pub fn set_tx_label(&self, tx_id: u32, label: Option<String>) -> Result<(), Error> {
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			let parent_key_id = w.get_parent_key_id();
+			tx::set_tx_label(w, &parent_key_id, tx_id, label)
+		})
+	}
+
+	/// Update the private notes of a transaction, see `tx::set_tx_notes`
+	pub fn set_tx_notes(
+		&self,
+		tx_id: u32,
+		notes: Option<BTreeMap<String, String>>,
+	) -> Result<(), Error> {
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			let parent_key_id = w.get_parent_key_id();
+			tx::set_tx_notes(w, &parent_key_id, tx_id, notes)
+		})
+	}
+
+	/// Cancel multiple transactions, refreshing from the node only once.
+	/// Returns the result of each cancellation, so one failure doesn't stop the others
+	pub fn cancel_txs(&self, tx_ids: &[u32]) -> Result<Vec<(u32, Result<(), Error>)>, Error> {
+		let spend_lock = self.spend_lock();
+		let _spend = queue_spend(&spend_lock)?;
+		self.open_and_close(|c| {
+			let timings = c.timings.clone();
+			let w = c.backend()?;
+			let parent_key_id = w.get_parent_key_id();
+			if timed(&timings, "refresh", || {
+				updater::refresh_outputs(w, &parent_key_id, false)
+			})
+			.is_err()
+			{
+				return Err(ErrorKind::Node.into());
+			}
+
+			Ok(tx_ids
+				.iter()
+				.map(|id| (*id, tx::cancel_tx(w, &parent_key_id, Some(*id), None)))
+				.collect())
+		})
+	}
+
+	/// Re-issue an unconfirmed send with its fee raised by `fee_factor`. The
+	/// original is cancelled and the new transaction spends exactly the same
+	/// inputs, so at most one of the two can ever confirm
+	pub fn bump_tx(&self, tx_id: u32, fee_factor: f64) -> Result<Slate, Error> {
+		if !(fee_factor > 1.0) {
+			return Err(ErrorKind::InvalidFeeFactor.into());
+		}
+		let spend_lock = self.spend_lock();
+		let _spend = queue_spend(&spend_lock)?;
+		let tx = self.retrieve_tx(Some(tx_id), None)?;
+		let not_bumpable = |reason: &str| -> Error {
+			ErrorKind::TransactionNotBumpable(tx_id.to_string(), reason.to_owned()).into()
+		};
+		if tx.tx_type != TxLogEntryType::TxSent || tx.confirmed {
+			return Err(not_bumpable("only unconfirmed sends can be bumped"));
+		}
+		if tx.linked_parent_key_id.is_some() {
+			return Err(not_bumpable("its fee was paid from another account"));
+		}
+		let dest = tx
+			.address
+			.clone()
+			.ok_or_else(|| not_bumpable("the recipient address is unknown"))?;
+		let fee = tx.fee.unwrap_or(0);
+
+		let (inputs, change_outputs) = self.open_and_close(|c| {
+			let w = c.backend()?;
+			let parent_key_id = w.get_parent_key_id();
+			let outputs =
+				updater::retrieve_outputs(w, false, Some(tx.id), Some(&parent_key_id), None)?;
+			let inputs: Vec<Identifier> = outputs
+				.iter()
+				.filter(|m| m.output.status == OutputStatus::Locked)
+				.map(|m| m.output.key_id.clone())
+				.collect();
+			let change_outputs = outputs
+				.iter()
+				.filter(|m| m.output.status == OutputStatus::Unconfirmed)
+				.count();
+			Ok((inputs, change_outputs))
+		})?;
+		if inputs.is_empty() {
+			return Err(not_bumpable("its inputs are no longer locked"));
+		}
+
+		// Scale the fee per unit of weight, so the new transaction pays
+		// `fee_factor` times the fee of the original for the same weight
+		let weight = tx_fee(inputs.len(), change_outputs + 1, 1, Some(1));
+		let fee_base = (fee as f64 * fee_factor / weight as f64).ceil() as u64;
+		let amount = tx.amount_debited - tx.amount_credited - fee;
+		let args = InitTxArgs {
+			amount: Amount::from_nano(amount),
+			minimum_confirmations: 0,
+			num_change_outputs: change_outputs.max(1) as u32,
+			notes: tx.notes.clone(),
+			fee_base: Some(fee_base),
+			inputs,
+			replaces: Some(tx.id),
+			payment_proof_recipient_address: tx
+				.payment_proof
+				.as_ref()
+				.map(|p| GrinboxAddress::new(p.receiver_address, None, None).stripped()),
+			send_args: Some(InitTxSendArgs {
+				method: None,
+				dest,
+				finalize: true,
+				post_tx: true,
+				fluff: false,
+			}),
+			..Default::default()
+		};
+
+		self.cancel_tx(Some(tx.id), None)?;
+		self.init_send_tx(args)
+	}
+
+	/// Start funding a 2-of-2 output shared with another wallet, from the
+	/// active account. Returns the first message for the other wallet
+	pub fn multisig_init(
+		&self,
+		amount: u64,
+		minimum_confirmations: u64,
+		num_change_outputs: usize,
+	) -> Result<MultisigSlate, Error> {
+		let spend_lock = self.spend_lock();
+		let _spend = queue_spend(&spend_lock)?;
+		let max_outputs = InitTxArgs::default().max_outputs as usize;
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			multisig::init_fund(
+				w,
+				amount,
+				minimum_confirmations,
+				max_outputs,
+				num_change_outputs,
+				false,
+			)
+		})
+	}
+
+	/// Take the next round of a multisig exchange. The wallet completing
+	/// the transaction posts it. Returns the message for the other wallet,
+	/// if it still needs one
+	pub fn multisig_join(&self, msg: &MultisigSlate) -> Result<Option<MultisigSlate>, Error> {
+		let spend_lock = self.spend_lock();
+		let _spend = queue_spend(&spend_lock)?;
+		let response = self.open_and_close(|c| {
+			let w = c.backend()?;
+			multisig::join(w, msg)
+		})?;
+		if let Some(response) = response.as_ref().filter(|r| r.is_final()) {
+			let slate: Slate = response.slate.clone().into();
+			self.post_tx(&slate.tx, false)?;
+		}
+		Ok(response)
+	}
+
+	/// Start spending a funded multisig output into the account it
+	/// belongs to. Returns the first message for the other wallet
+	pub fn multisig_spend(&self, id: &Uuid) -> Result<MultisigSlate, Error> {
+		let spend_lock = self.spend_lock();
+		let _spend = queue_spend(&spend_lock)?;
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			multisig::init_spend(w, id)
+		})
+	}
+
+	/// Multisig outputs of every account, newest first
+	pub fn multisig_outputs(&self) -> Result<Vec<MultisigOutput>, Error> {
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			let mut outputs: Vec<MultisigOutput> = w.multisig_outputs()?.collect();
+			outputs.sort_by(|a, b| b.created.cmp(&a.created));
+			Ok(outputs)
+		})
+	}
+
+	/// Full detail of a transaction of the active account: the messages of
+	/// its participants, its commitments and what the wallet stored of it
+	pub fn tx_detail(&self, tx_id: u32) -> Result<TxDetail, Error> {
+		let tx = self.retrieve_tx(Some(tx_id), None)?;
+		let mut detail = TxDetail {
+			messages: match &tx.messages {
+				Some(m) => m.messages.iter().map(TxMessage::from).collect(),
+				None => vec![],
+			},
+			excess: tx.excess.clone(),
+			inputs: vec![],
+			outputs: vec![],
+			stored_tx: false,
+			tx_proof: false,
+			slates: vec![],
+			tx,
+		};
+		let slate_id = match detail.tx.tx_slate_id {
+			Some(id) => id,
+			None => return Ok(detail),
+		};
+
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			if let Some(stored) = w.get_stored_tx(&slate_id.to_string())? {
+				detail.stored_tx = true;
+				detail.inputs = stored.inputs().iter().map(|i| i.commitment()).collect();
+				detail.outputs = stored.outputs().iter().map(|o| o.commitment()).collect();
+			}
+			if let Some(proof) = w.get_stored_tx_proof(&slate_id.to_string())? {
+				detail.tx_proof = true;
+				if !detail.stored_tx {
+					detail.inputs = proof.inputs.clone();
+					detail.outputs = proof.outputs.clone();
+				}
+				if let Ok((_, slate)) = proof.verify_extract(None) {
+					detail.slates.push(StoredSlate {
+						source: "proof".to_owned(),
+						version: slate.version(),
+					});
+				}
+			}
+			if let Some(queued) = w.get_queued_slate(&slate_id)? {
+				detail.slates.push(StoredSlate {
+					source: "outbox".to_owned(),
+					version: queued.version,
+				});
+			}
+			Ok(detail)
+		})
+	}
+
 	pub fn get_stored_tx(&self, slate_id: &Uuid) -> Result<Option<Transaction>, Error> {
 		let mut c = self.container.lock();
 		let w = c.backend()?;
@@ -581,27 +1903,269 @@ where
 		tx::verify_tx_proof(tx_proof)
 	}
 
-	pub fn restore(&self) -> Result<(), Error> {
-		let grinbox = self.stop_listener(ListenerInterface::Grinbox)?;
+	/// Build an exportable payment proof for a sent transaction, if the
+	/// recipient signed one. The proof is also signed by our sender address
+	pub fn get_payment_proof(
+		&self,
+		tx_id: Option<u32>,
+		tx_slate_id: Option<Uuid>,
+	) -> Result<Option<PaymentProof>, Error> {
+		let tx_entry = self.retrieve_tx(tx_id, tx_slate_id)?;
+		let info = match tx_entry.payment_proof {
+			Some(ref p) if p.receiver_signature.is_some() => p.clone(),
+			_ => return Ok(None),
+		};
+		let excess = match tx_entry.excess {
+			Some(e) => e,
+			None => return Ok(None),
+		};
+		let amount = Amount::from_nano(
+			tx_entry.amount_debited - tx_entry.amount_credited - tx_entry.fee.unwrap_or(0),
+		);
+
+		self.open_and_close(|c| {
+			let index = c.config.grinbox_address_index();
+			let w = c.backend()?;
+			let (parent_key_id, sender_index) = keys::address_path(w, &info.sender_address, index)?
+				.ok_or(ErrorKind::PaymentProof(
+					"sender address does not belong to this wallet".to_owned(),
+				))?;
+			let sec_key = derive_account_address_key(w.keychain(), &parent_key_id, sender_index)?;
+			Ok(Some(PaymentProof::new(amount, excess, info, &sec_key)?))
+		})
+	}
+
+	pub fn verify_payment_proof(&self, proof: &PaymentProof) -> Result<(), Error> {
+		proof.verify()
+	}
+
+	/// Prove that this wallet knows the blinding factor of an output
+	pub fn prove_ownership(
+		&self,
+		commit: &Commitment,
+		message: String,
+	) -> Result<OwnershipProof, Error> {
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			keys::ownership_proof(w, commit, message)
+		})
+	}
+
+	/// Verify an ownership proof. Returns whether the output is currently
+	/// unspent on chain
+	pub fn verify_ownership(&self, proof: &OwnershipProof) -> Result<bool, Error> {
+		proof.verify()?;
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			let outputs = w
+				.w2n_client()
+				.get_outputs_from_node(vec![proof.commit.clone()])?;
+			Ok(outputs.contains_key(&proof.commit))
+		})
+	}
+
+	/// Attest to the unspent outputs of the active account and their total
+	pub fn attest_balance(&self, message: String) -> Result<BalanceAttestation, Error> {
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			keys::balance_attestation(w, message)
+		})
+	}
+
+	/// Verify a balance attestation and check its outputs against the node
+	pub fn verify_balance_attestation(
+		&self,
+		attestation: &BalanceAttestation,
+	) -> Result<AttestationStatus, Error> {
+		attestation.verify()?;
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			let outputs = w
+				.w2n_client()
+				.get_outputs_from_node(attestation.commits.clone())?;
+			let mined_after = outputs
+				.values()
+				.filter(|(_, height, _)| *height > attestation.height)
+				.count();
+			Ok(AttestationStatus {
+				unspent: outputs.len(),
+				mined_after,
+			})
+		})
+	}
 
+	/// Public data a watch-only wallet needs to follow this wallet's outputs
+	pub fn export_watch_key(&self) -> Result<WatchKey, Error> {
 		self.open_and_close(|c| {
 			let w = c.backend()?;
-			w.restore()
+			Ok(WatchKey::from_keychain(w.keychain()))
+		})
+	}
+
+	/// Start watching the wallet of `watch_key`. Only a wallet without a
+	/// seed can watch, and it keeps watching the first key imported
+	pub fn import_watch_key(&self, watch_key: &WatchKey) -> Result<(), Error> {
+		let mut c = self.container.lock();
+		if c.raw_backend().has_seed()? {
+			return Err(ErrorKind::WalletSeedExists.into());
+		}
+		let data_file_dir = c.config.as_wallet_config()?.data_file_dir;
+		match WatchKey::from_file(&data_file_dir)? {
+			Some(ref current) if current == watch_key => Ok(()),
+			Some(_) => Err(ErrorKind::Conflict(
+				"this wallet watches another key, use a fresh data directory".to_owned(),
+			)
+			.into()),
+			None => {
+				fs::create_dir_all(&data_file_dir)?;
+				watch_key.save(&data_file_dir)
+			}
+		}
+	}
+
+	/// Update the watch-only outputs and history from the UTXO set
+	pub fn watch_scan(&self) -> Result<WatchScanSummary, Error> {
+		self.watch_only()?.watch_scan()
+	}
+
+	/// Watch-only outputs, with the height of the last scan
+	pub fn watch_outputs(&self, include_spent: bool) -> Result<(u64, Vec<OutputData>), Error> {
+		let watch_only = self.watch_only()?;
+		let outputs = watch_only
+			.outputs()?
+			.filter(|o| include_spent || o.status != OutputStatus::Spent)
+			.collect();
+		Ok((watch_only.get_last_confirmed_height()?, outputs))
+	}
+
+	/// Watch-only transaction history, with the height of the last scan
+	pub fn watch_txs(&self) -> Result<(u64, Vec<TxLogEntry>), Error> {
+		let watch_only = self.watch_only()?;
+		let txs = watch_only.tx_logs()?.collect();
+		Ok((watch_only.get_last_confirmed_height()?, txs))
+	}
+
+	/// Regular backend of the wallet's data directory, opened with the
+	/// keychain of its watch key instead of a seed
+	fn watch_only(&self) -> Result<Backend<C, WatchKeychain>, Error> {
+		let mut c = self.container.lock();
+		let config = c.config.as_wallet_config()?;
+		let watch_key =
+			WatchKey::from_file(&config.data_file_dir)?.ok_or(CommonErrorKind::NoWatchKey)?;
+		let client = c.raw_backend().w2n_client().clone();
+		let mut backend = Backend::watch_only(&config, client, watch_key)?;
+		backend.connect()?;
+		Ok(backend)
+	}
+
+	/// Restore the wallet from the chain. Accounts found are labelled by
+	/// `labeler`, or `account_<index>` when not set
+	pub fn restore(
+		&self,
+		labeler: Option<&AccountLabeler>,
+	) -> Result<Vec<DiscoveredAccount>, Error> {
+		let grinbox = self.stop_grinbox_listeners()?;
+
+		let accounts = self.open_and_close(|c| {
+			let w = c.backend()?;
+			w.restore(labeler)
 		})?;
 
-		if grinbox {
-			self.start_listener(ListenerInterface::Grinbox)?;
+		for interface in grinbox {
+			self.start_listener(interface)?;
 		}
 
-		Ok(())
+		Ok(accounts)
 	}
 
-	pub fn check_repair(&self, delete_unconfirmed: bool) -> Result<(), Error> {
+	pub fn check_repair(
+		&self,
+		delete_unconfirmed: bool,
+		start_index: Option<u64>,
+	) -> Result<(), Error> {
 		self.open_and_close(|c| {
+			let timings = c.timings.clone();
 			let w = c.backend()?;
 			let parent_key_id = w.get_parent_key_id();
-			updater::refresh_outputs(w, &parent_key_id, true)?;
-			w.check_repair(delete_unconfirmed)
+			timed(&timings, "refresh", || {
+				updater::refresh_outputs(w, &parent_key_id, true)
+			})?;
+			w.check_repair(delete_unconfirmed, start_index)
+		})
+	}
+
+	/// Look up the kernels of the transactions confirmed in the last `days` days
+	pub fn reconcile_kernels(&self, days: u64) -> Result<KernelReconciliation, Error> {
+		self.open_and_close(|c| {
+			let timings = c.timings.clone();
+			let w = c.backend()?;
+			timed(&timings, "reconcile", || {
+				updater::reconcile_kernels(w, days)
+			})
+		})
+	}
+
+	/// Strip the metadata of finished transactions, of all accounts, created
+	/// more than `days` days ago
+	pub fn wipe_history(&self, days: u64) -> Result<HistoryWipe, Error> {
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			updater::wipe_history(w, days)
+		})
+	}
+
+	/// Write the wallet database, stored transactions and proofs to a new
+	/// archive in the backup directory, and remove the oldest archives
+	/// beyond `backup_keep`
+	pub fn backup_now(&self) -> Result<BackupInfo, Error> {
+		let spend_lock = self.spend_lock();
+		let _spend = queue_spend(&spend_lock)?;
+		self.open_and_close(|c| {
+			let data_dir = c.config.as_wallet_config()?.data_file_dir;
+			let backup_dir = c.config.backup_dir()?;
+			let now = c.backend()?.clock().now();
+			let mut info = backup::create(Path::new(&data_dir), &backup_dir, now)?;
+			info.pruned = backup::prune(&backup_dir, c.config.backup_keep())?;
+			Ok(info)
+		})
+	}
+
+	/// When the newest archive in the backup directory was written
+	pub fn last_backup(&self) -> Result<Option<DateTime<Utc>>, Error> {
+		let backup_dir = self.config().backup_dir()?;
+		match backup::list(&backup_dir)?.pop() {
+			Some(path) => Ok(Some(fs::metadata(path)?.modified()?.into())),
+			None => Ok(None),
+		}
+	}
+
+	/// Replace the wallet database, stored transactions and proofs with the
+	/// archive `file`, given as a path or by its name in the backup directory.
+	/// The current ones are backed up first, and that backup is returned
+	pub fn restore_backup(&self, file: &str) -> Result<BackupInfo, Error> {
+		let spend_lock = self.spend_lock();
+		let _spend = queue_spend(&spend_lock)?;
+		self.open_and_close(|c| {
+			let data_dir = PathBuf::from(c.config.as_wallet_config()?.data_file_dir);
+			let backup_dir = c.config.backup_dir()?;
+			backup::stage(&backup::find(&backup_dir, file)?, &data_dir)?;
+			let now = c.backend()?.clock().now();
+			let previous = backup::create(&data_dir, &backup_dir, now)?;
+
+			let w = c.backend()?;
+			w.disconnect()?;
+			let restored = backup::restore(&data_dir);
+			w.connect()?;
+			restored?;
+			Ok(previous)
+		})
+	}
+
+	/// Raise child derivation indices that lag behind existing outputs
+	pub fn fix_derivation(&self) -> Result<Vec<DerivationFix>, Error> {
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			keys::fix_derivation(w)
 		})
 	}
 
@@ -616,6 +2180,86 @@ where
 		})
 	}
 
+	/// Height, sync state, peers and response time of the node
+	pub fn node_status(&self) -> Result<NodeStatus, Error> {
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			w.w2n_client().get_status()
+		})
+	}
+
+	/// Aggregated execution times of commands and internal stages
+	pub fn timings(&self) -> Vec<StageTiming> {
+		let c = self.container.lock();
+		let summary = c.timings.lock().summary();
+		summary
+	}
+
+	/// Counters and gauges of the wallet
+	pub fn metrics(&self) -> SharedMetrics {
+		self.container.lock().metrics.clone()
+	}
+
+	/// Metrics in the Prometheus text format, with the balances of all
+	/// accounts as stored in the wallet and the execution time of each
+	/// stage. Balances are left out while the wallet is locked
+	pub fn render_metrics(&self) -> String {
+		let mut c = self.container.lock();
+		let shared = c.metrics.clone();
+		let stages = c.timings.lock().summary();
+		let minimum_confirmations = InitTxArgs::default().minimum_confirmations;
+		let balances = c.backend().and_then(|w| {
+			let mut balances = Vec::new();
+			for account in keys::accounts(w)? {
+				let info = updater::retrieve_info(w, &account.path, minimum_confirmations)?;
+				balances.push((account.label, info));
+			}
+			Ok(balances)
+		});
+		drop(c);
+
+		let mut m = shared.lock();
+		for (account, info) in balances.unwrap_or_default() {
+			let states = [
+				("total", info.total),
+				("spendable", info.amount_currently_spendable),
+				("awaiting_confirmation", info.amount_awaiting_confirmation),
+				("awaiting_finalization", info.amount_awaiting_finalization),
+				("immature", info.amount_immature),
+				("locked", info.amount_locked),
+			];
+			for (state, amount) in states.iter() {
+				let labels = [("account", account.as_str()), ("state", *state)];
+				m.set(metrics::BALANCE, &labels, *amount as f64);
+			}
+		}
+		for stage in stages {
+			let labels = [("stage", stage.stage.as_str())];
+			let total = stage.total_ms as f64 / 1e3;
+			let max = stage.max_ms as f64 / 1e3;
+			m.set_summary(metrics::STAGE_SECONDS, &labels, total, stage.count);
+			m.set(metrics::STAGE_MAX_SECONDS, &labels, max);
+		}
+		m.render()
+	}
+
+	/// Execution times of the stages recorded since the previous call
+	pub fn take_recent_timings(&self) -> Vec<(String, u64)> {
+		let c = self.container.lock();
+		let recent = c.timings.lock().take_recent();
+		recent
+	}
+
+	pub fn record_timing(&self, stage: &str, elapsed: Duration) {
+		let c = self.container.lock();
+		c.timings.lock().record(stage, elapsed);
+	}
+
+	pub fn reset_timings(&self) {
+		let c = self.container.lock();
+		c.timings.lock().reset();
+	}
+
 	pub fn node_version(&self) -> Option<NodeVersionInfo> {
 		let version = self.open_and_close(|c| {
 			let w = c.backend()?;
@@ -627,18 +2271,80 @@ where
 		}
 	}
 
-	/// Convenience function that opens and closes the wallet with the stored credentials
+	/// Post a queued slate and store the outcome of the attempt
+	fn post_queued_slate(&self, queued: &mut QueuedSlate) -> Result<(), Error> {
+		let vslate = VersionedSlate::into_version(queued.slate.clone(), queued.version.clone());
+		let res = self
+			.adapter(&queued.method, &queued.parent_key_id)
+			.and_then(|a| a.send_tx_async(&queued.dest, &vslate));
+		queued.record_attempt(res.err().map(|e| e.to_string()), self.clock().now());
+
+		// The response may have been finalized or the transaction
+		// cancelled in the meantime
+		self.open_and_close(|c| {
+			let w = c.backend()?;
+			if w.get_queued_slate(&queued.slate_id())?.is_some() {
+				tx::queue_slate(w, queued)?;
+			}
+			Ok(())
+		})
+	}
+
+	/// Adapter to send slates of the account at `parent_key_id` with `method`,
+	/// one of the built in transports, an adapter registered on the container
+	/// or an exec adapter of the config
+	fn adapter<'a>(
+		&'a self,
+		method: &str,
+		parent_key_id: &Identifier,
+	) -> Result<Box<dyn Adapter + 'a>, Error> {
+		let adapter: Box<dyn Adapter + 'a> = match method {
+			"http" => HTTPAdapter::new(self.container.lock().config.http_seal_slates()),
+			"grinbox" => GrinboxAdapter::new(&self.container, account_number(parent_key_id)),
+			"keybase" => KeybaseAdapter::new(&self.container),
+			"email" => EmailAdapter::new(&self.container),
+			name => {
+				let c = self.container.lock();
+				if let Some(adapter) = c.adapters.get(name) {
+					Box::new(adapter.clone())
+				} else if let Some(adapter) = c.exec_adapters.get(name) {
+					Box::new(adapter.clone())
+				} else {
+					error!("unsupported payment method");
+					let msg = "unsupported payment method".to_owned();
+					return Err(ErrorKind::ClientCallback(msg))?;
+				}
+			}
+		};
+		Ok(adapter)
+	}
+
+	fn spend_lock(&self) -> Arc<ReentrantMutex<()>> {
+		self.container.lock().spend_lock.clone()
+	}
+
+	/// Convenience function that opens and closes the wallet with the stored credentials.
+	/// With a session, its account is active while `f` runs
 	fn open_and_close<F, X>(&self, f: F) -> Result<X, Error>
 	where
 		F: FnOnce(&mut MutexGuard<Container<W, C, K>>) -> Result<X, Error>,
 	{
+		let session_account = self.session_account();
 		let mut c = self.container.lock();
+		let account = c.account.clone();
 		{
 			let w = c.backend()?;
 			if !w.has_seed()? {
 				return Err(ErrorKind::NoSeed.into());
 			}
 			w.open_with_credentials()?;
+			if let Some(label) = &session_account {
+				if w.set_parent_key_id_by_name(label).is_err() {
+					let _ = w.close();
+					let msg = format!("account '{}' of this session no longer exists", label);
+					return Err(ErrorKind::Conflict(msg).into());
+				}
+			}
 		}
 		let res = f(&mut c);
 		{
@@ -646,7 +2352,11 @@ where
 			// Operation still considered successful, even if closing failed
 			let w = c.backend();
 			if w.is_ok() {
-				let _ = w.unwrap().close();
+				let w = w.unwrap();
+				if session_account.is_some() {
+					let _ = w.set_parent_key_id_by_name(&account);
+				}
+				let _ = w.close();
 			}
 		}
 		res
@@ -662,6 +2372,69 @@ where
 	fn clone(&self) -> Self {
 		Self {
 			container: self.container.clone(),
+			session: self.session.clone(),
 		}
 	}
 }
+
+/// Wait for the spend in progress, if any, to finish. The lock is reentrant,
+/// so spend paths can call each other
+fn queue_spend(lock: &ReentrantMutex<()>) -> Result<ReentrantMutexGuard<()>, Error> {
+	lock.try_lock_for(SPEND_QUEUE_TIMEOUT).ok_or_else(|| {
+		let msg = "another send, finalization or cancellation is still in progress";
+		ErrorKind::Conflict(msg.to_owned()).into()
+	})
+}
+
+/// Cancel the sent transactions that remain unconfirmed for too long after
+/// a successful refresh. Like reposts, failures are only logged
+fn auto_cancel<T: ?Sized, C, K>(
+	wallet: &mut T,
+	parent_key_id: &Identifier,
+	height: u64,
+	after_blocks: Option<u64>,
+	runtime: &RuntimeContext,
+) where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if let Some(after_blocks) = after_blocks {
+		match tx::auto_cancel_txs(wallet, parent_key_id, height, after_blocks) {
+			Ok(ids) => {
+				for id in ids {
+					cli_message!(
+						runtime,
+						"Transaction {} unconfirmed after {} blocks, cancelled",
+						id.to_string().bright_green(),
+						after_blocks
+					);
+				}
+			}
+			Err(e) => warn!("Unable to automatically cancel transactions: {}", e),
+		}
+	}
+}
+
+/// Run the automatic repost policy after a successful refresh. Failures
+/// are only logged, they shouldn't break the command that triggered the refresh
+fn auto_repost<T: ?Sized, C, K>(wallet: &mut T, height: u64, policy: Option<RepostPolicy>)
+where
+	T: WalletBackend<C, K>,
+	C: NodeClient,
+	K: Keychain,
+{
+	if let Some(policy) = policy {
+		if let Err(e) = updater::auto_repost(wallet, height, &policy) {
+			warn!("Unable to automatically repost transactions: {}", e);
+		}
+	}
+}
+
+/// Address in the form sends log it, so the same address given in another
+/// form still compares equal
+fn normalize_address(address: &str) -> String {
+	parse_address(address)
+		.map(|a| a.stripped())
+		.unwrap_or_else(|_| address.to_owned())
+}
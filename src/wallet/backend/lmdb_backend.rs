@@ -12,15 +12,21 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::lock_file::LockFile;
 use super::types::{
-	AcctPathMapping, ChildNumber, Context, Identifier, NodeClient, OutputData, Result, Transaction,
-	TxLogEntry, TxProof, WalletBackend, WalletBackendBatch, WalletSeed,
+	AccountLabeler, AcctPathMapping, BlockIdentifier, ChildNumber, Context, DailySpend,
+	DiscoveredAccount, EventBus, Identifier, IncomingSlate, MultisigOutput, NodeClient, OutputData,
+	OutputStatus, QueuedSlate, ReceivedMessage, Result, ScanOptions, SharedEventBus, SlateSigner,
+	Transaction, TxLogEntry, TxProof, WalletBackend, WalletBackendBatch, WalletSeed, WatchKey,
+	WatchKeychain, DEFAULT_ACCOUNT,
 };
+use crate::common::clock::{SharedClock, SystemClock};
 use crate::common::config::WalletConfig;
+use crate::common::entropy::{OsEntropy, SharedEntropy};
 use crate::common::{ErrorKind, Keychain};
 use crate::internal::restore;
 use blake2_rfc::blake2b::Blake2b;
-use chrono::Utc;
+use chrono::NaiveDate;
 use failure::ResultExt;
 use grin_core::{global, ser};
 use grin_keychain::SwitchCommitmentType;
@@ -28,11 +34,13 @@ use grin_store::Store;
 use grin_store::{self, option_to_not_found, to_key, to_key_u64};
 use grin_util::secp::constants::SECRET_KEY_SIZE;
 use grin_util::{from_hex, to_hex, ZeroingString};
+use log::debug;
 use std::cell::RefCell;
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::ops::Deref;
 use std::path::Path;
+use uuid::Uuid;
 
 pub const DB_DIR: &'static str = "db";
 pub const TX_SAVE_DIR: &'static str = "saved_txs";
@@ -45,6 +53,26 @@ const PRIVATE_TX_CONTEXT_PREFIX: u8 = 'p' as u8;
 const TX_LOG_ENTRY_PREFIX: u8 = 't' as u8;
 const TX_LOG_ID_PREFIX: u8 = 'i' as u8;
 const ACCOUNT_PATH_MAPPING_PREFIX: u8 = 'a' as u8;
+const QUEUED_SLATE_PREFIX: u8 = 'q' as u8;
+const MULTISIG_OUTPUT_PREFIX: u8 = 'm' as u8;
+const DAILY_SPEND_PREFIX: u8 = 's' as u8;
+const CHECKPOINT_PREFIX: u8 = 'b' as u8;
+const INCOMING_SLATE_PREFIX: u8 = 'r' as u8;
+const RECEIVED_MESSAGE_PREFIX: u8 = 'g' as u8;
+
+/// Key prefix of the outputs derived directly from `parent_key_id`. Output
+/// keys start with the serialized key id, which holds the depth followed by
+/// the path, so all children of an account share the same prefix
+fn account_output_prefix(parent_key_id: &Identifier) -> Vec<u8> {
+	let depth = parent_key_id.to_path().depth as usize;
+	let mut id = parent_key_id.to_bytes().to_vec();
+	id[0] = depth as u8 + 1;
+	id.truncate(1 + 4 * depth);
+
+	let mut prefix = vec![OUTPUT_PREFIX];
+	prefix.extend(id);
+	prefix
+}
 
 fn private_ctx_xor_keys<K>(
 	keychain: &K,
@@ -88,6 +116,13 @@ where
 	parent_key_id: Identifier,
 	config: WalletConfig,
 	w2n_client: C,
+	clock: SharedClock,
+	entropy: SharedEntropy,
+	events: SharedEventBus,
+	/// Held while connected, see `LockFile`
+	lock: Option<LockFile>,
+	/// Take the lock over on the next connect even if it is held
+	force_unlock: bool,
 }
 
 impl<C, K> Backend<C, K>
@@ -99,6 +134,10 @@ where
 		self.db.as_ref().ok_or(ErrorKind::NoWallet.into())
 	}
 
+	pub(super) fn scan_options(&self) -> ScanOptions {
+		ScanOptions::new(self.config.restore_threads, self.config.restore_batch_size)
+	}
+
 	/// Create `Backend` instance
 	pub fn new(config: &WalletConfig, client: C) -> Result<Self> {
 		Ok(Self {
@@ -108,9 +147,29 @@ where
 			parent_key_id: K::derive_key_id(2, 0, 0, 0, 0),
 			config: config.clone(),
 			w2n_client: client,
+			clock: SystemClock::shared(),
+			entropy: OsEntropy::shared(),
+			events: EventBus::shared(),
+			lock: None,
+			force_unlock: false,
 		})
 	}
 
+	/// Open the wallet even if another process seems to have it open, for
+	/// a lock left behind where the operating system doesn't release it
+	pub fn with_force_unlock(mut self, force_unlock: bool) -> Self {
+		self.force_unlock = force_unlock;
+		self
+	}
+
+	/// Replace the system clock and randomness, e.g. to make tests deterministic
+	pub fn with_sources(mut self, clock: SharedClock, entropy: SharedEntropy) -> Self {
+		debug!("Using {} entropy", entropy.name());
+		self.clock = clock;
+		self.entropy = entropy;
+		self
+	}
+
 	/*pub fn new(config: &WalletConfig, password: &str, n_client: C) -> Result<Self> {
 		let res = Backend {
 			db: None,
@@ -124,10 +183,23 @@ where
 	}*/
 }
 
+impl<C> Backend<C, WatchKeychain>
+where
+	C: NodeClient,
+{
+	/// Backend of a watch-only wallet, which has no seed. Its keychain only
+	/// knows the public root key of the watched wallet
+	pub fn watch_only(config: &WalletConfig, client: C, watch_key: WatchKey) -> Result<Self> {
+		let mut backend = Self::new(config, client)?;
+		backend.keychain = Some(WatchKeychain::new(watch_key));
+		Ok(backend)
+	}
+}
+
 impl<C, K> WalletBackend<C, K> for Backend<C, K>
 where
 	C: NodeClient,
-	K: Keychain,
+	K: Keychain + SlateSigner,
 {
 	/// Check whether the backend has a seed or not
 	fn has_seed(&self) -> Result<bool> {
@@ -158,7 +230,14 @@ where
 			return Err(ErrorKind::WalletHasSeed.into());
 		}
 		self.password = Some(password.clone());
-		let _ = WalletSeed::init_file(&self.config, 24, mnemonic, &password, overwrite)?;
+		let _ = WalletSeed::init_file(
+			&self.config,
+			24,
+			mnemonic,
+			&password,
+			overwrite,
+			&*self.entropy,
+		)?;
 		Ok(())
 	}
 
@@ -169,7 +248,8 @@ where
 
 	/// Connect to the backend
 	fn connect(&mut self) -> Result<()> {
-		if !self.has_seed()? {
+		// A watch-only wallet has its keychain before it connects
+		if !self.has_seed()? && self.keychain.is_none() {
 			return Err(ErrorKind::WalletNoSeed.into());
 		}
 		if self.connected()? {
@@ -177,6 +257,9 @@ where
 		}
 
 		let root_path = Path::new(&self.config.data_file_dir);
+		fs::create_dir_all(root_path)?;
+		let lock = LockFile::acquire(root_path, self.force_unlock)?;
+		self.force_unlock = false;
 
 		let db_path = root_path.join(DB_DIR);
 		fs::create_dir_all(&db_path)?;
@@ -190,8 +273,10 @@ where
 		let store = Store::new(db_path.to_str().unwrap(), None, Some(DB_DIR), None)?;
 
 		let default_account = AcctPathMapping {
-			label: "default".to_string(),
+			label: DEFAULT_ACCOUNT.to_string(),
 			path: K::derive_key_id(2, 0, 0, 0, 0),
+			notifications: None,
+			archived: false,
 		};
 		let acct_key = to_key(
 			ACCOUNT_PATH_MAPPING_PREFIX,
@@ -205,12 +290,14 @@ where
 		}
 
 		self.db = Some(store);
+		self.lock = Some(lock);
 		Ok(())
 	}
 
 	/// Disconnect from backend
 	fn disconnect(&mut self) -> Result<()> {
 		self.db = None;
+		self.lock = None;
 		Ok(())
 	}
 
@@ -221,6 +308,18 @@ where
 		Ok(())
 	}
 
+	/// Re-encrypt the seed with a new password. The database isn't
+	/// encrypted, so the seed file is all that changes
+	fn change_password(
+		&mut self,
+		old_password: ZeroingString,
+		new_password: ZeroingString,
+	) -> Result<()> {
+		WalletSeed::change_password(&self.config, &old_password, &new_password, &*self.entropy)?;
+		self.password = Some(new_password);
+		Ok(())
+	}
+
 	/// Clear out backend
 	fn clear(&mut self) -> Result<()> {
 		self.disconnect()?;
@@ -230,7 +329,7 @@ where
 			return Ok(());
 		}
 
-		let backup_dir = Utc::now().format("%Y%m%d-%H%M%S").to_string();
+		let backup_dir = self.clock.now().format("%Y%m%d-%H%M%S").to_string();
 		let backup_path = root_path.join("backups").join(backup_dir);
 		fs::create_dir_all(&backup_path)?;
 		let db_path = root_path.join(DB_DIR);
@@ -255,7 +354,7 @@ where
 	fn open_with_credentials(&mut self) -> Result<()> {
 		let wallet_seed = WalletSeed::from_file(
 			&self.config,
-			&self.password.clone().ok_or(ErrorKind::OpenWalletError)?,
+			&self.password.clone().ok_or(ErrorKind::WalletLocked)?,
 		)
 		.map_err(|_| ErrorKind::OpenWalletError)?;
 		self.keychain = Some(
@@ -272,16 +371,43 @@ where
 		Ok(())
 	}
 
+	/// The password is zeroed out when dropped
+	fn lock(&mut self) -> Result<()> {
+		self.keychain = None;
+		self.password = None;
+		Ok(())
+	}
+
+	fn is_locked(&self) -> bool {
+		self.password.is_none()
+	}
+
 	/// Return the keychain being used
 	fn keychain(&mut self) -> &mut K {
 		self.keychain.as_mut().unwrap()
 	}
 
+	fn signer(&self) -> &dyn SlateSigner {
+		self.keychain.as_ref().unwrap()
+	}
+
 	/// Return the node client being used
 	fn w2n_client(&mut self) -> &mut C {
 		&mut self.w2n_client
 	}
 
+	fn clock(&self) -> SharedClock {
+		self.clock.clone()
+	}
+
+	fn entropy(&self) -> SharedEntropy {
+		self.entropy.clone()
+	}
+
+	fn events(&self) -> SharedEventBus {
+		self.events.clone()
+	}
+
 	/// Set parent path by account name
 	fn set_parent_key_id_by_name(&mut self, label: &str) -> Result<()> {
 		let label = label.to_owned();
@@ -318,6 +444,25 @@ where
 		))
 	}
 
+	fn account_outputs<'a>(
+		&'a self,
+		parent_key_id: &Identifier,
+		statuses: &[OutputStatus],
+	) -> Result<Box<dyn Iterator<Item = OutputData> + 'a>> {
+		let parent_key_id = parent_key_id.clone();
+		let statuses = statuses.to_vec();
+		Ok(Box::new(
+			self.db()?
+				.iter(&account_output_prefix(&parent_key_id))
+				.unwrap()
+				.map(|x| x.1)
+				.filter(move |o: &OutputData| {
+					o.root_key_id == parent_key_id
+						&& (statuses.is_empty() || statuses.contains(&o.status))
+				}),
+		))
+	}
+
 	fn get_tx_log_by_slate_id(&self, slate_id: &str) -> Result<Option<TxLogEntry>> {
 		let key = to_key(TX_LOG_ENTRY_PREFIX, &mut slate_id.as_bytes().to_vec());
 		self.db()?.get_ser(&key).map_err(|e| e.into())
@@ -332,6 +477,74 @@ where
 		))
 	}
 
+	fn get_queued_slate(&self, slate_id: &Uuid) -> Result<Option<QueuedSlate>> {
+		let key = to_key(QUEUED_SLATE_PREFIX, &mut slate_id.as_bytes().to_vec());
+		self.db()?.get_ser(&key).map_err(|e| e.into())
+	}
+
+	fn queued_slates<'a>(&'a self) -> Result<Box<dyn Iterator<Item = QueuedSlate> + 'a>> {
+		Ok(Box::new(
+			self.db()?
+				.iter(&[QUEUED_SLATE_PREFIX])
+				.unwrap()
+				.map(|x| x.1),
+		))
+	}
+
+	fn get_incoming_slate(&self, slate_id: &Uuid) -> Result<Option<IncomingSlate>> {
+		let key = to_key(INCOMING_SLATE_PREFIX, &mut slate_id.as_bytes().to_vec());
+		self.db()?.get_ser(&key).map_err(|e| e.into())
+	}
+
+	fn incoming_slates<'a>(&'a self) -> Result<Box<dyn Iterator<Item = IncomingSlate> + 'a>> {
+		Ok(Box::new(
+			self.db()?
+				.iter(&[INCOMING_SLATE_PREFIX])
+				.unwrap()
+				.map(|x| x.1),
+		))
+	}
+
+	fn get_received_message(&self, method: &str, id: &str) -> Result<Option<ReceivedMessage>> {
+		let key = ReceivedMessage::key(method, id);
+		let key = to_key(RECEIVED_MESSAGE_PREFIX, &mut key.as_bytes().to_vec());
+		self.db()?.get_ser(&key).map_err(|e| e.into())
+	}
+
+	fn received_messages<'a>(&'a self) -> Result<Box<dyn Iterator<Item = ReceivedMessage> + 'a>> {
+		Ok(Box::new(
+			self.db()?
+				.iter(&[RECEIVED_MESSAGE_PREFIX])
+				.unwrap()
+				.map(|x| x.1),
+		))
+	}
+
+	fn get_multisig_output(&self, id: &Uuid) -> Result<Option<MultisigOutput>> {
+		let key = to_key(MULTISIG_OUTPUT_PREFIX, &mut id.as_bytes().to_vec());
+		self.db()?.get_ser(&key).map_err(|e| e.into())
+	}
+
+	fn multisig_outputs<'a>(&'a self) -> Result<Box<dyn Iterator<Item = MultisigOutput> + 'a>> {
+		Ok(Box::new(
+			self.db()?
+				.iter(&[MULTISIG_OUTPUT_PREFIX])
+				.unwrap()
+				.map(|x| x.1),
+		))
+	}
+
+	fn get_daily_spend(&self, day: &NaiveDate) -> Result<Option<DailySpend>> {
+		let key = to_key(DAILY_SPEND_PREFIX, &mut day.to_string().into_bytes());
+		self.db()?.get_ser(&key).map_err(|e| e.into())
+	}
+
+	fn checkpoints<'a>(&'a self) -> Result<Box<dyn Iterator<Item = BlockIdentifier> + 'a>> {
+		Ok(Box::new(
+			self.db()?.iter(&[CHECKPOINT_PREFIX]).unwrap().map(|x| x.1),
+		))
+	}
+
 	fn get_private_context(&mut self, slate_id: &[u8], participant_id: usize) -> Result<Context> {
 		let ctx_key = to_key_u64(
 			PRIVATE_TX_CONTEXT_PREFIX,
@@ -418,14 +631,7 @@ where
 	}
 
 	fn next_child<'a>(&mut self) -> Result<Identifier> {
-		let mut deriv_idx = {
-			let batch = self.db()?.batch()?;
-			let deriv_key = to_key(DERIV_PREFIX, &mut self.parent_key_id.to_bytes().to_vec());
-			match batch.get_ser(&deriv_key)? {
-				Some(idx) => idx,
-				None => 0,
-			}
-		};
+		let mut deriv_idx = self.child_index(&self.parent_key_id)?;
 		let mut return_path = self.parent_key_id.to_path();
 		return_path.depth = return_path.depth + 1;
 		return_path.path[return_path.depth as usize - 1] = ChildNumber::from(deriv_idx);
@@ -436,6 +642,12 @@ where
 		Ok(Identifier::from_path(&return_path))
 	}
 
+	fn child_index(&self, parent_key_id: &Identifier) -> Result<u32> {
+		let batch = self.db()?.batch()?;
+		let deriv_key = to_key(DERIV_PREFIX, &mut parent_key_id.to_bytes().to_vec());
+		Ok(batch.get_ser(&deriv_key)?.unwrap_or(0))
+	}
+
 	fn get_last_confirmed_height<'a>(&self) -> Result<u64> {
 		let batch = self.db()?.batch()?;
 		let height_key = to_key(
@@ -449,13 +661,18 @@ where
 		Ok(last_confirmed_height)
 	}
 
-	fn restore(&mut self) -> Result<()> {
-		restore::restore(self).context(ErrorKind::Restore)?;
-		Ok(())
+	fn restore(&mut self, labeler: Option<&AccountLabeler>) -> Result<Vec<DiscoveredAccount>> {
+		let options = self.scan_options();
+		let accounts = restore::restore(self, options, labeler).context(ErrorKind::Restore)?;
+		Ok(accounts)
 	}
 
-	fn check_repair(&mut self, delete_unconfirmed: bool) -> Result<()> {
-		restore::check_repair(self, delete_unconfirmed).context(ErrorKind::Restore)?;
+	fn check_repair(&mut self, delete_unconfirmed: bool, start_index: Option<u64>) -> Result<()> {
+		let mut options = self.scan_options();
+		if let Some(index) = start_index {
+			options = options.from_index(index);
+		}
+		restore::check_repair(self, delete_unconfirmed, options).context(ErrorKind::Restore)?;
 		Ok(())
 	}
 
@@ -471,6 +688,22 @@ where
 			)))
 		}
 	}
+
+	fn adaptive_selection(&self) -> bool {
+		self.config.adaptive_selection.unwrap_or(false)
+	}
+
+	fn min_output_value(&self) -> u64 {
+		self.config.min_output_value.unwrap_or(0)
+	}
+
+	fn fee_base(&self) -> Option<u64> {
+		self.config.fee_base
+	}
+
+	fn payjoin(&self) -> bool {
+		self.config.payjoin.unwrap_or(false)
+	}
 }
 
 /// An atomic batch in which all changes can be committed all at once or
@@ -548,6 +781,28 @@ where
 		Ok(())
 	}
 
+	fn delete_stored_tx(&self, uuid: &str) -> Result<()> {
+		let filename = format!("{}.grintx", uuid);
+		let path = Path::new(&self._store.config.data_file_dir)
+			.join(TX_SAVE_DIR)
+			.join(filename);
+		if path.exists() {
+			fs::remove_file(path)?;
+		}
+		Ok(())
+	}
+
+	fn delete_stored_tx_proof(&self, uuid: &str) -> Result<()> {
+		let filename = format!("{}.proof", uuid);
+		let path = Path::new(&self._store.config.data_file_dir)
+			.join(TX_PROOF_SAVE_DIR)
+			.join(filename);
+		if path.exists() {
+			fs::remove_file(path)?;
+		}
+		Ok(())
+	}
+
 	fn next_tx_log_id(&mut self, parent_key_id: &Identifier) -> Result<u32> {
 		let tx_id_key = to_key(TX_LOG_ID_PREFIX, &mut parent_key_id.to_bytes().to_vec());
 		let last_tx_log_id = match self.db.borrow().as_ref().unwrap().get_ser(&tx_id_key)? {
@@ -612,6 +867,12 @@ where
 		Ok(())
 	}
 
+	fn delete_acct_path(&mut self, label: &str) -> Result<()> {
+		let acct_key = to_key(ACCOUNT_PATH_MAPPING_PREFIX, &mut label.as_bytes().to_vec());
+		let _ = self.db.borrow().as_ref().unwrap().delete(&acct_key);
+		Ok(())
+	}
+
 	fn lock_output(&mut self, out: &mut OutputData) -> Result<()> {
 		out.lock();
 		self.save_output(out)
@@ -658,6 +919,79 @@ where
 			.map_err(|e| e.into())
 	}
 
+	fn save_queued_slate(&mut self, queued: &QueuedSlate) -> Result<()> {
+		let key = to_key(
+			QUEUED_SLATE_PREFIX,
+			&mut queued.slate_id().as_bytes().to_vec(),
+		);
+		self.db.borrow().as_ref().unwrap().put_ser(&key, &queued)?;
+		Ok(())
+	}
+
+	fn delete_queued_slate(&mut self, slate_id: &Uuid) -> Result<()> {
+		let key = to_key(QUEUED_SLATE_PREFIX, &mut slate_id.as_bytes().to_vec());
+		let _ = self.db.borrow().as_ref().unwrap().delete(&key);
+		Ok(())
+	}
+
+	fn save_incoming_slate(&mut self, incoming: &IncomingSlate) -> Result<()> {
+		let key = to_key(
+			INCOMING_SLATE_PREFIX,
+			&mut incoming.slate_id().as_bytes().to_vec(),
+		);
+		self.db
+			.borrow()
+			.as_ref()
+			.unwrap()
+			.put_ser(&key, &incoming)?;
+		Ok(())
+	}
+
+	fn delete_incoming_slate(&mut self, slate_id: &Uuid) -> Result<()> {
+		let key = to_key(INCOMING_SLATE_PREFIX, &mut slate_id.as_bytes().to_vec());
+		let _ = self.db.borrow().as_ref().unwrap().delete(&key);
+		Ok(())
+	}
+
+	fn save_received_message(&mut self, message: &ReceivedMessage) -> Result<()> {
+		let key = ReceivedMessage::key(&message.method, &message.id);
+		let key = to_key(RECEIVED_MESSAGE_PREFIX, &mut key.as_bytes().to_vec());
+		self.db.borrow().as_ref().unwrap().put_ser(&key, &message)?;
+		Ok(())
+	}
+
+	fn delete_received_message(&mut self, method: &str, id: &str) -> Result<()> {
+		let key = ReceivedMessage::key(method, id);
+		let key = to_key(RECEIVED_MESSAGE_PREFIX, &mut key.as_bytes().to_vec());
+		let _ = self.db.borrow().as_ref().unwrap().delete(&key);
+		Ok(())
+	}
+
+	fn save_multisig_output(&mut self, output: &MultisigOutput) -> Result<()> {
+		let key = to_key(MULTISIG_OUTPUT_PREFIX, &mut output.id.as_bytes().to_vec());
+		self.db.borrow().as_ref().unwrap().put_ser(&key, &output)?;
+		Ok(())
+	}
+
+	fn save_daily_spend(&mut self, spend: &DailySpend) -> Result<()> {
+		let key = to_key(DAILY_SPEND_PREFIX, &mut spend.day.to_string().into_bytes());
+		self.db.borrow().as_ref().unwrap().put_ser(&key, &spend)?;
+		Ok(())
+	}
+
+	fn save_checkpoint(&mut self, block: &BlockIdentifier) -> Result<()> {
+		// Big endian heights, so checkpoints iterate lowest first
+		let key = to_key_u64(CHECKPOINT_PREFIX, &mut vec![], block.height);
+		self.db.borrow().as_ref().unwrap().put_ser(&key, &block)?;
+		Ok(())
+	}
+
+	fn delete_checkpoint(&mut self, height: u64) -> Result<()> {
+		let key = to_key_u64(CHECKPOINT_PREFIX, &mut vec![], height);
+		let _ = self.db.borrow().as_ref().unwrap().delete(&key);
+		Ok(())
+	}
+
 	fn commit(&mut self) -> Result<()> {
 		let db = self.db.replace(None);
 		db.unwrap().commit()?;
@@ -0,0 +1,137 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Advisory lock on the data directory, so a second wallet713 process can't
+//! open the same database and spend the outputs the first one has locked
+
+use super::types::Result;
+use crate::common::ErrorKind;
+use fs2::FileExt;
+use log::warn;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+use std::process::{self, Command};
+
+pub const LOCK_FILE: &str = "wallet713.lock";
+
+/// Lock held as long as this is kept. The operating system releases it when
+/// the process dies, so a crash doesn't leave the wallet locked
+pub struct LockFile {
+	_file: File,
+}
+
+impl LockFile {
+	/// Lock `dir`, failing if another process holds the lock. With `force`,
+	/// the lock is taken over if the process that wrote the lock file is no
+	/// longer running, for file systems that don't release it
+	pub fn acquire(dir: &Path, force: bool) -> Result<Self> {
+		let path = dir.join(LOCK_FILE);
+		if let Some(lock) = Self::try_acquire(&path)? {
+			return Ok(lock);
+		}
+
+		let holder = fs::read_to_string(&path)
+			.ok()
+			.and_then(|pid| pid.trim().parse::<u32>().ok());
+		if let (true, Some(pid)) = (force, holder) {
+			if !is_running(pid) {
+				warn!("Taking over the lock of process {}, which is gone", pid);
+				fs::remove_file(&path)?;
+				if let Some(lock) = Self::try_acquire(&path)? {
+					return Ok(lock);
+				}
+			}
+		}
+		let holder = match holder {
+			Some(pid) => format!("process {}", pid),
+			None => "another process".to_owned(),
+		};
+		let path = path.display().to_string();
+		Err(ErrorKind::WalletInUse(path, holder).into())
+	}
+
+	/// The lock, or `None` when another process has it
+	fn try_acquire(path: &Path) -> Result<Option<Self>> {
+		let mut file = OpenOptions::new()
+			.read(true)
+			.write(true)
+			.create(true)
+			// Not before the lock is ours, the holder's id is read from it
+			.truncate(false)
+			.open(path)?;
+		if let Err(e) = file.try_lock_exclusive() {
+			if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+				return Ok(None);
+			}
+			return Err(ErrorKind::LockFile(e.to_string()).into());
+		}
+
+		// For the error message of the next process trying
+		file.set_len(0)?;
+		file.seek(SeekFrom::Start(0))?;
+		write!(file, "{}", process::id())?;
+		Ok(Some(Self { _file: file }))
+	}
+}
+
+/// Whether the process `pid` is running. Assumed so when it can't be told
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+	let proc_dir = Path::new("/proc");
+	if proc_dir.join("self").exists() {
+		return proc_dir.join(pid.to_string()).exists();
+	}
+	Command::new("ps")
+		.arg("-p")
+		.arg(pid.to_string())
+		.output()
+		.map(|o| o.status.success())
+		.unwrap_or(true)
+}
+
+/// Whether the process `pid` is running. Assumed so when it can't be told
+#[cfg(windows)]
+fn is_running(pid: u32) -> bool {
+	Command::new("tasklist")
+		.args(&["/NH", "/FI", &format!("PID eq {}", pid)])
+		.output()
+		.map(|o| String::from_utf8_lossy(&o.stdout).contains(&format!(" {} ", pid)))
+		.unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::env;
+	use uuid::Uuid;
+
+	#[test]
+	fn force_only_takes_over_locks_of_gone_processes() {
+		let dir = env::temp_dir().join(format!("wallet713-lock-{}", Uuid::new_v4()));
+		fs::create_dir_all(&dir).unwrap();
+
+		let lock = LockFile::acquire(&dir, false).unwrap();
+		for force in vec![false, true] {
+			let e = LockFile::acquire(&dir, force).err().unwrap();
+			match e.downcast_ref::<ErrorKind>() {
+				Some(ErrorKind::WalletInUse(..)) => {}
+				_ => panic!("unexpected error {}", e),
+			}
+		}
+		drop(lock);
+		LockFile::acquire(&dir, false).unwrap();
+		let _ = fs::remove_dir_all(&dir);
+	}
+}
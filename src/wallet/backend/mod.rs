@@ -13,6 +13,9 @@
 // limitations under the License.
 
 mod lmdb_backend;
+mod lock_file;
+mod watch_only;
 
-pub use self::lmdb_backend::Backend;
+pub use self::lmdb_backend::{Backend, DB_DIR, TX_PROOF_SAVE_DIR, TX_SAVE_DIR};
+pub use self::watch_only::WatchScanSummary;
 use super::types;
@@ -0,0 +1,139 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::lmdb_backend::Backend;
+use super::types::{
+	NodeClient, OutputData, OutputStatus, Result, TxLogEntry, TxLogEntryType, WalletBackend,
+	WatchKeychain,
+};
+use crate::common::ErrorKind;
+use crate::internal::restore;
+use std::collections::HashMap;
+
+/// Changes found by a scan of the UTXO set
+#[derive(Debug, Clone, Default)]
+pub struct WatchScanSummary {
+	pub height: u64,
+	pub received: usize,
+	pub spent: usize,
+}
+
+impl<C> Backend<C, WatchKeychain>
+where
+	C: NodeClient,
+{
+	/// Scan the UTXO set with the watch key. Outputs that appeared are
+	/// recorded as received, known outputs that disappeared as spent
+	pub fn watch_scan(&mut self) -> Result<WatchScanSummary> {
+		let client = self.w2n_client().clone();
+		let keychain = self.keychain().clone();
+		let height = client.get_chain_height()?;
+		let found = restore::collect_watched_outputs(&client, &keychain, self.scan_options())?;
+
+		let mut known: HashMap<String, OutputData> = self
+			.outputs()?
+			.filter_map(|o| o.commit.clone().map(|c| (c, o)))
+			.collect();
+		let mut summary = WatchScanSummary {
+			height,
+			..WatchScanSummary::default()
+		};
+
+		let now = self.clock().now();
+		let mut batch = self.batch()?;
+		for mut output in found {
+			let commit = output.commit.clone().ok_or(ErrorKind::Restore)?;
+			if let Some(o) = known.remove(&commit) {
+				if o.status == OutputStatus::Unspent {
+					continue;
+				}
+			}
+			let parent_key_id = output.root_key_id.clone();
+			let log_id = batch.next_tx_log_id(&parent_key_id)?;
+			let mut t = TxLogEntry::new(parent_key_id, TxLogEntryType::TxReceived, log_id, now);
+			t.confirmed = true;
+			t.update_confirmation_ts(now);
+			t.amount_credited = output.value;
+			t.num_outputs = 1;
+			output.tx_log_entry = Some(log_id);
+			batch.save_tx_log_entry(&t)?;
+			batch.save_output(&output)?;
+			summary.received += 1;
+		}
+
+		// Whatever is left was unspent before and is gone from the UTXO set now
+		for mut output in known.into_iter().map(|(_, o)| o) {
+			if output.status != OutputStatus::Unspent {
+				continue;
+			}
+			let parent_key_id = output.root_key_id.clone();
+			let log_id = batch.next_tx_log_id(&parent_key_id)?;
+			let mut t = TxLogEntry::new(parent_key_id, TxLogEntryType::TxSent, log_id, now);
+			t.confirmed = true;
+			t.update_confirmation_ts(now);
+			t.amount_debited = output.value;
+			t.num_inputs = 1;
+			output.status = OutputStatus::Spent;
+			batch.save_tx_log_entry(&t)?;
+			batch.save_output(&output)?;
+			summary.spent += 1;
+		}
+
+		batch.save_last_confirmed_height(height)?;
+		batch.commit()?;
+		Ok(summary)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::common::config::Wallet713Config;
+	use crate::wallet::api::{Foreign, Owner};
+	use crate::wallet::testing::{create_wallet, LoopbackNetwork, MockChain};
+	use grin_core::global::ChainTypes;
+	use grin_keychain::{Keychain, SwitchCommitmentType};
+	use std::env;
+	use uuid::Uuid;
+
+	#[test]
+	fn scans_the_outputs_of_a_watch_key() {
+		let chain = MockChain::new();
+		let network = LoopbackNetwork::new();
+		let container = create_wallet("xavier", &chain, &network).unwrap();
+		chain.mine_to(&Foreign::new(container.clone())).unwrap();
+		chain.mine(3);
+		let watch_key = Owner::new(container).export_watch_key().unwrap();
+
+		let data_path = env::temp_dir().join(format!("wallet713-watch-{}", Uuid::new_v4()));
+		let mut config = Wallet713Config::default(&Some(ChainTypes::AutomatedTesting)).unwrap();
+		config.wallet713_data_path = data_path.to_string_lossy().into_owned();
+		let config = config.as_wallet_config().unwrap();
+		let mut backend = Backend::watch_only(&config, chain.client(), watch_key).unwrap();
+		backend.connect().unwrap();
+
+		let summary = backend.watch_scan().unwrap();
+		assert_eq!(summary.received, 1);
+		assert_eq!(backend.outputs().unwrap().count(), 1);
+		assert_eq!(backend.tx_logs().unwrap().count(), 1);
+		// Outputs already known aren't recorded again
+		assert_eq!(backend.watch_scan().unwrap().received, 0);
+		// Nor can it spend them
+		let id = WatchKeychain::root_key_id();
+		let keychain = backend.keychain();
+		assert!(keychain
+			.derive_key(1, &id, &SwitchCommitmentType::Regular)
+			.is_err());
+	}
+}
@@ -0,0 +1,309 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Snapshots of the wallet database, stored transactions and proofs, as
+//! timestamped `.tar.gz` archives. The seed isn't part of them. Callers
+//! hold the wallet while a snapshot is taken, so no write transaction is
+//! in progress and the LMDB files can be copied as they are
+
+use super::backend::{DB_DIR, TX_PROOF_SAVE_DIR, TX_SAVE_DIR};
+use super::types::BackupInfo;
+use super::ErrorKind;
+use chrono::{DateTime, Utc};
+use failure::Error;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File};
+use std::path::{Component, Path, PathBuf};
+use tar::{Archive, Builder, EntryType};
+
+const BACKUP_PREFIX: &str = "wallet713-";
+const BACKUP_EXTENSION: &str = ".tar.gz";
+/// Directories of the data directory that are backed up
+const BACKUP_DIRS: [&str; 3] = [DB_DIR, TX_SAVE_DIR, TX_PROOF_SAVE_DIR];
+/// Reader table of LMDB, rebuilt when the database is opened
+const LMDB_LOCK_FILE: &str = "lock.mdb";
+/// Directory of the data directory a backup is unpacked in before it
+/// replaces the current files
+const RESTORE_DIR: &str = "restore";
+/// Directory of the data directory the current files are moved to while
+/// they are replaced
+const REPLACED_DIR: &str = "restore-replaced";
+
+/// Write a snapshot of `data_dir` to a new archive in `backup_dir`
+pub fn create(data_dir: &Path, backup_dir: &Path, now: DateTime<Utc>) -> Result<BackupInfo, Error> {
+	let mut files = vec![];
+	for dir in BACKUP_DIRS.iter() {
+		collect(&data_dir.join(dir), &mut files)?;
+	}
+
+	fs::create_dir_all(backup_dir)?;
+	let stamp = now.format("%Y%m%d-%H%M%S");
+	let mut path = backup_dir.join(format!("{}{}{}", BACKUP_PREFIX, stamp, BACKUP_EXTENSION));
+	let mut n = 1;
+	while path.exists() {
+		let name = format!("{}{}-{}{}", BACKUP_PREFIX, stamp, n, BACKUP_EXTENSION);
+		path = backup_dir.join(name);
+		n += 1;
+	}
+	// Written aside first, so a cut off archive is never taken for a backup
+	let partial = path.with_extension("partial");
+	let gz = GzEncoder::new(File::create(&partial)?, Compression::default());
+	let mut builder = Builder::new(gz);
+	for file in &files {
+		builder.append_path_with_name(file, file.strip_prefix(data_dir)?)?;
+	}
+	builder.into_inner()?.finish()?.sync_all()?;
+	fs::rename(&partial, &path)?;
+
+	Ok(BackupInfo {
+		path: path.display().to_string(),
+		created: now,
+		files: files.len(),
+		size: fs::metadata(&path)?.len(),
+		pruned: 0,
+	})
+}
+
+/// Archives in `backup_dir`, oldest first
+pub fn list(backup_dir: &Path) -> Result<Vec<PathBuf>, Error> {
+	if !backup_dir.exists() {
+		return Ok(vec![]);
+	}
+	let mut backups = vec![];
+	for entry in fs::read_dir(backup_dir)? {
+		let path = entry?.path();
+		let name = match path.file_name().and_then(|n| n.to_str()) {
+			Some(n) => n,
+			None => continue,
+		};
+		if name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_EXTENSION) {
+			backups.push(path);
+		}
+	}
+	backups.sort();
+	Ok(backups)
+}
+
+/// Remove all but the `keep` newest archives, none if `keep` is 0
+pub fn prune(backup_dir: &Path, keep: usize) -> Result<usize, Error> {
+	let backups = list(backup_dir)?;
+	if keep == 0 || backups.len() <= keep {
+		return Ok(0);
+	}
+	let pruned = backups.len() - keep;
+	for path in &backups[..pruned] {
+		fs::remove_file(path)?;
+	}
+	Ok(pruned)
+}
+
+/// Path of `file`, as given or as the name of an archive in `backup_dir`
+pub fn find(backup_dir: &Path, file: &str) -> Result<PathBuf, Error> {
+	let path = PathBuf::from(file);
+	if path.is_file() {
+		return Ok(path);
+	}
+	let path = backup_dir.join(file);
+	if path.is_file() {
+		return Ok(path);
+	}
+	Err(ErrorKind::BackupNotFound(file.to_owned()).into())
+}
+
+/// Check the archive at `path` and unpack it next to the files it replaces,
+/// one entry at a time. Nothing is replaced yet
+pub fn stage(path: &Path, data_dir: &Path) -> Result<(), Error> {
+	let staging = data_dir.join(RESTORE_DIR);
+	if staging.exists() {
+		fs::remove_dir_all(&staging)?;
+	}
+	let staged = unpack(path, &staging);
+	if staged.is_err() {
+		let _ = fs::remove_dir_all(&staging);
+	}
+	staged
+}
+
+/// Replace the backed up directories of `data_dir` with the staged ones.
+/// The current directories are moved aside and only removed once all of
+/// them were replaced, otherwise they are moved back. The wallet has to
+/// be disconnected
+pub fn restore(data_dir: &Path) -> Result<(), Error> {
+	let staging = data_dir.join(RESTORE_DIR);
+	let replaced = data_dir.join(REPLACED_DIR);
+	if replaced.exists() {
+		fs::remove_dir_all(&replaced)?;
+	}
+	fs::create_dir_all(&replaced)?;
+
+	// Directories moved aside and the ones moved in, to undo a partial swap
+	let mut moved_aside = vec![];
+	let mut moved_in = vec![];
+	let swapped = BACKUP_DIRS.iter().try_for_each(|dir| -> Result<(), Error> {
+		let current = data_dir.join(dir);
+		if current.exists() {
+			fs::rename(&current, replaced.join(dir))?;
+			moved_aside.push(dir);
+		}
+		fs::rename(staging.join(dir), &current)?;
+		moved_in.push(dir);
+		Ok(())
+	});
+	if let Err(e) = swapped {
+		for dir in moved_in {
+			fs::rename(data_dir.join(dir), staging.join(dir))?;
+		}
+		for dir in moved_aside {
+			fs::rename(replaced.join(dir), data_dir.join(dir))?;
+		}
+		return Err(e);
+	}
+
+	fs::remove_dir_all(&replaced)?;
+	fs::remove_dir_all(&staging)?;
+	Ok(())
+}
+
+/// Unpack the regular files of the archive at `path` into `dir`, refusing
+/// anything that isn't part of a backup
+fn unpack(path: &Path, dir: &Path) -> Result<(), Error> {
+	let invalid = |reason: String| ErrorKind::InvalidBackup(path.display().to_string(), reason);
+	for backup_dir in BACKUP_DIRS.iter() {
+		fs::create_dir_all(dir.join(backup_dir))?;
+	}
+
+	let mut archive = Archive::new(GzDecoder::new(File::open(path)?));
+	let mut has_db = false;
+	let entries = archive.entries().map_err(|e| invalid(e.to_string()))?;
+	for entry in entries {
+		let mut entry = entry.map_err(|e| invalid(e.to_string()))?;
+		let name = entry
+			.path()
+			.map_err(|e| invalid(e.to_string()))?
+			.into_owned();
+		let top = match name.components().next() {
+			Some(Component::Normal(top)) => top.to_str().unwrap_or_default().to_owned(),
+			_ => String::new(),
+		};
+		let safe = name.components().all(|c| match c {
+			Component::Normal(_) => true,
+			_ => false,
+		});
+		if !BACKUP_DIRS.contains(&top.as_str()) || !safe {
+			return Err(invalid(format!("unexpected file {}", name.display())).into());
+		}
+		match entry.header().entry_type() {
+			EntryType::Regular => {}
+			EntryType::Directory => continue,
+			t => {
+				let reason = format!("unsupported entry {} ({:?})", name.display(), t);
+				return Err(invalid(reason).into());
+			}
+		}
+		has_db |= top == DB_DIR;
+
+		let target = dir.join(&name);
+		if let Some(parent) = target.parent() {
+			fs::create_dir_all(parent)?;
+		}
+		entry.unpack(&target).map_err(|e| invalid(e.to_string()))?;
+	}
+	if !has_db {
+		return Err(invalid("no wallet database".to_owned()).into());
+	}
+	Ok(())
+}
+
+/// Add the files under `dir` to `files`
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+	if !dir.exists() {
+		return Ok(());
+	}
+	let mut paths = vec![];
+	for entry in fs::read_dir(dir)? {
+		paths.push(entry?.path());
+	}
+	paths.sort();
+
+	for path in paths {
+		if path.is_dir() {
+			collect(&path, files)?;
+			continue;
+		}
+		if path.file_name().and_then(|n| n.to_str()) == Some(LMDB_LOCK_FILE) {
+			continue;
+		}
+		files.push(path);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::wallet::api::{Foreign, Owner};
+	use crate::wallet::testing::{create_wallet, LoopbackNetwork, MockChain};
+	use std::env;
+	use tar::Header;
+	use uuid::Uuid;
+
+	#[test]
+	fn restores_the_wallet_as_it_was_backed_up() {
+		let chain = MockChain::new();
+		let network = LoopbackNetwork::new();
+		let container = create_wallet("backup", &chain, &network).unwrap();
+		let owner = Owner::new(container.clone());
+		let foreign = Foreign::new(container);
+		for _ in 0..2 {
+			chain.mine_to(&foreign).unwrap();
+		}
+		let (_, before) = owner.retrieve_summary_info(true, 1).unwrap();
+		let backup = owner.backup_now().unwrap();
+		assert!(backup.files > 0);
+
+		chain.mine_to(&foreign).unwrap();
+		let (_, after) = owner.retrieve_summary_info(true, 1).unwrap();
+		assert!(after.total > before.total);
+
+		let previous = owner.restore_backup(&backup.path).unwrap();
+		assert!(Path::new(&previous.path).exists());
+		let (_, restored) = owner.retrieve_summary_info(false, 1).unwrap();
+		assert_eq!(restored.total, before.total);
+	}
+
+	#[test]
+	fn refuses_archives_without_a_wallet_database() {
+		let dir = env::temp_dir().join(format!("wallet713-backup-{}", Uuid::new_v4()));
+		fs::create_dir_all(&dir).unwrap();
+		let path = dir.join("wallet713-20190101-000000.tar.gz");
+		let gz = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+		let mut builder = Builder::new(gz);
+		let data = b"{}";
+		let mut header = Header::new_gnu();
+		header.set_size(data.len() as u64);
+		header.set_mode(0o644);
+		header.set_cksum();
+		builder
+			.append_data(&mut header, "saved_txs/tx.json", &data[..])
+			.unwrap();
+		builder.into_inner().unwrap().finish().unwrap();
+
+		let data_dir = dir.join("data");
+		assert!(stage(&path, &data_dir).is_err());
+		assert!(!data_dir.join(RESTORE_DIR).exists());
+		fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -12,17 +12,32 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::adapter::Adapter;
+use super::hooks::start_hooks;
 use super::ErrorKind;
-use crate::api::listener::{Listener, ListenerInterface};
+use crate::api::listener::{self, Listener, ListenerInterface};
+use crate::broker::ExecAdapter;
 use crate::common::config::Wallet713Config;
-use crate::common::{Arc, Keychain, Mutex};
+use crate::common::features::{Feature, FeatureRegistry};
+use crate::common::metrics::{Metrics, SharedMetrics};
+use crate::common::timing::{SharedTimings, Timings};
+use crate::common::{Arc, Keychain, Mutex, RuntimeContext};
 use crate::contacts::AddressBook;
+use crate::internal::updater;
 use crate::wallet::backend::Backend;
-use crate::wallet::types::{HTTPNodeClient, NodeClient, WalletBackend};
+use crate::wallet::types::{
+	CachingNodeClient, HTTPNodeClient, InitTxArgs, NodeClient, Notification, NotificationEvent,
+	SharedEventBus, Slate, TxEvent, WalletBackend, WalletEvent, DEFAULT_ACCOUNT,
+};
+use crate::DefaultNodeClient;
 use failure::Error;
 use grin_keychain::ExtKeychain;
+use log::warn;
+use parking_lot::ReentrantMutex;
 use std::collections::HashMap;
 use std::marker::PhantomData;
+use std::time::Instant;
+use uuid::Uuid;
 
 pub struct Container<W, C, K>
 where
@@ -35,6 +50,24 @@ where
 	pub address_book: AddressBook,
 	pub account: String,
 	pub listeners: HashMap<ListenerInterface, Box<dyn Listener>>,
+	pub timings: SharedTimings,
+	/// Counters and gauges served on `/metrics`, shared with the node client
+	pub metrics: SharedMetrics,
+	/// Held by the spend paths, so sends, finalizations and cancellations
+	/// of different callers queue up instead of interleaving
+	pub spend_lock: Arc<ReentrantMutex<()>>,
+	/// Sends above `confirm_threshold` waiting to be confirmed, by token
+	pub pending_sends: HashMap<Uuid, (Instant, InitTxArgs)>,
+	/// Wallet events, shared with the backend
+	pub events: SharedEventBus,
+	/// Optional capabilities of this build and config
+	pub features: FeatureRegistry,
+	/// How this wallet is run, for defaults and user messages
+	pub runtime: RuntimeContext,
+	/// Transports run as external processes, by name
+	pub exec_adapters: HashMap<String, ExecAdapter>,
+	/// Transports provided by the code embedding the wallet, by name
+	pub adapters: HashMap<String, Arc<dyn Adapter + Send + Sync>>,
 	phantom_c: PhantomData<C>,
 	phantom_k: PhantomData<K>,
 }
@@ -45,19 +78,45 @@ where
 	C: NodeClient,
 	K: Keychain,
 {
-	pub fn new(config: Wallet713Config, backend: W, address_book: AddressBook) -> Arc<Mutex<Self>> {
+	pub fn new(
+		config: Wallet713Config,
+		backend: W,
+		address_book: AddressBook,
+		runtime: RuntimeContext,
+	) -> Arc<Mutex<Self>> {
+		let events = backend.events();
+		start_hooks(&events, config.event_hooks());
+		let mut features = FeatureRegistry::new();
+		register_features(&mut features, &config);
+		listener::register_features(&mut features, &config, &runtime);
+		let exec_adapters = ExecAdapter::from_config(config.exec_adapters());
 		let container = Self {
 			config,
 			backend,
 			address_book,
-			account: String::from("default"),
+			account: String::from(DEFAULT_ACCOUNT),
 			listeners: HashMap::with_capacity(4),
+			timings: Timings::new(),
+			metrics: Metrics::new(),
+			spend_lock: Arc::new(ReentrantMutex::new(())),
+			pending_sends: HashMap::new(),
+			events,
+			features,
+			runtime,
+			exec_adapters,
+			adapters: HashMap::new(),
 			phantom_c: PhantomData,
 			phantom_k: PhantomData,
 		};
 		Arc::new(Mutex::new(container))
 	}
 
+	/// Make slates sendable with `adapter` under the method `name`. Built in
+	/// transports keep precedence, exec adapters of the config don't
+	pub fn register_adapter(&mut self, name: &str, adapter: Box<dyn Adapter + Send + Sync>) {
+		self.adapters.insert(name.to_owned(), Arc::from(adapter));
+	}
+
 	pub fn raw_backend(&mut self) -> &mut W {
 		&mut self.backend
 	}
@@ -74,20 +133,116 @@ where
 			.get(&interface)
 			.ok_or(ErrorKind::NoListener(format!("{}", interface)))
 	}
+
+	/// Grinbox listener to publish through for `account`, preferring the
+	/// configured address index, then other addresses of the account
+	pub fn grinbox_listener(&self, account: u32) -> Result<&Box<dyn Listener>, ErrorKind> {
+		let primary = ListenerInterface::Grinbox(account, self.config.grinbox_address_index());
+		self.listeners
+			.get(&primary)
+			.or_else(|| {
+				self.listeners
+					.iter()
+					.find(|(i, _)| i.grinbox_account() == Some(account))
+					.map(|(_, l)| l)
+			})
+			.or_else(|| {
+				self.listeners
+					.iter()
+					.find(|(i, _)| i.is_grinbox())
+					.map(|(_, l)| l)
+			})
+			.ok_or(ErrorKind::NoListener("Grinbox".to_owned()))
+	}
+
+	/// Publish an event about a slate on the event bus and deliver it to the
+	/// route of the account whose transaction it is, or to the routes in the config
+	pub fn notify(&mut self, event: NotificationEvent, slate: &Slate) {
+		if let Err(e) = self.try_notify(event, slate) {
+			warn!("Unable to send {} notification: {}", event, e);
+		}
+	}
+
+	fn try_notify(&mut self, event: NotificationEvent, slate: &Slate) -> Result<(), Error> {
+		let fallback = self.config.notification_route();
+		let events = self.events.clone();
+		let w = self.backend()?;
+		let (txs, _) =
+			updater::retrieve_txs(w, None, Some(slate.id), None, false, false, None, None)?;
+		let tx = match txs.into_iter().find(|t| t.tx_type == event.tx_type()) {
+			Some(t) => t,
+			None => return Ok(()),
+		};
+
+		let mapping = w.accounts()?.find(|m| m.path == tx.parent_key_id);
+		let account = mapping
+			.as_ref()
+			.map(|m| m.label.clone())
+			.unwrap_or_default();
+		let route = mapping.and_then(|m| m.notifications).unwrap_or(fallback);
+		let tx_event = TxEvent::new(&account, &tx, w.clock().now());
+		events.publish(match event {
+			NotificationEvent::TxReceived => WalletEvent::SlateReceived(tx_event),
+			NotificationEvent::TxFinalized => WalletEvent::TxFinalized(tx_event),
+		});
+		if !route.is_empty() {
+			Notification::new(event, &account, slate, &tx).send(route);
+		}
+		Ok(())
+	}
 }
 
 pub fn create_container(
 	config: Wallet713Config,
 	address_book: AddressBook,
+	runtime: RuntimeContext,
 ) -> Result<
-	Arc<Mutex<Container<Backend<HTTPNodeClient, ExtKeychain>, HTTPNodeClient, ExtKeychain>>>,
+	Arc<Mutex<Container<Backend<DefaultNodeClient, ExtKeychain>, DefaultNodeClient, ExtKeychain>>>,
 	Error,
 > {
 	let wallet_config = config.as_wallet_config()?;
+	let metrics = Metrics::new();
 	let client = HTTPNodeClient::new(
 		&wallet_config.check_node_api_http_addr,
-		config.grin_node_secret().clone(),
-	);
-	let backend = Backend::new(&wallet_config, client)?;
-	Ok(Container::new(config, backend, address_book))
+		config.grin_node_secret()?,
+	)
+	.with_options(config.grin_node_client_options())
+	.with_session(runtime.node_session())
+	.with_metrics(metrics.clone());
+	let client = CachingNodeClient::new(client);
+	let backend = Backend::new(&wallet_config, client)?.with_force_unlock(runtime.force_unlock());
+	let container = Container::new(config, backend, address_book, runtime);
+	container.lock().metrics = metrics;
+	Ok(container)
+}
+
+/// Transaction capabilities of the wallet itself
+fn register_features(registry: &mut FeatureRegistry, config: &Wallet713Config) {
+	registry.register(Feature::new(
+		"payment_proofs_v2",
+		true,
+		true,
+		"address based proofs",
+	));
+	registry.register(Feature::new(
+		"invoices",
+		false,
+		false,
+		"not supported by this build",
+	));
+	registry.register(Feature::new(
+		"slate_retry",
+		true,
+		config.slate_retry(),
+		&format!("max {} attempts", config.slate_retry_max_attempts()),
+	));
+	let hooks = config.event_hooks();
+	let mut names: Vec<&str> = hooks.keys().cloned().collect();
+	names.sort();
+	registry.register(Feature::new(
+		"event_hooks",
+		true,
+		!hooks.is_empty(),
+		&names.join(", "),
+	));
 }
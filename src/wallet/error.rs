@@ -172,6 +172,17 @@ pub enum ErrorKind {
 	#[fail(display = "Unknown Account Label '{}'", _0)]
 	UnknownAccountLabel(String),
 
+	/// The default account is recreated when missing, so it stays as it is
+	#[fail(display = "The default account can't be {}", _0)]
+	DefaultAccount(String),
+
+	/// Attempt to hide the account in use
+	#[fail(
+		display = "Account '{}' is in use, switch to another account first",
+		_0
+	)]
+	ActiveAccount(String),
+
 	/// Error from summing commitments via committed trait.
 	#[fail(display = "Committed Error")]
 	Committed(committed::Error),
@@ -229,6 +240,218 @@ pub enum ErrorKind {
 	#[fail(display = "Unable to verify proof")]
 	VerifyProof,
 
+	/// Invalid number of change outputs
+	#[fail(display = "Number of change outputs should be at least 1")]
+	InvalidChangeOutputs,
+
+	/// Slate forwarding is not configured
+	#[fail(display = "No foreign API configured to forward slates to")]
+	NoForwardDestination,
+
+	/// Output not found in the wallet
+	#[fail(display = "Output {} not found in the wallet", _0)]
+	OutputNotFound(String),
+
+	/// Unable to parse a human readable amount
+	#[fail(display = "Invalid amount: {}", _0)]
+	InvalidAmount(String),
+
+	/// Payment proof error
+	#[fail(display = "Payment proof error: {}", _0)]
+	PaymentProof(String),
+
+	/// Slate returned without the signature of the requested payment proof
+	#[fail(
+		display = "The recipient didn't sign the payment proof requested for slate {}, it may not run wallet713",
+		_0
+	)]
+	PaymentProofNotSigned(String),
+
+	/// Unknown coin selection strategy
+	#[fail(display = "Unknown selection strategy '{}'", _0)]
+	UnknownSelectionStrategy(String),
+
+	/// Unknown transaction direction filter
+	#[fail(display = "Unknown transaction type '{}'", _0)]
+	UnknownTxDirection(String),
+
+	/// Invalid number of consolidation outputs
+	#[fail(display = "Number of target outputs should be at least 1")]
+	InvalidTargetOutputs,
+
+	/// Not enough outputs to consolidate
+	#[fail(display = "Only {} eligible outputs, nothing to consolidate", _0)]
+	NothingToConsolidate(usize),
+
+	/// Transaction fee above the budget set by the user
+	#[fail(display = "Fee of {} grin exceeds the budget of {} grin", _0, _1)]
+	FeeBudgetExceeded(String, String),
+
+	/// No outbox directory configured
+	#[fail(display = "No outbox directory configured, set outbox_dir in the config file")]
+	NoOutboxDir,
+
+	/// No external adapter configured
+	#[fail(display = "No adapter configured, add an [adapter.<name>] table to the config file")]
+	NoExecAdapters,
+
+	/// Backups are only scheduled with an interval or a number of transactions
+	#[fail(display = "No backup schedule, set backup_interval_hours or backup_every_txs")]
+	NoBackupSchedule,
+
+	/// Response slate found in the outbox failed the policy checks
+	#[fail(display = "Response slate rejected: {}", _0)]
+	ResponseRejected(String),
+
+	/// Node session bundle could not be recorded or replayed
+	#[fail(display = "Node session error: {}", _0)]
+	NodeSession(String),
+
+	/// Transaction without a slate waiting in the retry queue
+	#[fail(display = "Transaction {} has no queued slate", _0)]
+	SlateNotQueued(String),
+
+	/// Incoming slate refused by the `receive_*` rules
+	#[fail(display = "Slate refused: {}", _0)]
+	SlateRefused(String),
+
+	/// Incoming slate held until approved by hand
+	#[fail(display = "Slate {} is waiting for approval by the recipient", _0)]
+	SlateAwaitingApproval(String),
+
+	/// No slate with this id is waiting for approval
+	#[fail(display = "No incoming slate {}", _0)]
+	IncomingSlateNotFound(String),
+
+	/// Transaction is not a send waiting for a response
+	#[fail(display = "Transaction {} is not waiting for a response", _0)]
+	TransactionNotPending(String),
+
+	/// Transaction without a private context, either not a pending send or already finalized
+	#[fail(display = "Transaction {} has no private context", _0)]
+	NoPrivateContext(String),
+
+	/// Imported private context doesn't belong to the slate
+	#[fail(display = "Private context doesn't match the slate: {}", _0)]
+	ContextMismatch(String),
+
+	/// Wallet data is only exported to an empty directory
+	#[fail(display = "Directory {} is not empty", _0)]
+	DataDirNotEmpty(String),
+
+	/// File of exported wallet data that can't be read
+	#[fail(display = "Invalid wallet data in {}: {}", _0, _1)]
+	InvalidWalletData(String, String),
+
+	/// Backup to restore that doesn't exist, as a path or in the backup directory
+	#[fail(display = "Backup {} not found", _0)]
+	BackupNotFound(String),
+
+	/// Archive that isn't a wallet backup
+	#[fail(display = "Invalid backup {}: {}", _0, _1)]
+	InvalidBackup(String, String),
+
+	/// Seed backup written by a newer version
+	#[fail(display = "Unsupported seed backup: {}", _0)]
+	UnsupportedSeedBackup(String),
+
+	/// Another caller is working on the wallet in a way that clashes with this call
+	#[fail(display = "Conflict: {}", _0)]
+	Conflict(String),
+
+	/// Payment would create an output below the configured minimum output value
+	#[fail(
+		display = "Output of {} grin is below the minimum output value of {} grin",
+		_0, _1
+	)]
+	BelowMinOutputValue(String, String),
+
+	/// Transaction can't be re-issued with a higher fee
+	#[fail(display = "Transaction {} cannot be bumped: {}", _0, _1)]
+	TransactionNotBumpable(String, String),
+
+	/// Fee factor of a bump that wouldn't raise the fee
+	#[fail(display = "Fee factor should be more than 1")]
+	InvalidFeeFactor,
+
+	/// Option that can't be combined with paying several recipients
+	#[fail(display = "Paying several recipients is not supported {}", _0)]
+	RecipientsNotSupported(String),
+
+	/// Slate paying several recipients that not all of them have signed
+	#[fail(display = "Not every recipient has signed slate {} yet", _0)]
+	MissingRecipientSignatures(String),
+
+	/// Slate paying several recipients that not all of them have joined yet
+	#[fail(display = "Not every recipient has added its data to slate {} yet", _0)]
+	MissingRecipients(String),
+
+	/// Slate that already has the public data of all of its participants
+	#[fail(display = "Slate {} has no room for another recipient", _0)]
+	SlateFull(String),
+
+	/// Option that can't be combined with late locking
+	#[fail(display = "Late locking is not supported {}", _0)]
+	LateLockNotSupported(String),
+
+	/// Inputs available at finalize that need a higher fee than the one signed
+	#[fail(display = "Inputs need a fee of {}, above the fixed fee of {}", _1, _0)]
+	LateLockFee(String, String),
+
+	/// Multisig output not known to the wallet
+	#[fail(display = "Multisig output {} not found", _0)]
+	MultisigNotFound(String),
+
+	/// Multisig output in a state that doesn't allow the operation
+	#[fail(display = "Multisig output {} is {}", _0, _1)]
+	MultisigStatus(String, String),
+
+	/// Multisig message that doesn't follow from our side of the exchange
+	#[fail(
+		display = "Unexpected round {} of the multisig exchange for {}",
+		_1, _0
+	)]
+	MultisigRound(String, u8),
+
+	/// Multisig message with data that doesn't add up
+	#[fail(display = "Invalid multisig data: {}", _0)]
+	MultisigInvalid(String),
+
+	/// Outputs given as inputs of a transaction that can't be spent
+	#[fail(display = "Not all of the given inputs are unspent outputs of the account")]
+	InputsNotAvailable,
+
+	/// Send above `confirm_threshold`, put aside until it is confirmed with
+	/// the token
+	#[fail(display = "Sending {} grin needs confirmation with token {}", _1, _0)]
+	ConfirmationRequired(String, String),
+
+	/// No send is waiting for confirmation with the token, or it expired
+	#[fail(display = "No send waiting for confirmation with token {}", _0)]
+	UnknownConfirmation(String),
+
+	/// Payment of the same amount to the same address as a recent one
+	#[fail(
+		display = "Transaction {} already paid {} grin to {} within {} minutes, force to pay again",
+		tx_id, amount_disp, address, window
+	)]
+	DuplicatePayment {
+		/// Log entry of the earlier payment
+		tx_id: u32,
+		/// Amount paid
+		amount: u64,
+		/// Display friendly
+		amount_disp: String,
+		/// Address paid
+		address: String,
+		/// Minutes looked back
+		window: u64,
+	},
+
+	/// Send that would go over `daily_spend_limit`
+	#[fail(display = "Daily spend limit exceeded: {}", _0)]
+	SpendLimitExceeded(String),
+
 	/// Other
 	#[fail(display = "Generic error: {}", _0)]
 	GenericError(String),
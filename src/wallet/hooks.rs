@@ -0,0 +1,66 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Scripts run on wallet events
+
+use super::types::{EventBus, WalletEvent};
+use failure::Error;
+use futures::Stream;
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Run the script set for each event published on `events`, with the event
+/// name as argument and the event as JSON on stdin. Scripts run one after the
+/// other on a background thread, so they see events in order. Failures are
+/// only logged
+pub fn start_hooks(events: &EventBus, hooks: HashMap<&'static str, String>) {
+	if hooks.is_empty() {
+		return;
+	}
+
+	let receiver = events.subscribe();
+	thread::spawn(move || {
+		for event in receiver.wait() {
+			let event = match event {
+				Ok(e) => e.event,
+				Err(_) => break,
+			};
+			if let Some(script) = hooks.get(event.name()) {
+				match run_hook(script, &event) {
+					Ok(()) => debug!("Ran {} for {}", script, event.name()),
+					Err(e) => warn!("Unable to run {} for {}: {}", script, event.name(), e),
+				}
+			}
+		}
+	});
+}
+
+fn run_hook(script: &str, event: &WalletEvent) -> Result<(), Error> {
+	let mut child = Command::new(script)
+		.arg(event.name())
+		.stdin(Stdio::piped())
+		.spawn()?;
+	// Dropping stdin closes it, so the script sees the end of the event
+	if let Some(mut stdin) = child.stdin.take() {
+		stdin.write_all(serde_json::to_string(event)?.as_bytes())?;
+	}
+	let status = child.wait()?;
+	if !status.success() {
+		warn!("{} exited with {} for {}", script, status, event.name());
+	}
+	Ok(())
+}
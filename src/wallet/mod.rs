@@ -15,11 +15,15 @@
 mod adapter;
 pub mod api;
 mod backend;
+mod backup;
 mod container;
 pub mod error;
+mod hooks;
 mod seed;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod types;
 
-pub use self::backend::Backend;
+pub use self::backend::{Backend, WatchScanSummary};
 pub use self::container::{create_container, Container};
 pub use self::error::ErrorKind;
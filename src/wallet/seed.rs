@@ -14,19 +14,32 @@
 
 use super::ErrorKind;
 use crate::common::config::WalletConfig;
+use crate::common::entropy::Entropy;
+use argon2::{ThreadMode, Variant, Version};
 use failure::{Error, ResultExt};
 use grin_keychain::{mnemonic, Keychain};
 use grin_util::{from_hex, to_hex, ZeroingString};
 use log::{debug, error, warn};
-use rand::{thread_rng, Rng};
 use ring::{aead, digest, pbkdf2};
 use serde::{Deserialize, Serialize};
 use serde_json;
-use std::fs::{self, File};
-use std::io::{Read, Write};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
 use std::path::{Path, MAIN_SEPARATOR};
 
 pub const SEED_FILE: &'static str = "wallet.seed";
+/// Current format of `SeedBackup`
+pub const SEED_BACKUP_VERSION: u16 = 2;
+const SEED_BACKUP_KDF: &'static str = "argon2id";
+const SEED_BACKUP_ITERATIONS: u32 = 3;
+/// Memory used by the key derivation, in KiB
+const SEED_BACKUP_MEMORY: u32 = 64 * 1024;
+const SEED_BACKUP_LANES: u32 = 1;
+/// Upper bounds of the key derivation parameters read from a backup, so a
+/// tampered file can't make importing it run for hours or exhaust memory
+const MAX_ARGON2_ITERATIONS: u32 = 64;
+const MAX_ARGON2_MEMORY: u32 = 1024 * 1024;
+const MAX_ARGON2_LANES: u32 = 16;
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct WalletSeed(Vec<u8>);
@@ -57,13 +70,8 @@ impl WalletSeed {
 		Ok(result)
 	}
 
-	pub fn init_new(seed_length: usize) -> WalletSeed {
-		let mut seed: Vec<u8> = vec![];
-		let mut rng = thread_rng();
-		for _ in 0..seed_length {
-			seed.push(rng.gen());
-		}
-		WalletSeed(seed)
+	pub fn init_new(seed_length: usize, entropy: &dyn Entropy) -> WalletSeed {
+		WalletSeed(entropy.bytes(seed_length))
 	}
 
 	pub fn seed_file_exists(wallet_config: &WalletConfig) -> Result<(), Error> {
@@ -83,6 +91,7 @@ impl WalletSeed {
 		recovery_phrase: Option<ZeroingString>,
 		password: &str,
 		overwrite: bool,
+		entropy: &dyn Entropy,
 	) -> Result<WalletSeed, Error> {
 		// create directory if it doesn't exist
 		fs::create_dir_all(&wallet_config.data_file_dir).context(ErrorKind::IO)?;
@@ -99,12 +108,12 @@ impl WalletSeed {
 
 		let seed = match recovery_phrase {
 			Some(p) => WalletSeed::from_mnemonic(&p)?,
-			None => WalletSeed::init_new(seed_length),
+			None => WalletSeed::init_new(seed_length, entropy),
 		};
 
-		let enc_seed = EncryptedWalletSeed::from_seed(&seed, password)?;
+		let enc_seed = EncryptedWalletSeed::from_seed(&seed, password, entropy)?;
 		let enc_seed_json = serde_json::to_string_pretty(&enc_seed).context(ErrorKind::Format)?;
-		let mut file = File::create(seed_file_path).context(ErrorKind::IO)?;
+		let mut file = create_private_file(seed_file_path).context(ErrorKind::IO)?;
 		file.write_all(&enc_seed_json.as_bytes())
 			.context(ErrorKind::IO)?;
 		Ok(seed)
@@ -138,6 +147,67 @@ impl WalletSeed {
 			Err(ErrorKind::WalletSeedDoesntExist)?
 		}
 	}
+
+	/// Re-encrypt the seed file with `new_password`. The new file is written
+	/// and checked next to the current one before it takes its place, and the
+	/// current one is put back if the replaced file can't be read
+	pub fn change_password(
+		wallet_config: &WalletConfig,
+		old_password: &str,
+		new_password: &str,
+		entropy: &dyn Entropy,
+	) -> Result<(), Error> {
+		let seed = WalletSeed::from_file(wallet_config, old_password)?;
+		let enc_seed = EncryptedWalletSeed::from_seed(&seed, new_password, entropy)?;
+		if enc_seed.decrypt(new_password)? != seed {
+			return Err(ErrorKind::WalletSeedDecryption)?;
+		}
+		let enc_seed_json = serde_json::to_string_pretty(&enc_seed).context(ErrorKind::Format)?;
+
+		let seed_file_path = Path::new(&wallet_config.data_file_dir).join(SEED_FILE);
+		let new_file_path = seed_file_path.with_extension("seed.new");
+		let backup_file_path = seed_file_path.with_extension("seed.bak");
+
+		let mut file = create_private_file(&new_file_path).context(ErrorKind::IO)?;
+		file.write_all(enc_seed_json.as_bytes())
+			.context(ErrorKind::IO)?;
+		file.sync_all().context(ErrorKind::IO)?;
+		fs::copy(&seed_file_path, &backup_file_path).context(ErrorKind::IO)?;
+
+		// A rename replaces the file in one step, so an interruption leaves
+		// either the old or the new seed file in place
+		fs::rename(&new_file_path, &seed_file_path)
+			.map_err(|e| {
+				let _ = fs::remove_file(&new_file_path);
+				let _ = fs::remove_file(&backup_file_path);
+				e
+			})
+			.context(ErrorKind::IO)?;
+
+		match WalletSeed::from_file(wallet_config, new_password) {
+			Ok(ref s) if s == &seed => {
+				let _ = fs::remove_file(&backup_file_path);
+				Ok(())
+			}
+			_ => {
+				warn!("Restoring the previous seed file");
+				fs::rename(&backup_file_path, &seed_file_path).context(ErrorKind::IO)?;
+				Err(ErrorKind::WalletSeedDecryption)?
+			}
+		}
+	}
+}
+
+/// Create the file at `path`, or truncate it, readable by its owner only
+pub fn create_private_file<P: AsRef<Path>>(path: P) -> io::Result<File> {
+	let mut options = OpenOptions::new();
+	options.write(true).create(true).truncate(true);
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::OpenOptionsExt;
+		options.mode(0o600);
+	}
+	options.open(path)
 }
 
 /// Encrypted wallet seed, for storing on disk and decrypting
@@ -155,9 +225,15 @@ pub struct EncryptedWalletSeed {
 
 impl EncryptedWalletSeed {
 	/// Create a new encrypted seed from the given seed + password
-	pub fn from_seed(seed: &WalletSeed, password: &str) -> Result<EncryptedWalletSeed, Error> {
-		let salt: [u8; 8] = thread_rng().gen();
-		let nonce: [u8; 12] = thread_rng().gen();
+	pub fn from_seed(
+		seed: &WalletSeed,
+		password: &str,
+		entropy: &dyn Entropy,
+	) -> Result<EncryptedWalletSeed, Error> {
+		let mut salt = [0; 8];
+		entropy.fill_bytes(&mut salt);
+		let mut nonce = [0; 12];
+		entropy.fill_bytes(&mut nonce);
 		let password = password.as_bytes();
 		let mut key = [0; 32];
 		pbkdf2::derive(&digest::SHA512, 100, &salt, password, &mut key);
@@ -204,3 +280,180 @@ impl EncryptedWalletSeed {
 		Ok(WalletSeed::from_bytes(&decrypted_data))
 	}
 }
+
+/// Encrypted backup of the seed, to keep it in a file instead of writing
+/// down the mnemonic. The backup is meant to be stored away from the wallet,
+/// so the key is derived with argon2id instead of the quick derivation of
+/// the seed file. The format and key derivation are recorded, and
+/// authenticated along with the seed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SeedBackup {
+	pub version: u16,
+	pub kdf: String,
+	pub iterations: u32,
+	/// Memory of the key derivation in KiB
+	pub memory: u32,
+	/// Parallelism of the key derivation
+	pub lanes: u32,
+	salt: String,
+	nonce: String,
+	encrypted_seed: String,
+}
+
+impl SeedBackup {
+	/// Encrypt `seed` with `password`
+	pub fn new(seed: &WalletSeed, password: &str, entropy: &dyn Entropy) -> Result<Self, Error> {
+		let mut salt = [0; 16];
+		entropy.fill_bytes(&mut salt);
+		let mut nonce = [0; 12];
+		entropy.fill_bytes(&mut nonce);
+		let mut backup = SeedBackup {
+			version: SEED_BACKUP_VERSION,
+			kdf: SEED_BACKUP_KDF.to_owned(),
+			iterations: SEED_BACKUP_ITERATIONS,
+			memory: SEED_BACKUP_MEMORY,
+			lanes: SEED_BACKUP_LANES,
+			salt: to_hex(salt.to_vec()),
+			nonce: to_hex(nonce.to_vec()),
+			encrypted_seed: String::new(),
+		};
+		let key = backup.key(password, &salt)?;
+
+		let mut enc_bytes = seed.0.to_vec();
+		let suffix_len = aead::CHACHA20_POLY1305.tag_len();
+		for _ in 0..suffix_len {
+			enc_bytes.push(0);
+		}
+		let sealing_key =
+			aead::SealingKey::new(&aead::CHACHA20_POLY1305, &key).context(ErrorKind::Encryption)?;
+		aead::seal_in_place(
+			&sealing_key,
+			&nonce,
+			&backup.ad(),
+			&mut enc_bytes,
+			suffix_len,
+		)
+		.context(ErrorKind::Encryption)?;
+		backup.encrypted_seed = to_hex(enc_bytes);
+		Ok(backup)
+	}
+
+	/// Decrypt the seed, failing on backups of an unknown format or with key
+	/// derivation parameters out of bounds
+	pub fn decrypt(&self, password: &str) -> Result<WalletSeed, Error> {
+		let mut encrypted_seed =
+			from_hex(self.encrypted_seed.clone()).map_err(|_| ErrorKind::Encryption)?;
+		let salt = from_hex(self.salt.clone()).map_err(|_| ErrorKind::Encryption)?;
+		let nonce = from_hex(self.nonce.clone()).map_err(|_| ErrorKind::Encryption)?;
+		let key = self.key(password, &salt)?;
+
+		let opening_key =
+			aead::OpeningKey::new(&aead::CHACHA20_POLY1305, &key).context(ErrorKind::Encryption)?;
+		let decrypted =
+			aead::open_in_place(&opening_key, &nonce, &self.ad(), 0, &mut encrypted_seed)
+				.context(ErrorKind::Encryption)?;
+		Ok(WalletSeed::from_bytes(&decrypted))
+	}
+
+	/// Derive the key with the recorded parameters, after checking them
+	fn key(&self, password: &str, salt: &[u8]) -> Result<Vec<u8>, Error> {
+		let unsupported = |reason: String| -> Result<Vec<u8>, Error> {
+			Err(ErrorKind::UnsupportedSeedBackup(reason).into())
+		};
+		match (self.version, self.kdf.as_str()) {
+			(SEED_BACKUP_VERSION, SEED_BACKUP_KDF) => {
+				if self.iterations == 0 || self.iterations > MAX_ARGON2_ITERATIONS {
+					return unsupported(format!("{} iterations", self.iterations));
+				}
+				if self.lanes == 0 || self.lanes > MAX_ARGON2_LANES {
+					return unsupported(format!("{} lanes", self.lanes));
+				}
+				if self.memory < 8 * self.lanes || self.memory > MAX_ARGON2_MEMORY {
+					return unsupported(format!("{} KiB of memory", self.memory));
+				}
+				let config = argon2::Config {
+					variant: Variant::Argon2id,
+					version: Version::Version13,
+					time_cost: self.iterations,
+					mem_cost: self.memory,
+					lanes: self.lanes,
+					thread_mode: ThreadMode::Sequential,
+					hash_length: 32,
+					..argon2::Config::default()
+				};
+				let key = argon2::hash_raw(password.as_bytes(), salt, &config)
+					.map_err(|_| ErrorKind::Encryption)?;
+				Ok(key)
+			}
+			(version, kdf) => unsupported(format!("version {} with {}", version, kdf)),
+		}
+	}
+
+	/// Associated data, so the format can't be changed without the password
+	fn ad(&self) -> Vec<u8> {
+		format!(
+			"{}:{}:{}:{}:{}",
+			self.version, self.kdf, self.iterations, self.memory, self.lanes
+		)
+		.into_bytes()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::common::entropy::OsEntropy;
+
+	fn seed() -> WalletSeed {
+		WalletSeed::from_bytes(&[7; 32])
+	}
+
+	#[test]
+	fn seed_backups_decrypt_with_their_password() {
+		let backup = SeedBackup::new(&seed(), "backup", &OsEntropy).unwrap();
+		assert_eq!(backup.kdf, "argon2id");
+		assert_eq!(backup.decrypt("backup").unwrap(), seed());
+		assert!(backup.decrypt("wrong").is_err());
+	}
+
+	#[test]
+	fn seed_backups_with_bad_parameters_are_refused() {
+		let backup = SeedBackup::new(&seed(), "backup", &OsEntropy).unwrap();
+		let tampered = vec![
+			SeedBackup {
+				iterations: 0,
+				..backup.clone()
+			},
+			SeedBackup {
+				memory: u32::max_value(),
+				..backup.clone()
+			},
+			SeedBackup {
+				lanes: 0,
+				..backup.clone()
+			},
+			SeedBackup {
+				version: 1,
+				kdf: "pbkdf2-sha512".to_owned(),
+				..backup.clone()
+			},
+		];
+		for backup in tampered {
+			let e = backup.decrypt("backup").unwrap_err();
+			match e.downcast_ref::<ErrorKind>() {
+				Some(ErrorKind::UnsupportedSeedBackup(_)) => {}
+				_ => panic!("unexpected error {}", e),
+			}
+		}
+	}
+	#[cfg(unix)]
+	#[test]
+	fn private_files_are_only_readable_by_their_owner() {
+		use std::os::unix::fs::PermissionsExt;
+		let path = std::env::temp_dir().join(format!("wallet713-seed-{}", uuid::Uuid::new_v4()));
+		create_private_file(&path).unwrap();
+		let mode = fs::metadata(&path).unwrap().permissions().mode();
+		fs::remove_file(&path).unwrap();
+		assert_eq!(mode & 0o777, 0o600);
+	}
+}
@@ -0,0 +1,251 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! In-process stand-ins for the grin node and the slate transports, so the
+//! whole send, receive, finalize and post flow can run in `cargo test`.
+//! Built for the crate's own tests and with the `testing` feature
+
+mod loopback;
+mod node_client;
+
+pub use self::loopback::{LoopbackAdapter, LoopbackNetwork};
+pub use self::node_client::{MockChain, MockNodeClient};
+
+use super::backend::Backend;
+use super::Container;
+use crate::common::config::Wallet713Config;
+use crate::common::{Arc, Mutex, RuntimeContext, RuntimeMode};
+use crate::contacts::{self, AddressBook};
+use crate::wallet::api::{Foreign, Owner};
+use failure::Error;
+use grin_core::global::{set_mining_mode, ChainTypes};
+use grin_keychain::ExtKeychain;
+use grin_util::ZeroingString;
+use std::env;
+use uuid::Uuid;
+
+pub type TestContainer =
+	Container<Backend<MockNodeClient, ExtKeychain>, MockNodeClient, ExtKeychain>;
+
+/// Create a wallet with a fresh seed in a temporary directory, connected to
+/// `chain` and reachable by the other wallets of `network` as `http://<name>.loopback`
+pub fn create_wallet(
+	name: &str,
+	chain: &MockChain,
+	network: &LoopbackNetwork,
+) -> Result<Arc<Mutex<TestContainer>>, Error> {
+	set_mining_mode(ChainTypes::AutomatedTesting);
+	let data_path = env::temp_dir().join(format!("wallet713-{}-{}", name, Uuid::new_v4()));
+	let mut config = Wallet713Config::default(&Some(ChainTypes::AutomatedTesting))?;
+	config.wallet713_data_path = data_path.to_string_lossy().into_owned();
+
+	let backend = Backend::new(&config.as_wallet_config()?, chain.client())?;
+	let address_book = AddressBook::new(Box::new(contacts::Backend::new(
+		&config.wallet713_data_path,
+	)?))?;
+	let runtime = RuntimeContext::new(RuntimeMode::Daemon);
+	let container = Container::new(config, backend, address_book, runtime);
+	container
+		.lock()
+		.register_adapter("loopback", LoopbackAdapter::new(network.clone()));
+
+	let owner = Owner::new(container.clone());
+	owner.set_seed(None, ZeroingString::from("password"), false)?;
+	owner.connect()?;
+	network.register(&address(name), Foreign::new(container.clone()));
+	Ok(container)
+}
+
+/// Address of the wallet created with `name`
+pub fn address(name: &str) -> String {
+	format!("http://{}.loopback", name)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::wallet::types::{Amount, InitTxArgs, InitTxSendArgs, TxLogEntryType};
+	use crate::wallet::ErrorKind;
+
+	#[test]
+	fn sends_receives_finalizes_and_posts() {
+		let chain = MockChain::new();
+		let network = LoopbackNetwork::new();
+		let alice_container = create_wallet("alice", &chain, &network).unwrap();
+		let alice = Owner::new(alice_container.clone());
+		let bob = Owner::new(create_wallet("bob", &chain, &network).unwrap());
+
+		let alice_foreign = Foreign::new(alice_container);
+		for _ in 0..5 {
+			chain.mine_to(&alice_foreign).unwrap();
+		}
+		let (_, info) = alice.retrieve_summary_info(true, 1).unwrap();
+		assert!(info.amount_currently_spendable > 0);
+
+		let amount = Amount::from_nano(info.amount_currently_spendable / 2);
+		let args = InitTxArgs {
+			amount,
+			minimum_confirmations: 1,
+			send_args: Some(InitTxSendArgs {
+				method: Some("loopback".to_owned()),
+				dest: address("bob"),
+				finalize: true,
+				post_tx: true,
+				fluff: true,
+			}),
+			..Default::default()
+		};
+		alice.init_send_tx(args).unwrap();
+		assert_eq!(chain.pool_size(), 1);
+
+		let (_, info) = bob.retrieve_summary_info(true, 1).unwrap();
+		assert_eq!(info.amount_currently_spendable, 0);
+
+		chain.mine(1);
+		assert_eq!(chain.pool_size(), 0);
+		let (_, info) = bob.retrieve_summary_info(true, 1).unwrap();
+		assert_eq!(info.amount_currently_spendable, amount.nano());
+	}
+
+	#[test]
+	fn refuses_unknown_recipients() {
+		let chain = MockChain::new();
+		let network = LoopbackNetwork::new();
+		let container = create_wallet("carol", &chain, &network).unwrap();
+		let carol = Owner::new(container.clone());
+		chain.mine_to(&Foreign::new(container)).unwrap();
+		chain.mine(3);
+
+		let args = InitTxArgs {
+			amount: Amount::from_nano(1_000_000),
+			minimum_confirmations: 1,
+			send_args: Some(InitTxSendArgs {
+				method: Some("loopback".to_owned()),
+				dest: address("dave"),
+				finalize: true,
+				post_tx: true,
+				fluff: true,
+			}),
+			..Default::default()
+		};
+		assert!(carol.init_send_tx(args).is_err());
+		assert_eq!(chain.pool_size(), 0);
+	}
+
+	#[test]
+	fn refuses_responses_without_the_requested_payment_proof() {
+		let chain = MockChain::new();
+		let network = LoopbackNetwork::new();
+		let alice_container = create_wallet("erin", &chain, &network).unwrap();
+		let alice = Owner::new(alice_container.clone());
+		let bob_container = create_wallet("frank", &chain, &network).unwrap();
+		let bob = Owner::new(bob_container.clone());
+		chain.mine_to(&Foreign::new(alice_container)).unwrap();
+		chain.mine(3);
+
+		let args = InitTxArgs {
+			amount: Amount::from_nano(1_000_000),
+			minimum_confirmations: 1,
+			payment_proof_recipient_address: Some(bob.grinbox_address().unwrap().to_string()),
+			..Default::default()
+		};
+		let slate = alice.init_send_tx(args).unwrap();
+		alice.tx_lock_outputs(&slate, 0, None).unwrap();
+		let response = Foreign::new(bob_container)
+			.receive_tx(&slate, None, None, None)
+			.unwrap();
+		assert!(response.payment_proof.is_some());
+
+		let mut stripped = response.clone();
+		stripped.payment_proof = None;
+		let e = alice.finalize_tx(&stripped, None).unwrap_err();
+		match e.downcast_ref::<ErrorKind>() {
+			Some(ErrorKind::PaymentProofNotSigned(id)) => assert_eq!(id, &slate.id.to_string()),
+			_ => panic!("unexpected error {}", e),
+		}
+
+		alice.finalize_tx(&response, None).unwrap();
+		assert!(alice
+			.get_payment_proof(None, Some(slate.id))
+			.unwrap()
+			.is_some());
+	}
+	#[test]
+	fn logs_the_fee_raised_by_a_payjoin_recipient() {
+		let chain = MockChain::new();
+		let network = LoopbackNetwork::new();
+		let alice_container = create_wallet("uma", &chain, &network).unwrap();
+		let alice = Owner::new(alice_container.clone());
+		let bob_container = create_wallet("victor", &chain, &network).unwrap();
+		chain.mine_to(&Foreign::new(alice_container)).unwrap();
+		chain.mine_to(&Foreign::new(bob_container.clone())).unwrap();
+		chain.mine(12);
+
+		let args = InitTxArgs {
+			amount: Amount::from_nano(1_000_000),
+			minimum_confirmations: 1,
+			..Default::default()
+		};
+		let slate = alice.init_send_tx(args).unwrap();
+		alice.tx_lock_outputs(&slate, 0, None).unwrap();
+		let response = Foreign::new(bob_container)
+			.with_payjoin(true)
+			.receive_tx(&slate, None, None, None)
+			.unwrap();
+		assert!(response.fee > slate.fee);
+		assert_eq!(response.tx.inputs().len(), slate.tx.inputs().len() + 1);
+
+		let mut raised = response.clone();
+		raised.fee += 1;
+		let e = alice.finalize_tx(&raised, None).unwrap_err();
+		match e.downcast_ref::<ErrorKind>() {
+			Some(ErrorKind::Fee(_)) => {}
+			_ => panic!("unexpected error {}", e),
+		}
+
+		alice.finalize_tx(&response, None).unwrap();
+		let (_, _, txs, _, _) = alice
+			.retrieve_txs(false, false, false, None, Some(slate.id), None, None)
+			.unwrap();
+		let sent = txs
+			.iter()
+			.find(|t| t.tx_type == TxLogEntryType::TxSent)
+			.unwrap();
+		assert_eq!(sent.fee, Some(response.fee));
+	}
+
+	#[test]
+	fn times_the_stages_of_a_send_separately() {
+		let chain = MockChain::new();
+		let network = LoopbackNetwork::new();
+		let container = create_wallet("trent", &chain, &network).unwrap();
+		let trent = Owner::new(container.clone());
+		chain.mine_to(&Foreign::new(container)).unwrap();
+		chain.mine(3);
+		trent.take_recent_timings();
+
+		let args = InitTxArgs {
+			amount: Amount::from_nano(1_000_000),
+			minimum_confirmations: 1,
+			..Default::default()
+		};
+		trent.init_send_tx(args).unwrap();
+		let stages: Vec<String> = trent
+			.take_recent_timings()
+			.into_iter()
+			.map(|(stage, _)| stage)
+			.collect();
+		assert_eq!(stages, vec!["refresh", "selection", "signing"]);
+	}
+}
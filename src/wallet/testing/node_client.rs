@@ -0,0 +1,280 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::{Arc, Mutex};
+use crate::wallet::api::Foreign;
+use crate::wallet::types::{
+	BlockFees, Keychain, NodeClient, NodeStatus, NodeVersionInfo, Transaction, TxKernel, TxWrapper,
+	WalletBackend,
+};
+use crate::wallet::ErrorKind;
+use failure::Error;
+use grin_core::ser::{self, ProtocolVersion};
+use grin_util::secp::pedersen::{Commitment, RangeProof};
+use grin_util::{from_hex, to_hex};
+use std::collections::HashMap;
+
+/// An unspent output on the mock chain
+#[derive(Clone)]
+struct MockOutput {
+	proof: RangeProof,
+	is_coinbase: bool,
+	height: u64,
+	mmr_index: u64,
+}
+
+#[derive(Default)]
+struct ChainState {
+	height: u64,
+	utxos: HashMap<Commitment, MockOutput>,
+	/// Kernels with the height and MMR index they were mined at
+	kernels: Vec<(TxKernel, u64, u64)>,
+	/// Posted transactions, mined with the next block
+	pool: Vec<Transaction>,
+	last_output_index: u64,
+}
+
+impl ChainState {
+	fn add_output(&mut self, commit: Commitment, proof: RangeProof, is_coinbase: bool) {
+		self.last_output_index += 1;
+		let output = MockOutput {
+			proof,
+			is_coinbase,
+			height: self.height,
+			mmr_index: self.last_output_index,
+		};
+		self.utxos.insert(commit, output);
+	}
+
+	fn add_kernel(&mut self, kernel: TxKernel) {
+		let mmr_index = self.kernels.len() as u64 + 1;
+		self.kernels.push((kernel, self.height, mmr_index));
+	}
+
+	/// Whether all inputs of the transaction are unspent and not spent
+	/// by any transaction in the pool
+	fn spendable(&self, tx: &Transaction) -> bool {
+		tx.inputs().iter().all(|i| {
+			let commit = i.commitment();
+			self.utxos.contains_key(&commit)
+				&& !self
+					.pool
+					.iter()
+					.any(|p| p.inputs().iter().any(|pi| pi.commitment() == commit))
+		})
+	}
+}
+
+/// In-memory chain shared by the mock node clients of the wallets in a test.
+/// Nothing is mined until asked for, so tests decide when transactions confirm
+#[derive(Clone, Default)]
+pub struct MockChain {
+	state: Arc<Mutex<ChainState>>,
+}
+
+impl MockChain {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// A node client reading from and posting to this chain
+	pub fn client(&self) -> MockNodeClient {
+		MockNodeClient {
+			node_url: "mock://node".to_owned(),
+			node_api_secret: None,
+			chain: self.clone(),
+		}
+	}
+
+	pub fn height(&self) -> u64 {
+		self.state.lock().height
+	}
+
+	/// Number of posted transactions waiting to be mined
+	pub fn pool_size(&self) -> usize {
+		self.state.lock().pool.len()
+	}
+
+	/// Whether the given output is in the UTXO set
+	pub fn is_unspent(&self, commit: &Commitment) -> bool {
+		self.state.lock().utxos.contains_key(commit)
+	}
+
+	/// Mine `blocks` empty blocks, the first one including the pool
+	pub fn mine(&self, blocks: u64) {
+		let mut state = self.state.lock();
+		for _ in 0..blocks {
+			state.height += 1;
+			let pool: Vec<Transaction> = state.pool.drain(..).collect();
+			for tx in pool {
+				for input in tx.inputs() {
+					state.utxos.remove(&input.commitment());
+				}
+				for output in tx.outputs() {
+					state.add_output(output.commitment(), output.proof, output.is_coinbase());
+				}
+				for kernel in tx.kernels() {
+					state.add_kernel(kernel.clone());
+				}
+			}
+		}
+	}
+
+	/// Mine a block with its reward paid to the wallet behind `foreign`
+	pub fn mine_to<W, C, K>(&self, foreign: &Foreign<W, C, K>) -> Result<(), Error>
+	where
+		W: WalletBackend<C, K>,
+		C: NodeClient,
+		K: Keychain,
+	{
+		let block_fees = BlockFees {
+			fees: 0,
+			height: self.height() + 1,
+			key_id: None,
+		};
+		let cb_data = foreign.build_coinbase(&block_fees)?;
+		self.mine(1);
+		let mut state = self.state.lock();
+		let output = cb_data.output;
+		state.add_output(output.commitment(), output.proof, true);
+		state.add_kernel(cb_data.kernel);
+		Ok(())
+	}
+}
+
+/// `NodeClient` backed by a `MockChain` instead of a grin node
+#[derive(Clone)]
+pub struct MockNodeClient {
+	node_url: String,
+	node_api_secret: Option<String>,
+	chain: MockChain,
+}
+
+impl MockNodeClient {
+	pub fn chain(&self) -> &MockChain {
+		&self.chain
+	}
+}
+
+impl NodeClient for MockNodeClient {
+	fn node_url(&self) -> &str {
+		&self.node_url
+	}
+
+	fn set_node_url(&mut self, node_url: &str) {
+		self.node_url = node_url.to_owned();
+	}
+
+	fn node_api_secret(&self) -> Option<String> {
+		self.node_api_secret.clone()
+	}
+
+	fn set_node_api_secret(&mut self, node_api_secret: Option<String>) {
+		self.node_api_secret = node_api_secret;
+	}
+
+	fn get_version_info(&mut self) -> Option<NodeVersionInfo> {
+		Some(NodeVersionInfo {
+			node_version: "3.0.0".to_owned(),
+			block_header_version: 2,
+			verified: Some(true),
+		})
+	}
+
+	/// Adds the transaction to the pool, refusing it if it spends an output
+	/// that is unknown or already spent
+	fn post_tx(&self, tx: &TxWrapper, _fluff: bool) -> Result<(), Error> {
+		let tx_bin = from_hex(tx.tx_hex.clone()).map_err(|_| ErrorKind::Format)?;
+		let tx: Transaction = ser::deserialize(&mut &tx_bin[..], ProtocolVersion(1))?;
+		let mut state = self.chain.state.lock();
+		if !state.spendable(&tx) {
+			let report = "Posting transaction to node: invalid inputs".to_owned();
+			return Err(ErrorKind::ClientCallback(report).into());
+		}
+		state.pool.push(tx);
+		Ok(())
+	}
+
+	fn get_chain_height(&self) -> Result<u64, Error> {
+		Ok(self.chain.height())
+	}
+
+	fn get_status(&self) -> Result<NodeStatus, Error> {
+		Ok(NodeStatus {
+			user_agent: "MW/Grin mock".to_owned(),
+			protocol_version: 1,
+			height: self.chain.height(),
+			sync_status: "no_sync".to_owned(),
+			connections: 0,
+			latency: 0,
+		})
+	}
+
+	/// Blocks of the mock chain never change, so their hash is their height
+	fn get_header_hash(&self, height: u64) -> Result<Option<String>, Error> {
+		match height <= self.chain.height() {
+			true => Ok(Some(format!("{:064x}", height))),
+			false => Ok(None),
+		}
+	}
+
+	fn get_kernel(
+		&self,
+		excess: &Commitment,
+		min_height: Option<u64>,
+		max_height: Option<u64>,
+	) -> Result<Option<(TxKernel, u64, u64)>, Error> {
+		let state = self.chain.state.lock();
+		let kernel = state.kernels.iter().find(|(k, height, _)| {
+			k.excess == *excess
+				&& min_height.map(|h| *height >= h).unwrap_or(true)
+				&& max_height.map(|h| *height <= h).unwrap_or(true)
+		});
+		Ok(kernel.cloned())
+	}
+
+	fn get_outputs_from_node(
+		&self,
+		wallet_outputs: Vec<Commitment>,
+	) -> Result<HashMap<Commitment, (String, u64, u64)>, Error> {
+		let state = self.chain.state.lock();
+		let mut api_outputs = HashMap::new();
+		for commit in wallet_outputs {
+			if let Some(o) = state.utxos.get(&commit) {
+				let hex = to_hex(commit.0.to_vec());
+				api_outputs.insert(commit, (hex, o.height, o.mmr_index));
+			}
+		}
+		Ok(api_outputs)
+	}
+
+	fn get_outputs_by_pmmr_index(
+		&self,
+		start_height: u64,
+		max_outputs: u64,
+	) -> Result<(u64, u64, Vec<(Commitment, RangeProof, bool, u64, u64)>), Error> {
+		let state = self.chain.state.lock();
+		let mut outputs: Vec<(Commitment, RangeProof, bool, u64, u64)> = state
+			.utxos
+			.iter()
+			.filter(|(_, o)| o.mmr_index >= start_height)
+			.map(|(c, o)| (c.clone(), o.proof, o.is_coinbase, o.height, o.mmr_index))
+			.collect();
+		outputs.sort_by_key(|o| o.4);
+		outputs.truncate(max_outputs as usize);
+		let highest_index = state.last_output_index;
+		let last_retrieved_index = outputs.last().map(|o| o.4).unwrap_or(highest_index);
+		Ok((highest_index, last_retrieved_index, outputs))
+	}
+}
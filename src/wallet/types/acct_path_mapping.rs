@@ -12,10 +12,13 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::Identifier;
+use super::{Identifier, NotificationRoute};
 use grin_core::ser;
 use serde::{Deserialize, Serialize};
 
+/// Label of the account every wallet starts with
+pub const DEFAULT_ACCOUNT: &'static str = "default";
+
 /// Map of named accounts to BIP32 paths
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AcctPathMapping {
@@ -23,6 +26,13 @@ pub struct AcctPathMapping {
 	pub label: String,
 	/// Corresponding parent BIP32 derivation path
 	pub path: Identifier,
+	/// Where events of this account are delivered, instead of the
+	/// routes in the config
+	#[serde(default)]
+	pub notifications: Option<NotificationRoute>,
+	/// Hidden from the account listings
+	#[serde(default)]
+	pub archived: bool,
 }
 
 impl ser::Writeable for AcctPathMapping {
@@ -0,0 +1,136 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::amount::{self, Unit};
+use crate::common::{ser, ErrorKind as CommonErrorKind};
+use crate::wallet::ErrorKind;
+use grin_core::core::amount_to_hr_string;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
+
+/// An amount of grin, always denominated in nanogrin (`1 G = 1_000_000_000 nG`).
+/// Conversion from and to human readable units is explicit, so the two
+/// can't be mixed up. Serializes the same as a `string_or_u64` nanogrin value.
+/// Wire formats shared with other wallets (slates, tx log) keep using `u64`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Amount(u64);
+
+impl Amount {
+	pub const ZERO: Amount = Amount(0);
+
+	pub fn from_nano(nano: u64) -> Self {
+		Amount(nano)
+	}
+
+	/// Parse a human readable amount, e.g. "1.5" for 1.5 grin or "1200milli".
+	/// See `common::amount` for the accepted forms
+	pub fn from_hr_string(amount: &str) -> Result<Self, ErrorKind> {
+		amount::parse_nano(amount, Unit::Grin)
+			.map(Amount)
+			.map_err(|e| match e {
+				CommonErrorKind::InvalidAmount(a, reason) => {
+					ErrorKind::InvalidAmount(format!("'{}', {}", a, reason))
+				}
+				e => ErrorKind::InvalidAmount(e.to_string()),
+			})
+	}
+
+	pub fn nano(self) -> u64 {
+		self.0
+	}
+
+	/// Human readable amount in grin, optionally with trailing zeros removed
+	pub fn to_hr_string(self, truncate: bool) -> String {
+		amount_to_hr_string(self.0, truncate)
+	}
+
+	pub fn checked_add(self, other: Amount) -> Option<Amount> {
+		self.0.checked_add(other.0).map(Amount)
+	}
+
+	pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+		self.0.checked_sub(other.0).map(Amount)
+	}
+
+	pub fn saturating_sub(self, other: Amount) -> Amount {
+		Amount(self.0.saturating_sub(other.0))
+	}
+}
+
+impl Add for Amount {
+	type Output = Amount;
+
+	fn add(self, other: Amount) -> Amount {
+		Amount(self.0 + other.0)
+	}
+}
+
+impl AddAssign for Amount {
+	fn add_assign(&mut self, other: Amount) {
+		self.0 += other.0;
+	}
+}
+
+impl Sum for Amount {
+	fn sum<I: Iterator<Item = Amount>>(iter: I) -> Amount {
+		iter.fold(Amount::ZERO, Add::add)
+	}
+}
+
+/// Displays the human readable value, use `nano()` for the raw value
+impl fmt::Display for Amount {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.to_hr_string(false))
+	}
+}
+
+impl Serialize for Amount {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		ser::string_or_u64::serialize(&self.0, serializer)
+	}
+}
+
+/// Accepts what `string_or_u64` does, nanogrin as a number or a string of
+/// digits, and strings with a unit such as "5.2grin" for API clients
+impl<'de> Deserialize<'de> for Amount {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		struct Visitor;
+		impl<'a> de::Visitor<'a> for Visitor {
+			type Value = Amount;
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				write!(formatter, "an amount in nanogrin, or a string with a unit")
+			}
+			fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+				Ok(Amount(v))
+			}
+			fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+			where
+				E: de::Error,
+			{
+				amount::parse_nano(s, Unit::Nano)
+					.map(Amount)
+					.map_err(de::Error::custom)
+			}
+		}
+		deserializer.deserialize_any(Visitor)
+	}
+}
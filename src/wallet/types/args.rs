@@ -12,8 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use super::{Amount, Identifier};
 use crate::common::ser;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// V2 Init / Send TX API Args
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -22,9 +24,12 @@ pub struct InitTxArgs {
 	/// for the transaction, overriding whatever the active account is as set via the
 	/// `set_active_account` method.
 	pub src_acct_name: Option<String>,
-	#[serde(with = "ser::string_or_u64")]
-	/// The amount to send, in nanogrins. (`1 G = 1_000_000_000nG`)
-	pub amount: u64,
+	/// Optional account to draw the fee from. The amount itself is drawn from
+	/// `src_acct_name`, any change is returned to the account it came from
+	#[serde(default)]
+	pub fee_acct_name: Option<String>,
+	/// The amount to send
+	pub amount: Amount,
 	#[serde(with = "ser::string_or_u64")]
 	/// The minimum number of confirmations an output
 	/// should have in order to be included in the transaction.
@@ -61,11 +66,60 @@ pub struct InitTxArgs {
 	/// 'true', the amount field in the slate will contain the total amount locked, not the provided
 	/// transaction amount
 	pub estimate_only: Option<bool>,
+	/// Spend every eligible output of the account. The amount sent is the total
+	/// minus the fee, `amount` and `fee_acct_name` are ignored
+	#[serde(default)]
+	pub sweep: bool,
+	/// Create change outputs below the configured minimum output value,
+	/// instead of adding them to the fee
+	#[serde(default)]
+	pub allow_dust: bool,
+	/// Optional grinbox address of the recipient. If set, the recipient is asked
+	/// to sign a payment proof for the amount and kernel excess of the transaction
+	#[serde(default)]
+	pub payment_proof_recipient_address: Option<String>,
+	/// Private notes stored with the transaction log entry. They never leave
+	/// the wallet, unlike `message`
+	#[serde(default)]
+	pub notes: BTreeMap<String, String>,
+	/// Fee per unit of transaction weight, in nanogrin. The configured one
+	/// when not set
+	#[serde(default)]
+	pub fee_base: Option<u64>,
+	/// Outputs to spend, by key id, instead of selecting them. They have to
+	/// cover the amount and fee on their own
+	#[serde(default)]
+	pub inputs: Vec<Identifier>,
+	/// Entry of a cancelled transaction this one re-issues
+	#[serde(default)]
+	pub replaces: Option<u32>,
+	/// Only select and lock the inputs when the transaction is finalized. The
+	/// fee is fixed up front from the current selection
+	#[serde(default)]
+	pub late_lock: bool,
+	/// Send even if the same amount was paid to the same address within the
+	/// configured `duplicate_send_window`
+	#[serde(default)]
+	pub force: bool,
+	/// Pay several recipients with a single transaction. `amount` is ignored
+	/// when set
+	#[serde(default)]
+	pub recipients: Vec<TxRecipient>,
 	/// Sender arguments. If present, the underlying function will also attempt to send the
 	/// transaction to a destination and optionally finalize the result
 	pub send_args: Option<InitTxSendArgs>,
 }
 
+/// One of the recipients of a transaction paying several
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TxRecipient {
+	pub amount: Amount,
+	/// Http address the slate is sent to. Without one the slate is returned
+	/// to be passed on to the recipients by hand
+	#[serde(default)]
+	pub dest: Option<String>,
+}
+
 /// Send TX API Args, for convenience functionality that inits the transaction and sends
 /// in one go
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -86,7 +140,8 @@ impl Default for InitTxArgs {
 	fn default() -> InitTxArgs {
 		InitTxArgs {
 			src_acct_name: None,
-			amount: 0,
+			fee_acct_name: None,
+			amount: Amount::ZERO,
 			minimum_confirmations: 10,
 			max_outputs: 500,
 			num_change_outputs: 1,
@@ -94,11 +149,146 @@ impl Default for InitTxArgs {
 			message: None,
 			target_slate_version: None,
 			estimate_only: Some(false),
+			sweep: false,
+			allow_dust: false,
+			payment_proof_recipient_address: None,
+			notes: BTreeMap::new(),
+			fee_base: None,
+			inputs: vec![],
+			replaces: None,
+			late_lock: false,
+			force: false,
+			recipients: vec![],
 			send_args: None,
 		}
 	}
 }
 
+/// Fee estimation API Args
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EstimateFeeArgs {
+	/// The human readable account name from which to draw outputs
+	pub src_acct_name: Option<String>,
+	/// The amount to estimate the fee for
+	pub amount: Amount,
+	/// The selection strategies to estimate, can be 'smallest' or 'all'
+	pub strategies: Vec<String>,
+	/// The minimum number of confirmations an output
+	/// should have in order to be included in the transaction.
+	#[serde(with = "ser::string_or_u64")]
+	pub minimum_confirmations: u64,
+	/// Soft limit of outputs to spend, see `InitTxArgs`
+	pub max_outputs: u32,
+	/// The target number of change outputs to create in the transaction.
+	pub num_change_outputs: u32,
+}
+
+impl Default for EstimateFeeArgs {
+	fn default() -> EstimateFeeArgs {
+		EstimateFeeArgs {
+			src_acct_name: None,
+			amount: Amount::ZERO,
+			strategies: vec!["smallest".to_owned(), "all".to_owned()],
+			minimum_confirmations: 10,
+			max_outputs: 500,
+			num_change_outputs: 1,
+		}
+	}
+}
+
+/// Fee estimate for a single selection strategy
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FeeEstimate {
+	/// The selection strategy
+	pub strategy: String,
+	/// The transaction fee
+	pub fee: Amount,
+	/// The total amount that would be locked
+	pub total: Amount,
+	/// The number of change outputs that would be created
+	pub change_outputs: usize,
+}
+
+/// An output that a send would spend
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PreviewInput {
+	/// Commitment of the output, if cached
+	pub commit: Option<String>,
+	pub value: Amount,
+	/// Block height the output was confirmed at
+	#[serde(with = "ser::string_or_u64")]
+	pub height: u64,
+	/// Whether the output is drawn from the fee account
+	pub pays_fee: bool,
+}
+
+/// Everything a send would do, worked out before any key is used or any
+/// output is locked. Running `init_send_tx` with the same arguments and
+/// an unchanged wallet selects the same inputs
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SendPreview {
+	/// The amount the recipient gets
+	pub amount: Amount,
+	/// The transaction fee
+	pub fee: Amount,
+	/// Outputs that would be locked as inputs
+	pub inputs: Vec<PreviewInput>,
+	/// Values of the change outputs that would be created
+	pub change_outputs: Vec<Amount>,
+	/// Account the fee is paid from, if not the sending account
+	pub fee_account: Option<String>,
+	/// Total balance of the sending account before and after the transaction
+	pub balance_before: Amount,
+	pub balance_after: Amount,
+}
+
+/// What to do with the dust of an account
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DustAdvice {
+	/// There is no dust
+	Clean,
+	/// The dust is worth more than the fee, which is already at its minimum
+	SweepNow,
+	/// Sweeping recovers value, but the fee drops once more outputs are combined
+	Wait,
+	/// The dust is worth less than the fee to sweep it
+	Uneconomic,
+}
+
+/// An output worth less than the fee to spend it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DustOutput {
+	/// Commitment of the output, if cached
+	pub commit: Option<String>,
+	pub value: Amount,
+	/// Block height the output was confirmed at
+	#[serde(with = "ser::string_or_u64")]
+	pub height: u64,
+}
+
+/// The dust of an account and the cost of cleaning it up
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DustAnalysis {
+	/// Outputs worth at most this much are dust. Defaults to the fee of a
+	/// transaction spending a single output into a single output
+	pub threshold: Amount,
+	pub outputs: Vec<DustOutput>,
+	/// Total value of the dust
+	pub total: Amount,
+	/// Fee of combining all dust into one output now
+	pub sweep_fee: Amount,
+	/// Lowest fee of a transaction with one output, and the number of
+	/// inputs from which it applies
+	pub min_fee: Amount,
+	pub min_fee_inputs: usize,
+	/// Change in fee, in nanogrin, when the dust is spent as extra inputs of
+	/// an ordinary send with change instead. Negative when it lowers the fee
+	pub piggyback_fee: i64,
+	pub advice: DustAdvice,
+}
+
 /// V2 Issue Invoice Tx Args
 #[derive(Clone, Serialize, Deserialize)]
 pub struct IssueInvoiceTxArgs {
@@ -106,9 +296,8 @@ pub struct IssueInvoiceTxArgs {
 	/// overriding whatever the active account is as set via the
 	/// `set_active_account` method.
 	pub dest_acct_name: Option<String>,
-	/// The invoice amount in nanogrins. (`1 G = 1_000_000_000nG`)
-	#[serde(with = "ser::string_or_u64")]
-	pub amount: u64,
+	/// The invoice amount
+	pub amount: Amount,
 	/// Optional message, that will be signed
 	pub message: Option<String>,
 	/// Optionally set the output target slate version (acceptable
@@ -121,7 +310,7 @@ impl Default for IssueInvoiceTxArgs {
 	fn default() -> IssueInvoiceTxArgs {
 		IssueInvoiceTxArgs {
 			dest_acct_name: None,
-			amount: 0,
+			amount: Amount::ZERO,
 			message: None,
 			target_slate_version: None,
 		}
@@ -0,0 +1,29 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Archive of the wallet database, stored transactions and proofs
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BackupInfo {
+	pub path: String,
+	pub created: DateTime<Utc>,
+	/// Number of files in the archive
+	pub files: usize,
+	/// Size of the archive in bytes
+	pub size: u64,
+	/// Number of older archives removed to stay within `backup_keep`
+	pub pruned: usize,
+}
@@ -0,0 +1,118 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::crypto::{sign_challenge, verify_signature, Hex};
+use failure::{Error, Fail};
+use grin_util::secp::key::{PublicKey, SecretKey};
+use grin_util::secp::pedersen::Commitment;
+use grin_util::secp::{ContextFlag, Secp256k1, Signature};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Fail)]
+pub enum ErrorKind {
+	#[fail(display = "No unspent outputs to attest")]
+	Empty,
+	#[fail(display = "Unable to derive public key from commitments")]
+	PublicKey,
+	#[fail(display = "Unable to sign balance attestation")]
+	Sign,
+	#[fail(display = "Unable to verify signature")]
+	VerifySignature,
+}
+
+/// Statement that the wallet controls a set of unspent outputs worth
+/// `total` at `height`. Signed with the sum of the blinding factors, so the
+/// signing key is `sum(commits) - total*H`. Individual values and keys are
+/// not revealed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceAttestation {
+	pub height: u64,
+	pub total: u64,
+	pub commits: Vec<Commitment>,
+	pub message: String,
+	pub signature: Signature,
+}
+
+/// Result of checking the commitments of an attestation against a node
+#[derive(Debug, Clone)]
+pub struct AttestationStatus {
+	/// Outputs still in the UTXO set
+	pub unspent: usize,
+	/// Unspent outputs mined after the attested height
+	pub mined_after: usize,
+}
+
+impl BalanceAttestation {
+	pub fn new(
+		height: u64,
+		total: u64,
+		commits: Vec<Commitment>,
+		message: String,
+		blinding_sum: &SecretKey,
+	) -> Result<Self, Error> {
+		if commits.is_empty() {
+			return Err(ErrorKind::Empty.into());
+		}
+		let challenge = Self::challenge(height, total, &commits, &message);
+		let signature = sign_challenge(&challenge, blinding_sum).map_err(|_| ErrorKind::Sign)?;
+		let attestation = Self {
+			height,
+			total,
+			commits,
+			message,
+			signature,
+		};
+
+		// Make sure the key actually matches the commitments
+		attestation.verify()?;
+		Ok(attestation)
+	}
+
+	pub fn verify(&self) -> Result<(), ErrorKind> {
+		let public_key = self.public_key()?;
+		let challenge = Self::challenge(self.height, self.total, &self.commits, &self.message);
+		verify_signature(&challenge, &self.signature, &public_key)
+			.map_err(|_| ErrorKind::VerifySignature)
+	}
+
+	/// Whether all attested outputs are still unspent and none were mined
+	/// after the attested height
+	pub fn holds(&self, status: &AttestationStatus) -> bool {
+		status.unspent == self.commits.len() && status.mined_after == 0
+	}
+
+	fn public_key(&self) -> Result<PublicKey, ErrorKind> {
+		if self.commits.is_empty() {
+			return Err(ErrorKind::Empty);
+		}
+		let secp = Secp256k1::with_caps(ContextFlag::Commit);
+		let negative = if self.total == 0 {
+			vec![]
+		} else {
+			let value_commit = secp
+				.commit_value(self.total)
+				.map_err(|_| ErrorKind::PublicKey)?;
+			vec![value_commit]
+		};
+		let excess = secp
+			.commit_sum(self.commits.clone(), negative)
+			.map_err(|_| ErrorKind::PublicKey)?;
+		excess.to_pubkey(&secp).map_err(|_| ErrorKind::PublicKey)
+	}
+
+	fn challenge(height: u64, total: u64, commits: &[Commitment], message: &str) -> String {
+		let commits: Vec<String> = commits.iter().map(|c| c.to_hex()).collect();
+		format!("{}{}{}{}", height, total, commits.join(""), message)
+	}
+}
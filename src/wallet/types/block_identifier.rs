@@ -12,7 +12,27 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::Hash;
+use grin_core::ser;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Ord)]
-pub struct BlockIdentifier(pub Hash);
+/// Block the wallet saw on chain at a checkpoint height. A node whose chain
+/// has another block at that height has switched to a fork since
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockIdentifier {
+	pub height: u64,
+	/// Hash of the block header, in hex
+	pub hash: String,
+}
+
+impl ser::Writeable for BlockIdentifier {
+	fn write<W: ser::Writer>(&self, writer: &mut W) -> Result<(), ser::Error> {
+		writer.write_bytes(&serde_json::to_vec(self).map_err(|_| ser::Error::CorruptedData)?)
+	}
+}
+
+impl ser::Readable for BlockIdentifier {
+	fn read(reader: &mut dyn ser::Reader) -> Result<BlockIdentifier, ser::Error> {
+		let data = reader.read_bytes_len_prefix()?;
+		serde_json::from_slice(&data[..]).map_err(|_| ser::Error::CorruptedData)
+	}
+}
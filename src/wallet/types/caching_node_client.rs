@@ -0,0 +1,184 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Node client that saves round trips to the node: the chain height is
+//! reused for a few seconds, and outputs asked for by concurrent callers
+//! are fetched in one request
+
+use super::{NodeClient, NodeStatus, NodeVersionInfo, TxWrapper};
+use crate::common::{Arc, Mutex, MutexGuard};
+use crate::wallet::ErrorKind;
+use failure::Error;
+use grin_core::core::TxKernel;
+use grin_util::secp::pedersen::{Commitment, RangeProof};
+use parking_lot::Condvar;
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// How long a chain height is reused. Well below the block time, so the
+/// wallet is never more than a block behind because of it
+const HEIGHT_TTL: Duration = Duration::from_secs(5);
+
+type Outputs = HashMap<Commitment, (String, u64, u64)>;
+
+/// Outputs requests of concurrent callers. Callers arriving while a request
+/// runs queue their commitments for the next one, which the first of them
+/// sends once the running request is done
+#[derive(Default)]
+struct OutputBatches {
+	/// Commitments of the next request
+	queued: HashSet<Commitment>,
+	/// Callers waiting for the next request
+	waiting: usize,
+	in_flight: bool,
+	/// Requests completed so far
+	completed: u64,
+	/// Results of completed requests not yet picked up by all their callers,
+	/// with the number of callers still to pick them up
+	results: HashMap<u64, (Arc<Result<Outputs, String>>, usize)>,
+}
+
+#[derive(Clone)]
+pub struct CachingNodeClient<C: NodeClient> {
+	inner: C,
+	height: Arc<Mutex<Option<(Instant, u64)>>>,
+	batches: Arc<(Mutex<OutputBatches>, Condvar)>,
+}
+
+impl<C: NodeClient> CachingNodeClient<C> {
+	pub fn new(inner: C) -> Self {
+		Self {
+			inner,
+			height: Arc::new(Mutex::new(None)),
+			batches: Arc::new((Mutex::new(OutputBatches::default()), Condvar::new())),
+		}
+	}
+
+	/// The node may have changed, nothing cached applies to it anymore
+	fn invalidate(&self) {
+		*self.height.lock() = None;
+	}
+}
+
+impl<C: NodeClient> NodeClient for CachingNodeClient<C> {
+	fn node_url(&self) -> &str {
+		self.inner.node_url()
+	}
+
+	fn set_node_url(&mut self, node_url: &str) {
+		self.invalidate();
+		self.inner.set_node_url(node_url);
+	}
+
+	fn node_api_secret(&self) -> Option<String> {
+		self.inner.node_api_secret()
+	}
+
+	fn set_node_api_secret(&mut self, node_api_secret: Option<String>) {
+		self.invalidate();
+		self.inner.set_node_api_secret(node_api_secret);
+	}
+
+	fn get_version_info(&mut self) -> Option<NodeVersionInfo> {
+		self.inner.get_version_info()
+	}
+
+	fn post_tx(&self, tx: &TxWrapper, fluff: bool) -> Result<(), Error> {
+		self.inner.post_tx(tx, fluff)
+	}
+
+	fn get_chain_height(&self) -> Result<u64, Error> {
+		if let Some((fetched, height)) = *self.height.lock() {
+			if fetched.elapsed() < HEIGHT_TTL {
+				return Ok(height);
+			}
+		}
+		let height = self.inner.get_chain_height()?;
+		*self.height.lock() = Some((Instant::now(), height));
+		Ok(height)
+	}
+
+	fn get_status(&self) -> Result<NodeStatus, Error> {
+		let status = self.inner.get_status()?;
+		*self.height.lock() = Some((Instant::now(), status.height));
+		Ok(status)
+	}
+
+	fn get_header_hash(&self, height: u64) -> Result<Option<String>, Error> {
+		self.inner.get_header_hash(height)
+	}
+
+	fn get_kernel(
+		&self,
+		excess: &Commitment,
+		min_height: Option<u64>,
+		max_height: Option<u64>,
+	) -> Result<Option<(TxKernel, u64, u64)>, Error> {
+		self.inner.get_kernel(excess, min_height, max_height)
+	}
+
+	fn get_outputs_from_node(&self, wallet_outputs: Vec<Commitment>) -> Result<Outputs, Error> {
+		let (lock, done) = &*self.batches;
+		let mut batches = lock.lock();
+		batches.queued.extend(wallet_outputs.iter().cloned());
+		batches.waiting += 1;
+		let round = batches.completed + 1 + batches.in_flight as u64;
+
+		while batches.completed < round {
+			if batches.in_flight {
+				done.wait(&mut batches);
+				continue;
+			}
+			// First caller since the last request completed, send the queue
+			let commits: Vec<Commitment> = batches.queued.drain().collect();
+			let callers = batches.waiting;
+			batches.waiting = 0;
+			batches.in_flight = true;
+			let res =
+				MutexGuard::unlocked(&mut batches, || self.inner.get_outputs_from_node(commits));
+			batches.in_flight = false;
+			batches.completed += 1;
+			let res = Arc::new(res.map_err(|e| e.to_string()));
+			batches.results.insert(round, (res, callers));
+			done.notify_all();
+		}
+
+		let res = {
+			let (res, callers) = batches.results.get_mut(&round).unwrap();
+			*callers -= 1;
+			res.clone()
+		};
+		if batches.results[&round].1 == 0 {
+			batches.results.remove(&round);
+		}
+		drop(batches);
+
+		match &*res {
+			Ok(outputs) => Ok(wallet_outputs
+				.iter()
+				.filter_map(|c| outputs.get(c).map(|o| (c.clone(), o.clone())))
+				.collect()),
+			Err(e) => Err(ErrorKind::ClientCallback(e.clone()).into()),
+		}
+	}
+
+	fn get_outputs_by_pmmr_index(
+		&self,
+		start_height: u64,
+		max_outputs: u64,
+	) -> Result<(u64, u64, Vec<(Commitment, RangeProof, bool, u64, u64)>), Error> {
+		self.inner
+			.get_outputs_by_pmmr_index(start_height, max_outputs)
+	}
+}
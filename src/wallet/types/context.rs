@@ -12,12 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{Identifier, SecretKey};
-use grin_core::libtx::aggsig;
+use super::{Identifier, SecretKey, SlateSigner};
+use failure::Error;
 use grin_core::ser;
-use grin_util::secp;
 use grin_util::secp::pedersen::Commitment;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 /// Holds the context for a single aggsig transaction
@@ -43,20 +43,43 @@ pub struct Context {
 	pub output_commits: Vec<Commitment>,
 	/// Input commitments
 	pub input_commits: Vec<Commitment>,
+	/// Account the fee is drawn from, if different from `parent_key_id`
+	#[serde(default)]
+	pub fee_parent_key_id: Option<Identifier>,
+	/// Private notes, copied to the log entry when the outputs are locked
+	#[serde(default)]
+	pub notes: BTreeMap<String, String>,
+	/// Entry of a cancelled transaction this one re-issues
+	#[serde(default)]
+	pub replaces: Option<u32>,
+	/// Selection settings of a send whose inputs are only selected and
+	/// locked when it's finalized
+	#[serde(default)]
+	pub late_lock: Option<LateLock>,
+}
+
+/// Input selection settings of a late locked send, applied at finalize
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LateLock {
+	pub minimum_confirmations: u64,
+	pub max_outputs: usize,
+	pub num_change_outputs: usize,
+	pub selection_strategy_is_use_all: bool,
+	pub fee_base: Option<u64>,
 }
 
 impl Context {
 	/// Create a new context with defaults
 	pub fn new(
-		secp: &secp::Secp256k1,
+		signer: &dyn SlateSigner,
 		sec_key: SecretKey,
 		parent_key_id: &Identifier,
 		participant_id: usize,
-	) -> Context {
-		Context {
+	) -> Result<Context, Error> {
+		Ok(Context {
 			parent_key_id: parent_key_id.clone(),
 			sec_key,
-			sec_nonce: aggsig::create_secnonce(secp).unwrap(),
+			sec_nonce: signer.create_nonce()?,
 			output_ids: vec![],
 			input_ids: vec![],
 			participant_id,
@@ -64,7 +87,11 @@ impl Context {
 			fee: 0,
 			output_commits: vec![],
 			input_commits: vec![],
-		}
+			fee_parent_key_id: None,
+			notes: BTreeMap::new(),
+			replaces: None,
+			late_lock: None,
+		})
 	}
 }
 
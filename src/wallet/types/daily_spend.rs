@@ -0,0 +1,45 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::NaiveDate;
+use grin_core::ser;
+use serde::{Deserialize, Serialize};
+
+/// Total sent on a day (UTC), of every account, counted against the
+/// `daily_spend_limit` of the config
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DailySpend {
+	pub day: NaiveDate,
+	/// Amount in nanogrin, fees not included
+	pub amount: u64,
+}
+
+impl DailySpend {
+	pub fn new(day: NaiveDate) -> Self {
+		Self { day, amount: 0 }
+	}
+}
+
+impl ser::Writeable for DailySpend {
+	fn write<W: ser::Writer>(&self, writer: &mut W) -> Result<(), ser::Error> {
+		writer.write_bytes(&serde_json::to_vec(self).map_err(|_| ser::Error::CorruptedData)?)
+	}
+}
+
+impl ser::Readable for DailySpend {
+	fn read(reader: &mut dyn ser::Reader) -> Result<DailySpend, ser::Error> {
+		let data = reader.read_bytes_len_prefix()?;
+		serde_json::from_slice(&data[..]).map_err(|_| ser::Error::CorruptedData)
+	}
+}
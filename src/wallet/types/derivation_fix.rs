@@ -0,0 +1,36 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::Identifier;
+use serde::{Deserialize, Serialize};
+
+/// Child derivation index of an account, before and after a repair
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DerivationFix {
+	/// Account label, if the parent key has an account mapping
+	pub account: Option<String>,
+	pub parent_key_id: Identifier,
+	/// Highest child index used by a stored output of the account
+	pub highest_output_index: Option<u32>,
+	/// Next child index that was stored
+	pub before: u32,
+	/// Next child index after the repair
+	pub after: u32,
+}
+
+impl DerivationFix {
+	pub fn fixed(&self) -> bool {
+		self.after != self.before
+	}
+}
@@ -0,0 +1,36 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::Identifier;
+use serde::{Deserialize, Serialize};
+
+/// Account with outputs found on chain while restoring or checking the wallet
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DiscoveredAccount {
+	/// Parent BIP32 derivation path
+	pub path: Identifier,
+	/// Index of the account in its derivation path, the default account is 0
+	pub index: u32,
+	/// Label the account is stored under
+	pub label: String,
+	/// Whether the wallet already had a label for the account
+	pub existing: bool,
+	/// Number of outputs found
+	pub num_outputs: usize,
+	/// Total value of the outputs found
+	pub amount: u64,
+}
+
+/// Picks the label of a newly discovered account, `None` keeps the default one
+pub type AccountLabeler = dyn Fn(&DiscoveredAccount) -> Option<String>;
@@ -0,0 +1,107 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::Context;
+use crate::common::entropy::Entropy;
+use crate::wallet::ErrorKind;
+use failure::{Error, ResultExt};
+use grin_util::{from_hex, to_hex};
+use ring::{aead, digest, pbkdf2};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Private context of an outstanding transaction, encrypted with a
+/// password so the transaction can be finalized by another wallet
+/// holding the same seed
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EncryptedContext {
+	pub slate_id: Uuid,
+	pub participant_id: usize,
+	encrypted_context: String,
+	salt: String,
+	nonce: String,
+}
+
+impl EncryptedContext {
+	pub fn new(
+		slate_id: Uuid,
+		context: &Context,
+		password: &str,
+		entropy: &dyn Entropy,
+	) -> Result<Self, Error> {
+		let mut salt = [0; 8];
+		entropy.fill_bytes(&mut salt);
+		let mut nonce = [0; 12];
+		entropy.fill_bytes(&mut nonce);
+		let key = derive_key(&salt, password);
+
+		let mut enc_bytes = serde_json::to_vec(context)?;
+		let suffix_len = aead::CHACHA20_POLY1305.tag_len();
+		for _ in 0..suffix_len {
+			enc_bytes.push(0);
+		}
+		let sealing_key =
+			aead::SealingKey::new(&aead::CHACHA20_POLY1305, &key).context(ErrorKind::Encryption)?;
+		// The slate id is authenticated along with the context, so it
+		// can't be swapped for the id of another transaction
+		aead::seal_in_place(
+			&sealing_key,
+			&nonce,
+			slate_id.as_bytes(),
+			&mut enc_bytes,
+			suffix_len,
+		)
+		.context(ErrorKind::Encryption)?;
+
+		Ok(Self {
+			slate_id,
+			participant_id: context.participant_id,
+			encrypted_context: to_hex(enc_bytes),
+			salt: to_hex(salt.to_vec()),
+			nonce: to_hex(nonce.to_vec()),
+		})
+	}
+
+	pub fn decrypt(&self, password: &str) -> Result<Context, Error> {
+		let mut encrypted =
+			from_hex(self.encrypted_context.clone()).map_err(|_| ErrorKind::Encryption)?;
+		let salt = from_hex(self.salt.clone()).map_err(|_| ErrorKind::Encryption)?;
+		let nonce = from_hex(self.nonce.clone()).map_err(|_| ErrorKind::Encryption)?;
+		let key = derive_key(&salt, password);
+
+		let opening_key =
+			aead::OpeningKey::new(&aead::CHACHA20_POLY1305, &key).context(ErrorKind::Encryption)?;
+		let decrypted = aead::open_in_place(
+			&opening_key,
+			&nonce,
+			self.slate_id.as_bytes(),
+			0,
+			&mut encrypted,
+		)
+		.context(ErrorKind::Encryption)?;
+
+		let context: Context =
+			serde_json::from_slice(decrypted).map_err(|_| ErrorKind::Encryption)?;
+		if context.participant_id != self.participant_id {
+			return Err(ErrorKind::Encryption.into());
+		}
+		Ok(context)
+	}
+}
+
+fn derive_key(salt: &[u8], password: &str) -> [u8; 32] {
+	let mut key = [0; 32];
+	pbkdf2::derive(&digest::SHA512, 100, salt, password.as_bytes(), &mut key);
+	key
+}
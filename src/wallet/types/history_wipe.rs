@@ -0,0 +1,35 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Outcome of purging the metadata of old transactions
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HistoryWipe {
+	/// Finished transactions created before this time were wiped
+	pub before: DateTime<Utc>,
+	/// Number of transaction log entries stripped of their metadata
+	pub entries: usize,
+	/// Number of stored transaction files removed
+	pub stored_txs: usize,
+	/// Number of stored transaction proofs removed
+	pub proofs: usize,
+}
+
+impl HistoryWipe {
+	pub fn is_empty(&self) -> bool {
+		self.entries == 0 && self.stored_txs == 0 && self.proofs == 0
+	}
+}
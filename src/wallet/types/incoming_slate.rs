@@ -0,0 +1,70 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::Slate;
+use chrono::{DateTime, Utc};
+use grin_core::ser;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A slate received while `receive_manual_approval` is on. It is kept
+/// unsigned until approved, then signed and sent back to the sender
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IncomingSlate {
+	pub slate: Slate,
+	/// Address the slate came from, where the response goes
+	pub address: String,
+	/// Account to receive into, the active one when approved if not set
+	pub account: Option<String>,
+	pub message: Option<String>,
+	pub received: DateTime<Utc>,
+	/// Kept once rejected, so the sender posting it again is refused
+	pub rejected: bool,
+}
+
+impl IncomingSlate {
+	pub fn new(
+		slate: Slate,
+		address: String,
+		account: Option<String>,
+		message: Option<String>,
+		now: DateTime<Utc>,
+	) -> Self {
+		Self {
+			slate,
+			address,
+			account,
+			message,
+			received: now,
+			rejected: false,
+		}
+	}
+
+	pub fn slate_id(&self) -> Uuid {
+		self.slate.id
+	}
+}
+
+impl ser::Writeable for IncomingSlate {
+	fn write<W: ser::Writer>(&self, writer: &mut W) -> Result<(), ser::Error> {
+		writer.write_bytes(&serde_json::to_vec(self).map_err(|_| ser::Error::CorruptedData)?)
+	}
+}
+
+impl ser::Readable for IncomingSlate {
+	fn read(reader: &mut dyn ser::Reader) -> Result<IncomingSlate, ser::Error> {
+		let data = reader.read_bytes_len_prefix()?;
+		serde_json::from_slice(&data[..]).map_err(|_| ser::Error::CorruptedData)
+	}
+}
@@ -0,0 +1,41 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::TxLogEntry;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Outcome of looking up the kernels of recently confirmed transactions
+/// on chain
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KernelReconciliation {
+	/// Transactions confirmed before this time were not checked
+	pub since: DateTime<Utc>,
+	/// Number of transactions whose kernel was found on chain
+	pub found: usize,
+	/// Number of confirmed transactions without a stored kernel excess,
+	/// which can't be checked
+	pub unverifiable: usize,
+	/// Confirmed transactions whose kernel is not on chain
+	pub missing: Vec<TxLogEntry>,
+	/// Lowest MMR index of the outputs of the missing transactions, the
+	/// point from which a check needs to walk the UTXO set
+	pub repair_from: Option<u64>,
+}
+
+impl KernelReconciliation {
+	pub fn is_consistent(&self) -> bool {
+		self.missing.is_empty()
+	}
+}
@@ -13,38 +13,94 @@
 // limitations under the License.
 
 mod acct_path_mapping;
+mod amount;
 mod args;
+mod backup_info;
+mod balance_attestation;
 mod block_fees;
 mod block_identifier;
+mod caching_node_client;
 mod cb_data;
 mod context;
+mod daily_spend;
+mod derivation_fix;
+mod discovered_account;
+mod encrypted_context;
+mod history_wipe;
+mod incoming_slate;
+mod kernel_reconciliation;
+mod multisig;
 mod node_client;
+mod node_session;
+mod notification;
 mod output_commit_mapping;
 mod output_data;
 mod output_status;
+mod ownership_proof;
+mod page;
+mod payment_proof;
+mod queued_slate;
+mod receive_policy;
+mod received_message;
+mod scan_options;
 mod slate;
+mod slate_signer;
+mod tx_detail;
+mod tx_export;
+mod tx_filter;
 mod tx_log_entry;
 mod tx_log_entry_type;
 mod tx_proof;
 mod tx_wrapper;
 mod wallet_backend;
 mod wallet_backend_batch;
+mod wallet_data;
+mod wallet_event;
 mod wallet_info;
 mod wallet_inst;
+mod watch_key;
 
-pub use self::acct_path_mapping::AcctPathMapping;
+pub use self::acct_path_mapping::{AcctPathMapping, DEFAULT_ACCOUNT};
+pub use self::amount::Amount;
 pub use self::args::*;
+pub use self::backup_info::BackupInfo;
+pub use self::balance_attestation::ErrorKind as BalanceAttestationErrorKind;
+pub use self::balance_attestation::{AttestationStatus, BalanceAttestation};
 pub use self::block_fees::BlockFees;
 pub use self::block_identifier::BlockIdentifier;
+pub use self::caching_node_client::CachingNodeClient;
 pub use self::cb_data::CbData;
-pub use self::context::Context;
-pub use self::node_client::{HTTPNodeClient, NodeClient, NodeVersionInfo};
+pub use self::context::{Context, LateLock};
+pub use self::daily_spend::DailySpend;
+pub use self::derivation_fix::DerivationFix;
+pub use self::discovered_account::{AccountLabeler, DiscoveredAccount};
+pub use self::encrypted_context::EncryptedContext;
+pub use self::history_wipe::HistoryWipe;
+pub use self::incoming_slate::IncomingSlate;
+pub use self::kernel_reconciliation::KernelReconciliation;
+pub use self::multisig::{MultisigKind, MultisigOutput, MultisigSlate, MultisigStatus};
+pub use self::node_client::{HTTPNodeClient, NodeClient, NodeStatus, NodeVersionInfo};
+pub use self::node_session::NodeSession;
+pub use self::notification::{Notification, NotificationEvent, NotificationRoute};
 pub use self::output_commit_mapping::OutputCommitMapping;
 pub use self::output_data::OutputData;
 pub use self::output_status::OutputStatus;
+pub use self::ownership_proof::ErrorKind as OwnershipProofErrorKind;
+pub use self::ownership_proof::OwnershipProof;
+pub use self::page::Page;
+pub use self::payment_proof::{payment_proof_message, PaymentInfo, PaymentProof};
+pub use self::queued_slate::QueuedSlate;
+pub use self::receive_policy::{ReceiveDecision, ReceivePolicy};
+pub use self::received_message::ReceivedMessage;
+pub use self::scan_options::ScanOptions;
 pub use self::slate::{
-	Slate, SlateVersion, VersionedSlate, CURRENT_SLATE_VERSION, GRIN_BLOCK_HEADER_VERSION,
+	compact, ParticipantMessageData, ParticipantMessages, Slate, SlateVersion, VersionedSlate,
+	CURRENT_SLATE_VERSION, GRIN_BLOCK_HEADER_VERSION,
 };
+pub use self::slate_signer::SlateSigner;
+pub use self::tx_detail::{StoredSlate, TxDetail, TxMessage};
+pub use self::tx_export::{TxExportEntry, TxExportFormat};
+pub use self::tx_filter::{TxDirection, TxFilter};
 pub use self::tx_log_entry::TxLogEntry;
 pub use self::tx_log_entry_type::TxLogEntryType;
 pub use self::tx_proof::ErrorKind as TxProofErrorKind;
@@ -52,9 +108,17 @@ pub use self::tx_proof::TxProof;
 pub use self::tx_wrapper::TxWrapper;
 pub use self::wallet_backend::WalletBackend;
 pub use self::wallet_backend_batch::WalletBackendBatch;
+pub use self::wallet_data::{
+	GrinContext, GrinOutputData, GrinTxLogEntry, StoredContext, WalletDataSummary, ACCT_PATHS_FILE,
+	CONTEXTS_FILE, OUTPUTS_FILE, SAVED_PROOFS_DIR, SAVED_TXS_DIR, TX_LOG_FILE,
+};
+pub use self::wallet_event::{
+	EventBus, EventFilter, PublishedEvent, SharedEventBus, Subscription, TxEvent, WalletEvent,
+};
 pub use self::wallet_info::WalletInfo;
 pub use self::wallet_inst::WalletInst;
-pub use super::seed::{EncryptedWalletSeed, WalletSeed};
+pub use self::watch_key::{WatchKey, WatchKeychain};
+pub use super::seed::{create_private_file, EncryptedWalletSeed, SeedBackup, WalletSeed};
 pub use crate::common::{Arc, Mutex, MutexGuard, Result};
 pub use grin_core::core::hash::Hash;
 pub use grin_core::core::{Output, Transaction, TxKernel};
@@ -0,0 +1,126 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Identifier, VersionedSlate};
+use chrono::{DateTime, Utc};
+use grin_core::ser;
+use grin_util::secp::pedersen::Commitment;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use uuid::Uuid;
+
+/// Status of a 2-of-2 output shared with another wallet
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum MultisigStatus {
+	/// The exchange building the output is under way
+	Building,
+	/// The transaction creating the output was posted
+	Funded,
+	/// A transaction spending the output is being signed
+	Spending,
+	/// The transaction spending the output was posted
+	Spent,
+}
+
+impl fmt::Display for MultisigStatus {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			MultisigStatus::Building => write!(f, "Building"),
+			MultisigStatus::Funded => write!(f, "Funded"),
+			MultisigStatus::Spending => write!(f, "Spending"),
+			MultisigStatus::Spent => write!(f, "Spent"),
+		}
+	}
+}
+
+/// Our side of an output whose blinding factor is split between two
+/// wallets. Neither wallet can spend it on its own, and neither can find it
+/// on the chain during a restore, so this record is all there is
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultisigOutput {
+	/// Id of the slate that funds the output
+	pub id: Uuid,
+	pub parent_key_id: Identifier,
+	/// Our position in the exchange that built the output, 0 for the funder
+	pub participant_id: usize,
+	pub value: u64,
+	/// Commitments to the partial blinding factors, in participant order.
+	/// The funder's also commits to the value
+	pub partial_commits: Vec<Commitment>,
+	/// Sum of the partial commitments, once both are known
+	pub commit: Option<Commitment>,
+	/// Nonce shared by both wallets to build the range proof, hex encoded.
+	/// Agreed on in the exchange and never sent, empty until then
+	pub common_nonce: String,
+	pub status: MultisigStatus,
+	pub created: DateTime<Utc>,
+	/// Slate of the transaction spending the output
+	pub spend_slate_id: Option<Uuid>,
+}
+
+impl ser::Writeable for MultisigOutput {
+	fn write<W: ser::Writer>(&self, writer: &mut W) -> Result<(), ser::Error> {
+		writer.write_bytes(&serde_json::to_vec(self).map_err(|_| ser::Error::CorruptedData)?)
+	}
+}
+
+impl ser::Readable for MultisigOutput {
+	fn read(reader: &mut dyn ser::Reader) -> Result<MultisigOutput, ser::Error> {
+		let data = reader.read_bytes_len_prefix()?;
+		serde_json::from_slice(&data[..]).map_err(|_| ser::Error::CorruptedData)
+	}
+}
+
+/// Which transaction a multisig exchange builds
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum MultisigKind {
+	/// Create the shared output, from inputs of the initiator
+	Fund,
+	/// Spend the shared output to a new output of the initiator
+	Spend,
+}
+
+/// Message passed back and forth between the two wallets of a multisig
+/// output. Every `multisig join` takes one and answers with the next round,
+/// until the transaction is posted
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultisigSlate {
+	/// Id of the multisig output
+	pub id: Uuid,
+	pub kind: MultisigKind,
+	/// Number of the round this message completes, starting at 1
+	pub round: u8,
+	pub slate: VersionedSlate,
+	/// Commitments to the partial blinding factors known so far
+	pub commits: Vec<Commitment>,
+	/// Public key of the sender for agreeing on the common nonce of the range
+	/// proof, hex encoded. The nonce itself never leaves the wallets
+	pub exchange_key: Option<String>,
+	/// Range proof round data: the `T1` and `T2` public keys, and the
+	/// `tau_x` share of the sender, all hex encoded
+	pub t_one: Option<String>,
+	pub t_two: Option<String>,
+	pub tau_x: Option<String>,
+}
+
+impl MultisigSlate {
+	/// Whether the message carries the completed transaction, to be posted
+	/// by the wallet that completed it
+	pub fn is_final(&self) -> bool {
+		match self.kind {
+			MultisigKind::Fund => self.round >= 4,
+			MultisigKind::Spend => self.round >= 3,
+		}
+	}
+}
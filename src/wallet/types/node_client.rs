@@ -12,18 +12,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::TxWrapper;
-use crate::common::client;
+use super::{NodeSession, TxWrapper};
+use crate::common::client::{self, ClientOptions};
+use crate::common::metrics::{SharedMetrics, NODE_REQUEST_SECONDS};
+use crate::common::timing::as_millis;
 use crate::wallet::ErrorKind;
 use failure::Error;
 use futures::stream;
 use futures::Stream;
-use grin_api::{Output, OutputListing, OutputType, Tip};
+use grin_api::{
+	BlockHeaderPrintable, LocatedTxKernel, Output, OutputListing, OutputType, Status, Tip,
+};
+use grin_core::core::TxKernel;
+use grin_util::secp::constants::MAX_PROOF_SIZE;
 use grin_util::secp::pedersen::{Commitment, RangeProof};
-use grin_util::to_hex;
+use grin_util::{from_hex, to_hex};
 use log::error;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Instant;
 use tokio::runtime::Runtime;
 
 /// Node version info
@@ -37,6 +45,27 @@ pub struct NodeVersionInfo {
 	pub verified: Option<bool>,
 }
 
+/// Health of the node, as reported by its status endpoint
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NodeStatus {
+	pub user_agent: String,
+	pub protocol_version: u32,
+	/// Height of the node's chain tip
+	pub height: u64,
+	/// Sync state of the node, `no_sync` once it is fully synced
+	pub sync_status: String,
+	/// Number of connected peers
+	pub connections: u32,
+	/// Round trip time of the status request, in milliseconds
+	pub latency: u64,
+}
+
+impl NodeStatus {
+	pub fn is_synced(&self) -> bool {
+		self.sync_status == "no_sync"
+	}
+}
+
 /// Encapsulate all wallet-node communication functions. No functions within libwallet
 /// should care about communication details
 pub trait NodeClient: Sync + Send + Clone + 'static {
@@ -60,6 +89,22 @@ pub trait NodeClient: Sync + Send + Clone + 'static {
 	/// retrieves the current tip from the specified grin node
 	fn get_chain_height(&self) -> Result<u64, Error>;
 
+	/// retrieves the height, sync state and peer count of the node
+	fn get_status(&self) -> Result<NodeStatus, Error>;
+
+	/// retrieves the hash of the block header at the given height, in hex,
+	/// or `None` if the node's chain isn't that high
+	fn get_header_hash(&self, height: u64) -> Result<Option<String>, Error>;
+
+	/// retrieve the kernel with the given excess from the node, searching
+	/// between the given heights. Returns the kernel, its height and its MMR index
+	fn get_kernel(
+		&self,
+		excess: &Commitment,
+		min_height: Option<u64>,
+		max_height: Option<u64>,
+	) -> Result<Option<(TxKernel, u64, u64)>, Error>;
+
 	/// retrieve a list of outputs from the specified grin node
 	/// need "by_height" and "by_id" variants
 	fn get_outputs_from_node(
@@ -84,8 +129,14 @@ pub struct HTTPNodeClient {
 	node_url: String,
 	node_api_secret: Option<String>,
 	node_version_info: Option<NodeVersionInfo>,
+	options: ClientOptions,
+	session: Option<NodeSession>,
+	metrics: Option<SharedMetrics>,
 }
 
+/// Serializable form of an output returned by `get_outputs_by_pmmr_index`
+type RecordedOutput = (String, String, bool, u64, u64);
+
 impl HTTPNodeClient {
 	/// Create a new client that will communicate with the given grin node
 	pub fn new(node_url: &str, node_api_secret: Option<String>) -> HTTPNodeClient {
@@ -93,10 +144,68 @@ impl HTTPNodeClient {
 			node_url: node_url.to_owned(),
 			node_api_secret: node_api_secret,
 			node_version_info: None,
+			options: ClientOptions::default(),
+			session: None,
+			metrics: None,
+		}
+	}
+
+	/// Trust the CAs and use the timeout of `options` for requests to the node
+	pub fn with_options(mut self, options: ClientOptions) -> Self {
+		self.options = options;
+		self
+	}
+
+	/// Record all node calls to, or replay them from, a session bundle
+	pub fn with_session(mut self, session: Option<NodeSession>) -> Self {
+		self.session = session;
+		self
+	}
+
+	/// Record the duration of node calls
+	pub fn with_metrics(mut self, metrics: SharedMetrics) -> Self {
+		self.metrics = Some(metrics);
+		self
+	}
+
+	fn session_call<Q, R, F>(&self, method: &str, request: &Q, f: F) -> Result<R, Error>
+	where
+		Q: Serialize,
+		R: Serialize + DeserializeOwned,
+		F: FnOnce() -> Result<R, Error>,
+	{
+		let start = Instant::now();
+		let res = match &self.session {
+			Some(session) => session.call(method, request, f),
+			None => f(),
+		};
+		if let Some(metrics) = &self.metrics {
+			metrics
+				.lock()
+				.observe(NODE_REQUEST_SECONDS, &[("method", method)], start.elapsed());
 		}
+		res
 	}
 }
 
+fn commit_from_hex(hex: &str) -> Result<Commitment, Error> {
+	let bytes = from_hex(hex.to_owned()).map_err(|_| ErrorKind::Format)?;
+	Ok(Commitment::from_vec(bytes))
+}
+
+fn proof_from_hex(hex: &str) -> Result<RangeProof, Error> {
+	let bytes = from_hex(hex.to_owned()).map_err(|_| ErrorKind::Format)?;
+	if bytes.len() > MAX_PROOF_SIZE {
+		return Err(ErrorKind::Format.into());
+	}
+	let mut proof = [0; MAX_PROOF_SIZE];
+	proof[..bytes.len()].copy_from_slice(&bytes);
+	Ok(RangeProof {
+		proof,
+		plen: bytes.len(),
+	})
+}
+
 impl NodeClient for HTTPNodeClient {
 	fn node_url(&self) -> &str {
 		&self.node_url
@@ -118,28 +227,42 @@ impl NodeClient for HTTPNodeClient {
 			return Some(v.clone());
 		}
 		let url = format!("{}/v1/version", self.node_url());
-		let mut retval = match client::get::<NodeVersionInfo>(url.as_str(), self.node_api_secret())
-		{
-			Ok(n) => n,
-			Err(e) => {
-				// If node isn't available, allow offline functions
-				// unfortunately have to parse string due to error structure
-				let err_string = format!("{}", e);
-				if err_string.contains("404") {
-					return Some(NodeVersionInfo {
-						node_version: "1.0.0".into(),
-						block_header_version: 1,
-						verified: Some(false),
-					});
-				} else {
-					error!("Unable to contact Node to get version info: {}", e);
-					return None;
+		let node_api_secret = self.node_api_secret();
+		let retval = self.session_call("get_version_info", &(), || {
+			let res = client::get_with::<NodeVersionInfo>(&url, node_api_secret, &self.options);
+			match res {
+				Ok(mut n) => {
+					n.verified = Some(true);
+					Ok(Some(n))
+				}
+				Err(e) => {
+					// If node isn't available, allow offline functions
+					// unfortunately have to parse string due to error structure
+					let err_string = format!("{}", e);
+					if err_string.contains("404") {
+						Ok(Some(NodeVersionInfo {
+							node_version: "1.0.0".into(),
+							block_header_version: 1,
+							verified: Some(false),
+						}))
+					} else {
+						error!("Unable to contact Node to get version info: {}", e);
+						Ok(None)
+					}
 				}
 			}
+		});
+		let retval = match retval {
+			Ok(r) => r,
+			Err(e) => {
+				error!("Unable to get version info: {}", e);
+				None
+			}
 		};
-		retval.verified = Some(true);
-		self.node_version_info = Some(retval.clone());
-		Some(retval)
+		if let Some(v) = retval.as_ref().filter(|v| v.verified == Some(true)) {
+			self.node_version_info = Some(v.clone());
+		}
+		retval
 	}
 
 	/// Posts a transaction to a grin node
@@ -151,28 +274,119 @@ impl NodeClient for HTTPNodeClient {
 		} else {
 			url = format!("{}/v1/pool/push_tx", dest);
 		}
-		let res = client::post_no_ret(url.as_str(), self.node_api_secret(), tx);
-		if let Err(e) = res {
-			let report = format!("Posting transaction to node: {}", e);
-			error!("Post TX Error: {}", e);
-			return Err(ErrorKind::ClientCallback(report).into());
-		}
-		Ok(())
+		self.session_call("post_tx", &fluff, || {
+			let secret = self.node_api_secret();
+			let res = client::post_no_ret_with(url.as_str(), secret, tx, &self.options);
+			if let Err(e) = res {
+				let report = format!("Posting transaction to node: {}", e);
+				error!("Post TX Error: {}", e);
+				return Err(ErrorKind::ClientCallback(report).into());
+			}
+			Ok(())
+		})
 	}
 
 	/// Return the chain tip from a given node
 	fn get_chain_height(&self) -> Result<u64, Error> {
 		let addr = self.node_url();
 		let url = format!("{}/v1/chain", addr);
-		let res = client::get::<Tip>(url.as_str(), self.node_api_secret());
-		match res {
-			Err(e) => {
-				let report = format!("Getting chain height from node: {}", e);
-				error!("Get chain height error: {}", e);
-				Err(ErrorKind::ClientCallback(report).into())
+		self.session_call("get_chain_height", &(), || {
+			let res = client::get_with::<Tip>(url.as_str(), self.node_api_secret(), &self.options);
+			match res {
+				Err(e) => {
+					let report = format!("Getting chain height from node: {}", e);
+					error!("Get chain height error: {}", e);
+					Err(ErrorKind::ClientCallback(report).into())
+				}
+				Ok(r) => Ok(r.height),
+			}
+		})
+	}
+
+	/// Return the hash of the header at a height, `None` above the tip
+	fn get_header_hash(&self, height: u64) -> Result<Option<String>, Error> {
+		let url = format!("{}/v1/headers/{}", self.node_url(), height);
+		self.session_call("get_header_hash", &height, || {
+			let res = client::get_with::<BlockHeaderPrintable>(
+				url.as_str(),
+				self.node_api_secret(),
+				&self.options,
+			);
+			match res {
+				Ok(header) => Ok(Some(header.hash)),
+				// unfortunately have to parse string due to error structure
+				Err(e) if format!("{}", e).contains("404") => Ok(None),
+				Err(e) => {
+					let report = format!("Getting header from node: {}", e);
+					error!("Get header error: {}", e);
+					Err(ErrorKind::ClientCallback(report).into())
+				}
 			}
-			Ok(r) => Ok(r.height),
+		})
+	}
+
+	/// Return the status of a given node, timing the request
+	fn get_status(&self) -> Result<NodeStatus, Error> {
+		let url = format!("{}/v1/status", self.node_url());
+		self.session_call("get_status", &(), || {
+			let start = Instant::now();
+			let res =
+				client::get_with::<Status>(url.as_str(), self.node_api_secret(), &self.options);
+			match res {
+				Err(e) => {
+					let report = format!("Getting status from node: {}", e);
+					error!("Get status error: {}", e);
+					Err(ErrorKind::ClientCallback(report).into())
+				}
+				Ok(s) => Ok(NodeStatus {
+					user_agent: s.user_agent,
+					protocol_version: s.protocol_version,
+					height: s.tip.height,
+					sync_status: s.sync_status,
+					connections: s.connections,
+					latency: as_millis(start.elapsed()),
+				}),
+			}
+		})
+	}
+
+	/// Look up a kernel by its excess
+	fn get_kernel(
+		&self,
+		excess: &Commitment,
+		min_height: Option<u64>,
+		max_height: Option<u64>,
+	) -> Result<Option<(TxKernel, u64, u64)>, Error> {
+		let excess = to_hex(excess.0.to_vec());
+		let mut query = Vec::new();
+		if let Some(h) = min_height {
+			query.push(format!("min_height={}", h));
+		}
+		if let Some(h) = max_height {
+			query.push(format!("max_height={}", h));
 		}
+		let url = format!(
+			"{}/v1/chain/kernels/{}?{}",
+			self.node_url(),
+			excess,
+			query.join("&")
+		);
+		let request = (excess.clone(), min_height, max_height);
+		self.session_call("get_kernel", &request, || {
+			let res = client::get_with::<Option<LocatedTxKernel>>(
+				url.as_str(),
+				self.node_api_secret(),
+				&self.options,
+			);
+			match res {
+				Err(e) => {
+					let report = format!("Getting kernel from node: {}", e);
+					error!("Get kernel error: {}", e);
+					Err(ErrorKind::ClientCallback(report).into())
+				}
+				Ok(k) => Ok(k.map(|k| (k.tx_kernel, k.height, k.mmr_index))),
+			}
+		})
 	}
 
 	/// Retrieve outputs from node
@@ -188,41 +402,47 @@ impl NodeClient for HTTPNodeClient {
 			.map(|commit| format!("{}", to_hex(commit.as_ref().to_vec())))
 			.collect();
 
-		// build a map of api outputs by commit so we can look them up efficiently
-		let mut api_outputs: HashMap<Commitment, (String, u64, u64)> = HashMap::new();
-		let mut tasks = Vec::new();
+		let results = self.session_call("get_outputs_from_node", &query_params, || {
+			let mut tasks = Vec::new();
 
-		for query_chunk in query_params.chunks(120) {
-			let url = format!(
-				"{}/v1/chain/outputs/byids?id={}",
-				addr,
-				query_chunk.join(","),
-			);
-			tasks.push(client::get_async::<Vec<Output>>(
-				url.as_str(),
-				self.node_api_secret(),
-			));
-		}
+			for query_chunk in query_params.chunks(120) {
+				let url = format!(
+					"{}/v1/chain/outputs/byids?id={}",
+					addr,
+					query_chunk.join(","),
+				);
+				tasks.push(client::get_async_with::<Vec<Output>>(
+					url.as_str(),
+					self.node_api_secret(),
+					&self.options,
+				));
+			}
 
-		let task = stream::futures_unordered(tasks).collect();
+			let task = stream::futures_unordered(tasks).collect();
 
-		let mut rt = Runtime::new().unwrap();
-		let results = match rt.block_on(task) {
-			Ok(outputs) => outputs,
-			Err(e) => {
-				let report = format!("Getting outputs by id: {}", e);
-				error!("Outputs by id failed: {}", e);
-				return Err(ErrorKind::ClientCallback(report).into());
-			}
-		};
+			let mut rt = Runtime::new().unwrap();
+			let results = match rt.block_on(task) {
+				Ok(outputs) => outputs,
+				Err(e) => {
+					let report = format!("Getting outputs by id: {}", e);
+					error!("Outputs by id failed: {}", e);
+					return Err(ErrorKind::ClientCallback(report).into());
+				}
+			};
 
-		for res in results {
-			for out in res {
-				api_outputs.insert(
-					out.commit.commit(),
-					(to_hex(out.commit.to_vec()), out.height, out.mmr_index),
-				);
+			let mut outputs: Vec<(String, u64, u64)> = Vec::new();
+			for res in results {
+				for out in res {
+					outputs.push((to_hex(out.commit.to_vec()), out.height, out.mmr_index));
+				}
 			}
+			Ok(outputs)
+		})?;
+
+		// build a map of api outputs by commit so we can look them up efficiently
+		let mut api_outputs: HashMap<Commitment, (String, u64, u64)> = HashMap::new();
+		for (commit, height, mmr_index) in results {
+			api_outputs.insert(commit_from_hex(&commit)?, (commit, height, mmr_index));
 		}
 		Ok(api_outputs)
 	}
@@ -237,35 +457,56 @@ impl NodeClient for HTTPNodeClient {
 
 		let url = format!("{}/v1/txhashset/outputs?{}", addr, query_param,);
 
-		let mut api_outputs: Vec<(Commitment, RangeProof, bool, u64, u64)> = Vec::new();
-
-		match client::get::<OutputListing>(url.as_str(), self.node_api_secret()) {
-			Ok(o) => {
-				for out in o.outputs {
-					let is_coinbase = match out.output_type {
-						OutputType::Coinbase => true,
-						OutputType::Transaction => false,
-					};
-					api_outputs.push((
-						out.commit,
-						out.range_proof().unwrap(),
-						is_coinbase,
-						out.block_height.unwrap(),
-						out.mmr_index,
-					));
+		let request = (start_height, max_outputs);
+		let (highest_index, last_retrieved_index, outputs) =
+			self.session_call("get_outputs_by_pmmr_index", &request, || {
+				let mut api_outputs: Vec<RecordedOutput> = Vec::new();
+
+				match client::get_with::<OutputListing>(
+					url.as_str(),
+					self.node_api_secret(),
+					&self.options,
+				) {
+					Ok(o) => {
+						for out in o.outputs {
+							let is_coinbase = match out.output_type {
+								OutputType::Coinbase => true,
+								OutputType::Transaction => false,
+							};
+							let range_proof = out.range_proof().unwrap();
+							api_outputs.push((
+								to_hex(out.commit.0.to_vec()),
+								to_hex(range_proof.proof[..range_proof.plen].to_vec()),
+								is_coinbase,
+								out.block_height.unwrap(),
+								out.mmr_index,
+							));
+						}
+
+						Ok((o.highest_index, o.last_retrieved_index, api_outputs))
+					}
+					Err(e) => {
+						// if we got anything other than 200 back from server, bye
+						error!(
+							"get_outputs_by_pmmr_index: error contacting {}. Error: {}",
+							addr, e
+						);
+						let report = format!("outputs by pmmr index: {}", e);
+						Err(ErrorKind::ClientCallback(report))?
+					}
 				}
+			})?;
 
-				Ok((o.highest_index, o.last_retrieved_index, api_outputs))
-			}
-			Err(e) => {
-				// if we got anything other than 200 back from server, bye
-				error!(
-					"get_outputs_by_pmmr_index: error contacting {}. Error: {}",
-					addr, e
-				);
-				let report = format!("outputs by pmmr index: {}", e);
-				Err(ErrorKind::ClientCallback(report))?
-			}
+		let mut api_outputs = Vec::with_capacity(outputs.len());
+		for (commit, proof, is_coinbase, height, mmr_index) in outputs {
+			api_outputs.push((
+				commit_from_hex(&commit)?,
+				proof_from_hex(&proof)?,
+				is_coinbase,
+				height,
+				mmr_index,
+			));
 		}
+		Ok((highest_index, last_retrieved_index, api_outputs))
 	}
 }
@@ -0,0 +1,185 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::{Arc, Mutex};
+use crate::wallet::ErrorKind;
+use chrono::{DateTime, Utc};
+use clap::crate_version;
+use failure::Error;
+use log::warn;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+
+/// First line of a session bundle
+#[derive(Serialize, Deserialize)]
+struct SessionHeader {
+	wallet_version: String,
+	created: DateTime<Utc>,
+}
+
+/// A single node call. Only the arguments and results of the call are
+/// kept, never the node URL, the API secret or posted transactions
+#[derive(Clone, Serialize, Deserialize)]
+struct RecordedCall {
+	method: String,
+	request: Value,
+	#[serde(default)]
+	response: Option<Value>,
+	#[serde(default)]
+	error: Option<String>,
+}
+
+enum SessionState {
+	Record(File),
+	Replay {
+		calls: Vec<RecordedCall>,
+		used: Vec<bool>,
+	},
+}
+
+/// Records the node calls made during a session to a bundle file, or
+/// answers them from a previously recorded bundle without contacting
+/// the node. Bundles are JSON lines: a header followed by one call per line
+#[derive(Clone)]
+pub struct NodeSession {
+	path: String,
+	state: Arc<Mutex<SessionState>>,
+}
+
+impl NodeSession {
+	pub fn record(path: &str) -> Result<Self, Error> {
+		let mut file = OpenOptions::new()
+			.write(true)
+			.create(true)
+			.truncate(true)
+			.open(path)?;
+		let header = SessionHeader {
+			wallet_version: crate_version!().to_owned(),
+			created: Utc::now(),
+		};
+		writeln!(file, "{}", serde_json::to_string(&header)?)?;
+		Ok(Self {
+			path: path.to_owned(),
+			state: Arc::new(Mutex::new(SessionState::Record(file))),
+		})
+	}
+
+	pub fn replay(path: &str) -> Result<Self, Error> {
+		let mut lines = BufReader::new(File::open(path)?).lines();
+		let header = lines
+			.next()
+			.ok_or_else(|| ErrorKind::NodeSession(format!("{} is empty", path)))??;
+		let header: SessionHeader = serde_json::from_str(&header)
+			.map_err(|_| ErrorKind::NodeSession(format!("{} is not a session bundle", path)))?;
+		if header.wallet_version != crate_version!() {
+			warn!(
+				"Session bundle was recorded with wallet713 v{}",
+				header.wallet_version
+			);
+		}
+
+		let mut calls = Vec::new();
+		for line in lines {
+			let line = line?;
+			if line.trim().is_empty() {
+				continue;
+			}
+			let call: RecordedCall = serde_json::from_str(&line)
+				.map_err(|e| ErrorKind::NodeSession(format!("invalid call in {}: {}", path, e)))?;
+			calls.push(call);
+		}
+		let used = vec![false; calls.len()];
+		Ok(Self {
+			path: path.to_owned(),
+			state: Arc::new(Mutex::new(SessionState::Replay { calls, used })),
+		})
+	}
+
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+
+	/// Make sure all calls recorded so far are on disk
+	pub fn flush(&self) -> Result<(), Error> {
+		if let SessionState::Record(file) = &mut *self.state.lock() {
+			file.sync_all()?;
+		}
+		Ok(())
+	}
+
+	pub fn is_replay(&self) -> bool {
+		match *self.state.lock() {
+			SessionState::Replay { .. } => true,
+			SessionState::Record(_) => false,
+		}
+	}
+
+	/// Run `f` and record its result, or look the result up in the bundle
+	/// when replaying. Replayed calls are matched on method and arguments,
+	/// in recorded order. Once all matching calls have been used, the last
+	/// one keeps being returned
+	pub fn call<Q, R, F>(&self, method: &str, request: &Q, f: F) -> Result<R, Error>
+	where
+		Q: Serialize,
+		R: Serialize + DeserializeOwned,
+		F: FnOnce() -> Result<R, Error>,
+	{
+		let request = serde_json::to_value(request)?;
+		let mut state = self.state.lock();
+		match &mut *state {
+			SessionState::Record(file) => {
+				let result = f();
+				let mut call = RecordedCall {
+					method: method.to_owned(),
+					request,
+					response: None,
+					error: None,
+				};
+				match &result {
+					Ok(r) => call.response = Some(serde_json::to_value(r)?),
+					Err(e) => call.error = Some(e.to_string()),
+				}
+				if let Err(e) = writeln!(file, "{}", serde_json::to_string(&call)?) {
+					warn!("Unable to record node call to {}: {}", self.path, e);
+				}
+				result
+			}
+			SessionState::Replay { calls, used } => {
+				let matching = |c: &RecordedCall| c.method == method && c.request == request;
+				let index = calls
+					.iter()
+					.zip(used.iter())
+					.position(|(c, u)| !*u && matching(c))
+					.or_else(|| calls.iter().rposition(|c| matching(c)))
+					.ok_or_else(|| {
+						ErrorKind::NodeSession(format!("no recorded response for {}", method))
+					})?;
+				used[index] = true;
+
+				let call = &calls[index];
+				match (&call.response, &call.error) {
+					(Some(response), _) => Ok(serde_json::from_value(response.clone())?),
+					(None, Some(error)) => Err(ErrorKind::ClientCallback(error.clone()).into()),
+					(None, None) => {
+						let msg = format!("recorded call to {} has no result", method);
+						Err(ErrorKind::NodeSession(msg).into())
+					}
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,133 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Slate, TxLogEntry, TxLogEntryType};
+use crate::common::client;
+use chrono::{DateTime, Utc};
+use failure::Error;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+use uuid::Uuid;
+
+/// Where the events of an account are delivered
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationRoute {
+	/// URL the event is POSTed to as JSON
+	pub webhook: Option<String>,
+	/// Executable run for every event, with the event name as argument and
+	/// the event as JSON on stdin
+	pub script: Option<String>,
+}
+
+impl NotificationRoute {
+	pub fn is_empty(&self) -> bool {
+		self.webhook.is_none() && self.script.is_none()
+	}
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+	/// We signed an incoming slate as the receiver
+	TxReceived,
+	/// We finalized a slate we sent
+	TxFinalized,
+}
+
+impl NotificationEvent {
+	/// Type of the log entry the event is about
+	pub fn tx_type(&self) -> TxLogEntryType {
+		match self {
+			NotificationEvent::TxReceived => TxLogEntryType::TxReceived,
+			NotificationEvent::TxFinalized => TxLogEntryType::TxSent,
+		}
+	}
+}
+
+impl fmt::Display for NotificationEvent {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			NotificationEvent::TxReceived => write!(f, "tx_received"),
+			NotificationEvent::TxFinalized => write!(f, "tx_finalized"),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Notification {
+	pub event: NotificationEvent,
+	pub account: String,
+	pub tx_id: u32,
+	pub slate_id: Uuid,
+	pub amount: u64,
+	pub fee: u64,
+	pub address: Option<String>,
+	pub label: Option<String>,
+	pub timestamp: DateTime<Utc>,
+}
+
+impl Notification {
+	pub fn new(event: NotificationEvent, account: &str, slate: &Slate, tx: &TxLogEntry) -> Self {
+		Self {
+			event,
+			account: account.to_owned(),
+			tx_id: tx.id,
+			slate_id: slate.id,
+			amount: slate.amount,
+			fee: slate.fee,
+			address: tx.address.clone(),
+			label: tx.label.clone(),
+			timestamp: Utc::now(),
+		}
+	}
+
+	/// Deliver the notification in the background, so a slow endpoint
+	/// never holds up the listener that processed the slate
+	pub fn send(self, route: NotificationRoute) {
+		thread::spawn(move || {
+			if let Some(url) = &route.webhook {
+				match client::post_no_ret(url, None, &self) {
+					Ok(()) => debug!("Notified {} of {}", url, self.event),
+					Err(e) => warn!("Unable to notify {} of {}: {}", url, self.event, e),
+				}
+			}
+			if let Some(script) = &route.script {
+				if let Err(e) = self.run(script) {
+					warn!("Unable to run {} for {}: {}", script, self.event, e);
+				}
+			}
+		});
+	}
+
+	fn run(&self, script: &str) -> Result<(), Error> {
+		let mut child = Command::new(script)
+			.arg(self.event.to_string())
+			.stdin(Stdio::piped())
+			.spawn()?;
+		// Dropping stdin closes it, so the script sees the end of the event
+		if let Some(mut stdin) = child.stdin.take() {
+			stdin.write_all(serde_json::to_string(self)?.as_bytes())?;
+		}
+		let status = child.wait()?;
+		if !status.success() {
+			warn!("{} exited with {} for {}", script, status, self.event);
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,88 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::crypto::{sign_challenge, verify_signature, Hex};
+use failure::{Error, Fail};
+use grin_util::secp::key::{PublicKey, SecretKey};
+use grin_util::secp::pedersen::Commitment;
+use grin_util::secp::{ContextFlag, Secp256k1, Signature};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Fail)]
+pub enum ErrorKind {
+	#[fail(display = "Unable to derive public key from commitment")]
+	PublicKey,
+	#[fail(display = "Unable to sign ownership proof")]
+	Sign,
+	#[fail(display = "Unable to verify signature")]
+	VerifySignature,
+}
+
+/// Statement proving knowledge of the blinding factor of an output.
+/// The signing key is `commit - value*H`, so anyone can verify the
+/// signature using only the commitment and the revealed value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OwnershipProof {
+	pub commit: Commitment,
+	pub value: u64,
+	pub message: String,
+	pub signature: Signature,
+}
+
+impl OwnershipProof {
+	pub fn new(
+		commit: Commitment,
+		value: u64,
+		message: String,
+		blinding: &SecretKey,
+	) -> Result<Self, Error> {
+		let challenge = Self::challenge(&commit, value, &message);
+		let signature = sign_challenge(&challenge, blinding).map_err(|_| ErrorKind::Sign)?;
+		let proof = Self {
+			commit,
+			value,
+			message,
+			signature,
+		};
+
+		// Make sure the key actually matches the commitment
+		proof.verify()?;
+		Ok(proof)
+	}
+
+	pub fn verify(&self) -> Result<(), ErrorKind> {
+		let public_key = self.public_key()?;
+		let challenge = Self::challenge(&self.commit, self.value, &self.message);
+		verify_signature(&challenge, &self.signature, &public_key)
+			.map_err(|_| ErrorKind::VerifySignature)
+	}
+
+	fn public_key(&self) -> Result<PublicKey, ErrorKind> {
+		let secp = Secp256k1::with_caps(ContextFlag::Commit);
+		let excess = if self.value == 0 {
+			self.commit.clone()
+		} else {
+			let value_commit = secp
+				.commit_value(self.value)
+				.map_err(|_| ErrorKind::PublicKey)?;
+			secp.commit_sum(vec![self.commit.clone()], vec![value_commit])
+				.map_err(|_| ErrorKind::PublicKey)?
+		};
+		excess.to_pubkey(&secp).map_err(|_| ErrorKind::PublicKey)
+	}
+
+	fn challenge(commit: &Commitment, value: u64, message: &str) -> String {
+		format!("{}{}{}", commit.to_hex(), value, message)
+	}
+}
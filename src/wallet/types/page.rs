@@ -0,0 +1,38 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+use std::usize;
+
+/// Window into a list of results, so large wallets can be listed in
+/// bounded chunks. The default page contains every result
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Page {
+	/// Number of results to skip
+	pub offset: usize,
+	/// Maximum number of results to return
+	pub limit: Option<usize>,
+}
+
+impl Page {
+	pub fn new(offset: usize, limit: Option<usize>) -> Self {
+		Self { offset, limit }
+	}
+
+	pub fn apply<I: Iterator>(&self, iter: I) -> impl Iterator<Item = I::Item> {
+		iter.skip(self.offset)
+			.take(self.limit.unwrap_or(usize::MAX))
+	}
+}
@@ -0,0 +1,105 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! wallet713 payment proofs. The receiver signs the amount, kernel excess
+//! and sender address with the key of a grinbox address, and the sender adds
+//! its own signature when exporting the proof. This is wallet713's own
+//! format: it isn't compatible with the payment proofs of grin-wallet, which
+//! use ed25519 keys of onion addresses and a different message and slate
+//! layout. Neither wallet can request, sign or verify the other's proofs
+
+use super::Amount;
+use crate::common::crypto::{sign_challenge, to_hex, verify_signature};
+use crate::wallet::ErrorKind;
+use failure::Error;
+use grin_core::libtx::secp_ser;
+use grin_util::secp::key::{PublicKey, SecretKey};
+use grin_util::secp::pedersen::Commitment;
+use grin_util::secp::{Secp256k1, Signature};
+use serde::{Deserialize, Serialize};
+
+/// wallet713 payment proof request embedded in the slate by the sender. The
+/// receiver signs the amount and kernel excess with the key of
+/// `receiver_address`. Only wallet713 receivers fill it in
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PaymentInfo {
+	#[serde(with = "secp_ser::pubkey_serde")]
+	pub sender_address: PublicKey,
+	#[serde(with = "secp_ser::pubkey_serde")]
+	pub receiver_address: PublicKey,
+	#[serde(with = "secp_ser::option_sig_serde")]
+	pub receiver_signature: Option<Signature>,
+}
+
+/// Exportable wallet713 payment proof, independent of the transport that was
+/// used. Only wallet713 verifies it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PaymentProof {
+	pub amount: Amount,
+	pub excess: Commitment,
+	#[serde(with = "secp_ser::pubkey_serde")]
+	pub recipient_address: PublicKey,
+	pub recipient_sig: Signature,
+	#[serde(with = "secp_ser::pubkey_serde")]
+	pub sender_address: PublicKey,
+	pub sender_sig: Signature,
+}
+
+impl PaymentProof {
+	pub fn new(
+		amount: Amount,
+		excess: Commitment,
+		info: PaymentInfo,
+		sender_key: &SecretKey,
+	) -> Result<Self, Error> {
+		let recipient_sig = info.receiver_signature.ok_or(ErrorKind::PaymentProof(
+			"not signed by recipient".to_owned(),
+		))?;
+		let message = payment_proof_message(amount.nano(), &excess, &info.sender_address);
+		let sender_sig = sign_challenge(&message, sender_key)?;
+
+		let proof = Self {
+			amount,
+			excess,
+			recipient_address: info.receiver_address,
+			recipient_sig,
+			sender_address: info.sender_address,
+			sender_sig,
+		};
+		proof.verify()?;
+		Ok(proof)
+	}
+
+	pub fn verify(&self) -> Result<(), Error> {
+		let message = payment_proof_message(self.amount.nano(), &self.excess, &self.sender_address);
+		verify_signature(&message, &self.recipient_sig, &self.recipient_address)
+			.map_err(|_| ErrorKind::PaymentProof("invalid recipient signature".to_owned()))?;
+		verify_signature(&message, &self.sender_sig, &self.sender_address)
+			.map_err(|_| ErrorKind::PaymentProof("invalid sender signature".to_owned()))?;
+		Ok(())
+	}
+}
+
+/// Message signed by the recipient (and sender): amount | excess | sender address
+pub fn payment_proof_message(
+	amount: u64,
+	excess: &Commitment,
+	sender_address: &PublicKey,
+) -> String {
+	let secp = Secp256k1::new();
+	let mut message = amount.to_be_bytes().to_vec();
+	message.extend_from_slice(&excess.0);
+	message.extend_from_slice(&sender_address.serialize_vec(&secp, true));
+	to_hex(message)
+}
@@ -0,0 +1,109 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Identifier, Slate, SlateVersion};
+use chrono::{DateTime, Duration, Utc};
+use grin_core::ser;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Delay before the first retry, doubled after every attempt
+const RETRY_BASE_SECS: i64 = 60;
+/// Longest delay between two attempts
+const RETRY_MAX_SECS: i64 = 6 * 60 * 60;
+
+/// A slate sent over grinbox or keybase for which no response came back
+/// yet. It is posted again with an increasing delay until the response is
+/// finalized, the transaction is cancelled or the attempts run out
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueuedSlate {
+	/// Account the slate was sent from
+	pub parent_key_id: Identifier,
+	pub slate: Slate,
+	/// Version the slate is posted as
+	pub version: SlateVersion,
+	/// Transport, `grinbox` or `keybase`
+	pub method: String,
+	pub dest: String,
+	pub created: DateTime<Utc>,
+	/// Number of times the slate was posted, including failed attempts
+	pub attempts: u32,
+	pub last_attempt: Option<DateTime<Utc>>,
+	pub next_attempt: DateTime<Utc>,
+	/// Whether the slate reached the relay at least once
+	pub delivered: bool,
+	pub last_error: Option<String>,
+}
+
+impl QueuedSlate {
+	pub fn new(
+		parent_key_id: Identifier,
+		slate: Slate,
+		version: SlateVersion,
+		method: String,
+		dest: String,
+		now: DateTime<Utc>,
+	) -> Self {
+		Self {
+			parent_key_id,
+			slate,
+			version,
+			method,
+			dest,
+			created: now,
+			attempts: 0,
+			last_attempt: None,
+			next_attempt: now,
+			delivered: false,
+			last_error: None,
+		}
+	}
+
+	pub fn slate_id(&self) -> Uuid {
+		self.slate.id
+	}
+
+	/// Record the outcome of posting the slate and schedule the next attempt
+	pub fn record_attempt(&mut self, error: Option<String>, now: DateTime<Utc>) {
+		let exponent = self.attempts.min(16);
+		let delay = (RETRY_BASE_SECS << exponent).min(RETRY_MAX_SECS);
+		self.attempts += 1;
+		self.last_attempt = Some(now);
+		self.next_attempt = now + Duration::seconds(delay);
+		self.delivered |= error.is_none();
+		self.last_error = error;
+	}
+
+	/// Whether the slate is due to be posted again, `max_attempts` of 0 retries forever
+	pub fn is_due(&self, now: DateTime<Utc>, max_attempts: u32) -> bool {
+		!self.is_exhausted(max_attempts) && self.next_attempt <= now
+	}
+
+	pub fn is_exhausted(&self, max_attempts: u32) -> bool {
+		max_attempts > 0 && self.attempts >= max_attempts
+	}
+}
+
+impl ser::Writeable for QueuedSlate {
+	fn write<W: ser::Writer>(&self, writer: &mut W) -> Result<(), ser::Error> {
+		writer.write_bytes(&serde_json::to_vec(self).map_err(|_| ser::Error::CorruptedData)?)
+	}
+}
+
+impl ser::Readable for QueuedSlate {
+	fn read(reader: &mut dyn ser::Reader) -> Result<QueuedSlate, ser::Error> {
+		let data = reader.read_bytes_len_prefix()?;
+		serde_json::from_slice(&data[..]).map_err(|_| ser::Error::CorruptedData)
+	}
+}
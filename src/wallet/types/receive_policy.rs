@@ -0,0 +1,77 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::contacts::parse_address;
+use grin_core::core::amount_to_hr_string;
+
+/// What happens to an incoming slate
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReceiveDecision {
+	Accept,
+	/// Kept until approved or rejected by hand
+	Hold,
+	/// Refused, with the reason given to the sender
+	Refuse(String),
+}
+
+/// Rules incoming slates have to pass before they are signed, from the
+/// `receive_*` settings. Slates received from a file are not checked
+#[derive(Clone, Debug, Default)]
+pub struct ReceivePolicy {
+	/// Smallest amount accepted, in nanogrin
+	pub min_amount: Option<u64>,
+	/// Only senders with one of these addresses are accepted, when set
+	pub allowed_senders: Option<Vec<String>>,
+	/// Most slates received within an hour
+	pub max_per_hour: Option<u32>,
+	/// Hold every slate passing the rules until approved by hand
+	pub manual_approval: bool,
+}
+
+impl ReceivePolicy {
+	/// Decide on a slate of `amount` from `sender`, with `received` slates
+	/// received in the last hour
+	pub fn decide(&self, amount: u64, sender: &str, received: usize) -> ReceiveDecision {
+		if let Some(min) = self.min_amount.filter(|m| amount < *m) {
+			return ReceiveDecision::Refuse(format!(
+				"amounts below {} grin are not accepted",
+				amount_to_hr_string(min, false)
+			));
+		}
+		if let Some(allowed) = &self.allowed_senders {
+			let sender = normalize(sender);
+			if !allowed.iter().any(|a| normalize(a) == sender) {
+				return ReceiveDecision::Refuse("sender is not accepted".to_owned());
+			}
+		}
+		if let Some(max) = self.max_per_hour.filter(|m| received >= *m as usize) {
+			return ReceiveDecision::Refuse(format!(
+				"no more than {} slates are accepted per hour, try again later",
+				max
+			));
+		}
+		match self.manual_approval {
+			true => ReceiveDecision::Hold,
+			false => ReceiveDecision::Accept,
+		}
+	}
+}
+
+/// Address without its type prefix and default port, so the same address
+/// written either way compares equal
+fn normalize(address: &str) -> String {
+	parse_address(address)
+		.map(|a| a.stripped())
+		.unwrap_or_else(|_| address.to_owned())
+}
@@ -0,0 +1,85 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::Slate;
+use chrono::{DateTime, Utc};
+use grin_core::ser;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A slate read from a polled transport such as keybase. It is stored
+/// before the message is marked as read and kept once processed, so a
+/// restart neither loses the slate nor processes it again
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReceivedMessage {
+	/// Id of the message on the transport, unique per method
+	pub id: String,
+	/// Transport, `keybase`
+	pub method: String,
+	/// Address the slate came from, where the response goes
+	pub address: String,
+	/// Message body, the slate as sent
+	pub body: String,
+	pub slate_id: Uuid,
+	/// Participants that had joined the slate, telling a new slate from its
+	/// response
+	pub participants: usize,
+	pub received: DateTime<Utc>,
+	pub processed: bool,
+}
+
+impl ReceivedMessage {
+	pub fn new(
+		id: String,
+		method: String,
+		address: String,
+		body: String,
+		slate: &Slate,
+		now: DateTime<Utc>,
+	) -> Self {
+		Self {
+			id,
+			method,
+			address,
+			body,
+			slate_id: slate.id,
+			participants: slate.participant_data.len(),
+			received: now,
+			processed: false,
+		}
+	}
+
+	/// Key of the database entry, the method followed by the message id
+	pub fn key(method: &str, id: &str) -> String {
+		format!("{}:{}", method, id)
+	}
+
+	/// Whether both messages carry the same slate at the same stage
+	pub fn same_slate(&self, other: &ReceivedMessage) -> bool {
+		self.slate_id == other.slate_id && self.participants == other.participants
+	}
+}
+
+impl ser::Writeable for ReceivedMessage {
+	fn write<W: ser::Writer>(&self, writer: &mut W) -> Result<(), ser::Error> {
+		writer.write_bytes(&serde_json::to_vec(self).map_err(|_| ser::Error::CorruptedData)?)
+	}
+}
+
+impl ser::Readable for ReceivedMessage {
+	fn read(reader: &mut dyn ser::Reader) -> Result<ReceivedMessage, ser::Error> {
+		let data = reader.read_bytes_len_prefix()?;
+		serde_json::from_slice(&data[..]).map_err(|_| ser::Error::CorruptedData)
+	}
+}
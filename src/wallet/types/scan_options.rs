@@ -0,0 +1,53 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+
+/// How the UTXO set is walked when restoring or checking the wallet
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScanOptions {
+	/// Number of threads rewinding range proofs
+	pub threads: usize,
+	/// Number of outputs requested from the node per call
+	pub batch_size: u64,
+	/// MMR index the scan starts at
+	pub start_index: u64,
+}
+
+impl ScanOptions {
+	pub fn new(threads: Option<usize>, batch_size: Option<u64>) -> Self {
+		let default = Self::default();
+		Self {
+			threads: threads.unwrap_or(default.threads).max(1),
+			batch_size: batch_size.unwrap_or(default.batch_size).max(1),
+			start_index: default.start_index,
+		}
+	}
+
+	/// Skip the part of the UTXO set before `start_index`
+	pub fn from_index(mut self, start_index: u64) -> Self {
+		self.start_index = start_index.max(1);
+		self
+	}
+}
+
+impl Default for ScanOptions {
+	fn default() -> Self {
+		Self {
+			threads: 4,
+			batch_size: 1000,
+			start_index: 1,
+		}
+	}
+}
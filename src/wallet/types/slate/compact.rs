@@ -0,0 +1,98 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compact text form of a slate: its JSON compressed and base64 encoded
+//! between armor lines, small enough to be passed around as QR codes
+
+use super::VersionedSlate;
+use crate::common::{base64, deflate};
+use crate::wallet::error::ErrorKind;
+
+const HEADER: &str = "BEGINSLATE_COMPACT.";
+const FOOTER: &str = "ENDSLATE_COMPACT.";
+/// Largest slate accepted, so a crafted message can't inflate without bound
+const MAX_SLATE_SIZE: usize = 1024 * 1024;
+
+/// Encode a slate in its compact form
+pub fn encode(slate: &VersionedSlate) -> Result<String, ErrorKind> {
+	let json = serde_json::to_vec(slate).map_err(|_| ErrorKind::Format)?;
+	let body = base64::encode(&deflate::compress(&json));
+	Ok(format!("{}\n{}\n{}", HEADER, body, FOOTER))
+}
+
+/// Whether the text holds a compact slate, whole or as frames
+pub fn is_compact(text: &str) -> bool {
+	let text = text.trim_start();
+	text.starts_with(HEADER) || parse_frame(text.lines().next().unwrap_or("")).is_some()
+}
+
+/// Decode a compact slate, either whole or as the frames it was split into.
+/// Frames may come in any order
+pub fn decode(text: &str) -> Result<VersionedSlate, ErrorKind> {
+	let text = text.trim();
+	let armored = match text.starts_with(HEADER) {
+		true => text.to_owned(),
+		false => join_frames(text)?,
+	};
+	let body = armored
+		.trim()
+		.trim_start_matches(HEADER)
+		.trim_end_matches(FOOTER);
+	let compressed = base64::decode(body).map_err(|_| ErrorKind::SlateDeser)?;
+	let json =
+		deflate::decompress(&compressed, MAX_SLATE_SIZE).map_err(|_| ErrorKind::SlateDeser)?;
+	serde_json::from_slice(&json).map_err(|_| ErrorKind::SlateDeser)
+}
+
+/// Split a compact slate into numbered frames of at most `len` characters
+/// of content, each one shown as a separate QR code
+pub fn frames(compact: &str, len: usize) -> Vec<String> {
+	// Frames are single lines, the armor doesn't need its line breaks
+	let compact: String = compact.split_whitespace().collect();
+	let chunks: Vec<&[u8]> = compact.as_bytes().chunks(len).collect();
+	chunks
+		.iter()
+		.enumerate()
+		.map(|(i, c)| format!("{}/{}:{}", i + 1, chunks.len(), String::from_utf8_lossy(c)))
+		.collect()
+}
+
+/// Position, total and content of a frame
+fn parse_frame(line: &str) -> Option<(usize, usize, &str)> {
+	let colon = line.find(':')?;
+	let mut numbers = line[..colon].splitn(2, '/');
+	let index = numbers.next()?.trim().parse().ok()?;
+	let total = numbers.next()?.trim().parse().ok()?;
+	match index >= 1 && index <= total {
+		true => Some((index, total, &line[colon + 1..])),
+		false => None,
+	}
+}
+
+fn join_frames(text: &str) -> Result<String, ErrorKind> {
+	let mut frames: Vec<(usize, usize, &str)> = text
+		.lines()
+		.filter(|l| !l.trim().is_empty())
+		.map(|l| parse_frame(l.trim()).ok_or(ErrorKind::SlateDeser))
+		.collect::<Result<_, _>>()?;
+	frames.sort_by_key(|f| f.0);
+	frames.dedup_by_key(|f| f.0);
+
+	let total = frames.first().map(|f| f.1).unwrap_or(0);
+	let complete = total > 0 && frames.len() == total && frames.iter().all(|f| f.1 == total);
+	if !complete {
+		return Err(ErrorKind::SlateDeser);
+	}
+	Ok(frames.iter().map(|f| f.2).collect())
+}
@@ -17,6 +17,8 @@
 
 use super::versions::v2::*;
 use super::versions::{CompatKernelFeatures, CURRENT_SLATE_VERSION, GRIN_BLOCK_HEADER_VERSION};
+use crate::common::entropy::Entropy;
+use crate::wallet::types::{PaymentInfo, SlateSigner};
 use crate::wallet::ErrorKind;
 use blake2_rfc::blake2b::blake2b;
 use failure::Error;
@@ -29,13 +31,12 @@ use grin_core::core::verifier_cache::LruVerifierCache;
 use grin_core::libtx::proof::ProofBuild;
 use grin_core::libtx::{aggsig, build, secp_ser, tx_fee};
 use grin_core::map_vec;
-use grin_keychain::{BlindSum, BlindingFactor, Keychain};
+use grin_keychain::{BlindingFactor, Keychain};
 use grin_util::secp::key::{PublicKey, SecretKey};
 use grin_util::secp::pedersen::Commitment;
 use grin_util::secp::{self, Signature};
 use grin_util::RwLock;
 use log::{debug, error, info};
-use rand::thread_rng;
 use serde::{Deserialize, Serialize, Serializer};
 use std::sync::Arc;
 use uuid::Uuid;
@@ -95,7 +96,7 @@ pub struct ParticipantMessageData {
 	pub message_sig: Option<Signature>,
 }
 
-/*impl ParticipantMessageData {
+impl ParticipantMessageData {
 	/// extract relevant message data from participant data
 	pub fn from_participant_data(p: &ParticipantData) -> ParticipantMessageData {
 		ParticipantMessageData {
@@ -105,7 +106,32 @@ pub struct ParticipantMessageData {
 			message_sig: p.message_sig.clone(),
 		}
 	}
-}*/
+
+	/// Whether the message is signed by the participant's public key. There
+	/// is nothing to verify without a message
+	pub fn is_verified(&self) -> bool {
+		let (msg, signature) = match (&self.message, &self.message_sig) {
+			(None, _) => return true,
+			(Some(_), None) => return false,
+			(Some(msg), Some(signature)) => (msg, signature),
+		};
+		let secp = secp::Secp256k1::with_caps(secp::ContextFlag::VerifyOnly);
+		let hashed = blake2b(secp::constants::MESSAGE_SIZE, &[], &msg.as_bytes()[..]);
+		let m = match secp::Message::from_slice(&hashed.as_bytes()) {
+			Ok(m) => m,
+			Err(_) => return false,
+		};
+		aggsig::verify_single(
+			&secp,
+			signature,
+			&m,
+			None,
+			&self.public_key,
+			Some(&self.public_key),
+			false,
+		)
+	}
+}
 
 /// A 'Slate' is passed around to all parties to build up all of the public
 /// transaction data needed to create a finalized transaction. Callers can pass
@@ -139,6 +165,13 @@ pub struct Slate {
 	/// insert their public data here. For now, 0 is sender and 1
 	/// is receiver, though this will change for multi-party
 	pub participant_data: Vec<ParticipantData>,
+	/// Payment proof requested by the sender
+	#[serde(default)]
+	pub payment_proof: Option<PaymentInfo>,
+	/// Amount of each recipient, in participant order, when the transaction
+	/// pays more than one. `amount` is their total
+	#[serde(default)]
+	pub recipient_amounts: Vec<u64>,
 }
 
 /// Versioning and compatibility info about this slate
@@ -161,16 +194,18 @@ pub struct ParticipantMessages {
 
 impl Slate {
 	/// Create a new slate
-	pub fn blank(num_participants: usize) -> Slate {
+	pub fn blank(num_participants: usize, id: Uuid) -> Slate {
 		Slate {
 			num_participants: num_participants,
-			id: Uuid::new_v4(),
+			id,
 			tx: Transaction::empty(),
 			amount: 0,
 			fee: 0,
 			height: 0,
 			lock_height: 0,
 			participant_data: vec![],
+			payment_proof: None,
+			recipient_amounts: vec![],
 			version_info: VersionCompatInfo {
 				version: CURRENT_SLATE_VERSION,
 				orig_version: CURRENT_SLATE_VERSION,
@@ -203,29 +238,20 @@ impl Slate {
 
 	/// Completes callers part of round 1, adding public key info
 	/// to the slate
-	pub fn fill_round_1<K>(
+	pub fn fill_round_1(
 		&mut self,
-		keychain: &K,
+		signer: &dyn SlateSigner,
 		sec_key: &mut SecretKey,
 		sec_nonce: &SecretKey,
 		participant_id: usize,
 		message: Option<String>,
-	) -> Result<(), Error>
-	where
-		K: Keychain,
-	{
+		entropy: &dyn Entropy,
+	) -> Result<(), Error> {
 		// Whoever does this first generates the offset
 		if self.tx.offset == BlindingFactor::zero() {
-			self.generate_offset(keychain, sec_key)?;
+			self.generate_offset(signer, sec_key, entropy)?;
 		}
-		self.add_participant_info(
-			keychain,
-			&sec_key,
-			&sec_nonce,
-			participant_id,
-			None,
-			message,
-		)?;
+		self.add_participant_info(signer, &sec_key, &sec_nonce, participant_id, None, message)?;
 		Ok(())
 	}
 
@@ -247,25 +273,22 @@ impl Slate {
 	}
 
 	/// Completes caller's part of round 2, completing signatures
-	pub fn fill_round_2<K>(
+	pub fn fill_round_2(
 		&mut self,
-		keychain: &K,
+		signer: &dyn SlateSigner,
 		sec_key: &SecretKey,
 		sec_nonce: &SecretKey,
 		participant_id: usize,
-	) -> Result<(), Error>
-	where
-		K: Keychain,
-	{
+	) -> Result<(), Error> {
 		self.check_fees()?;
 
-		self.verify_part_sigs(keychain.secp())?;
-		let sig_part = aggsig::calculate_partial_sig(
-			keychain.secp(),
+		let secp = signer.secp();
+		self.verify_part_sigs(secp)?;
+		let sig_part = signer.partial_sig(
 			sec_key,
 			sec_nonce,
-			&self.pub_nonce_sum(keychain.secp())?,
-			Some(&self.pub_blind_sum(keychain.secp())?),
+			&self.pub_nonce_sum(secp)?,
+			&self.pub_blind_sum(secp)?,
 			&self.msg_to_sign()?,
 		)?;
 		self.participant_data[participant_id].part_sig = Some(sig_part);
@@ -321,30 +344,25 @@ impl Slate {
 	/// and saves participant's transaction context
 	/// sec_key can be overridden to replace the blinding
 	/// factor (by whoever split the offset)
-	fn add_participant_info<K>(
+	fn add_participant_info(
 		&mut self,
-		keychain: &K,
+		signer: &dyn SlateSigner,
 		sec_key: &SecretKey,
 		sec_nonce: &SecretKey,
 		id: usize,
 		part_sig: Option<Signature>,
 		message: Option<String>,
-	) -> Result<(), Error>
-	where
-		K: Keychain,
-	{
+	) -> Result<(), Error> {
 		// Add our public key and nonce to the slate
-		let pub_key = PublicKey::from_secret_key(keychain.secp(), &sec_key)?;
-		let pub_nonce = PublicKey::from_secret_key(keychain.secp(), &sec_nonce)?;
+		let pub_key = signer.public_key(&sec_key)?;
+		let pub_nonce = signer.public_key(&sec_nonce)?;
 
 		// Sign the provided message
 		let message_sig = {
 			if let Some(m) = message.clone() {
 				let hashed = blake2b(secp::constants::MESSAGE_SIZE, &[], &m.as_bytes()[..]);
 				let m = secp::Message::from_slice(&hashed.as_bytes())?;
-				let res =
-					aggsig::sign_single(&keychain.secp(), &m, &sec_key, None, Some(&pub_key))?;
-				Some(res)
+				Some(signer.sign_message(&sec_key, &m)?)
 			} else {
 				None
 			}
@@ -360,7 +378,7 @@ impl Slate {
 		Ok(())
 	}
 
-	/*/// helper to return all participant messages
+	/// helper to return all participant messages
 	pub fn participant_messages(&self) -> ParticipantMessages {
 		let mut ret = ParticipantMessages { messages: vec![] };
 		for ref m in self.participant_data.iter() {
@@ -368,28 +386,37 @@ impl Slate {
 				.push(ParticipantMessageData::from_participant_data(m));
 		}
 		ret
-	}*/
+	}
+
+	/// Amount the recipient with this participant id receives
+	pub fn recipient_amount(&self, participant_id: usize) -> u64 {
+		match self.recipient_amounts.get(participant_id.wrapping_sub(1)) {
+			Some(amount) => *amount,
+			None => self.amount,
+		}
+	}
+
+	/// Whether the transaction pays more than one recipient, which takes
+	/// a second pass over the recipients to collect their signatures
+	pub fn is_multi_recipient(&self) -> bool {
+		self.num_participants > 2
+	}
 
 	/// Somebody involved needs to generate an offset with their private key
 	/// For now, we'll have the transaction initiator be responsible for it
 	/// Return offset private key for the participant to use later in the
 	/// transaction
-	fn generate_offset<K>(&mut self, keychain: &K, sec_key: &mut SecretKey) -> Result<(), Error>
-	where
-		K: Keychain,
-	{
+	fn generate_offset(
+		&mut self,
+		signer: &dyn SlateSigner,
+		sec_key: &mut SecretKey,
+		entropy: &dyn Entropy,
+	) -> Result<(), Error> {
 		// Generate a random kernel offset here
 		// and subtract it from the blind_sum so we create
 		// the aggsig context with the "split" key
-		self.tx.offset =
-			BlindingFactor::from_secret_key(SecretKey::new(&keychain.secp(), &mut thread_rng()));
-
-		let blind_offset = keychain.blind_sum(
-			&BlindSum::new()
-				.add_blinding_factor(BlindingFactor::from_secret_key(sec_key.clone()))
-				.sub_blinding_factor(self.tx.offset.clone()),
-		)?;
-		*sec_key = blind_offset.secret_key(&keychain.secp())?;
+		self.tx.offset = BlindingFactor::from_secret_key(entropy.secret_key(signer.secp()));
+		*sec_key = signer.split_offset(sec_key, &self.tx.offset)?;
 		Ok(())
 	}
 
@@ -424,7 +451,7 @@ impl Slate {
 	}
 
 	/// Verifies all of the partial signatures in the Slate are valid
-	fn verify_part_sigs(&self, secp: &secp::Secp256k1) -> Result<(), Error> {
+	pub fn verify_part_sigs(&self, secp: &secp::Secp256k1) -> Result<(), Error> {
 		// collect public nonces
 		for p in self.participant_data.iter() {
 			if p.is_complete() {
@@ -624,6 +651,8 @@ impl From<Slate> for SlateV2 {
 			lock_height,
 			participant_data,
 			version_info,
+			payment_proof,
+			recipient_amounts,
 		} = slate;
 		let participant_data = map_vec!(participant_data, |data| ParticipantDataV2::from(data));
 		let version_info = VersionCompatInfoV2::from(&version_info);
@@ -638,6 +667,8 @@ impl From<Slate> for SlateV2 {
 			lock_height,
 			participant_data,
 			version_info,
+			payment_proof,
+			recipient_amounts,
 		}
 	}
 }
@@ -654,6 +685,8 @@ impl From<&Slate> for SlateV2 {
 			lock_height,
 			participant_data,
 			version_info,
+			payment_proof,
+			recipient_amounts,
 		} = slate;
 		let num_participants = *num_participants;
 		let id = *id;
@@ -664,6 +697,8 @@ impl From<&Slate> for SlateV2 {
 		let lock_height = *lock_height;
 		let participant_data = map_vec!(participant_data, |data| ParticipantDataV2::from(data));
 		let version_info = VersionCompatInfoV2::from(version_info);
+		let payment_proof = payment_proof.clone();
+		let recipient_amounts = recipient_amounts.clone();
 		SlateV2 {
 			num_participants,
 			id,
@@ -674,6 +709,8 @@ impl From<&Slate> for SlateV2 {
 			lock_height,
 			participant_data,
 			version_info,
+			payment_proof,
+			recipient_amounts,
 		}
 	}
 }
@@ -818,6 +855,8 @@ impl From<SlateV2> for Slate {
 			lock_height,
 			participant_data,
 			version_info,
+			payment_proof,
+			recipient_amounts,
 		} = slate;
 		let participant_data = map_vec!(participant_data, |data| ParticipantData::from(data));
 		let version_info = VersionCompatInfo::from(&version_info);
@@ -832,6 +871,8 @@ impl From<SlateV2> for Slate {
 			lock_height,
 			participant_data,
 			version_info,
+			payment_proof,
+			recipient_amounts,
 		}
 	}
 }
@@ -848,6 +889,8 @@ impl From<&SlateV2> for Slate {
 			lock_height,
 			participant_data,
 			version_info,
+			payment_proof,
+			recipient_amounts,
 		} = slate;
 		let num_participants = *num_participants;
 		let id = id.clone();
@@ -858,6 +901,8 @@ impl From<&SlateV2> for Slate {
 		let lock_height = *lock_height;
 		let participant_data = map_vec!(participant_data, |data| ParticipantData::from(data));
 		let version_info = VersionCompatInfo::from(version_info);
+		let payment_proof = payment_proof.clone();
+		let recipient_amounts = recipient_amounts.clone();
 
 		Slate {
 			num_participants,
@@ -869,6 +914,8 @@ impl From<&SlateV2> for Slate {
 			lock_height,
 			participant_data,
 			version_info,
+			payment_proof,
+			recipient_amounts,
 		}
 	}
 }
@@ -36,6 +36,7 @@
 //!    block_header_version: u16
 
 use super::CompatKernelFeatures;
+use crate::wallet::types::PaymentInfo;
 use grin_core::core::transaction::OutputFeatures;
 use grin_core::libtx::secp_ser;
 use grin_keychain::BlindingFactor;
@@ -72,6 +73,14 @@ pub struct SlateV2 {
 	/// insert their public data here. For now, 0 is sender and 1
 	/// is receiver, though this will change for multi-party
 	pub participant_data: Vec<ParticipantDataV2>,
+	/// Payment proof requested by the sender, not part of the
+	/// original V2 format so it's omitted when absent
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub payment_proof: Option<PaymentInfo>,
+	/// Amount of each recipient of a transaction paying several, also
+	/// omitted when empty
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub recipient_amounts: Vec<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
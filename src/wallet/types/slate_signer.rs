@@ -0,0 +1,104 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use failure::Error;
+use grin_core::libtx::aggsig;
+use grin_keychain::{BlindSum, BlindingFactor, ExtKeychain, Keychain};
+use grin_util::secp::key::{PublicKey, SecretKey};
+use grin_util::secp::{Message, Secp256k1, Signature};
+
+/// Secret key operations of signing a slate. Keeping them behind this trait
+/// allows them to be moved to a device that never hands out its keys, such
+/// as a hardware wallet
+pub trait SlateSigner {
+	fn secp(&self) -> &Secp256k1;
+	/// Public key of a blinding factor or nonce
+	fn public_key(&self, secret: &SecretKey) -> Result<PublicKey, Error>;
+	/// New secret nonce for the kernel signature
+	fn create_nonce(&self) -> Result<SecretKey, Error>;
+	/// Blinding factor minus the kernel offset
+	fn split_offset(
+		&self,
+		sec_key: &SecretKey,
+		offset: &BlindingFactor,
+	) -> Result<SecretKey, Error>;
+	/// Partial signature of the kernel message
+	fn partial_sig(
+		&self,
+		sec_key: &SecretKey,
+		sec_nonce: &SecretKey,
+		pub_nonce_sum: &PublicKey,
+		pub_blind_sum: &PublicKey,
+		msg: &Message,
+	) -> Result<Signature, Error>;
+	/// Signature of a participant message with the blinding factor
+	fn sign_message(&self, sec_key: &SecretKey, msg: &Message) -> Result<Signature, Error>;
+}
+
+impl SlateSigner for ExtKeychain {
+	fn secp(&self) -> &Secp256k1 {
+		Keychain::secp(self)
+	}
+
+	fn public_key(&self, secret: &SecretKey) -> Result<PublicKey, Error> {
+		Ok(PublicKey::from_secret_key(Keychain::secp(self), secret)?)
+	}
+
+	fn create_nonce(&self) -> Result<SecretKey, Error> {
+		Ok(aggsig::create_secnonce(Keychain::secp(self))?)
+	}
+
+	fn split_offset(
+		&self,
+		sec_key: &SecretKey,
+		offset: &BlindingFactor,
+	) -> Result<SecretKey, Error> {
+		let blind = self.blind_sum(
+			&BlindSum::new()
+				.add_blinding_factor(BlindingFactor::from_secret_key(sec_key.clone()))
+				.sub_blinding_factor(offset.clone()),
+		)?;
+		Ok(blind.secret_key(Keychain::secp(self))?)
+	}
+
+	fn partial_sig(
+		&self,
+		sec_key: &SecretKey,
+		sec_nonce: &SecretKey,
+		pub_nonce_sum: &PublicKey,
+		pub_blind_sum: &PublicKey,
+		msg: &Message,
+	) -> Result<Signature, Error> {
+		Ok(aggsig::calculate_partial_sig(
+			Keychain::secp(self),
+			sec_key,
+			sec_nonce,
+			pub_nonce_sum,
+			Some(pub_blind_sum),
+			msg,
+		)?)
+	}
+
+	fn sign_message(&self, sec_key: &SecretKey, msg: &Message) -> Result<Signature, Error> {
+		let secp = Keychain::secp(self);
+		let pub_key = PublicKey::from_secret_key(secp, sec_key)?;
+		Ok(aggsig::sign_single(
+			secp,
+			msg,
+			sec_key,
+			None,
+			Some(&pub_key),
+		)?)
+	}
+}
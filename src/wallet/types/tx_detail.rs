@@ -0,0 +1,62 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{ParticipantMessageData, SlateVersion, TxLogEntry};
+use grin_util::secp::pedersen::Commitment;
+use serde::{Deserialize, Serialize};
+
+/// Message a participant attached to the slate
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TxMessage {
+	pub participant_id: u64,
+	pub message: Option<String>,
+	/// Whether the message is signed by the participant
+	pub verified: bool,
+}
+
+impl From<&ParticipantMessageData> for TxMessage {
+	fn from(data: &ParticipantMessageData) -> Self {
+		Self {
+			participant_id: data.id,
+			message: data.message.clone(),
+			verified: data.is_verified(),
+		}
+	}
+}
+
+/// Copy of a slate of the transaction kept by the wallet
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StoredSlate {
+	/// Where the slate is kept, `outbox` or `proof`
+	pub source: String,
+	pub version: SlateVersion,
+}
+
+/// Everything the wallet knows about a single transaction
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TxDetail {
+	pub tx: TxLogEntry,
+	pub messages: Vec<TxMessage>,
+	/// Public kernel excess
+	pub excess: Option<Commitment>,
+	/// Input commitments of the stored transaction, or of the proof
+	pub inputs: Vec<Commitment>,
+	/// Output commitments of the stored transaction, or of the proof
+	pub outputs: Vec<Commitment>,
+	/// Whether the finalized transaction is stored
+	pub stored_tx: bool,
+	/// Whether a grinbox transaction proof is stored
+	pub tx_proof: bool,
+	pub slates: Vec<StoredSlate>,
+}
@@ -0,0 +1,190 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{OutputData, OutputStatus, TxLogEntry, TxLogEntryType};
+use crate::common::crypto::Hex;
+use crate::common::{ErrorKind, Result};
+use chrono::{DateTime, Utc};
+use grin_core::core::amount_to_hr_string;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// File format of an exported transaction history
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TxExportFormat {
+	Json,
+	Csv,
+}
+
+impl TxExportFormat {
+	/// Guess the format from the file extension, defaulting to CSV
+	pub fn from_path(path: &str) -> Self {
+		match Path::new(path).extension().and_then(|e| e.to_str()) {
+			Some(e) if e.eq_ignore_ascii_case("json") => TxExportFormat::Json,
+			_ => TxExportFormat::Csv,
+		}
+	}
+
+	pub fn mime(&self) -> mime::Mime {
+		match self {
+			TxExportFormat::Json => mime::APPLICATION_JSON,
+			TxExportFormat::Csv => mime::TEXT_CSV,
+		}
+	}
+
+	pub fn serialize(&self, entries: &[TxExportEntry]) -> Result<String> {
+		Ok(match self {
+			TxExportFormat::Json => serde_json::to_string_pretty(entries)?,
+			TxExportFormat::Csv => {
+				let mut data = String::from(
+					"id,tx_slate_id,tx_type,address,creation_ts,confirmed,confirmation_ts,\
+					 confirmation_height,confirmation_hash,num_inputs,num_outputs,amount_credited,\
+					 amount_credited_grin,amount_debited,amount_debited_grin,fee,fee_grin,\
+					 kernel_excess,label,notes\n",
+				);
+				let opt = |v: Option<String>| v.unwrap_or_default();
+				for e in entries {
+					data.push_str(&format!(
+						"{},{},{:?},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+						e.id,
+						opt(e.tx_slate_id.map(|id| id.to_string())),
+						e.tx_type,
+						csv_escape(e.address.as_ref().map(|a| a.as_str()).unwrap_or("")),
+						e.creation_ts.to_rfc3339(),
+						e.confirmed,
+						opt(e.confirmation_ts.map(|t| t.to_rfc3339())),
+						opt(e.confirmation_height.map(|h| h.to_string())),
+						opt(e.confirmation_hash.clone()),
+						e.num_inputs,
+						e.num_outputs,
+						e.amount_credited,
+						e.amount_credited_grin,
+						e.amount_debited,
+						e.amount_debited_grin,
+						opt(e.fee.map(|f| f.to_string())),
+						opt(e.fee_grin.clone()),
+						opt(e.kernel_excess.clone()),
+						csv_escape(e.label.as_ref().map(|l| l.as_str()).unwrap_or("")),
+						csv_escape(&notes(&e.notes)),
+					));
+				}
+				data
+			}
+		})
+	}
+}
+
+impl FromStr for TxExportFormat {
+	type Err = ErrorKind;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"json" => Ok(TxExportFormat::Json),
+			"csv" => Ok(TxExportFormat::Csv),
+			_ => Err(ErrorKind::UnknownExportFormat(s.to_owned())),
+		}
+	}
+}
+
+/// Flattened transaction log entry, for consumption by accounting tools.
+/// Amounts are given both in nanogrin and as a decimal grin string
+#[derive(Clone, Debug, Serialize)]
+pub struct TxExportEntry {
+	pub id: u32,
+	pub tx_slate_id: Option<Uuid>,
+	pub tx_type: TxLogEntryType,
+	pub address: Option<String>,
+	pub creation_ts: DateTime<Utc>,
+	pub confirmed: bool,
+	pub confirmation_ts: Option<DateTime<Utc>>,
+	/// Height of the block the transaction was mined in, when known
+	pub confirmation_height: Option<u64>,
+	/// Hash of that block, when known
+	pub confirmation_hash: Option<String>,
+	pub num_inputs: usize,
+	pub num_outputs: usize,
+	pub amount_credited: u64,
+	pub amount_credited_grin: String,
+	pub amount_debited: u64,
+	pub amount_debited_grin: String,
+	pub fee: Option<u64>,
+	pub fee_grin: Option<String>,
+	pub kernel_excess: Option<String>,
+	pub label: Option<String>,
+	pub notes: BTreeMap<String, String>,
+}
+
+impl TxExportEntry {
+	/// Build the export of a transaction. Transactions confirmed before
+	/// their confirmation height was recorded take it from the confirmed
+	/// outputs they created
+	pub fn new(tx: &TxLogEntry, outputs: &[OutputData]) -> Self {
+		let confirmation_height = if tx.confirmation_height.is_some() {
+			tx.confirmation_height
+		} else if tx.confirmed {
+			outputs
+				.iter()
+				.filter(|o| o.root_key_id == tx.parent_key_id)
+				.filter(|o| o.tx_log_entry == Some(tx.id))
+				.filter(|o| o.status != OutputStatus::Unconfirmed)
+				.map(|o| o.height)
+				.max()
+		} else {
+			None
+		};
+
+		Self {
+			id: tx.id,
+			tx_slate_id: tx.tx_slate_id,
+			tx_type: tx.tx_type.clone(),
+			address: tx.address.clone(),
+			creation_ts: tx.creation_ts,
+			confirmed: tx.confirmed,
+			confirmation_ts: tx.confirmation_ts,
+			confirmation_height,
+			confirmation_hash: tx.confirmation_hash.clone(),
+			num_inputs: tx.num_inputs,
+			num_outputs: tx.num_outputs,
+			amount_credited: tx.amount_credited,
+			amount_credited_grin: amount_to_hr_string(tx.amount_credited, false),
+			amount_debited: tx.amount_debited,
+			amount_debited_grin: amount_to_hr_string(tx.amount_debited, false),
+			fee: tx.fee,
+			fee_grin: tx.fee.map(|f| amount_to_hr_string(f, false)),
+			kernel_excess: tx.excess.as_ref().map(|e| e.to_hex()),
+			label: tx.label.clone(),
+			notes: tx.notes.clone(),
+		}
+	}
+}
+
+/// Notes as a single field, `key=value` pairs separated by semicolons
+fn notes(notes: &BTreeMap<String, String>) -> String {
+	notes
+		.iter()
+		.map(|(k, v)| format!("{}={}", k, v))
+		.collect::<Vec<String>>()
+		.join(";")
+}
+
+fn csv_escape(field: &str) -> String {
+	if field.contains(|c: char| c == ',' || c == '"' || c == '\n' || c == '\r') {
+		format!("\"{}\"", field.replace("\"", "\"\""))
+	} else {
+		field.to_owned()
+	}
+}
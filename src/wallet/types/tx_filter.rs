@@ -0,0 +1,125 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{Amount, TxLogEntry, TxLogEntryType};
+use crate::wallet::ErrorKind;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Direction of a transaction, ignoring whether it was cancelled
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxDirection {
+	Sent,
+	Received,
+	Coinbase,
+}
+
+impl TxDirection {
+	fn matches(&self, tx_type: &TxLogEntryType) -> bool {
+		match self {
+			TxDirection::Sent => match tx_type {
+				TxLogEntryType::TxSent | TxLogEntryType::TxSentCancelled => true,
+				_ => false,
+			},
+			TxDirection::Received => match tx_type {
+				TxLogEntryType::TxReceived | TxLogEntryType::TxReceivedCancelled => true,
+				_ => false,
+			},
+			TxDirection::Coinbase => *tx_type == TxLogEntryType::ConfirmedCoinbase,
+		}
+	}
+}
+
+impl FromStr for TxDirection {
+	type Err = ErrorKind;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_lowercase().as_str() {
+			"sent" => Ok(TxDirection::Sent),
+			"received" => Ok(TxDirection::Received),
+			"coinbase" => Ok(TxDirection::Coinbase),
+			_ => Err(ErrorKind::UnknownTxDirection(s.to_owned())),
+		}
+	}
+}
+
+/// Criteria to select transaction log entries. All criteria that are set
+/// have to match, unset criteria match every entry
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TxFilter {
+	/// Text contained in the label, a note, the address or the slate id,
+	/// ignoring case
+	pub search: Option<String>,
+	/// Text contained in the address of the other party
+	pub address: Option<String>,
+	/// Name of a contact. Resolved to its address by the owner API, ignored
+	/// by `matches`
+	pub contact: Option<String>,
+	/// Minimum amount moved by the transaction, excluding the fee
+	pub min_amount: Option<Amount>,
+	/// Only entries created at or after this time
+	pub since: Option<DateTime<Utc>>,
+	/// Only entries created before this time
+	pub until: Option<DateTime<Utc>>,
+	pub direction: Option<TxDirection>,
+	/// Only entries that are not confirmed yet
+	pub unconfirmed: bool,
+}
+
+impl TxFilter {
+	pub fn matches(&self, tx: &TxLogEntry) -> bool {
+		let contains = |field: Option<String>, text: &str| {
+			field
+				.map(|f| f.to_lowercase().contains(&text.to_lowercase()))
+				.unwrap_or(false)
+		};
+
+		if let Some(search) = &self.search {
+			let found = contains(tx.label.clone(), search)
+				|| tx.notes.values().any(|n| contains(Some(n.clone()), search))
+				|| contains(tx.address.clone(), search)
+				|| contains(tx.tx_slate_id.map(|id| id.to_string()), search);
+			if !found {
+				return false;
+			}
+		}
+		if let Some(address) = &self.address {
+			if !contains(tx.address.clone(), address) {
+				return false;
+			}
+		}
+		if let Some(min_amount) = self.min_amount {
+			let amount =
+				tx.amount_credited as i64 - tx.amount_debited as i64 + tx.fee.unwrap_or(0) as i64;
+			if amount.abs() < min_amount.nano() as i64 {
+				return false;
+			}
+		}
+		if self.since.map(|s| tx.creation_ts < s) == Some(true) {
+			return false;
+		}
+		if self.until.map(|u| tx.creation_ts >= u) == Some(true) {
+			return false;
+		}
+		if let Some(direction) = &self.direction {
+			if !direction.matches(&tx.tx_type) {
+				return false;
+			}
+		}
+		!(self.unconfirmed && tx.confirmed)
+	}
+}
@@ -12,11 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use super::{Identifier, TxLogEntryType};
+use super::{Identifier, ParticipantMessages, PaymentInfo, TxLogEntryType};
 use chrono::prelude::*;
 use grin_core::ser;
 use grin_util::secp::pedersen::Commitment;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 /// Optional transaction information, recorded when an event happens
@@ -35,12 +36,21 @@ pub struct TxLogEntry {
 	/// Address of the other party
 	#[serde(default)]
 	pub address: Option<String>,
+	/// Address book name of the other party when the entry was created
+	#[serde(default)]
+	pub contact: Option<String>,
 	/// Time this tx entry was created
 	/// #[serde(with = "tx_date_format")]
 	pub creation_ts: DateTime<Utc>,
 	/// Time this tx was confirmed (by this wallet)
 	/// #[serde(default, with = "opt_tx_date_format")]
 	pub confirmation_ts: Option<DateTime<Utc>>,
+	/// Height of the block the transaction was confirmed in
+	#[serde(default)]
+	pub confirmation_height: Option<u64>,
+	/// Hash of that block, hex encoded, when the node gave it
+	#[serde(default)]
+	pub confirmation_hash: Option<String>,
 	/// Whether the inputs+outputs involved in this transaction have been
 	/// confirmed (In all cases either all outputs involved in a tx should be
 	/// confirmed, or none should be; otherwise there's a deeper problem)
@@ -57,21 +67,56 @@ pub struct TxLogEntry {
 	pub fee: Option<u64>,
 	/// Public kernel excess
 	pub excess: Option<Commitment>,
+	/// Messages of the participants, with their signatures
+	#[serde(default)]
+	pub messages: Option<ParticipantMessages>,
 	/// Location of the store transaction, (reference or resending)
 	pub stored_tx: Option<String>,
+	/// Payment proof requested from the recipient
+	#[serde(default)]
+	pub payment_proof: Option<PaymentInfo>,
+	/// Chain height when the transaction was created
+	#[serde(default)]
+	pub creation_height: Option<u64>,
+	/// Chain height at which the transaction was last posted to the node
+	#[serde(default)]
+	pub posted_height: Option<u64>,
+	/// Number of automatic reposts so far
+	#[serde(default)]
+	pub repost_attempts: u32,
+	/// Account holding the other entry of a transaction whose fee was paid
+	/// from a separate account
+	#[serde(default)]
+	pub linked_parent_key_id: Option<Identifier>,
+	/// Free form label set by the user
+	#[serde(default)]
+	pub label: Option<String>,
+	/// Private key/value annotations for internal bookkeeping. Stored only
+	/// in the wallet, never sent to the other party
+	#[serde(default)]
+	pub notes: BTreeMap<String, String>,
+	/// Cancelled transaction this one re-issued with a higher fee
+	#[serde(default)]
+	pub replaces: Option<u32>,
+	/// Transaction that re-issued this one with a higher fee
+	#[serde(default)]
+	pub replaced_by: Option<u32>,
 }
 
 impl TxLogEntry {
-	/// Return a new blank with TS initialised with next entry
-	pub fn new(parent_key_id: Identifier, t: TxLogEntryType, id: u32) -> Self {
+	/// Return a new blank created at `now`
+	pub fn new(parent_key_id: Identifier, t: TxLogEntryType, id: u32, now: DateTime<Utc>) -> Self {
 		TxLogEntry {
 			parent_key_id: parent_key_id,
 			tx_type: t,
 			id: id,
 			address: None,
+			contact: None,
 			tx_slate_id: None,
-			creation_ts: Utc::now(),
+			creation_ts: now,
 			confirmation_ts: None,
+			confirmation_height: None,
+			confirmation_hash: None,
 			confirmed: false,
 			amount_credited: 0,
 			amount_debited: 0,
@@ -79,13 +124,23 @@ impl TxLogEntry {
 			num_outputs: 0,
 			fee: None,
 			excess: None,
+			messages: None,
 			stored_tx: None,
+			payment_proof: None,
+			creation_height: None,
+			posted_height: None,
+			repost_attempts: 0,
+			linked_parent_key_id: None,
+			label: None,
+			notes: BTreeMap::new(),
+			replaces: None,
+			replaced_by: None,
 		}
 	}
 
 	/// Update confirmation TS with now
-	pub fn update_confirmation_ts(&mut self) {
-		self.confirmation_ts = Some(Utc::now());
+	pub fn update_confirmation_ts(&mut self, now: DateTime<Utc>) {
+		self.confirmation_ts = Some(now);
 	}
 }
 
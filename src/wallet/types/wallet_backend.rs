@@ -13,10 +13,16 @@
 // limitations under the License.
 
 use super::{
-	AcctPathMapping, Context, Identifier, Keychain, NodeClient, OutputData, Result, Transaction,
-	TxLogEntry, TxProof, WalletBackendBatch,
+	AccountLabeler, AcctPathMapping, BlockIdentifier, Context, DailySpend, DiscoveredAccount,
+	Identifier, IncomingSlate, Keychain, MultisigOutput, NodeClient, OutputData, OutputStatus,
+	QueuedSlate, ReceivedMessage, Result, SharedEventBus, SlateSigner, Transaction, TxLogEntry,
+	TxProof, WalletBackendBatch,
 };
+use crate::common::clock::SharedClock;
+use crate::common::entropy::SharedEntropy;
+use chrono::NaiveDate;
 use grin_util::ZeroingString;
+use uuid::Uuid;
 
 pub trait WalletBackend<C, K>: Send + 'static
 where
@@ -44,20 +50,53 @@ where
 	fn disconnect(&mut self) -> Result<()>;
 	/// Set password
 	fn set_password(&mut self, password: ZeroingString) -> Result<()>;
+	/// Re-encrypt the seed with a new password
+	fn change_password(
+		&mut self,
+		old_password: ZeroingString,
+		new_password: ZeroingString,
+	) -> Result<()>;
 	/// Clear out backend
 	fn clear(&mut self) -> Result<()>;
 
 	fn open_with_credentials(&mut self) -> Result<()>;
 	fn close(&mut self) -> Result<()>;
-	fn restore(&mut self) -> Result<()>;
-	fn check_repair(&mut self, delete_unconfirmed: bool) -> Result<()>;
+	/// Forget the password along with the keychain, until `set_password`
+	/// is called again
+	fn lock(&mut self) -> Result<()>;
+	/// Whether there's no password to open the wallet with
+	fn is_locked(&self) -> bool;
+	/// Restore the wallet from the chain, labelling the accounts found with `labeler`
+	fn restore(&mut self, labeler: Option<&AccountLabeler>) -> Result<Vec<DiscoveredAccount>>;
+	/// Check the outputs from `start_index` on against the UTXO set, or all
+	/// of them when not set
+	fn check_repair(&mut self, delete_unconfirmed: bool, start_index: Option<u64>) -> Result<()>;
 	fn get_parent_key_id(&self) -> Identifier;
 	fn set_parent_key_id(&mut self, id: &Identifier);
 	fn set_parent_key_id_by_name(&mut self, label: &str) -> Result<()>;
 	fn w2n_client(&mut self) -> &mut C;
 	fn calc_commit_for_cache(&mut self, amount: u64, id: &Identifier) -> Result<Option<String>>;
+	/// Whether inputs are selected to keep the number of outputs low, rather
+	/// than smallest first
+	fn adaptive_selection(&self) -> bool;
+	/// Smallest output value, in nanogrin, created as change or accepted on receive
+	fn min_output_value(&self) -> u64;
+	/// Fee per unit of transaction weight, `None` for grin's default
+	fn fee_base(&self) -> Option<u64>;
+	/// Whether received transactions get one of our outputs as an input
+	fn payjoin(&self) -> bool;
 	fn keychain(&mut self) -> &mut K;
+	/// Signs slates with the keys of the wallet
+	fn signer(&self) -> &dyn SlateSigner;
+	/// Clock used for timestamps, schedules and file names
+	fn clock(&self) -> SharedClock;
+	/// Randomness used for seeds, slate ids, offsets and encryption
+	fn entropy(&self) -> SharedEntropy;
+	/// Bus wallet events are published on
+	fn events(&self) -> SharedEventBus;
 	fn next_child(&mut self) -> Result<Identifier>;
+	/// Child index the next call to `next_child` hands out for the account
+	fn child_index(&self, parent_key_id: &Identifier) -> Result<u32>;
 	fn get_output(&self, id: &Identifier, mmr_index: &Option<u64>) -> Result<OutputData>;
 	fn get_private_context(&mut self, slate_id: &[u8], participant_id: usize) -> Result<Context>;
 	fn get_acct_path(&self, label: &str) -> Result<Option<AcctPathMapping>>;
@@ -67,7 +106,35 @@ where
 	fn get_stored_tx_proof(&self, uuid: &str) -> Result<Option<TxProof>>;
 	fn get_tx_log_by_slate_id(&self, slate_id: &str) -> Result<Option<TxLogEntry>>;
 	fn outputs<'a>(&'a self) -> Result<Box<dyn Iterator<Item = OutputData> + 'a>>;
+	/// Outputs of a single account, restricted to `statuses` unless empty.
+	/// Only the key range of the account is read, and outputs are yielded
+	/// one at a time rather than collected
+	fn account_outputs<'a>(
+		&'a self,
+		parent_key_id: &Identifier,
+		statuses: &[OutputStatus],
+	) -> Result<Box<dyn Iterator<Item = OutputData> + 'a>>;
 	fn tx_logs<'a>(&'a self) -> Result<Box<dyn Iterator<Item = TxLogEntry> + 'a>>;
+	/// Slate queued for another delivery attempt, see `QueuedSlate`
+	fn get_queued_slate(&self, slate_id: &Uuid) -> Result<Option<QueuedSlate>>;
+	/// All queued slates, of every account
+	fn queued_slates<'a>(&'a self) -> Result<Box<dyn Iterator<Item = QueuedSlate> + 'a>>;
+	/// Slate held for approval, see `IncomingSlate`
+	fn get_incoming_slate(&self, slate_id: &Uuid) -> Result<Option<IncomingSlate>>;
+	/// All held and rejected slates
+	fn incoming_slates<'a>(&'a self) -> Result<Box<dyn Iterator<Item = IncomingSlate> + 'a>>;
+	/// Message read from a polled transport, see `ReceivedMessage`
+	fn get_received_message(&self, method: &str, id: &str) -> Result<Option<ReceivedMessage>>;
+	/// All received messages, of every transport
+	fn received_messages<'a>(&'a self) -> Result<Box<dyn Iterator<Item = ReceivedMessage> + 'a>>;
+	/// Our side of a 2-of-2 output, see `MultisigOutput`
+	fn get_multisig_output(&self, id: &Uuid) -> Result<Option<MultisigOutput>>;
+	/// All multisig outputs, of every account
+	fn multisig_outputs<'a>(&'a self) -> Result<Box<dyn Iterator<Item = MultisigOutput> + 'a>>;
+	/// Amount sent on a day, see `DailySpend`
+	fn get_daily_spend(&self, day: &NaiveDate) -> Result<Option<DailySpend>>;
+	/// Blocks seen at checkpoint heights, lowest first, see `BlockIdentifier`
+	fn checkpoints<'a>(&'a self) -> Result<Box<dyn Iterator<Item = BlockIdentifier> + 'a>>;
 	fn accounts<'a>(&'a self) -> Result<Box<dyn Iterator<Item = AcctPathMapping> + 'a>>;
 	fn batch<'a>(&'a self) -> Result<Box<dyn WalletBackendBatch<K> + 'a>>;
 }
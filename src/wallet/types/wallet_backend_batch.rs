@@ -13,9 +13,11 @@
 // limitations under the License.
 
 use super::{
-	AcctPathMapping, Context, Identifier, Keychain, OutputData, Result, Transaction, TxLogEntry,
+	AcctPathMapping, BlockIdentifier, Context, DailySpend, Identifier, IncomingSlate, Keychain,
+	MultisigOutput, OutputData, QueuedSlate, ReceivedMessage, Result, Transaction, TxLogEntry,
 };
 use crate::wallet::types::TxProof;
+use uuid::Uuid;
 
 pub trait WalletBackendBatch<K>
 where
@@ -30,6 +32,7 @@ where
 	fn next_tx_log_id(&mut self, parent_key_id: &Identifier) -> Result<u32>;
 	fn save_tx_log_entry(&mut self, t: &TxLogEntry) -> Result<()>;
 	fn save_acct_path(&mut self, mapping: &AcctPathMapping) -> Result<()>;
+	fn delete_acct_path(&mut self, label: &str) -> Result<()>;
 	fn save_private_context(
 		&mut self,
 		slate_id: &[u8],
@@ -37,7 +40,19 @@ where
 		ctx: &Context,
 	) -> Result<()>;
 	fn delete_private_context(&mut self, slate_id: &[u8], participant_id: usize) -> Result<()>;
+	fn save_queued_slate(&mut self, queued: &QueuedSlate) -> Result<()>;
+	fn delete_queued_slate(&mut self, slate_id: &Uuid) -> Result<()>;
+	fn save_incoming_slate(&mut self, incoming: &IncomingSlate) -> Result<()>;
+	fn delete_incoming_slate(&mut self, slate_id: &Uuid) -> Result<()>;
+	fn save_received_message(&mut self, message: &ReceivedMessage) -> Result<()>;
+	fn delete_received_message(&mut self, method: &str, id: &str) -> Result<()>;
+	fn save_multisig_output(&mut self, output: &MultisigOutput) -> Result<()>;
+	fn save_daily_spend(&mut self, spend: &DailySpend) -> Result<()>;
+	fn save_checkpoint(&mut self, block: &BlockIdentifier) -> Result<()>;
+	fn delete_checkpoint(&mut self, height: u64) -> Result<()>;
 	fn store_tx(&self, uuid: &str, tx: &Transaction) -> Result<()>;
 	fn store_tx_proof(&self, uuid: &str, tx_proof: &TxProof) -> Result<()>;
+	fn delete_stored_tx(&self, uuid: &str) -> Result<()>;
+	fn delete_stored_tx_proof(&self, uuid: &str) -> Result<()>;
 	fn commit(&mut self) -> Result<()>;
 }
@@ -0,0 +1,247 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Wallet records in the layout grin-wallet stores them in, for moving a
+//! wallet between the two. Fields grin-wallet doesn't know are left out
+
+use super::{
+	Context, Identifier, OutputData, OutputStatus, ParticipantMessages, SecretKey, TxLogEntry,
+	TxLogEntryType,
+};
+use chrono::{DateTime, Utc};
+use grin_core::libtx::secp_ser;
+use grin_util::secp::pedersen::Commitment;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Accounts, as `AcctPathMapping`
+pub const ACCT_PATHS_FILE: &str = "acct_paths.json";
+pub const OUTPUTS_FILE: &str = "outputs.json";
+pub const TX_LOG_FILE: &str = "tx_log.json";
+/// Private contexts of unfinished transactions, keys in the clear
+pub const CONTEXTS_FILE: &str = "contexts.json";
+/// Finalized transactions, `<slate id>.grintx`
+pub const SAVED_TXS_DIR: &str = "saved_txs";
+/// Grinbox transaction proofs, `<slate id>.proof`. Only read by wallet713
+pub const SAVED_PROOFS_DIR: &str = "saved_proofs";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GrinOutputData {
+	pub root_key_id: Identifier,
+	pub key_id: Identifier,
+	pub n_child: u32,
+	pub commit: Option<String>,
+	#[serde(with = "secp_ser::opt_string_or_u64")]
+	pub mmr_index: Option<u64>,
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub value: u64,
+	pub status: OutputStatus,
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub height: u64,
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub lock_height: u64,
+	pub is_coinbase: bool,
+	pub tx_log_entry: Option<u32>,
+}
+
+impl From<&OutputData> for GrinOutputData {
+	fn from(o: &OutputData) -> Self {
+		Self {
+			root_key_id: o.root_key_id.clone(),
+			key_id: o.key_id.clone(),
+			n_child: o.n_child,
+			commit: o.commit.clone(),
+			mmr_index: o.mmr_index,
+			value: o.value,
+			status: o.status.clone(),
+			height: o.height,
+			lock_height: o.lock_height,
+			is_coinbase: o.is_coinbase,
+			tx_log_entry: o.tx_log_entry,
+		}
+	}
+}
+
+impl From<GrinOutputData> for OutputData {
+	fn from(o: GrinOutputData) -> Self {
+		Self {
+			root_key_id: o.root_key_id,
+			key_id: o.key_id,
+			n_child: o.n_child,
+			commit: o.commit,
+			mmr_index: o.mmr_index,
+			value: o.value,
+			status: o.status,
+			height: o.height,
+			lock_height: o.lock_height,
+			is_coinbase: o.is_coinbase,
+			tx_log_entry: o.tx_log_entry,
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GrinTxLogEntry {
+	pub parent_key_id: Identifier,
+	pub id: u32,
+	pub tx_slate_id: Option<Uuid>,
+	pub tx_type: TxLogEntryType,
+	pub creation_ts: DateTime<Utc>,
+	pub confirmation_ts: Option<DateTime<Utc>>,
+	pub confirmed: bool,
+	pub num_inputs: usize,
+	pub num_outputs: usize,
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub amount_credited: u64,
+	#[serde(with = "secp_ser::string_or_u64")]
+	pub amount_debited: u64,
+	#[serde(with = "secp_ser::opt_string_or_u64")]
+	pub fee: Option<u64>,
+	pub messages: Option<ParticipantMessages>,
+	pub stored_tx: Option<String>,
+	#[serde(default, with = "secp_ser::option_commitment_serde")]
+	pub kernel_excess: Option<Commitment>,
+	/// Height to start looking for the kernel at, the height the
+	/// transaction was created at
+	#[serde(default, with = "secp_ser::opt_string_or_u64")]
+	pub kernel_lookup_min_height: Option<u64>,
+	/// Payment proofs of grin-wallet are signed with ed25519 keys, which
+	/// wallet713 doesn't use. Read and dropped, never written
+	#[serde(default)]
+	pub payment_proof: Option<Value>,
+}
+
+impl From<&TxLogEntry> for GrinTxLogEntry {
+	fn from(t: &TxLogEntry) -> Self {
+		Self {
+			parent_key_id: t.parent_key_id.clone(),
+			id: t.id,
+			tx_slate_id: t.tx_slate_id,
+			tx_type: t.tx_type.clone(),
+			creation_ts: t.creation_ts,
+			confirmation_ts: t.confirmation_ts,
+			confirmed: t.confirmed,
+			num_inputs: t.num_inputs,
+			num_outputs: t.num_outputs,
+			amount_credited: t.amount_credited,
+			amount_debited: t.amount_debited,
+			fee: t.fee,
+			messages: t.messages.clone(),
+			stored_tx: t.stored_tx.clone(),
+			kernel_excess: t.excess,
+			kernel_lookup_min_height: t.creation_height,
+			payment_proof: None,
+		}
+	}
+}
+
+impl From<GrinTxLogEntry> for TxLogEntry {
+	fn from(t: GrinTxLogEntry) -> Self {
+		let mut entry = TxLogEntry::new(t.parent_key_id, t.tx_type, t.id, t.creation_ts);
+		entry.tx_slate_id = t.tx_slate_id;
+		entry.confirmation_ts = t.confirmation_ts;
+		entry.confirmed = t.confirmed;
+		entry.num_inputs = t.num_inputs;
+		entry.num_outputs = t.num_outputs;
+		entry.amount_credited = t.amount_credited;
+		entry.amount_debited = t.amount_debited;
+		entry.fee = t.fee;
+		entry.messages = t.messages;
+		entry.stored_tx = t.stored_tx;
+		entry.excess = t.kernel_excess;
+		entry.creation_height = t.kernel_lookup_min_height;
+		entry
+	}
+}
+
+/// Private context of a transaction, with the keys unmasked
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GrinContext {
+	pub parent_key_id: Identifier,
+	pub sec_key: SecretKey,
+	pub sec_nonce: SecretKey,
+	pub output_ids: Vec<(Identifier, Option<u64>, u64)>,
+	pub input_ids: Vec<(Identifier, Option<u64>, u64)>,
+	#[serde(default)]
+	pub amount: u64,
+	pub fee: u64,
+	#[serde(default)]
+	pub payment_proof_derivation_index: Option<u32>,
+	pub participant_id: usize,
+}
+
+impl From<&Context> for GrinContext {
+	fn from(c: &Context) -> Self {
+		Self {
+			parent_key_id: c.parent_key_id.clone(),
+			sec_key: c.sec_key.clone(),
+			sec_nonce: c.sec_nonce.clone(),
+			output_ids: c.output_ids.clone(),
+			input_ids: c.input_ids.clone(),
+			amount: c.amount,
+			fee: c.fee,
+			payment_proof_derivation_index: None,
+			participant_id: c.participant_id,
+		}
+	}
+}
+
+impl GrinContext {
+	/// Context of this wallet, with the commitments of our inputs and outputs
+	pub fn into_context(
+		self,
+		input_commits: Vec<Commitment>,
+		output_commits: Vec<Commitment>,
+	) -> Context {
+		Context {
+			parent_key_id: self.parent_key_id,
+			sec_key: self.sec_key,
+			sec_nonce: self.sec_nonce,
+			output_ids: self.output_ids,
+			input_ids: self.input_ids,
+			participant_id: self.participant_id,
+			amount: self.amount,
+			fee: self.fee,
+			output_commits,
+			input_commits,
+			fee_parent_key_id: None,
+			notes: BTreeMap::new(),
+			replaces: None,
+			late_lock: None,
+		}
+	}
+}
+
+/// Entry of the contexts file, keyed like in the database
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StoredContext {
+	pub slate_id: Uuid,
+	pub participant_id: usize,
+	pub context: GrinContext,
+}
+
+/// Number of records of each kind exported or imported
+#[derive(Debug, Default, Serialize)]
+pub struct WalletDataSummary {
+	pub accounts: usize,
+	pub outputs: usize,
+	pub txs: usize,
+	pub contexts: usize,
+	pub stored_txs: usize,
+	pub proofs: usize,
+	/// Records left out on import, as the wallet already had them
+	pub skipped: usize,
+}
@@ -0,0 +1,374 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{TxLogEntry, TxLogEntryType};
+use crate::common::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use futures::sync::mpsc::{channel, Receiver, Sender};
+use futures::{Async, Poll, Stream};
+use log::warn;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Weak;
+use uuid::Uuid;
+
+/// Something that happened in the wallet, published on the event bus
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WalletEvent {
+	/// We signed an incoming slate as the receiver
+	SlateReceived(TxEvent),
+	/// We finalized a slate we sent
+	TxFinalized(TxEvent),
+	/// A transaction was seen on chain
+	TxConfirmed(TxEvent),
+	/// The block listener saw the chain grow
+	NewBlock {
+		height: u64,
+		timestamp: DateTime<Utc>,
+	},
+	/// A listener lost its connection or stopped unexpectedly
+	ListenerDown {
+		listener: String,
+		error: Option<String>,
+		timestamp: DateTime<Utc>,
+	},
+}
+
+impl WalletEvent {
+	/// Names of all events
+	pub const NAMES: [&'static str; 5] = [
+		"slate_received",
+		"tx_finalized",
+		"tx_confirmed",
+		"new_block",
+		"listener_down",
+	];
+
+	/// Name of the event, as in its JSON
+	pub fn name(&self) -> &'static str {
+		match self {
+			WalletEvent::SlateReceived(_) => "slate_received",
+			WalletEvent::TxFinalized(_) => "tx_finalized",
+			WalletEvent::TxConfirmed(_) => "tx_confirmed",
+			WalletEvent::NewBlock { .. } => "new_block",
+			WalletEvent::ListenerDown { .. } => "listener_down",
+		}
+	}
+
+	/// Account of a transaction event
+	pub fn account(&self) -> Option<&str> {
+		match self {
+			WalletEvent::SlateReceived(tx)
+			| WalletEvent::TxFinalized(tx)
+			| WalletEvent::TxConfirmed(tx) => Some(&tx.account),
+			_ => None,
+		}
+	}
+}
+
+/// Transaction an event is about
+#[derive(Clone, Debug, Serialize)]
+pub struct TxEvent {
+	pub account: String,
+	pub tx_id: u32,
+	pub slate_id: Option<Uuid>,
+	pub tx_type: TxLogEntryType,
+	pub amount_credited: u64,
+	pub amount_debited: u64,
+	pub fee: Option<u64>,
+	pub address: Option<String>,
+	pub timestamp: DateTime<Utc>,
+}
+
+impl TxEvent {
+	pub fn new(account: &str, tx: &TxLogEntry, timestamp: DateTime<Utc>) -> Self {
+		Self {
+			account: account.to_owned(),
+			tx_id: tx.id,
+			slate_id: tx.tx_slate_id,
+			tx_type: tx.tx_type.clone(),
+			amount_credited: tx.amount_credited,
+			amount_debited: tx.amount_debited,
+			fee: tx.fee,
+			address: tx.address.clone(),
+			timestamp,
+		}
+	}
+}
+
+pub type SharedEventBus = Arc<EventBus>;
+
+/// Events a subscriber can fall behind by before new ones are dropped for it
+pub const EVENT_BUFFER: usize = 256;
+/// Latest events kept for subscribers resuming after a disconnect
+pub const EVENT_HISTORY: usize = 1000;
+
+/// Event numbered in the order it was published, starting at 1 each time
+/// the wallet starts
+#[derive(Clone, Debug)]
+pub struct PublishedEvent {
+	pub seq: u64,
+	pub event: WalletEvent,
+}
+
+struct Subscriber {
+	id: usize,
+	sender: Sender<PublishedEvent>,
+}
+
+#[derive(Default)]
+struct BusState {
+	last_seq: u64,
+	history: VecDeque<PublishedEvent>,
+	subscribers: Vec<Subscriber>,
+	next_id: usize,
+}
+
+/// Fans wallet events out to every subscriber. Publishing never blocks: a
+/// subscriber with `EVENT_BUFFER` events waiting misses the next ones
+#[derive(Default)]
+pub struct EventBus {
+	state: Arc<Mutex<BusState>>,
+}
+
+impl EventBus {
+	pub fn shared() -> SharedEventBus {
+		Arc::new(Self::default())
+	}
+
+	/// Stream of the events published from now on
+	pub fn subscribe(&self) -> Subscription {
+		self.subscribe_since(None)
+	}
+
+	/// Stream of the kept events published after the one numbered `since`,
+	/// followed by the events published from now on
+	pub fn subscribe_since(&self, since: Option<u64>) -> Subscription {
+		let mut state = self.state.lock();
+		let (sender, receiver) = channel(EVENT_BUFFER);
+		let id = state.next_id;
+		state.next_id += 1;
+		state.subscribers.push(Subscriber { id, sender });
+
+		let (backlog, missed) = match since {
+			Some(since) => {
+				let backlog: VecDeque<_> = state
+					.history
+					.iter()
+					.filter(|e| e.seq > since)
+					.cloned()
+					.collect();
+				let first = backlog.front().map(|e| e.seq).unwrap_or(state.last_seq + 1);
+				// Numbers start over when the wallet restarts
+				(backlog, since > state.last_seq || first > since + 1)
+			}
+			None => (VecDeque::new(), false),
+		};
+		Subscription {
+			id,
+			backlog,
+			missed,
+			receiver,
+			state: Arc::downgrade(&self.state),
+		}
+	}
+
+	pub fn publish(&self, event: WalletEvent) {
+		let mut state = self.state.lock();
+		state.last_seq += 1;
+		let published = PublishedEvent {
+			seq: state.last_seq,
+			event,
+		};
+		if state.history.len() == EVENT_HISTORY {
+			state.history.pop_front();
+		}
+		state.history.push_back(published.clone());
+
+		let mut gone = vec![];
+		for s in state.subscribers.iter_mut() {
+			match s.sender.try_send(published.clone()) {
+				Ok(()) => {}
+				Err(ref e) if e.is_full() => warn!(
+					"Subscriber {} is behind, dropped {} {}",
+					s.id,
+					published.event.name(),
+					published.seq
+				),
+				Err(_) => gone.push(s.id),
+			}
+		}
+		state.subscribers.retain(|s| !gone.contains(&s.id));
+	}
+}
+
+/// Events of one subscriber. Dropping it unsubscribes
+pub struct Subscription {
+	id: usize,
+	backlog: VecDeque<PublishedEvent>,
+	missed: bool,
+	receiver: Receiver<PublishedEvent>,
+	state: Weak<Mutex<BusState>>,
+}
+
+impl Subscription {
+	/// Whether events after the requested one are no longer kept, or were
+	/// published before the wallet restarted
+	pub fn missed_events(&self) -> bool {
+		self.missed
+	}
+}
+
+impl Stream for Subscription {
+	type Item = PublishedEvent;
+	type Error = ();
+
+	fn poll(&mut self) -> Poll<Option<PublishedEvent>, ()> {
+		match self.backlog.pop_front() {
+			Some(event) => Ok(Async::Ready(Some(event))),
+			None => self.receiver.poll(),
+		}
+	}
+}
+
+impl Drop for Subscription {
+	fn drop(&mut self) {
+		if let Some(state) = self.state.upgrade() {
+			state.lock().subscribers.retain(|s| s.id != self.id);
+		}
+	}
+}
+
+/// Events a client asked for. Events that aren't about a transaction match
+/// any set of accounts
+#[derive(Clone, Debug, Default)]
+pub struct EventFilter {
+	pub events: Option<Vec<String>>,
+	pub accounts: Option<Vec<String>>,
+}
+
+impl EventFilter {
+	pub fn matches(&self, event: &WalletEvent) -> bool {
+		let name = event.name();
+		let event_matches = match &self.events {
+			Some(events) => events.iter().any(|e| e == name),
+			None => true,
+		};
+		let account_matches = match (&self.accounts, event.account()) {
+			(Some(accounts), Some(account)) => accounts.iter().any(|a| a == account),
+			_ => true,
+		};
+		event_matches && account_matches
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn new_block(height: u64) -> WalletEvent {
+		WalletEvent::NewBlock {
+			height,
+			timestamp: Utc::now(),
+		}
+	}
+
+	fn seqs(subscription: Subscription) -> Vec<u64> {
+		subscription.wait().map(|e| e.unwrap().seq).collect()
+	}
+
+	#[test]
+	fn drops_events_for_subscribers_that_fall_behind() {
+		let bus = EventBus::default();
+		let subscription = bus.subscribe();
+		for height in 0..EVENT_BUFFER as u64 + 10 {
+			bus.publish(new_block(height));
+		}
+		drop(bus);
+
+		let received = seqs(subscription);
+		assert!(received.len() >= EVENT_BUFFER);
+		assert!(received.len() < EVENT_BUFFER + 10);
+		assert_eq!(received[0], 1);
+	}
+
+	#[test]
+	fn unsubscribes_when_the_subscription_is_dropped() {
+		let bus = EventBus::default();
+		let first = bus.subscribe();
+		let second = bus.subscribe();
+		assert_eq!(bus.state.lock().subscribers.len(), 2);
+		drop(first);
+		assert_eq!(bus.state.lock().subscribers.len(), 1);
+		drop(second);
+		assert!(bus.state.lock().subscribers.is_empty());
+	}
+
+	#[test]
+	fn resumes_after_the_last_event_seen() {
+		let bus = EventBus::default();
+		for height in 0..5 {
+			bus.publish(new_block(height));
+		}
+		let resumed = bus.subscribe_since(Some(3));
+		assert!(!resumed.missed_events());
+		let restarted = bus.subscribe_since(Some(9));
+		assert!(restarted.missed_events());
+		bus.publish(new_block(5));
+		drop(bus);
+
+		assert_eq!(seqs(resumed), vec![4, 5, 6]);
+		assert_eq!(seqs(restarted), vec![6]);
+	}
+
+	#[test]
+	fn tells_when_resumed_events_are_no_longer_kept() {
+		let bus = EventBus::default();
+		for height in 0..EVENT_HISTORY as u64 + 2 {
+			bus.publish(new_block(height));
+		}
+		assert!(bus.subscribe_since(Some(1)).missed_events());
+		assert!(!bus.subscribe_since(Some(2)).missed_events());
+	}
+
+	#[test]
+	fn filters_by_event_and_account() {
+		let tx = TxEvent {
+			account: "savings".to_owned(),
+			tx_id: 1,
+			slate_id: None,
+			tx_type: TxLogEntryType::TxReceived,
+			amount_credited: 1,
+			amount_debited: 0,
+			fee: None,
+			address: None,
+			timestamp: Utc::now(),
+		};
+		let received = WalletEvent::SlateReceived(tx);
+		let filter = EventFilter {
+			events: Some(vec!["slate_received".to_owned()]),
+			accounts: Some(vec!["default".to_owned()]),
+		};
+		assert!(!filter.matches(&received));
+		assert!(!filter.matches(&new_block(1)));
+
+		let filter = EventFilter {
+			events: None,
+			accounts: Some(vec!["savings".to_owned()]),
+		};
+		assert!(filter.matches(&received));
+		assert!(filter.matches(&new_block(1)));
+	}
+}
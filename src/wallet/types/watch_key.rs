@@ -0,0 +1,274 @@
+// Copyright 2019 The vault713 Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use super::{create_private_file, SlateSigner};
+use blake2_rfc::blake2b::blake2b;
+use failure::Error;
+use grin_core::libtx;
+use grin_core::libtx::proof::ProofBuild;
+use grin_keychain::{
+	self, BlindSum, BlindingFactor, ExtKeychain, Identifier, Keychain, SwitchCommitmentType,
+};
+use grin_util::secp::key::{PublicKey, SecretKey};
+use grin_util::secp::pedersen::{Commitment, ProofMessage};
+use grin_util::secp::{ContextFlag, Message, Secp256k1, Signature};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+const PROOF_MESSAGE_SIZE: usize = 20;
+/// Watch key of a watch-only wallet, in place of its seed file
+pub const WATCH_KEY_FILE: &str = "wallet.watch";
+
+/// Public data that lets a wallet without a seed recognise our outputs
+/// in the UTXO set, by rewinding their range proofs. It can't be used
+/// to spend, and outputs created before the legacy range proof switch
+/// (which use the private root key) are not found
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchKey {
+	#[serde(with = "grin_core::libtx::secp_ser::pubkey_serde")]
+	pub root_public_key: PublicKey,
+}
+
+impl WatchKey {
+	pub fn from_keychain<K: Keychain>(keychain: &K) -> Self {
+		Self {
+			root_public_key: keychain.public_root_key(),
+		}
+	}
+
+	/// Watch key of the watch-only wallet in `data_file_dir`, if it is one
+	pub fn from_file(data_file_dir: &str) -> Result<Option<Self>, Error> {
+		let path = Path::new(data_file_dir).join(WATCH_KEY_FILE);
+		if !path.exists() {
+			return Ok(None);
+		}
+		let mut content = String::new();
+		File::open(path)?.read_to_string(&mut content)?;
+		Ok(Some(serde_json::from_str(&content)?))
+	}
+
+	pub fn save(&self, data_file_dir: &str) -> Result<(), Error> {
+		let path = Path::new(data_file_dir).join(WATCH_KEY_FILE);
+		let mut file = create_private_file(path)?;
+		file.write_all(serde_json::to_string(self)?.as_bytes())?;
+		file.sync_all()?;
+		Ok(())
+	}
+}
+
+/// Keychain of a watch-only wallet. It only knows the public root key, so
+/// it can find outputs by rewinding their range proofs but every operation
+/// needing a private key fails
+#[derive(Clone)]
+pub struct WatchKeychain {
+	watch_key: WatchKey,
+	secp: Secp256k1,
+}
+
+impl WatchKeychain {
+	pub fn new(watch_key: WatchKey) -> Self {
+		Self {
+			watch_key,
+			secp: Secp256k1::with_caps(ContextFlag::Commit),
+		}
+	}
+
+	fn rewind_hash(&self) -> Vec<u8> {
+		let public_root_key = self
+			.watch_key
+			.root_public_key
+			.serialize_vec(&self.secp, true);
+		blake2b(32, &[], &public_root_key[..]).as_bytes().to_vec()
+	}
+}
+
+fn no_private_keys() -> grin_keychain::Error {
+	grin_keychain::Error::Transaction("Watch-only wallets have no private keys".to_owned())
+}
+
+impl Keychain for WatchKeychain {
+	fn from_seed(_seed: &[u8], _is_floo: bool) -> Result<Self, grin_keychain::Error> {
+		Err(no_private_keys())
+	}
+
+	fn from_mnemonic(
+		_word_list: &str,
+		_extension_word: &str,
+		_is_floo: bool,
+	) -> Result<Self, grin_keychain::Error> {
+		Err(no_private_keys())
+	}
+
+	fn from_random_seed(_is_floo: bool) -> Result<Self, grin_keychain::Error> {
+		Err(no_private_keys())
+	}
+
+	fn mask_master_key(&mut self, _mask: &SecretKey) -> Result<(), grin_keychain::Error> {
+		Err(no_private_keys())
+	}
+
+	fn root_key_id() -> Identifier {
+		ExtKeychain::root_key_id()
+	}
+
+	fn derive_key_id(depth: u8, d1: u32, d2: u32, d3: u32, d4: u32) -> Identifier {
+		ExtKeychain::derive_key_id(depth, d1, d2, d3, d4)
+	}
+
+	fn public_root_key(&self) -> PublicKey {
+		self.watch_key.root_public_key.clone()
+	}
+
+	fn derive_key(
+		&self,
+		_amount: u64,
+		_id: &Identifier,
+		_switch: &SwitchCommitmentType,
+	) -> Result<SecretKey, grin_keychain::Error> {
+		Err(no_private_keys())
+	}
+
+	fn commit(
+		&self,
+		_amount: u64,
+		_id: &Identifier,
+		_switch: &SwitchCommitmentType,
+	) -> Result<Commitment, grin_keychain::Error> {
+		Err(no_private_keys())
+	}
+
+	fn blind_sum(&self, _blind_sum: &BlindSum) -> Result<BlindingFactor, grin_keychain::Error> {
+		Err(no_private_keys())
+	}
+
+	fn sign(
+		&self,
+		_msg: &Message,
+		_amount: u64,
+		_id: &Identifier,
+		_switch: &SwitchCommitmentType,
+	) -> Result<Signature, grin_keychain::Error> {
+		Err(no_private_keys())
+	}
+
+	fn sign_with_blinding(
+		&self,
+		_msg: &Message,
+		_blinding: &BlindingFactor,
+	) -> Result<Signature, grin_keychain::Error> {
+		Err(no_private_keys())
+	}
+
+	fn secp(&self) -> &Secp256k1 {
+		&self.secp
+	}
+}
+
+impl crate::common::Keychain for WatchKeychain {}
+
+impl SlateSigner for WatchKeychain {
+	fn secp(&self) -> &Secp256k1 {
+		&self.secp
+	}
+
+	fn public_key(&self, secret: &SecretKey) -> Result<PublicKey, Error> {
+		Ok(PublicKey::from_secret_key(&self.secp, secret)?)
+	}
+
+	fn create_nonce(&self) -> Result<SecretKey, Error> {
+		Err(no_private_keys().into())
+	}
+
+	fn split_offset(
+		&self,
+		_sec_key: &SecretKey,
+		_offset: &BlindingFactor,
+	) -> Result<SecretKey, Error> {
+		Err(no_private_keys().into())
+	}
+
+	fn partial_sig(
+		&self,
+		_sec_key: &SecretKey,
+		_sec_nonce: &SecretKey,
+		_pub_nonce_sum: &PublicKey,
+		_pub_blind_sum: &PublicKey,
+		_msg: &Message,
+	) -> Result<Signature, Error> {
+		Err(no_private_keys().into())
+	}
+
+	fn sign_message(&self, _sec_key: &SecretKey, _msg: &Message) -> Result<Signature, Error> {
+		Err(no_private_keys().into())
+	}
+}
+
+impl ProofBuild for WatchKeychain {
+	fn rewind_nonce(
+		&self,
+		secp: &Secp256k1,
+		commit: &Commitment,
+	) -> Result<SecretKey, libtx::Error> {
+		let res = blake2b(32, &commit.0, &self.rewind_hash());
+		SecretKey::from_slice(secp, res.as_bytes()).map_err(|e| {
+			libtx::ErrorKind::RangeProof(format!("Unable to create nonce: {:?}", e)).into()
+		})
+	}
+
+	fn private_nonce(
+		&self,
+		_secp: &Secp256k1,
+		_commit: &Commitment,
+	) -> Result<SecretKey, libtx::Error> {
+		Err(libtx::ErrorKind::RangeProof("Watch keys can't build range proofs".to_owned()).into())
+	}
+
+	fn proof_message(
+		&self,
+		_secp: &Secp256k1,
+		_id: &Identifier,
+		_switch: &SwitchCommitmentType,
+	) -> Result<ProofMessage, libtx::Error> {
+		Err(libtx::ErrorKind::RangeProof("Watch keys can't build range proofs".to_owned()).into())
+	}
+
+	/// Same message format as the keychain's proof builder. Without private
+	/// keys the commitment can't be recomputed, so we rely on the rewind
+	/// only succeeding with our nonce
+	fn check_output(
+		&self,
+		_secp: &Secp256k1,
+		_commit: &Commitment,
+		_amount: u64,
+		message: ProofMessage,
+	) -> Result<Option<(Identifier, SwitchCommitmentType)>, libtx::Error> {
+		if message.len() != PROOF_MESSAGE_SIZE {
+			return Ok(None);
+		}
+		let msg = message.as_bytes();
+		if msg[..2] != [0u8; 2] {
+			return Ok(None);
+		}
+		let switch = match SwitchCommitmentType::try_from(msg[2]) {
+			Ok(s) => s,
+			Err(_) => return Ok(None),
+		};
+		let depth = u8::min(msg[3], 4);
+		let id = Identifier::from_serialized_path(depth, &msg[4..]);
+		Ok(Some((id, switch)))
+	}
+}